@@ -0,0 +1,131 @@
+//! # Hot Path Benchmarks
+//!
+//! Criterion benchmarks of the commitment and audit paths scaled with the
+//! size of the poll: building the commitment Merkle tree, generating an
+//! inclusion proof, decoding vote payloads and counting the tally of a
+//! revealed plane and of the vote transactions.
+//!
+//! Run with `cargo bench`; `cargo bench -- '/10000$'` restricts the run
+//! to the smallest scale.
+
+use std::time::Duration;
+use criterion::{criterion_group, criterion_main, BatchSize, BenchmarkGroup, BenchmarkId, Criterion, Throughput};
+use criterion::measurement::WallTime;
+use seventh_estate::ballots::generate_votecodes;
+use seventh_estate::blockchain::merkle::*;
+use seventh_estate::cryptography::CSPRNGSeed;
+use seventh_estate::progress;
+use seventh_estate::subcommands::inclusion_proof;
+use seventh_estate_core::payload::{poll_votecode, VotePayload};
+use seventh_estate_core::tally::{CachedTransaction, PlaneFileRow, PlaneTally, Recount};
+
+const SCALES: [usize; 3] = [10_000, 100_000, 1_000_000];
+const POLL_IDENTIFIER: &str = "Benchmark Poll";
+
+fn leaves(n: usize) -> CryptoHashData {
+    let mut data = CryptoHashData::new((0..n).map(|i| format!("{:07},{:040x}", i, i)).collect());
+    data.pad();
+    data
+}
+
+fn payloads(n: usize) -> Vec<Vec<u8>> {
+    generate_votecodes(&CSPRNGSeed::from_vec(&vec![7; 32]), n).iter()
+        .map(|votecode| VotePayload::vote(POLL_IDENTIFIER, votecode).to_bytes())
+        .collect()
+}
+
+fn plane_rows(n: usize) -> Vec<PlaneFileRow> {
+    (0..n).map(|i| PlaneFileRow {
+        col1: format!("{:07}", i),
+        col2: if i % 3 == 0 { "Not Voted" } else { "Voted" }.to_owned(),
+        col3: ["For", "Against", "Decoy"][i % 3].to_owned()
+    }).collect()
+}
+
+fn transactions(n: usize) -> Vec<CachedTransaction> {
+    payloads(n).into_iter().enumerate().map(|(i, payload)| CachedTransaction {
+        transaction_hash: format!("0x{:064x}", i),
+        block_number: i as u64,
+        input: hex::encode(payload),
+        succeeded: Some(i % 50 != 0),
+        timestamp: None
+    }).collect()
+}
+
+/// Measure long enough for ten samples of the slowest paths at scale `n`.
+fn scale(group: &mut BenchmarkGroup<WallTime>, n: usize) {
+    group.sample_size(10);
+    group.measurement_time(Duration::from_secs(std::cmp::max(5, n as u64 / 10_000)));
+}
+
+fn bench_tree_construction(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tree_construction");
+    for &n in SCALES.iter() {
+        scale(&mut group, n);
+        group.throughput(Throughput::Elements(n as u64));
+        group.bench_with_input(BenchmarkId::from_parameter(n), &n, |b, &n| {
+            b.iter_batched(|| leaves(n), |data| new_tree(data).unwrap(), BatchSize::LargeInput)
+        });
+    }
+    group.finish();
+}
+
+fn bench_proof_generation(c: &mut Criterion) {
+    let mut group = c.benchmark_group("proof_generation");
+    for &n in SCALES.iter() {
+        scale(&mut group, n);
+        let path = std::env::temp_dir().join(format!("seventh-estate-bench-merkle-{}.yaml", n));
+        let path = path.to_str().unwrap().to_owned();
+        store_tree(&new_tree(leaves(n)).unwrap(), path.clone()).unwrap();
+        // The tree is loaded from merkle.yaml and searched for the leaf, as
+        // by `proof`; the last leaf is the slowest to find.
+        let data = format!("{:07},{:040x}", n - 1, n - 1);
+        group.bench_with_input(BenchmarkId::from_parameter(n), &data, |b, data| {
+            b.iter(|| inclusion_proof(&path, data).unwrap())
+        });
+        std::fs::remove_file(&path).unwrap();
+    }
+    group.finish();
+}
+
+fn bench_payload_decoding(c: &mut Criterion) {
+    let mut group = c.benchmark_group("payload_decoding");
+    for &n in SCALES.iter() {
+        scale(&mut group, n);
+        group.throughput(Throughput::Elements(n as u64));
+        let payloads = payloads(n);
+        group.bench_with_input(BenchmarkId::from_parameter(n), &payloads, |b, payloads| {
+            b.iter(|| payloads.iter().filter_map(|payload| poll_votecode(POLL_IDENTIFIER, payload)).count())
+        });
+    }
+    group.finish();
+}
+
+fn bench_tally_counting(c: &mut Criterion) {
+    let mut group = c.benchmark_group("tally_counting");
+    for &n in SCALES.iter() {
+        scale(&mut group, n);
+        group.throughput(Throughput::Elements(n as u64));
+        let rows = plane_rows(n);
+        group.bench_with_input(BenchmarkId::new("plane", n), &rows, |b, rows| {
+            b.iter(|| PlaneTally::from_rows(rows))
+        });
+        let transactions = transactions(n);
+        group.bench_with_input(BenchmarkId::new("recount", n), &transactions, |b, transactions| {
+            b.iter(|| Recount::from_transactions(POLL_IDENTIFIER, transactions))
+        });
+    }
+    group.finish();
+}
+
+fn config() -> Criterion {
+    progress::set_quiet(true);
+    Criterion::default()
+}
+
+criterion_group! {
+    name = benches;
+    config = config();
+    targets = bench_tree_construction, bench_proof_generation, bench_payload_decoding, bench_tally_counting
+}
+criterion_main!(benches);
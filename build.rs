@@ -0,0 +1,5 @@
+fn main() {
+    // Generate the gRPC service from its proto definitions.
+    #[cfg(feature = "grpc")]
+    tonic_build::compile_protos("proto/registrar.proto").unwrap();
+}
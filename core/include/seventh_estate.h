@@ -0,0 +1,54 @@
+/*
+ * Seventh Estate Verification Core: C interface.
+ *
+ * Build with `cargo build -p seventh-estate-core --release --features ffi`
+ * and link against libseventh_estate_core.
+ *
+ * Strings are NUL terminated UTF-8. Strings returned by the library must be
+ * released with se_string_free. Functions returning int return 1 for true,
+ * 0 for false and -1 if an argument is NULL or not valid UTF-8.
+ */
+#ifndef SEVENTH_ESTATE_H
+#define SEVENTH_ESTATE_H
+
+#include <stddef.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+#define SE_PAYLOAD_VOTE 0
+#define SE_PAYLOAD_PROOF 1
+
+/* Check a vote code, including its parity digits. */
+int se_votecode_is_valid(const char *votecode);
+
+/* Format a vote code as printed on the ballot, or NULL if it is not valid. */
+char *se_votecode_normalize(const char *votecode);
+
+/* Encode a payload of kind SE_PAYLOAD_VOTE or SE_PAYLOAD_PROOF,
+ * or NULL if the vote code is not valid. */
+char *se_payload_encode(int kind, const char *votecode, const char *poll_identifier);
+
+/* Decode a payload into a JSON object {"kind", "votecode", "poll_identifier"},
+ * or NULL if it is not a valid payload. */
+char *se_payload_decode(const char *payload);
+
+/* Check a proof of inclusion given as JSON {"data", "lemma", "path"},
+ * as written by `gen --output json`. */
+int se_proof_verify(const char *proof);
+
+/* Check a proof of inclusion given as its parts: the leaf data, the hex
+ * encoded hashes of the lemma and the sides of the path. */
+int se_proof_verify_parts(const char *data,
+                          const char *const *lemma, size_t lemma_len,
+                          const size_t *path, size_t path_len);
+
+/* Release a string returned by the library. */
+void se_string_free(char *value);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* SEVENTH_ESTATE_H */
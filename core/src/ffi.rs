@@ -0,0 +1,147 @@
+//! # C Interface
+//!
+//! C ABI of the verification core for mobile apps; see
+//! `include/seventh_estate.h` for the declarations.
+//!
+//! Strings are NUL terminated UTF-8. Strings returned by the library are
+//! owned by the caller and must be released with `se_string_free`.
+//! Functions returning `int` return `1` for true, `0` for false and `-1`
+//! if an argument is NULL or not valid UTF-8.
+
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use crate::*;
+
+pub const SE_PAYLOAD_VOTE: c_int = 0;
+pub const SE_PAYLOAD_PROOF: c_int = 1;
+
+unsafe fn read_str<'a>(value: *const c_char) -> Option<&'a str> {
+    if value.is_null() { return None; }
+    CStr::from_ptr(value).to_str().ok()
+}
+
+fn into_c_string(value: String) -> *mut c_char {
+    match CString::new(value) {
+        Ok(value) => value.into_raw(),
+        Err(_) => std::ptr::null_mut()
+    }
+}
+
+fn from_bool(value: bool) -> c_int {
+    if value { 1 } else { 0 }
+}
+
+/// Check a vote code, including its parity digits.
+///
+/// # Safety
+/// `votecode` must be NULL or a valid NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn se_votecode_is_valid(votecode: *const c_char) -> c_int {
+    match read_str(votecode) {
+        Some(votecode) => from_bool(votecode_from_string(votecode).is_some()),
+        None => -1
+    }
+}
+
+/// Format a vote code as printed on the ballot, or NULL if it is not valid.
+///
+/// # Safety
+/// `votecode` must be NULL or a valid NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn se_votecode_normalize(votecode: *const c_char) -> *mut c_char {
+    match read_str(votecode).and_then(votecode_from_string) {
+        Some(votecode) => into_c_string(string_from_votecode(&votecode)),
+        None => std::ptr::null_mut()
+    }
+}
+
+/// Encode a payload of `kind` (`SE_PAYLOAD_VOTE` or `SE_PAYLOAD_PROOF`), or
+/// NULL if the vote code is not valid.
+///
+/// # Safety
+/// `votecode` and `poll_identifier` must be NULL or valid NUL terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn se_payload_encode(kind: c_int, votecode: *const c_char, poll_identifier: *const c_char) -> *mut c_char {
+    let (votecode, poll_identifier) = match (read_str(votecode).and_then(votecode_from_string), read_str(poll_identifier)) {
+        (Some(votecode), Some(poll_identifier)) => (votecode, poll_identifier),
+        _ => return std::ptr::null_mut()
+    };
+    let payload = match kind {
+        SE_PAYLOAD_VOTE => VotePayload::vote(poll_identifier, &votecode),
+        SE_PAYLOAD_PROOF => VotePayload::proof(poll_identifier, &votecode),
+        _ => return std::ptr::null_mut()
+    };
+    into_c_string(payload.to_string())
+}
+
+/// Decode a payload into a JSON object `{"kind", "votecode", "poll_identifier"}`,
+/// or NULL if it is not a valid payload.
+///
+/// # Safety
+/// `payload` must be NULL or a valid NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn se_payload_decode(payload: *const c_char) -> *mut c_char {
+    let payload: VotePayload = match read_str(payload).and_then(|payload| payload.parse().ok()) {
+        Some(payload) => payload,
+        None => return std::ptr::null_mut()
+    };
+    let decoded = serde_json::json!({
+        "kind": payload.kind,
+        "votecode": string_from_votecode(&payload.votecode),
+        "poll_identifier": payload.poll_identifier
+    });
+    into_c_string(decoded.to_string())
+}
+
+/// Check a proof of inclusion given as JSON `{"data", "lemma", "path"}`,
+/// as written by `gen --output json`.
+///
+/// # Safety
+/// `proof` must be NULL or a valid NUL terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn se_proof_verify(proof: *const c_char) -> c_int {
+    match read_str(proof).map(serde_json::from_str::<InclusionProof>) {
+        Some(Ok(proof)) => from_bool(proof.validate()),
+        _ => -1
+    }
+}
+
+/// Check a proof of inclusion given as its parts: the leaf data, the
+/// `lemma_len` hex encoded hashes of the lemma and the `path_len` sides of
+/// the path.
+///
+/// # Safety
+/// `lemma` must point to `lemma_len` strings and `path` to `path_len` values.
+#[no_mangle]
+pub unsafe extern "C" fn se_proof_verify_parts(
+    data: *const c_char,
+    lemma: *const *const c_char,
+    lemma_len: usize,
+    path: *const usize,
+    path_len: usize) -> c_int
+{
+    if lemma.is_null() || path.is_null() { return -1; }
+    let data = match read_str(data) {
+        Some(data) => data,
+        None => return -1
+    };
+    let lemma: Option<Vec<String>> = std::slice::from_raw_parts(lemma, lemma_len).iter()
+        .map(|&hash| read_str(hash).map(|hash| hash.to_owned()))
+        .collect();
+    let path = std::slice::from_raw_parts(path, path_len);
+    match lemma {
+        Some(lemma) => from_bool(validate_proof(&lemma, path, data)),
+        None => -1
+    }
+}
+
+/// Release a string returned by the library.
+///
+/// # Safety
+/// `value` must be NULL or a string returned by the library, released once.
+#[no_mangle]
+pub unsafe extern "C" fn se_string_free(value: *mut c_char) {
+    if !value.is_null() {
+        drop(CString::from_raw(value));
+    }
+}
@@ -0,0 +1,40 @@
+//! # Seventh Estate Verification Core
+//!
+//! `seventh-estate-core` holds the code auditors and voters use to check a
+//! poll from its public artifacts: vote codes, vote payloads, proofs of
+//! inclusion in the committed Merkle tree and the tally recount.
+//!
+//! The core does no file or network access and compiles to WebAssembly,
+//! so that the voter-facing website runs the same checks in the browser.
+//! Build the JavaScript bindings with the `wasm` feature:
+//!
+//! ```text
+//! wasm-pack build core --target web -- --features wasm
+//! ```
+//!
+//! With the `ffi` feature, the same checks are exported through a C
+//! interface for mobile apps, declared in `include/seventh_estate.h`.
+//!
+//! Signatures of votes signed by the voter's device are checked with the
+//! `signatures` feature, which links the C secp256k1 library.
+
+pub mod votecode;
+pub use votecode::*;
+
+pub mod payload;
+pub use payload::*;
+
+pub mod typed_vote;
+pub use typed_vote::*;
+
+pub mod proof;
+pub use proof::*;
+
+pub mod tally;
+pub use tally::*;
+
+#[cfg(feature = "wasm")]
+pub mod wasm;
+
+#[cfg(feature = "ffi")]
+pub mod ffi;
@@ -0,0 +1,367 @@
+//! # Vote Payloads
+//!
+//! A payload is the text carried in the input data of a vote transaction
+//! and encoded in the QR codes printed on ballots and receipts:
+//!
+//! ```text
+//! 7E:<kind>:<vote code>:<poll identifier>
+//! ```
+//!
+//! * `kind` is `VOTE` for a vote submission and `PROOF` for the lookup of the
+//!   inclusion proof of a submitted vote (printed on receipts).
+//! * `vote code` is written as on the ballot, `12345-12345-12345-12345`,
+//!   including the parity digits.
+//! * `poll identifier` is the rest of the payload and may contain colons.
+//!
+//! For example, `7E:VOTE:12345-12345-12345-12345:Example Poll`.
+//!
+//! A bare vote code, as typed in by a voter, is also accepted as a vote
+//! submission for an unspecified poll.
+//!
+//! Vote transactions carry a compact binary payload of 17 bytes instead:
+//!
+//! ```text
+//! <version> <vote code> <poll tag>
+//! ```
+//!
+//! * `version` is one byte, `BINARY_PAYLOAD_VERSION`; text payloads never
+//!   start with a control character.
+//! * `vote code` is the number of the 16 digits of the vote code without
+//!   the parity digits, as 8 bytes big endian.
+//! * `poll tag` is the first 8 bytes of the SHA3-256 hash of the poll
+//!   identifier.
+//!
+//! The web interface posts a versioned JSON payload instead:
+//!
+//! ```text
+//! {"version": 1, "kind": "vote", "votecode": "12345-12345-12345-12345", "poll_identifier": "Example Poll"}
+//! ```
+//!
+//! * `version` is the version of the schema the payload was written with,
+//!   `JSON_PAYLOAD_VERSION`; payloads without one, as sent before the schema
+//!   was versioned, are version 1.
+//! * `min_version`, if given, is the oldest version of the schema that
+//!   reads the payload as intended. Later versions may only add fields, so
+//!   that payloads of later versions are read by the fields of this one and
+//!   the rest is ignored; a version that changes the meaning of a payload
+//!   raises `min_version`, and the payload is then not decoded as a vote.
+//! * `kind` is `vote` (the default) or `proof`; other kinds are not votes.
+//!
+//! Votes signed by the voter's device are posted in the typed payload of
+//! `typed_vote`. Vote transactions are decoded from any of the formats.
+
+use std::convert::TryInto;
+use std::fmt;
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
+use sha3::{Digest, Sha3_256};
+use crate::votecode::*;
+use crate::typed_vote::SignedVotePayload;
+
+pub const PAYLOAD_PREFIX: &str = "7E";
+
+pub const BINARY_PAYLOAD_VERSION: u8 = 1;
+
+pub const POLL_TAG_LENGTH: usize = 8;
+
+pub const BINARY_PAYLOAD_LENGTH: usize = 1 + 8 + POLL_TAG_LENGTH;
+
+/// Tag of a poll in binary payloads.
+pub fn poll_tag(poll_identifier: &str) -> [u8; POLL_TAG_LENGTH] {
+    let hash = Sha3_256::digest(poll_identifier.as_bytes());
+    let mut tag = [0u8; POLL_TAG_LENGTH];
+    tag.copy_from_slice(&hash[..POLL_TAG_LENGTH]);
+    tag
+}
+
+/// Number of the digits of `votecode` without its parity digits.
+pub(crate) fn pack_votecode(votecode: &VoteCode) -> u64 {
+    votecode.digits().chunks(VOTE_CODE_GROUP_SIZE + 1)
+        .flat_map(|group| group[..VOTE_CODE_GROUP_SIZE].iter())
+        .fold(0u64, |number, digit| number * 10 + u64::from(*digit))
+}
+
+/// Vote code of a number packed by `pack_votecode`, with its parity digits.
+pub(crate) fn unpack_votecode(mut number: u64) -> Option<VoteCode> {
+    if number >= 10u64.pow((VOTE_CODE_NUM_GROUPS * VOTE_CODE_GROUP_SIZE) as u32) {
+        return None;
+    }
+    let mut votecode = [0; VOTE_CODE_LENGTH];
+    for group in votecode.chunks_mut(VOTE_CODE_GROUP_SIZE + 1).rev() {
+        for digit in group[..VOTE_CODE_GROUP_SIZE].iter_mut().rev() {
+            *digit = (number % 10) as u8;
+            number /= 10;
+        }
+        let sum: u8 = group[..VOTE_CODE_GROUP_SIZE].iter().sum();
+        group[VOTE_CODE_GROUP_SIZE] = (10 - sum % 10) % 10;
+    }
+    VoteCode::from_digits(votecode)
+}
+
+/// Binary payload of the vote submission of `votecode` for a poll.
+pub fn encode_binary_vote(poll_identifier: &str, votecode: &VoteCode) -> Vec<u8> {
+    let mut payload = Vec::with_capacity(BINARY_PAYLOAD_LENGTH);
+    payload.push(BINARY_PAYLOAD_VERSION);
+    payload.extend_from_slice(&pack_votecode(votecode).to_be_bytes());
+    payload.extend_from_slice(&poll_tag(poll_identifier));
+    payload
+}
+
+/// Vote code and poll tag of a binary payload, or `None` if the data is
+/// not a binary payload of a known version.
+pub fn decode_binary_vote(data: &[u8]) -> Option<(VoteCode, [u8; POLL_TAG_LENGTH])> {
+    if data.len() != BINARY_PAYLOAD_LENGTH || data[0] != BINARY_PAYLOAD_VERSION {
+        return None;
+    }
+    let votecode = unpack_votecode(u64::from_be_bytes(data[1..9].try_into().ok()?))?;
+    Some((votecode, data[9..].try_into().ok()?))
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PayloadKind { Vote, Proof }
+
+pub const JSON_PAYLOAD_VERSION: u32 = 1;
+
+fn first_json_payload_version() -> u32 { 1 }
+
+/// Versioned JSON payload of the web interface.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct JsonVotePayload {
+    #[serde(default = "first_json_payload_version")]
+    pub version: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_version: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+    pub votecode: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poll_identifier: Option<String>
+}
+
+impl JsonVotePayload {
+    /// Whether a reader of `JSON_PAYLOAD_VERSION` reads the payload as
+    /// intended.
+    pub fn readable(&self) -> bool {
+        self.min_version.unwrap_or(1) <= JSON_PAYLOAD_VERSION
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        serde_json::to_vec(self).expect("JSON payload is serializable")
+    }
+
+    /// Vote payload carried by the JSON payload.
+    pub fn payload(&self) -> std::result::Result<VotePayload, String> {
+        if !self.readable() {
+            return Err(format!("Payload requires version {} of the schema; this reader has version {}",
+                self.min_version.unwrap_or_default(), JSON_PAYLOAD_VERSION));
+        }
+        let kind = match self.kind.as_deref().map(str::to_ascii_lowercase).as_deref() {
+            None | Some("vote") => PayloadKind::Vote,
+            Some("proof") => PayloadKind::Proof,
+            Some(kind) => return Err(format!("Unknown payload kind: {}", kind))
+        };
+        let votecode = VoteCode::parse(&self.votecode)
+            .ok_or_else(|| format!("Invalid vote code: {}", self.votecode))?;
+        Ok(VotePayload { kind, votecode, poll_identifier: self.poll_identifier.clone() })
+    }
+}
+
+impl From<&VotePayload> for JsonVotePayload {
+    fn from(payload: &VotePayload) -> Self {
+        JsonVotePayload {
+            version: JSON_PAYLOAD_VERSION,
+            min_version: None,
+            kind: Some(match payload.kind {
+                PayloadKind::Vote => "vote",
+                PayloadKind::Proof => "proof"
+            }.to_owned()),
+            votecode: string_from_votecode(&payload.votecode),
+            poll_identifier: payload.poll_identifier.clone()
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VotePayload {
+    pub kind: PayloadKind,
+    pub votecode: VoteCode,
+    pub poll_identifier: Option<String>
+}
+
+impl VotePayload {
+    pub fn vote(poll_identifier: &str, votecode: &VoteCode) -> Self {
+        VotePayload {
+            kind: PayloadKind::Vote,
+            votecode: *votecode,
+            poll_identifier: Some(poll_identifier.to_owned())
+        }
+    }
+
+    pub fn proof(poll_identifier: &str, votecode: &VoteCode) -> Self {
+        VotePayload {
+            kind: PayloadKind::Proof,
+            votecode: *votecode,
+            poll_identifier: Some(poll_identifier.to_owned())
+        }
+    }
+
+    /// Decode a payload from the input data of a transaction.
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        std::str::from_utf8(data).ok()?.parse().ok()
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        self.to_string().into_bytes()
+    }
+
+    /// Versioned JSON payload, as posted by the web interface.
+    pub fn to_json(&self) -> Vec<u8> {
+        JsonVotePayload::from(self).to_bytes()
+    }
+
+    /// Binary payload of a vote submission; payloads without a poll stay
+    /// text.
+    pub fn to_binary(&self) -> Vec<u8> {
+        match (&self.kind, &self.poll_identifier) {
+            (PayloadKind::Vote, Some(poll_identifier)) => encode_binary_vote(poll_identifier, &self.votecode),
+            _ => self.to_bytes()
+        }
+    }
+}
+
+impl fmt::Display for VotePayload {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let kind = match self.kind {
+            PayloadKind::Vote => "VOTE",
+            PayloadKind::Proof => "PROOF"
+        };
+        match &self.poll_identifier {
+            Some(poll_identifier) => write!(f, "{}:{}:{}:{}",
+                PAYLOAD_PREFIX, kind, string_from_votecode(&self.votecode), poll_identifier),
+            None => write!(f, "{}", string_from_votecode(&self.votecode))
+        }
+    }
+}
+
+impl FromStr for VotePayload {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        let value = value.trim();
+        if value.starts_with('{') {
+            let payload: JsonVotePayload = serde_json::from_str(value)
+                .map_err(|err| format!("Malformed JSON payload: {}", err))?;
+            return payload.payload();
+        }
+        let fields: Vec<&str> = value.splitn(4, ':').collect();
+        match fields.as_slice() {
+            [PAYLOAD_PREFIX, kind, votecode, poll_identifier] => {
+                let kind = match *kind {
+                    "VOTE" => PayloadKind::Vote,
+                    "PROOF" => PayloadKind::Proof,
+                    _ => return Err(format!("Unknown payload kind: {}", kind))
+                };
+                let votecode = VoteCode::parse(votecode)
+                    .ok_or_else(|| format!("Invalid vote code: {}", votecode))?;
+                Ok(VotePayload {
+                    kind,
+                    votecode,
+                    poll_identifier: Some((*poll_identifier).to_owned())
+                })
+            },
+            [_] => {
+                let votecode = VoteCode::parse(value)
+                    .ok_or_else(|| format!("Invalid vote code: {}", value))?;
+                Ok(VotePayload { kind: PayloadKind::Vote, votecode, poll_identifier: None })
+            },
+            _ => Err(format!("Malformed payload: {}", value))
+        }
+    }
+}
+
+/// Format a vote payload is written in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PayloadFormat {
+    Binary,
+    /// `7E:VOTE:...` payload or bare vote code.
+    Text,
+    Json,
+    /// Typed payload signed by the voter's device.
+    Signed
+}
+
+impl fmt::Display for PayloadFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PayloadFormat::Binary => write!(f, "binary"),
+            PayloadFormat::Text => write!(f, "text"),
+            PayloadFormat::Json => write!(f, "json"),
+            PayloadFormat::Signed => write!(f, "signed")
+        }
+    }
+}
+
+/// Format of the payload in the input data of a transaction, in the order
+/// the formats are decoded. Returns `None` if the data is not a payload.
+pub fn payload_format(data: &[u8]) -> Option<PayloadFormat> {
+    if decode_binary_vote(data).is_some() {
+        return Some(PayloadFormat::Binary);
+    }
+    if SignedVotePayload::from_bytes(data).is_some() {
+        return Some(PayloadFormat::Signed);
+    }
+    let text = std::str::from_utf8(data).ok()?;
+    VotePayload::from_bytes(data)?;
+    match text.trim().starts_with('{') {
+        true => Some(PayloadFormat::Json),
+        false => Some(PayloadFormat::Text)
+    }
+}
+
+/// Extract the vote code submitted by a transaction from its input data.
+/// Returns `None` if the data is not a vote submission.
+pub fn transaction_to_votecode(data: &[u8]) -> Option<VoteCode> {
+    if let Some((votecode, _)) = decode_binary_vote(data) {
+        return Some(votecode);
+    }
+    if let Some(signed) = SignedVotePayload::from_bytes(data) {
+        return Some(signed.votecode);
+    }
+    VotePayload::from_bytes(data)
+        .filter(|payload| payload.kind == PayloadKind::Vote)
+        .map(|payload| payload.votecode)
+}
+
+/// Extract the vote code submitted for a poll from the input data of a
+/// transaction. Payloads for other polls and signed payloads whose
+/// signature does not match are ignored; a bare vote code counts for any
+/// poll.
+pub fn poll_votecode(poll_identifier: &str, data: &[u8]) -> Option<VoteCode> {
+    if let Some((votecode, tag)) = decode_binary_vote(data) {
+        return Some(votecode).filter(|_| tag == poll_tag(poll_identifier));
+    }
+    if let Some(signed) = SignedVotePayload::from_bytes(data) {
+        return Some(signed.votecode).filter(|_| signed.verify(poll_identifier));
+    }
+    VotePayload::from_bytes(data)
+        .filter(|payload| payload.kind == PayloadKind::Vote)
+        .filter(|payload| match &payload.poll_identifier {
+            Some(identifier) => identifier == poll_identifier,
+            None => true
+        })
+        .map(|payload| payload.votecode)
+}
+
+/// Extract the vote code submitted for a poll from the input data of a
+/// transaction, as `poll_votecode`, from payloads naming the poll only: a
+/// bare vote code, or a JSON payload without a poll, counts for no poll.
+pub fn named_poll_votecode(poll_identifier: &str, data: &[u8]) -> Option<VoteCode> {
+    if decode_binary_vote(data).is_some() || SignedVotePayload::from_bytes(data).is_some() {
+        return poll_votecode(poll_identifier, data);
+    }
+    VotePayload::from_bytes(data)
+        .filter(|payload| payload.kind == PayloadKind::Vote)
+        .filter(|payload| payload.poll_identifier.as_deref() == Some(poll_identifier))
+        .map(|payload| payload.votecode)
+}
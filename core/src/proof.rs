@@ -0,0 +1,82 @@
+//! # Proofs of Inclusion
+//!
+//! A proof of inclusion shows that a leaf is part of the Merkle tree whose
+//! root was posted to the blockchain. The lemma lists the hex encoded leaf
+//! hash, the sibling hashes from the leaf up and the root; the path gives,
+//! for every level, whether the running hash is the left (0) or right (1)
+//! child.
+//!
+//! Hashes are SHA3-256, with leaves hashed as `H(0x00 || H(data))` and
+//! interior nodes as `H(0x01 || left || right)`.
+
+use serde::{Serialize, Deserialize};
+use sha3::{Digest, Sha3_256};
+
+const LEAF: u8 = 0x00;
+const INTERIOR: u8 = 0x01;
+
+pub type Hash = [u8; 32];
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InclusionProof {
+    pub data: String,
+    pub lemma: Vec<String>,
+    pub path: Vec<usize>
+}
+
+impl InclusionProof {
+    pub fn validate(&self) -> bool {
+        validate_proof(&self.lemma, &self.path, &self.data)
+    }
+
+    /// Hex encoded Merkle root the proof leads to.
+    pub fn root(&self) -> Option<&str> {
+        self.lemma.last().map(|root| root.as_str())
+    }
+}
+
+fn hash(parts: &[&[u8]]) -> Hash {
+    let mut hasher = Sha3_256::new();
+    parts.iter().for_each(|part| hasher.update(part));
+    let mut h = [0u8; 32];
+    h.copy_from_slice(&hasher.finalize());
+    h
+}
+
+/// Hash of the leaf holding `data`.
+pub fn leaf_hash(data: &str) -> Hash {
+    hash(&[&[LEAF], &hash(&[data.as_bytes()])])
+}
+
+fn decode_hash(value: &str) -> Option<Hash> {
+    let bytes = hex::decode(value).ok()?;
+    if bytes.len() != 32 { return None; }
+    let mut h = [0u8; 32];
+    h.copy_from_slice(&bytes);
+    Some(h)
+}
+
+/// Check that `data` is the leaf of the proof and that the proof leads to
+/// the root at the end of the lemma.
+pub fn validate_proof(lemma: &[String], path: &[usize], data: &str) -> bool {
+    let lemma: Vec<Hash> = match lemma.iter().map(|h| decode_hash(h)).collect::<Option<Vec<Hash>>>() {
+        Some(lemma) => lemma,
+        None => return false
+    };
+    if lemma.len() < 2 || path.len() + 2 != lemma.len() {
+        return false;
+    }
+    if lemma[0] != leaf_hash(data) {
+        return false;
+    }
+
+    let mut h = lemma[0];
+    for (sibling, &side) in lemma[1..lemma.len() - 1].iter().zip(path.iter()) {
+        h = match side {
+            0 => hash(&[&[INTERIOR], &h, sibling]),
+            1 => hash(&[&[INTERIOR], sibling, &h]),
+            _ => return false
+        };
+    }
+    h == lemma[lemma.len() - 1]
+}
@@ -0,0 +1,293 @@
+//! # Tally Recount
+//!
+//! `PlaneTally` counts the choices of voted rows in a plane whose third
+//! column has been revealed. Rows with an encrypted third column are not
+//! counted.
+//!
+//! `Recount` counts the votes submitted for a poll from a cache of the
+//! transactions sent to the vote address, so that the number of votes can
+//! be checked against the voted rows of the revealed planes.
+//!
+//! `RecountDiff` compares two recounts of a poll, for instance the
+//! official one and an independent auditor's, transaction by transaction.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use serde::{Serialize, Deserialize};
+use crate::votecode::string_from_votecode;
+use crate::payload::poll_votecode;
+
+/// Columns of a plane row needed for the tally.
+pub trait TallyRow {
+    fn col2(&self) -> &str;
+    fn col3(&self) -> &str;
+}
+
+/// Row of a plane file as published (`col1,col2,col3`).
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaneFileRow {
+    pub col1: String,
+    pub col2: String,
+    pub col3: String
+}
+
+impl TallyRow for PlaneFileRow {
+    fn col2(&self) -> &str { &self.col2 }
+    fn col3(&self) -> &str { &self.col3 }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PlaneTally {
+    pub for_votes: usize,
+    pub against_votes: usize,
+    pub decoy_votes: usize
+}
+
+impl PlaneTally {
+    pub fn from_rows<R: TallyRow>(rows: &[R]) -> Self {
+        let mut tally = PlaneTally::default();
+        rows.iter()
+            .filter(|row| row.col2() == "Voted")
+            .for_each(|row| {
+                match row.col3() {
+                    "For" => tally.for_votes += 1,
+                    "Against" => tally.against_votes += 1,
+                    "Decoy" => tally.decoy_votes += 1,
+                    _ => ()
+                }
+            });
+        tally
+    }
+
+    /// Count a published plane file.
+    pub fn from_csv(plane: &str) -> Result<Self, String> {
+        let rows: Vec<PlaneFileRow> = csv::Reader::from_reader(plane.as_bytes())
+            .deserialize()
+            .collect::<Result<Vec<PlaneFileRow>, csv::Error>>()
+            .map_err(|err| err.to_string())?;
+        Ok(PlaneTally::from_rows(&rows))
+    }
+
+    /// Number of voted rows with a revealed choice.
+    pub fn total(&self) -> usize {
+        self.for_votes + self.against_votes + self.decoy_votes
+    }
+}
+
+impl fmt::Display for PlaneTally {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "For: {}, Against: {}, Decoy: {}",
+            self.for_votes, self.against_votes, self.decoy_votes)
+    }
+}
+
+/// Transaction sent to the vote address, as cached for the recount.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CachedTransaction {
+    pub transaction_hash: String,
+    pub block_number: u64,
+    /// Hex encoded input data of the transaction.
+    pub input: String,
+    /// Whether the transaction succeeded; unknown if not given.
+    #[serde(default)]
+    pub succeeded: Option<bool>,
+    /// Unix time of the block; unknown if not given.
+    #[serde(default)]
+    pub timestamp: Option<i64>
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Recount {
+    pub poll_identifier: String,
+    pub transactions: usize,
+    /// Distinct vote codes submitted for the poll.
+    pub votes: usize,
+    /// Submissions of a vote code that was already submitted.
+    pub repeated_votes: usize,
+    pub failed_transactions: usize,
+    /// Transactions that are not a vote for the poll.
+    pub ignored_transactions: usize,
+    pub votecodes: Vec<String>,
+    /// Vote code decoded from every transaction, in order.
+    #[serde(default)]
+    pub decoded: Vec<DecodedTransaction>
+}
+
+/// Vote code a recount decoded from a transaction; none if the transaction
+/// failed or is not a vote for the poll.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecodedTransaction {
+    pub transaction_hash: String,
+    pub votecode: Option<String>
+}
+
+impl Recount {
+    /// Recount the votes for the poll, in the order of the transactions.
+    pub fn from_transactions(poll_identifier: &str, transactions: &[CachedTransaction]) -> Self {
+        let mut recount = Recount {
+            poll_identifier: poll_identifier.to_owned(),
+            transactions: transactions.len(),
+            ..Recount::default()
+        };
+        let mut seen: HashSet<String> = HashSet::new();
+        for transaction in transactions.iter() {
+            let failed = transaction.succeeded == Some(false);
+            let votecode = match failed {
+                true => None,
+                false => {
+                    let input = hex::decode(transaction.input.trim_start_matches("0x")).unwrap_or_default();
+                    poll_votecode(poll_identifier, &input).map(|votecode| string_from_votecode(&votecode))
+                }
+            };
+            recount.decoded.push(DecodedTransaction {
+                transaction_hash: transaction.transaction_hash.clone(),
+                votecode: votecode.clone()
+            });
+            match votecode {
+                _ if failed => recount.failed_transactions += 1,
+                Some(votecode) => {
+                    if seen.insert(votecode.clone()) {
+                        recount.votecodes.push(votecode);
+                    } else {
+                        recount.repeated_votes += 1;
+                    }
+                },
+                None => recount.ignored_transactions += 1
+            }
+        }
+        recount.votes = recount.votecodes.len();
+        recount
+    }
+}
+
+impl fmt::Display for Recount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Poll:         {}", self.poll_identifier)?;
+        writeln!(f, "Transactions: {}", self.transactions)?;
+        writeln!(f, "Votes:        {}", self.votes)?;
+        writeln!(f, "Repeated:     {}", self.repeated_votes)?;
+        writeln!(f, "Failed:       {}", self.failed_transactions)?;
+        write!(f, "Ignored:      {}", self.ignored_transactions)
+    }
+}
+
+/// Count that differs between two recounts.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CountDifference {
+    pub count: String,
+    pub official: usize,
+    pub independent: usize
+}
+
+/// Transaction the two recounts decoded differently.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecodeDisagreement {
+    pub transaction_hash: String,
+    pub official: Option<String>,
+    pub independent: Option<String>
+}
+
+/// Differences between the official recount of a poll and an independent
+/// one.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RecountDiff {
+    pub poll_identifier: String,
+    pub counts: Vec<CountDifference>,
+    /// Transactions only the official recount counted.
+    pub official_only: Vec<String>,
+    /// Transactions only the independent recount counted.
+    pub independent_only: Vec<String>,
+    pub decode_disagreements: Vec<DecodeDisagreement>
+}
+
+impl RecountDiff {
+    /// Compare two recounts of the same poll.
+    pub fn between(official: &Recount, independent: &Recount) -> Result<Self, String> {
+        if official.poll_identifier != independent.poll_identifier {
+            return Err(format!("Recounts are of different polls: {} and {}.",
+                official.poll_identifier, independent.poll_identifier));
+        }
+        let mut diff = RecountDiff {
+            poll_identifier: official.poll_identifier.clone(),
+            ..RecountDiff::default()
+        };
+        let counts = [
+            ("transactions", official.transactions, independent.transactions),
+            ("votes", official.votes, independent.votes),
+            ("repeated_votes", official.repeated_votes, independent.repeated_votes),
+            ("failed_transactions", official.failed_transactions, independent.failed_transactions),
+            ("ignored_transactions", official.ignored_transactions, independent.ignored_transactions)
+        ];
+        diff.counts = counts.iter()
+            .filter(|(_, official, independent)| official != independent)
+            .map(|(count, official, independent)| CountDifference {
+                count: count.to_string(),
+                official: *official,
+                independent: *independent
+            })
+            .collect();
+
+        let decoded: HashMap<&str, &Option<String>> = independent.decoded.iter()
+            .map(|transaction| (transaction.transaction_hash.as_str(), &transaction.votecode))
+            .collect();
+        let official_hashes: HashSet<&str> = official.decoded.iter()
+            .map(|transaction| transaction.transaction_hash.as_str())
+            .collect();
+        for transaction in official.decoded.iter() {
+            match decoded.get(transaction.transaction_hash.as_str()) {
+                None => diff.official_only.push(transaction.transaction_hash.clone()),
+                Some(votecode) if **votecode != transaction.votecode => {
+                    diff.decode_disagreements.push(DecodeDisagreement {
+                        transaction_hash: transaction.transaction_hash.clone(),
+                        official: transaction.votecode.clone(),
+                        independent: (*votecode).clone()
+                    });
+                },
+                Some(_) => ()
+            }
+        }
+        diff.independent_only = independent.decoded.iter()
+            .filter(|transaction| !official_hashes.contains(transaction.transaction_hash.as_str()))
+            .map(|transaction| transaction.transaction_hash.clone())
+            .collect();
+        Ok(diff)
+    }
+
+    /// Whether the recounts agree.
+    pub fn is_empty(&self) -> bool {
+        self.counts.is_empty()
+            && self.official_only.is_empty()
+            && self.independent_only.is_empty()
+            && self.decode_disagreements.is_empty()
+    }
+}
+
+fn display_votecode(votecode: &Option<String>) -> &str {
+    votecode.as_deref().unwrap_or("none")
+}
+
+impl fmt::Display for RecountDiff {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Poll:         {}", self.poll_identifier)?;
+        if self.is_empty() {
+            return write!(f, "\nRecounts agree.");
+        }
+        for difference in self.counts.iter() {
+            write!(f, "\n{}: {} official, {} independent",
+                difference.count, difference.official, difference.independent)?;
+        }
+        for transaction in self.official_only.iter() {
+            write!(f, "\nOnly in the official recount: {}", transaction)?;
+        }
+        for transaction in self.independent_only.iter() {
+            write!(f, "\nOnly in the independent recount: {}", transaction)?;
+        }
+        for disagreement in self.decode_disagreements.iter() {
+            write!(f, "\nDecoded differently: {} ({} official, {} independent)",
+                disagreement.transaction_hash,
+                display_votecode(&disagreement.official),
+                display_votecode(&disagreement.independent))?;
+        }
+        Ok(())
+    }
+}
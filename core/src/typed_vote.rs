@@ -0,0 +1,188 @@
+//! # Typed Vote Payloads
+//!
+//! A vote can be signed by the voter's device as EIP-712 typed data, so
+//! that the relayer posting it cannot change the vote code or the poll
+//! without the signature no longer matching. The typed data is
+//!
+//! ```text
+//! EIP712Domain(string name,string version)
+//! Vote(string poll,string votecode)
+//! ```
+//!
+//! with domain `{name: "7th Estate", version: "1"}`, the poll identifier as
+//! `poll` and the vote code written as on the ballot as `votecode`; wallets
+//! sign the data of `typed_vote_data` with `eth_signTypedData_v4`.
+//!
+//! A signed vote is posted in a binary payload of 102 bytes:
+//!
+//! ```text
+//! <version> <vote code> <poll tag> <signer> <signature>
+//! ```
+//!
+//! * `version` is one byte, `SIGNED_PAYLOAD_VERSION`.
+//! * `vote code` and `poll tag` are encoded as in binary vote payloads.
+//! * `signer` is the 20 byte address of the device key.
+//! * `signature` is `r`, `s` and `v` of the signature, 65 bytes.
+//!
+//! A signed vote only counts if the signature recovers to the signer and
+//! has a low `s`, so that no other signature of the same vote is accepted.
+//! Signatures are checked with the `signatures` feature; without it,
+//! signed votes are not counted.
+
+use std::convert::TryInto;
+use sha3::{Digest, Keccak256};
+use crate::votecode::*;
+use crate::payload::*;
+
+pub const SIGNED_PAYLOAD_VERSION: u8 = 2;
+
+pub const ADDRESS_LENGTH: usize = 20;
+
+pub const SIGNATURE_LENGTH: usize = 65;
+
+pub const SIGNED_PAYLOAD_LENGTH: usize = BINARY_PAYLOAD_LENGTH + ADDRESS_LENGTH + SIGNATURE_LENGTH;
+
+pub const TYPED_VOTE_DOMAIN_NAME: &str = "7th Estate";
+
+pub const TYPED_VOTE_DOMAIN_VERSION: &str = "1";
+
+const DOMAIN_TYPE: &str = "EIP712Domain(string name,string version)";
+
+const VOTE_TYPE: &str = "Vote(string poll,string votecode)";
+
+fn keccak256(data: &[u8]) -> [u8; 32] {
+    Keccak256::digest(data).into()
+}
+
+/// Hash signed for the vote of `votecode` for a poll, as defined by EIP-712.
+pub fn typed_vote_hash(poll_identifier: &str, votecode: &VoteCode) -> [u8; 32] {
+    let domain_separator = keccak256(&[
+        keccak256(DOMAIN_TYPE.as_bytes()),
+        keccak256(TYPED_VOTE_DOMAIN_NAME.as_bytes()),
+        keccak256(TYPED_VOTE_DOMAIN_VERSION.as_bytes())
+    ].concat());
+    let vote_hash = keccak256(&[
+        keccak256(VOTE_TYPE.as_bytes()),
+        keccak256(poll_identifier.as_bytes()),
+        keccak256(string_from_votecode(votecode).as_bytes())
+    ].concat());
+    keccak256(&[&[0x19, 0x01][..], &domain_separator, &vote_hash].concat())
+}
+
+/// Typed data of the vote of `votecode` for a poll, as passed to
+/// `eth_signTypedData_v4`.
+pub fn typed_vote_data(poll_identifier: &str, votecode: &VoteCode) -> serde_json::Value {
+    serde_json::json!({
+        "types": {
+            "EIP712Domain": [
+                { "name": "name", "type": "string" },
+                { "name": "version", "type": "string" }
+            ],
+            "Vote": [
+                { "name": "poll", "type": "string" },
+                { "name": "votecode", "type": "string" }
+            ]
+        },
+        "primaryType": "Vote",
+        "domain": { "name": TYPED_VOTE_DOMAIN_NAME, "version": TYPED_VOTE_DOMAIN_VERSION },
+        "message": { "poll": poll_identifier, "votecode": string_from_votecode(votecode) }
+    })
+}
+
+/// A vote signed by the voter's device.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SignedVotePayload {
+    pub votecode: VoteCode,
+    pub poll_tag: [u8; POLL_TAG_LENGTH],
+    pub signer: [u8; ADDRESS_LENGTH],
+    pub signature: [u8; SIGNATURE_LENGTH]
+}
+
+impl SignedVotePayload {
+    /// Signed payload of a signature, as returned by the wallet.
+    pub fn new(poll_identifier: &str, votecode: &VoteCode, signer: [u8; ADDRESS_LENGTH], signature: [u8; SIGNATURE_LENGTH]) -> Self {
+        SignedVotePayload { votecode: *votecode, poll_tag: poll_tag(poll_identifier), signer, signature }
+    }
+
+    /// Decode a signed payload, or `None` if the data is not one.
+    pub fn from_bytes(data: &[u8]) -> Option<Self> {
+        if data.len() != SIGNED_PAYLOAD_LENGTH || data[0] != SIGNED_PAYLOAD_VERSION {
+            return None;
+        }
+        let signer_offset = BINARY_PAYLOAD_LENGTH;
+        let signature_offset = signer_offset + ADDRESS_LENGTH;
+        Some(SignedVotePayload {
+            votecode: unpack_votecode(u64::from_be_bytes(data[1..9].try_into().ok()?))?,
+            poll_tag: data[9..signer_offset].try_into().ok()?,
+            signer: data[signer_offset..signature_offset].try_into().ok()?,
+            signature: data[signature_offset..].try_into().ok()?
+        })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut payload = Vec::with_capacity(SIGNED_PAYLOAD_LENGTH);
+        payload.push(SIGNED_PAYLOAD_VERSION);
+        payload.extend_from_slice(&pack_votecode(&self.votecode).to_be_bytes());
+        payload.extend_from_slice(&self.poll_tag);
+        payload.extend_from_slice(&self.signer);
+        payload.extend_from_slice(&self.signature);
+        payload
+    }
+
+    /// Whether the payload is for the poll and signed by its signer.
+    pub fn verify(&self, poll_identifier: &str) -> bool {
+        self.poll_tag == poll_tag(poll_identifier)
+            && recover_signer(&typed_vote_hash(poll_identifier, &self.votecode), &self.signature) == Some(self.signer)
+    }
+}
+
+/// Address of the key of a low `s` signature of `hash`.
+#[cfg(feature = "signatures")]
+pub fn recover_signer(hash: &[u8; 32], signature: &[u8; SIGNATURE_LENGTH]) -> Option<[u8; ADDRESS_LENGTH]> {
+    use secp256k1::{Message, Secp256k1};
+    use secp256k1::recovery::{RecoverableSignature, RecoveryId};
+    let recovery_id = match signature[64] {
+        v @ 27..=28 => v - 27,
+        v @ 0..=1 => v,
+        _ => return None
+    };
+    let recovery_id = RecoveryId::from_i32(i32::from(recovery_id)).ok()?;
+    let recoverable = RecoverableSignature::from_compact(&signature[..64], recovery_id).ok()?;
+    let mut normalized = recoverable.to_standard();
+    normalized.normalize_s();
+    if normalized != recoverable.to_standard() {
+        return None;
+    }
+    let message = Message::from_slice(hash).ok()?;
+    let public_key = Secp256k1::verification_only().recover(&message, &recoverable).ok()?;
+    Some(public_key_address(&public_key))
+}
+
+/// Signatures cannot be checked without the `signatures` feature.
+#[cfg(not(feature = "signatures"))]
+pub fn recover_signer(_hash: &[u8; 32], _signature: &[u8; SIGNATURE_LENGTH]) -> Option<[u8; ADDRESS_LENGTH]> {
+    None
+}
+
+#[cfg(feature = "signatures")]
+fn public_key_address(public_key: &secp256k1::PublicKey) -> [u8; ADDRESS_LENGTH] {
+    let hash = keccak256(&public_key.serialize_uncompressed()[1..]);
+    let mut address = [0u8; ADDRESS_LENGTH];
+    address.copy_from_slice(&hash[32 - ADDRESS_LENGTH..]);
+    address
+}
+
+/// Sign the vote of `votecode` for a poll with a device key, as a wallet
+/// would.
+#[cfg(feature = "signatures")]
+pub fn sign_typed_vote(secret_key: &secp256k1::SecretKey, poll_identifier: &str, votecode: &VoteCode) -> SignedVotePayload {
+    use secp256k1::{Message, PublicKey, Secp256k1};
+    let secp = Secp256k1::signing_only();
+    let message = Message::from_slice(&typed_vote_hash(poll_identifier, votecode)).unwrap();
+    let (recovery_id, compact) = secp.sign_recoverable(&message, secret_key).serialize_compact();
+    let mut signature = [0u8; SIGNATURE_LENGTH];
+    signature[..64].copy_from_slice(&compact);
+    signature[64] = 27 + recovery_id.to_i32() as u8;
+    let signer = public_key_address(&PublicKey::from_secret_key(&secp, secret_key));
+    SignedVotePayload::new(poll_identifier, votecode, signer, signature)
+}
@@ -0,0 +1,165 @@
+//! # Vote Codes
+//!
+//! A vote code is printed on a ballot as four groups of five digits,
+//! `12345-12345-12345-12345`. The last digit of each group is a parity
+//! digit, so that the digits of every group sum to a multiple of ten.
+//!
+//! A `VoteCode` is only constructed from digits whose parity checks, by
+//! `VoteCode::from_digits`, or from a vote code as written, by
+//! `VoteCode::parse`, so that vote codes written with other separators or
+//! spacing compare equal once parsed.
+//!
+//! `check_typed_votecode` tells a voter what is wrong with a vote code as
+//! typed in, before it is submitted: a character that is not a digit, a
+//! missing or extra digit, or the group whose parity digit does not match.
+//! It only checks the form of the code, never whether it is on a ballot.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
+
+pub const VOTE_CODE_NUM_GROUPS: usize = 4;
+pub const VOTE_CODE_GROUP_SIZE: usize = 4;
+pub const VOTE_CODE_LENGTH: usize = VOTE_CODE_NUM_GROUPS * (VOTE_CODE_GROUP_SIZE + 1);
+
+/// Vote code whose parity digits check. Serialized as its digits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "[u8; VOTE_CODE_LENGTH]", into = "[u8; VOTE_CODE_LENGTH]")]
+pub struct VoteCode([u8; VOTE_CODE_LENGTH]);
+
+impl VoteCode {
+    /// Vote code of `digits`, parity digits included. Returns `None` if a
+    /// digit is out of range or a parity digit does not match.
+    pub fn from_digits(digits: [u8; VOTE_CODE_LENGTH]) -> Option<Self> {
+        let valid = digits.chunks(VOTE_CODE_GROUP_SIZE + 1)
+            .all(|group| {
+                group.iter().all(|&x| x < 10)
+                    && group.iter().map(|&x| x as usize).sum::<usize>() % 10 == 0
+            });
+        Some(VoteCode(digits)).filter(|_| valid)
+    }
+
+    /// Parse a vote code as printed on a ballot (`12345-12345-12345-12345`).
+    /// Dashes and whitespace are ignored. Returns `None` if the code is
+    /// malformed or a parity digit does not match.
+    pub fn parse(value: &str) -> Option<Self> {
+        VoteCode::check(value).ok()
+    }
+
+    /// Parse a vote code as `VoteCode::parse`, telling what is wrong with
+    /// it if it is not a vote code.
+    pub fn check(value: &str) -> Result<Self, VoteCodeError> {
+        let mut digits: Vec<u8> = Vec::with_capacity(VOTE_CODE_LENGTH);
+        for (position, character) in value.chars().enumerate() {
+            if character == '-' || character.is_whitespace() {
+                continue;
+            }
+            match character.to_digit(10) {
+                Some(digit) => digits.push(digit as u8),
+                None => return Err(VoteCodeError::InvalidCharacter { character, position: position + 1 })
+            }
+        }
+        if digits.len() != VOTE_CODE_LENGTH {
+            return Err(VoteCodeError::WrongLength { digits: digits.len() });
+        }
+        let mut votecode = [0; VOTE_CODE_LENGTH];
+        votecode.copy_from_slice(&digits);
+        match digits.chunks(VOTE_CODE_GROUP_SIZE + 1).position(|group| group.iter().map(|&x| x as usize).sum::<usize>() % 10 != 0) {
+            Some(group) => Err(VoteCodeError::ParityMismatch { group: group + 1 }),
+            None => Ok(VoteCode(votecode))
+        }
+    }
+
+    /// Digits of the vote code, parity digits included.
+    pub fn digits(&self) -> &[u8; VOTE_CODE_LENGTH] {
+        &self.0
+    }
+}
+
+/// What is wrong with a vote code as typed in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum VoteCodeError {
+    /// A character other than a digit, a dash or whitespace, at its
+    /// position counted from 1.
+    InvalidCharacter { character: char, position: usize },
+    /// Fewer or more digits than a vote code has.
+    WrongLength { digits: usize },
+    /// The parity digit of a group, counted from 1, does not match.
+    ParityMismatch { group: usize }
+}
+
+impl fmt::Display for VoteCodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            VoteCodeError::InvalidCharacter { character, position } =>
+                write!(f, "'{}' at position {} is not a digit.", character, position),
+            VoteCodeError::WrongLength { digits } =>
+                write!(f, "A vote code has {} digits, not {}.", VOTE_CODE_LENGTH, digits),
+            VoteCodeError::ParityMismatch { group } =>
+                write!(f, "Group {} does not check: a digit of the group is mistyped.", group)
+        }
+    }
+}
+
+/// Check of a vote code as typed in: the vote code as printed on the
+/// ballot if it is one, what is wrong with it otherwise.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TypedVoteCodeCheck {
+    pub valid: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub votecode: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<VoteCodeError>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message: Option<String>
+}
+
+/// Check the form of a vote code as typed in by a voter.
+pub fn check_typed_votecode(value: &str) -> TypedVoteCodeCheck {
+    match VoteCode::check(value) {
+        Ok(votecode) => TypedVoteCodeCheck { valid: true, votecode: Some(votecode.to_string()), error: None, message: None },
+        Err(error) => TypedVoteCodeCheck { valid: false, votecode: None, error: Some(error), message: Some(error.to_string()) }
+    }
+}
+
+impl fmt::Display for VoteCode {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let groups: Vec<String> = self.0.chunks(VOTE_CODE_GROUP_SIZE + 1)
+            .map(|group| group.iter().map(|n| format!("{}", n)).collect())
+            .collect();
+        f.pad(&groups.join("-"))
+    }
+}
+
+impl FromStr for VoteCode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        VoteCode::parse(value).ok_or_else(|| format!("Invalid vote code: {}", value))
+    }
+}
+
+impl TryFrom<[u8; VOTE_CODE_LENGTH]> for VoteCode {
+    type Error = String;
+
+    fn try_from(digits: [u8; VOTE_CODE_LENGTH]) -> Result<Self, Self::Error> {
+        VoteCode::from_digits(digits).ok_or_else(|| format!("Invalid vote code digits: {:?}", digits))
+    }
+}
+
+impl From<VoteCode> for [u8; VOTE_CODE_LENGTH] {
+    fn from(votecode: VoteCode) -> Self {
+        votecode.0
+    }
+}
+
+pub fn string_from_votecode(votecode: &VoteCode) -> String {
+    votecode.to_string()
+}
+
+/// Parse a vote code as printed on a ballot, as `VoteCode::parse`.
+pub fn votecode_from_string(value: &str) -> Option<VoteCode> {
+    VoteCode::parse(value)
+}
@@ -0,0 +1,93 @@
+//! # JavaScript Bindings
+//!
+//! Structured values are passed to and from JavaScript as JSON strings.
+
+use std::convert::TryInto;
+use wasm_bindgen::prelude::*;
+use crate::*;
+
+fn to_json<T: serde::Serialize>(value: &T) -> Result<String, JsValue> {
+    serde_json::to_string(value).map_err(|err| JsValue::from_str(&err.to_string()))
+}
+
+/// Check a vote code as typed in by a voter.
+#[wasm_bindgen(js_name = isValidVoteCode)]
+pub fn is_valid_votecode(votecode: &str) -> bool {
+    votecode_from_string(votecode).is_some()
+}
+
+/// Check a vote code as typed in by a voter, into `{valid, votecode}`, or
+/// `{valid, error, message}` with what is wrong with it.
+#[wasm_bindgen(js_name = checkVoteCode)]
+pub fn check_vote_code(votecode: &str) -> Result<String, JsValue> {
+    to_json(&check_typed_votecode(votecode))
+}
+
+/// Decode a payload into `{kind, votecode, poll_identifier}`.
+#[wasm_bindgen(js_name = decodePayload)]
+pub fn decode_payload(payload: &str) -> Result<String, JsValue> {
+    let payload: VotePayload = payload.parse().map_err(|err: String| JsValue::from_str(&err))?;
+    to_json(&serde_json::json!({
+        "kind": payload.kind,
+        "votecode": string_from_votecode(&payload.votecode),
+        "poll_identifier": payload.poll_identifier
+    }))
+}
+
+/// Versioned JSON payload of the vote for `votecode` in a poll, to post in
+/// the input data of a vote transaction.
+#[wasm_bindgen(js_name = encodeJsonVote)]
+pub fn encode_json_vote(poll_identifier: &str, votecode: &str) -> Result<String, JsValue> {
+    let votecode = votecode_from_string(votecode).ok_or_else(|| JsValue::from_str("Invalid vote code"))?;
+    String::from_utf8(VotePayload::vote(poll_identifier, &votecode).to_json()).map_err(|err| JsValue::from_str(&err.to_string()))
+}
+
+/// Check a proof of inclusion given as `{data, lemma, path}`, as written
+/// by the `gen` command.
+#[wasm_bindgen(js_name = verifyInclusionProof)]
+pub fn verify_inclusion_proof(proof: &str) -> Result<bool, JsValue> {
+    let proof: InclusionProof = serde_json::from_str(proof).map_err(|err| JsValue::from_str(&err.to_string()))?;
+    Ok(proof.validate())
+}
+
+/// Count a published plane file.
+#[wasm_bindgen(js_name = tallyPlane)]
+pub fn tally_plane(plane: &str) -> Result<String, JsValue> {
+    to_json(&PlaneTally::from_csv(plane).map_err(|err| JsValue::from_str(&err))?)
+}
+
+/// Recount the votes for a poll from a JSON list of cached transactions.
+#[wasm_bindgen(js_name = recountTransactions)]
+pub fn recount_transactions(poll_identifier: &str, transactions: &str) -> Result<String, JsValue> {
+    let transactions: Vec<CachedTransaction> = serde_json::from_str(transactions).map_err(|err| JsValue::from_str(&err.to_string()))?;
+    to_json(&Recount::from_transactions(poll_identifier, &transactions))
+}
+
+/// Compare two recounts of a poll, given as JSON as returned by
+/// `recountTransactions`.
+#[wasm_bindgen(js_name = compareRecounts)]
+pub fn compare_recounts(official: &str, independent: &str) -> Result<String, JsValue> {
+    let official: Recount = serde_json::from_str(official).map_err(|err| JsValue::from_str(&err.to_string()))?;
+    let independent: Recount = serde_json::from_str(independent).map_err(|err| JsValue::from_str(&err.to_string()))?;
+    to_json(&RecountDiff::between(&official, &independent).map_err(|err| JsValue::from_str(&err))?)
+}
+
+/// Typed data of a vote, as passed to `eth_signTypedData_v4` by the
+/// voter's wallet.
+#[wasm_bindgen(js_name = typedVoteData)]
+pub fn typed_vote_data(poll_identifier: &str, votecode: &str) -> Result<String, JsValue> {
+    let votecode = votecode_from_string(votecode).ok_or_else(|| JsValue::from_str("Invalid vote code"))?;
+    to_json(&crate::typed_vote::typed_vote_data(poll_identifier, &votecode))
+}
+
+/// Hex encoded signed payload of a vote signed by the voter's wallet with
+/// the signature `0x<r><s><v>` from the address `signer`.
+#[wasm_bindgen(js_name = encodeSignedVote)]
+pub fn encode_signed_vote(poll_identifier: &str, votecode: &str, signer: &str, signature: &str) -> Result<String, JsValue> {
+    let votecode = votecode_from_string(votecode).ok_or_else(|| JsValue::from_str("Invalid vote code"))?;
+    let decode = |value: &str| hex::decode(value.trim_start_matches("0x")).map_err(|err| JsValue::from_str(&err.to_string()));
+    let signer = decode(signer)?.as_slice().try_into().map_err(|_| JsValue::from_str("Invalid signer address"))?;
+    let signature = decode(signature)?.as_slice().try_into().map_err(|_| JsValue::from_str("Invalid signature"))?;
+    let payload = SignedVotePayload::new(poll_identifier, &votecode, signer, signature);
+    Ok(format!("0x{}", hex::encode(payload.to_bytes())))
+}
@@ -0,0 +1,53 @@
+#![cfg(feature = "ffi")]
+
+use std::ffi::{CStr, CString};
+use seventh_estate_core::ffi::*;
+
+fn read(value: *mut std::os::raw::c_char) -> Option<String> {
+    if value.is_null() { return None; }
+    let string = unsafe { CStr::from_ptr(value) }.to_str().unwrap().to_owned();
+    unsafe { se_string_free(value) };
+    Some(string)
+}
+
+#[test]
+fn test_ffi_votecode_payload() {
+    let votecode = CString::new("12340567841111600000").unwrap();
+    let wrong_parity = CString::new("12341-56784-11116-00000").unwrap();
+    let poll = CString::new("Example Poll").unwrap();
+    unsafe {
+        assert_eq!(1, se_votecode_is_valid(votecode.as_ptr()));
+        assert_eq!(0, se_votecode_is_valid(wrong_parity.as_ptr()));
+        assert_eq!(-1, se_votecode_is_valid(std::ptr::null()));
+        assert_eq!(Some("12340-56784-11116-00000".to_owned()), read(se_votecode_normalize(votecode.as_ptr())));
+        assert_eq!(None, read(se_votecode_normalize(wrong_parity.as_ptr())));
+
+        let payload = read(se_payload_encode(SE_PAYLOAD_VOTE, votecode.as_ptr(), poll.as_ptr())).unwrap();
+        assert_eq!("7E:VOTE:12340-56784-11116-00000:Example Poll", payload);
+        let payload = CString::new(payload).unwrap();
+        let decoded: serde_json::Value = serde_json::from_str(&read(se_payload_decode(payload.as_ptr())).unwrap()).unwrap();
+        assert_eq!("Vote", decoded["kind"]);
+        assert_eq!("Example Poll", decoded["poll_identifier"]);
+    }
+}
+
+#[test]
+fn test_ffi_proof() {
+    let proof = serde_json::json!({
+        "data": "Not Voted",
+        "lemma": [
+            "fc5b5f4e58fcb551bdfcb729d5fc7c5cc80b5fb990758a50bb0827ee79effee1",
+            "d21154d769c7d5856960ddf30fc86b01385b27ca8760b608332f2d9e7b99a388",
+            "95fffb71ae3c85686532169334512226eaf55dc6f94333d2f5f6e950812c08fd",
+            "c1840a2abbd13f9b624ed37f6646da6c824324d17d06a039a1dacb79c62fa52f",
+            "5873a2bee359c763dc3adb0d44d72fe4fff49114f5fc02a7505c8ee61aa4c184"
+        ],
+        "path": [0, 1, 0]
+    });
+    let valid = CString::new(proof.to_string()).unwrap();
+    let malformed = CString::new("{}").unwrap();
+    unsafe {
+        assert_eq!(1, se_proof_verify(valid.as_ptr()));
+        assert_eq!(-1, se_proof_verify(malformed.as_ptr()));
+    }
+}
@@ -0,0 +1,173 @@
+use seventh_estate_core::*;
+
+const VOTE_CODE: &str = "12340-56784-11116-00000";
+
+#[test]
+fn test_inclusion_proof() {
+    let proof = InclusionProof {
+        data: "Not Voted".to_string(),
+        lemma: vec![
+            "fc5b5f4e58fcb551bdfcb729d5fc7c5cc80b5fb990758a50bb0827ee79effee1".to_string(),
+            "d21154d769c7d5856960ddf30fc86b01385b27ca8760b608332f2d9e7b99a388".to_string(),
+            "95fffb71ae3c85686532169334512226eaf55dc6f94333d2f5f6e950812c08fd".to_string(),
+            "c1840a2abbd13f9b624ed37f6646da6c824324d17d06a039a1dacb79c62fa52f".to_string(),
+            "5873a2bee359c763dc3adb0d44d72fe4fff49114f5fc02a7505c8ee61aa4c184".to_string(),
+        ],
+        path: vec![0, 1, 0]
+    };
+    assert!(proof.validate());
+
+    // Other data, wrong side, truncated path, malformed hash
+    assert!(!InclusionProof { data: "Voted".to_string(), ..proof.clone() }.validate());
+    assert!(!InclusionProof { path: vec![1, 1, 0], ..proof.clone() }.validate());
+    assert!(!InclusionProof { path: vec![0, 1], ..proof.clone() }.validate());
+    let mut lemma = proof.lemma.clone();
+    lemma[2] = "zz".to_string();
+    assert!(!InclusionProof { lemma, ..proof }.validate());
+}
+
+#[test]
+fn test_typed_votecode_check() {
+    let check = check_typed_votecode(" 12340 56784-11116-00000\n");
+    assert_eq!((true, Some(VOTE_CODE.to_owned()), None), (check.valid, check.votecode, check.error));
+
+    // What is wrong is told, never whether the code is on a ballot
+    assert_eq!(Err(VoteCodeError::InvalidCharacter { character: 'O', position: 19 }), VoteCode::check("12340-56784-11116-O0000"));
+    assert_eq!(Err(VoteCodeError::WrongLength { digits: 19 }), VoteCode::check("12340-56784-11116-0000"));
+    assert_eq!(Err(VoteCodeError::WrongLength { digits: 0 }), VoteCode::check(""));
+    assert_eq!(Err(VoteCodeError::ParityMismatch { group: 2 }), VoteCode::check("12340-56785-11116-00000"));
+    let check = check_typed_votecode("12340-56785-11116-00000");
+    assert!(!check.valid && check.votecode.is_none());
+    assert_eq!(Some("Group 2 does not check: a digit of the group is mistyped.".to_owned()), check.message);
+    assert_eq!(r#"{"valid":false,"error":{"reason":"parity_mismatch","group":2},"message":"Group 2 does not check: a digit of the group is mistyped."}"#,
+        serde_json::to_string(&check).unwrap());
+}
+
+#[test]
+fn test_plane_tally_csv() {
+    let plane = "col1,col2,col3\n01: x,Voted,For\n02: x,Voted,Against\n03: x,Not Voted,For\n04: x,Voted,For\n";
+    let tally = PlaneTally::from_csv(plane).unwrap();
+    assert_eq!(PlaneTally { for_votes: 2, against_votes: 1, decoy_votes: 0 }, tally);
+    assert!(PlaneTally::from_csv("col1,col2\n01,Voted\n").is_err());
+}
+
+#[test]
+fn test_recount() {
+    let transaction = |input: String, succeeded: Option<bool>| CachedTransaction {
+        transaction_hash: String::from("0x01"),
+        block_number: 1,
+        input: hex::encode(input),
+        succeeded,
+        timestamp: None
+    };
+    let transactions = vec![
+        transaction(format!("7E:VOTE:{}:Example Poll", VOTE_CODE), Some(true)),
+        transaction(VOTE_CODE.to_string(), None),
+        transaction(format!("7E:VOTE:{}:Other Poll", VOTE_CODE), None),
+        transaction(format!("7E:PROOF:{}:Example Poll", VOTE_CODE), None),
+        transaction("11116-11116-11116-11116".to_string(), Some(false)),
+    ];
+
+    let recount = Recount::from_transactions("Example Poll", &transactions);
+    assert_eq!(5, recount.transactions);
+    assert_eq!(1, recount.votes);
+    assert_eq!(1, recount.repeated_votes);
+    assert_eq!(1, recount.failed_transactions);
+    assert_eq!(2, recount.ignored_transactions);
+    assert_eq!(vec![VOTE_CODE.to_string()], recount.votecodes);
+}
+
+#[test]
+fn test_recount_diff() {
+    let transaction = |hash: &str, input: String| CachedTransaction {
+        transaction_hash: hash.to_string(),
+        block_number: 1,
+        input: hex::encode(input),
+        succeeded: None,
+        timestamp: None
+    };
+    let vote = format!("7E:VOTE:{}:Example Poll", VOTE_CODE);
+    let official = Recount::from_transactions("Example Poll", &[
+        transaction("0x01", vote.clone()),
+        transaction("0x02", "unrelated".to_string()),
+    ]);
+    assert!(RecountDiff::between(&official, &official).unwrap().is_empty());
+
+    let mut independent = Recount::from_transactions("Example Poll", &[
+        transaction("0x01", vote.clone()),
+        transaction("0x03", vote),
+    ]);
+    independent.decoded[0].votecode = None;
+    let diff = RecountDiff::between(&official, &independent).unwrap();
+    assert_eq!(vec!["repeated_votes", "ignored_transactions"],
+        diff.counts.iter().map(|difference| difference.count.as_str()).collect::<Vec<_>>());
+    assert_eq!(vec!["0x02".to_string()], diff.official_only);
+    assert_eq!(vec!["0x03".to_string()], diff.independent_only);
+    assert_eq!(1, diff.decode_disagreements.len());
+    assert_eq!(Some(VOTE_CODE.to_string()), diff.decode_disagreements[0].official);
+
+    let other = Recount::from_transactions("Other Poll", &[]);
+    assert!(RecountDiff::between(&official, &other).is_err());
+}
+
+#[test]
+fn test_binary_vote_payload() {
+    let votecode = votecode_from_string(VOTE_CODE).unwrap();
+    let payload = VotePayload::vote("poll", &votecode).to_binary();
+    assert_eq!(BINARY_PAYLOAD_LENGTH, payload.len());
+    assert_eq!(Some(votecode), transaction_to_votecode(&payload));
+    assert_eq!(Some(votecode), poll_votecode("poll", &payload));
+    assert_eq!(None, poll_votecode("other poll", &payload));
+    let text = VotePayload::vote("poll", &votecode).to_bytes();
+    assert_eq!(Some(votecode), poll_votecode("poll", &text));
+    let mut unknown = payload.clone();
+    unknown[0] = BINARY_PAYLOAD_VERSION + 1;
+    assert_eq!(None, transaction_to_votecode(&unknown));
+    let mut overflow = payload;
+    overflow[1..9].copy_from_slice(&u64::MAX.to_be_bytes());
+    assert_eq!(None, transaction_to_votecode(&overflow));
+}
+
+#[test]
+fn test_json_vote_payload() {
+    let votecode = votecode_from_string(VOTE_CODE).unwrap();
+    let payload = VotePayload::vote("poll: one", &votecode).to_json();
+    assert_eq!(Some(votecode), transaction_to_votecode(&payload));
+    assert_eq!(Some(votecode), poll_votecode("poll: one", &payload));
+    assert_eq!(None, poll_votecode("other poll", &payload));
+
+    // Payloads sent before the schema was versioned are version 1.
+    let unversioned = format!(r#"{{"votecode": "{}", "poll_identifier": "poll"}}"#, VOTE_CODE);
+    assert_eq!(Some(votecode), poll_votecode("poll", unversioned.as_bytes()));
+
+    // Later versions are read by the fields this version knows.
+    let later = format!(r#"{{"version": 3, "votecode": "{}", "poll_identifier": "poll", "locale": "fr"}}"#, VOTE_CODE);
+    assert_eq!(Some(votecode), poll_votecode("poll", later.as_bytes()));
+    let incompatible = format!(r#"{{"version": 3, "min_version": 2, "votecode": "{}", "poll_identifier": "poll"}}"#, VOTE_CODE);
+    assert_eq!(None, transaction_to_votecode(incompatible.as_bytes()));
+    let proof = format!(r#"{{"kind": "proof", "votecode": "{}"}}"#, VOTE_CODE);
+    assert_eq!(None, transaction_to_votecode(proof.as_bytes()));
+    let unknown = format!(r#"{{"kind": "recall", "votecode": "{}"}}"#, VOTE_CODE);
+    assert_eq!(None, transaction_to_votecode(unknown.as_bytes()));
+    assert_eq!(None, transaction_to_votecode(br#"{"version": 1}"#));
+}
+
+#[cfg(feature = "signatures")]
+#[test]
+fn test_signed_vote_payload() {
+    let votecode = votecode_from_string(VOTE_CODE).unwrap();
+    let key = secp256k1::SecretKey::from_slice(&[7u8; 32]).unwrap();
+    let signed = sign_typed_vote(&key, "poll", &votecode);
+    let payload = signed.to_bytes();
+    assert_eq!(SIGNED_PAYLOAD_LENGTH, payload.len());
+    assert_eq!(Some(signed.clone()), SignedVotePayload::from_bytes(&payload));
+    assert_eq!(Some(votecode), poll_votecode("poll", &payload));
+    assert_eq!(None, poll_votecode("other poll", &payload));
+
+    // The relayer cannot change the vote code or replace the signer.
+    let other = votecode_from_string("11116-11116-11116-11116").unwrap();
+    let changed = SignedVotePayload { votecode: other, ..signed.clone() };
+    assert_eq!(None, poll_votecode("poll", &changed.to_bytes()));
+    let replaced = SignedVotePayload { signer: [1u8; ADDRESS_LENGTH], ..signed };
+    assert_eq!(None, poll_votecode("poll", &replaced.to_bytes()));
+}
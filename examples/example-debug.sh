@@ -27,6 +27,10 @@ recordstep step2
 ${SEVENTH_ESTATE_BINARY} step3 --config example.yaml.secure --addresses addresses.csv --ballots ballots.csv
 recordstep step3
 ${SEVENTH_ESTATE_BINARY} step4 --config example.yaml.secure --serial-file serials.csv
+recordstep step4-proposal
+${SEVENTH_ESTATE_BINARY} approve-commit --proposal OneTrusteePoll/commit_proposal.yaml --trustee alice@example.com
+recordstep approve-commit
+${SEVENTH_ESTATE_BINARY} step4 --config example.yaml.secure --serial-file serials.csv --approval commit_approval_alice_example.com.yaml
 recordstep step4
 
 # Record some votes at random
@@ -61,6 +65,8 @@ ${SEVENTH_ESTATE_BINARY} step7 --config example.yaml.secure --seed ffeeddccbbaa9
 recordstep step7
 ${SEVENTH_ESTATE_BINARY} step8 --config example.yaml.secure
 recordstep step8
+${SEVENTH_ESTATE_BINARY} finalize --config example.yaml.secure
+recordstep finalize
 
 echo "Here are the valid votes. We tally the vote ourselves."
 grep ,Voted, ExamplePoll/final_plane_04.csv | sort -t , -k +3
@@ -9,6 +9,9 @@ seventh-estate.exe step1 --config data\example.yaml.secure
 seventh-estate.exe step2 --config data\example.yaml.secure --seed 00112233445566778899aabbccddeeffffeeddccbbaa99887766554433221100
 seventh-estate.exe step3 --config data\example.yaml.secure --addresses addresses.csv --ballots ballots.csv
 seventh-estate.exe step4 --config data\example.yaml.secure --serial-file serials.csv
+seventh-estate.exe approve-commit --proposal "Example Poll\commit_proposal.yaml" --trustee alice@example.com
+seventh-estate.exe approve-commit --proposal "Example Poll\commit_proposal.yaml" --trustee bob@example.com
+seventh-estate.exe step4 --config data\example.yaml.secure --serial-file serials.csv --approval commit_approval_alice_example.com.yaml --approval commit_approval_bob_example.com.yaml
 seventh-estate.exe step5
 seventh-estate.exe step6 --config data\example.yaml.secure --votes votes.csv
 seventh-estate.exe step7 --config data\example.yaml.secure --seed ffeeddccbbaa9988776655443322110000112233445566778899aabbccddeeff
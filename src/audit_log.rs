@@ -0,0 +1,114 @@
+//! # Audit Log
+//!
+//! Operations that hand poll data out of the data directory are recorded
+//! in `audit_log.jsonl` in the data directory of the poll, one JSON record
+//! per line, so that officials can account for every copy made. A record
+//! carries the digest of what was handed out, to tell the copies apart.
+
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use crate::Result;
+use crate::ballots::{BallotExportFormat, RedactionProfile};
+
+pub const AUDIT_LOG_FILENAME: &str = "audit_log.jsonl";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AuditLogEvent {
+    /// Ballots exported by `export_ballots`.
+    BallotExport {
+        profile: RedactionProfile,
+        format: BallotExportFormat,
+        file: String,
+        ballots: usize,
+        /// Hex encoded SHA-256 digest of the export file.
+        sha256: String
+    },
+    /// Ballot map sealed to escrow keys by `escrow_ballot_map`.
+    BallotMapEscrow {
+        file: String,
+        holders: usize,
+        quorum: usize,
+        /// Hex encoded SHA-256 digest of the ballot map.
+        map_sha256: String,
+        /// Hex encoded SHA-256 digest of the escrow file.
+        sha256: String
+    },
+    /// Committed plane cell opened for a challenge by `reveal_cell`.
+    CellReveal {
+        challenger: String,
+        plane: usize,
+        row: usize,
+        column: usize,
+        file: String,
+        /// Hex encoded SHA-256 digest of the opening file.
+        sha256: String
+    },
+    /// Fresh vote codes of a replacement ballot, opened by the print
+    /// pipeline, counted by `record_votes`.
+    ReplacementCodes {
+        spoiled_serial: usize,
+        replacement_serial: usize,
+        file: String,
+        /// Hex encoded SHA-256 digest of the vote codes file.
+        sha256: String
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditLogEntry {
+    pub recorded_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub event: AuditLogEvent
+}
+
+impl fmt::Display for AuditLogEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.event {
+            AuditLogEvent::BallotExport { profile, format, file, ballots, sha256 } => {
+                writeln!(f, "Exported {} ballots ({} profile, {}) to {}", ballots, profile, format, file)?;
+                write!(f, "SHA-256: {}", sha256)
+            },
+            AuditLogEvent::BallotMapEscrow { file, holders, quorum, map_sha256, sha256 } => {
+                writeln!(f, "Escrowed the ballot map {} to {} of {} holders in {}", map_sha256, quorum, holders, file)?;
+                write!(f, "SHA-256: {}", sha256)
+            },
+            AuditLogEvent::CellReveal { challenger, plane, row, column, file, sha256 } => {
+                writeln!(f, "Revealed plane {} row {} column {} to {} in {}", plane, row, column, challenger, file)?;
+                write!(f, "SHA-256: {}", sha256)
+            },
+            AuditLogEvent::ReplacementCodes { spoiled_serial, replacement_serial, file, sha256 } => {
+                writeln!(f, "Counted the vote codes of ballot {}, replacing ballot {}, from {}", replacement_serial, spoiled_serial, file)?;
+                write!(f, "SHA-256: {}", sha256)
+            }
+        }
+    }
+}
+
+/// Record `event` in the audit log of the poll of `datadir_path`.
+pub fn record_audit_event(datadir_path: &str, event: AuditLogEvent) -> Result<AuditLogEntry> {
+    let entry = AuditLogEntry { recorded_at: Utc::now(), event };
+    let mut log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(Path::new(datadir_path).join(AUDIT_LOG_FILENAME))?;
+    writeln!(log, "{}", serde_json::to_string(&entry)?)?;
+    Ok(entry)
+}
+
+/// Entries of the audit log of the poll of `datadir_path`, in the order
+/// recorded.
+pub fn read_audit_log(datadir_path: &str) -> Result<Vec<AuditLogEntry>> {
+    let path = Path::new(datadir_path).join(AUDIT_LOG_FILENAME);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    fs::read_to_string(&path)?.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| Ok(serde_json::from_str(line)?))
+        .collect()
+}
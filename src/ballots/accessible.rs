@@ -0,0 +1,121 @@
+//! # Accessible Ballots
+//!
+//! Voters flagged in the `accessibility` field of the roster receive their
+//! ballot in an accessible format, generated with the standard ballots:
+//!
+//! * `large_print`: a large-print PDF (see `print_large_print_ballot`).
+//! * `screen_reader`: an HTML document with headings, a table of the
+//!   choices and vote codes spelled out digit by digit for screen readers.
+//!
+//! The HTML ballot carries the vote codes in the clear, without a
+//! scratch-off: it must reach its voter as confidentially as the printed
+//! ballot.
+
+use std::fmt;
+use std::str::FromStr;
+use super::*;
+use crate::poll_configuration::LocalizedBallotText;
+use crate::voter_roster::VoterInfo;
+
+/// Roster field flagging the voters who need an accessible ballot.
+pub const ACCESSIBILITY_FIELD: &str = "accessibility";
+
+/// Format of the ballot of a voter.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BallotFormat {
+    #[default]
+    Standard,
+    LargePrint,
+    ScreenReader
+}
+
+pub const BALLOT_FORMATS: [&str; 3] = ["standard", "large_print", "screen_reader"];
+
+impl BallotFormat {
+    /// Format of the ballot of `voter`, by its `accessibility` field.
+    pub fn for_voter(voter: &VoterInfo) -> std::result::Result<Self, String> {
+        match voter.extra_fields.get(ACCESSIBILITY_FIELD) {
+            Some(format) => format.parse(),
+            None => Ok(BallotFormat::Standard)
+        }
+    }
+
+    /// File name of the accessible ballot of `serial`, if any.
+    pub fn file_name(&self, serial: BallotSerial) -> Option<String> {
+        match self {
+            BallotFormat::Standard => None,
+            BallotFormat::LargePrint => Some(format!("{}-large-print.pdf", serial)),
+            BallotFormat::ScreenReader => Some(format!("{}.html", serial))
+        }
+    }
+}
+
+impl fmt::Display for BallotFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(BALLOT_FORMATS[*self as usize])
+    }
+}
+
+impl FromStr for BallotFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value.trim().to_lowercase().replace('-', "_").as_str() {
+            "" | "standard" => Ok(BallotFormat::Standard),
+            "large_print" => Ok(BallotFormat::LargePrint),
+            "screen_reader" => Ok(BallotFormat::ScreenReader),
+            _ => Err(format!("Unknown ballot format: {}", value))
+        }
+    }
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Vote code as read out by a screen reader: digit by digit, a pause
+/// between its groups.
+fn spoken_votecode(votecode: &str) -> String {
+    votecode.split('-')
+        .map(|group| group.chars().map(String::from).collect::<Vec<String>>().join(" "))
+        .collect::<Vec<String>>()
+        .join(", ")
+}
+
+/// Language of the ballot: the first locale of the poll, or English.
+fn ballot_language(localized: &[LocalizedBallotText]) -> String {
+    localized.first().map_or("en".to_owned(), |text| text.locale.clone())
+}
+
+/// Render `ballot` as a self-contained HTML document for screen readers.
+pub fn ballot_html(ballot: &Ballot, text: &BallotText) -> String {
+    let mut html = String::new();
+    html.push_str(&format!("<!DOCTYPE html>\n<html lang=\"{}\">\n<head>\n<meta charset=\"utf-8\">\n", html_escape(&ballot_language(text.localized))));
+    html.push_str(&format!("<title>Ballot {}</title>\n", ballot.serial));
+    html.push_str("<style>body{font-family:sans-serif;font-size:1.25em;max-width:40em;margin:auto;line-height:1.5}td,th{text-align:left;padding:0.5em 1em 0.5em 0}</style>\n");
+    html.push_str("</head>\n<body>\n<main>\n");
+    html.push_str(&format!("<h1>Ballot {}</h1>\n", ballot.serial));
+    if let Some(question) = text.question() {
+        html.push_str("<h2>Question</h2>\n");
+        for paragraph in question.split("\n\n") {
+            html.push_str(&format!("<p>{}</p>\n", html_escape(paragraph.trim())));
+        }
+    }
+    html.push_str("<h2>How to vote</h2>\n");
+    html.push_str(&format!("<p>Vote online by entering the ballot serial number, {}, and the vote code of your choice.</p>\n", ballot.serial));
+    html.push_str("<table>\n<caption>Choices and their vote codes</caption>\n");
+    html.push_str("<thead><tr><th scope=\"col\">Choice</th><th scope=\"col\">Vote code</th></tr></thead>\n<tbody>\n");
+    for choice in [&ballot.choice1, &ballot.choice2] {
+        let votecode = string_from_votecode(&choice.votecode);
+        html.push_str(&format!("<tr><th scope=\"row\">{}</th><td><span aria-label=\"{}\">{}</span></td></tr>\n",
+            html_escape(&text.choice_label(&choice.choice)),
+            spoken_votecode(&votecode),
+            votecode));
+    }
+    html.push_str("</tbody>\n</table>\n</main>\n</body>\n</html>\n");
+    html
+}
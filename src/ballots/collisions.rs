@@ -0,0 +1,67 @@
+//! # Vote Code Collisions
+//!
+//! A vote code printed on two ballot choices would be counted for
+//! whichever of them the tally finds first. `generate_votecodes` draws
+//! again any vote code drawn for an earlier choice; `find_votecode_collisions`
+//! checks a set of ballots, as generated or as read back from a print
+//! file, for vote codes shared across ballots and choices before they are
+//! printed.
+
+use std::fmt;
+use super::*;
+
+/// Ballot choice of a set of ballots, named as it is printed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ChoiceLocation {
+    pub serial: String,
+    pub choice: String
+}
+
+impl fmt::Display for ChoiceLocation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "ballot {} ({})", self.serial, self.choice)
+    }
+}
+
+/// Vote code shared by more than one ballot choice.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct VoteCodeCollision {
+    pub votecode: String,
+    pub choices: Vec<ChoiceLocation>
+}
+
+impl fmt::Display for VoteCodeCollision {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let choices: Vec<String> = self.choices.iter().map(ChoiceLocation::to_string).collect();
+        write!(f, "{} on {}", self.votecode, choices.join(", "))
+    }
+}
+
+/// Vote codes shared by more than one of `choices`, in the order of their
+/// first choice.
+pub fn find_votecode_collisions(choices: &[(ChoiceLocation, VoteCode)]) -> Vec<VoteCodeCollision> {
+    let mut order: Vec<usize> = (0..choices.len()).collect();
+    order.sort_unstable_by_key(|&n| (choices[n].1, n));
+    let mut collisions: Vec<(usize, VoteCodeCollision)> = Vec::new();
+    for group in order.chunk_by(|&a, &b| choices[a].1 == choices[b].1) {
+        if group.len() < 2 { continue; }
+        collisions.push((group[0], VoteCodeCollision {
+            votecode: string_from_votecode(&choices[group[0]].1),
+            choices: group.iter().map(|&n| choices[n].0.clone()).collect()
+        }));
+    }
+    collisions.sort_by_key(|(first, _)| *first);
+    collisions.into_iter().map(|(_, collision)| collision).collect()
+}
+
+/// Vote codes shared by more than one choice of `ballots`.
+pub fn ballot_votecode_collisions(ballots: &[Ballot], num_ballots: usize) -> Vec<VoteCodeCollision> {
+    let choices: Vec<(ChoiceLocation, VoteCode)> = ballots.iter()
+        .flat_map(|ballot| [&ballot.choice1, &ballot.choice2])
+        .map(|choice| (ChoiceLocation {
+            serial: string_from_ballotserial(&choice.serial, num_ballots),
+            choice: string_from_choicevalue(&choice.choice)
+        }, choice.votecode))
+        .collect();
+    find_votecode_collisions(&choices)
+}
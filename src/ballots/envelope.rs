@@ -0,0 +1,203 @@
+//! # Replacement Envelopes
+//!
+//! The ballots of a poll are printed in bulk, but a replacement ballot is
+//! issued on its own, for the official who spoils the ballot it replaces.
+//! The vote codes of the ballots of the poll are derived from the Poll
+//! Master Key and are in the print files of step 3, so a replacement is
+//! issued with fresh vote codes instead: `ReplacementEnvelope::issue` draws
+//! them on the print pipeline, at random, with the order of the choices and
+//! the salt of their commitment, and seals them to the key of the print
+//! pipeline. Only the commitment leaves the print pipeline before voting
+//! closes; the official posts it with the amendment of the poll.
+//!
+//! Once voting closed, the print pipeline opens the envelope into the
+//! `ReplacementCodes` of the ballot, checked against the posted amendment,
+//! for the authority: step 6 counts the votes for the fresh vote codes for
+//! the committed choices of the replacement ballot, and no longer counts
+//! its printed vote codes.
+//!
+//! The envelope keys are sealed box keys, as for the encryption of votes.
+
+use std::collections::HashMap;
+use std::fmt;
+use chrono::{DateTime, Utc};
+use zeroize::Zeroizing;
+use crate::Result;
+use crate::blockchain::{amendment_hash, BallotAmendment};
+use crate::cryptography::{CSPRNGSeed, VoteDecryptionKey, VoteEncryptionKey};
+use super::*;
+
+/// File name of the envelope of the replacement ballot `serial`.
+pub fn replacement_envelope_filename(serial: BallotSerial) -> String {
+    format!("replacement_envelope_{}.yaml", serial)
+}
+
+/// File name of the opened vote codes of the replacement ballot `serial`,
+/// in the data directory of the poll.
+pub fn replacement_codes_filename(serial: BallotSerial) -> String {
+    format!("replacement_codes_{}.yaml", serial)
+}
+
+/// Public key of the print pipeline.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EnvelopeKey {
+    /// Base64 encoded public envelope key.
+    pub public_key: String
+}
+
+impl fmt::Display for EnvelopeKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.public_key)
+    }
+}
+
+/// Fresh vote codes of a replacement ballot sealed to the print pipeline.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplacementEnvelope {
+    pub poll_identifier: String,
+    pub spoiled_serial: BallotSerial,
+    pub replacement_serial: BallotSerial,
+    /// Hex encoded SHA3-256 commitment of the fresh vote codes.
+    pub commitment: String,
+    /// Base64 encoded sealed `ReplacementCodes`.
+    pub sealed: String,
+    pub sealed_at: DateTime<Utc>
+}
+
+impl ReplacementEnvelope {
+    /// Issue the replacement `replacement_serial` of `spoiled_serial` with
+    /// fresh vote codes, sealed to `print_key`, returning the envelope and
+    /// the ballot to print.
+    pub fn issue(poll_identifier: &str, spoiled_serial: BallotSerial, replacement_serial: BallotSerial, print_key: &VoteEncryptionKey) -> Result<(Self, ExportedBallot)> {
+        let codes = ReplacementCodes::generate(poll_identifier, spoiled_serial, replacement_serial)?;
+        let serialized = Zeroizing::new(serde_json::to_vec(&codes)?);
+        let envelope = ReplacementEnvelope {
+            poll_identifier: poll_identifier.to_owned(),
+            spoiled_serial,
+            replacement_serial,
+            commitment: codes.commitment()?,
+            sealed: base64::encode(&print_key.seal(&serialized)),
+            sealed_at: Utc::now()
+        };
+        let ballot = ExportedBallot {
+            serial: replacement_serial.to_string(),
+            choice1_votecode: Some(codes.choice1_votecode.clone()),
+            choice1_value: Some(codes.choice1_value.clone()),
+            choice2_votecode: Some(codes.choice2_votecode.clone()),
+            choice2_value: Some(codes.choice2_value.clone()),
+            ..Default::default()
+        };
+        Ok((envelope, ballot))
+    }
+
+    /// Open the envelope with the secret key of the print pipeline. The
+    /// vote codes must be those of the replacement of the envelope and open
+    /// its commitment.
+    pub fn open(&self, print_key: &VoteDecryptionKey) -> Result<ReplacementCodes> {
+        let sealed = base64::decode(&self.sealed)?;
+        let serialized = Zeroizing::new(print_key.open(&sealed)
+            .ok_or("The envelope is not sealed to this key.")?);
+        let codes: ReplacementCodes = serde_json::from_slice(&serialized)?;
+        if (codes.spoiled_serial, codes.replacement_serial) != (self.spoiled_serial, self.replacement_serial)
+            || codes.poll_identifier != self.poll_identifier {
+            return Err(format!("The envelope seals the vote codes of ballot {}, not those of the replacement ballot {}.",
+                codes.replacement_serial, self.replacement_serial).into());
+        }
+        if codes.commitment()? != self.commitment {
+            return Err(format!("The vote codes of ballot {} do not open the commitment of the envelope.", codes.replacement_serial).into());
+        }
+        Ok(codes)
+    }
+
+    /// Check that the envelope is the one committed to by `amendment`, as
+    /// published and posted to the blockchain.
+    pub fn check_amendment(&self, amendment: &BallotAmendment) -> Result<()> {
+        check_amendment(&self.poll_identifier, self.spoiled_serial, self.replacement_serial, &self.commitment, amendment)
+    }
+}
+
+/// Fresh vote codes of a replacement ballot, with the salt of their
+/// commitment.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReplacementCodes {
+    pub poll_identifier: String,
+    pub spoiled_serial: BallotSerial,
+    pub replacement_serial: BallotSerial,
+    pub choice1_votecode: String,
+    pub choice1_value: String,
+    pub choice2_votecode: String,
+    pub choice2_value: String,
+    /// Hex encoded salt of the commitment.
+    pub salt: String
+}
+
+impl ReplacementCodes {
+    fn generate(poll_identifier: &str, spoiled_serial: BallotSerial, replacement_serial: BallotSerial) -> Result<Self> {
+        let mut random = Zeroizing::new(vec![0u8; CSPRNGSeed::SIZE + 32 + 1]);
+        getrandom::getrandom(&mut random)?;
+        let votecodes = generate_votecodes(&CSPRNGSeed::from_vec(&random[..CSPRNGSeed::SIZE].to_vec()), 2);
+        let salt = &random[CSPRNGSeed::SIZE..CSPRNGSeed::SIZE + 32];
+        let choices = match random[CSPRNGSeed::SIZE + 32] & 1 {
+            0 => CHOICE_VALUES,
+            _ => [CHOICE_VALUES[1], CHOICE_VALUES[0]]
+        };
+        Ok(ReplacementCodes {
+            poll_identifier: poll_identifier.to_owned(),
+            spoiled_serial,
+            replacement_serial,
+            choice1_votecode: string_from_votecode(&votecodes[0]),
+            choice1_value: string_from_choicevalue(&choices[0]),
+            choice2_votecode: string_from_votecode(&votecodes[1]),
+            choice2_value: string_from_choicevalue(&choices[1]),
+            salt: hex::encode(salt)
+        })
+    }
+
+    /// Hex encoded SHA3-256 commitment of the vote codes.
+    pub fn commitment(&self) -> Result<String> {
+        Ok(ballot_commitment(&hex::decode(&self.salt)?, &self.replacement_serial.to_string(),
+            (&self.choice1_votecode, &self.choice1_value),
+            (&self.choice2_votecode, &self.choice2_value)))
+    }
+
+    /// Check that the vote codes are those committed to by `amendment`.
+    pub fn check_amendment(&self, amendment: &BallotAmendment) -> Result<()> {
+        check_amendment(&self.poll_identifier, self.spoiled_serial, self.replacement_serial, &self.commitment()?, amendment)
+    }
+
+    /// Vote codes of `ballot`, the replacement ballot as committed with the
+    /// poll, by the fresh vote code of the same choice.
+    pub fn translation(&self, ballot: &Ballot) -> Result<HashMap<String, String>> {
+        if ballot.serial != self.replacement_serial {
+            return Err(format!("The vote codes of ballot {} are not those of ballot {}.", self.replacement_serial, ballot.serial).into());
+        }
+        let committed = |value: &str| [&ballot.choice1, &ballot.choice2].iter()
+            .find(|choice| string_from_choicevalue(&choice.choice) == value)
+            .map(|choice| string_from_votecode(&choice.votecode))
+            .ok_or_else(|| format!("Ballot {} has no choice {}.", ballot.serial, value));
+        let mut translation = HashMap::new();
+        for (votecode, value) in [(&self.choice1_votecode, &self.choice1_value), (&self.choice2_votecode, &self.choice2_value)].iter() {
+            translation.insert(votecode.to_string(), committed(value)?);
+        }
+        match translation.len() {
+            2 => Ok(translation),
+            _ => Err(format!("The vote codes of ballot {} are not distinct.", self.replacement_serial).into())
+        }
+    }
+}
+
+// Check the commitment of a replacement against the amendment posted for it.
+fn check_amendment(poll_identifier: &str, spoiled_serial: BallotSerial, replacement_serial: BallotSerial, commitment: &str, amendment: &BallotAmendment) -> Result<()> {
+    if amendment.spoiled_serial != spoiled_serial || amendment.replacement_serial != replacement_serial {
+        return Err(format!("The amendment replaces ballot {} by ballot {}, not ballot {} by ballot {}.",
+            amendment.spoiled_serial, amendment.replacement_serial, spoiled_serial, replacement_serial).into());
+    }
+    if amendment.ballot_commitment.as_deref() != Some(commitment) {
+        return Err(format!("The amendment of ballot {} does not commit to the vote codes of the replacement.", spoiled_serial).into());
+    }
+    let hash = amendment_hash(poll_identifier, spoiled_serial, replacement_serial, amendment.reason.as_deref(), Some(commitment));
+    match hex::encode(hash) == amendment.amendment_hash {
+        true => Ok(()),
+        false => Err(format!("The amendment of ballot {} does not hash to its posted hash.", spoiled_serial).into())
+    }
+}
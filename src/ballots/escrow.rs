@@ -0,0 +1,228 @@
+//! # Ballot Map Escrow
+//!
+//! The map of every vote code of a poll to its ballot and choice is what a
+//! dispute over the tally may need opened, yet it must not sit within reach
+//! of the web stack of the poll authority. `BallotMapEscrow` encrypts the
+//! map with a random key, which is split into shares, each sealed to the
+//! escrow key of one holder (a court, or every member of a coalition of
+//! observers). A quorum of holders opens the map together; the authority
+//! keeps nothing that opens it.
+//!
+//! Only a `BallotMapCommitment` is published: the SHA-256 digest of the map
+//! and of the escrow file, with the holders and the quorum, so that the map
+//! opened in a dispute can be shown to be the one escrowed.
+//!
+//! The escrow keys are sealed box keys, as for the encryption of votes.
+
+use std::fmt;
+use std::fs::File;
+use chrono::{DateTime, Utc};
+use sha2::{Digest, Sha256};
+use zeroize::Zeroizing;
+use crate::Result;
+use crate::blockchain::approval_quorum;
+use crate::cryptography::{aead_decrypt, aead_encrypt, AEADKey, AEADString, Secret, SecretShare, ShamirSecretSharing, VoteDecryptionKey, VoteEncryptionKey};
+use super::*;
+
+pub const BALLOT_MAP_ESCROW_FILENAME: &str = "ballot_map_escrow.yaml";
+pub const BALLOT_MAP_COMMITMENT_FILENAME: &str = "ballot_map_commitment.yaml";
+
+/// Holder of an escrow key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EscrowHolder {
+    pub holder: String,
+    /// Base64 encoded public escrow key.
+    pub public_key: String
+}
+
+impl fmt::Display for EscrowHolder {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {}", self.holder, self.public_key)
+    }
+}
+
+/// Escrow keys the ballot map is sealed to, as read from a YAML file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EscrowConfiguration {
+    /// Holders needed to open the map, a majority of them by default.
+    #[serde(default)]
+    pub quorum: Option<usize>,
+    pub holders: Vec<EscrowHolder>
+}
+
+impl EscrowConfiguration {
+    pub fn from_file(path: &dyn AsRef<Path>) -> Result<Self> {
+        let configuration: EscrowConfiguration = serde_yaml::from_reader(File::open(path)?)?;
+        configuration.validate()?;
+        Ok(configuration)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        if self.holders.is_empty() {
+            return Err("The ballot map must be escrowed to at least one holder.".into());
+        }
+        if !(1..=self.holders.len()).contains(&self.quorum()) {
+            return Err(format!("The escrow quorum must be between 1 and {}.", self.holders.len()).into());
+        }
+        for holder in self.holders.iter() {
+            VoteEncryptionKey::from_base64(&holder.public_key)
+                .map_err(|_| format!("The escrow key of {} is invalid.", holder.holder))?;
+        }
+        Ok(())
+    }
+
+    pub fn quorum(&self) -> usize {
+        self.quorum.unwrap_or_else(|| approval_quorum(self.holders.len()))
+    }
+}
+
+/// Vote code of the poll, with its ballot and choice.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BallotMapEntry {
+    pub votecode: String,
+    pub serial: String,
+    pub choice: String
+}
+
+/// Map of every vote code of `ballots` to its ballot and choice, by vote
+/// code.
+pub fn ballot_map(ballots: &[Ballot], num_ballots: usize) -> Vec<BallotMapEntry> {
+    let mut map: Vec<BallotMapEntry> = ballots.iter()
+        .flat_map(|ballot| [&ballot.choice1, &ballot.choice2])
+        .map(|choice| BallotMapEntry {
+            votecode: string_from_votecode(&choice.votecode),
+            serial: string_from_ballotserial(&choice.serial, num_ballots),
+            choice: string_from_choicevalue(&choice.choice)
+        })
+        .collect();
+    map.sort_by(|a, b| a.votecode.cmp(&b.votecode));
+    map
+}
+
+fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// Share of the map key sealed to one holder.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SealedShare {
+    pub holder: String,
+    pub public_key: String,
+    /// Base64 encoded sealed share.
+    pub sealed: String
+}
+
+/// Encrypted ballot map, handed to the escrow holders.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BallotMapEscrow {
+    pub poll_identifier: String,
+    pub quorum: usize,
+    pub shares: Vec<SealedShare>,
+    /// Ballot map as JSON, encrypted with the map key.
+    pub map: AEADString,
+    /// Hex encoded SHA-256 digest of the ballot map as JSON.
+    pub map_sha256: String,
+    pub sealed_at: DateTime<Utc>
+}
+
+impl BallotMapEscrow {
+    /// Encrypt `map` to the holders of `configuration`.
+    pub fn seal(poll_identifier: &str, map: &[BallotMapEntry], configuration: &EscrowConfiguration) -> Result<Self> {
+        configuration.validate()?;
+        let serialized = Zeroizing::new(serde_json::to_vec(map)?);
+        let mut map_key = AEADKey([0u8; 32]);
+        getrandom::getrandom(&mut map_key.0)?;
+        let encrypted = aead_encrypt(&map_key, poll_identifier.as_bytes().to_vec(), serialized.to_vec())?;
+
+        let quorum = configuration.quorum();
+        let shares = ShamirSecretSharing::new()
+            .with_share_count(configuration.holders.len())
+            .with_threshold(quorum - 1)
+            .share(&Secret(map_key.0.to_vec()));
+        let shares = configuration.holders.iter().zip(shares.iter())
+            .map(|(holder, share)| Ok(SealedShare {
+                holder: holder.holder.clone(),
+                public_key: holder.public_key.clone(),
+                sealed: base64::encode(&VoteEncryptionKey::from_base64(&holder.public_key)?.seal(&Zeroizing::new(share.to_vec8())))
+            }))
+            .collect::<Result<Vec<SealedShare>>>()?;
+        Ok(BallotMapEscrow {
+            poll_identifier: poll_identifier.to_owned(),
+            quorum,
+            shares,
+            map: AEADString::from_values(encrypted),
+            map_sha256: sha256_hex(&serialized),
+            sealed_at: Utc::now()
+        })
+    }
+
+    /// Open the map with the escrow keys of a quorum of holders, checking
+    /// it against its digest.
+    pub fn open(&self, keys: &[VoteDecryptionKey]) -> Result<Vec<BallotMapEntry>> {
+        let shares: Vec<SecretShare> = self.shares.iter()
+            .filter_map(|share| {
+                let sealed = base64::decode(&share.sealed).ok()?;
+                keys.iter().find_map(|key| key.open(&sealed))
+            })
+            .map(SecretShare::from_vec8)
+            .collect();
+        if shares.len() < self.quorum {
+            return Err(format!("The escrow keys open {} shares of the {} needed.", shares.len(), self.quorum).into());
+        }
+        let secret = ShamirSecretSharing::new()
+            .with_share_count(self.shares.len())
+            .with_threshold(self.quorum - 1)
+            .reconstruct(&shares)?;
+        let mut map_key = AEADKey([0u8; 32]);
+        if secret.0.len() != map_key.0.len() {
+            return Err("The escrow shares do not open the ballot map.".into());
+        }
+        map_key.0.copy_from_slice(&secret.0);
+        let serialized = aead_decrypt(&map_key, &self.map.values()?)
+            .map_err(|_| "The escrow shares do not open the ballot map.")?;
+        if sha256_hex(&serialized) != self.map_sha256 {
+            return Err("The ballot map does not match its digest.".into());
+        }
+        Ok(serde_json::from_slice(&serialized)?)
+    }
+
+    /// Public commitment to the escrow, with the digest of its file.
+    pub fn commitment(&self, escrow_sha256: &str) -> BallotMapCommitment {
+        BallotMapCommitment {
+            poll_identifier: self.poll_identifier.clone(),
+            map_sha256: self.map_sha256.clone(),
+            escrow_sha256: escrow_sha256.to_owned(),
+            quorum: self.quorum,
+            holders: self.shares.iter()
+                .map(|share| EscrowHolder { holder: share.holder.clone(), public_key: share.public_key.clone() })
+                .collect(),
+            sealed_at: self.sealed_at
+        }
+    }
+}
+
+/// Published commitment to an escrowed ballot map.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BallotMapCommitment {
+    pub poll_identifier: String,
+    /// Hex encoded SHA-256 digest of the ballot map as JSON.
+    pub map_sha256: String,
+    /// Hex encoded SHA-256 digest of the escrow file.
+    pub escrow_sha256: String,
+    pub quorum: usize,
+    pub holders: Vec<EscrowHolder>,
+    pub sealed_at: DateTime<Utc>
+}
+
+impl fmt::Display for BallotMapCommitment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Poll:       {}", self.poll_identifier)?;
+        writeln!(f, "Ballot map: {}", self.map_sha256)?;
+        writeln!(f, "Escrow:     {}", self.escrow_sha256)?;
+        write!(f, "Quorum:     {} of {} holders", self.quorum, self.holders.len())?;
+        for holder in self.holders.iter() {
+            write!(f, "\n  {}", holder)?;
+        }
+        Ok(())
+    }
+}
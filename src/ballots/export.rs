@@ -0,0 +1,198 @@
+//! # Ballot Export
+//!
+//! `BallotExport` writes the ballots of a poll as CSV or JSON, redacted
+//! for whoever receives them by a `RedactionProfile`:
+//!
+//! - `full`: everything, for sealed escrow. Every ballot carries its vote
+//!   codes and choices, the voter it is mailed to, its commitment and the
+//!   salt that opens it.
+//! - `print-vendor`: the serials and the vote codes and choices printed on
+//!   the ballots, without any voter data.
+//! - `public-audit`: the serials and the commitments of the ballots only.
+//!
+//! The commitment of a ballot is the SHA3-256 hash of a salt and of its
+//! serial, vote codes and choices. The salt of a ballot is derived from the
+//! Poll Master Key and its serial, so that the exports of a poll commit to
+//! the same ballots and the escrowed export opens the published one.
+
+use std::fmt;
+use std::str::FromStr;
+use crypto::digest::Digest;
+use crypto::sha3::{Sha3, Sha3Mode};
+use crate::Result;
+use crate::voter_roster::VoterInfo;
+use super::*;
+
+/// Recipient of an export of the ballots, and what they may see.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum RedactionProfile { Full, PrintVendor, PublicAudit }
+
+pub const REDACTION_PROFILES: [&str; 3] = ["full", "print-vendor", "public-audit"];
+
+impl FromStr for RedactionProfile {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "full" => Ok(RedactionProfile::Full),
+            "print-vendor" => Ok(RedactionProfile::PrintVendor),
+            "public-audit" => Ok(RedactionProfile::PublicAudit),
+            _ => Err(format!("Unknown redaction profile: {}", value))
+        }
+    }
+}
+
+impl fmt::Display for RedactionProfile {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match self {
+            RedactionProfile::Full => "full",
+            RedactionProfile::PrintVendor => "print-vendor",
+            RedactionProfile::PublicAudit => "public-audit"
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BallotExportFormat { Csv, Json }
+
+pub const BALLOT_EXPORT_FORMATS: [&str; 2] = ["csv", "json"];
+
+impl FromStr for BallotExportFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "csv" => Ok(BallotExportFormat::Csv),
+            "json" => Ok(BallotExportFormat::Json),
+            _ => Err(format!("Unknown ballot export format: {}", value))
+        }
+    }
+}
+
+impl fmt::Display for BallotExportFormat {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match self {
+            BallotExportFormat::Csv => "csv",
+            BallotExportFormat::Json => "json"
+        })
+    }
+}
+
+/// Ballot as exported. The fields redacted by the profile are left out.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ExportedBallot {
+    pub serial: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub choice1_votecode: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub choice1_value: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub choice2_votecode: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub choice2_value: Option<String>,
+    /// Hex encoded SHA3-256 commitment of the ballot.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commitment: Option<String>,
+    /// Hex encoded salt opening the commitment.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub salt: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub first_name: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub street_address: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub city: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zip_code: Option<String>
+}
+
+const BALLOT_SALT_TAG: &[u8] = b"7E:BALLOT-SALT:";
+
+/// Salt of the ballot `serial`: SHA3-256(tag || key || serial).
+pub fn ballot_salt(key: &[u8], serial: &str) -> [u8; 32] {
+    let mut hasher = Sha3::new(Sha3Mode::Sha3_256);
+    hasher.input(BALLOT_SALT_TAG);
+    hasher.input(key);
+    hasher.input(serial.as_bytes());
+    let mut salt = [0u8; 32];
+    hasher.result(&mut salt);
+    salt
+}
+
+/// Hex encoded commitment of a ballot: SHA3-256 of the salt, then of the
+/// serial, vote codes and choices separated by colons.
+pub fn ballot_commitment(salt: &[u8], serial: &str, choice1: (&str, &str), choice2: (&str, &str)) -> String {
+    let mut hasher = Sha3::new(Sha3Mode::Sha3_256);
+    hasher.input(salt);
+    hasher.input(format!("{}:{}:{}:{}:{}", serial, choice1.0, choice1.1, choice2.0, choice2.1).as_bytes());
+    let mut commitment = [0u8; 32];
+    hasher.result(&mut commitment);
+    hex::encode(commitment)
+}
+
+/// Ballots of a poll, redacted for export.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BallotExport {
+    pub profile: RedactionProfile,
+    pub ballots: Vec<ExportedBallot>
+}
+
+impl BallotExport {
+    /// Redact `ballots` by `profile`, with `voters` the voters the ballots
+    /// are mailed to in the same order and `key` the key the salts of the
+    /// ballots are derived from.
+    pub fn new(profile: RedactionProfile, ballots: &[Ballot], num_ballots: usize, voters: &[VoterInfo], key: &[u8]) -> Self {
+        let ballots = ballots.iter().enumerate()
+            .map(|(n, ballot)| {
+                let serial = string_from_ballotserial(&ballot.serial, num_ballots);
+                let choice1 = (string_from_votecode(&ballot.choice1.votecode), string_from_choicevalue(&ballot.choice1.choice));
+                let choice2 = (string_from_votecode(&ballot.choice2.votecode), string_from_choicevalue(&ballot.choice2.choice));
+                let salt = ballot_salt(key, &serial);
+                let commitment = ballot_commitment(&salt, &serial, (&choice1.0, &choice1.1), (&choice2.0, &choice2.1));
+                let mut exported = ExportedBallot { serial, ..Default::default() };
+                if profile != RedactionProfile::PublicAudit {
+                    exported.choice1_votecode = Some(choice1.0);
+                    exported.choice1_value = Some(choice1.1);
+                    exported.choice2_votecode = Some(choice2.0);
+                    exported.choice2_value = Some(choice2.1);
+                }
+                if profile != RedactionProfile::PrintVendor {
+                    exported.commitment = Some(commitment);
+                }
+                if profile == RedactionProfile::Full {
+                    exported.salt = Some(hex::encode(salt));
+                    // Ballots without a voter are left blank.
+                    let voter = |field: fn(&VoterInfo) -> &String| Some(voters.get(n).map(field).cloned().unwrap_or_default());
+                    exported.last_name = voter(|voter| &voter.last_name);
+                    exported.first_name = voter(|voter| &voter.first_name);
+                    exported.street_address = voter(|voter| &voter.street_address);
+                    exported.city = voter(|voter| &voter.city);
+                    exported.state = voter(|voter| &voter.state);
+                    exported.zip_code = voter(|voter| &voter.zip_code);
+                }
+                exported
+            })
+            .collect();
+        BallotExport { profile, ballots }
+    }
+
+    /// Contents of the export file in `format`.
+    pub fn to_bytes(&self, format: BallotExportFormat) -> Result<Vec<u8>> {
+        match format {
+            BallotExportFormat::Csv => {
+                let mut csvwriter = csv::Writer::from_writer(Vec::new());
+                for ballot in self.ballots.iter() {
+                    csvwriter.serialize(ballot)?;
+                }
+                Ok(csvwriter.into_inner().map_err(|err| err.to_string())?)
+            },
+            BallotExportFormat::Json => Ok(serde_json::to_vec_pretty(&self.ballots)?)
+        }
+    }
+}
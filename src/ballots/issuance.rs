@@ -0,0 +1,173 @@
+//! # Ballot Issuance States
+//!
+//! Every ballot of a poll moves through the issuance states as the
+//! workflows handle it: generated with the committed planes (step 1),
+//! printed (step 3), mailed once its mailing label is exported, delivered
+//! once emailed to its voter, then either spoiled, when audited (step 4)
+//! or replaced (`spoil`), or voted (step 6).
+//!
+//! The states are kept in `ballot_states.jsonl` in the data directory of
+//! the poll, one JSON record per line for each update, with the serial
+//! ranges it moved. A ballot only moves forward: printing the ballots again
+//! does not take a mailed ballot back to printed, and spoiled and voted
+//! ballots stay so.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use chrono::{DateTime, Utc};
+use crate::Result;
+use super::*;
+
+pub const BALLOT_STATES_FILENAME: &str = "ballot_states.jsonl";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BallotState {
+    Generated,
+    Printed,
+    Mailed,
+    Delivered,
+    Spoiled,
+    Voted
+}
+
+impl BallotState {
+    /// Whether a ballot in this state moves to `next`.
+    pub fn moves_to(&self, next: BallotState) -> bool {
+        match self {
+            BallotState::Spoiled | BallotState::Voted => false,
+            _ => *self < next
+        }
+    }
+}
+
+impl fmt::Display for BallotState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match self {
+            BallotState::Generated => "generated",
+            BallotState::Printed => "printed",
+            BallotState::Mailed => "mailed",
+            BallotState::Delivered => "delivered",
+            BallotState::Spoiled => "spoiled",
+            BallotState::Voted => "voted"
+        })
+    }
+}
+
+/// Update of the state of the ballots of `serials`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BallotStateUpdate {
+    pub recorded_at: DateTime<Utc>,
+    pub state: BallotState,
+    pub serials: Vec<SerialRange>
+}
+
+/// Consecutive serials of `serials` as ranges.
+fn serial_ranges(serials: &[BallotSerial]) -> Vec<SerialRange> {
+    let mut serials = serials.to_vec();
+    serials.sort_unstable();
+    serials.dedup();
+    let mut ranges: Vec<SerialRange> = Vec::new();
+    for serial in serials {
+        match ranges.last_mut() {
+            Some(range) if range.end() == serial => range.count += 1,
+            _ => ranges.push(SerialRange { start: serial, count: 1 })
+        }
+    }
+    ranges
+}
+
+/// Record the ballots of `serials` as moved to `state`, in the ballot states
+/// of the poll of `datadir_path`.
+pub fn record_ballot_states(datadir_path: &str, state: BallotState, serials: &[BallotSerial]) -> Result<()> {
+    if serials.is_empty() {
+        return Ok(());
+    }
+    let update = BallotStateUpdate { recorded_at: Utc::now(), state, serials: serial_ranges(serials) };
+    let mut file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(Path::new(datadir_path).join(BALLOT_STATES_FILENAME))?;
+    writeln!(file, "{}", serde_json::to_string(&update)?)?;
+    Ok(())
+}
+
+/// State of every ballot tracked.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BallotStates(BTreeMap<BallotSerial, BallotState>);
+
+impl BallotStates {
+    /// Move `serial` to `state`, if it moves forward.
+    pub fn apply(&mut self, serial: BallotSerial, state: BallotState) {
+        match self.0.get(&serial) {
+            Some(current) if !current.moves_to(state) => (),
+            _ => { self.0.insert(serial, state); }
+        }
+    }
+
+    pub fn state(&self, serial: BallotSerial) -> Option<BallotState> {
+        self.0.get(&serial).copied()
+    }
+
+    pub fn counts(&self) -> BallotStateCounts {
+        let mut counts = BallotStateCounts::default();
+        for state in self.0.values() {
+            *match state {
+                BallotState::Generated => &mut counts.generated,
+                BallotState::Printed => &mut counts.printed,
+                BallotState::Mailed => &mut counts.mailed,
+                BallotState::Delivered => &mut counts.delivered,
+                BallotState::Spoiled => &mut counts.spoiled,
+                BallotState::Voted => &mut counts.voted
+            } += 1;
+        }
+        counts
+    }
+}
+
+/// Ballot states of the poll of `datadir_path`, replayed from its updates.
+pub fn read_ballot_states(datadir_path: &str) -> Result<BallotStates> {
+    let path = Path::new(datadir_path).join(BALLOT_STATES_FILENAME);
+    let mut states = BallotStates::default();
+    if !path.exists() {
+        return Ok(states);
+    }
+    for line in fs::read_to_string(&path)?.lines().filter(|line| !line.trim().is_empty()) {
+        let update: BallotStateUpdate = serde_json::from_str(line)?;
+        update.serials.iter()
+            .flat_map(SerialRange::serials)
+            .for_each(|serial| states.apply(serial, update.state));
+    }
+    Ok(states)
+}
+
+/// Ballots in each state.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct BallotStateCounts {
+    pub generated: usize,
+    pub printed: usize,
+    pub mailed: usize,
+    pub delivered: usize,
+    pub spoiled: usize,
+    pub voted: usize
+}
+
+impl BallotStateCounts {
+    pub fn tracked(&self) -> usize {
+        self.generated + self.printed + self.mailed + self.delivered + self.spoiled + self.voted
+    }
+
+    /// Ballots that reached their voter, or at least were mailed to them.
+    pub fn mailed_or_later(&self) -> usize {
+        self.mailed + self.delivered + self.voted
+    }
+}
+
+impl fmt::Display for BallotStateCounts {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} generated, {} printed, {} mailed, {} delivered, {} spoiled, {} voted",
+            self.generated, self.printed, self.mailed, self.delivered, self.spoiled, self.voted)
+    }
+}
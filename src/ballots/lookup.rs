@@ -0,0 +1,158 @@
+//! # Vote Code Lookup
+//!
+//! `lookup` matches submitted vote codes against the vote codes of a poll.
+//! Submitted vote codes come from voters, SMS gateways and transactions
+//! anyone can send, so they are hashed with SipHash under keys drawn at
+//! random for every map, and compared in constant time: crafted vote codes
+//! can neither be made to collide nor learn, from the time a lookup takes,
+//! how much of a vote code of the poll they matched.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::hash::{BuildHasher, Hash, Hasher};
+use std::iter::FromIterator;
+use rand::Rng;
+use rand::rngs::OsRng;
+use siphasher::sip::SipHasher13;
+use subtle::ConstantTimeEq;
+use super::{VoteCode, votecode_from_string};
+
+/// SipHash keyed at random.
+#[derive(Clone)]
+pub struct KeyedHashState {
+    keys: (u64, u64)
+}
+
+impl KeyedHashState {
+    pub fn new() -> Self {
+        KeyedHashState { keys: OsRng.gen() }
+    }
+}
+
+impl Default for KeyedHashState {
+    fn default() -> Self { KeyedHashState::new() }
+}
+
+impl fmt::Debug for KeyedHashState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "KeyedHashState")
+    }
+}
+
+impl BuildHasher for KeyedHashState {
+    type Hasher = SipHasher13;
+
+    fn build_hasher(&self) -> SipHasher13 {
+        SipHasher13::new_with_keys(self.keys.0, self.keys.1)
+    }
+}
+
+/// Vote code compared in constant time.
+#[derive(Clone, Copy)]
+struct LookupKey(VoteCode);
+
+impl PartialEq for LookupKey {
+    fn eq(&self, other: &LookupKey) -> bool {
+        self.0.digits()[..].ct_eq(&other.0.digits()[..]).into()
+    }
+}
+
+impl Eq for LookupKey {}
+
+impl Hash for LookupKey {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state);
+    }
+}
+
+/// Map of the vote codes of a poll.
+#[derive(Clone)]
+pub struct VoteCodeMap<V> {
+    map: HashMap<LookupKey, V, KeyedHashState>
+}
+
+impl<V> VoteCodeMap<V> {
+    pub fn new() -> Self {
+        VoteCodeMap { map: HashMap::with_hasher(KeyedHashState::new()) }
+    }
+
+    pub fn len(&self) -> usize { self.map.len() }
+
+    pub fn is_empty(&self) -> bool { self.map.is_empty() }
+
+    pub fn insert(&mut self, votecode: VoteCode, value: V) -> Option<V> {
+        self.map.insert(LookupKey(votecode), value)
+    }
+
+    pub fn remove(&mut self, votecode: &VoteCode) -> Option<V> {
+        self.map.remove(&LookupKey(*votecode))
+    }
+
+    pub fn get(&self, votecode: &VoteCode) -> Option<&V> {
+        self.map.get(&LookupKey(*votecode))
+    }
+
+    pub fn contains(&self, votecode: &VoteCode) -> bool {
+        self.map.contains_key(&LookupKey(*votecode))
+    }
+
+    /// Value of a vote code as printed on a ballot; `None` if it is not a
+    /// valid vote code or not in the map.
+    pub fn get_str(&self, votecode: &str) -> Option<&V> {
+        votecode_from_string(votecode).and_then(|votecode| self.get(&votecode))
+    }
+
+    pub fn contains_str(&self, votecode: &str) -> bool {
+        self.get_str(votecode).is_some()
+    }
+}
+
+impl<V> Default for VoteCodeMap<V> {
+    fn default() -> Self { VoteCodeMap::new() }
+}
+
+impl<V> fmt::Debug for VoteCodeMap<V> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "VoteCodeMap({} vote codes)", self.map.len())
+    }
+}
+
+impl<V> FromIterator<(VoteCode, V)> for VoteCodeMap<V> {
+    fn from_iter<I: IntoIterator<Item = (VoteCode, V)>>(iter: I) -> Self {
+        let mut map = VoteCodeMap::new();
+        iter.into_iter().for_each(|(votecode, value)| { map.insert(votecode, value); });
+        map
+    }
+}
+
+/// Set of vote codes.
+#[derive(Clone, Debug, Default)]
+pub struct VoteCodeSet(VoteCodeMap<()>);
+
+impl VoteCodeSet {
+    pub fn new() -> Self { VoteCodeSet(VoteCodeMap::new()) }
+
+    pub fn len(&self) -> usize { self.0.len() }
+
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+
+    /// Whether the vote code was not in the set.
+    pub fn insert(&mut self, votecode: VoteCode) -> bool {
+        self.0.insert(votecode, ()).is_none()
+    }
+
+    /// Whether the vote code was in the set.
+    pub fn remove(&mut self, votecode: &VoteCode) -> bool {
+        self.0.remove(votecode).is_some()
+    }
+
+    pub fn contains(&self, votecode: &VoteCode) -> bool { self.0.contains(votecode) }
+
+    pub fn contains_str(&self, votecode: &str) -> bool { self.0.contains_str(votecode) }
+}
+
+impl FromIterator<VoteCode> for VoteCodeSet {
+    fn from_iter<I: IntoIterator<Item = VoteCode>>(iter: I) -> Self {
+        VoteCodeSet(iter.into_iter().map(|votecode| (votecode, ())).collect())
+    }
+}
@@ -18,6 +18,33 @@ pub use printed::*;
 pub mod print;
 pub use print::*;
 
+pub mod accessible;
+pub use accessible::*;
+
+pub mod lookup;
+pub use lookup::*;
+
+pub mod ranges;
+pub use ranges::*;
+
+pub mod collisions;
+pub use collisions::*;
+
+pub mod export;
+pub use export::*;
+
+pub mod escrow;
+pub use escrow::*;
+
+pub mod undelivered;
+pub use undelivered::*;
+
+pub mod issuance;
+pub use issuance::*;
+
+pub mod envelope;
+pub use envelope::*;
+
 use std::io::ErrorKind;
 use std::path::Path;
 use std::fs::DirBuilder;
@@ -8,14 +8,28 @@
 //!     Write choices
 //!         Write choice1 ("{ballot.choice1.votecode} {ballot.choice1.choice")
 //!         Write choice2 ("{ballot.choice2.votecode} {ballot.choice2.choice")
+//!     Write poll question (in every locale of the poll, see `LocalizedBallotText`)
+//!     Draw vote payload QR codes next to the vote codes (see `crate::votes`)
+//!     Draw fold lines and scratch-off markers (see `BallotLayout`)
 //!     Leavy empty space for Decoy text ("This ballot is a decoy!...")
+//!
+//! Ballots are either printed one PDF per ballot (`print_ballot`) or as a
+//! single print-ready document with one ballot per page (`print_ballots`).
+//! Large-print ballots (`print_large_print_ballot`) are printed on letter
+//! paper with the text one item per line, at 16 points or more.
+//! Vote receipts carry a QR code to look up the inclusion proof of the vote
+//! (`print_receipt`).
 
 use super::*;
 use std::result::Result;
 use printpdf::*;
 use std::fs::File;
 use std::io::BufWriter;
-use super::untagged::{Ballot, BallotChoice, ChoiceValue, string_from_votecode, string_from_choicevalue};
+use qrcode::{QrCode, Color as QrColor};
+use super::untagged::{Ballot, BallotChoice, ChoiceValue, VoteCode, string_from_votecode, string_from_choicevalue};
+use crate::votes::{VotePayload, PayloadKind};
+use crate::poll_configuration::{LocalizedBallotText, localized_choice_labels};
+use log::warn;
 
 struct Text<'a> {
     pub text: String,
@@ -25,27 +39,107 @@ struct Text<'a> {
     pub font: &'a IndirectFontRef,
 }
 
+#[derive(Debug, Clone, Copy)]
 struct FileSize {
     pub height: Mm,
     pub width: Mm,
 }
 
 const _A5: FileSize = FileSize {
-    height: Mm(210.0), 
+    height: Mm(210.0),
     width: Mm(148.0)
 };
 
 const _A6: FileSize = FileSize {
-    height: Mm(148.0), 
+    height: Mm(148.0),
     width: Mm(105.0)
 };
 
+const _LETTER: FileSize = FileSize {
+    height: Mm(279.4),
+    width: Mm(215.9)
+};
+
+/// Paper size of a printed ballot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PageSize { A5, A6, Letter }
+
+/// Layout options for printed ballots.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct BallotLayout {
+    /// Paper size of each ballot.
+    pub page_size: PageSize,
+    /// Number of evenly spaced horizontal fold lines.
+    pub fold_lines: usize,
+    /// Mark the corners of the scratch-off areas over the vote codes.
+    pub scratch_off_markers: bool,
+    /// Print the vote payload of each choice as a QR code left of its
+    /// vote code. The QR codes must be covered by the scratch-off too.
+    pub qr_codes: bool,
+    /// TrueType font file for all ballot text, for scripts the built-in
+    /// Courier fonts do not cover.
+    pub font: Option<String>
+}
+
+/// Poll specific text printed on ballots.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct BallotText<'a> {
+    /// Poll identifier of the vote payloads of the QR codes.
+    pub poll_identifier: Option<&'a str>,
+    pub question: Option<&'a str>,
+    /// Question and choice text in every locale of the poll. Takes
+    /// precedence over `question` when given.
+    pub localized: &'a [LocalizedBallotText]
+}
+
+impl<'a> BallotText<'a> {
+    pub(crate) fn question(&self) -> Option<String> {
+        match self.localized.is_empty() {
+            true => self.question.map(|question| question.to_owned()),
+            false => Some(self.localized.iter()
+                .map(|text| text.question.as_str())
+                .collect::<Vec<&str>>()
+                .join("\n\n"))
+        }
+    }
+
+    pub(crate) fn choice_label(&self, choice: &ChoiceValue) -> String {
+        match (localized_choice_labels(self.localized), choice) {
+            (Some((label, _)), ChoiceValue::For) => label,
+            (Some((_, label)), ChoiceValue::Against) => label,
+            (None, _) => string_from_choicevalue(choice)
+        }
+    }
+}
+
+impl Default for BallotLayout {
+    fn default() -> Self {
+        BallotLayout {
+            page_size: PageSize::A5,
+            fold_lines: 0,
+            scratch_off_markers: false,
+            qr_codes: false,
+            font: None
+        }
+    }
+}
+
+impl BallotLayout {
+    fn size(&self) -> FileSize {
+        match self.page_size {
+            PageSize::A5 => _A5,
+            PageSize::A6 => _A6,
+            PageSize::Letter => _LETTER
+        }
+    }
+}
+
 // Text to be printed to PDF file
 pub const BALLOTS_PATH: &str = "ballots/";
-const BALLOT_SIZE: FileSize = _A5;
 const TITLE_TEXT: &str = "YES/NO Ballot";
 const INST_TITLE: &str = "Instructions";
-const INST_TEXT: &str = 
+const INST_TEXT: &str =
 "vote online by\n
 entering ballot serial\n
 number and the vote\n
@@ -54,11 +148,28 @@ the scratch-off next\n
 to your choice:";
 const BALLOT_SERIAL_TEXT: &str = "ballot serial: ";
 const VOTE_CODE_TEXT: &str = "vote code:           choice:";
-const _DECOY_TEXT: &str = 
+const QUESTION_TITLE: &str = "Question";
+const SCRATCH_OFF_TEXT: &str = "scratch off";
+const RECEIPT_TITLE: &str = "Vote Receipt";
+const RECEIPT_VOTE_CODE_TEXT: &str = "vote code: ";
+const RECEIPT_TEXT: &str = "scan to check that your vote was recorded";
+const _DECOY_TEXT: &str =
 "This ballot is a decoy!\n
 Remove this sticker\n
 and sell this vote!";
 
+// Courier glyphs are 0.6 em wide, 1 pt = 0.3528 mm
+const COURIER_CHAR_WIDTH_MM: f64 = 0.6 * 0.3528;
+const QUESTION_SIZE: i64 = 10;
+const SCRATCH_OFF_HEIGHT: Pt = Pt(20.0);
+const MARKER_LENGTH: Mm = Mm(3.0);
+const QR_CODE_SIZE: Mm = Mm(18.0);
+const QR_CODE_MARGIN: Mm = Mm(3.0);
+const RECEIPT_QR_CODE_SIZE: Mm = Mm(50.0);
+const LARGE_PRINT_SIZE: i64 = 16;
+const LARGE_PRINT_LINE_HEIGHT: Mm = Mm(9.0);
+const LARGE_PRINT_MARGIN: Mm = Mm(15.0);
+
 
 fn add_text(layer: &PdfLayerReference, text: &Text){
     layer.use_text(text.text.to_string(), text.size as f64, text.startx, text.starty, &text.font);
@@ -68,16 +179,69 @@ fn make_circle(radius: Pt, startx: Pt, starty: Pt) -> Line{
 
     // Make circle
     let circle = Line {
-        points: utils::calculate_points_for_rect(radius, Pt(20.0), startx, starty),
+        points: utils::calculate_points_for_rect(radius, SCRATCH_OFF_HEIGHT, startx, starty),
         is_closed: true,
         has_fill: true,
         has_stroke: true,
         is_clipping_path: false,
     };
-    
+
     circle
 }
 
+/// Draw `payload` as a QR code of `size` centered on (`x`, `y`).
+fn make_qr_code(payload: &str, x: Mm, y: Mm, size: Mm, layer: &PdfLayerReference) {
+    let code = QrCode::new(payload.as_bytes()).unwrap();
+    let width = code.width();
+    let module = size / width as f64;
+    let left = x - size / 2.0;
+    let top = y + size / 2.0;
+
+    layer.set_fill_color(Color::Greyscale(Greyscale::new(0.0, None)));
+    code.to_colors().iter().enumerate()
+        .filter(|(_, &color)| color == QrColor::Dark)
+        .for_each(|(n, _)| {
+            let (column, row) = (n % width, n / width);
+            let center_x = left + module * (column as f64 + 0.5);
+            let center_y = top - module * (row as f64 + 0.5);
+            layer.add_shape(Line {
+                points: utils::calculate_points_for_rect(module, module, center_x, center_y),
+                is_closed: true,
+                has_fill: true,
+                has_stroke: false,
+                is_clipping_path: false,
+            });
+        });
+}
+
+fn make_line(from: (Mm, Mm), to: (Mm, Mm)) -> Line {
+    Line {
+        points: vec![(Point::new(from.0, from.1), false), (Point::new(to.0, to.1), false)],
+        is_closed: false,
+        has_fill: false,
+        has_stroke: true,
+        is_clipping_path: false,
+    }
+}
+
+/// Split text into lines no longer than `width` characters.
+fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    text.lines().for_each(|paragraph| {
+        let mut line = String::new();
+        paragraph.split_whitespace().for_each(|word| {
+            if !line.is_empty() && line.len() + 1 + word.len() > width {
+                lines.push(line.clone());
+                line.clear();
+            }
+            if !line.is_empty() { line.push(' '); }
+            line.push_str(word);
+        });
+        lines.push(line);
+    });
+    lines
+}
+
 fn make_dir() -> Result<(), std::io::Error>{
     match DirBuilder::new().create(Path::new(BALLOTS_PATH)) {
         Ok(_) => Ok(()),
@@ -93,6 +257,11 @@ fn make_dir() -> Result<(), std::io::Error>{
 }
 
 pub fn print_ballot(ballot: &Ballot) -> () {
+    print_ballot_with_layout(ballot, &BallotText::default(), &BallotLayout::default())
+}
+
+/// Print a single ballot to its own PDF file in `BALLOTS_PATH`.
+pub fn print_ballot_with_layout(ballot: &Ballot, text: &BallotText, layout: &BallotLayout) {
 
     // Create ballots dir
     make_dir().unwrap();
@@ -100,42 +269,194 @@ pub fn print_ballot(ballot: &Ballot) -> () {
     // Create new document
     let file = BALLOTS_PATH.to_string() + &ballot.serial.to_string()  + ".pdf";
     let mut file_writer = BufWriter::new(File::create(file).unwrap());
-    
+
     // Start new PDF
-    let (doc, page1, layer1) = PdfDocument::new(ballot.serial.to_string(), BALLOT_SIZE.width, BALLOT_SIZE.height, "layer1".to_string());
+    let size = layout.size();
+    let (doc, page1, layer1) = PdfDocument::new(ballot.serial.to_string(), size.width, size.height, "layer1".to_string());
     let current_layer = doc.get_page(page1).get_layer(layer1);
 
     // Add fonts for title and text
-    let font_title = doc.add_builtin_font(BuiltinFont::CourierBold).unwrap();
-    let font_text = doc.add_builtin_font(BuiltinFont::Courier).unwrap();
+    let (font_title, font_text) = add_fonts(&doc, layout).unwrap();
+
+    render_ballot(ballot, text, layout, &current_layer, &font_title, &font_text);
+
+    // Save document
+    doc.save(&mut file_writer).unwrap()
+}
+
+/// Print all ballots into one print-ready PDF, one ballot per page.
+pub fn print_ballots(ballots: &[Ballot], text: &BallotText, layout: &BallotLayout, path: &dyn AsRef<Path>) -> crate::Result<()> {
+    let mut file_writer = BufWriter::new(File::create(path)?);
+
+    let size = layout.size();
+    let (doc, page1, layer1) = PdfDocument::new(TITLE_TEXT, size.width, size.height, "layer1".to_string());
+    let (font_title, font_text) = add_fonts(&doc, layout)?;
+
+    ballots.iter().enumerate().for_each(|(n, ballot)| {
+        let (page, layer) = match n {
+            0 => (page1, layer1),
+            _ => doc.add_page(size.width, size.height, "layer1".to_string())
+        };
+        let current_layer = doc.get_page(page).get_layer(layer);
+        render_ballot(ballot, text, layout, &current_layer, &font_title, &font_text);
+    });
+
+    doc.save(&mut file_writer)?;
+    Ok(())
+}
+
+/// Print a large-print ballot to `path`, on letter paper whatever the page
+/// size of `layout`.
+pub fn print_large_print_ballot(ballot: &Ballot, text: &BallotText, layout: &BallotLayout, path: &dyn AsRef<Path>) -> crate::Result<()> {
+    let mut file_writer = BufWriter::new(File::create(path)?);
+
+    let size = _LETTER;
+    let (doc, page1, layer1) = PdfDocument::new(TITLE_TEXT, size.width, size.height, "layer1".to_string());
+    let (font_title, font_text) = add_fonts(&doc, layout)?;
+    let line_width = ((size.width - LARGE_PRINT_MARGIN * 2.0).0 / (LARGE_PRINT_SIZE as f64 * COURIER_CHAR_WIDTH_MM)) as usize;
+
+    // Lay the ballot out top to bottom, one item per line: text, size,
+    // font and whether the line is covered by a scratch-off.
+    let blank = || (String::new(), LARGE_PRINT_SIZE, &font_text, false);
+    let mut lines: Vec<(String, i64, &IndirectFontRef, bool)> = vec![
+        (TITLE_TEXT.to_string(), 28, &font_title, false),
+        blank(),
+        (BALLOT_SERIAL_TEXT.to_string() + &ballot.serial.to_string(), 20, &font_title, false),
+        blank(),
+        (INST_TITLE.to_string(), 20, &font_title, false)
+    ];
+    let instructions = INST_TEXT.split_whitespace().collect::<Vec<&str>>().join(" ");
+    lines.extend(wrap_text(&instructions, line_width).into_iter().map(|line| (line, LARGE_PRINT_SIZE, &font_text, false)));
+    lines.push(blank());
+    for choice in [&ballot.choice1, &ballot.choice2] {
+        lines.push((text.choice_label(&choice.choice), 20, &font_title, false));
+        lines.push((RECEIPT_VOTE_CODE_TEXT.to_string() + &string_from_votecode(&choice.votecode), 18, &font_text, true));
+        lines.push(blank());
+    }
+    if let Some(question) = text.question() {
+        lines.push((QUESTION_TITLE.to_string(), 20, &font_title, false));
+        lines.extend(wrap_text(&question, line_width).into_iter().map(|line| (line, LARGE_PRINT_SIZE, &font_text, false)));
+    }
+
+    // Continue on a new page when the question does not fit.
+    let lines_per_page = ((size.height - LARGE_PRINT_MARGIN * 2.0).0 / LARGE_PRINT_LINE_HEIGHT.0) as usize;
+    for (n, page_lines) in lines.chunks(lines_per_page).enumerate() {
+        let (page, layer) = match n {
+            0 => (page1, layer1),
+            _ => doc.add_page(size.width, size.height, "layer1".to_string())
+        };
+        let layer = doc.get_page(page).get_layer(layer);
+        for (row, (line, font_size, font, scratch_off)) in page_lines.iter().enumerate() {
+            let y = size.height - LARGE_PRINT_MARGIN - LARGE_PRINT_LINE_HEIGHT * (row + 1) as f64;
+            layer.use_text(line.clone(), *font_size as f64, LARGE_PRINT_MARGIN, y, font);
+
+            // Frame the scratch-off area over the vote code
+            if *scratch_off && layout.scratch_off_markers {
+                let left = LARGE_PRINT_MARGIN - Mm(1.0);
+                let right = LARGE_PRINT_MARGIN + Mm(line.len() as f64 * *font_size as f64 * COURIER_CHAR_WIDTH_MM + 1.0);
+                let (top, bottom) = (y + LARGE_PRINT_LINE_HEIGHT - Mm(2.0), y - Mm(2.0));
+                layer.add_shape(make_line((left, top), (right, top)));
+                layer.add_shape(make_line((right, top), (right, bottom)));
+                layer.add_shape(make_line((right, bottom), (left, bottom)));
+                layer.add_shape(make_line((left, bottom), (left, top)));
+            }
+        }
+    }
+
+    doc.save(&mut file_writer)?;
+    Ok(())
+}
+
+/// Print a vote receipt with a QR code to look up the inclusion proof of the vote.
+pub fn print_receipt(poll_identifier: &str, votecode: &VoteCode, path: &dyn AsRef<Path>) -> crate::Result<()> {
+    let mut file_writer = BufWriter::new(File::create(path)?);
+
+    let size = _A6;
+    let (doc, page1, layer1) = PdfDocument::new(RECEIPT_TITLE, size.width, size.height, "layer1".to_string());
+    let current_layer = doc.get_page(page1).get_layer(layer1);
+    let font_title = doc.add_builtin_font(BuiltinFont::CourierBold)?;
+    let font_text = doc.add_builtin_font(BuiltinFont::Courier)?;
 
     let title: Text = Text {
-        text: TITLE_TEXT.to_string(), 
-        size: 30, 
-        startx: Mm(30.0), 
-        starty: BALLOT_SIZE.height - Mm(20.0),
-        font: &font_title,
+        text: RECEIPT_TITLE.to_string(),
+        size: 20,
+        startx: Mm(10.0),
+        starty: size.height - Mm(20.0),
+        font: &font_title
+    };
+    let poll: Text = Text {
+        text: poll_identifier.to_string(),
+        size: 10,
+        starty: size.height - Mm(32.0),
+        font: &font_text,
+        ..title
+    };
+    let vote_code: Text = Text {
+        text: RECEIPT_VOTE_CODE_TEXT.to_string() + &string_from_votecode(votecode),
+        starty: size.height - Mm(40.0),
+        ..poll
+    };
+    let scan: Text = Text {
+        text: RECEIPT_TEXT.to_string(),
+        size: 7,
+        starty: size.height / 2.0 - Mm(10.0) - RECEIPT_QR_CODE_SIZE / 2.0 - Mm(6.0),
+        ..poll
+    };
+    add_text(&current_layer, &title);
+    add_text(&current_layer, &poll);
+    add_text(&current_layer, &vote_code);
+    add_text(&current_layer, &scan);
+
+    let payload = VotePayload::proof(poll_identifier, votecode);
+    make_qr_code(&payload.to_string(), size.width / 2.0, size.height / 2.0 - Mm(10.0), RECEIPT_QR_CODE_SIZE, &current_layer);
+
+    doc.save(&mut file_writer)?;
+    Ok(())
+}
+
+// Fonts for title and text
+fn add_fonts(doc: &PdfDocumentReference, layout: &BallotLayout) -> crate::Result<(IndirectFontRef, IndirectFontRef)> {
+    match &layout.font {
+        Some(font) => {
+            let font = doc.add_external_font(File::open(font)?)?;
+            Ok((font.clone(), font))
+        },
+        None => Ok((
+            doc.add_builtin_font(BuiltinFont::CourierBold)?,
+            doc.add_builtin_font(BuiltinFont::Courier)?))
+    }
+}
+
+fn render_ballot(ballot: &Ballot, text: &BallotText, layout: &BallotLayout, current_layer: &PdfLayerReference, font_title: &IndirectFontRef, font_text: &IndirectFontRef) {
+    let size = layout.size();
+
+    let title: Text = Text {
+        text: TITLE_TEXT.to_string(),
+        size: 30,
+        startx: Mm(30.0),
+        starty: size.height - Mm(20.0),
+        font: font_title,
     };
 
     let instructions_title: Text = Text {
         text: INST_TITLE.to_string(),
         size: 15,
         startx: Mm(10.0),
-        starty: BALLOT_SIZE.height - Mm(35.0),
+        starty: size.height - Mm(35.0),
         ..title
     };
     let instructions_text: Text = Text {
         text: INST_TEXT.to_string(),
         size: 10,
-        starty: BALLOT_SIZE.height - Mm(40.0),
-        font: &font_text,
+        starty: size.height - Mm(40.0),
+        font: font_text,
         ..instructions_title
     };
 
     let ballot_serial: Text = Text {
         text: BALLOT_SERIAL_TEXT.to_string() + &ballot.serial.to_string(),
         size: 12,
-        starty: BALLOT_SIZE.height - Mm(70.0),
+        starty: size.height - Mm(70.0),
         ..instructions_text
     };
 
@@ -145,53 +466,64 @@ pub fn print_ballot(ballot: &Ballot) -> () {
     };
 
     // Add ballot/instructions title
-    add_text(&current_layer, &title);
-    add_text(&current_layer, &instructions_title);
+    add_text(current_layer, &title);
+    add_text(current_layer, &instructions_title);
 
     // Start instructions text section
     current_layer.begin_text_section();
-        current_layer.set_font(&font_text, instructions_text.size as f64);
+        current_layer.set_font(font_text, instructions_text.size as f64);
         current_layer.set_text_cursor(instructions_text.startx, instructions_text.starty);
         current_layer.set_line_height(6.0);
-        
+
         // Write lines of instructions
         instructions_text.text.lines()
             .for_each(|line|{
-                current_layer.write_text(line, &font_text);
+                current_layer.write_text(line, font_text);
                 current_layer.add_line_break();
             });
     current_layer.end_text_section();
-    
+
     // Start ballot serial/vote code section
     current_layer.begin_text_section();
-        current_layer.set_font(&font_title, ballot_serial.size as f64);
+        current_layer.set_font(font_title, ballot_serial.size as f64);
         current_layer.set_text_cursor(ballot_serial.startx, ballot_serial.starty);
         current_layer.set_line_height(10.0);
-        
+
         // Write Ballot Serial
-        current_layer.write_text(ballot_serial.text, &font_title);
+        current_layer.write_text(ballot_serial.text, font_title);
         current_layer.add_line_break();
 
         // Write Ballot vote code text
-        current_layer.write_text(vote_code.text, &font_title);
+        current_layer.write_text(vote_code.text, font_title);
     current_layer.end_text_section();
     // End text section
 
     // Add choices
-    make_choice(ballot.choice1, &current_layer, &font_text);
-    make_choice(ballot.choice2, &current_layer, &font_text);
+    make_choice(ballot.choice1, text, size, layout, current_layer, font_text);
+    make_choice(ballot.choice2, text, size, layout, current_layer, font_text);
 
-    // Save document
-    doc.save(&mut file_writer).unwrap()
+    // Add poll question below the choices
+    if let Some(question) = text.question() {
+        make_question(&question, size, current_layer, font_title, font_text);
+    }
+
+    // Add fold lines
+    make_fold_lines(layout.fold_lines, size, current_layer);
 }
 
-fn make_choice(choice: BallotChoice, layer: &PdfLayerReference, font: &IndirectFontRef){
+fn make_choice(choice: BallotChoice, text: &BallotText, size: FileSize, layout: &BallotLayout, layer: &PdfLayerReference, font: &IndirectFontRef){
     let votecode: String = string_from_votecode(&choice.votecode);
-    let width = BALLOT_SIZE.width/2.0 - Mm(20.0);
+    let payload = VotePayload {
+        kind: PayloadKind::Vote,
+        votecode: choice.votecode,
+        poll_identifier: text.poll_identifier.map(|poll| poll.to_owned())
+    };
+    let width = size.width/2.0 - Mm(20.0);
     let height: Mm = match choice.choice {
-        ChoiceValue::For => BALLOT_SIZE.height/2.0 + Mm(20.0),
-        ChoiceValue::Against => BALLOT_SIZE.height/2.0 - Mm(20.0),
+        ChoiceValue::For => size.height/2.0 + Mm(20.0),
+        ChoiceValue::Against => size.height/2.0 - Mm(20.0),
     };
+    let scratch_width = Mm((votecode.len() * 2) as f64);
 
     // Make dash
     let mut dash_pattern = LineDashPattern::default();
@@ -200,16 +532,17 @@ fn make_choice(choice: BallotChoice, layer: &PdfLayerReference, font: &IndirectF
     // Make choice circle
     layer.set_line_dash_pattern(dash_pattern);
     layer.set_fill_color(Color::Greyscale(Greyscale::new(0.8, None)));
-    let circle = make_circle(Mm((votecode.len() * 2) as f64).into(), width.into(), height.into());
+    let circle = make_circle(scratch_width.into(), width.into(), height.into());
     layer.add_shape(circle);
 
     // Make choice value
     dash_pattern.dash_1 = None;
+    layer.set_line_dash_pattern(dash_pattern);
     layer.set_fill_color(Color::Greyscale(Greyscale::new(0.0, None)));
     let choice: Text = Text {
-        text: string_from_choicevalue(&choice.choice), 
-        size: 15, 
-        startx: width + Mm(40.0), 
+        text: text.choice_label(&choice.choice),
+        size: if text.localized.len() < 2 { 15 } else { 10 },
+        startx: width + Mm(40.0),
         starty: height,
         font: font,
     };
@@ -217,10 +550,79 @@ fn make_choice(choice: BallotChoice, layer: &PdfLayerReference, font: &IndirectF
 
     // Make vote code
     let votetext: Text = Text {
-        text: votecode, 
+        text: votecode,
         startx: width - Mm(22.0),
-        size: 9, 
+        size: 9,
         ..choice
     };
     add_text(layer, &votetext);
-}
\ No newline at end of file
+
+    // Add the vote payload QR code left of the scratch-off area
+    if layout.qr_codes {
+        let right = width - scratch_width / 2.0 - QR_CODE_MARGIN;
+        match right - QR_CODE_SIZE >= QR_CODE_MARGIN {
+            true => make_qr_code(&payload.to_string(), right - QR_CODE_SIZE / 2.0, height, QR_CODE_SIZE, layer),
+            false => warn!("No room for QR codes on {:?} ballots.", layout.page_size)
+        }
+    }
+
+    // Mark the corners of the scratch-off area
+    if layout.scratch_off_markers {
+        let half_height: Mm = Mm::from(SCRATCH_OFF_HEIGHT) / 2.0 + Mm(1.0);
+        let left = width - scratch_width / 2.0 - Mm(1.0);
+        let right = width + scratch_width / 2.0 + Mm(1.0);
+        let top = height + half_height;
+        let bottom = height - half_height;
+        vec![(left, top, 1.0, -1.0), (right, top, -1.0, -1.0), (left, bottom, 1.0, 1.0), (right, bottom, -1.0, 1.0)]
+            .into_iter()
+            .for_each(|(x, y, dx, dy)| {
+                layer.add_shape(make_line((x, y), (x + MARKER_LENGTH * dx, y)));
+                layer.add_shape(make_line((x, y), (x, y + MARKER_LENGTH * dy)));
+            });
+        let marker_text: Text = Text {
+            text: SCRATCH_OFF_TEXT.to_string(),
+            startx: left,
+            starty: bottom - Mm(3.0),
+            size: 6,
+            ..votetext
+        };
+        add_text(layer, &marker_text);
+    }
+}
+
+fn make_question(question: &str, size: FileSize, layer: &PdfLayerReference, font_title: &IndirectFontRef, font_text: &IndirectFontRef) {
+    let startx = Mm(10.0);
+    let starty = size.height/2.0 - Mm(40.0);
+    let line_width = ((size.width - startx * 2.0).0 / (QUESTION_SIZE as f64 * COURIER_CHAR_WIDTH_MM)) as usize;
+
+    add_text(layer, &Text {
+        text: QUESTION_TITLE.to_string(),
+        size: 12,
+        startx,
+        starty,
+        font: font_title
+    });
+
+    layer.begin_text_section();
+        layer.set_font(font_text, QUESTION_SIZE as f64);
+        layer.set_text_cursor(startx, starty - Mm(6.0));
+        layer.set_line_height(12.0);
+        wrap_text(question, line_width).iter()
+            .for_each(|line| {
+                layer.write_text(line.clone(), font_text);
+                layer.add_line_break();
+            });
+    layer.end_text_section();
+}
+
+fn make_fold_lines(fold_lines: usize, size: FileSize, layer: &PdfLayerReference) {
+    let mut dash_pattern = LineDashPattern::default();
+    dash_pattern.dash_1 = Some(6);
+    layer.set_line_dash_pattern(dash_pattern);
+    (1..=fold_lines).for_each(|n| {
+        let height = size.height * (n as f64 / (fold_lines + 1) as f64);
+        layer.add_shape(make_line((Mm(0.0), height), (size.width, height)));
+    });
+    dash_pattern.dash_1 = None;
+    layer.set_line_dash_pattern(dash_pattern);
+}
@@ -0,0 +1,147 @@
+//! # Serial Ranges
+//!
+//! The ballots of a poll have the serials `0` to `num_ballots - 1`. Their
+//! serial ranges describe which of them are issued to voters, and which
+//! are reserved: test ballots, for usability tests or as coercion canaries,
+//! whose votes are reported instead of counted, and replacement ballots,
+//! issued instead of spoiled ballots. The ranges are committed with the
+//! ballot question, so that a replacement cannot be taken from the ballots
+//! of other voters without it showing.
+
+use std::fmt;
+use super::*;
+
+/// Serials `start` to `start + count - 1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SerialRange {
+    pub start: BallotSerial,
+    pub count: usize
+}
+
+impl SerialRange {
+    pub fn end(&self) -> BallotSerial {
+        self.start + self.count
+    }
+
+    pub fn contains(&self, serial: BallotSerial) -> bool {
+        self.start <= serial && serial < self.end()
+    }
+
+    pub fn overlaps(&self, other: &SerialRange) -> bool {
+        self.start < other.end() && other.start < self.end()
+    }
+
+    pub fn serials(&self) -> std::ops::Range<BallotSerial> {
+        self.start..self.end()
+    }
+}
+
+impl fmt::Display for SerialRange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.count {
+            0 => write!(f, "none"),
+            _ => write!(f, "{}-{}", self.start, self.end() - 1)
+        }
+    }
+}
+
+/// Use of a reserved range.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SerialPurpose {
+    Test,
+    Replacement
+}
+
+impl fmt::Display for SerialPurpose {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SerialPurpose::Test => write!(f, "test"),
+            SerialPurpose::Replacement => write!(f, "replacement")
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ReservedRange {
+    pub purpose: SerialPurpose,
+    pub start: BallotSerial,
+    pub count: usize
+}
+
+impl ReservedRange {
+    pub fn range(&self) -> SerialRange {
+        SerialRange { start: self.start, count: self.count }
+    }
+}
+
+/// Serial ranges of the ballots of a poll.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SerialRanges {
+    /// Ballots issued to voters.
+    pub issued: SerialRange,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reserved: Vec<ReservedRange>
+}
+
+impl SerialRanges {
+    /// Ranges of a poll without reserved ballots: every ballot is issued.
+    pub fn for_poll(num_ballots: usize) -> Self {
+        SerialRanges {
+            issued: SerialRange { start: 0, count: num_ballots },
+            reserved: Vec::new()
+        }
+    }
+
+    /// Check that the ranges are ballots of a poll of `num_ballots`
+    /// ballots and that no two of them overlap.
+    pub fn validate(&self, num_ballots: usize) -> crate::Result<()> {
+        if self.issued.count == 0 {
+            return Err("No ballot serials are issued to voters.".into());
+        }
+        let ranges: Vec<(String, SerialRange)> = std::iter::once(("issued".to_owned(), self.issued))
+            .chain(self.reserved.iter().map(|reserved| (format!("{} reserve", reserved.purpose), reserved.range())))
+            .collect();
+        for (n, (name, range)) in ranges.iter().enumerate() {
+            if num_ballots < range.end() {
+                return Err(format!("Serials {} of the {} range are not ballots of the poll, which has {} ballots.", range, name, num_ballots).into());
+            }
+            if let Some((other, _)) = ranges[..n].iter().find(|(_, other)| other.overlaps(range)) {
+                return Err(format!("Serials {} of the {} range collide with the {} range.", range, name, other).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Purpose of the reserved range of `serial`, `None` for other ballots.
+    pub fn purpose(&self, serial: BallotSerial) -> Option<SerialPurpose> {
+        self.reserved.iter()
+            .find(|reserved| reserved.range().contains(serial))
+            .map(|reserved| reserved.purpose)
+    }
+
+    /// Serials of the test ballots.
+    pub fn test_serials(&self) -> Vec<BallotSerial> {
+        self.reserved.iter()
+            .filter(|reserved| reserved.purpose == SerialPurpose::Test)
+            .flat_map(|reserved| reserved.range().serials())
+            .collect()
+    }
+
+    /// Check that the issued ballot `spoiled` may be replaced by
+    /// `replacement`: a replacement ballot when replacement ballots are
+    /// reserved, never a test ballot.
+    pub fn check_replacement(&self, spoiled: BallotSerial, replacement: BallotSerial) -> crate::Result<()> {
+        if !self.issued.contains(spoiled) {
+            return Err(format!("Ballot {} was not issued to a voter: issued serials are {}.", spoiled, self.issued).into());
+        }
+        let reserved_replacements = self.reserved.iter()
+            .any(|reserved| reserved.purpose == SerialPurpose::Replacement);
+        match self.purpose(replacement) {
+            Some(SerialPurpose::Replacement) => Ok(()),
+            Some(SerialPurpose::Test) => Err(format!("Ballot {} is a test ballot.", replacement).into()),
+            None if reserved_replacements => Err(format!("Ballot {} is not a replacement ballot: replacements are taken from the replacement reserve.", replacement).into()),
+            None => Ok(())
+        }
+    }
+}
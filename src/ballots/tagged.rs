@@ -59,7 +59,7 @@ pub fn string_from_taggedchoicevalue_padded(choice: &TaggedChoiceValue) -> Strin
 }
 
 
-pub fn generate_decoy_serials(seed: CSPRNGSeed, num_decoys: usize, num_ballots: usize) -> Vec<BallotSerial> {
+pub fn generate_decoy_serials(seed: &CSPRNGSeed, num_decoys: usize, num_ballots: usize) -> Vec<BallotSerial> {
     let mut rng = CSPRNG::from_csprng_seed(seed);
     let mut set = HashSet::new();
     while set.len() < num_decoys {
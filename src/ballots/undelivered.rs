@@ -0,0 +1,77 @@
+//! # Undelivered Ballots
+//!
+//! After mailing, the mailing vendor returns the list of the ballots it
+//! could not deliver. Undelivered ballots are recorded in the poll
+//! configuration and left out of the ballots expected to be voted. The
+//! `UndeliveredPolicy` of the poll decides what happens to their vote
+//! codes: kept, since a returned ballot may still reach its voter, or
+//! invalidated, so that votes for them are neither recorded nor counted,
+//! as for spoiled ballots.
+
+use std::fmt;
+use chrono::{DateTime, Utc};
+use super::*;
+
+/// What happens to the vote codes of undelivered ballots.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UndeliveredPolicy {
+    /// Votes for undelivered ballots are counted.
+    #[default]
+    Keep,
+    /// Votes for undelivered ballots are rejected.
+    Invalidate
+}
+
+impl UndeliveredPolicy {
+    pub fn is_default(&self) -> bool {
+        *self == UndeliveredPolicy::default()
+    }
+}
+
+impl fmt::Display for UndeliveredPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UndeliveredPolicy::Keep => write!(f, "vote codes kept"),
+            UndeliveredPolicy::Invalidate => write!(f, "vote codes invalidated")
+        }
+    }
+}
+
+/// Ballot returned by the mailing vendor as undeliverable.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UndeliveredBallot {
+    pub serial: BallotSerial,
+    /// Reason given by the vendor, such as an unknown address.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    pub recorded_at: DateTime<Utc>
+}
+
+impl fmt::Display for UndeliveredBallot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match &self.reason {
+            Some(reason) => write!(f, "Ballot {} undelivered: {}", self.serial, reason),
+            None => write!(f, "Ballot {} undelivered", self.serial)
+        }
+    }
+}
+
+/// Row of the undeliverable list of the mailing vendor: a CSV file with a
+/// `serial` column and an optional `reason` column.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UndeliverableRow {
+    pub serial: BallotSerial,
+    #[serde(default)]
+    pub reason: Option<String>
+}
+
+/// Rows of an undeliverable list.
+pub fn read_undeliverable_list<R: std::io::Read>(reader: R) -> crate::Result<Vec<UndeliverableRow>> {
+    let mut csvreader = csv::ReaderBuilder::new().trim(csv::Trim::All).from_reader(reader);
+    let rows = csvreader.deserialize::<UndeliverableRow>()
+        .collect::<std::result::Result<Vec<UndeliverableRow>, csv::Error>>()?;
+    Ok(rows.into_iter()
+        .map(|row| UndeliverableRow { reason: row.reason.filter(|reason| !reason.is_empty()), ..row })
+        .collect())
+}
@@ -7,6 +7,8 @@ use std::cmp::max;
 use super::{Serialize, Deserialize};
 use crate::cryptography::csprng::*;
 use crate::cryptography::fast_dice_roller::*;
+use zeroize::Zeroizing;
+use tracing::debug;
 
 pub type BallotSerial = usize;
 
@@ -14,14 +16,13 @@ pub type BallotSerial = usize;
 pub enum ChoiceValue { For, Against }
 pub const CHOICE_VALUES: [ChoiceValue; 2] = [ChoiceValue::For, ChoiceValue::Against];
 
-const VOTE_CODE_NUM_GROUPS: usize = 4;
-const VOTE_CODE_GROUP_SIZE: usize = 4;
+pub use seventh_estate_core::votecode::{VoteCode, VOTE_CODE_LENGTH, string_from_votecode, votecode_from_string};
+use seventh_estate_core::votecode::{VOTE_CODE_NUM_GROUPS, VOTE_CODE_GROUP_SIZE};
+
 const VOTE_CODE_NO_PARITY_LENGTH: usize = VOTE_CODE_NUM_GROUPS * VOTE_CODE_GROUP_SIZE;
-pub const VOTE_CODE_LENGTH: usize = VOTE_CODE_NUM_GROUPS * (VOTE_CODE_GROUP_SIZE + 1);
 const NPVC_MODULUS: u128 = 1_0000_0000_0000_0000;
 
 pub type VoteCodeNoParity = [u8; VOTE_CODE_NO_PARITY_LENGTH];
-pub type VoteCode = [u8; VOTE_CODE_LENGTH];
 
 
 #[derive(Debug, Clone, Copy)]
@@ -51,20 +52,6 @@ pub fn string_from_ballotserial(serial: &BallotSerial, num_ballots: usize) -> St
     format!("{:0width$}", serial, width=digits)
 }
 
-pub fn string_from_votecode(votecode: &VoteCode) -> String {
-    let votecode_digits: Vec<String> = votecode.iter()
-        .map(|n| format!("{}", n))
-        .collect();
-    (0..votecode_digits.len()).into_iter()
-        .step_by(VOTE_CODE_GROUP_SIZE + 1)
-        .map(|base| {
-            votecode_digits[base..(base + VOTE_CODE_GROUP_SIZE + 1)].join("")
-        })
-        .collect::<Vec<String>>()
-        .join("-")
-        .to_owned()
-}
-
 pub fn string_from_choicevalue(choice: &ChoiceValue) -> String {
     match choice {
         ChoiceValue::For => "For".to_owned(),
@@ -72,38 +59,46 @@ pub fn string_from_choicevalue(choice: &ChoiceValue) -> String {
     }
 }
 
-pub fn generate_votecodes(seed: CSPRNGSeed, count: usize) -> Vec<VoteCode> {
-    fn try_generate(seed: CSPRNGSeed, count: usize, num_bytes: usize) -> Option<Vec<usize>> {
+/// Generate `count` distinct vote codes from `seed`. A vote code drawn
+/// for an earlier choice is drawn again, so that no two ballot choices of
+/// a poll share one. The random bytes and the codes without parity digits
+/// they are drawn into are scrubbed once used.
+pub fn generate_votecodes(seed: &CSPRNGSeed, count: usize) -> Vec<VoteCode> {
+    fn try_generate(seed: &CSPRNGSeed, count: usize, num_bytes: usize) -> Option<Zeroizing<Vec<usize>>> {
         let mut prng = CSPRNG::from_csprng_seed(seed);
-        let mut bytes = Vec::<u8>::new();
-        bytes.resize_with(num_bytes, || {0});
+        let mut bytes = Zeroizing::new(vec![0u8; num_bytes]);
         prng.fill_bytes(&mut bytes);
         let mut fdr = FastDiceRoller::from_bytes(&bytes);
-        let npvotecodes: Vec<Option<u128>> = (0..count).into_iter()
-            .map(|_| { fdr.random(NPVC_MODULUS) })
-            .collect();
-        match npvotecodes.iter().any(|s| s.is_none()) {
-            true => None,
-            false => Some(npvotecodes.iter().map(|s| s.unwrap() as usize).collect())
+        let mut npvotecodes: Zeroizing<Vec<u128>> = Zeroizing::new(Vec::with_capacity(count));
+        for _ in 0..count {
+            npvotecodes.push(fdr.random(NPVC_MODULUS)?);
+        }
+        loop {
+            let collisions = later_duplicates(&npvotecodes);
+            if collisions.is_empty() { break; }
+            debug!(collisions = collisions.len(), "Vote codes drawn again");
+            for n in collisions {
+                npvotecodes[n] = fdr.random(NPVC_MODULUS)?;
+            }
         }
+        Some(Zeroizing::new(npvotecodes.iter().map(|&s| s as usize).collect()))
     }
 
     let mut num_bytes: usize = 1024;
-    let npvotecodes: Vec<VoteCodeNoParity>;
+    let npvotecodes: Zeroizing<Vec<VoteCodeNoParity>>;
     loop {
         let maybe_npvotecodes = try_generate(seed, count, num_bytes);
         if let Some(npvcs) = maybe_npvotecodes {
-            npvotecodes = npvcs.iter()
+            npvotecodes = Zeroizing::new(npvcs.iter()
                 .map(|&npvc| {
-                    vcnp_from_vec(
-                        (0..VOTE_CODE_NO_PARITY_LENGTH).into_iter()
-                            .rev()
-                            .map(|n| {
-                                let shift = usize::checked_pow(10, n as u32).unwrap();
-                                ((npvc / shift) % 10) as u8
-                            })
-                            .collect::<Vec<u8>>())
-                }).collect();
+                    let mut vcnp: VoteCodeNoParity = [0; VOTE_CODE_NO_PARITY_LENGTH];
+                    vcnp.iter_mut().rev().enumerate()
+                        .for_each(|(n, digit)| {
+                            let shift = usize::checked_pow(10, n as u32).unwrap();
+                            *digit = ((npvc / shift) % 10) as u8;
+                        });
+                    vcnp
+                }).collect());
             break;
         }
         num_bytes = num_bytes + 1024;
@@ -111,7 +106,7 @@ pub fn generate_votecodes(seed: CSPRNGSeed, count: usize) -> Vec<VoteCode> {
 
     npvotecodes.iter()
         .map(|npvc| {
-            let mut vc: VoteCode = [0; VOTE_CODE_LENGTH];
+            let mut vc = [0; VOTE_CODE_LENGTH];
 
             // Copy the no-parity code into the real code.
             (0..vc.len()).into_iter().step_by(VOTE_CODE_GROUP_SIZE + 1)
@@ -134,10 +129,22 @@ pub fn generate_votecodes(seed: CSPRNGSeed, count: usize) -> Vec<VoteCode> {
                         .sum::<usize>();
                     vc[parityidx] = (((10 * VOTE_CODE_GROUP_SIZE) - sum) % 10) as u8;
                 });
-            vc
+            VoteCode::from_digits(vc).expect("generated vote codes have valid parity digits")
         }).collect::<Vec<VoteCode>>()
 }
 
+/// Positions of the values equal to a value at an earlier position. The
+/// positions are sorted instead of the values, so that no copy of them is
+/// left behind.
+fn later_duplicates(values: &[u128]) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..values.len()).collect();
+    order.sort_unstable_by_key(|&n| (values[n], n));
+    order.windows(2)
+        .filter(|pair| values[pair[0]] == values[pair[1]])
+        .map(|pair| pair[1])
+        .collect()
+}
+
 pub fn generate_ballots(serials: &Vec<BallotSerial>, votecodes: &Vec<VoteCode>) -> ListOfBallots {
     assert!((2 * serials.len()) <= votecodes.len(),
         "Too many vote codes supplied.");
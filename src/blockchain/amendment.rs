@@ -0,0 +1,65 @@
+//! # Ballot Amendments
+//!
+//! A ballot that a voter damaged or lost is spoiled and replaced by another
+//! ballot of the poll. The replacement is committed to the blockchain as an
+//! amendment of the poll: the SHA3-256 hash of the spoiled and replacement
+//! serials is posted with the commit key, so that ballots cannot be spoiled
+//! after the fact without it showing on the chain. Votes for the vote codes
+//! of a spoiled ballot are not counted. A replacement issued in a sealed
+//! envelope is committed to with the commitment of its ballot too.
+
+use std::fmt;
+use crypto::digest::Digest;
+use crypto::sha3::{Sha3, Sha3Mode};
+use serde::{Serialize, Deserialize};
+use chrono::{DateTime, Utc};
+use crate::ballots::BallotSerial;
+use super::merkle::CryptoSHA3256Hash;
+
+/// Spoiled ballot, its replacement and the transaction committing to them.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BallotAmendment {
+    pub spoiled_serial: BallotSerial,
+    pub replacement_serial: BallotSerial,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    /// Hex encoded commitment of the replacement ballot, when it is sealed
+    /// in an envelope for the print pipeline.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ballot_commitment: Option<String>,
+    /// Hex encoded hash posted to the blockchain.
+    pub amendment_hash: String,
+    pub transaction_hash: String,
+    pub address: String,
+    pub submitted_at_block: u64,
+    pub posted_at: DateTime<Utc>
+}
+
+impl fmt::Display for BallotAmendment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Spoiled ballot: {}", self.spoiled_serial)?;
+        writeln!(f, "Replacement:    {}", self.replacement_serial)?;
+        if let Some(reason) = &self.reason {
+            writeln!(f, "Reason:         {}", reason)?;
+        }
+        if let Some(commitment) = &self.ballot_commitment {
+            writeln!(f, "Commitment:     {}", commitment)?;
+        }
+        writeln!(f, "Transaction:    {}", self.transaction_hash)?;
+        write!(f, "Block:          {}", self.submitted_at_block)
+    }
+}
+
+/// SHA3-256 hash committing to the replacement of a spoiled ballot, and to
+/// the commitment of the replacement ballot when sealed in an envelope.
+pub fn amendment_hash(poll_identifier: &str, spoiled_serial: BallotSerial, replacement_serial: BallotSerial, reason: Option<&str>, ballot_commitment: Option<&str>) -> CryptoSHA3256Hash {
+    let mut amendment = format!("7E:SPOIL:{}:{}:{}:{}", poll_identifier, spoiled_serial, replacement_serial, reason.unwrap_or_default());
+    if let Some(commitment) = ballot_commitment {
+        amendment.push_str(&format!(":{}", commitment));
+    }
+    let mut hasher = Sha3::new(Sha3Mode::Sha3_256);
+    hasher.input(amendment.as_bytes());
+    let mut hash = [0u8; 32];
+    hasher.result(&mut hash);
+    hash
+}
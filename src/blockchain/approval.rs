@@ -0,0 +1,225 @@
+//! # Commit Approval
+//!
+//! The Merkle root of a poll is only posted once a quorum of its trustees
+//! approved it. Step 4 builds the Merkle tree and writes a `CommitProposal`
+//! to `commit_proposal.yaml` in the data directory of the poll: the root,
+//! the hash of the ballot question and a manifest of the public artifacts
+//! committed. Every trustee checks the proposal offline and signs its hash
+//! with their own approval key, writing a `CommitApproval`. Step 4 run
+//! again with the approvals of a majority of the trustees posts the root,
+//! as long as the rebuilt proposal has the hash they signed.
+//!
+//! The approval keys are independent of the Poll Master Key, so that whoever
+//! holds it cannot approve for the trustees. Their verification keys are
+//! published in `trustee_approval_keys.yaml` in the data directory when the
+//! poll is created, outside the encrypted poll configuration; a poll
+//! without them is not committed.
+
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use chrono::{DateTime, Utc};
+use crypto::digest::Digest;
+use crypto::sha3::{Sha3, Sha3Mode};
+use serde::{Serialize, Deserialize};
+use crate::Result;
+use crate::bulletin_board::{is_public_artifact, sha256_hex};
+use crate::cryptography::{sign, verify, Base64String};
+
+pub const COMMIT_PROPOSAL_FILENAME: &str = "commit_proposal.yaml";
+pub const TRUSTEE_APPROVAL_KEYS_FILENAME: &str = "trustee_approval_keys.yaml";
+
+/// Verification key of the approvals of a trustee.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TrusteeApprovalKey {
+    pub identifier: String,
+    /// Base64 encoded verification key.
+    pub public_key: String
+}
+
+/// Verification keys of the trustees published in the data directory
+/// `datadir`; the commit of a poll without them is refused.
+pub fn read_trustee_approval_keys(datadir: &Path) -> Result<Vec<TrusteeApprovalKey>> {
+    let path = datadir.join(TRUSTEE_APPROVAL_KEYS_FILENAME);
+    let trustees: Vec<TrusteeApprovalKey> = match fs::File::open(&path) {
+        Ok(file) => serde_yaml::from_reader(file)?,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(err) => return Err(err.into())
+    };
+    if trustees.is_empty() {
+        return Err(format!("No trustee approval keys in {}: the poll is not committed without the approval of its trustees.",
+            path.display()).into());
+    }
+    Ok(trustees)
+}
+
+/// Public artifact of the data directory committed by a proposal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProposedArtifact {
+    pub name: String,
+    pub size: u64,
+    /// Hex encoded SHA-256 hash of the artifact.
+    pub sha256: String
+}
+
+/// Number of trustees whose approvals allow the root to be posted: a
+/// majority of them.
+pub fn approval_quorum(num_trustees: usize) -> usize {
+    num_trustees / 2 + 1
+}
+
+/// Manifest of the public artifacts of a data directory, by name.
+pub fn proposal_manifest(datadir: &Path) -> Result<Vec<ProposedArtifact>> {
+    let mut manifest: Vec<ProposedArtifact> = Vec::new();
+    for entry in fs::read_dir(datadir)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if !is_public_artifact(&name) {
+            continue;
+        }
+        let data = fs::read(entry.path())?;
+        manifest.push(ProposedArtifact { name, size: data.len() as u64, sha256: sha256_hex(&data) });
+    }
+    manifest.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(manifest)
+}
+
+/// Commitment of a poll awaiting the approval of its trustees. It holds
+/// nothing that changes between runs of step 4, so that approvals signed
+/// for it stay valid while the poll is unchanged.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitProposal {
+    pub poll_identifier: String,
+    /// Hex encoded Merkle root to post.
+    pub root: String,
+    pub leaves: usize,
+    /// Hex encoded SHA3-256 hash of the ballot question posted with the root.
+    pub question_hash: String,
+    pub end_block: Option<u64>,
+    pub quorum: usize,
+    pub trustees: Vec<TrusteeApprovalKey>,
+    pub manifest: Vec<ProposedArtifact>
+}
+
+impl CommitProposal {
+    /// Hex encoded SHA3-256 hash of the proposal, signed by the trustees.
+    pub fn hash(&self) -> String {
+        let mut hasher = Sha3::new(Sha3Mode::Sha3_256);
+        hasher.input(&serde_json::to_vec(self).unwrap());
+        let mut hash = [0u8; 32];
+        hasher.result(&mut hash);
+        hex::encode(hash)
+    }
+
+    pub fn trustee(&self, identifier: &str) -> Option<&TrusteeApprovalKey> {
+        self.trustees.iter().find(|trustee| trustee.identifier == identifier)
+    }
+
+    /// Check `approvals` against the proposal, counting every trustee once.
+    pub fn check_approvals(&self, approvals: &[CommitApproval]) -> ApprovalCheck {
+        let hash = self.hash();
+        let mut check = ApprovalCheck {
+            proposal_hash: hash.clone(),
+            root: self.root.clone(),
+            quorum: self.quorum,
+            approved_by: Vec::new(),
+            rejected: Vec::new()
+        };
+        for approval in approvals.iter() {
+            let valid = approval.proposal_hash == hash && self.trustee(&approval.trustee)
+                .map(|trustee| approval.verify(&Base64String(trustee.public_key.clone())))
+                .unwrap_or(false);
+            match valid {
+                true if !check.approved_by.contains(&approval.trustee) => check.approved_by.push(approval.trustee.clone()),
+                true => (),
+                false => check.rejected.push(approval.trustee.clone())
+            }
+        }
+        check
+    }
+}
+
+impl fmt::Display for CommitProposal {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Poll:      {}", self.poll_identifier)?;
+        writeln!(f, "Root:      {} ({} leaves)", self.root, self.leaves)?;
+        writeln!(f, "Question:  {}", self.question_hash)?;
+        writeln!(f, "Quorum:    {} of {} trustees", self.quorum, self.trustees.len())?;
+        write!(f, "Artifacts: {}", self.manifest.len())?;
+        for artifact in self.manifest.iter() {
+            write!(f, "\n  {} {}", artifact.sha256, artifact.name)?;
+        }
+        write!(f, "\nProposal:  {}", self.hash())
+    }
+}
+
+/// Approval of a commit proposal by a trustee.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CommitApproval {
+    pub trustee: String,
+    /// Hex encoded hash of the approved proposal.
+    pub proposal_hash: String,
+    /// Base64 encoded signature of the proposal hash.
+    pub signature: String,
+    pub approved_at: DateTime<Utc>
+}
+
+impl CommitApproval {
+    /// Approve `proposal` as `trustee` with its approval signing key.
+    pub fn sign(proposal: &CommitProposal, trustee: &str, signing_key: &Base64String) -> Result<Self> {
+        let proposal_hash = proposal.hash();
+        let (_, signature) = sign(signing_key, proposal_hash.as_bytes().to_vec())?;
+        Ok(CommitApproval {
+            trustee: trustee.to_owned(),
+            proposal_hash,
+            signature: base64::encode(&signature),
+            approved_at: Utc::now()
+        })
+    }
+
+    pub fn verify(&self, public_key: &Base64String) -> bool {
+        match base64::decode(&self.signature) {
+            Ok(signature) => verify(public_key, self.proposal_hash.as_bytes(), &signature).unwrap_or(false),
+            Err(_) => false
+        }
+    }
+}
+
+impl fmt::Display for CommitApproval {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Proposal {} approved by {}", self.proposal_hash, self.trustee)
+    }
+}
+
+/// Approvals of a commit proposal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ApprovalCheck {
+    pub proposal_hash: String,
+    pub root: String,
+    pub quorum: usize,
+    pub approved_by: Vec<String>,
+    /// Approvals of other proposals, of unknown trustees or whose signature
+    /// does not match, by trustee.
+    pub rejected: Vec<String>
+}
+
+impl ApprovalCheck {
+    pub fn valid(&self) -> bool {
+        self.quorum <= self.approved_by.len()
+    }
+}
+
+impl fmt::Display for ApprovalCheck {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Proposal:  {}", self.proposal_hash)?;
+        writeln!(f, "Root:      {}", self.root)?;
+        writeln!(f, "Approvals: {} of {} required", self.approved_by.len(), self.quorum)?;
+        for trustee in self.approved_by.iter() {
+            writeln!(f, "  {}", trustee)?;
+        }
+        for trustee in self.rejected.iter() {
+            writeln!(f, "  ! {} (rejected)", trustee)?;
+        }
+        write!(f, "Approved:  {}", if self.valid() { "yes" } else { "NO" })
+    }
+}
@@ -0,0 +1,119 @@
+//! # Blockchain Backends
+//!
+//! `ChainBackend` is the interface of the blockchain operations to a chain:
+//! `Web3Backend` talks to the node of the network each operation is given
+//! and `MockChain` keeps an in-memory chain for tests. `set_backend` selects
+//! the backend used by all operations instead.
+//!
+//! The node is connected to in the `KeyRole` of each operation, so that
+//! commitments, relayed votes and reads never share a key.
+
+use std::fmt;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use crate::Result;
+use super::{ChainTransaction, PostedTransaction, SignedTransaction, TransactionStatus, UnsignedTransaction};
+
+/// Recipient of a transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Recipient {
+    /// Address of the sending account, for handovers of the vote address.
+    Own,
+    /// Poll address of the network, the address of the sending account if
+    /// none is configured, for commitments and the other records of the
+    /// poll.
+    Poll,
+    /// Vote address of the network.
+    Votes
+}
+
+impl Recipient {
+    /// Role of the key transactions to the recipient are signed with.
+    pub fn role(&self) -> KeyRole {
+        match self {
+            Recipient::Own | Recipient::Poll => KeyRole::Commit,
+            Recipient::Votes => KeyRole::Relay
+        }
+    }
+}
+
+/// Role a network configuration is loaded in. Each role only decodes the
+/// key it needs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyRole {
+    /// Posts commitments with `commit_key`.
+    Commit,
+    /// Relays votes to the vote address with `relayer_key`.
+    Relay,
+    /// Reads the chain; no key is needed.
+    Audit
+}
+
+impl fmt::Display for KeyRole {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            KeyRole::Commit => write!(f, "commit"),
+            KeyRole::Relay => write!(f, "relay"),
+            KeyRole::Audit => write!(f, "audit")
+        }
+    }
+}
+
+#[async_trait]
+pub trait ChainBackend: Send + Sync {
+    /// Blocks read by each request window when scanning for votes.
+    fn block_window(&self) -> u64 { 100 }
+
+    /// Requests sent to the chain at the same time.
+    fn max_concurrent_requests(&self) -> usize { 4 }
+
+    /// Configured address votes are sent to, if any.
+    fn vote_address(&self) -> Option<String>;
+
+    /// Address of the account transactions are signed by, if a key is loaded
+    /// or the key is kept offline.
+    fn sender_address(&self) -> Option<String>;
+
+    /// Whether transactions are signed offline, the key being kept on
+    /// another machine.
+    fn signs_offline(&self) -> bool { false }
+
+    /// Number of the latest block.
+    async fn latest_block(&self) -> Result<u64>;
+
+    /// Number of the latest final block, by the finality policy of the
+    /// network. Every mined block is final unless the backend has a policy.
+    async fn final_block(&self) -> Result<u64> {
+        self.latest_block().await
+    }
+
+    /// Balance of `address` in wei.
+    async fn balance(&self, address: &str) -> Result<u128>;
+
+    /// Current gas price in wei.
+    async fn gas_price(&self) -> Result<u64>;
+
+    /// Timestamps of the blocks `block_numbers`, in order, read in one
+    /// batch of requests.
+    async fn block_timestamps(&self, block_numbers: &[u64]) -> Result<Vec<DateTime<Utc>>>;
+
+    /// Send a transaction carrying `data` to `recipient`.
+    async fn send_transaction(&self, recipient: Recipient, data: Vec<u8>) -> Result<PostedTransaction>;
+
+    /// Build the transaction carrying `data` to `recipient` from the sender
+    /// address, to be signed offline.
+    async fn build_transaction(&self, recipient: Recipient, data: Vec<u8>, purpose: &str) -> Result<UnsignedTransaction>;
+
+    /// Broadcast a transaction signed offline.
+    async fn broadcast_transaction(&self, signed: &SignedTransaction) -> Result<PostedTransaction>;
+
+    /// Transaction `transaction_hash`, if it was mined.
+    async fn transaction(&self, transaction_hash: &str) -> Result<Option<ChainTransaction>>;
+
+    /// Confirmation state of a transaction.
+    async fn transaction_status(&self, transaction_hash: &str) -> Result<TransactionStatus>;
+
+    /// Transactions sent to `address` in blocks `start` to `end`, both
+    /// included, in block order.
+    async fn transactions_to(&self, address: &str, start: u64, end: u64) -> Result<Vec<ChainTransaction>>;
+}
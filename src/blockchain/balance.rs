@@ -0,0 +1,92 @@
+//! # Account Balances
+//!
+//! The accounts of the commit and relayer keys pay for the transactions of
+//! a poll. `BalanceWatch` reports the accounts whose balance falls below
+//! the cost of a number of transactions at the current gas price, so that
+//! they can be topped up before a submission fails in the middle of the
+//! poll. An account is reported again only once it was topped up and fell
+//! low again.
+
+use std::collections::HashSet;
+use std::fmt;
+use serde::{Serialize, Deserialize};
+use super::format_ether;
+
+/// Gas of a transaction the remaining transactions are estimated with: the
+/// cost of a transaction, and of the data of a vote or commitment.
+pub const DEFAULT_GAS_PER_TRANSACTION: u64 = 25_000;
+
+/// Balance of the account of a key.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountBalance {
+    /// Role of the key, `commit` or `relay`.
+    pub role: String,
+    pub address: String,
+    /// Balance in wei.
+    pub balance: u128,
+    /// Gas price in wei when the balance was read.
+    pub gas_price: u64
+}
+
+impl AccountBalance {
+    /// Transactions of `gas_per_transaction` gas the balance pays for at
+    /// the gas price.
+    pub fn remaining_transactions(&self, gas_per_transaction: u64) -> u128 {
+        let cost = u128::from(self.gas_price) * u128::from(gas_per_transaction);
+        self.balance / std::cmp::max(1, cost)
+    }
+}
+
+impl fmt::Display for AccountBalance {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} {} {} ETH (~{} transactions)", self.role, self.address, format_ether(self.balance),
+            self.remaining_transactions(DEFAULT_GAS_PER_TRANSACTION))
+    }
+}
+
+/// Accounts are low once they pay for fewer than `min_transactions`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BalanceAlert {
+    pub min_transactions: u64,
+    #[serde(default = "default_gas_per_transaction")]
+    pub gas_per_transaction: u64
+}
+
+fn default_gas_per_transaction() -> u64 { DEFAULT_GAS_PER_TRANSACTION }
+
+impl BalanceAlert {
+    pub fn new(min_transactions: u64) -> Self {
+        BalanceAlert { min_transactions, gas_per_transaction: DEFAULT_GAS_PER_TRANSACTION }
+    }
+
+    pub fn is_low(&self, balance: &AccountBalance) -> bool {
+        balance.remaining_transactions(self.gas_per_transaction) < u128::from(self.min_transactions)
+    }
+}
+
+/// Reports the accounts that fall low.
+#[derive(Debug, Clone)]
+pub struct BalanceWatch {
+    pub alert: BalanceAlert,
+    low: HashSet<String>
+}
+
+impl BalanceWatch {
+    pub fn new(alert: BalanceAlert) -> Self {
+        BalanceWatch { alert, low: HashSet::new() }
+    }
+
+    /// Balances that fell low since the last check.
+    pub fn check(&mut self, balances: &[AccountBalance]) -> Vec<AccountBalance> {
+        let mut fallen: Vec<AccountBalance> = Vec::new();
+        for balance in balances.iter() {
+            let address = balance.address.to_lowercase();
+            if !self.alert.is_low(balance) {
+                self.low.remove(&address);
+            } else if self.low.insert(address) {
+                fallen.push(balance.clone());
+            }
+        }
+        fallen
+    }
+}
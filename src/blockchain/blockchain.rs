@@ -2,26 +2,210 @@
 //!
 //! Post/read information to/from blockchain
 //! Information posted is a merkle root
+//!
+//! All operations are async, so that they can run in an async server and
+//! independent requests can be sent concurrently; `blocking` wraps them
+//! for the CLI.
 
-use crate::blockchain::merkle::{CryptoSHA3256Hash, new_tree, CryptoHashData, store_tree};
+use crate::blockchain::merkle::{CryptoSHA3256Hash, MerkleRoot, new_tree, CryptoHashData, store_tree};
 use crate::Result;
-use crate::voter_roster::VoterRoster;
-use crate::poll_configuration::PollConfiguration;
-use crate::planes::Plane;
-use crate::debug;
+use crate::cryptography::sign_file;
+use crate::voter_roster::{RestrictedVoterRosterFileRow, VoterRoster};
+use crate::poll_configuration::{BallotQuestion, PollConfiguration, PollUuid};
+use crate::planes::{check_planes, Plane};
+use crate::ballots::BallotSerial;
+use crate::progress::Progress;
+use crate::metrics;
+use super::{check_payload_length, split_payload, reassemble_payload, leaf_index_path, LeafCategory, LeafIndex, AccountBalance, BLOCK_TIMESTAMP_BATCH, FailoverTransport, read_keyring_secret, tally_hash, amendment_hash, BallotAmendment, roster_chunk_hash, check_roster_chunks, RosterChunk, FinalityPolicy, FinalizationReceipt, find_handover, handover_payload, normalize_address, ChainBackend, ExplorerConfig, SignedTransaction, UnsignedTransaction, UNSIGNED_TRANSACTION_FILENAME, NodeError, CommitJournal, CommitPhase, Handover, KeyRole, Recipient, approval_quorum, proposal_manifest, ApprovalCheck, CommitApproval, CommitProposal, COMMIT_PROPOSAL_FILENAME, read_trustee_approval_keys};
+use tracing::{debug, info, info_span, warn, Instrument};
 
-use web3::types::{BlockNumber, Address, TransactionParameters, U256, CallRequest};
+use web3::types::{BlockId, BlockNumber, Address, TransactionId, TransactionParameters, U256, CallRequest, H256};
 use web3::signing::Key;
+use web3::Transport;
 use hex;
 use secp256k1::SecretKey;
 use web3::signing::SecretKeyRef;
+use std::convert::TryInto;
+use std::fmt;
 use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+use futures::{StreamExt, TryStreamExt};
+use async_trait::async_trait;
+use lazy_static::lazy_static;
+use secrecy::{ExposeSecret, SecretString};
+use zeroize::Zeroizing;
 use serde::{Serialize, Deserialize};
+use chrono::{DateTime, TimeZone, Utc};
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+/// Network configuration. Commitments and relayed votes are signed with
+/// distinct keys, and a configuration without keys can only read the
+/// chain, e.g. to audit a poll.
+#[derive(Debug, Clone, Deserialize)]
 struct NetworkConfig {
-    node: String,
-    key: String,
+    /// URLs of the node endpoints, tried in order; read from the keyring if
+    /// left out.
+    #[serde(default, deserialize_with = "deserialize_node")]
+    node: Vec<String>,
+    /// Hex encoded private key of the account posting commitments.
+    #[serde(default)]
+    commit_key: Option<SecretString>,
+    /// Address of the commit key, when the key is kept on an offline
+    /// machine and commitments are signed there.
+    #[serde(default)]
+    commit_address: Option<String>,
+    /// Address the records of the poll are sent to, whatever key posts
+    /// them; the address of the commit key if not given.
+    #[serde(default)]
+    poll_address: Option<String>,
+    /// Hex encoded private key of the account relaying votes.
+    #[serde(default)]
+    relayer_key: Option<SecretString>,
+    /// Single key of former configurations, refused.
+    #[serde(default)]
+    key: Option<SecretString>,
+    /// Address votes are sent to.
+    #[serde(default)]
+    vote_address: Option<String>,
+    /// Blocks read by each request window when scanning for votes.
+    #[serde(default = "default_block_window")]
+    block_window: u64,
+    /// Requests sent to the node at the same time.
+    #[serde(default = "default_max_concurrent_requests")]
+    max_concurrent_requests: usize,
+    /// Requests sent to the node per second; unlimited if not given.
+    #[serde(default)]
+    requests_per_second: Option<u32>,
+    /// Read the keys and node left out from the operating system keyring.
+    #[serde(default)]
+    keyring: bool,
+    /// Block explorer votes are read from instead of the node.
+    #[serde(default)]
+    explorer: Option<ExplorerConfig>,
+    /// When a block is final on the network; every mined block if not given.
+    #[serde(default)]
+    finality: FinalityPolicy,
+    /// File the configuration was loaded from.
+    #[serde(skip)]
+    path: String
+}
+
+/// Node of a network configuration: a single URL, or a list of them.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum NodeEndpoints {
+    Url(String),
+    Urls(Vec<String>)
+}
+
+fn deserialize_node<'de, D: serde::Deserializer<'de>>(deserializer: D) -> std::result::Result<Vec<String>, D::Error> {
+    let urls = match NodeEndpoints::deserialize(deserializer)? {
+        NodeEndpoints::Url(url) => vec![url],
+        NodeEndpoints::Urls(urls) => urls
+    };
+    Ok(urls.into_iter().filter(|url| !url.trim().is_empty()).collect())
+}
+
+fn default_block_window() -> u64 { 100 }
+
+fn default_max_concurrent_requests() -> usize { 4 }
+
+/// Transaction sent to the blockchain by `post`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PostedTransaction {
+    pub transaction_hash: String,
+    pub address: String,
+    /// Latest block number observed when the transaction was sent.
+    pub submitted_at_block: u64
+}
+
+/// Receipt for a commitment posted to the blockchain.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitReceipt {
+    pub poll_identifier: String,
+    pub root: String,
+    pub leaves: usize,
+    pub transaction_hash: String,
+    pub address: String,
+    pub submitted_at_block: u64,
+    pub posted_at: DateTime<Utc>,
+    /// Vote address when the root was posted, from which readers follow
+    /// the handovers of the vote address.
+    #[serde(default)]
+    pub vote_address: Option<String>,
+    /// Last block in which votes are accepted; votes are accepted until the
+    /// poll is tallied if not given.
+    #[serde(default)]
+    pub end_block: Option<u64>,
+    /// Hex encoded hash of the ballot question, posted after the root.
+    #[serde(default)]
+    pub question_hash: Option<String>,
+    /// UUID of the poll, posted after the question hash.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poll_uuid: Option<PollUuid>
+}
+
+impl CommitReceipt {
+    /// Poll identifier of the vote payloads of the poll: its UUID, or its
+    /// poll identifier for polls committed without one.
+    pub fn payload_identifier(&self) -> String {
+        match &self.poll_uuid {
+            Some(poll_uuid) => poll_uuid.to_string(),
+            None => self.poll_identifier.clone()
+        }
+    }
+}
+
+impl fmt::Display for CommitReceipt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Poll:        {}", self.poll_identifier)?;
+        writeln!(f, "Root:        {} ({} leaves)", self.root, self.leaves)?;
+        writeln!(f, "Transaction: {}", self.transaction_hash)?;
+        writeln!(f, "Address:     {}", self.address)?;
+        write!(f, "Block:       {}", self.submitted_at_block)?;
+        if let Some(question_hash) = &self.question_hash {
+            write!(f, "\nQuestion:    {}", question_hash)?;
+        }
+        if let Some(poll_uuid) = &self.poll_uuid {
+            write!(f, "\nPoll UUID:   {}", poll_uuid)?;
+        }
+        if let Some(end_block) = self.end_block {
+            write!(f, "\nEnd block:   {}", end_block)?;
+        }
+        Ok(())
+    }
+}
+
+/// Confirmation state of a transaction on the blockchain.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TransactionStatus {
+    pub transaction_hash: String,
+    /// Block the transaction was mined in, if it was mined.
+    pub block_number: Option<u64>,
+    pub confirmations: u64,
+    /// Whether the block of the transaction is final by the finality policy
+    /// of the network.
+    #[serde(default)]
+    pub finalized: bool,
+    pub succeeded: Option<bool>,
+    pub gas_used: Option<u64>,
+    /// Gas price paid in wei, once the transaction is mined.
+    #[serde(default)]
+    pub gas_price: Option<u64>
+}
+
+/// Transaction read back from the blockchain.
+#[derive(Debug, Clone)]
+pub struct ChainTransaction {
+    pub transaction_hash: String,
+    pub from: String,
+    pub block_number: u64,
+    pub timestamp: DateTime<Utc>,
+    /// Gas price in wei.
+    pub gas_price: u64,
+    pub input: Vec<u8>
 }
 
 // returns block #
@@ -30,101 +214,1024 @@ pub fn retrieve_from_chain(value: Vec<u8>) -> u64 {
     0
 }
 
-// Load blockchain network configurations
-fn load_xxn() -> Result<NetworkConfig>{
-    let config = "examples/xxn_config.yaml";
-    let config = File::open(config)?;
-    let config: NetworkConfig  = serde_yaml::from_reader(config).expect("Error loading XXN config file");
+/// Networks with a configuration file in `examples/<preset>_config.yaml`.
+pub const NETWORK_PRESETS: [&str; 2] = ["xxn", "local"];
 
-    Ok(config)
+lazy_static! {
+    static ref BACKEND: RwLock<Option<Arc<dyn ChainBackend>>> = RwLock::new(None);
+    static ref AUDIT_VOTE_ADDRESS: RwLock<Option<String>> = RwLock::new(None);
 }
 
-pub fn post(data: CryptoSHA3256Hash) -> Result<()> {
-    // Load configuration file
-    let config = load_xxn()?;
-
-    // Get private key from config
-    let key = SecretKey::from_slice(&hex::decode(config.key)?)?;
-    let key = SecretKeyRef::new(&key);
-    
-    // Get public address of private key
-    let pub_addr: Address = key.address();
-    let uri = config.node;
-
-    // Placeholder request to be used to estimate gas
-    let req = CallRequest {
-        from: None,
-        to: None,
-        gas: None,
-        gas_price: None,
-        value: None,
-        data: None
-    };
+/// Run requests to the blockchain node, recording them in the metrics.
+async fn rpc<T, E>(requests: impl std::future::Future<Output = std::result::Result<T, E>>) -> std::result::Result<T, E> {
+    let started = std::time::Instant::now();
+    let result = requests.await;
+    metrics::record_rpc(started.elapsed(), result.is_ok());
+    result
+}
+
+/// Paces the requests sent to the node.
+struct RateLimit {
+    interval: Option<Duration>,
+    next: Mutex<Instant>
+}
+
+impl RateLimit {
+    fn new(requests_per_second: Option<u32>) -> Self {
+        RateLimit {
+            interval: requests_per_second.filter(|&rate| 0 < rate).map(|rate| Duration::from_secs(1) / rate),
+            next: Mutex::new(Instant::now())
+        }
+    }
+
+    /// Wait for the next free slot.
+    async fn wait(&self) {
+        let interval = match self.interval {
+            Some(interval) => interval,
+            None => return
+        };
+        let slot = {
+            let mut next = self.next.lock().unwrap();
+            let slot = std::cmp::max(*next, Instant::now());
+            *next = slot + interval;
+            slot
+        };
+        let now = Instant::now();
+        if now < slot {
+            futures_timer::Delay::new(slot - now).await;
+        }
+    }
+}
+
+/// Backend of the node of the network configuration.
+pub struct Web3Backend {
+    web3: web3::Web3<FailoverTransport>,
+    role: KeyRole,
+    /// Key of the role; none for auditing.
+    key: Option<SecretKey>,
+    /// Address of the key of the role, if it is kept offline.
+    offline_sender: Option<Address>,
+    /// Only needed to post commitments if it is handed over.
+    vote_address: Option<Address>,
+    /// Address the records of the poll are sent to, if configured.
+    poll_address: Option<Address>,
+    rate_limit: RateLimit,
+    max_concurrent_requests: usize,
+    block_window: u64,
+    finality: FinalityPolicy,
+    /// File of the network configuration, for errors.
+    config_path: String,
+    /// Explorer votes are read from, if configured.
+    #[cfg(feature = "explorer")]
+    explorer: Option<super::ExplorerClient>
+}
+
+impl Web3Backend {
+    /// Connect to the node of the network `network` in `role`, decoding
+    /// the key of that role only.
+    pub fn connect(network: &str, role: KeyRole) -> Result<Self> {
+        let config = load_xxn(network)?;
+        if config.node.is_empty() {
+            return Err(format!("node missing from {}", config.path).into());
+        }
+        let offline_sender = match (role, &config.commit_address) {
+            (KeyRole::Commit, Some(address)) => Some(parse_address(address)?),
+            _ => None
+        };
+        let poll_address = match (role, &config.poll_address) {
+            (KeyRole::Commit, Some(address)) => Some(parse_address(address)?),
+            _ => None
+        };
+        let key = match role {
+            KeyRole::Commit if offline_sender.is_some() => None,
+            KeyRole::Commit => Some(secret_key(&config, &config.commit_key, "commit_key")?),
+            KeyRole::Relay => Some(secret_key(&config, &config.relayer_key, "relayer_key")?),
+            KeyRole::Audit => None
+        };
+        let vote_address = match (role, audit_vote_address()) {
+            (KeyRole::Audit, Some(address)) => Some(parse_address(&address)?),
+            (KeyRole::Commit | KeyRole::Audit, None) if config.vote_address.is_none() => None,
+            _ => Some(vote_address(&config)?)
+        };
+        #[cfg(feature = "explorer")]
+        let explorer = match &config.explorer {
+            Some(explorer) => Some(super::ExplorerClient::new(super::explorer(explorer)?)),
+            None => None
+        };
+        #[cfg(not(feature = "explorer"))]
+        if let Some(explorer) = &config.explorer {
+            return Err(format!("Reading votes from the {} explorer of {} needs the explorer feature.",
+                explorer.kind, config.path).into());
+        }
+        let transport = FailoverTransport::new(&config.node)?;
+        Ok(Web3Backend {
+            web3: web3::Web3::new(transport),
+            role,
+            key,
+            offline_sender,
+            vote_address,
+            poll_address,
+            rate_limit: RateLimit::new(config.requests_per_second),
+            max_concurrent_requests: std::cmp::max(1, config.max_concurrent_requests),
+            block_window: std::cmp::max(1, config.block_window),
+            finality: config.finality,
+            config_path: config.path,
+            #[cfg(feature = "explorer")]
+            explorer
+        })
+    }
+
+    fn vote_address(&self) -> Result<Address> {
+        Ok(self.vote_address.ok_or_else(|| format!("vote_address missing from {}", self.config_path))?)
+    }
+
+    async fn rpc<T>(&self, requests: impl std::future::Future<Output = web3::Result<T>>) -> std::result::Result<T, NodeError> {
+        self.rate_limit.wait().await;
+        rpc(requests).await.map_err(NodeError::from)
+    }
+
+    /// Label of the node endpoint that served the last request.
+    fn provider(&self) -> String {
+        self.web3.transport().serving()
+    }
+}
+
+#[async_trait]
+impl ChainBackend for Web3Backend {
+    fn block_window(&self) -> u64 { self.block_window }
+
+    fn max_concurrent_requests(&self) -> usize { self.max_concurrent_requests }
+
+    fn vote_address(&self) -> Option<String> {
+        self.vote_address.map(|address| format!("{:?}", address))
+    }
+
+    fn sender_address(&self) -> Option<String> {
+        self.key.as_ref().map(|key| SecretKeyRef::new(key).address())
+            .or(self.offline_sender)
+            .map(|address| format!("{:?}", address))
+    }
+
+    fn signs_offline(&self) -> bool {
+        self.offline_sender.is_some()
+    }
 
-    // Start web3 class
-    let transport = web3::transports::Http::new(&uri).unwrap();
-    let web3 = web3::Web3::new(transport);
-    
-    let send = async {
-        // Get last block and estimate gas
-        let block_number = web3.eth().block_number().await.expect("Error getting last block number");
-        let gas = web3.eth().estimate_gas(req, Some(BlockNumber::Number(block_number))).await.expect("Error getting gas value");
-
-        // Build transaction with data to post
-        let params = TransactionParameters {
-            nonce: None,
-            to: Some(pub_addr), // Send to own address
+    async fn latest_block(&self) -> Result<u64> {
+        Ok(self.rpc(self.web3.eth().block_number()).await?.as_u64())
+    }
+
+    async fn final_block(&self) -> Result<u64> {
+        let tag = match self.finality.block_tag() {
+            Some(tag) => tag,
+            None => return Ok(self.finality.final_block(self.latest_block().await?).unwrap_or_default())
+        };
+        let block = self.rpc(self.web3.transport().execute("eth_getBlockByNumber", vec![serde_json::json!(tag), serde_json::json!(false)])).await?;
+        let number = block.get("number").and_then(|number| number.as_str())
+            .ok_or_else(|| format!("The node reports no {} block: set the finality of {} to a number of confirmations.", tag, self.config_path))?;
+        Ok(u64::from_str_radix(number.trim_start_matches("0x"), 16)?)
+    }
+
+    async fn balance(&self, address: &str) -> Result<u128> {
+        let address = Address::from_slice(&hex::decode(address.trim_start_matches("0x"))?);
+        Ok(self.rpc(self.web3.eth().balance(address, None)).await?.low_u128())
+    }
+
+    async fn gas_price(&self) -> Result<u64> {
+        Ok(self.rpc(self.web3.eth().gas_price()).await?.low_u64())
+    }
+
+    async fn block_timestamps(&self, block_numbers: &[u64]) -> Result<Vec<DateTime<Utc>>> {
+        let batch = web3::Web3::new(web3::transports::Batch::new(self.web3.transport().clone()));
+        let blocks: Vec<_> = block_numbers.iter()
+            .map(|&number| batch.eth().block(BlockId::Number(BlockNumber::Number(number.into()))))
+            .collect();
+        self.rpc(batch.transport().submit_batch()).await?;
+        let mut timestamps: Vec<DateTime<Utc>> = Vec::with_capacity(block_numbers.len());
+        for (number, block) in block_numbers.iter().zip(blocks) {
+            let block = block.await?.ok_or_else(|| format!("Block {} not found.", number))?;
+            timestamps.push(Utc.timestamp(block.timestamp.as_u64() as i64, 0));
+        }
+        Ok(timestamps)
+    }
+
+    // Sign and send a transaction carrying `data`, with the key of the
+    // role of the recipient only
+    async fn send_transaction(&self, recipient: Recipient, data: Vec<u8>) -> Result<PostedTransaction> {
+        if recipient.role() != self.role {
+            return Err(format!("Network configuration loaded in the {} role cannot sign {} transactions.",
+                self.role, recipient.role()).into());
+        }
+        check_payload_length(&data)?;
+        let key = SecretKeyRef::new(self.key.as_ref().ok_or("No key is loaded to sign transactions.")?);
+
+        // Get public address of private key
+        let pub_addr: Address = key.address();
+        let to = match recipient {
+            Recipient::Own => pub_addr,
+            Recipient::Poll => self.poll_address.unwrap_or(pub_addr),
+            Recipient::Votes => self.vote_address()?
+        };
+        debug!(address = ?pub_addr, to = ?to, "Posting data to blockchain");
+
+        // Placeholder request to be used to estimate gas
+        let req = CallRequest {
+            from: None,
+            to: None,
+            gas: None,
+            gas_price: None,
+            value: None,
+            data: None
+        };
+
+        let web3 = &self.web3;
+        let send = async {
+            // Get last block and estimate gas
+            let block_number = web3.eth().block_number().await?;
+            let gas = web3.eth().estimate_gas(req, Some(BlockNumber::Number(block_number))).await?;
+
+            // Build transaction with data to post
+            let params = TransactionParameters {
+                nonce: None,
+                to: Some(to),
+                gas_price: None,
+                chain_id: None,
+                data: data.into(), // Data to be posted
+                value: U256::zero(),
+                gas
+            };
+
+            // Sign transaction before posting
+            let signed = web3.accounts().sign_transaction(params, key).await?;
+            let transaction = signed.raw_transaction;
+
+            // Send signed transaction
+            let sent = web3.eth().send_raw_transaction(transaction).await?;
+            info!(tx_hash = ?sent, block = %block_number, provider = %self.provider(), "Transaction sent");
+
+            Ok::<_, web3::Error>(PostedTransaction {
+                transaction_hash: format!("{:?}", sent),
+                address: format!("{:?}", pub_addr),
+                submitted_at_block: block_number.as_u64()
+            })
+        };
+
+        Ok(self.rpc(send).await?)
+    }
+
+    async fn build_transaction(&self, recipient: Recipient, data: Vec<u8>, purpose: &str) -> Result<UnsignedTransaction> {
+        if recipient.role() != self.role {
+            return Err(format!("Network configuration loaded in the {} role cannot build {} transactions.",
+                self.role, recipient.role()).into());
+        }
+        check_payload_length(&data)?;
+        let from = self.offline_sender.ok_or_else(|| format!("commit_address missing from {}", self.config_path))?;
+        let to = match recipient {
+            Recipient::Own => from,
+            Recipient::Poll => self.poll_address.unwrap_or(from),
+            Recipient::Votes => self.vote_address()?
+        };
+        let eth = self.web3.eth();
+        let call = CallRequest {
+            from: Some(from),
+            to: Some(to),
+            gas: None,
             gas_price: None,
-            chain_id: None,
-            data: data.into(), // Data to be posted
-            value: U256::zero(),
-            gas: gas
+            value: None,
+            data: Some(data.clone().into())
+        };
+        let build = async {
+            let nonce = eth.transaction_count(from, Some(BlockNumber::Pending)).await?;
+            let gas = eth.estimate_gas(call, None).await?;
+            let gas_price = eth.gas_price().await?;
+            let chain_id = eth.chain_id().await?;
+            Ok::<_, web3::Error>((nonce, gas, gas_price, chain_id))
+        };
+        let (nonce, gas, gas_price, chain_id) = self.rpc(build).await?;
+        debug!(from = ?from, to = ?to, nonce = %nonce, provider = %self.provider(), "Transaction built for offline signing");
+        Ok(UnsignedTransaction {
+            purpose: purpose.to_owned(),
+            chain_id: chain_id.as_u64(),
+            from: format!("{:?}", from),
+            to: format!("{:?}", to),
+            nonce: nonce.as_u64(),
+            gas: gas.as_u64(),
+            gas_price: gas_price.low_u64(),
+            data: hex::encode(&data),
+            built_at: Utc::now()
+        })
+    }
+
+    async fn broadcast_transaction(&self, signed: &SignedTransaction) -> Result<PostedTransaction> {
+        let raw = signed.raw()?;
+        let broadcast = async {
+            let block_number = self.web3.eth().block_number().await?;
+            let sent = self.web3.eth().send_raw_transaction(raw.into()).await?;
+            Ok::<_, web3::Error>((block_number, sent))
+        };
+        let (block_number, sent) = self.rpc(broadcast).await?;
+        info!(tx_hash = ?sent, block = %block_number, provider = %self.provider(), "Transaction signed offline sent");
+        Ok(PostedTransaction {
+            transaction_hash: format!("{:?}", sent),
+            address: signed.transaction.from.clone(),
+            submitted_at_block: block_number.as_u64()
+        })
+    }
+
+    async fn transaction(&self, transaction_hash: &str) -> Result<Option<ChainTransaction>> {
+        let hash = H256::from_slice(&hex::decode(transaction_hash.trim_start_matches("0x"))?);
+        let transaction = match self.rpc(self.web3.eth().transaction(TransactionId::Hash(hash))).await? {
+            Some(transaction) => transaction,
+            None => return Ok(None)
+        };
+        let (block_hash, block_number) = match (transaction.block_hash, transaction.block_number) {
+            (Some(block_hash), Some(block_number)) => (block_hash, block_number.as_u64()),
+            _ => return Ok(None)
         };
+        let timestamp = match self.rpc(self.web3.eth().block(BlockId::Hash(block_hash))).await? {
+            Some(block) => Utc.timestamp(block.timestamp.as_u64() as i64, 0),
+            None => return Ok(None)
+        };
+        debug!(tx_hash = %transaction_hash, block = block_number, provider = %self.provider(), "Transaction read");
+        Ok(Some(ChainTransaction {
+            transaction_hash: format!("{:?}", transaction.hash),
+            from: format!("{:?}", transaction.from),
+            block_number,
+            timestamp,
+            gas_price: transaction.gas_price.low_u64(),
+            input: transaction.input.0
+        }))
+    }
+
+    async fn transaction_status(&self, transaction_hash: &str) -> Result<TransactionStatus> {
+        let hash = H256::from_slice(&hex::decode(transaction_hash.trim_start_matches("0x"))?);
+
+        let lookup = async {
+            let receipt = self.web3.eth().transaction_receipt(hash).await?;
+            let latest = self.web3.eth().block_number().await?.as_u64();
+            let gas_price = match receipt {
+                Some(_) => self.web3.eth().transaction(TransactionId::Hash(hash)).await?
+                    .map(|transaction| transaction.gas_price.low_u64()),
+                None => None
+            };
+            Ok::<_, web3::Error>((receipt, latest, gas_price))
+        };
+        let (receipt, latest, gas_price) = self.rpc(lookup).await?;
+        debug!(tx_hash = %transaction_hash, latest_block = latest, found = receipt.is_some(), provider = %self.provider(), "Transaction looked up");
+
+        let block_number = receipt.as_ref()
+            .and_then(|receipt| receipt.block_number)
+            .map(|number| number.as_u64());
+        let final_block = match (block_number, self.finality.final_block(latest)) {
+            (None, _) => None,
+            (Some(_), Some(final_block)) => Some(final_block),
+            (Some(_), None) => Some(self.final_block().await?)
+        };
+        Ok(TransactionStatus {
+            transaction_hash: transaction_hash.to_owned(),
+            block_number,
+            confirmations: block_number.map(|number| latest.saturating_sub(number) + 1).unwrap_or(0),
+            finalized: matches!((block_number, final_block), (Some(number), Some(final_block)) if number <= final_block),
+            succeeded: receipt.as_ref()
+                .and_then(|receipt| receipt.status)
+                .map(|status| status.as_u64() == 1),
+            gas_used: receipt.as_ref()
+                .and_then(|receipt| receipt.gas_used)
+                .map(|gas| gas.as_u64()),
+            gas_price
+        })
+    }
+
+    async fn transactions_to(&self, address: &str, start: u64, end: u64) -> Result<Vec<ChainTransaction>> {
+        #[cfg(feature = "explorer")]
+        if let Some(explorer) = &self.explorer {
+            return super::explorer_transactions_to(explorer.explorer.as_ref(), address, start, end, |url| async move {
+                self.rate_limit.wait().await;
+                rpc(explorer.get(url)).await
+            }).await;
+        }
+
+        let address = Address::from_slice(&hex::decode(address.trim_start_matches("0x"))?);
+        let mut transactions: Vec<ChainTransaction> = Vec::new();
+        for number in start..=end {
+            let block_id = BlockId::Number(BlockNumber::Number(number.into()));
+            if let Some(block) = self.rpc(self.web3.eth().block_with_txs(block_id)).await? {
+                let timestamp = Utc.timestamp(block.timestamp.as_u64() as i64, 0);
+                block.transactions.into_iter()
+                    .filter(|transaction| transaction.to == Some(address))
+                    .for_each(|transaction| transactions.push(ChainTransaction {
+                        transaction_hash: format!("{:?}", transaction.hash),
+                        from: format!("{:?}", transaction.from),
+                        block_number: number,
+                        timestamp,
+                        gas_price: transaction.gas_price.low_u64(),
+                        input: transaction.input.0
+                    }));
+            }
+        }
+        debug!(address = ?address, start, end, transactions = transactions.len(), provider = %self.provider(), "Blocks read");
+        Ok(transactions)
+    }
+}
+
+/// Split blocks `from_block` to `to_block`, both included, into windows of
+/// at most `size` blocks, in order.
+pub fn block_windows(from_block: u64, to_block: u64, size: u64) -> Vec<(u64, u64)> {
+    let size = std::cmp::max(1, size);
+    let mut windows: Vec<(u64, u64)> = Vec::new();
+    let mut start = from_block;
+    while start <= to_block {
+        let end = std::cmp::min(to_block, start.saturating_add(size - 1));
+        windows.push((start, end));
+        if end == u64::MAX { break; }
+        start = end + 1;
+    }
+    windows
+}
+
+/// Read votes from `address` in the audit role, whatever the vote address
+/// of the network configuration, so that auditors need no configured
+/// address. Commit receipts recording the vote address take precedence.
+pub fn set_audit_vote_address(address: Option<&str>) -> Result<()> {
+    let address = match address {
+        Some(address) => Some(normalize_address(address).ok_or_else(|| format!("Not an address: {}", address))?),
+        None => None
+    };
+    *AUDIT_VOTE_ADDRESS.write().unwrap() = address;
+    Ok(())
+}
+
+fn audit_vote_address() -> Option<String> {
+    AUDIT_VOTE_ADDRESS.read().unwrap().clone()
+}
+
+/// Configuration file of the network `network`, one of `NETWORK_PRESETS`.
+pub fn network_config_path(network: &str) -> String {
+    format!("examples/{}_config.yaml", network)
+}
+
+// Load blockchain network configurations
+// The file is read into a buffer scrubbed once the configuration is parsed.
+fn load_xxn(network: &str) -> Result<NetworkConfig>{
+    let path = network_config_path(network);
+    let mut contents = Zeroizing::new(String::new());
+    File::open(&path)?.read_to_string(&mut contents)?;
+    let mut config: NetworkConfig  = serde_yaml::from_str(&contents).expect("Error loading XXN config file");
+    config.path = path;
+
+    if config.keyring {
+        read_keyring(network, &mut config)?;
+    }
+
+    if config.key.is_some() {
+        return Err("The single key of the network configuration is no longer used: configure commit_key and relayer_key instead.".into());
+    }
+    if config.commit_key.is_some() && config.commit_address.is_some() {
+        return Err("The network configuration sets both commit_key and commit_address: keep the commit key either here or offline.".into());
+    }
+    if let (Some(commit_key), Some(relayer_key)) = (&config.commit_key, &config.relayer_key) {
+        if commit_key.expose_secret().trim_start_matches("0x") == relayer_key.expose_secret().trim_start_matches("0x") {
+            return Err("commit_key and relayer_key of the network configuration must be distinct keys.".into());
+        }
+    }
+    Ok(config)
+}
+
+// Values of the network configuration left out and kept in the keyring
+fn read_keyring(network: &str, config: &mut NetworkConfig) -> Result<()> {
+    if config.commit_key.is_none() && config.commit_address.is_none() {
+        config.commit_key = read_keyring_secret(network, "commit_key")?;
+    }
+    if config.relayer_key.is_none() {
+        config.relayer_key = read_keyring_secret(network, "relayer_key")?;
+    }
+    if config.node.is_empty() {
+        if let Some(node) = read_keyring_secret(network, "node")? {
+            config.node = vec![node.expose_secret().clone()];
+        }
+    }
+    Ok(())
+}
 
-        // Sign transaction before posting
-        let signed = web3.accounts().sign_transaction(params, key).await.expect("Error signing transaction");
-        let transaction = signed.raw_transaction;
+// Private key of a role, decoded in a scrubbed buffer
+fn secret_key(config: &NetworkConfig, key: &Option<SecretString>, name: &str) -> Result<SecretKey> {
+    let key = key.as_ref()
+        .ok_or_else(|| format!("{} missing from {}", name, config.path))?;
+    let key = Zeroizing::new(hex::decode(key.expose_secret().trim_start_matches("0x"))?);
+    Ok(SecretKey::from_slice(&key)?)
+}
+
+// Address votes are sent to
+fn vote_address(config: &NetworkConfig) -> Result<Address> {
+    let address = config.vote_address.as_ref()
+        .ok_or_else(|| format!("vote_address missing from {}", config.path))?;
+    parse_address(address)
+}
+
+fn parse_address(address: &str) -> Result<Address> {
+    let bytes = hex::decode(address.trim_start_matches("0x"))?;
+    if bytes.len() != 20 {
+        return Err(format!("Not an address: {}", address).into());
+    }
+    Ok(Address::from_slice(&bytes))
+}
+
+/// Sign `unsigned` with the commit key of the configuration of `network`,
+/// on the offline machine: no node is needed.
+pub fn sign_offline(network: &str, unsigned: &UnsignedTransaction) -> Result<SignedTransaction> {
+    let config = load_xxn(network)?;
+    let key = secret_key(&config, &config.commit_key, "commit_key")?;
+    unsigned.sign(&key)
+}
+
+/// Use `backend` for all blockchain operations instead of the node of the
+/// network they are given.
+pub fn set_backend(backend: Arc<dyn ChainBackend>) {
+    *BACKEND.write().unwrap() = Some(backend);
+}
+
+fn backend(network: &str, role: KeyRole) -> Result<Arc<dyn ChainBackend>> {
+    match BACKEND.read().unwrap().clone() {
+        Some(backend) => Ok(backend),
+        None => Ok(Arc::new(Web3Backend::connect(network, role)?))
+    }
+}
+
+pub async fn post(network: &str, data: CryptoSHA3256Hash) -> Result<PostedTransaction> {
+    let span = info_span!("post", phase = "post", root = %hex::encode(data));
+
+    // Send to the poll address
+    let backend = backend(network, KeyRole::Commit)?;
+    backend.send_transaction(Recipient::Poll, data.to_vec()).instrument(span).await
+}
+
+/// Input data of a commitment transaction: the Merkle root, followed by
+/// the hash of the ballot question and, for polls with one, the UUID of
+/// the poll.
+pub fn commitment_payload(root: &CryptoSHA3256Hash, question_hash: &CryptoSHA3256Hash, poll_uuid: Option<&PollUuid>) -> Vec<u8> {
+    let poll_uuid: &[u8] = poll_uuid.map_or(&[], |poll_uuid| &poll_uuid.as_bytes()[..]);
+    [&root[..], &question_hash[..], poll_uuid].concat()
+}
+
+/// Contents of the input data of a commitment transaction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CommitmentPayload {
+    pub root: CryptoSHA3256Hash,
+    /// Hash of the ballot question; commitments posted before the question
+    /// was committed only carry the root.
+    pub question_hash: Option<CryptoSHA3256Hash>,
+    /// UUID of the poll, for polls created with one.
+    pub poll_uuid: Option<PollUuid>
+}
+
+/// Contents of the input data of a commitment transaction, if it is one.
+pub fn read_commitment_payload(data: &[u8]) -> Option<CommitmentPayload> {
+    let hash = |bytes: &[u8]| bytes.try_into().ok();
+    let poll_uuid = |bytes: &[u8]| bytes.try_into().ok().map(PollUuid::from_bytes);
+    match data.len() {
+        32 => Some(CommitmentPayload { root: hash(data)?, question_hash: None, poll_uuid: None }),
+        64 => Some(CommitmentPayload { root: hash(&data[..32])?, question_hash: Some(hash(&data[32..])?), poll_uuid: None }),
+        80 => Some(CommitmentPayload { root: hash(&data[..32])?, question_hash: Some(hash(&data[32..64])?), poll_uuid: Some(poll_uuid(&data[64..])?) }),
+        _ => None
+    }
+}
+
+/// Post the Merkle root of the poll with the hash of its ballot question
+/// and its UUID.
+pub async fn post_commitment(network: &str, root: CryptoSHA3256Hash, question_hash: CryptoSHA3256Hash, poll_uuid: Option<PollUuid>) -> Result<PostedTransaction> {
+    let span = info_span!("post", phase = "post", root = %hex::encode(root), question = %hex::encode(question_hash));
+    let backend = backend(network, KeyRole::Commit)?;
+    backend.send_transaction(Recipient::Poll, commitment_payload(&root, &question_hash, poll_uuid.as_ref())).instrument(span).await
+}
+
+/// Relay a vote payload to the vote address.
+pub async fn post_vote(network: &str, payload: Vec<u8>) -> Result<PostedTransaction> {
+    let span = info_span!("post_vote", phase = "vote");
+    let backend = backend(network, KeyRole::Relay)?;
+    backend.send_transaction(Recipient::Votes, payload).instrument(span).await
+}
+
+/// Post `payload` to `recipient`, in parts if it does not fit in a
+/// transaction. Parts are posted in order, each once the previous one is
+/// sent; the transactions of the parts are returned in that order.
+pub async fn post_payload(network: &str, recipient: Recipient, payload: &[u8]) -> Result<Vec<PostedTransaction>> {
+    let parts = split_payload(payload)?;
+    let span = info_span!("post_payload", phase = "post", bytes = payload.len(), parts = parts.len());
+    let backend = backend(network, recipient.role())?;
+    let mut posted = Vec::with_capacity(parts.len());
+    for part in parts {
+        posted.push(backend.send_transaction(recipient, part).instrument(span.clone()).await?);
+    }
+    Ok(posted)
+}
+
+/// Payload posted by `post_payload` in the transactions
+/// `transaction_hashes`, reassembled from its parts as read back from the
+/// chain.
+pub async fn read_posted_payload(network: &str, transaction_hashes: &[String]) -> Result<Vec<u8>> {
+    let backend = backend(network, KeyRole::Audit)?;
+    let mut transactions = Vec::with_capacity(transaction_hashes.len());
+    for transaction_hash in transaction_hashes {
+        transactions.push(backend.transaction(transaction_hash).await?
+            .ok_or_else(|| format!("Transaction {} is not on chain.", transaction_hash))?);
+    }
+    reassemble_payload(&transactions)
+}
+
+/// Hand the vote address over to `new_address`, posting the handover record
+/// with the commit key. The commit key must hold the current vote address,
+/// `vote_address`, for readers to follow the record.
+pub async fn post_handover(network: &str, vote_address: &str, new_address: &str) -> Result<PostedTransaction> {
+    let span = info_span!("post_handover", phase = "handover", to = %new_address);
+    let payload = handover_payload(new_address).ok_or_else(|| format!("Not an address: {}", new_address))?;
+    let backend = backend(network, KeyRole::Commit)?;
+    let sender = backend.sender_address().ok_or("No key is loaded to sign the handover record.")?;
+    if normalize_address(&sender) != normalize_address(vote_address) {
+        return Err(format!("The commit key holds {}, not the vote address {}: only the key of the vote address can hand it over.",
+            sender, vote_address).into());
+    }
+    backend.send_transaction(Recipient::Own, payload).instrument(span).await
+}
+
+/// Post the hash of the signed tally report as the closing commitment of
+/// the poll, with the commit key.
+pub async fn post_finalization(network: &str, poll_identifier: &str, tally: &[u8]) -> Result<FinalizationReceipt> {
+    let hash = tally_hash(tally);
+    let span = info_span!("post_finalization", phase = "finalize", tally_hash = %hex::encode(hash));
+    let backend = backend(network, KeyRole::Commit)?;
+    let posted = backend.send_transaction(Recipient::Poll, hash.to_vec()).instrument(span).await?;
+    Ok(FinalizationReceipt {
+        poll_identifier: poll_identifier.to_owned(),
+        tally_hash: hex::encode(hash),
+        transaction_hash: posted.transaction_hash,
+        address: posted.address,
+        submitted_at_block: posted.submitted_at_block,
+        posted_at: Utc::now()
+    })
+}
+
+/// Post the hash of the replacement of a spoiled ballot as an amendment of
+/// the poll, with the commit key. `ballot_commitment` is the commitment of
+/// the replacement ballot, when sealed in an envelope.
+pub async fn post_amendment(network: &str, poll_identifier: &str, spoiled_serial: BallotSerial, replacement_serial: BallotSerial, reason: Option<&str>, ballot_commitment: Option<&str>) -> Result<BallotAmendment> {
+    let hash = amendment_hash(poll_identifier, spoiled_serial, replacement_serial, reason, ballot_commitment);
+    let span = info_span!("post_amendment", phase = "amend", spoiled_serial, replacement_serial);
+    let backend = backend(network, KeyRole::Commit)?;
+    let posted = backend.send_transaction(Recipient::Poll, hash.to_vec()).instrument(span).await?;
+    Ok(BallotAmendment {
+        spoiled_serial,
+        replacement_serial,
+        reason: reason.map(str::to_owned),
+        ballot_commitment: ballot_commitment.map(str::to_owned),
+        amendment_hash: hex::encode(hash),
+        transaction_hash: posted.transaction_hash,
+        address: posted.address,
+        submitted_at_block: posted.submitted_at_block,
+        posted_at: Utc::now()
+    })
+}
+
+/// Post the hash of a chunk of the roster, the Merkle root `root` of the
+/// roster leaves of its voters, with the commit key.
+pub async fn post_roster_chunk(network: &str, poll_identifier: &str, index: usize, first_position: usize, voters: usize, root: &CryptoSHA3256Hash) -> Result<RosterChunk> {
+    let hash = roster_chunk_hash(poll_identifier, index, first_position, voters, root);
+    let span = info_span!("post_roster_chunk", phase = "roster", index, voters);
+    let backend = backend(network, KeyRole::Commit)?;
+    let posted = backend.send_transaction(Recipient::Poll, hash.to_vec()).instrument(span).await?;
+    Ok(RosterChunk {
+        index,
+        first_position,
+        voters,
+        root: hex::encode(root),
+        chunk_hash: hex::encode(hash),
+        transaction_hash: posted.transaction_hash,
+        address: posted.address,
+        submitted_at_block: posted.submitted_at_block,
+        posted_at: Utc::now()
+    })
+}
+
+/// Transaction `transaction_hash` sent by `address` since `from_block`, if
+/// it was mined. The transaction is looked up by its hash, so that records
+/// sent to the poll address are found whatever address they were sent to.
+pub async fn posted_transaction(network: &str, address: &str, transaction_hash: &str, from_block: u64) -> Result<Option<ChainTransaction>> {
+    let backend = backend(network, KeyRole::Audit)?;
+    Ok(backend.transaction(transaction_hash).await?
+        .filter(|transaction| from_block <= transaction.block_number)
+        .filter(|transaction| normalize_address(&transaction.from) == normalize_address(address)))
+}
+
+/// Commitment transaction of a commit receipt, as read back from the chain.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitmentCheck {
+    pub transaction_hash: String,
+    /// Block of the transaction, if it was found mined.
+    pub block_number: Option<u64>,
+    /// Whether the transaction was sent by the address of the receipt.
+    pub sender_matches: bool,
+    /// Whether the transaction carries the root of the receipt.
+    pub root_matches: bool,
+    /// Whether the transaction carries the question hash of the receipt, or
+    /// none for receipts without one.
+    pub question_matches: bool,
+    /// Whether the transaction carries the poll UUID of the receipt, or none
+    /// for receipts without one.
+    pub poll_matches: bool
+}
+
+impl CommitmentCheck {
+    pub fn valid(&self) -> bool {
+        self.block_number.is_some() && self.sender_matches && self.root_matches && self.question_matches && self.poll_matches
+    }
+}
+
+impl fmt::Display for CommitmentCheck {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.block_number {
+            Some(block) if self.valid() => write!(f, "yes (block {})", block),
+            Some(block) if !self.sender_matches => write!(f, "NO (block {} has the transaction from another sender)", block),
+            Some(block) => write!(f, "NO (block {} has the transaction with other data)", block),
+            None => write!(f, "NO (transaction {} not found)", self.transaction_hash)
+        }
+    }
+}
+
+/// Check that the commitment transaction of `receipt` is on chain and still
+/// carries the root of the receipt. The transaction is looked up by its
+/// hash, so no blocks are scanned and no explorer is needed.
+pub async fn check_commitment(network: &str, receipt: &CommitReceipt) -> Result<CommitmentCheck> {
+    let backend = backend(network, KeyRole::Audit)?;
+    let transaction = backend.transaction(&receipt.transaction_hash).await?;
+    let payload = transaction.as_ref().and_then(|transaction| read_commitment_payload(&transaction.input));
+    Ok(CommitmentCheck {
+        transaction_hash: receipt.transaction_hash.clone(),
+        block_number: transaction.as_ref().map(|transaction| transaction.block_number),
+        sender_matches: transaction.as_ref()
+            .is_some_and(|transaction| normalize_address(&transaction.from) == normalize_address(&receipt.address)),
+        root_matches: payload.is_some_and(|payload| hex::encode(payload.root) == receipt.root),
+        question_matches: payload.is_some_and(|payload| payload.question_hash.map(hex::encode) == receipt.question_hash),
+        poll_matches: payload.is_some_and(|payload| payload.poll_uuid == receipt.poll_uuid)
+    })
+}
+
+/// Build the transaction carrying `data` to `recipient` for the key of its
+/// role kept offline.
+pub async fn build_transaction(network: &str, recipient: Recipient, data: Vec<u8>, purpose: &str) -> Result<UnsignedTransaction> {
+    let backend = backend(network, recipient.role())?;
+    backend.build_transaction(recipient, data, purpose).await
+}
+
+/// Broadcast a transaction signed offline.
+pub async fn broadcast_transaction(network: &str, signed: &SignedTransaction) -> Result<PostedTransaction> {
+    let span = info_span!("broadcast", phase = "post", tx_hash = %signed.transaction_hash);
+    let backend = backend(network, KeyRole::Audit)?;
+    backend.broadcast_transaction(signed).instrument(span).await
+}
+
+/// Look up the confirmation state of a posted transaction.
+pub async fn transaction_status(network: &str, transaction_hash: &str) -> Result<TransactionStatus> {
+    let backend = backend(network, KeyRole::Audit)?;
+    backend.transaction_status(transaction_hash).await
+}
+
+/// Look up the confirmation state of several transactions concurrently, in
+/// the order of `transaction_hashes`.
+pub async fn transaction_statuses(network: &str, transaction_hashes: &[String]) -> Result<Vec<TransactionStatus>> {
+    let backend = backend(network, KeyRole::Audit)?;
+    futures::stream::iter(transaction_hashes.iter().map(|transaction_hash| backend.transaction_status(transaction_hash)))
+        .buffered(backend.max_concurrent_requests())
+        .try_collect().await
+}
+
+/// Result of a commit: the root is posted once the trustees approved it.
+#[derive(Debug, Clone)]
+pub enum CommitOutcome {
+    /// Proposal written, without the approvals of a quorum of trustees.
+    Proposed(CommitProposal, ApprovalCheck),
+    /// Commitment built and written for the offline commit key to sign.
+    Unsigned(UnsignedTransaction),
+    Posted(CommitReceipt)
+}
+
+/// Store the Merkle tree of the poll and post its root, recording the
+/// phases in `journal`. A root posted by an interrupted commit is not
+/// posted again: the tree is rebuilt and must have the posted root.
+///
+/// The root is only posted with `approvals` of the commit proposal by a
+/// quorum of the trustees whose keys are published in the data directory;
+/// otherwise the proposal is written there for them to approve.
+///
+/// With the commit key kept offline, the commitment transaction is built
+/// and written to the data directory instead, and posted once `signed`,
+/// its signature, is given.
+pub async fn commit(network: &str, pollconf: PollConfiguration, planes: Vec<Plane>, journal: &mut CommitJournal, approvals: &[CommitApproval], signed: Option<&SignedTransaction>) -> Result<CommitOutcome> {
+    let span = info_span!("commit", poll_id = %journal.poll_identifier, phase = "commit");
+    let end_block = pollconf.end_block;
+    let poll_uuid = pollconf.poll_uuid;
+    let question = BallotQuestion::from_poll(&journal.poll_identifier, &pollconf);
+    let (merkle_tree, leaves) = span.in_scope(|| merkle_tree(pollconf, &question, planes))?;
+    let root = hex::encode(merkle_tree.root());
+
+    if let Some(receipt) = &journal.receipt {
+        if receipt.root != root {
+            return Err(format!("Rebuilt Merkle root {} differs from the root {} posted by the interrupted commit.",
+                root, receipt.root).into());
+        }
+        info!(tx_hash = %receipt.transaction_hash, "Root already posted by the interrupted commit");
+        return Ok(CommitOutcome::Posted(receipt.clone()));
+    }
+    journal.record(CommitPhase::TreeStored)?;
 
-        // Send signed transaction
-        let sent = web3.eth().send_raw_transaction(transaction.into()).await.expect("Error sending transaction");
-        debug!("Transaction Hash: {:?}", sent);
+    // Votes must be possible after the commitment.
+    if let Some(end_block) = end_block {
+        let latest = latest_block(network).await?;
+        if end_block <= latest {
+            return Err(format!("The end block {} of the poll is not after the latest block {}.", end_block, latest).into());
+        }
+    }
 
+    // Post only the proposal approved by a quorum of trustees.
+    let datadir = Path::new(&journal.poll_identifier);
+    let trustees = read_trustee_approval_keys(datadir)?;
+    let proposal = CommitProposal {
+        poll_identifier: journal.poll_identifier.clone(),
+        root: root.clone(),
+        leaves,
+        question_hash: hex::encode(question.hash()),
+        end_block,
+        quorum: approval_quorum(trustees.len()),
+        trustees,
+        manifest: proposal_manifest(datadir)?
     };
+    let check = proposal.check_approvals(approvals);
+    for trustee in check.rejected.iter() {
+        warn!(trustee = %trustee, "Approval rejected: it is not a valid approval of the commit proposal");
+    }
+    if !check.valid() {
+        serde_yaml::to_writer(File::create(datadir.join(COMMIT_PROPOSAL_FILENAME))?, &proposal)?;
+        journal.record(CommitPhase::Proposed)?;
+        info!(proposal = %check.proposal_hash, approvals = check.approved_by.len(), quorum = check.quorum,
+            "Commit proposal awaiting the approval of the trustees");
+        return Ok(CommitOutcome::Proposed(proposal, check));
+    }
+    info!(proposal = %check.proposal_hash, approvals = check.approved_by.len(), "Commit proposal approved");
+
+    // Post root to blockchain, or build it for the offline commit key
+    let backend = backend(network, KeyRole::Commit)?;
+    let payload = commitment_payload(&merkle_tree.root(), &question.hash(), poll_uuid.as_ref());
+    let signed = match (backend.signs_offline(), signed) {
+        (false, Some(_)) => return Err("The commit key is not kept offline: the commitment is posted without a signed transaction.".into()),
+        (false, None) => None,
+        (true, None) => {
+            let unsigned = backend.build_transaction(Recipient::Poll, payload, &format!("Commitment of {}", journal.poll_identifier)).instrument(span).await?;
+            serde_yaml::to_writer(File::create(datadir.join(UNSIGNED_TRANSACTION_FILENAME))?, &unsigned)?;
+            journal.record(CommitPhase::Unsigned)?;
+            info!(from = %unsigned.from, nonce = unsigned.nonce, "Commitment built for the offline commit key");
+            return Ok(CommitOutcome::Unsigned(unsigned));
+        },
+        (true, Some(signed)) => {
+            let unsigned: UnsignedTransaction = serde_yaml::from_reader(File::open(datadir.join(UNSIGNED_TRANSACTION_FILENAME))
+                .map_err(|_| format!("{} missing: build the commitment before signing it.", UNSIGNED_TRANSACTION_FILENAME))?)?;
+            if unsigned.input()? != payload {
+                return Err(format!("{} does not commit to the Merkle root {}.", UNSIGNED_TRANSACTION_FILENAME, root).into());
+            }
+            signed.verify(&unsigned)?;
+            Some(signed)
+        }
+    };
+    if journal.resumed_from == Some(CommitPhase::Posting) {
+        warn!(root = %root, "The interrupted commit may have posted the root already; posting it again");
+    }
+    journal.record(CommitPhase::Posting)?;
+    let posted = match signed {
+        Some(signed) => backend.broadcast_transaction(signed).instrument(span).await?,
+        None => post_commitment(network, merkle_tree.root(), question.hash(), poll_uuid).instrument(span).await?
+    };
+    let vote_address = backend.vote_address();
+    let receipt = CommitReceipt {
+        poll_identifier: journal.poll_identifier.clone(),
+        root,
+        leaves,
+        transaction_hash: posted.transaction_hash,
+        address: posted.address,
+        submitted_at_block: posted.submitted_at_block,
+        posted_at: Utc::now(),
+        vote_address,
+        end_block,
+        question_hash: Some(hex::encode(question.hash())),
+        poll_uuid
+    };
+    journal.receipt = Some(receipt.clone());
+    journal.record(CommitPhase::Posted)?;
+    Ok(CommitOutcome::Posted(receipt))
+}
+
+// Construct and store the Merkle tree of the ballot question, roster, audited ballots and planes
+fn merkle_tree(pollconf: PollConfiguration, question: &BallotQuestion, planes: Vec<Plane>) -> Result<(MerkleRoot, usize)> {
+    let (data, mut index) = merkle_tree_leaves(&pollconf, question, planes)?;
 
-    web3::block_on(send);
-    Ok(())   
+    // Create new tree with Vec of data
+    let leaves = data.0.len();
+    let merkle_tree = new_tree(data)
+        .map_err(|err| format!("Merkle tree of {} leaves cannot be built: {}", leaves, err))?;
+    info!(root = %hex::encode(merkle_tree.root()), leaves, "Merkle tree constructed");
+
+    // Store full tree in file, to be later used for proof of inclusions
+    store_tree(&merkle_tree, String::from("merkle.yaml"))
+        .map_err(|err| format!("merkle.yaml cannot be written: {}", err))?;
+    sign_file(&pollconf.signing_key, Path::new("merkle.yaml"))
+        .map_err(|err| format!("merkle.yaml cannot be signed: {}", err))?;
+
+    // Store the index of the leaves next to it, to locate them
+    index.root = hex::encode(merkle_tree.root());
+    let index_path = leaf_index_path(Path::new("merkle.yaml"));
+    serde_yaml::to_writer(File::create(&index_path)?, &index)?;
+    sign_file(&pollconf.signing_key, &index_path)
+        .map_err(|err| format!("{} cannot be signed: {}", index_path.display(), err))?;
+    Ok((merkle_tree, leaves))
 }
 
-pub fn commit (pollconf: PollConfiguration, planes: Vec<Plane>) -> Result<()> {
+/// Leaves of the roster in the Merkle tree of a poll, one per voter: only
+/// the committed entries under a roster policy.
+pub fn roster_leaves(pollconf: &PollConfiguration) -> Result<Vec<String>> {
+
     // Re-construct roster
     let roster: VoterRoster = {
-        let encoded_roster = pollconf.voter_roster.clone().unwrap();
-        let decoded_roster = base64::decode(&encoded_roster.0).unwrap();
-        let serialized_roster = std::str::from_utf8(&decoded_roster).unwrap();
-        serde_yaml::from_str(serialized_roster).unwrap()
+        let encoded_roster = pollconf.voter_roster.clone()
+            .ok_or("voter_roster missing: run bind-roster first")?;
+        let decoded_roster = base64::decode(&encoded_roster.0)
+            .map_err(|err| format!("voter_roster is not valid base64: {}", err))?;
+        let serialized_roster = std::str::from_utf8(&decoded_roster)
+            .map_err(|err| format!("voter_roster is not valid UTF-8: {}", err))?;
+        serde_yaml::from_str(serialized_roster)
+            .map_err(|err| format!("voter_roster is not a valid roster: {}", err))?
     };
 
-    // Get voter info
-    let roster = roster.records.into_iter()
-        .map(|voter| {
-            let ser_v = serde_yaml::to_string(&voter).unwrap();
-            ser_v
-        }).collect();
+    // Get voter info, only the committed entries under a roster policy
+    let leaves = match pollconf.roster_policy() {
+        Some(policy) => roster.restricted_with(policy)?.records.into_iter()
+            .map(|record| {
+                let position = record.position;
+                serde_yaml::to_string(&RestrictedVoterRosterFileRow { position, restricted: record.voter_info })
+                    .map_err(|err| format!("voter {} of voter_roster cannot be serialized: {}", position, err))
+            })
+            .collect::<std::result::Result<Vec<String>, String>>()?,
+        None => roster.records.iter().enumerate()
+            .map(|(position, voter)| serde_yaml::to_string(voter)
+                .map_err(|err| format!("voter {} of voter_roster cannot be serialized: {}", position, err)))
+            .collect::<std::result::Result<Vec<String>, String>>()?
+    };
+    Ok(leaves)
+}
 
+/// Leaves of the Merkle tree of a poll, padded to a power of two: the
+/// ballot question, the roster, the chunks the roster was committed in,
+/// the audited ballots and the cells of the planes.
+pub fn merkle_tree_data(pollconf: &PollConfiguration, question: &BallotQuestion, planes: Vec<Plane>) -> Result<CryptoHashData> {
+    Ok(merkle_tree_leaves(pollconf, question, planes)?.0)
+}
+
+/// Leaves of the Merkle tree of a poll, as `merkle_tree_data`, with the
+/// index of their sections.
+pub fn merkle_tree_leaves(pollconf: &PollConfiguration, question: &BallotQuestion, planes: Vec<Plane>) -> Result<(CryptoHashData, LeafIndex)> {
+    let roster = roster_leaves(pollconf)?;
+
+    // Bind the roster chunks posted, refusing a roster they do not cover.
+    check_roster_chunks(&pollconf.roster_chunks, &roster)?;
+    if roster.len() != pollconf.voter_roster_size {
+        return Err(format!("The roster has {} voters, but the poll configuration records {}.", roster.len(), pollconf.voter_roster_size).into());
+    }
 
     // Re-construct the audited ballots.
-    let audited_ballots = pollconf.audited_ballots.to_owned().unwrap();
-    
+    let audited_ballots = pollconf.audited_ballots.to_owned()
+        .ok_or("audited_ballots missing: record the audited ballots (step 4) first")?;
+    check_audited_ballots(&audited_ballots, pollconf.num_ballots)?;
+
+    // Every plane has two rows per ballot, in serial order.
+    check_planes(&planes, pollconf.num_ballots)?;
+
     // Start vec of data for the tree
+    // Push ballot question
+    let mut data = CryptoHashData::new(vec![question.canonical()]);
+    let mut index = LeafIndex::default();
+    index.push(LeafCategory::Question, 1, None, Vec::new());
+
     // Push roster
-    let mut data = CryptoHashData::new(roster);
+    index.push(LeafCategory::Voter, roster.len(), None, Vec::new());
+    data.push_vec(roster);
+
+    // Push roster chunks
+    index.push(LeafCategory::RosterChunk, pollconf.roster_chunks.len(), None, Vec::new());
+    data.push_vec(pollconf.roster_chunks.iter().map(RosterChunk::leaf).collect());
 
     // Push audited ballots
+    index.push(LeafCategory::AuditedBallot, audited_ballots.len(), None, audited_ballots.clone());
     data.push_vec(audited_ballots);
    
     // Push planes
-    planes.into_iter().for_each(|plane|
+    let mut progress = Progress::new("Collecting plane leaves", "rows", planes.iter().map(|plane| plane.len()).sum());
+    planes.into_iter().enumerate().for_each(|(n, plane)|
     {        
+        index.push(LeafCategory::PlaneCell, 2 * plane.len(), Some(n + 1), Vec::new());
         plane.rows.into_iter().for_each(|row|
         {
             let ser_row = row.serializable(pollconf.num_ballots);
@@ -132,20 +1239,166 @@ pub fn commit (pollconf: PollConfiguration, planes: Vec<Plane>) -> Result<()> {
             // Each row cell is a leaf
             data.push(ser_row.col1);
             data.push(ser_row.col3);
+            progress.inc(1);
         });
     });
+    progress.finish();
 
     // After all data is in vec, pad it to be pow 2
     data.pad();
+    index.push(LeafCategory::Padding, data.0.len() - index.leaves, None, Vec::new());
+    Ok((data, index))
+}
 
+/// Check that the audited ballots are distinct ballots of the poll.
+fn check_audited_ballots(audited_ballots: &[String], num_ballots: usize) -> Result<()> {
+    let mut serials: Vec<BallotSerial> = Vec::new();
+    for audited in audited_ballots.iter() {
+        let serial: BallotSerial = audited.parse()
+            .map_err(|_| format!("Audited ballot {} is not a ballot serial.", audited))?;
+        if serial >= num_ballots {
+            return Err(format!("Audited ballot {} is not a ballot of the poll, which has {} ballots.", serial, num_ballots).into());
+        }
+        if serials.contains(&serial) {
+            return Err(format!("Ballot {} is recorded as audited twice.", serial).into());
+        }
+        serials.push(serial);
+    }
+    Ok(())
+}
 
-    // Create new tree with Vec of data
-    let merkle_tree = new_tree(data).unwrap();
-    debug!("Root: {}", hex::encode(merkle_tree.root()));
+/// Balances of the accounts of the commit and relayer keys that are
+/// configured, with the current gas price.
+pub async fn account_balances(network: &str) -> Result<Vec<AccountBalance>> {
+    let audit = backend(network, KeyRole::Audit)?;
+    let gas_price = audit.gas_price().await?;
+    let mut balances: Vec<AccountBalance> = Vec::new();
+    for role in [KeyRole::Commit, KeyRole::Relay].iter() {
+        let address = match backend(network, *role).map(|backend| backend.sender_address()) {
+            Ok(Some(address)) => address,
+            _ => continue
+        };
+        balances.push(AccountBalance {
+            role: role.to_string(),
+            balance: audit.balance(&address).await?,
+            address,
+            gas_price
+        });
+    }
+    Ok(balances)
+}
 
-    // Store full tree in file, to be later used for proof of inclusions
-    store_tree(&merkle_tree, String::from("merkle.yaml"))?;
+/// Timestamps of the blocks `block_numbers`, in order, read in batches of
+/// `BLOCK_TIMESTAMP_BATCH` blocks.
+pub async fn block_timestamps(network: &str, block_numbers: &[u64]) -> Result<Vec<DateTime<Utc>>> {
+    let backend = backend(network, KeyRole::Audit)?;
+    let mut timestamps: Vec<DateTime<Utc>> = Vec::with_capacity(block_numbers.len());
+    for batch in block_numbers.chunks(BLOCK_TIMESTAMP_BATCH) {
+        timestamps.extend(backend.block_timestamps(batch).await?);
+    }
+    Ok(timestamps)
+}
+
+/// Number of the latest block.
+pub async fn latest_block(network: &str) -> Result<u64> {
+    let backend = backend(network, KeyRole::Audit)?;
+    backend.latest_block().await
+}
+
+/// Number of the latest final block, by the finality policy of the network.
+pub async fn final_block(network: &str) -> Result<u64> {
+    let backend = backend(network, KeyRole::Audit)?;
+    backend.final_block().await
+}
 
-    // Post root to blockchain
-    post(merkle_tree.root())
-}
\ No newline at end of file
+/// Transactions sent to the vote address, following its handovers.
+#[derive(Debug, Clone)]
+pub struct VoteTransactions {
+    /// Transactions in block order, without the handover records.
+    pub transactions: Vec<ChainTransaction>,
+    /// Handovers followed, in order.
+    pub handovers: Vec<Handover>,
+    /// Vote address at the last block read.
+    pub vote_address: String
+}
+
+/// Read all transactions sent to the vote address, from block `from_block`
+/// up to the latest final block, or `end_block` once it is final. The
+/// handovers of the vote address are followed from `vote_address`, the
+/// configured vote address if not given.
+pub async fn vote_transactions(network: &str, vote_address: Option<&str>, from_block: u64, end_block: Option<u64>) -> Result<Vec<ChainTransaction>> {
+    let final_block = final_block(network).await?;
+    let to_block = end_block.map_or(final_block, |end_block| end_block.min(final_block));
+    Ok(vote_transactions_between(network, vote_address, from_block, to_block).await?.transactions)
+}
+
+/// Read all transactions sent to the vote address in blocks
+/// `from_block` to `to_block`, both included, following its handovers
+/// from `vote_address`, the configured vote address if not given. The
+/// blocks are read in windows of `block_window` blocks, at most
+/// `max_concurrent_requests` windows at a time, and the transactions are
+/// returned in block order.
+pub async fn vote_transactions_between(network: &str, vote_address: Option<&str>, from_block: u64, to_block: u64) -> Result<VoteTransactions> {
+    let span = info_span!("vote_transactions", phase = "read", from_block, to_block);
+    follow_vote_transactions(network, vote_address, from_block, to_block).instrument(span).await
+}
+
+async fn follow_vote_transactions(network: &str, vote_address: Option<&str>, from_block: u64, to_block: u64) -> Result<VoteTransactions> {
+    let backend = backend(network, KeyRole::Audit)?;
+    let mut address = match vote_address {
+        Some(address) => address.to_owned(),
+        None => backend.vote_address()
+            .ok_or_else(|| format!("vote_address missing from {} and the commit receipt: pass --vote-address to read votes without it.", network_config_path(network)))?
+    };
+    let mut transactions: Vec<ChainTransaction> = Vec::new();
+    let mut handovers: Vec<Handover> = Vec::new();
+    let mut start = from_block;
+    loop {
+        let read = read_transactions_to(&backend, &address, start, to_block).await?;
+        match find_handover(&address, &read, &handovers) {
+            Some(handover) => {
+                info!(from = %handover.from, to = %handover.to, block = handover.block_number, "Vote address handed over");
+                transactions.extend(read.into_iter()
+                    .filter(|transaction| transaction.block_number <= handover.block_number)
+                    .filter(|transaction| transaction.transaction_hash != handover.transaction_hash));
+                start = handover.block_number;
+                address = handover.to.clone();
+                handovers.push(handover);
+            },
+            None => {
+                transactions.extend(read);
+                break;
+            }
+        }
+    }
+    info!(transactions = transactions.len(), handovers = handovers.len(), "Vote transactions read");
+
+    Ok(VoteTransactions { transactions, handovers, vote_address: address })
+}
+
+async fn read_transactions_to(backend: &Arc<dyn ChainBackend>, address: &str, from_block: u64, to_block: u64) -> Result<Vec<ChainTransaction>> {
+    let windows = block_windows(from_block, to_block, backend.block_window());
+    debug!(address = %address, windows = windows.len(), concurrency = backend.max_concurrent_requests(), "Reading vote transactions");
+
+    let mut read: Vec<(u64, Vec<ChainTransaction>)> = Vec::new();
+    let mut progress = Progress::new("Scanning blocks", "blocks", (to_block + 1).saturating_sub(from_block) as usize);
+    let mut pending = futures::stream::iter(windows.into_iter()
+            .map(|(start, end)| {
+                let backend = backend.clone();
+                let address = address.to_owned();
+                async move { backend.transactions_to(&address, start, end).await.map(|transactions| (start, end, transactions)) }
+            }))
+        .buffer_unordered(backend.max_concurrent_requests());
+    while let Some(window) = pending.next().await {
+        let (start, end, transactions) = window?;
+        progress.inc((end - start + 1) as usize);
+        read.push((start, transactions));
+    }
+    progress.finish();
+
+    // Windows complete in any order.
+    read.sort_by_key(|(start, _)| *start);
+    Ok(read.into_iter()
+        .flat_map(|(_, transactions)| transactions)
+        .collect())
+}
@@ -0,0 +1,115 @@
+//! # Blocking Blockchain Access
+//!
+//! Blocking versions of the blockchain operations, for the CLI. Each call
+//! runs the async operation to completion on the current thread.
+
+use chrono::{DateTime, Utc};
+use crate::Result;
+use crate::blockchain::merkle::CryptoSHA3256Hash;
+use crate::poll_configuration::{PollConfiguration, PollUuid};
+use crate::planes::Plane;
+use crate::ballots::BallotSerial;
+use super::{AccountBalance, BallotAmendment, ChainTransaction, CommitApproval, CommitJournal, CommitOutcome, CommitReceipt, CommitmentCheck, FinalizationReceipt, PostedTransaction, Recipient, RosterChunk, SignedTransaction, TransactionStatus, UnsignedTransaction, VoteTransactions};
+
+pub fn post(network: &str, data: CryptoSHA3256Hash) -> Result<PostedTransaction> {
+    web3::block_on(super::post(network, data))
+}
+
+pub fn post_commitment(network: &str, root: CryptoSHA3256Hash, question_hash: CryptoSHA3256Hash, poll_uuid: Option<PollUuid>) -> Result<PostedTransaction> {
+    web3::block_on(super::post_commitment(network, root, question_hash, poll_uuid))
+}
+
+pub fn account_balances(network: &str) -> Result<Vec<AccountBalance>> {
+    web3::block_on(super::account_balances(network))
+}
+
+pub fn block_timestamps(network: &str, block_numbers: &[u64]) -> Result<Vec<DateTime<Utc>>> {
+    web3::block_on(super::block_timestamps(network, block_numbers))
+}
+
+pub fn post_vote(network: &str, payload: Vec<u8>) -> Result<PostedTransaction> {
+    web3::block_on(super::post_vote(network, payload))
+}
+
+pub fn post_payload(network: &str, recipient: Recipient, payload: &[u8]) -> Result<Vec<PostedTransaction>> {
+    web3::block_on(super::post_payload(network, recipient, payload))
+}
+
+pub fn read_posted_payload(network: &str, transaction_hashes: &[String]) -> Result<Vec<u8>> {
+    web3::block_on(super::read_posted_payload(network, transaction_hashes))
+}
+
+pub fn post_handover(network: &str, vote_address: &str, new_address: &str) -> Result<PostedTransaction> {
+    web3::block_on(super::post_handover(network, vote_address, new_address))
+}
+
+pub fn post_finalization(network: &str, poll_identifier: &str, tally: &[u8]) -> Result<FinalizationReceipt> {
+    web3::block_on(super::post_finalization(network, poll_identifier, tally))
+}
+
+pub fn post_amendment(network: &str, poll_identifier: &str, spoiled_serial: BallotSerial, replacement_serial: BallotSerial, reason: Option<&str>, ballot_commitment: Option<&str>) -> Result<BallotAmendment> {
+    web3::block_on(super::post_amendment(network, poll_identifier, spoiled_serial, replacement_serial, reason, ballot_commitment))
+}
+
+pub fn post_roster_chunk(network: &str, poll_identifier: &str, index: usize, first_position: usize, voters: usize, root: &CryptoSHA3256Hash) -> Result<RosterChunk> {
+    web3::block_on(super::post_roster_chunk(network, poll_identifier, index, first_position, voters, root))
+}
+
+pub fn posted_transaction(network: &str, address: &str, transaction_hash: &str, from_block: u64) -> Result<Option<ChainTransaction>> {
+    web3::block_on(super::posted_transaction(network, address, transaction_hash, from_block))
+}
+
+pub fn check_commitment(network: &str, receipt: &CommitReceipt) -> Result<CommitmentCheck> {
+    web3::block_on(super::check_commitment(network, receipt))
+}
+
+pub fn transaction_status(network: &str, transaction_hash: &str) -> Result<TransactionStatus> {
+    web3::block_on(super::transaction_status(network, transaction_hash))
+}
+
+pub fn transaction_statuses(network: &str, transaction_hashes: &[String]) -> Result<Vec<TransactionStatus>> {
+    web3::block_on(super::transaction_statuses(network, transaction_hashes))
+}
+
+pub fn commit(network: &str, pollconf: PollConfiguration, planes: Vec<Plane>, journal: &mut CommitJournal, approvals: &[CommitApproval], signed: Option<&SignedTransaction>) -> Result<CommitOutcome> {
+    web3::block_on(super::commit(network, pollconf, planes, journal, approvals, signed))
+}
+
+pub fn latest_block(network: &str) -> Result<u64> {
+    web3::block_on(super::latest_block(network))
+}
+
+pub fn build_transaction(network: &str, recipient: Recipient, data: Vec<u8>, purpose: &str) -> Result<UnsignedTransaction> {
+    web3::block_on(super::build_transaction(network, recipient, data, purpose))
+}
+
+pub fn broadcast_transaction(network: &str, signed: &SignedTransaction) -> Result<PostedTransaction> {
+    web3::block_on(super::broadcast_transaction(network, signed))
+}
+
+pub fn final_block(network: &str) -> Result<u64> {
+    web3::block_on(super::final_block(network))
+}
+
+pub fn vote_transactions(network: &str, vote_address: Option<&str>, from_block: u64, end_block: Option<u64>) -> Result<Vec<ChainTransaction>> {
+    web3::block_on(super::vote_transactions(network, vote_address, from_block, end_block))
+}
+
+pub fn vote_transactions_between(network: &str, vote_address: Option<&str>, from_block: u64, to_block: u64) -> Result<VoteTransactions> {
+    web3::block_on(super::vote_transactions_between(network, vote_address, from_block, to_block))
+}
+
+#[cfg(feature = "timestamps")]
+pub fn stamp(digest: [u8; 32], calendars: &[String]) -> Result<super::DetachedTimestamp> {
+    web3::block_on(super::TimestampClient::new().stamp(digest, calendars))
+}
+
+#[cfg(feature = "timestamps")]
+pub fn upgrade_timestamp(detached: &mut super::DetachedTimestamp) -> Result<usize> {
+    web3::block_on(super::TimestampClient::new().upgrade(detached))
+}
+
+#[cfg(feature = "timestamps")]
+pub fn bitcoin_merkle_root(esplora: &str, height: u64) -> Result<Vec<u8>> {
+    web3::block_on(super::TimestampClient::new().bitcoin_merkle_root(esplora, height))
+}
@@ -0,0 +1,301 @@
+//! # Block Explorer Backends
+//!
+//! Votes can be read from the transaction list of a block explorer rather
+//! than block by block from the node, which takes a request per page of
+//! transactions instead of a request per block. An `Explorer` builds the
+//! requests of its API: `EtherscanExplorer` for Etherscan and the
+//! Etherscan-compatible Routescan API, `BlockscoutExplorer` for the
+//! Blockscout instances of private and L2 chains. The pagination, the
+//! response schema and, through the node backend, the rate limit are shared
+//! by every explorer.
+//!
+//! The explorer is configured in the `explorer` section of the network
+//! configuration; requests are only sent with the `explorer` feature.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::future::Future;
+use chrono::{TimeZone, Utc};
+use secrecy::{ExposeSecret, SecretString};
+use serde::Deserialize;
+use crate::Result;
+use super::{normalize_address, ChainTransaction, NodeError};
+
+/// Results Etherscan returns for a query, across all its pages.
+pub const MAX_RESULT_WINDOW: u64 = 10_000;
+
+const NO_TRANSACTIONS_FOUND: &str = "No transactions found";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ExplorerKind {
+    Etherscan,
+    Blockscout,
+    Routescan
+}
+
+impl fmt::Display for ExplorerKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ExplorerKind::Etherscan => write!(f, "etherscan"),
+            ExplorerKind::Blockscout => write!(f, "blockscout"),
+            ExplorerKind::Routescan => write!(f, "routescan")
+        }
+    }
+}
+
+/// `explorer` section of the network configuration.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ExplorerConfig {
+    pub kind: ExplorerKind,
+    /// URL of the API, such as `https://api.etherscan.io/v2/api`.
+    pub url: String,
+    /// API key; required by Etherscan.
+    #[serde(default)]
+    pub api_key: Option<SecretString>,
+    /// Chain of the Etherscan API; Blockscout and Routescan URLs name the
+    /// chain themselves.
+    #[serde(default)]
+    pub chain_id: Option<u64>,
+    /// Transactions read by each request.
+    #[serde(default = "default_page_size")]
+    pub page_size: u64
+}
+
+fn default_page_size() -> u64 { 1000 }
+
+/// Requests of the transaction list API of a block explorer.
+pub trait Explorer: Send + Sync {
+    fn kind(&self) -> ExplorerKind;
+
+    /// Transactions read by each request.
+    fn page_size(&self) -> u64;
+
+    /// Results returned for a query across all its pages, if limited.
+    fn result_window(&self) -> Option<u64> { Some(MAX_RESULT_WINDOW) }
+
+    /// URL of page `page`, numbered from 1, of the transactions of
+    /// `address` in blocks `start` to `end`, in block order.
+    fn transactions_url(&self, address: &str, start: u64, end: u64, page: u64) -> String;
+}
+
+pub struct EtherscanExplorer {
+    kind: ExplorerKind,
+    url: String,
+    api_key: Option<SecretString>,
+    chain_id: Option<u64>,
+    page_size: u64
+}
+
+impl Explorer for EtherscanExplorer {
+    fn kind(&self) -> ExplorerKind { self.kind }
+
+    fn page_size(&self) -> u64 { self.page_size }
+
+    fn transactions_url(&self, address: &str, start: u64, end: u64, page: u64) -> String {
+        let mut url = format!("{}?module=account&action=txlist&address={}&startblock={}&endblock={}&page={}&offset={}&sort=asc",
+            self.url, address, start, end, page, self.page_size);
+        if let Some(chain_id) = self.chain_id {
+            url.push_str(&format!("&chainid={}", chain_id));
+        }
+        if let Some(api_key) = &self.api_key {
+            url.push_str(&format!("&apikey={}", api_key.expose_secret()));
+        }
+        url
+    }
+}
+
+pub struct BlockscoutExplorer {
+    url: String,
+    api_key: Option<SecretString>,
+    page_size: u64
+}
+
+impl Explorer for BlockscoutExplorer {
+    fn kind(&self) -> ExplorerKind { ExplorerKind::Blockscout }
+
+    fn page_size(&self) -> u64 { self.page_size }
+
+    fn result_window(&self) -> Option<u64> { None }
+
+    fn transactions_url(&self, address: &str, start: u64, end: u64, page: u64) -> String {
+        let mut url = format!("{}?module=account&action=txlist&address={}&startblock={}&endblock={}&page={}&offset={}&sort=asc",
+            self.url, address, start, end, page, self.page_size);
+        if let Some(api_key) = &self.api_key {
+            url.push_str(&format!("&apikey={}", api_key.expose_secret()));
+        }
+        url
+    }
+}
+
+/// Explorer of the `explorer` section of the network configuration.
+pub fn explorer(config: &ExplorerConfig) -> Result<Box<dyn Explorer>> {
+    if config.url.is_empty() {
+        return Err(format!("url missing from the {} explorer configuration", config.kind).into());
+    }
+    let page_size = config.page_size.clamp(1, MAX_RESULT_WINDOW);
+    let url = config.url.trim_end_matches(['?', '/']).to_owned();
+    match config.kind {
+        ExplorerKind::Etherscan | ExplorerKind::Routescan => {
+            if config.kind == ExplorerKind::Etherscan && config.api_key.is_none() {
+                return Err("api_key missing from the etherscan explorer configuration".into());
+            }
+            Ok(Box::new(EtherscanExplorer {
+                kind: config.kind,
+                url,
+                api_key: config.api_key.clone(),
+                chain_id: config.chain_id,
+                page_size
+            }))
+        },
+        ExplorerKind::Blockscout => Ok(Box::new(BlockscoutExplorer {
+            url,
+            api_key: config.api_key.clone(),
+            page_size
+        }))
+    }
+}
+
+/// Response of the transaction list API, shared by the explorers. A
+/// refused request has status `0` and a message in place of the result.
+#[derive(Debug, Deserialize)]
+struct ExplorerResponse {
+    status: String,
+    #[serde(default)]
+    message: String,
+    result: serde_json::Value
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExplorerTransaction {
+    hash: String,
+    from: String,
+    #[serde(default)]
+    to: String,
+    block_number: String,
+    time_stamp: String,
+    #[serde(default)]
+    gas_price: String,
+    #[serde(default)]
+    input: String
+}
+
+impl ExplorerTransaction {
+    fn into_chain_transaction(self) -> std::result::Result<ChainTransaction, NodeError> {
+        let invalid = |field: &str, value: &str| NodeError::InvalidResponse(format!("transaction {} has {} {}", self.hash, field, value));
+        let block_number = self.block_number.parse().map_err(|_| invalid("block number", &self.block_number))?;
+        let timestamp = self.time_stamp.parse().map_err(|_| invalid("timestamp", &self.time_stamp))?;
+        let input = hex::decode(self.input.trim_start_matches("0x")).map_err(|_| invalid("input", &self.input))?;
+        Ok(ChainTransaction {
+            block_number,
+            timestamp: Utc.timestamp(timestamp, 0),
+            gas_price: self.gas_price.parse::<u128>().map(|price| price.min(u128::from(u64::MAX)) as u64).unwrap_or(0),
+            input,
+            transaction_hash: self.hash,
+            from: self.from
+        })
+    }
+}
+
+/// Transactions of a response page, with their recipient. A page without
+/// transactions is not an error.
+fn read_transactions_page(body: &[u8]) -> std::result::Result<Vec<(String, ChainTransaction)>, NodeError> {
+    let response: ExplorerResponse = serde_json::from_slice(body)
+        .map_err(|err| NodeError::InvalidResponse(format!("explorer response cannot be read: {}", err)))?;
+    match response.result {
+        serde_json::Value::Array(transactions) => transactions.into_iter()
+            .map(|transaction| {
+                let transaction: ExplorerTransaction = serde_json::from_value(transaction)
+                    .map_err(|err| NodeError::InvalidResponse(format!("explorer transaction cannot be read: {}", err)))?;
+                let to = transaction.to.clone();
+                Ok((to, transaction.into_chain_transaction()?))
+            })
+            .collect(),
+        serde_json::Value::Null if response.message.starts_with(NO_TRANSACTIONS_FOUND) => Ok(Vec::new()),
+        result => {
+            let detail = match result {
+                serde_json::Value::String(detail) => detail,
+                result => result.to_string()
+            };
+            if response.message.starts_with(NO_TRANSACTIONS_FOUND) || detail.starts_with(NO_TRANSACTIONS_FOUND) {
+                return Ok(Vec::new());
+            }
+            Err(NodeError::from_explorer(&format!("{} (status {}): {}", response.message, response.status, detail)))
+        }
+    }
+}
+
+/// Transactions sent to `address` in blocks `start` to `end`, both
+/// included, in block order, reading the pages of `explorer` with `fetch`.
+/// Queries past the result window of the explorer are continued from the
+/// last block read.
+pub async fn explorer_transactions_to<F, R>(explorer: &dyn Explorer, address: &str, start: u64, end: u64, fetch: F) -> Result<Vec<ChainTransaction>>
+    where F: Fn(String) -> R,
+          R: Future<Output = std::result::Result<Vec<u8>, NodeError>>
+{
+    let page_size = explorer.page_size();
+    let recipient = normalize_address(address).ok_or_else(|| format!("Not an address: {}", address))?;
+    let mut transactions: Vec<ChainTransaction> = Vec::new();
+    let mut seen: HashSet<String> = HashSet::new();
+    let (mut from_block, mut page) = (start, 1);
+    loop {
+        let body = fetch(explorer.transactions_url(address, from_block, end, page)).await?;
+        let page_transactions = read_transactions_page(&body)?;
+        let count = page_transactions.len() as u64;
+        let last_block = page_transactions.last().map(|(_, transaction)| transaction.block_number);
+        for (to, transaction) in page_transactions {
+            if normalize_address(&to).as_ref() == Some(&recipient)
+                && start <= transaction.block_number && transaction.block_number <= end
+                && seen.insert(transaction.transaction_hash.clone()) {
+                transactions.push(transaction);
+            }
+        }
+        if count < page_size {
+            break;
+        }
+        if explorer.result_window().is_some_and(|window| window < (page + 1) * page_size) {
+            // Read again from the last block, whose transactions may not all be read.
+            let last_block = last_block.unwrap_or(end);
+            if last_block == from_block {
+                return Err(format!("Block {} has more transactions of {} than the {} explorer returns.",
+                    last_block, address, explorer.kind()).into());
+            }
+            from_block = last_block;
+            page = 1;
+        } else {
+            page += 1;
+        }
+    }
+    transactions.sort_by_key(|transaction| transaction.block_number);
+    Ok(transactions)
+}
+
+/// Requests sent to an explorer.
+#[cfg(feature = "explorer")]
+pub struct ExplorerClient {
+    pub explorer: Box<dyn Explorer>,
+    client: hyper::Client<hyper_tls::HttpsConnector<hyper::client::HttpConnector>>
+}
+
+#[cfg(feature = "explorer")]
+impl ExplorerClient {
+    pub fn new(explorer: Box<dyn Explorer>) -> Self {
+        let client = hyper::Client::builder().build::<_, hyper::Body>(hyper_tls::HttpsConnector::new());
+        ExplorerClient { explorer, client }
+    }
+
+    /// Body of the response to `url`.
+    pub async fn get(&self, url: String) -> std::result::Result<Vec<u8>, NodeError> {
+        let uri: hyper::Uri = url.parse()
+            .map_err(|err| NodeError::Transport(format!("explorer URL is not valid: {}", err)))?;
+        let response = self.client.get(uri).await
+            .map_err(|err| NodeError::from_transport(&err.to_string()))?;
+        if !response.status().is_success() {
+            return Err(NodeError::from_transport(&format!("Unexpected response status code: {}", response.status())));
+        }
+        let body = hyper::body::to_bytes(response.into_body()).await
+            .map_err(|err| NodeError::from_transport(&err.to_string()))?;
+        Ok(body.to_vec())
+    }
+}
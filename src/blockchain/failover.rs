@@ -0,0 +1,170 @@
+//! # Node Failover
+//!
+//! The `node` of a network configuration may list several endpoints, e.g.
+//! the node of the poll authority followed by hosted providers.
+//! `FailoverTransport` sends every request to the first healthy endpoint
+//! in that order, and sends it on to the next one when the endpoint fails
+//! rather than the request: it cannot be reached, it rate limits the
+//! request or it refuses its API key. A failed endpoint is left out for
+//! `FAILOVER_COOLDOWN`, after which it is tried again, so requests return
+//! to the first endpoints once they recover.
+//!
+//! Endpoints are logged by `endpoint_label`, without the path of the URL,
+//! which carries the API key of most providers.
+
+use std::future::Future;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use futures::future::BoxFuture;
+use tracing::{info, warn};
+use web3::transports::Http;
+use web3::{BatchTransport, RequestId, Transport};
+use crate::metrics;
+use super::NodeError;
+
+/// Time a failed endpoint is left out for.
+pub const FAILOVER_COOLDOWN: Duration = Duration::from_secs(30);
+
+/// Scheme and host of an endpoint URL, without credentials, path or query.
+pub fn endpoint_label(url: &str) -> String {
+    let (scheme, rest) = match url.find("://") {
+        Some(position) => (&url[..position + 3], &url[position + 3..]),
+        None => ("", url)
+    };
+    let authority = rest.split(['/', '?', '#']).next().unwrap_or_default();
+    let host = authority.rsplit('@').next().unwrap_or_default();
+    format!("{}{}", scheme, host)
+}
+
+/// Whether `err` is a failure of the endpoint, for which the request is
+/// sent on to the next endpoint.
+fn fails_over(err: &web3::Error) -> bool {
+    match NodeError::from(err) {
+        NodeError::Unauthorized(_) => true,
+        err => err.is_retryable()
+    }
+}
+
+#[derive(Debug)]
+struct Endpoint {
+    label: String,
+    http: Http
+}
+
+#[derive(Debug)]
+struct Health {
+    /// Time until which each endpoint is left out, if it failed.
+    down_until: Vec<Option<Instant>>,
+    /// Endpoint that served the last request.
+    serving: usize
+}
+
+/// Transport sending requests to the first healthy endpoint of a list.
+#[derive(Debug, Clone)]
+pub struct FailoverTransport {
+    endpoints: Arc<Vec<Endpoint>>,
+    health: Arc<Mutex<Health>>,
+    id: Arc<AtomicUsize>
+}
+
+impl FailoverTransport {
+    pub fn new(urls: &[String]) -> web3::Result<Self> {
+        if urls.is_empty() {
+            return Err(web3::Error::Transport("No node endpoint is configured.".to_owned()));
+        }
+        let endpoints = urls.iter()
+            .map(|url| Ok(Endpoint { label: endpoint_label(url), http: Http::new(url)? }))
+            .collect::<web3::Result<Vec<Endpoint>>>()?;
+        Ok(FailoverTransport {
+            health: Arc::new(Mutex::new(Health { down_until: vec![None; endpoints.len()], serving: 0 })),
+            endpoints: Arc::new(endpoints),
+            id: Arc::new(AtomicUsize::new(1))
+        })
+    }
+
+    /// Label of the endpoint that served the last request.
+    pub fn serving(&self) -> String {
+        self.endpoints[self.health.lock().unwrap().serving].label.clone()
+    }
+
+    /// Endpoints in the order they are tried: the healthy ones first, then
+    /// those left out, should they all have failed.
+    fn order(&self) -> Vec<usize> {
+        let now = Instant::now();
+        let health = self.health.lock().unwrap();
+        let (healthy, down): (Vec<usize>, Vec<usize>) = (0..self.endpoints.len())
+            .partition(|&index| health.down_until[index].is_none_or(|until| until <= now));
+        healthy.into_iter().chain(down).collect()
+    }
+
+    fn served(&self, index: usize) {
+        let mut health = self.health.lock().unwrap();
+        health.down_until[index] = None;
+        if health.serving != index {
+            info!(provider = %self.endpoints[index].label, previous = %self.endpoints[health.serving].label, "Node endpoint now serving requests");
+            health.serving = index;
+        }
+    }
+
+    fn failed(&self, index: usize) {
+        self.health.lock().unwrap().down_until[index] = Some(Instant::now() + FAILOVER_COOLDOWN);
+    }
+
+    /// Send `request` to the endpoints in turn, until one serves it or
+    /// fails on the request itself.
+    async fn failover<T, F, R>(&self, request: F) -> web3::Result<T>
+    where
+        F: Fn(&Http) -> R,
+        R: Future<Output = web3::Result<T>>
+    {
+        let order = self.order();
+        let mut failure: Option<web3::Error> = None;
+        for (attempt, &index) in order.iter().enumerate() {
+            let endpoint = &self.endpoints[index];
+            match request(&endpoint.http).await {
+                Ok(result) => {
+                    self.served(index);
+                    return Ok(result);
+                },
+                Err(err) if fails_over(&err) => {
+                    self.failed(index);
+                    if attempt + 1 < order.len() {
+                        warn!(provider = %endpoint.label, error = %err, "Node endpoint failed; failing over to the next endpoint");
+                        metrics::inc_rpc_failovers();
+                    }
+                    failure = Some(err);
+                },
+                Err(err) => return Err(err)
+            }
+        }
+        Err(failure.unwrap_or(web3::Error::Unreachable))
+    }
+}
+
+impl Transport for FailoverTransport {
+    type Out = BoxFuture<'static, web3::Result<jsonrpc_core::Value>>;
+
+    fn prepare(&self, method: &str, params: Vec<jsonrpc_core::Value>) -> (RequestId, jsonrpc_core::Call) {
+        let id = self.id.fetch_add(1, Ordering::AcqRel);
+        (id, web3::helpers::build_request(id, method, params))
+    }
+
+    fn send(&self, id: RequestId, request: jsonrpc_core::Call) -> Self::Out {
+        let transport = self.clone();
+        Box::pin(async move { transport.failover(|http| http.send(id, request.clone())).await })
+    }
+}
+
+impl BatchTransport for FailoverTransport {
+    type Batch = BoxFuture<'static, web3::Result<Vec<web3::Result<jsonrpc_core::Value>>>>;
+
+    fn send_batch<T>(&self, requests: T) -> Self::Batch
+    where
+        T: IntoIterator<Item = (RequestId, jsonrpc_core::Call)>
+    {
+        let transport = self.clone();
+        let requests: Vec<(RequestId, jsonrpc_core::Call)> = requests.into_iter().collect();
+        Box::pin(async move { transport.failover(|http| http.send_batch(requests.clone())).await })
+    }
+}
@@ -0,0 +1,63 @@
+//! # Finality
+//!
+//! What makes a block final differs between chains. On proof-of-work
+//! chains a block is final once enough blocks are mined on top of it. On
+//! proof-of-stake chains the node reports the latest `finalized` checkpoint,
+//! and the `safe` block before it. On rollups every block past the `safe`
+//! one only has the word of the sequencer: the `safe` block is the latest
+//! one whose batch was posted to the parent chain, and the `finalized` block
+//! the latest one whose batch is final there.
+//!
+//! The `FinalityPolicy` of a network is set with `finality` in its network
+//! configuration. Commitments are confirmed, and votes counted, in final
+//! blocks only.
+
+use std::fmt;
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FinalityPolicy {
+    /// Final once this many blocks, its own included, are mined.
+    Confirmations(u64),
+    /// Final once the node reports it as safe.
+    Safe,
+    /// Final once the node reports it as finalized.
+    Finalized
+}
+
+/// Every mined block is final, as on a development chain.
+impl Default for FinalityPolicy {
+    fn default() -> Self { FinalityPolicy::Confirmations(1) }
+}
+
+impl FinalityPolicy {
+    /// Block tag the node is asked for, for the policies of the node.
+    pub fn block_tag(&self) -> Option<&'static str> {
+        match self {
+            FinalityPolicy::Confirmations(_) => None,
+            FinalityPolicy::Safe => Some("safe"),
+            FinalityPolicy::Finalized => Some("finalized")
+        }
+    }
+
+    /// Latest final block when `latest` is the latest block, for the
+    /// policies counting confirmations.
+    pub fn final_block(&self, latest: u64) -> Option<u64> {
+        match self {
+            FinalityPolicy::Confirmations(confirmations) => Some(latest.saturating_sub(confirmations.saturating_sub(1))),
+            _ => None
+        }
+    }
+}
+
+impl fmt::Display for FinalityPolicy {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FinalityPolicy::Confirmations(1) => write!(f, "1 confirmation"),
+            FinalityPolicy::Confirmations(confirmations) => write!(f, "{} confirmations", confirmations),
+            FinalityPolicy::Safe => write!(f, "safe"),
+            FinalityPolicy::Finalized => write!(f, "finalized")
+        }
+    }
+}
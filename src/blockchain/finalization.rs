@@ -0,0 +1,45 @@
+//! # Tally Finalization
+//!
+//! Once the tally report of step 8 is signed, its SHA3-256 hash is posted
+//! to the blockchain as the closing commitment of the poll, the same way
+//! the Merkle root is posted by step 4. The published result is then
+//! anchored on the chain: a report revised afterwards no longer matches
+//! the hash of the finalization transaction, which anyone can read back.
+
+use std::fmt;
+use crypto::digest::Digest;
+use crypto::sha3::{Sha3, Sha3Mode};
+use serde::{Serialize, Deserialize};
+use chrono::{DateTime, Utc};
+use super::merkle::CryptoSHA3256Hash;
+
+/// Receipt for the hash of the tally report posted to the blockchain.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FinalizationReceipt {
+    pub poll_identifier: String,
+    /// Hex encoded SHA3-256 hash of the tally report.
+    pub tally_hash: String,
+    pub transaction_hash: String,
+    pub address: String,
+    pub submitted_at_block: u64,
+    pub posted_at: DateTime<Utc>
+}
+
+impl fmt::Display for FinalizationReceipt {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Poll:        {}", self.poll_identifier)?;
+        writeln!(f, "Tally hash:  {}", self.tally_hash)?;
+        writeln!(f, "Transaction: {}", self.transaction_hash)?;
+        writeln!(f, "Address:     {}", self.address)?;
+        write!(f, "Block:       {}", self.submitted_at_block)
+    }
+}
+
+/// SHA3-256 hash of a tally report.
+pub fn tally_hash(tally: &[u8]) -> CryptoSHA3256Hash {
+    let mut hasher = Sha3::new(Sha3Mode::Sha3_256);
+    hasher.input(tally);
+    let mut hash = [0u8; 32];
+    hasher.result(&mut hash);
+    hash
+}
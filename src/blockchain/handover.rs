@@ -0,0 +1,75 @@
+//! # Vote Address Handover
+//!
+//! The address votes are collected at can be handed over to a new address
+//! in the middle of a poll, so that the posting key can be rotated: the key
+//! of the current address sends the address a handover record naming the
+//! new address,
+//!
+//! ```text
+//! 7E:HANDOVER:<new address>
+//! ```
+//!
+//! Readers start at the vote address of the poll and follow the chain of
+//! handovers: the transactions to an address are gathered up to the block
+//! of its handover record, and those to the new address from that block
+//! on. A record is only followed if it was sent from the address itself,
+//! i.e. signed by its key; records sent from other accounts are ignored.
+
+use serde::{Serialize, Deserialize};
+use seventh_estate_core::payload::PAYLOAD_PREFIX;
+use super::ChainTransaction;
+
+pub const HANDOVER_KIND: &str = "HANDOVER";
+
+/// Handover of the vote address, read from the chain.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Handover {
+    pub from: String,
+    pub to: String,
+    pub transaction_hash: String,
+    pub block_number: u64
+}
+
+/// Address in the form read back from the chain, `0x` and 40 lowercase hex
+/// digits, or `None` if it is not an address.
+pub fn normalize_address(address: &str) -> Option<String> {
+    let digits = address.trim().trim_start_matches("0x").to_lowercase();
+    if digits.len() != 40 || !digits.chars().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    Some(format!("0x{}", digits))
+}
+
+/// Input data of the handover record naming `address`.
+pub fn handover_payload(address: &str) -> Option<Vec<u8>> {
+    let address = normalize_address(address)?;
+    Some(format!("{}:{}:{}", PAYLOAD_PREFIX, HANDOVER_KIND, address).into_bytes())
+}
+
+/// Address named by the handover record carried in `data`.
+pub fn handover_address(data: &[u8]) -> Option<String> {
+    let value = std::str::from_utf8(data).ok()?.trim();
+    let fields: Vec<&str> = value.splitn(3, ':').collect();
+    match fields.as_slice() {
+        [PAYLOAD_PREFIX, HANDOVER_KIND, address] => normalize_address(address),
+        _ => None
+    }
+}
+
+/// First handover of `address` among the transactions sent to it, skipping
+/// the handovers already `followed`.
+pub fn find_handover(address: &str, transactions: &[ChainTransaction], followed: &[Handover]) -> Option<Handover> {
+    let address = normalize_address(address)?;
+    transactions.iter()
+        .filter(|transaction| normalize_address(&transaction.from).as_ref() == Some(&address))
+        .filter(|transaction| !followed.iter().any(|handover| handover.transaction_hash == transaction.transaction_hash))
+        .find_map(|transaction| {
+            let to = handover_address(&transaction.input).filter(|to| *to != address)?;
+            Some(Handover {
+                from: address.clone(),
+                to,
+                transaction_hash: transaction.transaction_hash.clone(),
+                block_number: transaction.block_number
+            })
+        })
+}
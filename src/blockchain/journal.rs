@@ -0,0 +1,103 @@
+//! # Commit Journal
+//!
+//! `journal` is the write-ahead journal of the commit of a poll (step 4),
+//! kept in `commit_journal.yaml` in the data directory of the poll. Every
+//! phase of the commit is recorded before it is taken, and the posted
+//! Merkle root as soon as the node accepts it, so that a run interrupted
+//! between posting the root and writing `merkle.yaml` or the commit
+//! receipt leaves enough behind to complete the commit.
+//!
+//! Running the step again resumes an incomplete commit. Phases before the
+//! root was posted are redone from scratch, overwriting the files they
+//! wrote; once the root is posted, its transaction is kept and only the
+//! Merkle tree and the receipt are written again.
+
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use crate::Result;
+use super::CommitReceipt;
+
+const JOURNAL_FILE: &str = "commit_journal.yaml";
+
+/// Phases of a commit, in order.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CommitPhase {
+    /// Audited ballots and planes being written.
+    Started,
+    /// Poll configuration saved with the ceremony conducted.
+    ConfigurationSaved,
+    /// Merkle tree stored in `merkle.yaml`.
+    TreeStored,
+    /// Commit proposal written, awaiting the approval of the trustees.
+    Proposed,
+    /// Commitment built for the offline commit key, awaiting its signature.
+    Unsigned,
+    /// Root being posted; the node may have accepted it.
+    Posting,
+    /// Root posted, receipt being written.
+    Posted,
+    /// Commit receipt written.
+    Completed
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CommitJournal {
+    pub poll_identifier: String,
+    pub phase: CommitPhase,
+    pub audited_ballots: Vec<String>,
+    /// Receipt of the posted root, kept until it is written.
+    #[serde(default)]
+    pub receipt: Option<CommitReceipt>,
+    pub updated_at: DateTime<Utc>,
+    /// Phase reached by the interrupted commit the journal was left by.
+    #[serde(skip)]
+    pub resumed_from: Option<CommitPhase>
+}
+
+impl CommitJournal {
+    fn path(datadir_path: &str) -> PathBuf {
+        Path::new(datadir_path).join(JOURNAL_FILE)
+    }
+
+    /// Journal of the interrupted commit of the poll, if any, or of a new
+    /// commit. Nothing is written until the first phase is recorded.
+    pub fn open(datadir_path: &str) -> Result<Self> {
+        let path = CommitJournal::path(datadir_path);
+        if path.exists() {
+            let journal: CommitJournal = serde_yaml::from_reader(File::open(&path)?)?;
+            if journal.phase != CommitPhase::Completed {
+                return Ok(CommitJournal { resumed_from: Some(journal.phase), ..journal });
+            }
+        }
+        Ok(CommitJournal {
+            poll_identifier: datadir_path.to_owned(),
+            phase: CommitPhase::Started,
+            audited_ballots: Vec::new(),
+            receipt: None,
+            updated_at: Utc::now(),
+            resumed_from: None
+        })
+    }
+
+    /// Whether the root was posted by this or an interrupted commit.
+    pub fn is_posted(&self) -> bool {
+        self.receipt.is_some()
+    }
+
+    /// Record that `phase` is reached. The journal is replaced atomically,
+    /// so that it is never left half written.
+    pub fn record(&mut self, phase: CommitPhase) -> Result<()> {
+        self.phase = phase;
+        self.updated_at = Utc::now();
+        let path = CommitJournal::path(&self.poll_identifier);
+        let partial_path = path.with_extension("yaml.partial");
+        let file = File::create(&partial_path)?;
+        serde_yaml::to_writer(&file, self)?;
+        file.sync_all()?;
+        fs::rename(&partial_path, &path)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,61 @@
+//! # Network Keyring
+//!
+//! The private keys and the node URL, which often carries the API key of
+//! the node provider, can be kept in the keyring of the operating system
+//! (macOS Keychain, Windows Credential Manager or Secret Service) instead of
+//! the network configuration file. A configuration with `keyring: true`
+//! reads the values it leaves out from the entries `<NETWORK>/<ENTRY>` of
+//! the `seventh-estate` keyring service, where `ENTRY` is one of
+//! `KEYRING_ENTRIES`. Requires the `keyring` feature.
+
+use secrecy::SecretString;
+use crate::Result;
+
+pub const KEYRING_SERVICE: &str = "seventh-estate";
+
+/// Network configuration values that can be kept in the keyring.
+pub const KEYRING_ENTRIES: [&str; 3] = ["commit_key", "relayer_key", "node"];
+
+fn keyring_user(network: &str, entry: &str) -> Result<String> {
+    if !KEYRING_ENTRIES.contains(&entry) {
+        return Err(format!("{} is not a keyring entry; expected one of {}.", entry, KEYRING_ENTRIES.join(", ")).into());
+    }
+    Ok(format!("{}/{}", network, entry))
+}
+
+/// Value of a keyring entry of `network`, or `None` if it is not stored.
+#[cfg(feature = "keyring")]
+pub fn read_keyring_secret(network: &str, entry: &str) -> Result<Option<SecretString>> {
+    let user = keyring_user(network, entry)?;
+    match os_keyring::Entry::new(KEYRING_SERVICE, &user)?.get_password() {
+        Ok(secret) => Ok(Some(SecretString::new(secret))),
+        Err(os_keyring::Error::NoEntry) => Ok(None),
+        Err(err) => Err(format!("Keyring entry {} cannot be read: {}", user, err).into())
+    }
+}
+
+/// Store the value of a keyring entry of `network`, replacing any value.
+#[cfg(feature = "keyring")]
+pub fn store_keyring_secret(network: &str, entry: &str, secret: &SecretString) -> Result<()> {
+    use secrecy::ExposeSecret;
+    let user = keyring_user(network, entry)?;
+    os_keyring::Entry::new(KEYRING_SERVICE, &user)?.set_password(secret.expose_secret())
+        .map_err(|err| format!("Keyring entry {} cannot be stored: {}", user, err).into())
+}
+
+/// Delete a keyring entry of `network`, returning whether it was stored.
+#[cfg(feature = "keyring")]
+pub fn delete_keyring_secret(network: &str, entry: &str) -> Result<bool> {
+    let user = keyring_user(network, entry)?;
+    match os_keyring::Entry::new(KEYRING_SERVICE, &user)?.delete_password() {
+        Ok(()) => Ok(true),
+        Err(os_keyring::Error::NoEntry) => Ok(false),
+        Err(err) => Err(format!("Keyring entry {} cannot be deleted: {}", user, err).into())
+    }
+}
+
+#[cfg(not(feature = "keyring"))]
+pub fn read_keyring_secret(network: &str, entry: &str) -> Result<Option<SecretString>> {
+    keyring_user(network, entry)?;
+    Err("The keyring requires the keyring feature.".into())
+}
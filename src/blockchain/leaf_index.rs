@@ -0,0 +1,162 @@
+//! # Leaf Index
+//!
+//! The leaves of the Merkle tree of a poll are pushed in a fixed order: the
+//! ballot question, a leaf per voter by roster position, a leaf per roster
+//! chunk, the audited ballots, then the column 1 and column 3 cells of every
+//! row of every plane, rows in serial order, and the padding. `LeafIndex`
+//! records the sections of that order as the tree is built, and is stored
+//! next to the tree, so that the leaf of a voter, of a ballot or of a plane
+//! cell is located without rebuilding the leaves:
+//!
+//! * `voter`: the roster position of the voter.
+//! * `roster_chunk`: the chunk number.
+//! * `audited_ballot`: the serial of the ballot.
+//! * `plane_cell`: `<plane>:<row>:<column>`, the plane counted from 1 and
+//!   the row of the unpermuted plane, whose ballot serial is `row / 2`.
+//!
+//! An index is only used with the tree of its root.
+
+use std::fmt;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use serde::{Serialize, Deserialize};
+use crate::Result;
+use crate::ballots::BallotSerial;
+
+/// Category of the leaves of a section of the tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LeafCategory {
+    Question,
+    Voter,
+    RosterChunk,
+    AuditedBallot,
+    PlaneCell,
+    Padding
+}
+
+impl fmt::Display for LeafCategory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match self {
+            LeafCategory::Question => "question",
+            LeafCategory::Voter => "voter",
+            LeafCategory::RosterChunk => "roster_chunk",
+            LeafCategory::AuditedBallot => "audited_ballot",
+            LeafCategory::PlaneCell => "plane_cell",
+            LeafCategory::Padding => "padding"
+        })
+    }
+}
+
+/// Consecutive leaves of one category.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeafSection {
+    pub category: LeafCategory,
+    pub first_leaf: usize,
+    pub leaves: usize,
+    /// Plane of the cells, counted from 1.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub plane: Option<usize>,
+    /// Identifiers of the leaves, when they are not their offset in the
+    /// section.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub identifiers: Vec<String>
+}
+
+/// Leaf of the tree, with its category and identifier.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct LeafEntry {
+    pub leaf: usize,
+    pub category: LeafCategory,
+    pub identifier: String
+}
+
+impl fmt::Display for LeafEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Leaf {}: {} {}", self.leaf, self.category, self.identifier)
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LeafIndex {
+    /// Hex encoded root of the tree, once built.
+    #[serde(default)]
+    pub root: String,
+    pub leaves: usize,
+    pub sections: Vec<LeafSection>
+}
+
+/// Path of the leaf index of the tree stored at `tree_path`.
+pub fn leaf_index_path(tree_path: &Path) -> PathBuf {
+    tree_path.with_extension("leaves.yaml")
+}
+
+impl LeafIndex {
+    /// Record the next `leaves` leaves as a section of `category`.
+    pub fn push(&mut self, category: LeafCategory, leaves: usize, plane: Option<usize>, identifiers: Vec<String>) {
+        if leaves == 0 {
+            return;
+        }
+        self.sections.push(LeafSection { category, first_leaf: self.leaves, leaves, plane, identifiers });
+        self.leaves += leaves;
+    }
+
+    /// Leaf index stored at `path`, if it indexes the tree of `root`.
+    pub fn open_current(path: &Path, root: &str) -> Result<Option<Self>> {
+        match fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.file_type().is_file() => (),
+            _ => return Ok(None)
+        }
+        let index: LeafIndex = serde_yaml::from_reader(File::open(path)?)?;
+        Ok(Some(index).filter(|index| index.root == root))
+    }
+
+    fn section(&self, category: LeafCategory, plane: Option<usize>) -> Option<&LeafSection> {
+        self.sections.iter().find(|section| section.category == category && section.plane == plane)
+    }
+
+    /// Category and identifier of `leaf`.
+    pub fn entry(&self, leaf: usize) -> Option<LeafEntry> {
+        let section = self.sections.iter().find(|section| section.first_leaf <= leaf && leaf < section.first_leaf + section.leaves)?;
+        let offset = leaf - section.first_leaf;
+        let identifier = match (section.category, section.plane) {
+            (_, _) if !section.identifiers.is_empty() => section.identifiers.get(offset)?.clone(),
+            (LeafCategory::Question, _) => "question".to_owned(),
+            (LeafCategory::PlaneCell, Some(plane)) => format!("{}:{}:{}", plane, offset / 2, 1 + 2 * (offset % 2)),
+            _ => offset.to_string()
+        };
+        Some(LeafEntry { leaf, category: section.category, identifier })
+    }
+
+    /// Leaf of the voter at roster `position`.
+    pub fn voter_leaf(&self, position: usize) -> Option<usize> {
+        self.section(LeafCategory::Voter, None)
+            .filter(|section| position < section.leaves)
+            .map(|section| section.first_leaf + position)
+    }
+
+    /// Leaf of the cell of `column` (1 or 3) of the unpermuted `row` of
+    /// `plane`, counted from 1.
+    pub fn plane_cell_leaf(&self, plane: usize, row: usize, column: usize) -> Option<usize> {
+        let offset = match column {
+            1 => 2 * row,
+            3 => 2 * row + 1,
+            _ => return None
+        };
+        self.section(LeafCategory::PlaneCell, Some(plane))
+            .filter(|section| offset < section.leaves)
+            .map(|section| section.first_leaf + offset)
+    }
+
+    /// Leaves of ballot `serial`: its audited ballot leaf, if audited, then
+    /// the cells of its two rows in every plane.
+    pub fn ballot_leaves(&self, serial: BallotSerial) -> Vec<usize> {
+        let audited = self.section(LeafCategory::AuditedBallot, None).and_then(|section| section.identifiers.iter()
+            .position(|identifier| identifier.parse::<BallotSerial>().ok() == Some(serial))
+            .map(|offset| section.first_leaf + offset));
+        let cells = self.sections.iter()
+            .filter(|section| section.category == LeafCategory::PlaneCell && 4 * serial + 4 <= section.leaves)
+            .flat_map(|section| (0..4).map(move |offset| section.first_leaf + 4 * serial + offset));
+        audited.into_iter().chain(cells).collect()
+    }
+}
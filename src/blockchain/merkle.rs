@@ -1,15 +1,17 @@
 use merkletree::merkle::MerkleTree;
 use merkletree::store::{Store, VecStore};
 use merkletree::proof::Proof;
+use memmap2::Mmap;
+use typenum::U0;
 use crate::Result;
+use crate::progress::Progress;
 use crypto::digest::Digest;
 use crypto::sha3::{Sha3, Sha3Mode};
 use merkletree::hash::Algorithm;
 use std::hash::Hasher;
 
-use typenum::U0;
 
-use std::fs::File;
+use std::fs::{self, File};
 use std::io::{Write, Read}; //, BufReader, BufRead};
 
 pub type MerkleRoot = MerkleTree<CryptoSHA3256Hash, CryptoSha3Algorithm, VecStore<CryptoSHA3256Hash>>;
@@ -95,6 +97,11 @@ impl Algorithm<CryptoSHA3256Hash> for CryptoSha3Algorithm {
     }
 }
 
+/// Hash of the leaf of `data`.
+pub fn leaf_hash(data: &str) -> CryptoSHA3256Hash {
+    get_hash(&mut CryptoSha3Algorithm::default(), &data.to_owned())
+}
+
 // Get hash of String of data
 fn get_hash (a: &mut CryptoSha3Algorithm, v: &String) -> [u8; 32] {
     a.reset();
@@ -127,7 +134,19 @@ fn get_leaf_index(t: &MerkleRoot, hash: CryptoSHA3256Hash) -> Result<usize>{
 // Create new tree from array of data
 // Size of data MUST be power of 2
 pub fn new_tree(hashed: CryptoHashData) -> Result<MerkleRoot> {
-    Ok(MerkleTree::from_data(hashed.0)? as MerkleRoot)
+    let mut progress = Progress::new("Hashing tree leaves", "leaves", hashed.0.len());
+    let mut a = CryptoSha3Algorithm::default();
+
+    // Hash each data element, the tree adds the leaf prefix itself
+    let leafs = hashed.0.iter().map(|d| {
+        a.reset();
+        a.write(d.as_bytes());
+        progress.inc(1);
+        a.hash()
+    });
+    let tree = MerkleTree::new(leafs)? as MerkleRoot;
+    progress.finish();
+    Ok(tree)
 }
 
 // Get merkle path for a String of data
@@ -148,30 +167,19 @@ pub fn get_path(t: MerkleRoot, data: String) -> Result<Proof<CryptoSHA3256Hash>>
 
 // Validate proof of inclusion
 pub fn validate(lemma: Vec<String>, path: Vec<usize>, data: String) -> Result<bool> {
-    // Decode hash Strings into [u8; 32] bytes 
-    let lemma: Vec<CryptoSHA3256Hash> = lemma.into_iter().map(|l| {
-        let decode = hex::decode(l).unwrap();
-        *slice_as_hash(&decode)
-    }).collect();
-
-    // Generate Proof struct with given Lemma and Path
-    let proof: Proof<CryptoSHA3256Hash> = Proof::new::<U0, U0>(
-        None,
-        lemma,
-        path,
-    ).unwrap();
-
-    // Return proof result
-    Ok(proof.validate_with_data::<CryptoSha3Algorithm>(&data).unwrap())
+    Ok(seventh_estate_core::proof::validate_proof(&lemma, &path, &data))
 }
 
 // Store tree in YAML file
+// The file is replaced atomically, so that a tree mapped by `MappedTree`
+// is never truncated while it is read.
 pub fn store_tree(tree: &MerkleRoot, path: String) -> Result<()> {
     // Open file for writing
-    let mut output_file = File::create(path)?;
+    let partial_path = format!("{}.partial", path);
+    let mut output_file = File::create(&partial_path)?;
 
     // Get tree data
-    let t_data = tree.data().unwrap();
+    let t_data = tree.data().ok_or("Merkle tree has no leaf data to store")?;
 
     // Serialize tree data (hashes) into Vec of hex encoded strings
     let mut ser_data = Vec::with_capacity(t_data.len());
@@ -180,10 +188,12 @@ pub fn store_tree(tree: &MerkleRoot, path: String) -> Result<()> {
     }
 
     // Load Vec<String> into YAML array
-    let ser_data = serde_yaml::to_string(&ser_data).unwrap();
+    let ser_data = serde_yaml::to_string(&ser_data)?;
 
     // Write YAML array to file
-    Ok(write!(output_file, "{}", ser_data)?)
+    write!(output_file, "{}", ser_data)?;
+    output_file.sync_all()?;
+    Ok(fs::rename(&partial_path, &path)?)
 }
 
 
@@ -215,4 +225,102 @@ pub fn load_tree(path: String) -> Result<MerkleRoot> {
     let reconstructed: MerkleTree<[u8; 32], CryptoSha3Algorithm, VecStore<_>> = MerkleTree::from_data_store(v_store, leafs)?;
 
     Ok(reconstructed)
-}
\ No newline at end of file
+}
+
+
+// Header and length of a hash entry of a tree stored by `store_tree`:
+// `- <hex encoded hash>` on a line of its own.
+const TREE_HEADER: &[u8] = b"---\n";
+const TREE_ENTRY_LENGTH: usize = 2 + 2 * 32 + 1;
+
+/// Merkle tree stored by `store_tree`, mapped in memory. Hashes are only
+/// decoded when a proof needs them, so that proofs are generated without
+/// loading the whole tree.
+pub struct MappedTree {
+    map: Mmap,
+    offset: usize,
+    nodes: usize,
+    leafs: usize
+}
+
+impl MappedTree {
+    pub fn open(path: &str) -> Result<Self> {
+        let file = File::open(path)?;
+        if file.metadata()?.len() == 0 {
+            return Err(format!("{} is empty", path).into());
+        }
+        // The file is replaced, never modified, by `store_tree`.
+        let map = unsafe { Mmap::map(&file)? };
+        let offset = if map.starts_with(TREE_HEADER) { TREE_HEADER.len() } else { 0 };
+        let nodes = (map.len() - offset) / TREE_ENTRY_LENGTH;
+        let leafs = nodes / 2 + 1;
+        if (map.len() - offset) % TREE_ENTRY_LENGTH != 0 || leafs < 2 || !leafs.is_power_of_two() || nodes != 2 * leafs - 1 {
+            return Err(format!("{} is not a Merkle tree stored by this program", path).into());
+        }
+        Ok(MappedTree { map, offset, nodes, leafs })
+    }
+
+    pub fn leafs(&self) -> usize { self.leafs }
+
+    // Hex encoded hash of node `i`, leafs first and root last
+    fn entry(&self, i: usize) -> Result<&[u8]> {
+        let start = self.offset + i * TREE_ENTRY_LENGTH;
+        let entry = &self.map[start..start + TREE_ENTRY_LENGTH];
+        if !entry.starts_with(b"- ") || entry[TREE_ENTRY_LENGTH - 1] != b'\n' {
+            return Err(format!("Malformed Merkle tree node {}", i).into());
+        }
+        Ok(&entry[2..TREE_ENTRY_LENGTH - 1])
+    }
+
+    pub fn hash_at(&self, i: usize) -> Result<CryptoSHA3256Hash> {
+        let mut hash = [0u8; 32];
+        hex::decode_to_slice(self.entry(i)?, &mut hash)?;
+        Ok(hash)
+    }
+
+    pub fn root(&self) -> Result<CryptoSHA3256Hash> {
+        self.hash_at(self.nodes - 1)
+    }
+
+    // Search the leafs for a hash, comparing hex encodings in place
+    fn leaf_index(&self, hash: CryptoSHA3256Hash) -> Result<Option<usize>> {
+        let encoded = hex::encode(hash);
+        for i in 0..self.leafs {
+            if self.entry(i)? == encoded.as_bytes() {
+                return Ok(Some(i));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Proof of inclusion of `data`, as generated by `get_path`, or `None`
+    /// if `data` is not in the tree.
+    pub fn proof(&self, data: &str) -> Result<Option<Proof<CryptoSHA3256Hash>>> {
+        let hash = get_hash(&mut CryptoSha3Algorithm::default(), &data.to_owned());
+        match self.leaf_index(hash)? {
+            Some(index) => Ok(Some(self.proof_at(index)?)),
+            None => Ok(None)
+        }
+    }
+
+    /// Proof of inclusion of leaf `index`.
+    pub fn proof_at(&self, index: usize) -> Result<Proof<CryptoSHA3256Hash>> {
+        if self.leafs <= index {
+            return Err(format!("The tree has no leaf {}", index).into());
+        }
+
+        // Leaf first, then the sibling on every level, root last
+        let mut lemma = vec![self.hash_at(index)?];
+        let mut path = Vec::new();
+        let (mut base, mut width, mut j) = (0, self.leafs, index);
+        while base + 1 < self.nodes {
+            lemma.push(self.hash_at(base + (j ^ 1))?);
+            path.push(j % 2);
+            base += width;
+            width >>= 1;
+            j >>= 1;
+        }
+        lemma.push(self.root()?);
+        Ok(Proof::new::<U0, U0>(None, lemma, path)?)
+    }
+}
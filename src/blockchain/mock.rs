@@ -0,0 +1,327 @@
+//! # Mock Chain
+//!
+//! `MockChain` is an in-memory chain for tests. Every transaction sent is
+//! mined in a block of its own, and blocks can also be added directly.
+//! Requests can be made to fail, and transactions to fail on execution.
+//! Every mined block is final unless a finality policy is set, in which
+//! case the blocks reported safe or finalized are set by the test.
+//! Transactions are signed offline once the test sets the address of the
+//! offline key.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use async_trait::async_trait;
+use chrono::{DateTime, Duration, TimeZone, Utc};
+use crate::Result;
+use super::{check_payload_length, normalize_address, ChainBackend, ChainTransaction, FinalityPolicy, PostedTransaction, Recipient, SignedTransaction, TransactionStatus, UnsignedTransaction};
+
+/// Address transactions are sent from.
+pub const MOCK_ADDRESS: &str = "0x7e5f4552091a69125d5dfcb7b8c2659029395bdf";
+/// Vote address of the mock chain.
+pub const MOCK_VOTE_ADDRESS: &str = "0x0000000000000000000000000000000000000001";
+
+/// Gas used by every transaction.
+const MOCK_GAS: u64 = 21000;
+/// Gas price of every transaction, in wei.
+const MOCK_GAS_PRICE: u64 = 1;
+/// Chain identifier transactions are built for.
+pub const MOCK_CHAIN_ID: u64 = 1337;
+/// Balance of the accounts not given one, in wei.
+const MOCK_BALANCE: u128 = 1_000_000_000_000_000_000;
+
+struct MockTransaction {
+    transaction: ChainTransaction,
+    to: String
+}
+
+struct MockBlock {
+    timestamp: DateTime<Utc>,
+    transactions: Vec<MockTransaction>
+}
+
+struct MockState {
+    blocks: Vec<MockBlock>,
+    /// Requests still to fail.
+    failing_requests: usize,
+    failed_transactions: HashSet<String>,
+    balances: HashMap<String, u128>,
+    sent: u64,
+    finality: FinalityPolicy,
+    /// Block reported safe or finalized.
+    finalized_block: u64,
+    /// Address of the key transactions are signed offline by, if any.
+    offline_sender: Option<String>,
+    /// Address the records of the poll are sent to, if any.
+    poll_address: Option<String>
+}
+
+pub struct MockChain {
+    state: Mutex<MockState>
+}
+
+impl Default for MockChain {
+    fn default() -> Self { MockChain::new() }
+}
+
+impl MockChain {
+    /// Chain with an empty genesis block.
+    pub fn new() -> Self {
+        MockChain {
+            state: Mutex::new(MockState {
+                blocks: vec![MockBlock { timestamp: Utc.timestamp(1_600_000_000, 0), transactions: Vec::new() }],
+                failing_requests: 0,
+                failed_transactions: HashSet::new(),
+                balances: HashMap::new(),
+                sent: 0,
+                finality: FinalityPolicy::default(),
+                finalized_block: 0,
+                offline_sender: None,
+                poll_address: None
+            })
+        }
+    }
+
+    /// Add empty blocks, returning the number of the last one.
+    pub fn add_blocks(&self, count: usize) -> u64 {
+        let mut state = self.state.lock().unwrap();
+        for _ in 0..count {
+            state.mine(Vec::new());
+        }
+        state.latest()
+    }
+
+    /// Mine a transaction from `from` carrying `data`, returning its hash.
+    pub fn submit(&self, from: &str, recipient: Recipient, data: Vec<u8>) -> String {
+        let mut state = self.state.lock().unwrap();
+        let to = state.recipient_address(from, recipient);
+        state.submit(from, &to, data)
+    }
+
+    /// Mine a transaction from `from` to the address `to` carrying `data`,
+    /// returning its hash, e.g. to vote at a vote address handed over to.
+    pub fn submit_to(&self, from: &str, to: &str, data: Vec<u8>) -> String {
+        self.state.lock().unwrap().submit(from, to, data)
+    }
+
+    /// Make the next `count` requests fail.
+    pub fn fail_requests(&self, count: usize) {
+        self.state.lock().unwrap().failing_requests = count;
+    }
+
+    /// Make a mined transaction fail on execution.
+    pub fn fail_transaction(&self, transaction_hash: &str) {
+        self.state.lock().unwrap().failed_transactions.insert(transaction_hash.to_owned());
+    }
+
+    /// Set the balance of an account, in wei.
+    pub fn set_balance(&self, address: &str, balance: u128) {
+        self.state.lock().unwrap().balances.insert(address.to_lowercase(), balance);
+    }
+
+    /// Set the finality policy of the chain.
+    pub fn set_finality(&self, finality: FinalityPolicy) {
+        self.state.lock().unwrap().finality = finality;
+    }
+
+    /// Sign transactions offline with the key of `address`, or with the key
+    /// of `MOCK_ADDRESS` again if none.
+    pub fn sign_offline(&self, address: Option<&str>) {
+        self.state.lock().unwrap().offline_sender = address.map(str::to_owned);
+    }
+
+    /// Send the records of the poll to `address`, or to their sender again
+    /// if none.
+    pub fn set_poll_address(&self, address: Option<&str>) {
+        self.state.lock().unwrap().poll_address = address.map(str::to_owned);
+    }
+
+    /// Report blocks up to `block_number` as safe or finalized.
+    pub fn finalize_block(&self, block_number: u64) {
+        self.state.lock().unwrap().finalized_block = block_number;
+    }
+
+    fn request(&self) -> Result<std::sync::MutexGuard<'_, MockState>> {
+        let mut state = self.state.lock().unwrap();
+        if 0 < state.failing_requests {
+            state.failing_requests -= 1;
+            return Err("Mock chain request failed.".into());
+        }
+        Ok(state)
+    }
+}
+
+impl MockState {
+    fn latest(&self) -> u64 { (self.blocks.len() - 1) as u64 }
+
+    fn recipient_address(&self, from: &str, recipient: Recipient) -> String {
+        match recipient {
+            Recipient::Own => from.to_owned(),
+            Recipient::Poll => self.poll_address.clone().unwrap_or_else(|| from.to_owned()),
+            Recipient::Votes => MOCK_VOTE_ADDRESS.to_owned()
+        }
+    }
+
+    fn final_block(&self) -> u64 {
+        self.finality.final_block(self.latest())
+            .unwrap_or_else(|| self.finalized_block.min(self.latest()))
+    }
+
+    /// Blocks are mined every 15 seconds.
+    fn next_timestamp(&self) -> DateTime<Utc> {
+        self.blocks[self.blocks.len() - 1].timestamp + Duration::seconds(15)
+    }
+
+    fn mine(&mut self, transactions: Vec<MockTransaction>) {
+        let timestamp = self.next_timestamp();
+        self.blocks.push(MockBlock { timestamp, transactions });
+    }
+
+    fn submit(&mut self, from: &str, to: &str, data: Vec<u8>) -> String {
+        self.sent += 1;
+        let transaction_hash = format!("0x{:064x}", self.sent);
+        self.mine_transaction(&transaction_hash, from, to, data);
+        transaction_hash
+    }
+
+    fn mine_transaction(&mut self, transaction_hash: &str, from: &str, to: &str, data: Vec<u8>) {
+        let block_number = self.latest() + 1;
+        let timestamp = self.next_timestamp();
+        self.mine(vec![MockTransaction {
+            transaction: ChainTransaction {
+                transaction_hash: transaction_hash.to_owned(),
+                from: from.to_owned(),
+                block_number,
+                timestamp,
+                gas_price: MOCK_GAS_PRICE,
+                input: data
+            },
+            to: to.to_owned()
+        }]);
+    }
+}
+
+#[async_trait]
+impl ChainBackend for MockChain {
+    // Small windows, so that scans read several of them.
+    fn block_window(&self) -> u64 { 2 }
+
+    fn vote_address(&self) -> Option<String> { Some(MOCK_VOTE_ADDRESS.to_owned()) }
+
+    fn sender_address(&self) -> Option<String> {
+        Some(self.state.lock().unwrap().offline_sender.clone().unwrap_or_else(|| MOCK_ADDRESS.to_owned()))
+    }
+
+    fn signs_offline(&self) -> bool {
+        self.state.lock().unwrap().offline_sender.is_some()
+    }
+
+    async fn latest_block(&self) -> Result<u64> {
+        Ok(self.request()?.latest())
+    }
+
+    async fn final_block(&self) -> Result<u64> {
+        Ok(self.request()?.final_block())
+    }
+
+    async fn balance(&self, address: &str) -> Result<u128> {
+        Ok(*self.request()?.balances.get(&address.to_lowercase()).unwrap_or(&MOCK_BALANCE))
+    }
+
+    async fn gas_price(&self) -> Result<u64> {
+        self.request().map(|_| MOCK_GAS_PRICE)
+    }
+
+    async fn block_timestamps(&self, block_numbers: &[u64]) -> Result<Vec<DateTime<Utc>>> {
+        let state = self.request()?;
+        block_numbers.iter()
+            .map(|&number| state.blocks.get(number as usize)
+                .map(|block| block.timestamp)
+                .ok_or_else(|| format!("Block {} not found.", number).into()))
+            .collect()
+    }
+
+    async fn send_transaction(&self, recipient: Recipient, data: Vec<u8>) -> Result<PostedTransaction> {
+        check_payload_length(&data)?;
+        let mut state = self.request()?;
+        let submitted_at_block = state.latest();
+        Ok(PostedTransaction {
+            transaction_hash: {
+                let to = state.recipient_address(MOCK_ADDRESS, recipient);
+                state.submit(MOCK_ADDRESS, &to, data)
+            },
+            address: MOCK_ADDRESS.to_owned(),
+            submitted_at_block
+        })
+    }
+
+    async fn build_transaction(&self, recipient: Recipient, data: Vec<u8>, purpose: &str) -> Result<UnsignedTransaction> {
+        check_payload_length(&data)?;
+        let state = self.request()?;
+        let from = state.offline_sender.clone().ok_or("No key is kept offline.")?;
+        let nonce = state.blocks.iter()
+            .flat_map(|block| block.transactions.iter())
+            .filter(|mined| normalize_address(&mined.transaction.from) == normalize_address(&from))
+            .count() as u64;
+        Ok(UnsignedTransaction {
+            purpose: purpose.to_owned(),
+            chain_id: MOCK_CHAIN_ID,
+            to: state.recipient_address(&from, recipient),
+            from,
+            nonce,
+            gas: MOCK_GAS,
+            gas_price: MOCK_GAS_PRICE,
+            data: hex::encode(&data),
+            built_at: Utc::now()
+        })
+    }
+
+    async fn broadcast_transaction(&self, signed: &SignedTransaction) -> Result<PostedTransaction> {
+        let mut state = self.request()?;
+        let submitted_at_block = state.latest();
+        let transaction = &signed.transaction;
+        state.mine_transaction(&signed.transaction_hash, &transaction.from, &transaction.to, transaction.input()?);
+        Ok(PostedTransaction {
+            transaction_hash: signed.transaction_hash.clone(),
+            address: transaction.from.clone(),
+            submitted_at_block
+        })
+    }
+
+    async fn transaction(&self, transaction_hash: &str) -> Result<Option<ChainTransaction>> {
+        let state = self.request()?;
+        Ok(state.blocks.iter()
+            .flat_map(|block| block.transactions.iter())
+            .find(|mined| mined.transaction.transaction_hash == transaction_hash)
+            .map(|mined| mined.transaction.clone()))
+    }
+
+    async fn transaction_status(&self, transaction_hash: &str) -> Result<TransactionStatus> {
+        let state = self.request()?;
+        let mined = state.blocks.iter()
+            .flat_map(|block| block.transactions.iter())
+            .find(|mined| mined.transaction.transaction_hash == transaction_hash);
+        let block_number = mined.map(|mined| mined.transaction.block_number);
+        let failed = state.failed_transactions.contains(transaction_hash);
+        Ok(TransactionStatus {
+            transaction_hash: transaction_hash.to_owned(),
+            block_number,
+            confirmations: block_number.map(|number| state.latest() - number + 1).unwrap_or(0),
+            finalized: block_number.is_some_and(|number| number <= state.final_block()),
+            succeeded: block_number.map(|_| !failed),
+            gas_used: block_number.map(|_| MOCK_GAS),
+            gas_price: mined.map(|mined| mined.transaction.gas_price)
+        })
+    }
+
+    async fn transactions_to(&self, address: &str, start: u64, end: u64) -> Result<Vec<ChainTransaction>> {
+        let address = normalize_address(address);
+        let state = self.request()?;
+        Ok(state.blocks.iter()
+            .skip(start as usize)
+            .take((end.saturating_sub(start) + 1) as usize)
+            .flat_map(|block| block.transactions.iter())
+            .filter(|mined| normalize_address(&mined.to) == address)
+            .map(|mined| mined.transaction.clone())
+            .collect())
+    }
+}
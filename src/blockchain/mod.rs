@@ -6,4 +6,71 @@
 pub mod blockchain;
 pub use blockchain::*;
 
-pub mod merkle;
\ No newline at end of file
+pub mod blocking;
+
+pub mod journal;
+pub use journal::*;
+
+pub mod approval;
+pub use approval::*;
+
+pub mod handover;
+pub use handover::*;
+
+pub mod keyring;
+pub use keyring::*;
+
+pub mod finalization;
+pub use finalization::*;
+
+pub mod amendment;
+pub use amendment::*;
+
+pub mod roster_chunk;
+pub use roster_chunk::*;
+
+pub mod spending;
+pub use spending::*;
+
+pub mod balance;
+pub use balance::*;
+
+pub mod timestamps;
+pub use timestamps::*;
+
+pub mod opentimestamps;
+pub use opentimestamps::*;
+
+pub mod backend;
+pub use backend::*;
+
+pub mod finality;
+pub use finality::*;
+
+pub mod node_error;
+pub use node_error::*;
+
+pub mod failover;
+pub use failover::*;
+
+pub mod explorer;
+pub use explorer::*;
+
+pub mod node_export;
+pub use node_export::*;
+
+pub mod offline;
+pub use offline::*;
+
+pub mod mock;
+pub use mock::*;
+
+pub mod merkle;
+pub mod proof_index;
+pub use proof_index::*;
+
+pub mod leaf_index;
+pub use leaf_index::*;
+
+pub mod payload_parts;
+pub use payload_parts::*;
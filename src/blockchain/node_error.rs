@@ -0,0 +1,106 @@
+//! # Node Errors
+//!
+//! `NodeError` sorts the failures of requests to the blockchain node, so
+//! that a rate limited request or a refused API key is reported as such
+//! instead of as the response the node could not be parsed into. Hosted
+//! nodes answer both with an HTTP status or with a JSON-RPC error whose
+//! code and message vary between providers, so both are matched.
+
+use std::error::Error;
+use std::fmt;
+
+/// JSON-RPC error code of requests over the limit of the node.
+const LIMIT_EXCEEDED: i64 = -32005;
+
+const RATE_LIMIT_MESSAGES: [&str; 4] = ["rate limit", "limit exceeded", "too many requests", "exceeded the quota"];
+
+const UNAUTHORIZED_MESSAGES: [&str; 4] = ["unauthorized", "invalid project id", "invalid api key", "forbidden"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum NodeError {
+    /// The node refused the request over a rate limit.
+    RateLimited(String),
+    /// The node refused the API key or project of the node URL.
+    Unauthorized(String),
+    /// The node answered with a JSON-RPC error.
+    Rpc { code: i64, message: String },
+    /// The node answered with a result of an unexpected type, such as a
+    /// message in place of a block.
+    InvalidResponse(String),
+    /// The node could not be reached or did not answer.
+    Transport(String)
+}
+
+impl NodeError {
+    /// Sort a JSON-RPC error of the node.
+    pub fn from_rpc(code: i64, message: &str) -> Self {
+        if code == LIMIT_EXCEEDED || matches_any(message, &RATE_LIMIT_MESSAGES) {
+            NodeError::RateLimited(message.to_owned())
+        } else if matches_any(message, &UNAUTHORIZED_MESSAGES) {
+            NodeError::Unauthorized(message.to_owned())
+        } else {
+            NodeError::Rpc { code, message: message.to_owned() }
+        }
+    }
+
+    /// Sort a transport failure, carrying the HTTP status of the node.
+    pub fn from_transport(message: &str) -> Self {
+        if message.contains("429") || matches_any(message, &RATE_LIMIT_MESSAGES) {
+            NodeError::RateLimited(message.to_owned())
+        } else if message.contains("401") || message.contains("403") || matches_any(message, &UNAUTHORIZED_MESSAGES) {
+            NodeError::Unauthorized(message.to_owned())
+        } else {
+            NodeError::Transport(message.to_owned())
+        }
+    }
+
+    /// Sort the message of a request refused by a block explorer.
+    pub fn from_explorer(message: &str) -> Self {
+        match NodeError::from_transport(message) {
+            NodeError::Transport(_) => NodeError::InvalidResponse(message.to_owned()),
+            refusal => refusal
+        }
+    }
+
+    /// Whether the request may succeed if sent again later.
+    pub fn is_retryable(&self) -> bool {
+        matches!(self, NodeError::RateLimited(_) | NodeError::Transport(_))
+    }
+}
+
+fn matches_any(message: &str, patterns: &[&str]) -> bool {
+    let message = message.to_lowercase();
+    patterns.iter().any(|pattern| message.contains(pattern))
+}
+
+impl From<&web3::Error> for NodeError {
+    fn from(err: &web3::Error) -> Self {
+        match err {
+            web3::Error::Rpc(err) => NodeError::from_rpc(err.code.code(), &err.message),
+            web3::Error::Transport(message) => NodeError::from_transport(message),
+            // A message in place of the result is still a refusal.
+            web3::Error::InvalidResponse(message) | web3::Error::Decoder(message) => NodeError::from_explorer(message),
+            err => NodeError::Transport(err.to_string())
+        }
+    }
+}
+
+impl From<web3::Error> for NodeError {
+    fn from(err: web3::Error) -> Self {
+        NodeError::from(&err)
+    }
+}
+
+impl fmt::Display for NodeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            NodeError::RateLimited(message) => write!(f, "The node rate limited the request ({}): lower requests_per_second or max_concurrent_requests.", message),
+            NodeError::Unauthorized(message) => write!(f, "The node refused the API key of the node URL ({}).", message),
+            NodeError::Rpc { code, message } => write!(f, "The node answered with error {}: {}", code, message),
+            NodeError::InvalidResponse(message) => write!(f, "The node answered with an unexpected result: {}", message),
+            NodeError::Transport(message) => write!(f, "The node could not be reached: {}", message)
+        }
+    }
+}
+
+impl Error for NodeError {}
@@ -0,0 +1,285 @@
+//! # Node Exports
+//!
+//! `node_export` reads the transactions of raw chain data exported from a
+//! node, so that a poll can be audited from files on disk, without a node
+//! or a third-party explorer:
+//!
+//! - `trace-filter`: the result of a `trace_filter` JSON-RPC call of an
+//!   archive node (Erigon, Nethermind, Reth or OpenEthereum), as an array of
+//!   traces, a JSON-RPC response, or several of them one after the other.
+//!   Traces carry whether the call failed, but no timestamps.
+//! - `geth`: blocks written by `geth export`, RLP encoded one after the
+//!   other and optionally gzipped. Blocks carry timestamps, and the senders
+//!   are recovered from the signatures, but not whether calls failed.
+//!
+//! `exported_vote_transactions` selects the transactions sent to the vote
+//! address, following its handovers as when they are read from the chain,
+//! in the records of a transaction cache.
+
+use std::collections::HashSet;
+use std::io::Read;
+use std::str::FromStr;
+use chrono::{DateTime, TimeZone, Utc};
+use flate2::read::GzDecoder;
+use rlp::{Rlp, RlpStream};
+use serde::Deserialize;
+use serde_json::Value;
+use web3::signing::{keccak256, recover};
+use seventh_estate_core::CachedTransaction;
+use crate::Result;
+use super::{find_handover, normalize_address, ChainTransaction};
+
+/// Format of a node export.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeExportFormat { TraceFilter, Geth }
+
+pub const NODE_EXPORT_FORMATS: [&str; 2] = ["trace-filter", "geth"];
+
+impl FromStr for NodeExportFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "trace-filter" => Ok(NodeExportFormat::TraceFilter),
+            "geth" => Ok(NodeExportFormat::Geth),
+            _ => Err(format!("Unknown node export format: {}", value))
+        }
+    }
+}
+
+/// Transaction read from a node export.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExportedTransaction {
+    pub transaction_hash: String,
+    pub from: String,
+    /// Recipient, `None` for a contract creation.
+    pub to: Option<String>,
+    pub block_number: u64,
+    pub timestamp: Option<DateTime<Utc>>,
+    pub input: Vec<u8>,
+    pub succeeded: Option<bool>
+}
+
+/// Read the transactions of a node export.
+pub fn read_node_export(format: NodeExportFormat, data: &[u8]) -> Result<Vec<ExportedTransaction>> {
+    match format {
+        NodeExportFormat::TraceFilter => read_trace_filter(data),
+        NodeExportFormat::Geth => read_geth_export(data)
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Trace {
+    #[serde(rename = "type")]
+    kind: String,
+    action: TraceAction,
+    block_number: u64,
+    transaction_hash: Option<String>,
+    #[serde(default)]
+    trace_address: Vec<u64>,
+    #[serde(default)]
+    error: Option<String>
+}
+
+#[derive(Debug, Deserialize)]
+struct TraceAction {
+    #[serde(default)]
+    from: String,
+    #[serde(default)]
+    to: Option<String>,
+    #[serde(default)]
+    input: String
+}
+
+/// Read the top-level calls of a `trace_filter` dump; calls made by
+/// contracts and block rewards are skipped.
+pub fn read_trace_filter(data: &[u8]) -> Result<Vec<ExportedTransaction>> {
+    let mut traces: Vec<Trace> = Vec::new();
+    for value in serde_json::Deserializer::from_slice(data).into_iter::<Value>() {
+        let value = match value? {
+            Value::Object(mut response) if response.contains_key("result") => response.remove("result").unwrap_or(Value::Null),
+            value => value
+        };
+        match value {
+            Value::Array(values) => for value in values {
+                traces.push(serde_json::from_value(value)?);
+            },
+            value => traces.push(serde_json::from_value(value)?)
+        }
+    }
+    traces.into_iter()
+        .filter(|trace| trace.kind == "call" && trace.trace_address.is_empty())
+        .filter_map(|trace| Some((trace.transaction_hash.clone()?, trace)))
+        .map(|(transaction_hash, trace)| Ok(ExportedTransaction {
+            input: hex::decode(trace.action.input.trim_start_matches("0x"))
+                .map_err(|_| format!("Trace of {} has invalid input", transaction_hash))?,
+            transaction_hash,
+            from: trace.action.from,
+            to: trace.action.to,
+            block_number: trace.block_number,
+            timestamp: None,
+            succeeded: Some(trace.error.is_none())
+        }))
+        .collect()
+}
+
+/// Read the transactions of the blocks of a `geth export` file.
+pub fn read_geth_export(data: &[u8]) -> Result<Vec<ExportedTransaction>> {
+    let mut decompressed = Vec::new();
+    let data = match data.starts_with(&[0x1f, 0x8b]) {
+        true => {
+            GzDecoder::new(data).read_to_end(&mut decompressed)?;
+            &decompressed[..]
+        },
+        false => data
+    };
+    let mut transactions = Vec::new();
+    let mut position = 0;
+    while position < data.len() {
+        let length = Rlp::new(&data[position..]).payload_info()?.total();
+        if data.len() - position < length {
+            return Err("Block export ends unexpectedly".into());
+        }
+        let block = Rlp::new(&data[position..position + length]);
+        position += length;
+        let header = block.at(0)?;
+        let block_number: u64 = header.val_at(8)?;
+        let timestamp: u64 = header.val_at(11)?;
+        for transaction in block.at(1)?.iter() {
+            transactions.push(read_block_transaction(&transaction, block_number, Utc.timestamp(timestamp as i64, 0))
+                .map_err(|err| format!("Block {}: {}", block_number, err))?);
+        }
+    }
+    Ok(transactions)
+}
+
+pub(super) fn read_block_transaction(item: &Rlp, block_number: u64, timestamp: DateTime<Utc>) -> Result<ExportedTransaction> {
+    // Typed transactions are a byte string of the type and the RLP list of
+    // their fields, legacy transactions the list itself.
+    let (kind, encoded) = match item.is_list() {
+        true => (None, item.as_raw()),
+        false => {
+            let encoded = item.data()?;
+            (Some(*encoded.first().ok_or("Empty transaction")?), encoded)
+        }
+    };
+    let fields = Rlp::new(match kind {
+        Some(_) => &encoded[1..],
+        None => encoded
+    });
+    let to_field = match kind {
+        None => 3,
+        Some(1) => 4,
+        Some(2) | Some(3) | Some(4) => 5,
+        Some(kind) => return Err(format!("Unknown transaction type {}", kind).into())
+    };
+    let count = fields.item_count()?;
+    if count < to_field + 6 {
+        return Err("Transaction has too few fields".into());
+    }
+
+    // The sender signed the hash of the fields before the signature.
+    let signed = count - 3;
+    let v: u64 = fields.val_at(signed)?;
+    let mut stream = RlpStream::new_list(match (kind, 35 <= v) {
+        (None, true) => signed + 3,
+        _ => signed
+    });
+    for field in 0..signed {
+        stream.append_raw(fields.at(field)?.as_raw(), 1);
+    }
+    let (signing_payload, recovery_id) = match kind {
+        // EIP-155 signatures also sign the chain identifier.
+        None if 35 <= v => {
+            stream.append(&((v - 35) / 2)).append_empty_data().append_empty_data();
+            (stream.out().to_vec(), (v - 35) % 2)
+        },
+        None => (stream.out().to_vec(), v.wrapping_sub(27)),
+        Some(kind) => ([&[kind][..], &stream.out()].concat(), v)
+    };
+    let mut signature = [0u8; 64];
+    for (n, field) in [signed + 1, signed + 2].iter().enumerate() {
+        let value = fields.at(*field)?.data()?;
+        if 32 < value.len() {
+            return Err("Transaction has an invalid signature".into());
+        }
+        signature[(n + 1) * 32 - value.len()..(n + 1) * 32].copy_from_slice(value);
+    }
+    let from = recover(&keccak256(&signing_payload), &signature, recovery_id as i32)
+        .map_err(|_| "Transaction has an invalid signature")?;
+
+    let to = fields.at(to_field)?.data()?;
+    Ok(ExportedTransaction {
+        transaction_hash: format!("0x{}", hex::encode(keccak256(encoded))),
+        from: format!("0x{}", hex::encode(from.as_bytes())),
+        to: match to.is_empty() {
+            true => None,
+            false => Some(format!("0x{}", hex::encode(to)))
+        },
+        block_number,
+        timestamp: Some(timestamp),
+        input: fields.at(to_field + 2)?.data()?.to_vec(),
+        succeeded: None
+    })
+}
+
+impl ExportedTransaction {
+    fn chain_transaction(&self) -> ChainTransaction {
+        ChainTransaction {
+            transaction_hash: self.transaction_hash.clone(),
+            from: self.from.clone(),
+            block_number: self.block_number,
+            timestamp: self.timestamp.unwrap_or_else(|| Utc.timestamp(0, 0)),
+            gas_price: 0,
+            input: self.input.clone()
+        }
+    }
+}
+
+/// Transactions of a node export sent to the vote address since block
+/// `from_block` and up to `end_block`, if given, following its handovers
+/// from `vote_address`, as transaction cache records in block order.
+pub fn exported_vote_transactions(exported: &[ExportedTransaction], vote_address: &str, from_block: u64, end_block: Option<u64>) -> Result<Vec<CachedTransaction>> {
+    let mut address = normalize_address(vote_address).ok_or_else(|| format!("Invalid vote address {}", vote_address))?;
+    let mut seen = HashSet::new();
+    let exported: Vec<&ExportedTransaction> = exported.iter()
+        .filter(|transaction| seen.insert(transaction.transaction_hash.to_lowercase()))
+        .filter(|transaction| end_block.is_none_or(|end_block| transaction.block_number <= end_block))
+        .collect();
+    let mut selected: Vec<&ExportedTransaction> = Vec::new();
+    let mut handovers = Vec::new();
+    let mut start = from_block;
+    loop {
+        let read: Vec<&ExportedTransaction> = exported.iter()
+            .filter(|transaction| start <= transaction.block_number)
+            .filter(|transaction| transaction.to.as_deref().and_then(normalize_address).as_ref() == Some(&address))
+            .copied()
+            .collect();
+        let chain_transactions: Vec<ChainTransaction> = read.iter().map(|transaction| transaction.chain_transaction()).collect();
+        match find_handover(&address, &chain_transactions, &handovers) {
+            Some(handover) => {
+                selected.extend(read.into_iter()
+                    .filter(|transaction| transaction.block_number <= handover.block_number)
+                    .filter(|transaction| transaction.transaction_hash != handover.transaction_hash));
+                start = handover.block_number;
+                address = handover.to.clone();
+                handovers.push(handover);
+            },
+            None => {
+                selected.extend(read);
+                break;
+            }
+        }
+    }
+    selected.sort_by_key(|transaction| transaction.block_number);
+    Ok(selected.into_iter()
+        .map(|transaction| CachedTransaction {
+            transaction_hash: transaction.transaction_hash.clone(),
+            block_number: transaction.block_number,
+            input: hex::encode(&transaction.input),
+            succeeded: transaction.succeeded,
+            timestamp: transaction.timestamp.map(|timestamp| timestamp.timestamp())
+        })
+        .collect())
+}
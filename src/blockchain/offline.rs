@@ -0,0 +1,164 @@
+//! # Offline Signing
+//!
+//! The commit key can be kept on an air-gapped machine that never touches
+//! the network. The network configuration of the online machine then sets
+//! `commit_address`, the address of the key, instead of `commit_key`, and
+//! a commit-key transaction goes through four files and two machines:
+//!
+//! 1. The online machine builds the `UnsignedTransaction`, with the nonce,
+//!    gas, gas price and chain identifier read from the node, and writes it
+//!    to `unsigned_transaction.yaml`.
+//! 2. The offline machine, with `commit_key` in its own network
+//!    configuration, signs it into a `SignedTransaction`, written to
+//!    `signed_transaction.yaml`.
+//! 3. The online machine imports the signed transaction, checking that it
+//!    is the transaction built, signed by the commit address.
+//! 4. The online machine broadcasts it.
+//!
+//! Signing needs no node: the transaction is RLP encoded and signed with
+//! the chain identifier (EIP-155) on the offline machine itself.
+
+use std::fmt;
+use chrono::{DateTime, Utc};
+use rlp::{Rlp, RlpStream};
+use secp256k1::SecretKey;
+use serde::{Serialize, Deserialize};
+use web3::signing::{keccak256, Key, SecretKeyRef};
+use web3::types::U256;
+use crate::Result;
+use super::{normalize_address, read_block_transaction};
+
+pub const UNSIGNED_TRANSACTION_FILENAME: &str = "unsigned_transaction.yaml";
+pub const SIGNED_TRANSACTION_FILENAME: &str = "signed_transaction.yaml";
+
+/// Transaction built on the online machine, to be signed offline.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct UnsignedTransaction {
+    /// What the transaction posts, e.g. the commitment of a poll.
+    pub purpose: String,
+    pub chain_id: u64,
+    pub from: String,
+    pub to: String,
+    pub nonce: u64,
+    pub gas: u64,
+    /// Gas price in wei.
+    pub gas_price: u64,
+    /// Hex encoded input data.
+    pub data: String,
+    pub built_at: DateTime<Utc>
+}
+
+fn address_bytes(address: &str) -> Result<Vec<u8>> {
+    let bytes = hex::decode(address.trim_start_matches("0x"))?;
+    if bytes.len() != 20 {
+        return Err(format!("Not an address: {}", address).into());
+    }
+    Ok(bytes)
+}
+
+impl UnsignedTransaction {
+    pub fn input(&self) -> Result<Vec<u8>> {
+        Ok(hex::decode(&self.data)?)
+    }
+
+    /// RLP encoding of the fields of the transaction, followed by `tail`.
+    fn encode(&self, tail: [U256; 3]) -> Result<Vec<u8>> {
+        let mut stream = RlpStream::new_list(9);
+        stream.append(&self.nonce)
+            .append(&self.gas_price)
+            .append(&self.gas)
+            .append(&address_bytes(&self.to)?)
+            .append(&0u8)
+            .append(&self.input()?);
+        for value in tail.iter() {
+            stream.append(value);
+        }
+        Ok(stream.out().to_vec())
+    }
+
+    /// Sign the transaction with `key`, which must be the key of the sender.
+    pub fn sign(&self, key: &SecretKey) -> Result<SignedTransaction> {
+        let key = SecretKeyRef::new(key);
+        let address = format!("{:?}", key.address());
+        if normalize_address(&address) != normalize_address(&self.from) {
+            return Err(format!("The key holds {}, not the sender {} of the unsigned transaction.", address, self.from).into());
+        }
+        let signing_payload = self.encode([self.chain_id.into(), U256::zero(), U256::zero()])?;
+        let signature = key.sign(&keccak256(&signing_payload), Some(self.chain_id))
+            .map_err(|err| format!("The transaction cannot be signed: {}", err))?;
+        let raw = self.encode([
+            signature.v.into(),
+            U256::from_big_endian(signature.r.as_bytes()),
+            U256::from_big_endian(signature.s.as_bytes())
+        ])?;
+        Ok(SignedTransaction {
+            transaction: self.clone(),
+            transaction_hash: format!("0x{}", hex::encode(keccak256(&raw))),
+            raw_transaction: hex::encode(&raw),
+            signed_at: Utc::now()
+        })
+    }
+}
+
+impl fmt::Display for UnsignedTransaction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Purpose:   {}", self.purpose)?;
+        writeln!(f, "From:      {}", self.from)?;
+        writeln!(f, "To:        {}", self.to)?;
+        writeln!(f, "Chain:     {}", self.chain_id)?;
+        writeln!(f, "Nonce:     {}", self.nonce)?;
+        writeln!(f, "Gas:       {} at {} wei", self.gas, self.gas_price)?;
+        write!(f, "Data:      {}", self.data)
+    }
+}
+
+/// Transaction signed on the offline machine, to be broadcast online.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SignedTransaction {
+    pub transaction: UnsignedTransaction,
+    pub transaction_hash: String,
+    /// Hex encoded raw signed transaction.
+    pub raw_transaction: String,
+    pub signed_at: DateTime<Utc>
+}
+
+impl SignedTransaction {
+    pub fn raw(&self) -> Result<Vec<u8>> {
+        Ok(hex::decode(&self.raw_transaction)?)
+    }
+
+    /// Check that the raw transaction is `unsigned`, signed by its sender.
+    pub fn verify(&self, unsigned: &UnsignedTransaction) -> Result<()> {
+        if self.transaction != *unsigned {
+            return Err("The signed transaction is not the transaction built.".into());
+        }
+        let raw = self.raw()?;
+        let expected = unsigned.encode([U256::zero(); 3])?;
+        let (fields, expected_fields) = (Rlp::new(&raw), Rlp::new(&expected));
+        for field in 0..6 {
+            if fields.at(field)?.as_raw() != expected_fields.at(field)?.as_raw() {
+                return Err("The signed transaction does not carry the fields built.".into());
+            }
+        }
+        let v: u64 = fields.val_at(6)?;
+        if v < 35 || (v - 35) / 2 != unsigned.chain_id {
+            return Err(format!("The transaction is not signed for chain {}.", unsigned.chain_id).into());
+        }
+        let decoded = read_block_transaction(&fields, 0, Utc::now())
+            .map_err(|err| format!("The signed transaction cannot be read: {}", err))?;
+        if normalize_address(&decoded.from) != normalize_address(&unsigned.from) {
+            return Err(format!("The transaction is signed by {}, not by the sender {}.", decoded.from, unsigned.from).into());
+        }
+        if decoded.transaction_hash != self.transaction_hash {
+            return Err("The signed transaction does not have its transaction hash.".into());
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SignedTransaction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "{}", self.transaction)?;
+        write!(f, "Signed:    {}", self.transaction_hash)
+    }
+}
@@ -0,0 +1,525 @@
+//! # OpenTimestamps
+//!
+//! Commitments can also be timestamped with OpenTimestamps, next to the
+//! transaction on the EVM chain: the SHA-256 digest of the commitment
+//! payload is submitted to public calendar servers, which aggregate the
+//! digests they receive into Bitcoin transactions. The proof is stored as
+//! `commit_receipt.ots` next to the commit receipt, in the `.ots` format of
+//! the OpenTimestamps clients, and is completed by upgrading it from the
+//! calendars once their Bitcoin transaction is mined.
+//!
+//! Proofs are read, written and evaluated here; the requests to the
+//! calendars and to the Bitcoin block explorer need the `timestamps`
+//! feature.
+
+use std::fs;
+use std::path::Path;
+use crypto::digest::Digest;
+use crypto::ripemd160::Ripemd160;
+use crypto::sha1::Sha1;
+use crypto::sha3::Sha3;
+use crate::Result;
+#[cfg(feature = "timestamps")]
+use tracing::warn;
+use super::{commitment_payload, CommitReceipt};
+
+pub const OTS_FILENAME: &str = "commit_receipt.ots";
+
+/// Public calendars commitments are submitted to.
+pub const DEFAULT_CALENDARS: [&str; 3] = [
+    "https://alice.btc.calendar.opentimestamps.org",
+    "https://bob.btc.calendar.opentimestamps.org",
+    "https://finney.calendar.eternitywall.com"
+];
+
+/// Esplora API the Bitcoin block headers are read from.
+pub const DEFAULT_ESPLORA: &str = "https://blockstream.info/api";
+
+const HEADER_MAGIC: &[u8] = b"\x00OpenTimestamps\x00\x00Proof\x00\xbf\x89\xe2\xe8\x84\xe8\x92\x94";
+const MAJOR_VERSION: u64 = 1;
+const SHA256_TAG: u8 = 0x08;
+const FORK_TAG: u8 = 0xff;
+const ATTESTATION_TAG: u8 = 0x00;
+const PENDING_TAG: [u8; 8] = [0x83, 0xdf, 0xe3, 0x0d, 0x2e, 0xf9, 0x0c, 0x8e];
+const BITCOIN_TAG: [u8; 8] = [0x05, 0x88, 0x96, 0x0d, 0x73, 0xd7, 0x19, 0x01];
+const MAX_DEPTH: usize = 256;
+const MAX_ARGUMENT_LENGTH: usize = 4096;
+const MAX_PAYLOAD_LENGTH: usize = 8192;
+#[cfg(feature = "timestamps")]
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// Operation from a message to the next commitment.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Op {
+    Append(Vec<u8>),
+    Prepend(Vec<u8>),
+    Sha1,
+    Ripemd160,
+    Sha256,
+    Keccak256,
+    Reverse,
+    Hexlify
+}
+
+fn digest<D: Digest>(mut hasher: D, message: &[u8]) -> Vec<u8> {
+    hasher.input(message);
+    let mut output = vec![0u8; hasher.output_bytes()];
+    hasher.result(&mut output);
+    output
+}
+
+impl Op {
+    fn tag(&self) -> u8 {
+        match self {
+            Op::Append(_) => 0xf0,
+            Op::Prepend(_) => 0xf1,
+            Op::Sha1 => 0x02,
+            Op::Ripemd160 => 0x03,
+            Op::Sha256 => SHA256_TAG,
+            Op::Keccak256 => 0x67,
+            Op::Reverse => 0xf2,
+            Op::Hexlify => 0xf3
+        }
+    }
+
+    pub fn apply(&self, message: &[u8]) -> Vec<u8> {
+        match self {
+            Op::Append(argument) => [message, argument].concat(),
+            Op::Prepend(argument) => [argument, message].concat(),
+            Op::Sha1 => digest(Sha1::new(), message),
+            Op::Ripemd160 => digest(Ripemd160::new(), message),
+            Op::Sha256 => sha256(message).to_vec(),
+            Op::Keccak256 => digest(Sha3::keccak256(), message),
+            Op::Reverse => message.iter().rev().copied().collect(),
+            Op::Hexlify => hex::encode(message).into_bytes()
+        }
+    }
+
+    fn read(tag: u8, reader: &mut Reader) -> Result<Self> {
+        Ok(match tag {
+            0xf0 => Op::Append(reader.varbytes(MAX_ARGUMENT_LENGTH)?),
+            0xf1 => Op::Prepend(reader.varbytes(MAX_ARGUMENT_LENGTH)?),
+            0x02 => Op::Sha1,
+            0x03 => Op::Ripemd160,
+            SHA256_TAG => Op::Sha256,
+            0x67 => Op::Keccak256,
+            0xf2 => Op::Reverse,
+            0xf3 => Op::Hexlify,
+            _ => return Err(format!("Unknown timestamp operation {:#04x}", tag).into())
+        })
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        out.push(self.tag());
+        if let Op::Append(argument) | Op::Prepend(argument) = self {
+            write_varbytes(out, argument);
+        }
+    }
+}
+
+/// SHA-256 digest of `data`.
+pub fn sha256(data: &[u8]) -> [u8; 32] {
+    use sha2::{Digest, Sha256};
+    let mut digest = [0u8; 32];
+    digest.copy_from_slice(&Sha256::digest(data));
+    digest
+}
+
+/// Claim that a commitment existed at some time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Attestation {
+    /// Submitted to the calendar at this URL, not in Bitcoin yet.
+    Pending(String),
+    /// Merkle root of the Bitcoin block at this height.
+    Bitcoin(u64),
+    Unknown { tag: [u8; 8], payload: Vec<u8> }
+}
+
+impl Attestation {
+    fn read(reader: &mut Reader) -> Result<Self> {
+        let mut tag = [0u8; 8];
+        tag.copy_from_slice(reader.bytes(8)?);
+        let payload = reader.varbytes(MAX_PAYLOAD_LENGTH)?;
+        let mut payload_reader = Reader::new(&payload);
+        Ok(match tag {
+            PENDING_TAG => Attestation::Pending(String::from_utf8(payload_reader.varbytes(MAX_PAYLOAD_LENGTH)?)?),
+            BITCOIN_TAG => Attestation::Bitcoin(payload_reader.varuint()?),
+            _ => Attestation::Unknown { tag, payload }
+        })
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        let (tag, payload) = match self {
+            Attestation::Pending(url) => {
+                let mut payload = Vec::new();
+                write_varbytes(&mut payload, url.as_bytes());
+                (PENDING_TAG, payload)
+            },
+            Attestation::Bitcoin(height) => {
+                let mut payload = Vec::new();
+                write_varuint(&mut payload, *height);
+                (BITCOIN_TAG, payload)
+            },
+            Attestation::Unknown { tag, payload } => (*tag, payload.clone())
+        };
+        out.extend_from_slice(&tag);
+        write_varbytes(out, &payload);
+    }
+}
+
+/// Timestamp of a message: the attestations of the message itself, and
+/// the timestamps of the results of operations on it.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Timestamp {
+    pub attestations: Vec<Attestation>,
+    pub ops: Vec<(Op, Timestamp)>
+}
+
+impl Timestamp {
+    /// Read the timestamp of `message`.
+    pub fn from_bytes(data: &[u8], message: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(data);
+        let timestamp = Timestamp::read(&mut reader, message, 0)?;
+        reader.finish()?;
+        Ok(timestamp)
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.write(&mut out);
+        out
+    }
+
+    fn read(reader: &mut Reader, message: &[u8], depth: usize) -> Result<Self> {
+        if MAX_DEPTH < depth {
+            return Err("Timestamp is nested too deep".into());
+        }
+        let mut timestamp = Timestamp::default();
+        loop {
+            let mut tag = reader.byte()?;
+            let fork = tag == FORK_TAG;
+            if fork {
+                tag = reader.byte()?;
+            }
+            if tag == ATTESTATION_TAG {
+                timestamp.attestations.push(Attestation::read(reader)?);
+            } else {
+                let op = Op::read(tag, reader)?;
+                let result = op.apply(message);
+                let next = Timestamp::read(reader, &result, depth + 1)?;
+                timestamp.ops.push((op, next));
+            }
+            if !fork {
+                return Ok(timestamp);
+            }
+        }
+    }
+
+    fn write(&self, out: &mut Vec<u8>) {
+        let items = self.attestations.len() + self.ops.len();
+        for (n, attestation) in self.attestations.iter().enumerate() {
+            if n + 1 < items {
+                out.push(FORK_TAG);
+            }
+            out.push(ATTESTATION_TAG);
+            attestation.write(out);
+        }
+        for (n, (op, next)) in self.ops.iter().enumerate() {
+            if self.attestations.len() + n + 1 < items {
+                out.push(FORK_TAG);
+            }
+            op.write(out);
+            next.write(out);
+        }
+    }
+
+    /// Attestations of the timestamp of `message`, with the commitment
+    /// each of them attests.
+    pub fn attestations(&self, message: &[u8]) -> Vec<(Vec<u8>, Attestation)> {
+        let mut found: Vec<(Vec<u8>, Attestation)> = self.attestations.iter()
+            .map(|attestation| (message.to_vec(), attestation.clone()))
+            .collect();
+        for (op, next) in self.ops.iter() {
+            found.extend(next.attestations(&op.apply(message)));
+        }
+        found
+    }
+
+    /// Merge another timestamp of the same message.
+    pub fn merge(&mut self, other: Timestamp) {
+        for attestation in other.attestations {
+            if !self.attestations.contains(&attestation) {
+                self.attestations.push(attestation);
+            }
+        }
+        for (op, next) in other.ops {
+            match self.ops.iter_mut().find(|(existing, _)| *existing == op) {
+                Some((_, existing)) => existing.merge(next),
+                None => self.ops.push((op, next))
+            }
+        }
+    }
+
+    /// Merge `upgrade`, a timestamp of `commitment`, where the timestamp of
+    /// `message` reaches `commitment`. Returns whether it was reached.
+    pub fn merge_at(&mut self, message: &[u8], commitment: &[u8], upgrade: &Timestamp) -> bool {
+        if message == commitment {
+            self.merge(upgrade.clone());
+            return true;
+        }
+        let mut merged = false;
+        for (op, next) in self.ops.iter_mut() {
+            merged |= next.merge_at(&op.apply(message), commitment, upgrade);
+        }
+        merged
+    }
+}
+
+/// Timestamp of a SHA-256 digest, as stored in `.ots` files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetachedTimestamp {
+    pub digest: [u8; 32],
+    pub timestamp: Timestamp
+}
+
+impl DetachedTimestamp {
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let mut reader = Reader::new(data);
+        if reader.bytes(HEADER_MAGIC.len())? != HEADER_MAGIC {
+            return Err("Not an OpenTimestamps proof".into());
+        }
+        let version = reader.varuint()?;
+        if version != MAJOR_VERSION {
+            return Err(format!("Unsupported OpenTimestamps proof version {}", version).into());
+        }
+        if reader.byte()? != SHA256_TAG {
+            return Err("Only OpenTimestamps proofs of SHA-256 digests are supported".into());
+        }
+        let mut digest = [0u8; 32];
+        digest.copy_from_slice(reader.bytes(32)?);
+        let timestamp = Timestamp::read(&mut reader, &digest, 0)?;
+        reader.finish()?;
+        Ok(DetachedTimestamp { digest, timestamp })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = HEADER_MAGIC.to_vec();
+        write_varuint(&mut out, MAJOR_VERSION);
+        out.push(SHA256_TAG);
+        out.extend_from_slice(&self.digest);
+        self.timestamp.write(&mut out);
+        out
+    }
+
+    pub fn read(path: &Path) -> Result<Self> {
+        DetachedTimestamp::from_bytes(&fs::read(path)?)
+            .map_err(|err| format!("{}: {}", path.display(), err).into())
+    }
+
+    pub fn write(&self, path: &Path) -> Result<()> {
+        Ok(fs::write(path, self.to_bytes())?)
+    }
+
+    pub fn attestations(&self) -> Vec<(Vec<u8>, Attestation)> {
+        self.timestamp.attestations(&self.digest)
+    }
+}
+
+/// Digest of the commitment of a receipt that is timestamped: the SHA-256
+/// digest of the payload of the commitment transaction.
+pub fn commitment_digest(receipt: &CommitReceipt) -> Result<[u8; 32]> {
+    fn hash(value: &str) -> Result<[u8; 32]> {
+        let mut hash = [0u8; 32];
+        hex::decode_to_slice(value, &mut hash)?;
+        Ok(hash)
+    }
+    let root = hash(&receipt.root)?;
+    let payload = match &receipt.question_hash {
+        Some(question_hash) => commitment_payload(&root, &hash(question_hash)?, receipt.poll_uuid.as_ref()),
+        None => root.to_vec()
+    };
+    Ok(sha256(&payload))
+}
+
+/// Client for the calendars and the Bitcoin block explorer.
+#[cfg(feature = "timestamps")]
+pub struct TimestampClient {
+    client: hyper::Client<hyper_tls::HttpsConnector<hyper::client::HttpConnector>>
+}
+
+#[cfg(feature = "timestamps")]
+impl Default for TimestampClient {
+    fn default() -> Self {
+        TimestampClient::new()
+    }
+}
+
+#[cfg(feature = "timestamps")]
+impl TimestampClient {
+    pub fn new() -> Self {
+        let client = hyper::Client::builder().build::<_, hyper::Body>(hyper_tls::HttpsConnector::new());
+        TimestampClient { client }
+    }
+
+    /// Status and body of the response to a request, or `None` for a
+    /// response with status 404.
+    async fn send(&self, method: hyper::Method, url: &str, body: Vec<u8>) -> Result<Option<Vec<u8>>> {
+        let request = hyper::Request::builder()
+            .method(method)
+            .uri(url)
+            .header("Accept", "application/vnd.opentimestamps.v1")
+            .header("User-Agent", "seventh-estate")
+            .body(hyper::Body::from(body))?;
+        let response = tokio::time::timeout(REQUEST_TIMEOUT, self.client.request(request)).await
+            .map_err(|_| format!("{}: request timed out", url))??;
+        if response.status() == hyper::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            return Err(format!("{}: unexpected response status code: {}", url, response.status()).into());
+        }
+        Ok(Some(hyper::body::to_bytes(response.into_body()).await?.to_vec()))
+    }
+
+    /// Submit a digest to a calendar, which returns the timestamp leading
+    /// from the digest to its pending attestation.
+    pub async fn submit_digest(&self, calendar: &str, digest: &[u8; 32]) -> Result<Timestamp> {
+        let url = format!("{}/digest", calendar.trim_end_matches('/'));
+        let body = self.send(hyper::Method::POST, &url, digest.to_vec()).await?
+            .ok_or_else(|| format!("{}: not found", url))?;
+        Timestamp::from_bytes(&body, digest)
+    }
+
+    /// Timestamp a digest with the calendars; calendars that cannot be
+    /// reached are skipped, as long as one of them can.
+    pub async fn stamp(&self, digest: [u8; 32], calendars: &[String]) -> Result<DetachedTimestamp> {
+        let mut timestamp = Timestamp::default();
+        let mut stamped = 0;
+        for calendar in calendars {
+            match self.submit_digest(calendar, &digest).await {
+                Ok(calendar_timestamp) => {
+                    timestamp.merge(calendar_timestamp);
+                    stamped += 1;
+                },
+                Err(err) => warn!(calendar = %calendar, error = %err, "Calendar did not timestamp the commitment")
+            }
+        }
+        if stamped == 0 {
+            return Err("No calendar timestamped the commitment.".into());
+        }
+        Ok(DetachedTimestamp { digest, timestamp })
+    }
+
+    /// Complete the pending attestations of a timestamp from their
+    /// calendars. Returns the number of attestations completed; calendars
+    /// that have not reached Bitcoin yet are left pending.
+    pub async fn upgrade(&self, detached: &mut DetachedTimestamp) -> Result<usize> {
+        let mut upgraded = 0;
+        for (commitment, attestation) in detached.attestations() {
+            let calendar = match attestation {
+                Attestation::Pending(calendar) => calendar,
+                _ => continue
+            };
+            let url = format!("{}/timestamp/{}", calendar.trim_end_matches('/'), hex::encode(&commitment));
+            let body = match self.send(hyper::Method::GET, &url, Vec::new()).await {
+                Ok(Some(body)) => body,
+                Ok(None) => continue,
+                Err(err) => {
+                    warn!(calendar = %calendar, error = %err, "Calendar could not be reached");
+                    continue;
+                }
+            };
+            let upgrade = Timestamp::from_bytes(&body, &commitment)?;
+            if detached.timestamp.merge_at(&detached.digest.clone(), &commitment, &upgrade) {
+                upgraded += 1;
+            }
+        }
+        Ok(upgraded)
+    }
+
+    /// Merkle root of the Bitcoin block at `height`, in the byte order of
+    /// the commitments of attestations.
+    pub async fn bitcoin_merkle_root(&self, esplora: &str, height: u64) -> Result<Vec<u8>> {
+        let esplora = esplora.trim_end_matches('/');
+        let url = format!("{}/block-height/{}", esplora, height);
+        let hash = self.send(hyper::Method::GET, &url, Vec::new()).await?
+            .ok_or_else(|| format!("Bitcoin block {} not found", height))?;
+        let url = format!("{}/block/{}", esplora, String::from_utf8(hash)?.trim());
+        let block = self.send(hyper::Method::GET, &url, Vec::new()).await?
+            .ok_or_else(|| format!("Bitcoin block {} not found", height))?;
+        let block: serde_json::Value = serde_json::from_slice(&block)?;
+        let merkle_root = block["merkle_root"].as_str()
+            .ok_or_else(|| format!("Bitcoin block {} has no merkle root", height))?;
+        let mut merkle_root = hex::decode(merkle_root)?;
+        merkle_root.reverse();
+        Ok(merkle_root)
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    position: usize
+}
+
+impl<'a> Reader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Reader { data, position: 0 }
+    }
+
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8]> {
+        if self.data.len() - self.position < count {
+            return Err("Timestamp ends unexpectedly".into());
+        }
+        let bytes = &self.data[self.position..self.position + count];
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn byte(&mut self) -> Result<u8> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn varuint(&mut self) -> Result<u64> {
+        let mut value: u64 = 0;
+        for shift in (0..64).step_by(7) {
+            let byte = self.byte()?;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("Timestamp integer is too large".into())
+    }
+
+    fn varbytes(&mut self, max_length: usize) -> Result<Vec<u8>> {
+        let length = self.varuint()? as usize;
+        if max_length < length {
+            return Err("Timestamp field is too long".into());
+        }
+        Ok(self.bytes(length)?.to_vec())
+    }
+
+    fn finish(&self) -> Result<()> {
+        match self.position == self.data.len() {
+            true => Ok(()),
+            false => Err("Timestamp is followed by unexpected data".into())
+        }
+    }
+}
+
+fn write_varuint(out: &mut Vec<u8>, mut value: u64) {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+        if value == 0 {
+            out.push(byte);
+            return;
+        }
+        out.push(byte | 0x80);
+    }
+}
+
+fn write_varbytes(out: &mut Vec<u8>, bytes: &[u8]) {
+    write_varuint(out, bytes.len() as u64);
+    out.extend_from_slice(bytes);
+}
@@ -0,0 +1,140 @@
+//! # Multi-Part Payloads
+//!
+//! Nodes refuse transactions beyond the size limit of their transaction
+//! pool (128 KiB for go-ethereum), and every byte of input data costs gas.
+//! Payloads longer than `MAX_PAYLOAD_LENGTH` are refused by the backends,
+//! and `post_payload` posts them in parts instead, each the input data of a
+//! transaction of the same sender, after a linking header:
+//!
+//! ```text
+//! <magic> <digest> <part> <parts> <data>
+//! ```
+//!
+//! * `magic` is `PAYLOAD_PART_MAGIC`.
+//! * `digest` is the SHA3-256 hash of the whole payload, which links the
+//!   parts and checks their reassembly.
+//! * `part` and `parts` are 2 bytes big endian: the number of the part,
+//!   counted from 0, and the number of parts.
+//!
+//! Payloads that fit in a transaction are posted as they are, without a
+//! header. `reassemble_payload` rebuilds a payload from the transactions of
+//! its parts, in whatever order they were mined.
+
+use std::convert::TryInto;
+use crypto::digest::Digest;
+use crypto::sha3::{Sha3, Sha3Mode};
+use crate::Result;
+use super::merkle::CryptoSHA3256Hash;
+use super::{normalize_address, ChainTransaction};
+
+pub const PAYLOAD_PART_MAGIC: &[u8; 8] = b"7EPART01";
+const PAYLOAD_PART_HEADER_LENGTH: usize = 8 + 32 + 2 + 2;
+
+/// Longest input data of a transaction, header included.
+pub const MAX_PAYLOAD_LENGTH: usize = 64 * 1024;
+
+/// Longest payload posted in parts.
+pub const MAX_PARTED_PAYLOAD_LENGTH: usize = u16::MAX as usize * (MAX_PAYLOAD_LENGTH - PAYLOAD_PART_HEADER_LENGTH);
+
+/// Refuse input data longer than a transaction carries.
+pub fn check_payload_length(data: &[u8]) -> Result<()> {
+    match data.len() <= MAX_PAYLOAD_LENGTH {
+        true => Ok(()),
+        false => Err(format!("A payload of {} bytes exceeds the {} bytes a transaction carries: post it in parts.",
+            data.len(), MAX_PAYLOAD_LENGTH).into())
+    }
+}
+
+/// SHA3-256 hash linking the parts of `payload`.
+pub fn payload_digest(payload: &[u8]) -> CryptoSHA3256Hash {
+    let mut hasher = Sha3::new(Sha3Mode::Sha3_256);
+    hasher.input(payload);
+    let mut hash = [0u8; 32];
+    hasher.result(&mut hash);
+    hash
+}
+
+/// Part of a payload posted in parts.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PayloadPart {
+    pub digest: CryptoSHA3256Hash,
+    /// Part number, counted from 0.
+    pub part: usize,
+    pub parts: usize,
+    pub data: Vec<u8>
+}
+
+impl PayloadPart {
+    /// Input data of the transaction of the part.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        [&PAYLOAD_PART_MAGIC[..], &self.digest[..], &(self.part as u16).to_be_bytes(), &(self.parts as u16).to_be_bytes(), &self.data].concat()
+    }
+
+    /// Part carried by the input data of a transaction, if it is one.
+    pub fn read(input: &[u8]) -> Option<Self> {
+        if input.len() <= PAYLOAD_PART_HEADER_LENGTH || !input.starts_with(PAYLOAD_PART_MAGIC) {
+            return None;
+        }
+        let part = u16::from_be_bytes(input[40..42].try_into().ok()?) as usize;
+        let parts = u16::from_be_bytes(input[42..44].try_into().ok()?) as usize;
+        if parts < 2 || parts <= part {
+            return None;
+        }
+        Some(PayloadPart {
+            digest: input[8..40].try_into().ok()?,
+            part,
+            parts,
+            data: input[PAYLOAD_PART_HEADER_LENGTH..].to_vec()
+        })
+    }
+}
+
+/// Input data of the transactions posting `payload`: the payload itself if
+/// it fits in one, its parts otherwise.
+pub fn split_payload(payload: &[u8]) -> Result<Vec<Vec<u8>>> {
+    if payload.len() <= MAX_PAYLOAD_LENGTH {
+        return Ok(vec![payload.to_vec()]);
+    }
+    if MAX_PARTED_PAYLOAD_LENGTH < payload.len() {
+        return Err(format!("A payload of {} bytes exceeds the {} bytes posted in parts.", payload.len(), MAX_PARTED_PAYLOAD_LENGTH).into());
+    }
+    let digest = payload_digest(payload);
+    let chunks: Vec<&[u8]> = payload.chunks(MAX_PAYLOAD_LENGTH - PAYLOAD_PART_HEADER_LENGTH).collect();
+    Ok(chunks.iter().enumerate()
+        .map(|(part, data)| PayloadPart { digest, part, parts: chunks.len(), data: data.to_vec() }.to_bytes())
+        .collect())
+}
+
+/// Payload of the transactions posting it, parts or not. Every part must
+/// be posted by the same sender, once, and the payload must hash to the
+/// digest of its parts.
+pub fn reassemble_payload(transactions: &[ChainTransaction]) -> Result<Vec<u8>> {
+    let first = transactions.first().ok_or("No transactions to reassemble a payload from.")?;
+    let head = match PayloadPart::read(&first.input) {
+        Some(part) => part,
+        None if transactions.len() == 1 => return Ok(first.input.clone()),
+        None => return Err(format!("Transaction {} does not carry a payload part.", first.transaction_hash).into())
+    };
+    let mut parts: Vec<Option<Vec<u8>>> = vec![None; head.parts];
+    for transaction in transactions.iter() {
+        let part = PayloadPart::read(&transaction.input)
+            .ok_or_else(|| format!("Transaction {} does not carry a payload part.", transaction.transaction_hash))?;
+        if part.digest != head.digest || part.parts != head.parts {
+            return Err(format!("Transaction {} carries a part of another payload.", transaction.transaction_hash).into());
+        }
+        if normalize_address(&transaction.from) != normalize_address(&first.from) {
+            return Err(format!("Transaction {} was posted by another sender.", transaction.transaction_hash).into());
+        }
+        if parts[part.part].replace(part.data).is_some() {
+            return Err(format!("Part {} of the payload is posted twice.", part.part).into());
+        }
+    }
+    if let Some(missing) = parts.iter().position(Option::is_none) {
+        return Err(format!("Part {} of the {} parts of the payload is missing.", missing, head.parts).into());
+    }
+    let payload: Vec<u8> = parts.into_iter().flatten().flatten().collect();
+    match payload_digest(&payload) == head.digest {
+        true => Ok(payload),
+        false => Err("The parts of the payload do not hash to their digest.".into())
+    }
+}
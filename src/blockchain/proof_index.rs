@@ -0,0 +1,202 @@
+//! # Proof Index
+//!
+//! Finding the proof of inclusion of a leaf in a `MappedTree` scans the
+//! leaves, which is slow for the trees of large rosters when every voter
+//! looks up a proof. `build_proof_index` precomputes the proofs of every
+//! leaf of a tree into a proof index, stored next to the tree, from which
+//! `ProofIndex` reads a proof with a single hash table lookup:
+//!
+//! ```text
+//! <magic> <leaves> <slots> <root> <slots x leaf number> <leaves x proof>
+//! ```
+//!
+//! * `magic` is `PROOF_INDEX_MAGIC`; `leaves` and `slots` are 8 bytes big
+//!   endian, and `root` is the root of the tree.
+//! * The table has `slots` slots, a power of two at least twice the number
+//!   of leaves, of 4 bytes big endian each: the number of a leaf, counted
+//!   from 1, or 0 for an empty slot. A leaf is stored in the first empty
+//!   slot from the one of the first 8 bytes of its hash.
+//! * The proof of a leaf is its hash followed by the hash of its sibling
+//!   on every level of the tree. The path of the proof is given by the
+//!   number of the leaf.
+//!
+//! An index is only used while its root is the root of the tree, so that a
+//! tree stored again is never served with the proofs of the previous one.
+
+use std::convert::TryInto;
+use std::fs::{self, File};
+use std::io::{BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::Duration;
+use memmap2::Mmap;
+use merkletree::proof::Proof;
+use typenum::U0;
+use crate::Result;
+use crate::progress::Progress;
+use super::merkle::{leaf_hash, CryptoSHA3256Hash, MappedTree};
+
+pub const PROOF_INDEX_MAGIC: &[u8; 8] = b"7EPROOF1";
+const PROOF_INDEX_HEADER_LENGTH: usize = 8 + 8 + 8 + 32;
+const SLOT_LENGTH: usize = 4;
+
+/// Leaves whose proofs are written between two pauses of a throttled
+/// build.
+pub const PROOF_INDEX_CHUNK: usize = 4096;
+
+/// Path of the proof index of the tree stored at `tree_path`.
+pub fn proof_index_path(tree_path: &Path) -> PathBuf {
+    tree_path.with_extension("proofs")
+}
+
+/// Precompute the proofs of every leaf of the tree stored at `tree_path`
+/// into the proof index at `index_path`. After every `PROOF_INDEX_CHUNK`
+/// leaves the build sleeps for `pause`, so that a server building the
+/// index in the background keeps serving requests. The index is replaced
+/// atomically, as the tree is.
+pub fn build_proof_index(tree_path: &Path, index_path: &Path, pause: Duration) -> Result<ProofIndex> {
+    let tree = MappedTree::open(&tree_path.to_string_lossy())?;
+    let leafs = tree.leafs();
+    let depth = leafs.trailing_zeros() as usize;
+    let slots = (2 * leafs).next_power_of_two();
+    if u32::MAX as usize <= leafs {
+        return Err(format!("{} has too many leaves to be indexed", tree_path.display()).into());
+    }
+
+    let partial_path = index_path.with_extension("proofs.partial");
+    let mut writer = BufWriter::new(File::create(&partial_path)?);
+    writer.write_all(PROOF_INDEX_MAGIC)?;
+    writer.write_all(&(leafs as u64).to_be_bytes())?;
+    writer.write_all(&(slots as u64).to_be_bytes())?;
+    writer.write_all(&tree.root()?)?;
+
+    // Leaves with the hash of an earlier leaf, such as the padding, are
+    // proven as the first one.
+    let mut table = vec![0u32; slots];
+    for leaf in 0..leafs {
+        let hash = tree.hash_at(leaf)?;
+        let mut slot = slot_of(&hash, slots);
+        loop {
+            match table[slot] {
+                0 => {
+                    table[slot] = leaf as u32 + 1;
+                    break;
+                },
+                number if tree.hash_at(number as usize - 1)? == hash => break,
+                _ => slot = (slot + 1) % slots
+            }
+        }
+    }
+    table.iter().try_for_each(|number| writer.write_all(&number.to_be_bytes()))?;
+
+    let mut progress = Progress::new("Indexing proofs", "leaves", leafs);
+    for leaf in 0..leafs {
+        writer.write_all(&tree.hash_at(leaf)?)?;
+        let (mut base, mut width, mut j) = (0, leafs, leaf);
+        for _ in 0..depth {
+            writer.write_all(&tree.hash_at(base + (j ^ 1))?)?;
+            base += width;
+            width >>= 1;
+            j >>= 1;
+        }
+        progress.inc(1);
+        if (leaf + 1) % PROOF_INDEX_CHUNK == 0 && !pause.is_zero() {
+            thread::sleep(pause);
+        }
+    }
+    progress.finish();
+    writer.into_inner().map_err(|err| err.into_error())?.sync_all()?;
+    fs::rename(&partial_path, index_path)?;
+    ProofIndex::open(index_path)
+}
+
+fn slot_of(hash: &CryptoSHA3256Hash, slots: usize) -> usize {
+    (u64::from_be_bytes(hash[..8].try_into().unwrap()) % slots as u64) as usize
+}
+
+/// Proof index built by `build_proof_index`, mapped in memory.
+pub struct ProofIndex {
+    map: Mmap,
+    leafs: usize,
+    slots: usize,
+    depth: usize
+}
+
+impl ProofIndex {
+    pub fn open(path: &Path) -> Result<Self> {
+        let malformed = || format!("{} is not a proof index built by this program", path.display());
+        let file = File::open(path)?;
+        if (file.metadata()?.len() as usize) < PROOF_INDEX_HEADER_LENGTH {
+            return Err(malformed().into());
+        }
+        // The file is replaced, never modified, by `build_proof_index`.
+        let map = unsafe { Mmap::map(&file)? };
+        let number = |at: usize| u64::from_be_bytes(map[at..at + 8].try_into().unwrap()) as usize;
+        let (leafs, slots) = (number(8), number(16));
+        if !map.starts_with(PROOF_INDEX_MAGIC) || leafs < 2 || !leafs.is_power_of_two() || slots < 2 * leafs || !slots.is_power_of_two() {
+            return Err(malformed().into());
+        }
+        let depth = leafs.trailing_zeros() as usize;
+        let length = slots.checked_mul(SLOT_LENGTH)
+            .and_then(|table| leafs.checked_mul((depth + 1) * 32).and_then(|proofs| proofs.checked_add(table)))
+            .and_then(|length| length.checked_add(PROOF_INDEX_HEADER_LENGTH));
+        if length != Some(map.len()) {
+            return Err(malformed().into());
+        }
+        Ok(ProofIndex { map, leafs, slots, depth })
+    }
+
+    /// Proof index at `path` if it is a file indexing the proofs of `tree`,
+    /// or `None` if there is none or it indexes another tree.
+    pub fn open_current(path: &Path, tree: &MappedTree) -> Result<Option<Self>> {
+        match fs::symlink_metadata(path) {
+            Ok(metadata) if metadata.file_type().is_file() => (),
+            _ => return Ok(None)
+        }
+        let index = ProofIndex::open(path)?;
+        match index.leafs == tree.leafs() && index.root() == tree.root()? {
+            true => Ok(Some(index)),
+            false => Ok(None)
+        }
+    }
+
+    pub fn leafs(&self) -> usize { self.leafs }
+
+    pub fn root(&self) -> CryptoSHA3256Hash {
+        self.map[24..PROOF_INDEX_HEADER_LENGTH].try_into().unwrap()
+    }
+
+    fn hash(&self, at: usize) -> CryptoSHA3256Hash {
+        self.map[at..at + 32].try_into().unwrap()
+    }
+
+    /// Proof of inclusion of `data`, as generated by `MappedTree::proof`,
+    /// or `None` if `data` is not in the tree.
+    pub fn proof(&self, data: &str) -> Result<Option<Proof<CryptoSHA3256Hash>>> {
+        let hash = leaf_hash(data);
+        let record_length = (self.depth + 1) * 32;
+        let records = PROOF_INDEX_HEADER_LENGTH + self.slots * SLOT_LENGTH;
+        let mut slot = slot_of(&hash, self.slots);
+        for _ in 0..self.slots {
+            let at = PROOF_INDEX_HEADER_LENGTH + slot * SLOT_LENGTH;
+            let number = u32::from_be_bytes(self.map[at..at + SLOT_LENGTH].try_into().unwrap()) as usize;
+            if number == 0 {
+                return Ok(None);
+            }
+            if self.leafs < number {
+                return Err(format!("Malformed proof index slot {}", slot).into());
+            }
+            let record = records + (number - 1) * record_length;
+            if self.hash(record) == hash {
+                let lemma: Vec<CryptoSHA3256Hash> = (0..=self.depth)
+                    .map(|level| self.hash(record + level * 32))
+                    .chain(std::iter::once(self.root()))
+                    .collect();
+                let path: Vec<usize> = (0..self.depth).map(|level| ((number - 1) >> level) & 1).collect();
+                return Ok(Some(Proof::new::<U0, U0>(None, lemma, path)?));
+            }
+            slot = (slot + 1) % self.slots;
+        }
+        Ok(None)
+    }
+}
@@ -0,0 +1,99 @@
+//! # Roster Chunks
+//!
+//! Registration may close in batches. Each batch of voters is committed as
+//! a chunk of the roster as soon as it closes: the Merkle root of the roster
+//! leaves of its voters, the same leaves the poll commits to, is posted with
+//! the commit key, so that the early batches are committed without waiting
+//! for late registration. The commitment of the poll then binds every chunk
+//! root: it has one leaf per chunk after the roster leaves, and is refused if
+//! the roster no longer hashes to the roots posted.
+
+use std::fmt;
+use crypto::digest::Digest;
+use crypto::sha3::{Sha3, Sha3Mode};
+use serde::{Serialize, Deserialize};
+use chrono::{DateTime, Utc};
+use crate::Result;
+use super::merkle::{new_tree, CryptoHashData, CryptoSHA3256Hash};
+
+pub const ROSTER_CHUNKS_FILENAME: &str = "roster_chunks.yaml";
+
+/// Batch of voters of the roster and the transaction committing to them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RosterChunk {
+    /// Chunk number, counted from 0.
+    pub index: usize,
+    /// Roster position of the first voter of the chunk.
+    pub first_position: usize,
+    pub voters: usize,
+    /// Hex encoded Merkle root of the roster leaves of the chunk.
+    pub root: String,
+    /// Hex encoded hash posted to the blockchain.
+    pub chunk_hash: String,
+    pub transaction_hash: String,
+    pub address: String,
+    pub submitted_at_block: u64,
+    pub posted_at: DateTime<Utc>
+}
+
+impl RosterChunk {
+    /// Leaf of the chunk in the Merkle tree of the poll.
+    pub fn leaf(&self) -> String {
+        format!("7E:ROSTER:{}:{}:{}:{}", self.index, self.first_position, self.voters, self.root)
+    }
+}
+
+impl fmt::Display for RosterChunk {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Roster chunk:   {}", self.index)?;
+        writeln!(f, "Voters:         {} (positions {} to {})", self.voters, self.first_position, self.first_position + self.voters - 1)?;
+        writeln!(f, "Root:           {}", self.root)?;
+        writeln!(f, "Transaction:    {}", self.transaction_hash)?;
+        write!(f, "Block:          {}", self.submitted_at_block)
+    }
+}
+
+/// Merkle root of the roster leaves of a chunk.
+pub fn roster_chunk_root(leaves: &[String]) -> Result<CryptoSHA3256Hash> {
+    if leaves.is_empty() {
+        return Err("A roster chunk has at least one voter.".into());
+    }
+    let mut data = CryptoHashData::new(leaves.to_vec());
+    data.pad();
+    Ok(new_tree(data)?.root())
+}
+
+/// SHA3-256 hash committing to a chunk of the roster of the poll.
+pub fn roster_chunk_hash(poll_identifier: &str, index: usize, first_position: usize, voters: usize, root: &CryptoSHA3256Hash) -> CryptoSHA3256Hash {
+    let chunk = format!("7E:ROSTER:{}:{}:{}:{}:{}", poll_identifier, index, first_position, voters, hex::encode(root));
+    let mut hasher = Sha3::new(Sha3Mode::Sha3_256);
+    hasher.input(chunk.as_bytes());
+    let mut hash = [0u8; 32];
+    hasher.result(&mut hash);
+    hash
+}
+
+/// Check that `chunks` cover the roster of `leaves` in order, and that every
+/// chunk still hashes to the root posted. A roster bound whole has no
+/// chunks.
+pub fn check_roster_chunks(chunks: &[RosterChunk], leaves: &[String]) -> Result<()> {
+    if chunks.is_empty() {
+        return Ok(());
+    }
+    let mut position = 0;
+    for (n, chunk) in chunks.iter().enumerate() {
+        if chunk.index != n || chunk.first_position != position {
+            return Err(format!("Roster chunk {} does not follow chunk {}.", chunk.index, n.saturating_sub(1)).into());
+        }
+        let chunk_leaves = leaves.get(position..position + chunk.voters)
+            .ok_or_else(|| format!("Roster chunk {} has voters beyond the roster.", chunk.index))?;
+        if hex::encode(roster_chunk_root(chunk_leaves)?) != chunk.root {
+            return Err(format!("The voters of roster chunk {} no longer hash to the root posted.", chunk.index).into());
+        }
+        position += chunk.voters;
+    }
+    if position != leaves.len() {
+        return Err(format!("{} voters of the roster are not in a roster chunk.", leaves.len() - position).into());
+    }
+    Ok(())
+}
@@ -0,0 +1,184 @@
+//! # Gas Spending
+//!
+//! Every transaction the tool sends for a poll (the commitment, relayed
+//! votes, handovers, amendments and the finalization) is recorded in
+//! `transactions_sent.jsonl` in the data directory of the poll, one JSON
+//! record per line. `SpendReport` adds up the gas used and the ether spent
+//! by these transactions, per phase and per sending account, from their
+//! receipts on the chain, so that the spending can be budgeted and
+//! reconciled with the funding wallet.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use crate::Result;
+use super::TransactionStatus;
+
+pub const SPENDING_LOG_FILENAME: &str = "transactions_sent.jsonl";
+
+const WEI_PER_ETHER: u128 = 1_000_000_000_000_000_000;
+
+/// Phase of the poll a transaction was sent in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SpendPhase {
+    Commit,
+    Vote,
+    Handover,
+    Amendment,
+    Roster,
+    Finalization
+}
+
+impl fmt::Display for SpendPhase {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(match self {
+            SpendPhase::Commit => "commit",
+            SpendPhase::Vote => "vote",
+            SpendPhase::Handover => "handover",
+            SpendPhase::Amendment => "amendment",
+            SpendPhase::Roster => "roster",
+            SpendPhase::Finalization => "finalization"
+        })
+    }
+}
+
+/// Transaction sent by the tool, as recorded in the spending log.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SentTransaction {
+    pub phase: SpendPhase,
+    pub transaction_hash: String,
+    /// Address of the sending account.
+    pub address: String,
+    pub sent_at: DateTime<Utc>
+}
+
+/// Record a transaction sent from `address` for the poll of
+/// `datadir_path`.
+pub fn record_sent_transaction(datadir_path: &str, phase: SpendPhase, transaction_hash: &str, address: &str) -> Result<()> {
+    let record = SentTransaction {
+        phase,
+        transaction_hash: transaction_hash.to_owned(),
+        address: address.to_owned(),
+        sent_at: Utc::now()
+    };
+    let mut log = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(Path::new(datadir_path).join(SPENDING_LOG_FILENAME))?;
+    writeln!(log, "{}", serde_json::to_string(&record)?)?;
+    Ok(())
+}
+
+/// Transactions sent for the poll of `datadir_path`, in the order sent.
+pub fn read_sent_transactions(datadir_path: &str) -> Result<Vec<SentTransaction>> {
+    let path = Path::new(datadir_path).join(SPENDING_LOG_FILENAME);
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    fs::read_to_string(&path)?.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| Ok(serde_json::from_str(line)?))
+        .collect()
+}
+
+/// Ether of an amount of wei, with all 18 decimals.
+pub fn format_ether(wei: u128) -> String {
+    format!("{}.{:018}", wei / WEI_PER_ETHER, wei % WEI_PER_ETHER)
+}
+
+/// Gas used and wei spent by a set of transactions.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Spend {
+    pub transactions: usize,
+    /// Transactions not mined yet, which have not spent anything.
+    pub pending: usize,
+    pub gas_used: u64,
+    pub spent_wei: u128
+}
+
+impl Spend {
+    /// Add a transaction, with its status if it could be read.
+    pub fn add(&mut self, status: Option<&TransactionStatus>) {
+        self.transactions += 1;
+        match status.and_then(|status| status.gas_used.zip(status.gas_price)) {
+            Some((gas_used, gas_price)) => {
+                self.gas_used += gas_used;
+                self.spent_wei += u128::from(gas_used) * u128::from(gas_price);
+            },
+            None => self.pending += 1
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PhaseSpend {
+    pub phase: SpendPhase,
+    pub spend: Spend
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AccountSpend {
+    pub address: String,
+    pub spend: Spend
+}
+
+/// Spending of the transactions sent for a poll.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SpendReport {
+    pub poll_identifier: String,
+    pub phases: Vec<PhaseSpend>,
+    pub accounts: Vec<AccountSpend>,
+    pub total: Spend
+}
+
+impl SpendReport {
+    /// Add up the sent transactions, with the statuses read from the chain
+    /// in the same order.
+    pub fn new(poll_identifier: &str, sent: &[SentTransaction], statuses: &[TransactionStatus]) -> Self {
+        let mut phases: BTreeMap<SpendPhase, Spend> = BTreeMap::new();
+        let mut accounts: BTreeMap<String, Spend> = BTreeMap::new();
+        let mut total = Spend::default();
+        for (n, transaction) in sent.iter().enumerate() {
+            let status = statuses.get(n).filter(|status| status.transaction_hash == transaction.transaction_hash);
+            phases.entry(transaction.phase).or_default().add(status);
+            accounts.entry(transaction.address.to_lowercase()).or_default().add(status);
+            total.add(status);
+        }
+        SpendReport {
+            poll_identifier: poll_identifier.to_owned(),
+            phases: phases.into_iter().map(|(phase, spend)| PhaseSpend { phase, spend }).collect(),
+            accounts: accounts.into_iter().map(|(address, spend)| AccountSpend { address, spend }).collect(),
+            total
+        }
+    }
+}
+
+impl fmt::Display for Spend {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ETH, {} gas in {} transactions", format_ether(self.spent_wei), self.gas_used, self.transactions)?;
+        if 0 < self.pending {
+            write!(f, " ({} pending)", self.pending)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for SpendReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Poll:     {}", self.poll_identifier)?;
+        writeln!(f, "Phases:")?;
+        for phase in self.phases.iter() {
+            writeln!(f, "  {:<13} {}", phase.phase, phase.spend)?;
+        }
+        writeln!(f, "Accounts:")?;
+        for account in self.accounts.iter() {
+            writeln!(f, "  {} {}", account.address, account.spend)?;
+        }
+        write!(f, "Total:    {}", self.total)
+    }
+}
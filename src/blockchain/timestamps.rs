@@ -0,0 +1,75 @@
+//! # Block Timestamps
+//!
+//! `BlockTimestamps` caches the timestamps of the blocks votes were mined
+//! in, in `block_timestamps.json` in the data directory of the poll. The
+//! cache is seeded with the timestamps read with the votes, and the blocks
+//! still missing are read in batches of `BLOCK_TIMESTAMP_BATCH` blocks
+//! rather than with one request per vote.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::Path;
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use crate::Result;
+use super::blocking;
+
+pub const BLOCK_TIMESTAMPS_FILENAME: &str = "block_timestamps.json";
+
+/// Blocks read by each batch of requests.
+pub const BLOCK_TIMESTAMP_BATCH: usize = 100;
+
+/// Timestamps of blocks, by block number.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockTimestamps(BTreeMap<u64, DateTime<Utc>>);
+
+impl BlockTimestamps {
+    /// Cache of the poll of `datadir_path`, empty if not written yet.
+    pub fn open(datadir_path: &str) -> Result<Self> {
+        let path = Path::new(datadir_path).join(BLOCK_TIMESTAMPS_FILENAME);
+        match path.exists() {
+            true => Ok(serde_json::from_reader(File::open(&path)?)?),
+            false => Ok(BlockTimestamps::default())
+        }
+    }
+
+    pub fn save(&self, datadir_path: &str) -> Result<()> {
+        serde_json::to_writer(File::create(Path::new(datadir_path).join(BLOCK_TIMESTAMPS_FILENAME))?, self)?;
+        Ok(())
+    }
+
+    pub fn get(&self, block_number: u64) -> Option<DateTime<Utc>> {
+        self.0.get(&block_number).copied()
+    }
+
+    pub fn insert(&mut self, block_number: u64, timestamp: DateTime<Utc>) {
+        self.0.insert(block_number, timestamp);
+    }
+
+    pub fn len(&self) -> usize { self.0.len() }
+
+    pub fn is_empty(&self) -> bool { self.0.is_empty() }
+
+    /// Blocks of `block_numbers` not cached, in order and once each.
+    pub fn missing(&self, block_numbers: &[u64]) -> Vec<u64> {
+        let mut missing: Vec<u64> = block_numbers.iter()
+            .copied()
+            .filter(|block_number| !self.0.contains_key(block_number))
+            .collect();
+        missing.sort_unstable();
+        missing.dedup();
+        missing
+    }
+
+    /// Read the timestamps of the blocks not cached from the chain of
+    /// `network`, returning the number of blocks read.
+    pub fn resolve(&mut self, network: &str, block_numbers: &[u64]) -> Result<usize> {
+        let missing = self.missing(block_numbers);
+        if missing.is_empty() {
+            return Ok(0);
+        }
+        let timestamps = blocking::block_timestamps(network, &missing)?;
+        missing.iter().zip(timestamps).for_each(|(&block_number, timestamp)| self.insert(block_number, timestamp));
+        Ok(missing.len())
+    }
+}
@@ -0,0 +1,124 @@
+//! # Bulletin Board over HTTP
+//!
+//! `serve_board` serves a board directory: `GET /` and `GET /manifest.yaml`
+//! return the manifest, `GET /<artifact>` an artifact with its hash in the
+//! `ETag` and `Digest` headers and its signature in `X-Signature`.
+//!
+//! `mirror_board` downloads a served board into a local directory, checking
+//! the manifest signature and every artifact before anything is written.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use hyper::{Body, Client, Method, Request, Response, Server, StatusCode, Uri};
+use hyper::header::{HeaderValue, ACCESS_CONTROL_ALLOW_ORIGIN, CONTENT_TYPE, ETAG};
+use hyper::service::{make_service_fn, service_fn};
+use hyper_tls::HttpsConnector;
+use tracing::{info, debug};
+use super::*;
+
+/// Serve a board directory on `address` until the process is stopped.
+pub async fn serve_board(board_path: &Path, address: SocketAddr) -> Result<()> {
+    let board_path = Arc::new(board_path.to_path_buf());
+    let make_service = make_service_fn(move |_| {
+        let board_path = board_path.clone();
+        async move {
+            Ok::<_, hyper::Error>(service_fn(move |request| {
+                handle(board_path.clone(), request)
+            }))
+        }
+    });
+    let server = Server::try_bind(&address)?.serve(make_service);
+    info!(address = %address, "Serving bulletin board");
+    server.await?;
+    Ok(())
+}
+
+async fn handle(board_path: Arc<PathBuf>, request: Request<Body>) -> std::result::Result<Response<Body>, hyper::Error> {
+    let name = match request.uri().path().trim_start_matches('/') {
+        "" => MANIFEST_FILENAME,
+        name => name
+    }.to_owned();
+    debug!(method = %request.method(), name = %name, "Request");
+
+    if request.method() != Method::GET {
+        return Ok(empty(StatusCode::METHOD_NOT_ALLOWED));
+    }
+    // Only board files are served, which also keeps requests inside the directory.
+    if !is_board_filename(&name) {
+        return Ok(empty(StatusCode::NOT_FOUND));
+    }
+    let data = match tokio::fs::read(board_path.join(&name)).await {
+        Ok(data) => data,
+        Err(_) => return Ok(empty(StatusCode::NOT_FOUND))
+    };
+
+    let manifest: Option<Manifest> = tokio::fs::read(board_path.join(MANIFEST_FILENAME)).await.ok()
+        .and_then(|manifest| serde_yaml::from_slice(&manifest).ok());
+    let artifact = manifest.as_ref().and_then(|manifest| manifest.artifact(&name));
+
+    let mut response = Response::new(Body::empty());
+    let headers = response.headers_mut();
+    let content_type = mime_guess::from_path(&name).first_or_octet_stream();
+    headers.insert(CONTENT_TYPE, HeaderValue::from_str(content_type.as_ref()).unwrap());
+    headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, HeaderValue::from_static("*"));
+    headers.insert(ETAG, HeaderValue::from_str(&format!("\"{}\"", sha256_hex(&data))).unwrap());
+    headers.insert("Digest", HeaderValue::from_str(&format!("sha-256={}", base64::encode(&Sha256::digest(&data)))).unwrap());
+    if let Some(artifact) = artifact {
+        headers.insert("X-Signature", HeaderValue::from_str(&artifact.signature).unwrap());
+    }
+    *response.body_mut() = Body::from(data);
+    Ok(response)
+}
+
+fn empty(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    response
+}
+
+async fn fetch(client: &Client<HttpsConnector<hyper::client::HttpConnector>>, base_url: &str, name: &str) -> Result<Vec<u8>> {
+    let uri: Uri = format!("{}/{}", base_url.trim_end_matches('/'), name).parse()?;
+    let response = client.get(uri).await?;
+    if !response.status().is_success() {
+        return Err(format!("{} could not be downloaded: {}", name, response.status()).into());
+    }
+    Ok(hyper::body::to_bytes(response.into_body()).await?.to_vec())
+}
+
+/// Download the board served at `base_url` into `board_path`.
+/// The manifest is checked with `public_key` if given.
+pub async fn mirror_board(base_url: &str, board_path: &Path, public_key: Option<&str>) -> Result<BoardVerification> {
+    let client = Client::builder().build::<_, Body>(HttpsConnector::new());
+
+    let serialized_manifest = fetch(&client, base_url, MANIFEST_FILENAME).await?;
+    let signature = fetch(&client, base_url, MANIFEST_SIGNATURE_FILENAME).await?;
+    let (manifest, manifest_valid) = read_manifest(&serialized_manifest, &signature, public_key)?;
+    if !manifest_valid {
+        return Err("Manifest signature is invalid.".into());
+    }
+
+    // Download and check all artifacts before writing the mirror.
+    let verification_key = Base64String(manifest.public_key.clone());
+    let mut artifacts: Vec<(&Artifact, Vec<u8>)> = Vec::new();
+    for artifact in manifest.artifacts.iter() {
+        if !is_public_artifact(&artifact.name) && artifact.name != MERKLE_TREE_FILENAME {
+            return Err(format!("Manifest lists an unexpected artifact: {}", artifact.name).into());
+        }
+        let data = fetch(&client, base_url, &artifact.name).await?;
+        if !verify_artifact(&verification_key, artifact, &data) {
+            return Err(format!("Artifact does not match the manifest: {}", artifact.name).into());
+        }
+        debug!(name = %artifact.name, size = data.len(), "Artifact downloaded");
+        artifacts.push((artifact, data));
+    }
+
+    fs::create_dir_all(board_path)?;
+    for (artifact, data) in artifacts.iter() {
+        fs::write(board_path.join(&artifact.name), data)?;
+    }
+    fs::write(board_path.join(MANIFEST_FILENAME), &serialized_manifest)?;
+    fs::write(board_path.join(MANIFEST_SIGNATURE_FILENAME), &signature)?;
+    info!(artifacts = artifacts.len(), "Bulletin board mirrored");
+
+    verify_board_directory(board_path, Some(&manifest.public_key))
+}
@@ -0,0 +1,253 @@
+//! # Public Bulletin Board
+//!
+//! `bulletin_board` collects the public artifacts of a poll (commitments,
+//! the Merkle tree, the commit receipt, the revealed planes and the tally
+//! report) into a board directory, together with a manifest listing the
+//! SHA-256 hash and signature of every artifact. The manifest itself is
+//! signed with the poll signing key in `manifest.yaml.sig`.
+//!
+//! A board can be served over HTTP and mirrored by third parties; since
+//! every artifact is copied byte for byte, any mirror can be checked
+//! against the manifest signature with `verify_board_directory`, and the
+//! audit of the poll reported from it with `AuditReport`.
+
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use crate::Result;
+use crate::cryptography::{sign, verify, check_artifact, is_signed_artifact, Base64String};
+
+pub mod report;
+pub use report::*;
+
+pub mod recount;
+pub use recount::*;
+
+#[cfg(feature = "server")]
+pub mod http;
+#[cfg(feature = "server")]
+pub use http::*;
+
+pub const MANIFEST_FILENAME: &str = "manifest.yaml";
+pub const MANIFEST_SIGNATURE_FILENAME: &str = "manifest.yaml.sig";
+
+/// Poll data files published on the board.
+const PUBLIC_FILES: [&str; 17] = [
+    "ballot_question.json",
+    "trustee_approval_keys.yaml",
+    "time_lock.yaml",
+    "committed_roster.csv",
+    "committed_summands.yaml",
+    "drawn_summands.yaml",
+    "commit_receipt.yaml",
+    "commit_receipt.ots",
+    "audited_columns.yaml",
+    "committed_summands_revealed.csv",
+    "committed_summands_key.key",
+    "tally.yaml",
+    "finalization_receipt.yaml",
+    "amendments.yaml",
+    "roster_chunks.yaml",
+    "interference_report.yaml",
+    "ballot_map_commitment.yaml"
+];
+
+/// Prefixes of the per-plane poll data files published on the board.
+const PUBLIC_PLANE_FILES: [&str; 4] = [
+    "committed_plane_",
+    "print_audit_plane_",
+    "vote_plane_",
+    "final_plane_"
+];
+
+pub const MERKLE_TREE_FILENAME: &str = "merkle.yaml";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Artifact {
+    pub name: String,
+    pub size: u64,
+    /// Hex encoded SHA-256 hash of the artifact.
+    pub sha256: String,
+    /// Base64 encoded signature of the artifact.
+    pub signature: String
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub poll_identifier: String,
+    /// Base64 encoded poll verification key.
+    pub public_key: String,
+    pub published_at: DateTime<Utc>,
+    pub artifacts: Vec<Artifact>
+}
+
+impl Manifest {
+    pub fn artifact(&self, name: &str) -> Option<&Artifact> {
+        self.artifacts.iter().find(|artifact| artifact.name == name)
+    }
+}
+
+impl fmt::Display for Manifest {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Poll:      {}", self.poll_identifier)?;
+        writeln!(f, "Published: {}", self.published_at.to_rfc3339())?;
+        write!(f, "Artifacts: {}", self.artifacts.len())
+    }
+}
+
+/// Result of checking a board directory against its manifest.
+#[derive(Debug, Clone, Serialize)]
+pub struct BoardVerification {
+    pub poll_identifier: String,
+    pub manifest_valid: bool,
+    pub artifacts: usize,
+    /// Artifacts that are missing, or whose hash or signature do not match.
+    pub invalid_artifacts: Vec<String>
+}
+
+impl BoardVerification {
+    pub fn valid(&self) -> bool {
+        self.manifest_valid && self.invalid_artifacts.is_empty()
+    }
+}
+
+impl fmt::Display for BoardVerification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Poll:      {}", self.poll_identifier)?;
+        writeln!(f, "Manifest:  {}", if self.manifest_valid { "signature valid" } else { "SIGNATURE INVALID" })?;
+        write!(f, "Artifacts: {} ({} invalid)", self.artifacts, self.invalid_artifacts.len())?;
+        for name in self.invalid_artifacts.iter() {
+            write!(f, "\n  ! {}", name)?;
+        }
+        Ok(())
+    }
+}
+
+/// Whether a poll data file is a public artifact.
+pub fn is_public_artifact(name: &str) -> bool {
+    PUBLIC_FILES.contains(&name) || PUBLIC_PLANE_FILES.iter().any(|prefix| {
+        name.strip_prefix(prefix)
+            .and_then(|rest| rest.strip_suffix(".csv"))
+            .map(|rest| {
+                let number = rest.strip_suffix("_keys").unwrap_or(rest);
+                !number.is_empty() && number.chars().all(|c| c.is_ascii_digit())
+            })
+            .unwrap_or(false)
+    })
+}
+
+/// Names that can be served from a board directory.
+pub fn is_board_filename(name: &str) -> bool {
+    name == MANIFEST_FILENAME || name == MANIFEST_SIGNATURE_FILENAME
+        || name == MERKLE_TREE_FILENAME || is_public_artifact(name)
+}
+
+pub fn sha256_hex(data: &[u8]) -> String {
+    hex::encode(Sha256::digest(data))
+}
+
+/// Check an artifact against its manifest entry.
+pub fn verify_artifact(public_key: &Base64String, artifact: &Artifact, data: &[u8]) -> bool {
+    let signature = match base64::decode(&artifact.signature) {
+        Ok(signature) => signature,
+        Err(_) => return false
+    };
+    data.len() as u64 == artifact.size
+        && sha256_hex(data) == artifact.sha256
+        && verify(public_key, data, &signature).unwrap_or(false)
+}
+
+/// Check a serialized manifest against its signature, returning the manifest.
+/// The manifest is checked with `public_key` if given, and with the key it
+/// carries otherwise.
+pub fn read_manifest(manifest: &[u8], signature: &[u8], public_key: Option<&str>) -> Result<(Manifest, bool)> {
+    let parsed: Manifest = serde_yaml::from_slice(manifest)?;
+    let public_key = Base64String(public_key.unwrap_or(&parsed.public_key).to_owned());
+    let signature = base64::decode(String::from_utf8_lossy(signature).trim())?;
+    let valid = public_key.0 == parsed.public_key && verify(&public_key, manifest, &signature)?;
+    Ok((parsed, valid))
+}
+
+/// Copy the public artifacts of a poll into a board directory and write the
+/// signed manifest.
+pub fn publish_board_directory(
+    poll_identifier: &str,
+    datadir_path: &Path,
+    merkle_tree: &Path,
+    board_path: &Path,
+    signing_key: &Base64String,
+    public_key: &Base64String) -> Result<Manifest>
+{
+    let mut sources: Vec<(String, PathBuf)> = Vec::new();
+    for entry in fs::read_dir(datadir_path)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if entry.file_type()?.is_file() && is_public_artifact(&name) {
+            sources.push((name, entry.path()));
+        }
+    }
+    if merkle_tree.exists() {
+        sources.push((MERKLE_TREE_FILENAME.to_owned(), merkle_tree.to_path_buf()));
+    }
+    sources.sort();
+
+    fs::create_dir_all(board_path)?;
+    let mut artifacts: Vec<Artifact> = Vec::new();
+    for (name, source) in sources.iter() {
+        if is_signed_artifact(name) {
+            check_artifact(source)?;
+        }
+        let data = fs::read(source)?;
+        let (data, signature) = sign(signing_key, data)?;
+        fs::write(board_path.join(name), &data)?;
+        artifacts.push(Artifact {
+            name: name.clone(),
+            size: data.len() as u64,
+            sha256: sha256_hex(&data),
+            signature: base64::encode(&signature)
+        });
+    }
+
+    let manifest = Manifest {
+        poll_identifier: poll_identifier.to_owned(),
+        public_key: public_key.0.clone(),
+        published_at: Utc::now(),
+        artifacts
+    };
+    let serialized_manifest = serde_yaml::to_string(&manifest)?;
+    let (serialized_manifest, signature) = sign(signing_key, serialized_manifest.into_bytes())?;
+    fs::write(board_path.join(MANIFEST_FILENAME), &serialized_manifest)?;
+    fs::write(board_path.join(MANIFEST_SIGNATURE_FILENAME), base64::encode(&signature))?;
+    Ok(manifest)
+}
+
+/// Check every artifact of a board directory against the signed manifest.
+pub fn verify_board_directory(board_path: &Path, public_key: Option<&str>) -> Result<BoardVerification> {
+    let manifest = fs::read(board_path.join(MANIFEST_FILENAME))?;
+    let signature = fs::read(board_path.join(MANIFEST_SIGNATURE_FILENAME))?;
+    let (manifest, manifest_valid) = read_manifest(&manifest, &signature, public_key)?;
+
+    let verification_key = Base64String(manifest.public_key.clone());
+    let invalid_artifacts: Vec<String> = manifest.artifacts.iter()
+        .filter(|artifact| {
+            if !is_board_filename(&artifact.name) {
+                return true;
+            }
+            match fs::read(board_path.join(&artifact.name)) {
+                Ok(data) => !verify_artifact(&verification_key, artifact, &data),
+                Err(_) => true
+            }
+        })
+        .map(|artifact| artifact.name.clone())
+        .collect();
+
+    Ok(BoardVerification {
+        poll_identifier: manifest.poll_identifier,
+        manifest_valid,
+        artifacts: manifest.artifacts.len(),
+        invalid_artifacts
+    })
+}
@@ -0,0 +1,203 @@
+//! # Public Recount
+//!
+//! `PublicRecount` recounts the tally of a poll from a board directory
+//! alone, for auditors who never had the ballots of the poll. Each side of
+//! the map of vote codes to choices is rebuilt from the committed planes
+//! and the cells the final planes open: the planes with the third column
+//! revealed give the choices of the voted rows, and the planes with the
+//! first column revealed give their vote codes. Only cells that open their
+//! commitment and are leaves of the Merkle tree are read.
+//!
+//! Every final plane must mark the same number of voted rows, the planes
+//! must agree on the choices and on the vote codes, and both must match the
+//! tally report, which is otherwise taken on trust.
+
+use std::collections::HashSet;
+use std::fmt;
+use seventh_estate_core::tally::{PlaneFileRow, PlaneTally};
+use crate::ballots::VoteCode;
+use crate::blockchain::merkle::{CryptoSHA3256Hash, MappedTree};
+use crate::subcommands::PollTally;
+use super::*;
+
+fn read_plane_rows(path: &Path) -> Result<Vec<PlaneFileRow>> {
+    let mut csvreader = csv::Reader::from_path(path)?;
+    Ok(csvreader.deserialize::<PlaneFileRow>()
+        .collect::<std::result::Result<Vec<PlaneFileRow>, csv::Error>>()?)
+}
+
+/// Names of the final plane files and of their key files.
+pub fn final_plane_files() -> impl Iterator<Item = (String, String)> {
+    revealed_plane_files().filter(|(plane, _)| plane.starts_with("final_plane_"))
+}
+
+/// Final plane, as recounted from its opened cells.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlaneRecount {
+    pub plane: String,
+    /// Column revealed for the ballots that were not audited.
+    pub column: usize,
+    pub voted_rows: usize,
+    /// Choices of the voted rows, if the third column is revealed.
+    pub tally: Option<PlaneTally>,
+    /// Number of vote codes of the voted rows, if the first column is
+    /// revealed.
+    pub votecodes: Option<usize>
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PublicRecount {
+    pub planes: Vec<PlaneRecount>,
+    /// Tally of the planes with the third column revealed, if they agree.
+    pub tally: Option<PlaneTally>,
+    /// Vote codes voted, as opened by the planes with the first column
+    /// revealed if they agree, sorted.
+    pub votecodes: Vec<String>,
+    pub anomalies: Vec<String>
+}
+
+impl PublicRecount {
+    /// Recount a board directory from its final planes, its Merkle tree and
+    /// its tally report. The artifacts are not checked against the manifest.
+    pub fn from_board(board_path: &Path) -> Result<Self> {
+        let tree = MappedTree::open(&board_path.join(MERKLE_TREE_FILENAME).to_string_lossy())?;
+        let tally: Option<PollTally> = fs::read(board_path.join("tally.yaml")).ok()
+            .and_then(|data| serde_yaml::from_slice(&data).ok());
+        let planes: Vec<(String, String)> = final_plane_files()
+            .filter(|(plane, keys)| board_path.join(plane).exists() && board_path.join(keys).exists())
+            .collect();
+        if planes.is_empty() {
+            return Err(format!("No final planes in {}", board_path.display()).into());
+        }
+        PublicRecount::new(board_path, &planes, &merkle_leaves(&tree), tally.as_ref())
+    }
+
+    /// Recount the final planes `planes` of a directory, given as the names
+    /// of the plane files and of their key files, against `tally`.
+    pub fn new(dir: &Path, planes: &[(String, String)], leaves: &HashSet<CryptoSHA3256Hash>, tally: Option<&PollTally>) -> Result<Self> {
+        let mut recounts: Vec<PlaneRecount> = Vec::new();
+        let mut votecode_sets: Vec<Vec<String>> = Vec::new();
+        let mut anomalies: Vec<String> = Vec::new();
+        for (plane, keys) in planes.iter() {
+            let number = plane.trim_end_matches(".csv").rsplit('_').next().unwrap_or_default();
+            let committed = read_plane_rows(&dir.join(format!("committed_plane_{}.csv", number)))?;
+            let revealed = read_plane_rows(&dir.join(plane))?;
+            let keys = read_plane_rows(&dir.join(keys))?;
+            if committed.len() != revealed.len() || committed.len() != keys.len() {
+                anomalies.push(format!("{} does not have the rows of its committed plane.", plane));
+                continue;
+            }
+
+            // The rows of audited ballots reveal both columns.
+            let reveals = |column: fn(&PlaneFileRow) -> &String, other: fn(&PlaneFileRow) -> &String|
+                keys.iter().any(|key| !column(key).is_empty() && other(key).is_empty());
+            let column = match (reveals(|row| &row.col1, |row| &row.col3), reveals(|row| &row.col3, |row| &row.col1)) {
+                (false, true) => 3,
+                (true, false) => 1,
+                _ => {
+                    anomalies.push(format!("{} does not reveal a single column.", plane));
+                    continue;
+                }
+            };
+
+            let mut recount = PlaneRecount { plane: plane.clone(), column, voted_rows: 0, tally: None, votecodes: None };
+            let mut opened: Vec<PlaneFileRow> = Vec::new();
+            let mut votecodes: Vec<String> = Vec::new();
+            for (row, ((committed, revealed), key)) in committed.iter().zip(revealed.iter()).zip(keys.iter()).enumerate() {
+                if revealed.col2 != "Voted" {
+                    continue;
+                }
+                recount.voted_rows += 1;
+                let (cell, entry, key) = match column {
+                    1 => (&committed.col1, &revealed.col1, &key.col1),
+                    _ => (&committed.col3, &revealed.col3, &key.col3)
+                };
+                if key.is_empty() || !cell_opens(cell, entry, key, leaves) {
+                    anomalies.push(format!("{} row {} is voted but does not open its commitment.", plane, row + 1));
+                    continue;
+                }
+                match column {
+                    1 => match entry.rsplit(':').next().and_then(VoteCode::parse) {
+                        Some(votecode) => votecodes.push(votecode.to_string()),
+                        None => anomalies.push(format!("{} row {} does not open to a vote code.", plane, row + 1))
+                    },
+                    _ => opened.push(revealed.clone())
+                }
+            }
+            match column {
+                1 => {
+                    votecodes.sort();
+                    recount.votecodes = Some(votecodes.len());
+                    votecode_sets.push(votecodes);
+                },
+                _ => recount.tally = Some(PlaneTally::from_rows(&opened))
+            }
+            recounts.push(recount);
+        }
+
+        if !recounts.windows(2).all(|pair| pair[0].voted_rows == pair[1].voted_rows) {
+            anomalies.push("The final planes do not mark the same number of voted rows.".to_owned());
+        }
+        let tallies: Vec<&PlaneTally> = recounts.iter().filter_map(|recount| recount.tally.as_ref()).collect();
+        let recounted_tally = match tallies.windows(2).all(|pair| pair[0] == pair[1]) {
+            true => tallies.first().map(|&tally| tally.clone()),
+            false => {
+                anomalies.push("The final planes with the choices revealed do not agree on the tally.".to_owned());
+                None
+            }
+        };
+        let recounted_votecodes = match votecode_sets.windows(2).all(|pair| pair[0] == pair[1]) {
+            true => votecode_sets.first().cloned(),
+            false => {
+                anomalies.push("The final planes with the vote codes revealed do not agree on the vote codes voted.".to_owned());
+                None
+            }
+        };
+
+        if let Some(tally) = tally {
+            if let Some(recounted_tally) = &recounted_tally {
+                if *recounted_tally != tally.tally {
+                    anomalies.push(format!("The recount of the final planes ({}) does not match the tally report ({}).", recounted_tally, tally.tally));
+                }
+            }
+            if let Some(recounted_votecodes) = &recounted_votecodes {
+                let mut reported: Vec<String> = tally.votes.iter()
+                    .map(|vote| VoteCode::parse(&vote.votecode).map(|votecode| votecode.to_string()).unwrap_or_else(|| vote.votecode.clone()))
+                    .collect();
+                reported.sort();
+                if !tally.votes.is_empty() && reported != *recounted_votecodes {
+                    anomalies.push("The vote codes opened by the final planes do not match the votes of the tally report.".to_owned());
+                }
+            }
+        }
+
+        Ok(PublicRecount {
+            planes: recounts,
+            tally: recounted_tally,
+            votecodes: recounted_votecodes.unwrap_or_default(),
+            anomalies
+        })
+    }
+
+    pub fn agrees(&self) -> bool {
+        self.anomalies.is_empty()
+    }
+}
+
+impl fmt::Display for PublicRecount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let counted = |column: usize| self.planes.iter().filter(|plane| plane.column == column).count();
+        writeln!(f, "Planes recounted: {} ({} with the choices, {} with the vote codes revealed)",
+            self.planes.len(), counted(3), counted(1))?;
+        match &self.tally {
+            Some(tally) => writeln!(f, "Tally:            {}", tally)?,
+            None => writeln!(f, "Tally:            not recounted")?
+        }
+        writeln!(f, "Vote codes voted: {}", self.votecodes.len())?;
+        write!(f, "Anomalies: {}", self.anomalies.len())?;
+        for anomaly in self.anomalies.iter() {
+            write!(f, "\n  ! {}", anomaly)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,527 @@
+//! # Audit Report
+//!
+//! `AuditReport` reads a board directory and checks the outcome of the
+//! audit of the poll from its public artifacts alone: the commitment, the
+//! columns challenged, the cells opened by every revealed plane, the tally
+//! and its finalization. Every opened cell is decrypted with its revealed
+//! key, compared with the revealed entry and looked up among the leaves of
+//! the Merkle tree. The tally is recounted from the final planes with
+//! `PublicRecount` and checked against the tally report. The interference
+//! report is included if it was published, to show the transactions that
+//! tried to flood the vote address and what they cost their senders. The
+//! report is rendered as a self-contained HTML or PDF document for
+//! publication, carrying the hash and signature of every artifact so that
+//! readers can check it against the board.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use printpdf::*;
+use crate::blockchain::{tally_hash, CommitReceipt, FinalizationReceipt};
+use crate::blockchain::format_ether;
+use crate::blockchain::merkle::{leaf_hash, CryptoSHA3256Hash, MappedTree};
+use crate::monitor::{InterferenceReport, INTERFERENCE_REPORT_FILENAME};
+use crate::cryptography::{aead_decrypt, AEADKey, AEADString};
+use crate::poll_configuration::{BallotQuestion, BALLOT_QUESTION_FILENAME};
+use crate::subcommands::PollTally;
+use super::*;
+
+const REVEALED_PLANE_FILES: [&str; 3] = ["print_audit_plane_", "vote_plane_", "final_plane_"];
+
+const PDF_WIDTH: Mm = Mm(210.0);
+const PDF_HEIGHT: Mm = Mm(297.0);
+const PDF_MARGIN: Mm = Mm(15.0);
+const PDF_FONT_SIZE: f64 = 8.0;
+const PDF_LINE_HEIGHT: Mm = Mm(4.0);
+const PDF_LINE_LENGTH: usize = 100;
+
+/// Row of a plane file, committed or revealed.
+#[derive(Debug, Deserialize)]
+struct PlaneFileRow {
+    col1: String,
+    col3: String
+}
+
+fn read_plane_file(path: &Path) -> Result<Vec<PlaneFileRow>> {
+    let mut csvreader = csv::Reader::from_path(path)?;
+    Ok(csvreader.deserialize::<PlaneFileRow>()
+        .collect::<std::result::Result<Vec<PlaneFileRow>, csv::Error>>()?)
+}
+
+/// Decrypt a committed cell with its revealed base64 key.
+pub(crate) fn open_cell(cell: &str, key: &str) -> Option<String> {
+    if cell.split('$').count() != 7 {
+        return None;
+    }
+    let key = base64::decode(key.trim()).ok().filter(|key| key.len() == 32)?;
+    let values = AEADString(cell.to_owned()).values().ok()?;
+    let value = aead_decrypt(&AEADKey::from(key), &values).ok()?;
+    String::from_utf8(value.to_vec()).ok()
+}
+
+/// Whether a committed cell decrypts with its revealed base64 key to the
+/// revealed entry and is a leaf of the Merkle tree.
+pub(crate) fn cell_opens(committed: &str, revealed: &str, key: &str, leaves: &HashSet<CryptoSHA3256Hash>) -> bool {
+    open_cell(committed, key)
+        .map(|value| value.trim() == revealed.trim())
+        .unwrap_or(false)
+        && leaves.contains(&leaf_hash(committed))
+}
+
+/// Names of the revealed plane files and of their key files.
+pub fn revealed_plane_files() -> impl Iterator<Item = (String, String)> {
+    REVEALED_PLANE_FILES.iter().flat_map(|prefix| (1..=crate::NUMBER_OF_PLANES)
+        .map(move |n| (format!("{}{:02}.csv", prefix, n), format!("{}{:02}_keys.csv", prefix, n))))
+}
+
+/// Hashes of the leaves of a Merkle tree.
+pub fn merkle_leaves(tree: &MappedTree) -> HashSet<CryptoSHA3256Hash> {
+    (0..tree.leafs()).filter_map(|n| tree.hash_at(n).ok()).collect()
+}
+
+/// Check the cells opened by the revealed plane file `plane` of a directory,
+/// with the key file `keys`, against the committed plane of the same number
+/// in the directory. Returns the openings with the cells that do not open
+/// their commitment.
+pub fn check_plane_openings(dir: &Path, plane: &str, keys: &str, leaves: &HashSet<CryptoSHA3256Hash>) -> Result<(PlaneOpenings, Vec<String>)> {
+    let number = plane.trim_end_matches(".csv").rsplit('_').next().unwrap_or_default();
+    let committed = read_plane_file(&dir.join(format!("committed_plane_{}.csv", number)))?;
+    let revealed = read_plane_file(&dir.join(plane))?;
+    let keys = read_plane_file(&dir.join(keys))?;
+
+    let mut checked = PlaneOpenings { plane: plane.to_owned(), opened: 0, verified: 0 };
+    let mut problems: Vec<String> = Vec::new();
+    for (row, ((committed, revealed), keys)) in committed.iter().zip(revealed.iter()).zip(keys.iter()).enumerate() {
+        let cells = [
+            (1, &committed.col1, &revealed.col1, &keys.col1),
+            (3, &committed.col3, &revealed.col3, &keys.col3)
+        ];
+        for (column, committed, revealed, key) in cells.iter() {
+            if key.is_empty() {
+                continue;
+            }
+            checked.opened += 1;
+            if cell_opens(committed, revealed, key, leaves) {
+                checked.verified += 1;
+            } else {
+                problems.push(format!("{} row {} column {} does not open its commitment.", plane, row + 1, column));
+            }
+        }
+    }
+    if committed.len() != revealed.len() || committed.len() != keys.len() {
+        problems.push(format!("{} does not have the rows of its committed plane.", plane));
+    }
+    Ok((checked, problems))
+}
+
+/// Artifact of the board, with the result of checking it against the manifest.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportArtifact {
+    #[serde(flatten)]
+    pub artifact: Artifact,
+    pub valid: bool
+}
+
+/// Cells opened by a revealed plane file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PlaneOpenings {
+    pub plane: String,
+    pub opened: usize,
+    /// Opened cells that decrypt to the revealed entry and are leaves of the
+    /// Merkle tree.
+    pub verified: usize
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditReport {
+    pub poll_identifier: String,
+    pub public_key: String,
+    pub published_at: DateTime<Utc>,
+    pub generated_at: DateTime<Utc>,
+    pub manifest_valid: bool,
+    pub commitment: Option<CommitReceipt>,
+    /// Hex encoded root of the Merkle tree published on the board.
+    pub merkle_root: Option<String>,
+    pub question: Option<BallotQuestion>,
+    pub timestamped: bool,
+    /// Columns challenged by the drawn summands.
+    pub audited_columns: Vec<String>,
+    pub openings: Vec<PlaneOpenings>,
+    pub tally: Option<PollTally>,
+    /// Tally recounted from the final planes, if they were published.
+    pub recount: Option<PublicRecount>,
+    pub finalization: Option<FinalizationReceipt>,
+    pub interference: Option<InterferenceReport>,
+    pub artifacts: Vec<ReportArtifact>,
+    pub anomalies: Vec<String>
+}
+
+/// Section of a report, as rendered in either format.
+struct Section {
+    title: &'static str,
+    rows: Vec<(String, String)>
+}
+
+impl AuditReport {
+    /// Build the report of a board directory, checking its manifest with
+    /// `public_key` if given and with the key it carries otherwise.
+    pub fn from_board(board_path: &Path, public_key: Option<&str>) -> Result<Self> {
+        let manifest = fs::read(board_path.join(MANIFEST_FILENAME))?;
+        let signature = fs::read(board_path.join(MANIFEST_SIGNATURE_FILENAME))?;
+        let (manifest, manifest_valid) = read_manifest(&manifest, &signature, public_key)?;
+        let mut anomalies: Vec<String> = Vec::new();
+        if !manifest_valid {
+            anomalies.push("The manifest signature is invalid.".to_owned());
+        }
+
+        let verification_key = Base64String(manifest.public_key.clone());
+        let artifacts: Vec<ReportArtifact> = manifest.artifacts.iter()
+            .map(|artifact| ReportArtifact {
+                artifact: artifact.clone(),
+                valid: is_board_filename(&artifact.name) && fs::read(board_path.join(&artifact.name))
+                    .map(|data| verify_artifact(&verification_key, artifact, &data))
+                    .unwrap_or(false)
+            })
+            .collect();
+        artifacts.iter()
+            .filter(|artifact| !artifact.valid)
+            .for_each(|artifact| anomalies.push(format!("{} does not match the manifest.", artifact.artifact.name)));
+        let read = |name: &str| match manifest.artifact(name) {
+            Some(_) => fs::read(board_path.join(name)).ok(),
+            None => None
+        };
+
+        // Commitment
+        let commitment: Option<CommitReceipt> = read("commit_receipt.yaml")
+            .and_then(|data| serde_yaml::from_slice(&data).ok());
+        let tree = match manifest.artifact(MERKLE_TREE_FILENAME) {
+            Some(_) => MappedTree::open(&board_path.join(MERKLE_TREE_FILENAME).to_string_lossy()).ok(),
+            None => None
+        };
+        let merkle_root = tree.as_ref().and_then(|tree| tree.root().ok()).map(hex::encode);
+        let question: Option<BallotQuestion> = read(BALLOT_QUESTION_FILENAME)
+            .and_then(|data| serde_json::from_slice(&data).ok());
+        match &commitment {
+            None => anomalies.push("The commit receipt is missing.".to_owned()),
+            Some(receipt) => {
+                if merkle_root.as_ref() != Some(&receipt.root) {
+                    anomalies.push("The Merkle tree does not hash to the committed root.".to_owned());
+                }
+                if let (Some(question), Some(question_hash)) = (&question, &receipt.question_hash) {
+                    if &hex::encode(question.hash()) != question_hash {
+                        anomalies.push("The ballot question does not hash to the committed question.".to_owned());
+                    }
+                }
+            }
+        }
+
+        // Challenges
+        let audited_columns: Vec<String> = read("audited_columns.yaml")
+            .and_then(|data| serde_yaml::from_slice(&data).ok())
+            .unwrap_or_default();
+
+        // Openings
+        let leaves = tree.as_ref().map(merkle_leaves).unwrap_or_default();
+        let mut openings: Vec<PlaneOpenings> = Vec::new();
+        for (plane, keys) in revealed_plane_files() {
+            if manifest.artifact(&plane).is_none() || manifest.artifact(&keys).is_none() {
+                continue;
+            }
+            match check_plane_openings(board_path, &plane, &keys, &leaves) {
+                Ok((checked, problems)) => {
+                    openings.push(checked);
+                    anomalies.extend(problems);
+                },
+                Err(_) => anomalies.push(format!("{} cannot be checked against its committed plane.", plane))
+            }
+        }
+
+        // Tally
+        let tally_data = read("tally.yaml");
+        let tally: Option<PollTally> = tally_data.as_ref()
+            .and_then(|data| serde_yaml::from_slice(data).ok());
+        let finalization: Option<FinalizationReceipt> = read("finalization_receipt.yaml")
+            .and_then(|data| serde_yaml::from_slice(&data).ok());
+        match &tally {
+            Some(tally) if !tally.consistent => anomalies.push("The planes counted do not agree on the tally.".to_owned()),
+            Some(_) => (),
+            None => anomalies.push("The tally report is missing.".to_owned())
+        }
+        if let (Some(data), Some(finalization)) = (&tally_data, &finalization) {
+            if hex::encode(tally_hash(data)) != finalization.tally_hash {
+                anomalies.push("The tally report does not hash to the finalized tally.".to_owned());
+            }
+        }
+
+        // Recount
+        let final_planes: Vec<(String, String)> = final_plane_files()
+            .filter(|(plane, keys)| manifest.artifact(plane).is_some() && manifest.artifact(keys).is_some())
+            .collect();
+        let recount = match final_planes.is_empty() {
+            true => None,
+            false => match PublicRecount::new(board_path, &final_planes, &leaves, tally.as_ref()) {
+                Ok(recount) => {
+                    anomalies.extend(recount.anomalies.iter().cloned());
+                    Some(recount)
+                },
+                Err(_) => {
+                    anomalies.push("The final planes cannot be recounted.".to_owned());
+                    None
+                }
+            }
+        };
+
+        // Interference
+        let interference: Option<InterferenceReport> = read(INTERFERENCE_REPORT_FILENAME)
+            .and_then(|data| serde_yaml::from_slice(&data).ok());
+
+        Ok(AuditReport {
+            poll_identifier: manifest.poll_identifier.clone(),
+            public_key: manifest.public_key.clone(),
+            published_at: manifest.published_at,
+            generated_at: Utc::now(),
+            manifest_valid,
+            commitment,
+            merkle_root,
+            question,
+            timestamped: manifest.artifact("commit_receipt.ots").is_some(),
+            audited_columns,
+            openings,
+            tally,
+            recount,
+            finalization,
+            interference,
+            artifacts,
+            anomalies
+        })
+    }
+
+    pub fn passed(&self) -> bool {
+        self.anomalies.is_empty()
+    }
+
+    fn cells_opened(&self) -> (usize, usize) {
+        self.openings.iter()
+            .fold((0, 0), |(opened, verified), plane| (opened + plane.opened, verified + plane.verified))
+    }
+
+    fn sections(&self) -> Vec<Section> {
+        let yes_no = |value: bool| if value { "yes" } else { "NO" }.to_owned();
+        let mut sections: Vec<Section> = Vec::new();
+        sections.push(Section {
+            title: "Poll",
+            rows: vec![
+                ("Poll".to_owned(), self.poll_identifier.clone()),
+                ("Result".to_owned(), if self.passed() { "PASSED".to_owned() } else { format!("{} ANOMALIES", self.anomalies.len()) }),
+                ("Published".to_owned(), self.published_at.to_rfc3339()),
+                ("Report generated".to_owned(), self.generated_at.to_rfc3339()),
+                ("Verification key".to_owned(), self.public_key.clone()),
+                ("Manifest signature valid".to_owned(), yes_no(self.manifest_valid))
+            ]
+        });
+
+        let mut commitment = vec![("Merkle root".to_owned(), self.merkle_root.clone().unwrap_or_else(|| "missing".to_owned()))];
+        if let Some(receipt) = &self.commitment {
+            commitment.push(("Committed root".to_owned(), format!("{} ({} leaves)", receipt.root, receipt.leaves)));
+            commitment.push(("Transaction".to_owned(), receipt.transaction_hash.clone()));
+            commitment.push(("Address".to_owned(), receipt.address.clone()));
+            commitment.push(("Block".to_owned(), receipt.submitted_at_block.to_string()));
+            commitment.push(("Posted".to_owned(), receipt.posted_at.to_rfc3339()));
+            if let Some(question_hash) = &receipt.question_hash {
+                commitment.push(("Question hash".to_owned(), question_hash.clone()));
+            }
+        }
+        if let Some(question) = &self.question {
+            if let Some(text) = &question.question {
+                commitment.push(("Question".to_owned(), text.clone()));
+            }
+            commitment.push(("Choices".to_owned(), question.choices.join(", ")));
+        }
+        commitment.push(("OpenTimestamps proof".to_owned(), if self.timestamped { "yes" } else { "no" }.to_owned()));
+        sections.push(Section { title: "Commitment", rows: commitment });
+
+        let mut challenges = vec![("Columns challenged".to_owned(), self.audited_columns.len().to_string())];
+        challenges.extend(self.audited_columns.iter().map(|column| (String::new(), column.clone())));
+        sections.push(Section { title: "Challenges", rows: challenges });
+
+        let (opened, verified) = self.cells_opened();
+        let mut openings = vec![("Cells opened".to_owned(), format!("{} ({} verified)", opened, verified))];
+        openings.extend(self.openings.iter()
+            .filter(|plane| 0 < plane.opened)
+            .map(|plane| (plane.plane.clone(), format!("{} opened, {} verified", plane.opened, plane.verified))));
+        sections.push(Section { title: "Openings", rows: openings });
+
+        let mut tally = Vec::new();
+        match &self.tally {
+            Some(poll_tally) => {
+                tally.push(("Tally".to_owned(), poll_tally.tally.to_string()));
+                tally.push(("Planes counted".to_owned(), poll_tally.planes_counted.to_string()));
+                tally.push(("Consistent".to_owned(), yes_no(poll_tally.consistent)));
+            },
+            None => tally.push(("Tally".to_owned(), "missing".to_owned()))
+        }
+        if let Some(finalization) = &self.finalization {
+            tally.push(("Finalized tally hash".to_owned(), finalization.tally_hash.clone()));
+            tally.push(("Finalization transaction".to_owned(), finalization.transaction_hash.clone()));
+            tally.push(("Finalization block".to_owned(), finalization.submitted_at_block.to_string()));
+        }
+        sections.push(Section { title: "Tally", rows: tally });
+
+        if let Some(recount) = &self.recount {
+            let mut rows = vec![
+                ("Tally".to_owned(), recount.tally.as_ref().map(|tally| tally.to_string()).unwrap_or_else(|| "not recounted".to_owned())),
+                ("Vote codes voted".to_owned(), recount.votecodes.len().to_string()),
+                ("Agrees with the report".to_owned(), yes_no(recount.agrees()))
+            ];
+            rows.extend(recount.planes.iter()
+                .map(|plane| (plane.plane.clone(), match &plane.tally {
+                    Some(tally) => format!("{} voted rows, {}", plane.voted_rows, tally),
+                    None => format!("{} voted rows, {} vote codes", plane.voted_rows, plane.votecodes.unwrap_or_default())
+                })));
+            sections.push(Section { title: "Recount", rows });
+        }
+
+        if let Some(interference) = &self.interference {
+            let mut rows = vec![
+                ("Blocks".to_owned(), format!("{} to {}", interference.from_block, interference.to_block)),
+                ("Transactions".to_owned(), interference.transactions.to_string()),
+                ("Invalid payloads".to_owned(), interference.invalid_payloads.to_string()),
+                ("Unknown vote codes".to_owned(), interference.unknown_votecodes.to_string()),
+                ("Estimated spend".to_owned(), interference.total.to_string())
+            ];
+            rows.extend(interference.senders.iter()
+                .map(|sender| (sender.address.clone(), format!("{} invalid, {} unknown, {} ETH",
+                    sender.invalid_payloads, sender.unknown_votecodes, format_ether(sender.spend.spent_wei)))));
+            sections.push(Section { title: "Interference", rows });
+        }
+
+        sections.push(Section {
+            title: "Anomalies",
+            rows: match self.anomalies.is_empty() {
+                true => vec![(String::new(), "None".to_owned())],
+                false => self.anomalies.iter().map(|anomaly| (String::new(), anomaly.clone())).collect()
+            }
+        });
+
+        sections.push(Section {
+            title: "Artifacts",
+            rows: self.artifacts.iter()
+                .map(|artifact| (artifact.artifact.name.clone(), format!("{} bytes{}\nSHA-256 {}\nSignature {}",
+                    artifact.artifact.size,
+                    if artifact.valid { "" } else { ", INVALID" },
+                    artifact.artifact.sha256,
+                    artifact.artifact.signature)))
+                .collect()
+        });
+        sections
+    }
+
+    /// Render the report as a self-contained HTML document.
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        html.push_str("<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n");
+        html.push_str(&format!("<title>Audit report: {}</title>\n", html_escape(&self.poll_identifier)));
+        html.push_str("<style>body{font-family:sans-serif;max-width:60em;margin:auto}th{text-align:left;vertical-align:top;padding-right:1em}td{font-family:monospace;white-space:pre-wrap;word-break:break-all}</style>\n");
+        html.push_str("</head>\n<body>\n");
+        html.push_str(&format!("<h1>Audit report: {}</h1>\n", html_escape(&self.poll_identifier)));
+        for section in self.sections() {
+            html.push_str(&format!("<h2>{}</h2>\n<table>\n", section.title));
+            for (name, value) in section.rows.iter() {
+                html.push_str(&format!("<tr><th>{}</th><td>{}</td></tr>\n", html_escape(name), html_escape(value)));
+            }
+            html.push_str("</table>\n");
+        }
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    /// Render the report as a PDF document.
+    pub fn write_pdf(&self, path: &Path) -> Result<()> {
+        let title = format!("Audit report: {}", self.poll_identifier);
+        let mut lines: Vec<(String, f64)> = vec![(title.clone(), 2.0 * PDF_FONT_SIZE), (String::new(), PDF_FONT_SIZE)];
+        for section in self.sections() {
+            lines.push((section.title.to_owned(), 1.5 * PDF_FONT_SIZE));
+            for (name, value) in section.rows.iter() {
+                let text = match name.is_empty() {
+                    true => value.clone(),
+                    false => format!("{}: {}", name, value)
+                };
+                lines.extend(wrap_text(&text, PDF_LINE_LENGTH).into_iter().map(|line| (line, PDF_FONT_SIZE)));
+            }
+            lines.push((String::new(), PDF_FONT_SIZE));
+        }
+
+        let (doc, page1, layer1) = PdfDocument::new(title, PDF_WIDTH, PDF_HEIGHT, "layer1".to_string());
+        let font = doc.add_builtin_font(BuiltinFont::Courier)?;
+        let lines_per_page = ((PDF_HEIGHT.0 - 2.0 * PDF_MARGIN.0) / PDF_LINE_HEIGHT.0) as usize;
+        for (n, page_lines) in lines.chunks(lines_per_page).enumerate() {
+            let (page, layer) = match n {
+                0 => (page1, layer1),
+                _ => doc.add_page(PDF_WIDTH, PDF_HEIGHT, "layer1".to_string())
+            };
+            let layer = doc.get_page(page).get_layer(layer);
+            for (row, (line, size)) in page_lines.iter().enumerate() {
+                let y = PDF_HEIGHT - PDF_MARGIN - Mm(PDF_LINE_HEIGHT.0 * (row + 1) as f64);
+                layer.use_text(line.clone(), *size, PDF_MARGIN, y, &font);
+            }
+        }
+        doc.save(&mut BufWriter::new(File::create(path)?))?;
+        Ok(())
+    }
+}
+
+impl fmt::Display for AuditReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let (opened, verified) = self.cells_opened();
+        writeln!(f, "Poll:      {}", self.poll_identifier)?;
+        writeln!(f, "Manifest:  {}", if self.manifest_valid { "signature valid" } else { "SIGNATURE INVALID" })?;
+        writeln!(f, "Root:      {}", self.merkle_root.as_deref().unwrap_or("missing"))?;
+        writeln!(f, "Challenged columns: {}", self.audited_columns.len())?;
+        writeln!(f, "Cells opened:       {} ({} verified)", opened, verified)?;
+        match &self.tally {
+            Some(tally) => writeln!(f, "Tally:     {}", tally.tally)?,
+            None => writeln!(f, "Tally:     missing")?
+        }
+        if let Some(recount) = &self.recount {
+            match &recount.tally {
+                Some(tally) => writeln!(f, "Recount:   {}", tally)?,
+                None => writeln!(f, "Recount:   not recounted")?
+            }
+        }
+        write!(f, "Anomalies: {}", self.anomalies.len())?;
+        for anomaly in self.anomalies.iter() {
+            write!(f, "\n  ! {}", anomaly)?;
+        }
+        Ok(())
+    }
+}
+
+fn html_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Split text into lines no longer than `width` characters, breaking words
+/// longer than a line.
+fn wrap_text(text: &str, width: usize) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+    text.lines().for_each(|paragraph| {
+        let mut line = String::new();
+        paragraph.split_whitespace().for_each(|word| {
+            if !line.is_empty() && line.len() + 1 + word.len() > width {
+                lines.push(std::mem::take(&mut line));
+            }
+            if !line.is_empty() { line.push(' '); }
+            line.push_str(word);
+            while let Some((split, _)) = line.char_indices().nth(width) {
+                let rest = line.split_off(split);
+                lines.push(std::mem::replace(&mut line, rest));
+            }
+        });
+        lines.push(line);
+    });
+    lines
+}
@@ -6,13 +6,19 @@
 //!
 //! The specific authenticated encryption scheme employed is
 //! ChaCha20-Poly1305 using a 256-bit key.
+//!
+//! Keys and copies of the plaintext are scrubbed from memory once used,
+//! and the plaintext is never logged; decrypted data is returned in a
+//! buffer scrubbed when dropped.
 
-use std::str;
+use std::fmt;
 use std::cmp::max;
 use serde::{Serialize, Deserialize};
+use zeroize::{Zeroize, Zeroizing};
 use super::{Result, debug};
 
-#[derive(Debug, Clone, Copy)]
+/// AEAD key, scrubbed from memory when dropped.
+#[derive(Clone)]
 pub struct AEADKey(pub [u8; 32]);
 #[derive(Debug, Clone, Copy)]
 pub struct AEADNonce(pub [u8; 12]);
@@ -25,10 +31,29 @@ pub struct DecryptedData(pub Vec<u8>);
 
 impl AEADKey {
     pub fn from(value: Vec<u8>) -> Self {
-        let mut key = [0u8; 32];
-        let copylen = max(key.len(), value.len());
-        key.copy_from_slice(&value[0..copylen]);
-        AEADKey(key)
+        let value = Zeroizing::new(value);
+        let mut key = AEADKey([0u8; 32]);
+        let copylen = max(key.0.len(), value.len());
+        key.0.copy_from_slice(&value[0..copylen]);
+        key
+    }
+}
+
+impl fmt::Debug for AEADKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("AEADKey(..)")
+    }
+}
+
+impl Zeroize for AEADKey {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Drop for AEADKey {
+    fn drop(&mut self) {
+        self.zeroize();
     }
 }
 
@@ -98,11 +123,12 @@ fn aead_authenticate_ex(aead_key: &AEADKey, aead_nonce: &AEADNonce, aad: Vec<u8>
 ///
 /// This function should never need to be used.
 pub fn aead_encrypt_ex(aead_key: &AEADKey, aead_nonce: &AEADNonce, aad: Vec<u8>, value: Vec<u8>) -> Result<AEADValues> {
-    let key: [u8; 32] = aead_key.0;
+    let key = Zeroizing::new(aead_key.0);
+    let value = Zeroizing::new(value);
     let nonce: [u8; 12] = aead_nonce.0;
     let mut encrypted = Vec::with_capacity(value.len());
     let tag = chacha20_poly1305_aead::encrypt(
-        &key,
+        &key[..],
         &nonce,
         &aad,
         &value,
@@ -112,10 +138,6 @@ pub fn aead_encrypt_ex(aead_key: &AEADKey, aead_nonce: &AEADNonce, aad: Vec<u8>,
         Ok(s) => s,
         Err(_) => hex::encode(&aad)
     });
-    debug!("Unencrypted Data:   {}", match String::from_utf8(value.clone()) {
-        Ok(s) => s,
-        Err(_) => hex::encode(&value)
-    });
     debug!("Encrypted Data:     {}", hex::encode(&encrypted));
     debug!("Tag:                {}", hex::encode(&tag));
     
@@ -133,8 +155,8 @@ pub fn aead_encrypt_ex(aead_key: &AEADKey, aead_nonce: &AEADNonce, aad: Vec<u8>,
 ///
 /// ```
 /// ```
-pub fn aead_decrypt(aead_key: &AEADKey, aead_values: &AEADValues) -> Result<Vec<u8>> {
-    let mut key = [0u8; 32];
+pub fn aead_decrypt(aead_key: &AEADKey, aead_values: &AEADValues) -> Result<Zeroizing<Vec<u8>>> {
+    let mut key = Zeroizing::new([0u8; 32]);
     let mut nonce = [0u8; 12];
     let aad: Vec<u8> = aead_values.aad.clone();
     let encrypted_value: Vec<u8> = aead_values.encrypted_value.clone();
@@ -149,7 +171,7 @@ pub fn aead_decrypt(aead_key: &AEADKey, aead_values: &AEADValues) -> Result<Vec<
     tag.copy_from_slice(&aead_values.tag[..tag_len]);
     
     chacha20_poly1305_aead::decrypt(
-        &key,
+        &key[..],
         &nonce,
         &aad,
         &encrypted_value,
@@ -158,12 +180,8 @@ pub fn aead_decrypt(aead_key: &AEADKey, aead_values: &AEADValues) -> Result<Vec<
 
     debug!("Encrypted Data: {}", hex::encode(&encrypted_value));
     debug!("Tag:            {}", hex::encode(tag));
-    debug!("Decrypted Data: {}", match String::from_utf8(decrypted.clone()) {
-        Ok(s) => s,
-        Err(_) => hex::encode(&decrypted)
-    });
 
-    Ok(decrypted.clone())
+    Ok(Zeroizing::new(decrypted))
 }
 
 
@@ -5,13 +5,16 @@
 //!
 //! The specific CSPRNG employed is ChaCha20. ChaCha20 accepts a 256-bit
 //! random seed, has a 512-bit state, and has a period of 70 bits.
+//!
+//! Seeds are scrubbed from memory when they are dropped.
 
 pub use rand::{Rng, RngCore, SeedableRng};
 use rand_chacha::ChaChaRng;
+use zeroize::Zeroize;
 
 pub type CSPRNG = ChaChaRng;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone)]
 pub struct CSPRNGSeed([u8; 32]);
 
 impl CSPRNGSeed {
@@ -31,12 +34,24 @@ impl CSPRNGSeed {
     }
 }
 
+impl Zeroize for CSPRNGSeed {
+    fn zeroize(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Drop for CSPRNGSeed {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
 pub trait CSPRNGExt<T: SeedableRng> {
-    fn from_csprng_seed(value: CSPRNGSeed) -> T;
+    fn from_csprng_seed(value: &CSPRNGSeed) -> T;
 }
 
 impl CSPRNGExt<ChaChaRng> for ChaChaRng {
-    fn from_csprng_seed(value: CSPRNGSeed) -> Self {
+    fn from_csprng_seed(value: &CSPRNGSeed) -> Self {
         Self::from_seed(value.0)
     }
 }
@@ -2,6 +2,10 @@
 //!
 //! The [Fast Dice Roller algorithm](https://arxiv.org/abs/1304.1916) optimally
 //! samples the discrete uniform distribution.
+//!
+//! The random bytes of the roller are scrubbed when it is dropped.
+
+use zeroize::Zeroize;
 
 #[derive(Debug)]
 pub struct FastDiceRoller {
@@ -61,3 +65,9 @@ impl FastDiceRoller {
     }
     */
 }
+
+impl Drop for FastDiceRoller {
+    fn drop(&mut self) {
+        self.byte_stream.zeroize();
+    }
+}
@@ -22,6 +22,15 @@ pub use secret_sharing::*;
 pub mod secured_file;
 pub use secured_file::*;
 
+pub mod signed_file;
+pub use signed_file::*;
+
+pub mod vote_encryption;
+pub use vote_encryption::*;
+
+pub mod time_lock;
+pub use time_lock::*;
+
 pub mod csprng;
 pub use csprng::*;
 
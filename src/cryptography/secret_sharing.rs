@@ -7,8 +7,11 @@
 //! Sharing using a prime arithmetic field. Since the smallest
 //! prime arithmetic field that can contain an 8-bit byte requires
 //! 2 bytes (Z-257), we share over the larger 15-bit Z-32749.
+//!
+//! Secrets and shares are scrubbed from memory when they are dropped.
 
 use threshold_secret_sharing as tss;
+use zeroize::Zeroize;
 use super::endian;
 use super::Result;
 
@@ -18,6 +21,18 @@ pub struct Secret(pub Vec<u8>);
 #[derive(Debug, Clone)]
 pub struct SecretShare(pub Vec<u16>);
 
+impl Drop for Secret {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Drop for SecretShare {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
 impl SecretShare {
     pub fn from_vec8(v8: Vec<u8>) -> Self {
         SecretShare(endian::le_bytes::to_slice_u16(&v8).to_vec())
@@ -51,7 +51,7 @@ impl SecuredFile {
             encrypted_value: base64::decode(&self.protected)?,
             tag: tag
         };
-        let protected: String = String::from_utf8(aead_decrypt(key, &aead_values)?)?;
+        let protected: String = str::from_utf8(&aead_decrypt(key, &aead_values)?)?.to_owned();
         Ok((self.assured.clone(), protected))
     }
 }
@@ -11,14 +11,22 @@
 use signatory::ed25519;
 use signatory::encoding::{Encode, Decode, Base64};
 // use signatory::public_key::PublicKey;
-use signatory::signature::{Signer};
-use signatory_sodiumoxide::{Ed25519Signer};
+use signatory::signature::{Signer, Verifier, Signature};
+use signatory_sodiumoxide::{Ed25519Signer, Ed25519Verifier};
 
 use super::{Result, Base64String};
 
 /// Generate a key pair for signing and signature verification.
 pub fn new_signing_key() -> Result<(Base64String, Base64String)> {
-    let seed = ed25519::Seed::generate();
+    signing_key_pair(ed25519::Seed::generate())
+}
+
+/// Key pair of a 32 byte seed, for signing keys derived from other secrets.
+pub fn signing_key_from_seed(seed: [u8; 32]) -> Result<(Base64String, Base64String)> {
+    signing_key_pair(ed25519::Seed::new(seed))
+}
+
+fn signing_key_pair(seed: ed25519::Seed) -> Result<(Base64String, Base64String)> {
     let signer = Ed25519Signer::from(&seed);
     let pk = signatory::ed25519::PublicKey::from(&signer);
     
@@ -34,3 +42,13 @@ pub fn sign(key: &Base64String, data: Vec<u8>) -> Result<(Vec<u8>, Vec<u8>)> {
     Ok((data, signature))
 }
 
+/// Verify a signature over data using a provided verification key.
+pub fn verify(public_key: &Base64String, data: &[u8], signature: &[u8]) -> Result<bool> {
+    let pk = ed25519::PublicKey::decode_from_str(&public_key.0, &Base64::default())
+        .map_err(|_| "Invalid verification key")?;
+    let signature = match ed25519::Signature::from_bytes(signature) {
+        Ok(signature) => signature,
+        Err(_) => return Ok(false)
+    };
+    Ok(Ed25519Verifier::from(&pk).verify(data, &signature).is_ok())
+}
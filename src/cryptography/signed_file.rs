@@ -0,0 +1,102 @@
+//! # Signed Files
+//!
+//! `signed_file` signs the local artifacts of a poll that are read back
+//! after they are written: the Merkle tree, transaction caches and the
+//! files revealed by steps 7 and 8. The signature of `FILE` is detached in
+//! `FILE.sig`, base64 encoded, as written by `sign`.
+//!
+//! Once a verification key is set, every signed artifact is checked when it
+//! is loaded, so that an artifact modified between the commit and the audit
+//! is detected instead of used. Artifacts without a signature are refused.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::RwLock;
+use lazy_static::lazy_static;
+use super::{sign, verify, Base64String};
+use crate::Result;
+
+pub const SIGNATURE_EXTENSION: &str = "sig";
+
+/// Revealed poll data files that are signed.
+const SIGNED_FILES: [&str; 6] = [
+    "merkle.yaml",
+    "audited_columns.yaml",
+    "committed_summands_revealed.csv",
+    "committed_summands_key.key",
+    "tally.yaml",
+    "logic_and_accuracy.yaml"
+];
+
+/// Prefix of the signed per-plane poll data files.
+const SIGNED_PLANE_FILES: &str = "final_plane_";
+
+/// Key checking the artifacts, and whether it was given by the user rather
+/// than taken from the poll configuration.
+struct VerificationKey {
+    public_key: Base64String,
+    pinned: bool
+}
+
+lazy_static! {
+    static ref VERIFICATION_KEY: RwLock<Option<VerificationKey>> = RwLock::new(None);
+}
+
+/// Path of the detached signature of `path`.
+pub fn signature_path(path: &Path) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(".");
+    name.push(SIGNATURE_EXTENSION);
+    PathBuf::from(name)
+}
+
+/// Whether a poll data file is signed when it is written.
+pub fn is_signed_artifact(name: &str) -> bool {
+    SIGNED_FILES.contains(&name)
+        || (name.starts_with(SIGNED_PLANE_FILES) && name.ends_with(".csv"))
+}
+
+/// Write the detached signature of the file at `path`.
+pub fn sign_file(signing_key: &Base64String, path: &Path) -> Result<()> {
+    let (_, signature) = sign(signing_key, fs::read(path)?)?;
+    fs::write(signature_path(path), base64::encode(&signature))?;
+    Ok(())
+}
+
+/// Check the file at `path` against its detached signature.
+pub fn verify_file(public_key: &Base64String, path: &Path) -> Result<()> {
+    let signature_path = signature_path(path);
+    let signature = fs::read(&signature_path)
+        .map_err(|_| format!("{} has no signature {}; sign it with `sign`.", path.display(), signature_path.display()))?;
+    let signature = base64::decode(String::from_utf8_lossy(&signature).trim())
+        .map_err(|err| format!("{} is not a valid signature: {}", signature_path.display(), err))?;
+    if !verify(public_key, &fs::read(path)?, &signature)? {
+        return Err(format!("{} does not match its signature {}: it was modified after it was signed.",
+            path.display(), signature_path.display()).into());
+    }
+    Ok(())
+}
+
+/// Check the artifacts loaded from now on with `public_key`, whatever the
+/// poll they belong to.
+pub fn set_verification_key(public_key: Base64String) {
+    *VERIFICATION_KEY.write().unwrap() = Some(VerificationKey { public_key, pinned: true });
+}
+
+/// Check the artifacts loaded from now on with the verification key of the
+/// poll, unless a key was set with `set_verification_key`.
+pub fn use_verification_key(public_key: Base64String) {
+    let mut verification_key = VERIFICATION_KEY.write().unwrap();
+    if !verification_key.as_ref().is_some_and(|key| key.pinned) {
+        *verification_key = Some(VerificationKey { public_key, pinned: false });
+    }
+}
+
+/// Check an artifact about to be loaded against its signature, if a
+/// verification key is set.
+pub fn check_artifact(path: &Path) -> Result<()> {
+    match VERIFICATION_KEY.read().unwrap().as_ref() {
+        Some(key) => verify_file(&key.public_key, path),
+        None => Ok(())
+    }
+}
@@ -0,0 +1,265 @@
+//! # Time-Lock Encryption
+//!
+//! `time_lock` locks a secret so that it can only be read after a number
+//! of sequential squarings modulo an RSA modulus, the time-lock puzzle of
+//! Rivest, Shamir and Wagner. The factors of the modulus, which would allow
+//! the puzzle to be solved at once, are only known while it is created and
+//! are then scrubbed from memory, so that nobody, including the trustees, can read the
+//! secret before the squarings are done; the squarings cannot be run in
+//! parallel.
+//!
+//! The number of squarings is calibrated from the squarings per second of
+//! the machine creating the puzzle, so that the puzzle is solved at about
+//! the reveal time if solving starts right away on a machine as fast.
+//!
+//! The calibration leaves no safety margin: the reveal time only holds
+//! against solvers no faster than the machine creating the puzzle. A solver
+//! squaring k times faster reads the secret after 1/k of the time to the
+//! reveal time; big integer libraries tuned for modular squaring, faster
+//! processors or dedicated hardware can be several times faster than the
+//! calibration with `num_bigint`, so the secret should be expected to be
+//! readable well before the reveal time. Conversely, the puzzle must be
+//! solved without pause from the start on a machine at least as fast for
+//! the secret to be read at the reveal time; `TimeLockPuzzle::resume`
+//! continues from a checkpoint after an interruption.
+//!
+//! `TimeLockedKey` locks the secret key of a vote encryption key pair.
+
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
+use num_bigint::BigUint;
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use sodiumoxide::crypto::secretbox;
+use zeroize::{Zeroize, Zeroizing};
+
+use super::{Result, VoteDecryptionKey, VoteEncryptionKey};
+
+/// Bits of the modulus of time-lock puzzles.
+pub const TIME_LOCK_MODULUS_BITS: usize = 2048;
+
+const MILLER_RABIN_ROUNDS: usize = 40;
+
+const SMALL_PRIMES: [u32; 24] = [3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97];
+
+/// Squarings between two checkpoints of a puzzle being solved.
+pub const TIME_LOCK_CHECKPOINT_SQUARINGS: u64 = 0x10000;
+
+/// Time-lock puzzle holding a secret, with values written as hexadecimal.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeLockPuzzle {
+    pub modulus: String,
+    pub base: String,
+    pub squarings: u64,
+    /// Base64 encoded nonce and secret, encrypted with the hash of the
+    /// solution.
+    pub nonce: String,
+    pub locked: String
+}
+
+fn random_below(bound: &BigUint) -> Result<BigUint> {
+    let mut bytes = vec![0u8; (bound.bits() as usize).div_ceil(8) + 8];
+    getrandom::getrandom(&mut bytes)?;
+    Ok(BigUint::from_bytes_be(&bytes) % bound)
+}
+
+fn is_probable_prime(candidate: &BigUint) -> Result<bool> {
+    let one = BigUint::from(1u32);
+    let two = BigUint::from(2u32);
+    if SMALL_PRIMES.iter().any(|prime| candidate % *prime == BigUint::from(0u32)) {
+        return Ok(SMALL_PRIMES.iter().any(|prime| *candidate == BigUint::from(*prime)));
+    }
+    let minus_one = candidate - &one;
+    let shift = minus_one.trailing_zeros().unwrap_or(0);
+    let odd = &minus_one >> shift;
+    for _ in 0..MILLER_RABIN_ROUNDS {
+        let witness = random_below(&(candidate - 3u32))? + &two;
+        let mut value = witness.modpow(&odd, candidate);
+        if value == one || value == minus_one {
+            continue;
+        }
+        let mut composite = true;
+        for _ in 1..shift {
+            value = value.modpow(&two, candidate);
+            if value == minus_one {
+                composite = false;
+                break;
+            }
+        }
+        if composite {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+fn random_prime(bits: usize) -> Result<BigUint> {
+    loop {
+        let mut bytes = vec![0u8; bits / 8];
+        getrandom::getrandom(&mut bytes)?;
+        bytes[0] |= 0xc0;
+        let last = bytes.len() - 1;
+        bytes[last] |= 1;
+        let candidate = BigUint::from_bytes_be(&bytes);
+        if is_probable_prime(&candidate)? {
+            return Ok(candidate);
+        }
+    }
+}
+
+fn solution_key(solution: &BigUint) -> Result<secretbox::Key> {
+    let mut digest = Sha256::digest(&Zeroizing::new(solution.to_bytes_be()));
+    let key = secretbox::Key::from_slice(&digest);
+    digest.as_mut_slice().zeroize();
+    key.ok_or_else(|| "Invalid time-lock key".into())
+}
+
+// BigUint cannot be zeroized: overwrite its digits in place, as many as it
+// has, so that its buffer holds none of its value once dropped.
+fn scrub(value: &mut BigUint) {
+    let digits = vec![u32::MAX; value.bits().div_ceil(32) as usize];
+    value.assign_from_slice(&digits);
+    *value = BigUint::default();
+}
+
+fn read_hex(value: &str) -> Result<BigUint> {
+    BigUint::parse_bytes(value.as_bytes(), 16).ok_or_else(|| format!("Not a hexadecimal number: {}", value).into())
+}
+
+impl TimeLockPuzzle {
+    /// Lock `secret` behind `squarings` sequential squarings modulo a
+    /// modulus of `modulus_bits` bits.
+    pub fn lock(secret: &[u8], squarings: u64, modulus_bits: usize) -> Result<Self> {
+        let (mut p, mut q) = (random_prime(modulus_bits / 2)?, random_prime(modulus_bits / 2)?);
+        let modulus = &p * &q;
+        p -= 1u32;
+        q -= 1u32;
+        let mut totient = &p * &q;
+        scrub(&mut p);
+        scrub(&mut q);
+        let base = random_below(&(&modulus - 2u32))? + 2u32;
+
+        // With the factors, 2^squarings is reduced modulo the totient.
+        let mut exponent = BigUint::from(2u32).modpow(&BigUint::from(squarings), &totient);
+        scrub(&mut totient);
+        let mut solution = base.modpow(&exponent, &modulus);
+        scrub(&mut exponent);
+        let key = solution_key(&solution);
+        scrub(&mut solution);
+        let nonce = secretbox::gen_nonce();
+        let locked = secretbox::seal(secret, &nonce, &key?);
+        Ok(TimeLockPuzzle {
+            modulus: modulus.to_str_radix(16),
+            base: base.to_str_radix(16),
+            squarings,
+            nonce: base64::encode(&nonce),
+            locked: base64::encode(&locked)
+        })
+    }
+
+    /// Solve the puzzle and read the secret, calling `progress` with the
+    /// squarings done every so often.
+    pub fn unlock<F: FnMut(u64)>(&self, mut progress: F) -> Result<Vec<u8>> {
+        self.resume(None, |checkpoint| {
+            progress(checkpoint.squarings);
+            Ok(())
+        })
+    }
+
+    /// Solve the puzzle from `from`, or from its base, and read the secret,
+    /// calling `checkpoint` with the squarings done every
+    /// `TIME_LOCK_CHECKPOINT_SQUARINGS` squarings and once solved.
+    pub fn resume<F: FnMut(&TimeLockCheckpoint) -> Result<()>>(&self, from: Option<&TimeLockCheckpoint>, mut checkpoint: F) -> Result<Vec<u8>> {
+        let modulus = read_hex(&self.modulus)?;
+        let (start, mut solution) = match from {
+            Some(from) if from.squarings > self.squarings =>
+                return Err(format!("The checkpoint after {} squarings is past the {} squarings of the puzzle.", from.squarings, self.squarings).into()),
+            Some(from) => (from.squarings, read_hex(&from.value)?),
+            None => (0, read_hex(&self.base)?)
+        };
+        for done in start..self.squarings {
+            if done % TIME_LOCK_CHECKPOINT_SQUARINGS == 0 {
+                checkpoint(&TimeLockCheckpoint::new(done, &solution))?;
+            }
+            solution = &solution * &solution % &modulus;
+        }
+        checkpoint(&TimeLockCheckpoint::new(self.squarings, &solution))?;
+        let nonce = secretbox::Nonce::from_slice(&base64::decode(&self.nonce)?).ok_or("Invalid time-lock nonce")?;
+        secretbox::open(&base64::decode(&self.locked)?, &nonce, &solution_key(&solution)?)
+            .map_err(|_| "Time-lock puzzle does not unlock its secret".into())
+    }
+}
+
+/// Squarings done on a time-lock puzzle, to resume solving it from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TimeLockCheckpoint {
+    pub squarings: u64,
+    /// Base of the puzzle squared `squarings` times, as hexadecimal.
+    pub value: String
+}
+
+impl TimeLockCheckpoint {
+    fn new(squarings: u64, value: &BigUint) -> Self {
+        TimeLockCheckpoint { squarings, value: value.to_str_radix(16) }
+    }
+}
+
+/// Sequential squarings per second of this machine modulo a modulus of
+/// `modulus_bits` bits.
+pub fn squarings_per_second(modulus_bits: usize) -> Result<u64> {
+    let mut bytes = vec![0u8; modulus_bits / 8];
+    getrandom::getrandom(&mut bytes)?;
+    bytes[0] |= 0x80;
+    let modulus = BigUint::from_bytes_be(&bytes);
+    let mut value = random_below(&modulus)?;
+    let started = Instant::now();
+    let mut squarings = 0u64;
+    while started.elapsed() < Duration::from_millis(500) {
+        for _ in 0..1000 {
+            value = &value * &value % &modulus;
+        }
+        squarings += 1000;
+    }
+    Ok((squarings as f64 / started.elapsed().as_secs_f64()) as u64)
+}
+
+/// Vote encryption key whose secret key is locked until the reveal time.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TimeLockedKey {
+    pub reveal_at: DateTime<Utc>,
+    /// Base64 encoded key votes are encrypted to.
+    pub encryption_key: String,
+    pub puzzle: TimeLockPuzzle
+}
+
+impl TimeLockedKey {
+    /// Generate a vote encryption key pair and lock its secret key until
+    /// `reveal_at`, at `squarings_per_second`.
+    pub fn new(reveal_at: DateTime<Utc>, squarings_per_second: u64, modulus_bits: usize) -> Result<Self> {
+        let seconds = (reveal_at - Utc::now()).num_seconds();
+        if seconds <= 0 {
+            return Err(format!("Reveal time {} has passed.", reveal_at).into());
+        }
+        let key = VoteDecryptionKey::generate();
+        let puzzle = TimeLockPuzzle::lock(key.secret_key(), seconds as u64 * squarings_per_second, modulus_bits)?;
+        Ok(TimeLockedKey {
+            reveal_at,
+            encryption_key: key.encryption_key().to_base64(),
+            puzzle
+        })
+    }
+
+    pub fn encryption_key(&self) -> Result<VoteEncryptionKey> {
+        VoteEncryptionKey::from_base64(&self.encryption_key)
+    }
+
+    /// Decryption key of a solved secret key, checked against the
+    /// encryption key.
+    pub fn decryption_key(&self, secret_key: &[u8]) -> Result<VoteDecryptionKey> {
+        let key = VoteDecryptionKey::from_secret_key(secret_key).ok_or("Invalid vote decryption key")?;
+        if key.encryption_key() != self.encryption_key()? {
+            return Err("Vote decryption key does not match the time-locked encryption key.".into());
+        }
+        Ok(key)
+    }
+}
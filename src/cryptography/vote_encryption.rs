@@ -0,0 +1,91 @@
+//! # Vote Encryption
+//!
+//! `vote_encryption` seals vote payloads to a key of the poll, so that the
+//! vote codes are not readable from the transactions before the votes are
+//! counted. The key pair is derived from the poll signing key, which only
+//! the trustees can reconstruct; the public key is published with the poll.
+//!
+//! The specific scheme employed is the libsodium sealed box: X25519 with an
+//! ephemeral key for every payload, and XSalsa20-Poly1305.
+
+use sha2::{Digest, Sha256};
+use signatory::ed25519;
+use signatory::encoding::{Decode, Base64};
+use sodiumoxide::crypto::box_;
+use sodiumoxide::crypto::sealedbox;
+
+use super::{Result, Base64String};
+
+const VOTE_ENCRYPTION_KEY_LABEL: &[u8] = b"7E vote encryption";
+
+/// Public key votes are encrypted to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VoteEncryptionKey(box_::PublicKey);
+
+impl VoteEncryptionKey {
+    pub fn from_base64(value: &str) -> Result<Self> {
+        let key = base64::decode(value.trim())?;
+        box_::PublicKey::from_slice(&key)
+            .map(VoteEncryptionKey)
+            .ok_or_else(|| "Invalid vote encryption key".into())
+    }
+
+    pub fn to_base64(&self) -> String {
+        base64::encode(&self.0)
+    }
+
+    pub fn seal(&self, data: &[u8]) -> Vec<u8> {
+        sealedbox::seal(data, &self.0)
+    }
+}
+
+/// Key pair sealed votes are decrypted with.
+#[derive(Clone)]
+pub struct VoteDecryptionKey {
+    public_key: box_::PublicKey,
+    secret_key: box_::SecretKey
+}
+
+impl VoteDecryptionKey {
+    /// Derive the vote encryption key pair of a poll signing key.
+    pub fn from_signing_key(signing_key: &Base64String) -> Result<Self> {
+        let seed = ed25519::Seed::decode_from_str(&signing_key.0, &Base64::default())
+            .map_err(|_| "Invalid signing key")?;
+        let mut hasher = Sha256::new();
+        hasher.input(VOTE_ENCRYPTION_KEY_LABEL);
+        hasher.input(seed.as_secret_slice());
+        let box_seed = box_::Seed::from_slice(&hasher.result()).ok_or("Invalid signing key")?;
+        let (public_key, secret_key) = box_::keypair_from_seed(&box_seed);
+        Ok(VoteDecryptionKey { public_key, secret_key })
+    }
+
+    /// Generate a random key pair.
+    pub fn generate() -> Self {
+        let (public_key, secret_key) = box_::gen_keypair();
+        VoteDecryptionKey { public_key, secret_key }
+    }
+
+    pub fn from_secret_key(secret_key: &[u8]) -> Option<Self> {
+        let secret_key = box_::SecretKey::from_slice(secret_key)?;
+        Some(VoteDecryptionKey { public_key: secret_key.public_key(), secret_key })
+    }
+
+    pub fn secret_key(&self) -> &[u8] {
+        &self.secret_key.0
+    }
+
+    pub fn encryption_key(&self) -> VoteEncryptionKey {
+        VoteEncryptionKey(self.public_key)
+    }
+
+    /// Decrypt sealed data, or `None` if it was not sealed to this key.
+    pub fn open(&self, sealed: &[u8]) -> Option<Vec<u8>> {
+        sealedbox::open(sealed, &self.public_key, &self.secret_key).ok()
+    }
+}
+
+impl std::fmt::Debug for VoteDecryptionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "VoteDecryptionKey({})", self.encryption_key().to_base64())
+    }
+}
@@ -0,0 +1,291 @@
+//! # Email Delivery
+//!
+//! `email` sends each selected voter a message rendered from a template:
+//! a notification that their ballot is on its way, the receipt of their
+//! recorded vote or, only for polls configured for electronic delivery,
+//! the ballot itself with its vote codes.
+//!
+//! Templates are YAML files with a `subject` and a `body`, in which every
+//! `{placeholder}` is replaced by the value for the voter:
+//!
+//! ```yaml
+//! subject: "{poll}: your ballot is on its way"
+//! body: |
+//!   Dear {first_name} {last_name},
+//!   ballot {serial} has been mailed to you.
+//! ```
+//!
+//! Messages are sent through the SMTP relay of a configuration file, or
+//! written to an outbox directory for inspection, at most `rate` messages
+//! per minute. Every selected voter gets a row in the delivery report,
+//! keyed by ballot serial and roster position.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs::File;
+use std::path::Path;
+use std::str::FromStr;
+use std::thread;
+use std::time::{Duration, Instant};
+use chrono::{DateTime, Utc};
+use lettre::{FileTransport, Message, SmtpTransport, Transport};
+use lettre::message::Mailbox;
+use lettre::message::header::ContentType;
+use lettre::transport::smtp::authentication::Credentials;
+use serde::{Serialize, Deserialize};
+use crate::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EmailKind { Notification, Receipt, Ballot }
+
+pub const EMAIL_KINDS: [&str; 3] = ["notification", "receipt", "ballot"];
+
+/// Placeholders available in all emails.
+const VOTER_PLACEHOLDERS: [&str; 4] = ["poll", "serial", "first_name", "last_name"];
+const RECEIPT_PLACEHOLDERS: [&str; 3] = ["status", "block", "transaction"];
+const BALLOT_PLACEHOLDERS: [&str; 4] = ["choice1", "votecode1", "choice2", "votecode2"];
+
+impl EmailKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EmailKind::Notification => "notification",
+            EmailKind::Receipt => "receipt",
+            EmailKind::Ballot => "ballot"
+        }
+    }
+
+    /// Placeholders a template of this kind may use. Only ballots carry
+    /// vote codes.
+    pub fn placeholders(&self) -> Vec<&'static str> {
+        let specific: &[&'static str] = match self {
+            EmailKind::Notification => &[],
+            EmailKind::Receipt => &RECEIPT_PLACEHOLDERS,
+            EmailKind::Ballot => &BALLOT_PLACEHOLDERS
+        };
+        VOTER_PLACEHOLDERS.iter().chain(specific.iter()).cloned().collect()
+    }
+}
+
+impl FromStr for EmailKind {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "notification" => Ok(EmailKind::Notification),
+            "receipt" => Ok(EmailKind::Receipt),
+            "ballot" => Ok(EmailKind::Ballot),
+            _ => Err(format!("Unknown email kind: {}", value))
+        }
+    }
+}
+
+impl fmt::Display for EmailKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct EmailTemplate {
+    pub subject: String,
+    pub body: String
+}
+
+impl EmailTemplate {
+    pub fn from_file(path: &dyn AsRef<Path>, kind: EmailKind) -> Result<Self> {
+        let template: EmailTemplate = serde_yaml::from_reader(File::open(path)?)?;
+        template.validate(kind)?;
+        Ok(template)
+    }
+
+    /// Built-in template for `kind`.
+    pub fn default_for(kind: EmailKind) -> Self {
+        let (subject, body) = match kind {
+            EmailKind::Notification => (
+                "{poll}: your ballot is on its way",
+                "Dear {first_name} {last_name},\n\n\
+                 Ballot {serial} of the poll {poll} has been mailed to you.\n\
+                 Keep the ballot serial to check your receipt once you have voted.\n"),
+            EmailKind::Receipt => (
+                "{poll}: receipt for ballot {serial}",
+                "Dear {first_name} {last_name},\n\n\
+                 Vote of ballot {serial}: {status}.\n\
+                 Block: {block}\n\
+                 Transaction: {transaction}\n"),
+            EmailKind::Ballot => (
+                "{poll}: your ballot {serial}",
+                "Dear {first_name} {last_name},\n\n\
+                 Your ballot {serial} of the poll {poll}:\n\n\
+                 {choice1}: {votecode1}\n\
+                 {choice2}: {votecode2}\n\n\
+                 Submit the vote code of your choice. Do not share this email.\n")
+        };
+        EmailTemplate { subject: subject.to_owned(), body: body.to_owned() }
+    }
+
+    /// Check that the template only uses the placeholders of `kind`, so
+    /// that vote codes cannot be pasted into notifications or receipts.
+    pub fn validate(&self, kind: EmailKind) -> Result<()> {
+        let available = kind.placeholders();
+        for text in [&self.subject, &self.body].iter() {
+            if let Some(name) = placeholders_in(text).into_iter().find(|name| !available.contains(name)) {
+                return Err(format!("Placeholder {{{}}} is not available in {} emails (expected one of {})",
+                    name, kind, available.join(", ")).into());
+            }
+        }
+        Ok(())
+    }
+
+    /// Render the subject and body with `values`.
+    pub fn render(&self, values: &HashMap<&str, String>) -> (String, String) {
+        let fill = |text: &str| values.iter()
+            .fold(text.to_owned(), |text, (name, value)| text.replace(&format!("{{{}}}", name), value));
+        (fill(&self.subject), fill(&self.body))
+    }
+}
+
+/// Names of the `{placeholder}` in `text`.
+fn placeholders_in(text: &str) -> Vec<&str> {
+    text.split('{').skip(1)
+        .filter_map(|rest| rest.find('}').map(|end| &rest[..end]))
+        .filter(|name| !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'))
+        .collect()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity { Tls, #[default] Starttls, None }
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmtpConfiguration {
+    pub host: String,
+    /// Port of the relay; the default port of `security` if omitted.
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub security: SmtpSecurity,
+    #[serde(default)]
+    pub username: Option<String>,
+    #[serde(default)]
+    pub password: Option<String>,
+    /// Sender of the emails.
+    pub from: String
+}
+
+impl SmtpConfiguration {
+    pub fn from_file(path: &dyn AsRef<Path>) -> Result<Self> {
+        let configuration: SmtpConfiguration = serde_yaml::from_reader(File::open(path)?)?;
+        configuration.from.parse::<Mailbox>()?;
+        Ok(configuration)
+    }
+}
+
+enum MailTransport {
+    Smtp(Box<SmtpTransport>),
+    Outbox(FileTransport)
+}
+
+/// Sends emails at most at the given rate.
+pub struct Mailer {
+    from: Mailbox,
+    transport: MailTransport,
+    interval: Duration,
+    last_sent: Option<Instant>
+}
+
+impl Mailer {
+    /// Send through the SMTP relay of `configuration`.
+    pub fn smtp(configuration: &SmtpConfiguration, per_minute: u32) -> Result<Self> {
+        let mut builder = match configuration.security {
+            SmtpSecurity::Tls => SmtpTransport::relay(&configuration.host)?,
+            SmtpSecurity::Starttls => SmtpTransport::starttls_relay(&configuration.host)?,
+            SmtpSecurity::None => SmtpTransport::builder_dangerous(&configuration.host)
+        };
+        if let Some(port) = configuration.port {
+            builder = builder.port(port);
+        }
+        if let Some(username) = &configuration.username {
+            let password = configuration.password.clone().unwrap_or_default();
+            builder = builder.credentials(Credentials::new(username.clone(), password));
+        }
+        Mailer::new(&configuration.from, MailTransport::Smtp(Box::new(builder.build())), per_minute)
+    }
+
+    /// Write the emails to `outbox` as `.eml` files instead of sending them.
+    pub fn outbox(outbox: &Path, from: &str, per_minute: u32) -> Result<Self> {
+        std::fs::create_dir_all(outbox)?;
+        Mailer::new(from, MailTransport::Outbox(FileTransport::new(outbox)), per_minute)
+    }
+
+    fn new(from: &str, transport: MailTransport, per_minute: u32) -> Result<Self> {
+        if per_minute == 0 {
+            return Err("Rate must be at least one email per minute.".into());
+        }
+        Ok(Mailer {
+            from: from.parse()?,
+            transport,
+            interval: Duration::from_secs(60) / per_minute,
+            last_sent: None
+        })
+    }
+
+    pub fn send(&mut self, to: &str, subject: &str, body: &str) -> Result<()> {
+        let message = Message::builder()
+            .from(self.from.clone())
+            .to(to.parse()?)
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN)
+            .body(body.to_owned())?;
+
+        if let Some(last_sent) = self.last_sent {
+            let elapsed = last_sent.elapsed();
+            if elapsed < self.interval {
+                thread::sleep(self.interval - elapsed);
+            }
+        }
+        self.last_sent = Some(Instant::now());
+        match &self.transport {
+            MailTransport::Smtp(transport) => { transport.send(&message)?; },
+            MailTransport::Outbox(transport) => { transport.send(&message)?; }
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DeliveryStatus { Sent, Failed, Skipped }
+
+/// Row of the delivery report.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeliveryRecord {
+    pub serial: String,
+    pub roster_position: usize,
+    pub email: String,
+    pub status: DeliveryStatus,
+    /// Reason the email failed or was skipped.
+    pub detail: String,
+    pub sent_at: Option<DateTime<Utc>>
+}
+
+/// Row of the contacts file, which maps roster positions to email addresses.
+#[derive(Debug, Clone, Deserialize)]
+struct ContactRow {
+    position: usize,
+    email: String
+}
+
+/// Read a contacts CSV file with `position` and `email` columns.
+pub fn read_contacts(path: &dyn AsRef<Path>) -> Result<HashMap<usize, String>> {
+    let mut csvreader = csv::Reader::from_path(path)?;
+    let mut contacts = HashMap::new();
+    for row in csvreader.deserialize::<ContactRow>() {
+        let row = row?;
+        let email = row.email.trim().to_owned();
+        if !email.is_empty() {
+            contacts.insert(row.position, email);
+        }
+    }
+    Ok(contacts)
+}
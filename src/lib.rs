@@ -33,5 +33,37 @@ pub mod blockchain;
 pub mod ballots;
 use ballots::*;
 
+pub mod votes;
+
+pub mod monitor;
+
+pub mod simulation;
+
+#[cfg(feature = "webhooks")]
+pub mod webhooks;
+
+#[cfg(feature = "email")]
+pub mod email;
+
+pub mod bulletin_board;
+
+pub mod audit_log;
+
+pub mod workspace;
+
+#[cfg(feature = "server")]
+pub mod server;
+
+#[cfg(feature = "python")]
+pub mod python;
+
 pub mod subcommands;
 
+pub mod logging;
+
+pub mod progress;
+
+pub mod output;
+
+pub mod metrics;
+
@@ -0,0 +1,59 @@
+//! # Logging
+//!
+//! `logging` installs the `tracing` subscriber used by the application.
+//! Events from the `log` macros used throughout the crate are forwarded
+//! to the same subscriber, so both end up in the same output stream.
+//!
+//! Two output formats are supported:
+//! * `text`: human readable lines on stderr (default).
+//! * `json`: one JSON object per line on stderr, suitable for log aggregators.
+//!
+//! Output can be muted while a full screen terminal UI is shown.
+
+use std::io::{self, Write};
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use tracing_subscriber::fmt::format::FmtSpan;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LogFormat { Text, Json }
+
+pub const LOG_FORMATS: [&str; 2] = ["text", "json"];
+
+static MUTED: AtomicBool = AtomicBool::new(false);
+
+impl FromStr for LogFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "text" => Ok(LogFormat::Text),
+            "json" => Ok(LogFormat::Json),
+            _ => Err(format!("Unknown log format: {}", value))
+        }
+    }
+}
+
+/// Discard log output, e.g. while the terminal UI owns the screen.
+pub fn set_muted(muted: bool) {
+    MUTED.store(muted, Ordering::Relaxed);
+}
+
+fn make_writer() -> Box<dyn Write> {
+    match MUTED.load(Ordering::Relaxed) {
+        true => Box::new(io::sink()),
+        false => Box::new(io::stderr())
+    }
+}
+
+/// Install the global logging subscriber.
+pub fn init_logging(format: LogFormat) {
+    let builder = tracing_subscriber::fmt()
+        .with_max_level(tracing::Level::DEBUG)
+        .with_span_events(FmtSpan::CLOSE)
+        .with_writer(make_writer);
+    match format {
+        LogFormat::Text => builder.init(),
+        LogFormat::Json => builder.json().init()
+    }
+}
@@ -10,266 +10,1592 @@
 //! * Secure Poll Configuration (YAML)
 //! * Address Labels (CSV)
 //! * Ballot Information (CSV)
-use clap::{Arg, App, SubCommand};
+use std::path::PathBuf;
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueHint};
+use clap_complete::Shell;
 use seventh_estate::subcommands::*;
+use seventh_estate::logging::{init_logging, LogFormat, LOG_FORMATS};
+use seventh_estate::progress::set_quiet;
+use seventh_estate::output::{set_output_format, OutputFormat, OUTPUT_FORMATS};
+use seventh_estate::blockchain::{set_audit_vote_address, DEFAULT_ESPLORA, NETWORK_PRESETS, NODE_EXPORT_FORMATS};
+#[cfg(feature = "keyring")]
+use seventh_estate::blockchain::KEYRING_ENTRIES;
+use seventh_estate::cryptography::{set_verification_key, Base64String};
+use seventh_estate::simulation::SimulationConfiguration;
+use seventh_estate::votes::{read_votecodes_file, VoteSource, VoteChannel, ChainVotes, CachedVotes, MessageExport, FileVotes};
+use seventh_estate::ballots::{VoteCodeSet, BALLOT_EXPORT_FORMATS, REDACTION_PROFILES};
+use seventh_estate::voter_roster::{DEFAULT_MIN_ANONYMITY, ROSTER_FIELDS};
+#[cfg(feature = "email")]
+use seventh_estate::email::EMAIL_KINDS;
 use tokio;
 
 type Exception = Box<dyn std::error::Error + 'static>;
 
+const WORKFLOW: &str = "\
+Poll workflow:
+  new             Create a new poll.
+  bind-roster     Bind roster to poll.
+  step1           Generate initial commitments.
+  step2           Generate drawn summands.
+  step3           Generate address labels and ballot information.
+  step4           Record audited (spoiled) ballots and propose the commit.
+  approve-commit  Approve the commit proposal as a trustee.
+  step4           Commit to the blockchain with the approvals of a quorum.
+  step5           Voting takes place.
+  spoil           Replace a damaged or lost ballot during voting.
+  provisional     Mark provisional ballots, and resolve them before step 8.
+  step6           Record votes.
+  step7           Generate audited plane columns.
+  step8           Generate decrypted plane columns.
+  finalize        Post the hash of the tally report to the blockchain.
+
+Use `status` at any point to see where the poll is in its lifecycle, and
+`workspace` to run several polls side by side.";
+
+#[derive(Parser)]
+#[command(name = "seventh-estate", about = "Seventh-Estate Poll Manager", version = "1.0", after_help = WORKFLOW)]
+struct Cli {
+    /// Log output format.
+    #[arg(long, value_name = "FORMAT", value_parser = LOG_FORMATS, default_value = "text", global = true, help_heading = "Global Options")]
+    log_format: String,
+
+    /// Command result output format.
+    #[arg(long = "output", value_name = "FORMAT", value_parser = OUTPUT_FORMATS, default_value = "text", global = true, help_heading = "Global Options")]
+    output_format: String,
+
+    /// Do not report progress of long operations.
+    #[arg(short, long, global = true, help_heading = "Global Options")]
+    quiet: bool,
+
+    /// Blockchain network, configured in examples/<NETWORK>_config.yaml.
+    #[arg(long, value_name = "NETWORK", value_parser = NETWORK_PRESETS, default_value = NETWORK_PRESETS[0], global = true, help_heading = "Global Options")]
+    network: String,
+
+    /// Base64 encoded verification key checking the signatures of the Merkle
+    /// tree, transaction caches and revealed files when they are loaded.
+    #[arg(long, value_name = "KEY", global = true, help_heading = "Global Options")]
+    artifact_key: Option<String>,
+
+    /// Vote address read by commands that only read the chain, for commit
+    /// receipts that do not record it, instead of vote_address of the
+    /// network configuration.
+    #[arg(long, value_name = "ADDRESS", global = true, help_heading = "Global Options")]
+    vote_address: Option<String>,
+
+    /// Workspace directory, whose active poll is used when no poll
+    /// configuration is given.
+    #[arg(long, value_name = "DIR", value_hint = ValueHint::DirPath, default_value = ".", global = true, help_heading = "Global Options")]
+    workspace: String,
+
+    #[command(subcommand)]
+    command: Command
+}
+
+#[derive(Args)]
+struct PollConfigurationArg {
+    /// Poll configuration YAML file; the active poll of the workspace if omitted.
+    #[arg(short = 'c', long = "config", value_name = "FILE", value_hint = ValueHint::FilePath)]
+    poll_configuration: Option<String>
+}
+
+impl PollConfigurationArg {
+    /// Poll configuration file given, or that of the active poll of the
+    /// workspace, changing to its poll directory.
+    fn resolve(&self, workspace: &str) -> Result<String, Exception> {
+        match &self.poll_configuration {
+            Some(poll_configuration) => Ok(poll_configuration.clone()),
+            None => enter_active_poll(workspace)
+        }
+    }
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Create a new poll.
+    New {
+        /// New poll configuration YAML file.
+        #[arg(short = 'c', long = "config", value_name = "FILE", value_hint = ValueHint::FilePath)]
+        poll_configuration: String,
+        /// File holding the seed of the ballots as hexadecimal string of bytes, or - for standard input; random if omitted.
+        #[arg(short = 's', long = "seed-file", value_name = "FILE", value_hint = ValueHint::FilePath)]
+        generation_seed_file: Option<String>
+    },
+    /// Bind roster to poll.
+    BindRoster {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg,
+        /// Voter roster CSV file.
+        #[arg(long = "roster", value_name = "FILE", value_hint = ValueHint::FilePath)]
+        roster_file: String,
+        /// Commit roster with full voter name and address information.
+        #[arg(long)]
+        disable_voter_privacy: bool,
+        /// Force a re-commit of the voter roster.
+        #[arg(long)]
+        force: bool
+    },
+    /// Add a batch of voters to the roster as its registration closes and commit to it on chain.
+    CommitRosterChunk {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg,
+        /// Voter roster CSV file of the batch.
+        #[arg(long = "roster", value_name = "FILE", value_hint = ValueHint::FilePath)]
+        roster_file: String
+    },
+    /// Choose the roster fields published in the committed roster, the others only as salted hashes.
+    RosterPolicy {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg,
+        /// Roster field to publish; all others are hashed.
+        #[arg(long = "publish", value_name = "FIELD", value_parser = ROSTER_FIELDS, value_delimiter = ',')]
+        published_fields: Vec<String>,
+        /// Smallest number of voters that may share their published fields.
+        #[arg(long, value_name = "K", default_value_t = DEFAULT_MIN_ANONYMITY)]
+        min_anonymity: usize
+    },
+    /// Check that the fields to publish of a roster file cannot identify voters.
+    CheckRosterPrivacy {
+        /// Voter roster CSV file.
+        #[arg(long = "roster", value_name = "FILE", value_hint = ValueHint::FilePath)]
+        roster_file: String,
+        /// Roster field to publish.
+        #[arg(long = "publish", value_name = "FIELD", value_parser = ROSTER_FIELDS, value_delimiter = ',')]
+        published_fields: Vec<String>,
+        /// Smallest number of voters that may share their published fields.
+        #[arg(long, value_name = "K", default_value_t = DEFAULT_MIN_ANONYMITY)]
+        min_anonymity: usize
+    },
+    /// Step 1: Generate initial commitments.
+    Step1 {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg,
+        /// Force a re-commit of the initial commitments.
+        #[arg(long)]
+        force: bool
+    },
+    /// Step 2: Generate drawn summands.
+    Step2 {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg,
+        /// Seed value as hexadecimal string of bytes.
+        #[arg(short = 's', long = "seed", value_name = "HEX")]
+        drawn_summands_seed: String,
+        /// Force a re-generation of the drawn summands.
+        #[arg(long)]
+        force: bool
+    },
+    /// Step 3: Generate address labels and ballot information.
+    Step3 {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg,
+        /// Address label CSV file.
+        #[arg(long = "addresses", value_name = "FILE", value_hint = ValueHint::FilePath)]
+        address_label: String,
+        /// Ballot information CSV file.
+        #[arg(long = "ballots", value_name = "FILE", value_hint = ValueHint::FilePath)]
+        ballot_information: String,
+        /// Ballot layout YAML file.
+        #[arg(long = "layout", value_name = "FILE", value_hint = ValueHint::FilePath)]
+        ballot_layout: Option<String>,
+        /// Print-ready PDF file with all ballots.
+        #[arg(long = "ballots-pdf", value_name = "FILE", value_hint = ValueHint::FilePath)]
+        ballots_pdf: Option<String>
+    },
+    /// Step 4: Record audited (spoiled) ballots.
+    Step4 {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg,
+        /// Ballot serials LIST file.
+        #[arg(long = "serial-file", value_name = "FILE", value_hint = ValueHint::FilePath)]
+        audited_ballots: String,
+        /// Approval of the commit proposal by a trustee, as written by approve-commit.
+        #[arg(long = "approval", value_name = "FILE", value_hint = ValueHint::FilePath)]
+        approvals: Vec<String>,
+        /// Commitment signed on the offline machine, as written by sign-transaction.
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        signed: Option<String>
+    },
+    /// Generate the commit approval key pair of a trustee, printing the public key.
+    ApprovalKeygen {
+        /// File the secret key is written to.
+        #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        out: String
+    },
+    /// Approve the commit proposal of step 4 as a trustee.
+    ApproveCommit {
+        /// Commit proposal written by step 4.
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        proposal: String,
+        /// Identifier of the approving trustee.
+        #[arg(long, value_name = "TRUSTEE")]
+        trustee: String,
+        /// Secret approval key of the trustee; commit_approval_<TRUSTEE>.key by default.
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        key: Option<String>,
+        /// File the approval is written to; commit_approval_<TRUSTEE>.yaml by default.
+        #[arg(long = "approval", value_name = "FILE", value_hint = ValueHint::FilePath)]
+        approval_file: Option<String>
+    },
+    /// Step 5: --VOTE-- (This command does nothing.)
+    Step5,
+    /// Spoil a damaged or lost ballot and record the ballot issued instead.
+    Spoil {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg,
+        /// Serial of the spoiled ballot.
+        #[arg(long, value_name = "SERIAL")]
+        serial: usize,
+        /// Serial of the ballot issued to the voter instead.
+        #[arg(long, value_name = "SERIAL")]
+        replacement: usize,
+        /// Reason the ballot was spoiled, committed with the amendment.
+        #[arg(long, value_name = "TEXT")]
+        reason: Option<String>,
+        /// Commitment of the fresh vote codes of the replacement, issued by
+        /// the print pipeline with `envelope issue`.
+        #[arg(long, value_name = "HASH")]
+        commitment: Option<String>
+    },
+    /// Issue replacement ballots with fresh vote codes on the print pipeline.
+    Envelope {
+        #[command(subcommand)]
+        command: EnvelopeCommand
+    },
+    /// Mark provisional ballots and resolve the eligibility of their voters.
+    Provisional {
+        #[command(subcommand)]
+        command: ProvisionalCommand
+    },
+    /// Step 6: Record votes.
+    Step6 {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg,
+        /// Votes recorded CSV file.
+        #[arg(long = "votes", value_name = "FILE", value_hint = ValueHint::FilePath, required_unless_present_any = ["chain", "cached", "sms", "api"])]
+        votes_file: Option<String>,
+        /// Also record the votes submitted on the blockchain since the commitment.
+        #[arg(long)]
+        chain: bool,
+        /// Also record the votes of a transaction cache written by `cache-transactions`.
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        cached: Option<String>,
+        /// Also record the votes received by SMS, from a gateway export (CSV or JSON)
+        /// or an API URL (requires the sms feature).
+        #[arg(long, value_name = "FILE|URL")]
+        sms: Option<String>,
+        /// Bearer token for the SMS gateway API.
+        #[arg(long, value_name = "TOKEN", requires = "sms")]
+        sms_token: Option<String>,
+        /// Also record the votes of a web API export, in the format of SMS exports.
+        #[arg(long, value_name = "FILE|URL")]
+        api: Option<String>,
+        /// Bearer token for the web API.
+        #[arg(long, value_name = "TOKEN", requires = "api")]
+        api_token: Option<String>,
+        /// Test vote codes, with a `votecode` column, reported instead of counted.
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        test_codes: Option<String>,
+        /// Log every counted vote.
+        #[arg(short, long)]
+        verbose: bool,
+        /// Force an overwrite of the recorded votes.
+        #[arg(long)]
+        force: bool
+    },
+    /// Solve the time lock of the votes of a poll with a reveal time.
+    UnlockVotes {
+        /// Time-lock puzzle published in the data directory of the poll.
+        #[arg(long)]
+        time_lock: String
+    },
+    /// Step 7: Generate audited plane columns.
+    Step7 {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg,
+        /// Seed value as hexadecimal string of bytes.
+        #[arg(short = 's', long = "seed", value_name = "HEX")]
+        tally_audit_seed: String,
+        /// Force a re-generation of the audited planes columns.
+        #[arg(long)]
+        force: bool
+    },
+    /// Step 8: Generated decrypted plane columns.
+    Step8 {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg,
+        /// Append the sender, block and decoded payload format of every counted vote to the tally report.
+        #[arg(long)]
+        provenance: bool,
+        /// Force a re-decrypt of the plane columns.
+        #[arg(long)]
+        force: bool
+    },
+    /// Post the hash of the signed tally report to the blockchain as the closing commitment.
+    Finalize {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg
+    },
+    /// Check a tally report against its finalization receipt and transaction.
+    VerifyTally {
+        /// Tally report, as published on the bulletin board.
+        #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        tally: String,
+        /// Finalization receipt, as published on the bulletin board.
+        #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        receipt: String
+    },
+    /// Check a ballot question against its commit receipt and transaction.
+    VerifyQuestion {
+        /// Ballot question, as published on the bulletin board.
+        #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        question: String,
+        /// Commit receipt, as published on the bulletin board.
+        #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        receipt: String
+    },
+    /// Check the ballot information file sent to the printer against the committed Merkle tree.
+    VerifyPrintFile {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg,
+        /// Ballot information CSV file, as written by step 3.
+        #[arg(long = "ballots", value_name = "FILE", value_hint = ValueHint::FilePath)]
+        ballot_information: String,
+        /// Merkle tree in YAML format, as stored by step 4.
+        #[arg(short = 'm', long = "merkle", value_name = "FILE", value_hint = ValueHint::FilePath, default_value = "merkle.yaml")]
+        merkle_tree: String
+    },
+    /// Check the ballot information file for vote codes shared by more than one ballot choice.
+    CheckVotecodes {
+        /// Ballot information CSV file, as written by step 3.
+        #[arg(long = "ballots", value_name = "FILE", value_hint = ValueHint::FilePath)]
+        ballot_information: String
+    },
+    /// Write the ballot information file and the committed planes again from the sealed seed of the poll.
+    RegenerateBallots {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg,
+        /// Directory to write the regenerated files to; existing files are not overwritten.
+        #[arg(short, long, value_name = "DIR", value_hint = ValueHint::DirPath)]
+        out: String
+    },
+    /// Export the ballots, redacted for sealed escrow, the print vendor or public audit.
+    ExportBallots {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg,
+        /// Redaction profile: everything, serials and vote codes, or serials and commitments.
+        #[arg(long, value_name = "PROFILE", value_parser = REDACTION_PROFILES)]
+        profile: String,
+        /// Format of the export file.
+        #[arg(long, value_name = "FORMAT", value_parser = BALLOT_EXPORT_FORMATS, default_value = "csv")]
+        format: String,
+        /// Output file.
+        #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        out: String
+    },
+    /// Export the mailing labels of the voters the ballots are mailed to.
+    MailingLabels {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg,
+        /// Format of the labels: print-house CSV or one label per paragraph.
+        #[arg(long, value_name = "FORMAT", value_parser = MAILING_LABEL_FORMATS, default_value = "csv")]
+        format: String,
+        /// Country the ballots are mailed from, and of voters without a country field.
+        #[arg(long, value_name = "CODE", default_value = "US")]
+        country: String,
+        /// Output file.
+        #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        out: String
+    },
+    /// Record the ballots the mailing vendor returned as undeliverable.
+    ImportUndeliverable {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg,
+        /// Undeliverable list CSV file, with a serial and an optional reason column.
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        list: String
+    },
+    /// Escrow the map of the vote codes to their ballots and choices.
+    Escrow {
+        #[command(subcommand)]
+        command: EscrowCommand
+    },
+    /// Check that the ballots regenerated from the sealed seed hash to the committed artifacts.
+    CheckRegeneration {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg,
+        /// Ballot information CSV file sent to the printer, to check as well.
+        #[arg(long = "ballots", value_name = "FILE", value_hint = ValueHint::FilePath)]
+        ballot_information: Option<String>
+    },
+    /// Timestamp the commitment of the poll with OpenTimestamps (requires the timestamps feature).
+    StampCommit {
+        /// Data directory of the poll.
+        #[arg(long, value_name = "DIR", value_hint = ValueHint::DirPath)]
+        datadir: String,
+        /// Calendar to submit the commitment to, in place of the public calendars.
+        #[arg(long = "calendar", value_name = "URL")]
+        calendars: Vec<String>
+    },
+    /// Check an OpenTimestamps proof against its commit receipt.
+    VerifyStamp {
+        /// Commit receipt, as published on the bulletin board.
+        #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        receipt: String,
+        /// Proof, by default commit_receipt.ots next to the receipt.
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        proof: Option<String>,
+        /// Complete pending attestations from the calendars and check Bitcoin attestations (requires the timestamps feature).
+        #[arg(long)]
+        upgrade: bool,
+        /// Esplora API Bitcoin blocks are read from.
+        #[arg(long, value_name = "URL", default_value = DEFAULT_ESPLORA)]
+        esplora: String
+    },
+    /// Compare two recounts of a poll, such as the official and an independent one.
+    CompareTally {
+        /// Official recount, or transactions written by cache-transactions.
+        #[arg(value_name = "OFFICIAL", value_hint = ValueHint::FilePath)]
+        official: String,
+        /// Independent recount, or transactions written by cache-transactions.
+        #[arg(value_name = "INDEPENDENT", value_hint = ValueHint::FilePath)]
+        independent: String,
+        /// Poll identifier to recount cached transactions for.
+        #[arg(long)]
+        poll: Option<String>
+    },
+    /// Report the gas used and ether spent by the transactions sent for the poll.
+    SpendReport {
+        /// Data directory of the poll.
+        #[arg(long, value_name = "DIR", value_hint = ValueHint::DirPath)]
+        datadir: String
+    },
+    /// Report the transactions to the vote address that could not change the tally, by sender.
+    InterferenceReport {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg
+    },
+    /// Show where the poll is in its lifecycle.
+    Status {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg,
+        /// Check the commitment transaction on the blockchain.
+        #[arg(long)]
+        chain: bool
+    },
+    /// Check on the blockchain whether a vote was recorded for a ballot.
+    VerifyReceipt {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg,
+        /// Ballot serial.
+        #[arg(short, long, value_name = "SERIAL", required_unless_present = "receipt", conflicts_with = "receipt")]
+        serial: Option<String>,
+        /// Vote code or receipt QR code payload.
+        #[arg(short, long, value_name = "CODE")]
+        receipt: Option<String>
+    },
+    /// Follow the votes submitted for the poll on the blockchain.
+    Monitor {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg,
+        /// Time between refreshes.
+        #[arg(long, value_name = "SECONDS", default_value_t = 15)]
+        interval: u64,
+        /// Show the terminal UI instead of printing a report on every refresh.
+        #[arg(long, conflicts_with = "once")]
+        tui: bool,
+        /// Refresh once, print the report and exit.
+        #[arg(long)]
+        once: bool,
+        /// Post the poll events to the webhooks of this YAML file (requires the webhooks feature).
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        webhooks: Option<String>,
+        /// Serve `GET /metrics` for Prometheus on this address (requires the server feature).
+        #[arg(long, value_name = "ADDRESS")]
+        metrics: Option<String>,
+        /// Warn when the commit or relayer account pays for fewer transactions than this.
+        #[arg(long, value_name = "TRANSACTIONS")]
+        low_balance: Option<u64>
+    },
+    /// Cache the transactions sent to the vote address in a JSON file.
+    CacheTransactions {
+        /// Commit receipt of the poll, as published on the bulletin board.
+        #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath, required_unless_present = "from_block", conflicts_with = "from_block")]
+        receipt: Option<String>,
+        /// First block to read.
+        #[arg(long, value_name = "BLOCK")]
+        from_block: Option<u64>,
+        /// Output file.
+        #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath, default_value = "transactions.json")]
+        out: String,
+        /// Poll configuration YAML file, to sign the cache with the poll signing key.
+        #[arg(short = 'c', long = "config", value_name = "FILE", value_hint = ValueHint::FilePath)]
+        poll_configuration: Option<String>
+    },
+    /// Cache the transactions sent to the vote address from a node export, to audit without a node.
+    ImportNodeExport {
+        /// Format of the export: a trace_filter dump or a geth export.
+        #[arg(long, value_name = "FORMAT", value_parser = NODE_EXPORT_FORMATS)]
+        format: String,
+        /// Node export file.
+        #[arg(value_name = "EXPORT", value_hint = ValueHint::FilePath)]
+        input: String,
+        /// Commit receipt of the poll, as published on the bulletin board.
+        #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        receipt: String,
+        /// Output file.
+        #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath, default_value = "transactions.json")]
+        out: String,
+        /// Poll configuration YAML file, to sign the cache with the poll signing key.
+        #[arg(short = 'c', long = "config", value_name = "FILE", value_hint = ValueHint::FilePath)]
+        poll_configuration: Option<String>
+    },
+    /// Sign a transaction built by step 4 with the commit key, on the offline machine.
+    SignTransaction {
+        /// Unsigned transaction, as written by step 4.
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath, default_value = "unsigned_transaction.yaml")]
+        unsigned: String,
+        /// Output file.
+        #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath, default_value = "signed_transaction.yaml")]
+        out: String
+    },
+    /// Hand the vote address over to a new address, to rotate the posting key.
+    RotateKey {
+        /// Commit receipt of the poll, as published on the bulletin board.
+        #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        receipt: String,
+        /// New vote address.
+        #[arg(long, value_name = "ADDRESS")]
+        to: String
+    },
+    /// Manage the keys and node of the network kept in the operating system keyring.
+    #[cfg(feature = "keyring")]
+    Keyring {
+        #[command(subcommand)]
+        command: KeyringCommand
+    },
+    /// Run a simulated poll against an in-memory chain and check its tally.
+    Simulate {
+        /// Directory the files of the simulated poll are written to.
+        #[arg(long, value_name = "DIR", value_hint = ValueHint::DirPath, default_value = "simulation")]
+        dir: String,
+        /// Size of the synthetic voter roster.
+        #[arg(long, value_name = "COUNT", default_value_t = 200)]
+        voters: usize,
+        /// Number of ballots.
+        #[arg(long, value_name = "COUNT", default_value_t = 100)]
+        ballots: usize,
+        /// Number of decoy ballots.
+        #[arg(long, value_name = "COUNT", default_value_t = 10)]
+        decoys: usize,
+        /// Number of ballots spoiled in the public audit.
+        #[arg(long, value_name = "COUNT", default_value_t = 5)]
+        audited: usize,
+        /// Probability that a ballot is voted.
+        #[arg(long, value_name = "RATE", default_value_t = 0.8)]
+        turnout: f64,
+        /// Probability that a vote is submitted twice.
+        #[arg(long, value_name = "RATE", default_value_t = 0.1)]
+        duplicate_rate: f64,
+        /// Probability that a vote is followed by an invalid submission.
+        #[arg(long, value_name = "RATE", default_value_t = 0.05)]
+        invalid_rate: f64,
+        /// Seed value as hexadecimal string of bytes, to repeat a simulation.
+        #[arg(short = 's', long = "seed", value_name = "HEX")]
+        seed: Option<String>
+    },
+    /// Run the logic and accuracy test of the poll on a known test deck against an in-memory chain.
+    LogicAccuracy {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg,
+        /// Directory the files of the test poll are written to.
+        #[arg(long, value_name = "DIR", value_hint = ValueHint::DirPath, default_value = "logic_and_accuracy")]
+        dir: String,
+        /// Seed value as hexadecimal string of bytes, to repeat a test.
+        #[arg(short = 's', long = "seed", value_name = "HEX")]
+        seed: Option<String>
+    },
+    /// Email the selected voters their notification, receipt or electronic ballot.
+    #[cfg(feature = "email")]
+    Email {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg,
+        /// Email to send; ballots include the vote codes and require electronic delivery.
+        #[arg(long, value_name = "KIND", value_parser = EMAIL_KINDS)]
+        kind: String,
+        /// CSV file with the `position` in the roster and `email` of the voters.
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        contacts: String,
+        /// Template in YAML format with `subject` and `body`.
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        template: Option<String>,
+        /// SMTP relay configuration in YAML format.
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath, required_unless_present = "outbox", conflicts_with = "outbox")]
+        smtp: Option<String>,
+        /// Write the emails to this directory instead of sending them.
+        #[arg(long, value_name = "DIR", value_hint = ValueHint::DirPath, requires = "from")]
+        outbox: Option<String>,
+        /// Sender of the emails written to the outbox.
+        #[arg(long, value_name = "ADDRESS")]
+        from: Option<String>,
+        /// Maximum number of emails sent per minute.
+        #[arg(long, value_name = "COUNT", default_value_t = 30)]
+        rate: u32,
+        /// Delivery report.
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath, default_value = "email_report.csv")]
+        report: String
+    },
+    /// Serve the vote relay and verification HTTP endpoints.
+    #[cfg(feature = "server")]
+    Serve {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg,
+        /// Address to listen on.
+        #[arg(long, value_name = "ADDRESS", default_value = "127.0.0.1:8080")]
+        listen: String,
+        /// Merkle tree in YAML format, as stored by step 4.
+        #[arg(short = 'm', long = "merkle", value_name = "FILE", value_hint = ValueHint::FilePath, default_value = "merkle.yaml")]
+        merkle_tree: String,
+        /// Also serve the registrar gRPC service on this address (requires the grpc feature).
+        #[arg(long, value_name = "ADDRESS")]
+        grpc: Option<String>,
+        /// File with the token of the help desk, to serve ballot lookups to its holders.
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        helpdesk_token: Option<String>,
+        /// Requests accepted per minute from every client address.
+        #[arg(long, value_name = "REQUESTS", value_parser = clap::value_parser!(u32).range(1..))]
+        rate_limit: Option<u32>,
+        /// Warn when the commit or relayer account pays for fewer transactions than this.
+        #[arg(long, value_name = "TRANSACTIONS")]
+        low_balance: Option<u64>,
+        /// Close the voting window at this time (RFC 3339) and record the votes.
+        #[arg(long, value_name = "TIME")]
+        close_at: Option<String>,
+        /// Close the voting window once the closing block of the poll is mined and record the votes.
+        #[arg(long)]
+        close_at_end_block: bool,
+        /// Audited columns seed as hexadecimal string of bytes, to count the tally once voting closes.
+        #[arg(long, value_name = "HEX")]
+        tally_audit_seed: Option<String>,
+        /// Post the closing events to the webhooks of this configuration (requires the webhooks feature).
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        webhooks: Option<String>
+    },
+    /// Serve only the proofs of inclusion of a published Merkle tree, for the public.
+    #[cfg(feature = "server")]
+    ServeProofs {
+        /// Directory of the Merkle tree, such as a bulletin board directory.
+        #[arg(long, value_name = "DIR", value_hint = ValueHint::DirPath)]
+        dir: String,
+        /// Address to listen on.
+        #[arg(long, value_name = "ADDRESS", default_value = "127.0.0.1:8082")]
+        listen: String,
+        /// Requests accepted per minute from every client address.
+        #[arg(long, value_name = "REQUESTS", default_value_t = 60, value_parser = clap::value_parser!(u32).range(1..))]
+        rate_limit: u32
+    },
+    /// Manage the polls of a workspace and archive completed polls.
+    Workspace {
+        #[command(subcommand)]
+        command: WorkspaceCommand
+    },
+    /// Export the public artifacts of a finalized poll, and check or import an export.
+    Archive {
+        #[command(subcommand)]
+        command: ArchiveCommand
+    },
+    /// Publish, verify, serve and mirror the public bulletin board.
+    Board {
+        #[command(subcommand)]
+        command: BoardCommand
+    },
+    /// Generate signature for a file to publish.
+    Sign {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg,
+        /// File for which to generate a signature.
+        #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        file: String
+    },
+    /// Generate proof of inclusion for data in YAML format.
+    Gen {
+        /// Merkle tree in YAML format.
+        #[arg(short = 'm', long = "merkle", value_name = "FILE", value_hint = ValueHint::FilePath)]
+        merkle_tree: String,
+        /// Data to generate proof of.
+        #[arg(short, long, value_name = "STRING")]
+        data: String
+    },
+    /// Validate proof of inclusion given in YAML format.
+    Validate {
+        /// Proof of inclusion in YAML format (Given by gen subcommand).
+        #[arg(short = 'p', long = "proof", value_name = "FILE", value_hint = ValueHint::FilePath)]
+        inclusion_proof: String
+    },
+    /// Locate the leaves of a voter or ballot in a Merkle tree by its leaf index, with their proofs.
+    Locate {
+        /// Merkle tree in YAML format, stored with its leaf index.
+        #[arg(short = 'm', long = "merkle", value_name = "FILE", value_hint = ValueHint::FilePath, default_value = "merkle.yaml")]
+        merkle_tree: String,
+        /// Roster position of a voter.
+        #[arg(long, value_name = "POSITION", required_unless_present_any = ["serial", "leaf"], conflicts_with_all = ["serial", "leaf"])]
+        voter: Option<usize>,
+        /// Ballot serial: its audited ballot leaf and plane cells.
+        #[arg(long, value_name = "SERIAL", conflicts_with = "leaf")]
+        serial: Option<usize>,
+        /// Leaf number.
+        #[arg(long, value_name = "N")]
+        leaf: Option<usize>
+    },
+    /// Open a committed plane cell for a challenge, with its proof of inclusion.
+    RevealCell {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg,
+        /// Merkle tree in YAML format, as stored by step 4.
+        #[arg(short = 'm', long = "merkle", value_name = "FILE", value_hint = ValueHint::FilePath, default_value = "merkle.yaml")]
+        merkle_tree: String,
+        /// Plane number, from 1.
+        #[arg(long, value_name = "N")]
+        plane: usize,
+        /// Row of the committed plane file, from 1.
+        #[arg(long, value_name = "N")]
+        row: usize,
+        /// Column of the cell: 1 or 3.
+        #[arg(long, value_name = "N")]
+        column: usize,
+        /// Who requested the reveal, for the audit log.
+        #[arg(long, value_name = "NAME")]
+        challenger: String,
+        /// Opening file.
+        #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        out: String
+    },
+    /// Check a cell opening written by reveal-cell.
+    VerifyCell {
+        /// Opening file.
+        #[arg(value_name = "FILE", value_hint = ValueHint::FilePath)]
+        opening: String
+    },
+    /// Precompute the proofs of inclusion of a Merkle tree into its proof index.
+    IndexProofs {
+        /// Merkle tree in YAML format.
+        #[arg(short = 'm', long = "merkle", value_name = "FILE", value_hint = ValueHint::FilePath)]
+        merkle_tree: String,
+        /// Milliseconds to pause after every 4096 leaves, to spare a busy machine.
+        #[arg(long, value_name = "MILLISECONDS", default_value_t = 0)]
+        throttle_ms: u64
+    },
+    /// Print the shell completion script.
+    Completions {
+        /// Shell to generate the completion script for.
+        #[arg(value_name = "SHELL")]
+        shell: Shell
+    },
+    /// Generate the man pages.
+    Man {
+        /// Directory to write the man pages to.
+        #[arg(long, value_name = "DIR", value_hint = ValueHint::DirPath, default_value = ".")]
+        dir: PathBuf
+    }
+}
+
+#[derive(Subcommand)]
+enum EnvelopeCommand {
+    /// Generate the key pair of the print pipeline, printing the public key.
+    Keygen {
+        /// File the secret key is written to.
+        #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        out: String
+    },
+    /// Issue a replacement ballot with fresh vote codes, sealed in an envelope.
+    Issue {
+        /// Identifier of the poll.
+        #[arg(long, value_name = "POLL")]
+        poll: String,
+        /// Serial of the spoiled ballot.
+        #[arg(long, value_name = "SERIAL")]
+        spoiled: usize,
+        /// Serial of the replacement ballot.
+        #[arg(long, value_name = "SERIAL")]
+        replacement: usize,
+        /// Base64 encoded public key of the print pipeline.
+        #[arg(long, value_name = "KEY")]
+        key: String,
+        /// CSV file the ballot to print is written to.
+        #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        out: String,
+        /// File the envelope is written to; replacement_envelope_<SERIAL>.yaml by default.
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        envelope: Option<String>
+    },
+    /// Open a replacement envelope once voting closed and write its vote codes for step 6.
+    Open {
+        /// Envelope file of the replacement ballot.
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        envelope: String,
+        /// Secret key file of the print pipeline.
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        key: String,
+        /// Published amendments of the poll.
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        amendments: String,
+        /// File the vote codes are written to; replacement_codes_<SERIAL>.yaml by default.
+        #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        out: Option<String>
+    }
+}
+
+#[derive(Subcommand)]
+enum EscrowCommand {
+    /// Generate the key pair of an escrow holder, printing the public key.
+    Keygen {
+        /// Name of the holder.
+        #[arg(long, value_name = "NAME")]
+        holder: String,
+        /// File the secret key is written to.
+        #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        out: String
+    },
+    /// Seal the ballot map to the escrow keys and write the commitment to publish.
+    Seal {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg,
+        /// YAML file of the escrow holders, their public keys and the quorum.
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        keys: String
+    },
+    /// Open an escrowed ballot map with the secret keys of a quorum of holders.
+    Open {
+        /// Escrow file of the poll.
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        escrow: String,
+        /// Secret key file of a holder; repeat for every holder.
+        #[arg(long = "key", value_name = "FILE", value_hint = ValueHint::FilePath, required = true)]
+        keys: Vec<String>,
+        /// CSV file the ballot map is written to.
+        #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        out: String
+    }
+}
+
+#[derive(Subcommand)]
+enum ProvisionalCommand {
+    /// Mark a ballot issued to a voter whose eligibility is checked later.
+    Mark {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg,
+        /// Serial of the provisional ballot.
+        #[arg(long, value_name = "SERIAL")]
+        serial: usize
+    },
+    /// Record whether the voter of a provisional ballot is eligible.
+    Resolve {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg,
+        /// Serial of the provisional ballot.
+        #[arg(long, value_name = "SERIAL")]
+        serial: usize,
+        /// The voter is eligible: count the votes of the ballot.
+        #[arg(long, conflicts_with = "reject", required_unless_present = "reject")]
+        accept: bool,
+        /// The voter is not eligible: do not count the votes of the ballot.
+        #[arg(long)]
+        reject: bool
+    }
+}
+
+#[derive(Subcommand)]
+enum WorkspaceCommand {
+    /// Create an empty workspace.
+    Init,
+    /// Create a new poll in a poll directory of its own and make it active.
+    Create {
+        /// New poll configuration YAML file.
+        #[arg(short = 'c', long = "config", value_name = "FILE", value_hint = ValueHint::FilePath)]
+        poll_configuration: String,
+        /// File holding the seed of the ballots as hexadecimal string of bytes, or - for standard input; random if omitted.
+        #[arg(short = 's', long = "seed-file", value_name = "FILE", value_hint = ValueHint::FilePath)]
+        generation_seed_file: Option<String>
+    },
+    /// Register a poll directory of the workspace and make it active.
+    Add {
+        /// Poll directory in the workspace.
+        #[arg(long, value_name = "DIR", value_hint = ValueHint::DirPath)]
+        dir: String,
+        /// Secured poll configuration file in the poll directory.
+        #[arg(short = 'c', long = "config", value_name = "FILE")]
+        poll_configuration: String
+    },
+    /// List the polls of the workspace.
+    List,
+    /// Switch the active poll.
+    Use {
+        /// Poll directory or identifier.
+        poll: String
+    },
+    /// Archive a finalized poll into a signed tarball.
+    Archive {
+        /// Poll directory or identifier; the active poll if omitted.
+        poll: Option<String>,
+        /// Archive file; <poll directory>.tar.gz in the workspace if omitted.
+        #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        out: Option<String>
+    },
+    /// Check the files of a poll archive against its signed manifest.
+    VerifyArchive {
+        /// Archive file.
+        archive: String,
+        /// Expected poll verification key (base64).
+        #[arg(long, value_name = "KEY")]
+        public_key: Option<String>
+    }
+}
+
+#[derive(Subcommand)]
+enum ArchiveCommand {
+    /// Pack the public artifacts of a finalized poll into a signed archive.
+    Export {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg,
+        /// Merkle tree in YAML format, as stored by step 4.
+        #[arg(short = 'm', long = "merkle", value_name = "FILE", value_hint = ValueHint::FilePath, default_value = "merkle.yaml")]
+        merkle_tree: String,
+        /// Transaction cache, as written by `cache-transactions`.
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath, default_value = "transactions.json")]
+        transactions: String,
+        /// Archive file; <poll>-public.tar.gz if omitted.
+        #[arg(short, long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        out: Option<String>
+    },
+    /// Check a public archive against its manifest and finalization receipt.
+    Verify {
+        /// Archive file.
+        archive: String,
+        /// Expected poll verification key (base64).
+        #[arg(long, value_name = "KEY")]
+        public_key: Option<String>
+    },
+    /// Check a public archive and extract it into a directory.
+    Import {
+        /// Archive file.
+        archive: String,
+        /// Directory to extract the archive into.
+        #[arg(long, value_name = "DIR", value_hint = ValueHint::DirPath)]
+        dir: String,
+        /// Trusted poll verification key (base64).
+        #[arg(long, value_name = "KEY")]
+        public_key: String
+    }
+}
+
+#[derive(Subcommand)]
+enum BoardCommand {
+    /// Copy the public artifacts of a poll into a board directory with a signed manifest.
+    Publish {
+        #[command(flatten)]
+        pollconf: PollConfigurationArg,
+        /// Board directory.
+        #[arg(long, value_name = "DIR", value_hint = ValueHint::DirPath)]
+        dir: String,
+        /// Merkle tree in YAML format, as stored by step 4.
+        #[arg(short = 'm', long = "merkle", value_name = "FILE", value_hint = ValueHint::FilePath, default_value = "merkle.yaml")]
+        merkle_tree: String
+    },
+    /// Check the artifacts of a board directory against its signed manifest.
+    Verify {
+        /// Board directory.
+        #[arg(long, value_name = "DIR", value_hint = ValueHint::DirPath)]
+        dir: String,
+        /// Expected poll verification key (base64).
+        #[arg(long, value_name = "KEY")]
+        public_key: Option<String>
+    },
+    /// Report the audit of the poll from a board directory, as HTML or PDF for publication.
+    Report {
+        /// Board directory.
+        #[arg(long, value_name = "DIR", value_hint = ValueHint::DirPath)]
+        dir: String,
+        /// Expected poll verification key (base64).
+        #[arg(long, value_name = "KEY")]
+        public_key: Option<String>,
+        /// HTML file to write the report to.
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        html: Option<String>,
+        /// PDF file to write the report to.
+        #[arg(long, value_name = "FILE", value_hint = ValueHint::FilePath)]
+        pdf: Option<String>
+    },
+    /// Recount the tally of a board directory from its final planes, without the ballots.
+    Recount {
+        /// Board directory.
+        #[arg(long, value_name = "DIR", value_hint = ValueHint::DirPath)]
+        dir: String
+    },
+    /// Serve a board directory over HTTP.
+    #[cfg(feature = "server")]
+    Serve {
+        /// Board directory.
+        #[arg(long, value_name = "DIR", value_hint = ValueHint::DirPath)]
+        dir: String,
+        /// Address to listen on.
+        #[arg(long, value_name = "ADDRESS", default_value = "127.0.0.1:8081")]
+        listen: String
+    },
+    /// Download a served board into a directory, checking every artifact.
+    #[cfg(feature = "server")]
+    Mirror {
+        /// URL of the served board.
+        #[arg(long, value_name = "URL", value_hint = ValueHint::Url)]
+        url: String,
+        /// Board directory.
+        #[arg(long, value_name = "DIR", value_hint = ValueHint::DirPath)]
+        dir: String,
+        /// Expected poll verification key (base64).
+        #[arg(long, value_name = "KEY")]
+        public_key: Option<String>
+    }
+}
+
+#[cfg(feature = "keyring")]
+#[derive(Subcommand)]
+enum KeyringCommand {
+    /// Store an entry, prompting for its value.
+    Set {
+        /// Network configuration value to store.
+        #[arg(long, value_name = "ENTRY", value_parser = KEYRING_ENTRIES)]
+        entry: String
+    },
+    /// Delete an entry.
+    Delete {
+        /// Network configuration value to delete.
+        #[arg(long, value_name = "ENTRY", value_parser = KEYRING_ENTRIES)]
+        entry: String
+    }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Exception> {
-    let matches = App::new("Seventh-Estate")
-        .about("Seventh-Estate Poll Manager")
-        .version("1.0")
-        .subcommand(SubCommand::with_name("new")
-            .about("Create a new poll.")
-            .arg(Arg::with_name("poll_configuration")
-                .short("c")
-                .long("config")
-                .value_name("FILE")
-                .help("Poll configuration YAML file.")
-                .required(true)))
-        .subcommand(SubCommand::with_name("bind-roster")
-            .about("Bind roster to poll.")
-            .arg(Arg::with_name("poll_configuration")
-                .short("c")
-                .long("config")
-                .value_name("FILE")
-                .help("Poll configuration YAML file.")
-                .required(true))
-            .arg(Arg::with_name("roster_file")
-                .long("roster")
-                .value_name("FILE")
-                .help("Voter roster CSV file.")
-                .required(true))
-            .arg(Arg::with_name("disable_voter_privacy")
-                .long("disable-voter-privacy")
-                .help("Commit roster with full voter name and address information.")
-                .required(false))
-            .arg(Arg::with_name("force")
-                .long("force")
-                .help("Force a re-commit of the voter roster.")
-                .required(false)))
-        .subcommand(SubCommand::with_name("step1")
-            .about("Step 1: Generate initial commitments.")
-            .arg(Arg::with_name("poll_configuration")
-                .short("c")
-                .long("config")
-                .value_name("FILE")
-                .help("Poll configuration YAML file.")
-                .required(true))
-            .arg(Arg::with_name("force")
-                .long("force")
-                .help("Force a re-commit of the initial commitments.")
-                .required(false)))
-        .subcommand(SubCommand::with_name("step2")
-            .about("Step 2: Generate drawn summands.")
-            .arg(Arg::with_name("poll_configuration")
-                .short("c")
-                .long("config")
-                .value_name("FILE")
-                .help("Poll configuration YAML file.")
-                .required(true))
-            .arg(Arg::with_name("drawn_summands_seed")
-                .short("s")
-                .long("seed")
-                .value_name("HEX")
-                .help("Seed value as hexadecimal string of bytes.")
-                .required(true))
-            .arg(Arg::with_name("force")
-                .long("force")
-                .help("Force a re-generation of the drawn summands.")
-                .required(false)))
-        .subcommand(SubCommand::with_name("step3")
-            .about("Step 3: Generate address labels and ballot information.")
-            .arg(Arg::with_name("poll_configuration")
-                .short("c")
-                .long("config")
-                .value_name("FILE")
-                .help("Poll configuration YAML file.")
-                .required(true))
-            .arg(Arg::with_name("address_label")
-                .long("addresses")
-                .value_name("FILE")
-                .help("Address label CSV file.")
-                .required(true))
-            .arg(Arg::with_name("ballot_information")
-                .long("ballots")
-                .value_name("FILE")
-                .help("Ballot information CSV file.")
-                .required(true)))
-        .subcommand(SubCommand::with_name("step4")
-            .about("Step 4: Record audited (spoiled) ballots.")
-            .arg(Arg::with_name("poll_configuration")
-                .short("c")
-                .long("config")
-                .value_name("FILE")
-                .help("Poll configuration YAML file.")
-                .required(true))
-            .arg(Arg::with_name("audited_ballots")
-                .long("serial-file")
-                .value_name("FILE")
-                .help("Ballot serials LIST file.")
-                .required(true)))
-        .subcommand(SubCommand::with_name("step5")
-            .about("Step 5: --VOTE-- (This command does nothing.)"))
-        .subcommand(SubCommand::with_name("step6")
-            .about("Step 6: Record votes.")
-            .arg(Arg::with_name("poll_configuration")
-                .short("c")
-                .long("config")
-                .value_name("FILE")
-                .help("Poll configuration YAML file.")
-                .required(true))
-            .arg(Arg::with_name("votes_file")
-                .long("votes")
-                .value_name("FILE")
-                .help("Votes recorded CSV file.")
-                .required(true))
-            .arg(Arg::with_name("force")
-                .long("force")
-                .help("Force an overwrite of the recorded votes.")
-                .required(false)))
-        .subcommand(SubCommand::with_name("step7")
-            .about("Step 7: Generate audited plane columns.")
-            .arg(Arg::with_name("poll_configuration")
-                .short("c")
-                .long("config")
-                .value_name("FILE")
-                .help("Poll configuration YAML file.")
-                .required(true))
-            .arg(Arg::with_name("tally_audit_seed")
-                .short("s")
-                .long("seed")
-                .value_name("HEX")
-                .help("Seed value as hexadecimal string of bytes.")
-                .required(true))
-            .arg(Arg::with_name("force")
-                .long("force")
-                .help("Force a re-generation of the audited planes columns.")
-                .required(false)))
-        .subcommand(SubCommand::with_name("step8")
-            .about("Step 8: Generated decrypted plane columns.")
-            .arg(Arg::with_name("poll_configuration")
-                .short("c")
-                .long("config")
-                .value_name("FILE")
-                .help("Poll configuration YAML file.")
-                .required(true))
-            .arg(Arg::with_name("force")
-                .long("force")
-                .help("Force a re-decrypt of the plane columns.")
-                .required(false)))
-        .subcommand(SubCommand::with_name("sign")
-            .about("Generate signature for a file to publish.")
-            .arg(Arg::with_name("poll_configuration")
-                .short("c")
-                .long("config")
-                .value_name("FILE")
-                .help("Poll configuration YAML file.")
-                .required(true))
-            .arg(Arg::with_name("file")
-                .short("f")
-                .long("file")
-                .value_name("FILE")
-                .help("File for which to generate a signature.")
-                .required(true)))
-        .subcommand(SubCommand::with_name("gen")
-            .about("Generate proof of inclusion for data in YAML format.")
-            .arg(Arg::with_name("merkle_tree")
-                .short("m")
-                .long("merkle")
-                .value_name("FILE")
-                .help("Merkle tree in YAML format.")
-                .required(true))
-            .arg(Arg::with_name("data")
-                .short("d")
-                .long("data")
-                .value_name("STRING")
-                .help("Data to generate proof of.")
-                .required(true)))
-        .subcommand(SubCommand::with_name("validate")
-            .about("Validate proof of inclusion given in YAML format.")
-            .arg(Arg::with_name("inclusion_proof")
-                .short("p")
-                .long("proof")
-                .value_name("FILE")
-                .help("Proof of inclusion in YAML format (Given by gen subcommand).")
-                .required(true)))
-        .get_matches();
+    let cli = Cli::parse();
 
-    stderrlog::new().verbosity(4).init().unwrap();
+    init_logging(cli.log_format.parse::<LogFormat>()?);
+    set_quiet(cli.quiet);
+    set_output_format(cli.output_format.parse::<OutputFormat>()?);
+    set_audit_vote_address(cli.vote_address.as_deref())?;
+    if let Some(artifact_key) = cli.artifact_key {
+        set_verification_key(Base64String(artifact_key));
+    }
+    let workspace = cli.workspace;
+    let network = cli.network;
 
-    match matches.subcommand() {
-        ("new", Some(arguments)) => {
+    match cli.command {
+        Command::New { poll_configuration, generation_seed_file } => {
+            let generation_seed = generation_seed_file.as_deref().map(read_generation_seed).transpose()?;
             create_new_poll(
-                arguments.value_of("poll_configuration").unwrap())?;
+                &poll_configuration,
+                generation_seed.as_deref())?;
         },
-        ("bind-roster", Some(arguments)) => {
+        Command::BindRoster { pollconf, roster_file, disable_voter_privacy, force } => {
             bind_roster(
-                arguments.value_of("poll_configuration").unwrap(),
-                arguments.value_of("roster_file").unwrap(),
-                0 < arguments.occurrences_of("disable_voter_privacy"),
-                0 < arguments.occurrences_of("force"))?;
+                &pollconf.resolve(&workspace)?,
+                &roster_file,
+                disable_voter_privacy,
+                force)?;
+        },
+        Command::CommitRosterChunk { pollconf, roster_file } => {
+            commit_roster_chunk(
+                &network,
+                &pollconf.resolve(&workspace)?,
+                &roster_file)?;
+        },
+        Command::RosterPolicy { pollconf, published_fields, min_anonymity } => {
+            set_roster_policy(
+                &pollconf.resolve(&workspace)?,
+                &parse_roster_fields(&published_fields)?,
+                min_anonymity)?;
         },
-        ("step1", Some(arguments)) => {
+        Command::CheckRosterPrivacy { roster_file, published_fields, min_anonymity } => {
+            check_roster_privacy(&roster_file, &parse_roster_fields(&published_fields)?, min_anonymity)?;
+        },
+        Command::Step1 { pollconf, force } => {
             generate_poll_commitments(
-                arguments.value_of("poll_configuration").unwrap(),
-                0 < arguments.occurrences_of("force"))?;
+                &pollconf.resolve(&workspace)?,
+                force)?;
         },
-        ("step2", Some(arguments)) => {
+        Command::Step2 { pollconf, drawn_summands_seed, force } => {
             generate_drawn_summands(
-                arguments.value_of("poll_configuration").unwrap(),
-                arguments.value_of("drawn_summands_seed").unwrap(),
-                0 < arguments.occurrences_of("force"))?;
+                &pollconf.resolve(&workspace)?,
+                &drawn_summands_seed,
+                force)?;
         },
-        ("step3", Some(arguments)) => {
+        Command::Step3 { pollconf, address_label, ballot_information, ballot_layout, ballots_pdf } => {
             generate_print_files(
-                arguments.value_of("poll_configuration").unwrap(),
-                arguments.value_of("address_label").unwrap(),
-                arguments.value_of("ballot_information").unwrap())?;
+                &pollconf.resolve(&workspace)?,
+                &address_label,
+                &ballot_information,
+                ballot_layout.as_deref(),
+                ballots_pdf.as_deref())?;
         },
-        ("step4", Some(arguments)) => {
+        Command::Step4 { pollconf, audited_ballots, approvals, signed } => {
             record_audited_ballots(
-                arguments.value_of("poll_configuration").unwrap(),
-                arguments.value_of("audited_ballots").unwrap(),
-                0 < arguments.occurrences_of("force"))?;
+                &network,
+                &pollconf.resolve(&workspace)?,
+                &audited_ballots,
+                &approvals,
+                signed.as_deref(),
+                false)?;
+        },
+        Command::ApprovalKeygen { out } => {
+            generate_approval_key(&out)?;
         },
-        ("step6", Some(arguments)) => {
+        Command::ApproveCommit { proposal, trustee, key, approval_file } => {
+            approve_commit(
+                &proposal,
+                &trustee,
+                key.as_deref(),
+                approval_file.as_deref())?;
+        },
+        Command::Step5 => (),
+        Command::Provisional { command } => match command {
+            ProvisionalCommand::Mark { pollconf, serial } => {
+                mark_provisional(
+                    &pollconf.resolve(&workspace)?,
+                    serial)?;
+            },
+            ProvisionalCommand::Resolve { pollconf, serial, accept, reject: _ } => {
+                resolve_provisional(
+                    &pollconf.resolve(&workspace)?,
+                    serial,
+                    accept)?;
+            }
+        },
+        Command::Spoil { pollconf, serial, replacement, reason, commitment } => {
+            spoil_ballot(
+                &network,
+                &pollconf.resolve(&workspace)?,
+                serial,
+                replacement,
+                reason.as_deref(),
+                commitment.as_deref())?;
+        },
+        Command::Envelope { command } => match command {
+            EnvelopeCommand::Keygen { out } => {
+                generate_envelope_key(&out)?;
+            },
+            EnvelopeCommand::Issue { poll, spoiled, replacement, key, out, envelope } => {
+                issue_replacement_ballot(&poll, spoiled, replacement, &key, &out, envelope.as_deref())?;
+            },
+            EnvelopeCommand::Open { envelope, key, amendments, out } => {
+                open_replacement_envelope(&envelope, &key, &amendments, out.as_deref())?;
+            }
+        },
+        Command::Step6 { pollconf, votes_file, chain, cached, sms, sms_token, api, api_token, test_codes, verbose, force } => {
+            // Votes submitted through several sources count for the first one.
+            let mut sources: Vec<Box<dyn VoteSource>> = Vec::new();
+            if chain {
+                sources.push(Box::new(ChainVotes(network.clone())));
+            }
+            if let Some(cached) = cached {
+                sources.push(Box::new(CachedVotes(cached.into())));
+            }
+            if let Some(sms) = sms {
+                sources.push(Box::new(MessageExport { channel: VoteChannel::Sms, location: sms, token: sms_token }));
+            }
+            if let Some(api) = api {
+                sources.push(Box::new(MessageExport { channel: VoteChannel::Api, location: api, token: api_token }));
+            }
+            if let Some(votes_file) = votes_file {
+                sources.push(Box::new(FileVotes(votes_file.into())));
+            }
+            let test_codes = match test_codes {
+                Some(test_codes) => read_votecodes_file(&PathBuf::from(test_codes))?,
+                None => VoteCodeSet::new()
+            };
             record_votes(
-                arguments.value_of("poll_configuration").unwrap(),
-                arguments.value_of("votes_file").unwrap(),
-                0 < arguments.occurrences_of("force"))?;
+                &network,
+                &pollconf.resolve(&workspace)?,
+                &sources,
+                &test_codes,
+                verbose,
+                force)?;
+        },
+        Command::UnlockVotes { time_lock } => {
+            unlock_votes(&time_lock)?;
         },
-        ("step7", Some(arguments)) => {
+        Command::Step7 { pollconf, tally_audit_seed, force: _ } => {
             generate_tally_audit(
-                arguments.value_of("poll_configuration").unwrap(),
-                arguments.value_of("tally_audit_seed").unwrap())?;
+                &pollconf.resolve(&workspace)?,
+                &tally_audit_seed)?;
         },
-        ("step8", Some(arguments)) => {
+        Command::Step8 { pollconf, provenance, force } => {
             generate_poll_revelations(
-                arguments.value_of("poll_configuration").unwrap(),
-                0 < arguments.occurrences_of("force"))?;
+                &network,
+                &pollconf.resolve(&workspace)?,
+                provenance,
+                force)?;
+        },
+        Command::Finalize { pollconf } => {
+            finalize_tally(
+                &network,
+                &pollconf.resolve(&workspace)?)?;
+        },
+        Command::VerifyTally { tally, receipt } => {
+            verify_tally(
+                &network,
+                &tally,
+                &receipt)?;
+        },
+        Command::VerifyQuestion { question, receipt } => {
+            verify_question(
+                &network,
+                &question,
+                &receipt)?;
+        },
+        Command::VerifyPrintFile { pollconf, ballot_information, merkle_tree } => {
+            verify_print_file(
+                &pollconf.resolve(&workspace)?,
+                &ballot_information,
+                &merkle_tree)?;
+        },
+        Command::CheckVotecodes { ballot_information } => {
+            check_votecodes(&ballot_information)?;
+        },
+        Command::RegenerateBallots { pollconf, out } => {
+            regenerate_ballots(
+                &pollconf.resolve(&workspace)?,
+                &out)?;
+        },
+        Command::ExportBallots { pollconf, profile, format, out } => {
+            export_ballots(
+                &pollconf.resolve(&workspace)?,
+                profile.parse()?,
+                format.parse()?,
+                &out)?;
+        },
+        Command::MailingLabels { pollconf, format, country, out } => {
+            export_mailing_labels(
+                &pollconf.resolve(&workspace)?,
+                format.parse()?,
+                country.parse()?,
+                &out)?;
+        },
+        Command::ImportUndeliverable { pollconf, list } => {
+            import_undeliverable(
+                &pollconf.resolve(&workspace)?,
+                &list)?;
+        },
+        Command::Escrow { command } => match command {
+            EscrowCommand::Keygen { holder, out } => {
+                generate_escrow_key(&holder, &out)?;
+            },
+            EscrowCommand::Seal { pollconf, keys } => {
+                escrow_ballot_map(
+                    &pollconf.resolve(&workspace)?,
+                    &keys)?;
+            },
+            EscrowCommand::Open { escrow, keys, out } => {
+                open_ballot_map_escrow(&escrow, &keys, &out)?;
+            }
+        },
+        Command::CheckRegeneration { pollconf, ballot_information } => {
+            check_regenerated_ballots(
+                &pollconf.resolve(&workspace)?,
+                ballot_information.as_deref())?;
+        },
+        Command::StampCommit { datadir, calendars } => {
+            stamp_commit(
+                &datadir,
+                &calendars)?;
+        },
+        Command::VerifyStamp { receipt, proof, upgrade, esplora } => {
+            verify_stamp(
+                &receipt,
+                proof.as_deref(),
+                upgrade,
+                &esplora)?;
+        },
+        Command::CompareTally { official, independent, poll } => {
+            compare_tally(
+                &official,
+                &independent,
+                poll.as_deref())?;
+        },
+        Command::SpendReport { datadir } => {
+            spend_report(&network, &datadir)?;
+        },
+        Command::InterferenceReport { pollconf } => {
+            interference_report(&network, &pollconf.resolve(&workspace)?)?;
+        },
+        Command::Status { pollconf, chain } => {
+            poll_status(
+                &network,
+                &pollconf.resolve(&workspace)?,
+                chain)?;
+        },
+        Command::VerifyReceipt { pollconf, serial, receipt } => {
+            verify_receipt(
+                &network,
+                &pollconf.resolve(&workspace)?,
+                serial.as_deref(),
+                receipt.as_deref())?;
         },
-        ("sign", Some(arguments)) => {
+        Command::Monitor { pollconf, interval, tui, once, webhooks, metrics, low_balance } => {
+            monitor_poll(
+                &network,
+                &pollconf.resolve(&workspace)?,
+                interval,
+                tui,
+                once,
+                webhooks.as_deref(),
+                metrics.as_deref(),
+                low_balance)?;
+        },
+        Command::CacheTransactions { receipt, from_block, out, poll_configuration } => {
+            cache_transactions(
+                &network,
+                receipt.as_deref(),
+                from_block,
+                &out,
+                poll_configuration.as_deref())?;
+        },
+        Command::ImportNodeExport { format, input, receipt, out, poll_configuration } => {
+            import_node_export(
+                format.parse()?,
+                &input,
+                &receipt,
+                cli.vote_address.as_deref(),
+                &out,
+                poll_configuration.as_deref())?;
+        },
+        Command::SignTransaction { unsigned, out } => {
+            sign_transaction(
+                &network,
+                &unsigned,
+                &out)?;
+        },
+        Command::RotateKey { receipt, to } => {
+            rotate_key(
+                &network,
+                &receipt,
+                &to)?;
+        },
+        #[cfg(feature = "keyring")]
+        Command::Keyring { command } => match command {
+            KeyringCommand::Set { entry } => {
+                store_keyring_entry(
+                    &network,
+                    &entry)?;
+            },
+            KeyringCommand::Delete { entry } => {
+                delete_keyring_entry(
+                    &network,
+                    &entry)?;
+            }
+        },
+        Command::Simulate { dir, voters, ballots, decoys, audited, turnout, duplicate_rate, invalid_rate, seed } => {
+            simulate_poll(
+                &dir,
+                &SimulationConfiguration {
+                    num_voters: voters,
+                    num_ballots: ballots,
+                    num_decoys: decoys,
+                    num_audited: audited,
+                    turnout,
+                    duplicate_rate,
+                    invalid_rate,
+                    seed
+                })?;
+        },
+        Command::LogicAccuracy { pollconf, dir, seed } => {
+            logic_and_accuracy(
+                &pollconf.resolve(&workspace)?,
+                &dir,
+                seed.as_deref())?;
+        },
+        #[cfg(feature = "email")]
+        Command::Email { pollconf, kind, contacts, template, smtp, outbox, from, rate, report } => {
+            let destination = match (&smtp, &outbox, &from) {
+                (Some(smtp), _, _) => EmailDestination::Smtp(smtp),
+                (None, Some(outbox), Some(from)) => EmailDestination::Outbox(outbox, from),
+                _ => unreachable!()
+            };
+            send_email(
+                &network,
+                &pollconf.resolve(&workspace)?,
+                kind.parse()?,
+                &contacts,
+                template.as_deref(),
+                destination,
+                rate,
+                &report)?;
+        },
+        #[cfg(feature = "server")]
+        Command::Serve { pollconf, listen, merkle_tree, grpc, helpdesk_token, rate_limit, low_balance, close_at, close_at_end_block, tally_audit_seed, webhooks } => {
+            let deadline = DeadlineOptions {
+                close_at: close_at.as_deref(),
+                at_end_block: close_at_end_block,
+                audited_columns_seed: tally_audit_seed.as_deref(),
+                webhooks: webhooks.as_deref()
+            };
+            serve_poll(
+                &network,
+                &pollconf.resolve(&workspace)?,
+                &listen,
+                &merkle_tree,
+                grpc.as_deref(),
+                helpdesk_token.as_deref(),
+                rate_limit,
+                low_balance,
+                &deadline).await?;
+        },
+        #[cfg(feature = "server")]
+        Command::ServeProofs { dir, listen, rate_limit } => {
+            serve_public_proofs(
+                &dir,
+                &listen,
+                rate_limit).await?;
+        },
+        Command::Workspace { command } => match command {
+            WorkspaceCommand::Init => {
+                workspace_init(&workspace)?;
+            },
+            WorkspaceCommand::Create { poll_configuration, generation_seed_file } => {
+                let generation_seed = generation_seed_file.as_deref().map(read_generation_seed).transpose()?;
+                workspace_create(
+                    &workspace,
+                    &poll_configuration,
+                    generation_seed.as_deref())?;
+            },
+            WorkspaceCommand::Add { dir, poll_configuration } => {
+                workspace_add(
+                    &workspace,
+                    &dir,
+                    &poll_configuration)?;
+            },
+            WorkspaceCommand::List => {
+                workspace_list(&workspace)?;
+            },
+            WorkspaceCommand::Use { poll } => {
+                workspace_use(
+                    &workspace,
+                    &poll)?;
+            },
+            WorkspaceCommand::Archive { poll, out } => {
+                archive_poll(
+                    &workspace,
+                    poll.as_deref(),
+                    out.as_deref())?;
+            },
+            WorkspaceCommand::VerifyArchive { archive, public_key } => {
+                verify_archive(
+                    &archive,
+                    public_key.as_deref())?;
+            }
+        },
+        Command::Archive { command } => match command {
+            ArchiveCommand::Export { pollconf, merkle_tree, transactions, out } => {
+                export_archive(
+                    &pollconf.resolve(&workspace)?,
+                    &merkle_tree,
+                    &transactions,
+                    out.as_deref())?;
+            },
+            ArchiveCommand::Verify { archive, public_key } => {
+                verify_public_archive(
+                    &archive,
+                    public_key.as_deref())?;
+            },
+            ArchiveCommand::Import { archive, dir, public_key } => {
+                import_archive(
+                    &archive,
+                    &dir,
+                    &public_key)?;
+            }
+        },
+        Command::Board { command } => match command {
+            BoardCommand::Publish { pollconf, dir, merkle_tree } => {
+                publish_board(
+                    &pollconf.resolve(&workspace)?,
+                    &dir,
+                    &merkle_tree)?;
+            },
+            BoardCommand::Verify { dir, public_key } => {
+                verify_board(
+                    &dir,
+                    public_key.as_deref())?;
+            },
+            BoardCommand::Report { dir, public_key, html, pdf } => {
+                board_report(
+                    &dir,
+                    public_key.as_deref(),
+                    html.as_deref(),
+                    pdf.as_deref())?;
+            },
+            BoardCommand::Recount { dir } => {
+                recount_board(
+                    &dir)?;
+            },
+            #[cfg(feature = "server")]
+            BoardCommand::Serve { dir, listen } => {
+                serve_board(
+                    &dir,
+                    &listen).await?;
+            },
+            #[cfg(feature = "server")]
+            BoardCommand::Mirror { url, dir, public_key } => {
+                mirror_board(
+                    &url,
+                    &dir,
+                    public_key.as_deref()).await?;
+            }
+        },
+        Command::Sign { pollconf, file } => {
             sign_document(
-                arguments.value_of("poll_configuration").unwrap(),
-                arguments.value_of("file").unwrap())?;
+                &pollconf.resolve(&workspace)?,
+                &file)?;
         },
-        ("gen", Some(arguments)) => {
+        Command::Gen { merkle_tree, data } => {
             generate_proof(
-                arguments.value_of("merkle_tree").unwrap(),
-                arguments.value_of("data").unwrap())?;
-
+                &merkle_tree,
+                &data)?;
         },
-        ("validate", Some(arguments)) => {
+        Command::Validate { inclusion_proof } => {
             validate_proof(
-                arguments.value_of("inclusion_proof").unwrap())?;
-
+                &inclusion_proof)?;
+        },
+        Command::Locate { merkle_tree, voter, serial, leaf } => {
+            let target = match (voter, serial, leaf) {
+                (Some(position), _, _) => LeafTarget::Voter(position),
+                (_, Some(serial), _) => LeafTarget::Ballot(serial),
+                (_, _, leaf) => LeafTarget::Leaf(leaf.unwrap_or_default())
+            };
+            locate_leaf(&merkle_tree, target)?;
+        },
+        Command::RevealCell { pollconf, merkle_tree, plane, row, column, challenger, out } => {
+            reveal_cell(
+                &pollconf.resolve(&workspace)?,
+                &merkle_tree,
+                plane,
+                row,
+                column,
+                &challenger,
+                &out)?;
+        },
+        Command::VerifyCell { opening } => {
+            verify_cell_opening(&opening)?;
+        },
+        Command::IndexProofs { merkle_tree, throttle_ms } => {
+            index_proofs(
+                &merkle_tree,
+                std::time::Duration::from_millis(throttle_ms))?;
+        },
+        Command::Completions { shell } => {
+            let mut command = Cli::command();
+            let name = command.get_name().to_owned();
+            clap_complete::generate(shell, &mut command, name, &mut std::io::stdout());
+        },
+        Command::Man { dir } => {
+            clap_mangen::generate_to(Cli::command(), &dir)?;
         }
-        _ => ()
     }
 
     Ok(())
 }
-
@@ -0,0 +1,120 @@
+//! # Metrics
+//!
+//! `metrics` keeps process-wide counters of the vote relay server and the
+//! monitor, and renders them in the Prometheus text format for the
+//! `/metrics` endpoint:
+//!
+//! * `seventh_estate_votes_counted`: votes counted by the monitor.
+//! * `seventh_estate_votes_relayed_total`: votes relayed by the server.
+//! * `seventh_estate_invalid_payloads_total`: payloads refused by the server
+//!   or not counted by the monitor.
+//! * `seventh_estate_audit_warnings`: audit warnings raised by the monitor.
+//! * `seventh_estate_rpc_requests_total`, `seventh_estate_rpc_errors_total`
+//!   and `seventh_estate_rpc_latency_seconds`: requests to the blockchain
+//!   node (the JSON-RPC endpoint of the network configuration).
+//! * `seventh_estate_rpc_failovers_total`: requests sent on to the next
+//!   node endpoint after the one tried failed.
+//! * `seventh_estate_last_confirmed_block`: last block read by the monitor.
+
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+/// Upper bounds of the RPC latency histogram buckets, in seconds.
+const LATENCY_BUCKETS: [f64; 8] = [0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0];
+
+#[allow(clippy::declare_interior_mutable_const)]
+const ZERO: AtomicU64 = AtomicU64::new(0);
+
+static VOTES_COUNTED: AtomicU64 = AtomicU64::new(0);
+static VOTES_RELAYED: AtomicU64 = AtomicU64::new(0);
+static INVALID_PAYLOADS: AtomicU64 = AtomicU64::new(0);
+static AUDIT_WARNINGS: AtomicU64 = AtomicU64::new(0);
+static RPC_REQUESTS: AtomicU64 = AtomicU64::new(0);
+static RPC_ERRORS: AtomicU64 = AtomicU64::new(0);
+static RPC_FAILOVERS: AtomicU64 = AtomicU64::new(0);
+/// Total RPC latency, in microseconds.
+static RPC_LATENCY: AtomicU64 = AtomicU64::new(0);
+/// Number of requests per latency bucket, the last one for slower requests.
+static RPC_LATENCY_BUCKETS: [AtomicU64; LATENCY_BUCKETS.len() + 1] = [ZERO; LATENCY_BUCKETS.len() + 1];
+static LAST_CONFIRMED_BLOCK: AtomicU64 = AtomicU64::new(0);
+
+pub fn set_votes_counted(votes: usize) {
+    VOTES_COUNTED.store(votes as u64, Ordering::Relaxed);
+}
+
+pub fn set_audit_warnings(warnings: usize) {
+    AUDIT_WARNINGS.store(warnings as u64, Ordering::Relaxed);
+}
+
+pub fn set_last_confirmed_block(block: u64) {
+    LAST_CONFIRMED_BLOCK.store(block, Ordering::Relaxed);
+}
+
+pub fn inc_votes_relayed() {
+    VOTES_RELAYED.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn inc_invalid_payloads() {
+    INVALID_PAYLOADS.fetch_add(1, Ordering::Relaxed);
+}
+
+pub fn inc_rpc_failovers() {
+    RPC_FAILOVERS.fetch_add(1, Ordering::Relaxed);
+}
+
+/// Record a request to the blockchain node.
+pub fn record_rpc(latency: Duration, succeeded: bool) {
+    RPC_REQUESTS.fetch_add(1, Ordering::Relaxed);
+    if !succeeded {
+        RPC_ERRORS.fetch_add(1, Ordering::Relaxed);
+    }
+    RPC_LATENCY.fetch_add(latency.as_micros() as u64, Ordering::Relaxed);
+    let bucket = LATENCY_BUCKETS.iter()
+        .position(|&bound| latency.as_secs_f64() <= bound)
+        .unwrap_or(LATENCY_BUCKETS.len());
+    RPC_LATENCY_BUCKETS[bucket].fetch_add(1, Ordering::Relaxed);
+}
+
+fn metric(output: &mut String, name: &str, kind: &str, help: &str, value: u64) {
+    writeln!(output, "# HELP {} {}", name, help).unwrap();
+    writeln!(output, "# TYPE {} {}", name, kind).unwrap();
+    writeln!(output, "{} {}", name, value).unwrap();
+}
+
+/// All metrics in the Prometheus text format.
+pub fn render() -> String {
+    let load = |value: &AtomicU64| value.load(Ordering::Relaxed);
+    let mut output = String::new();
+    metric(&mut output, "seventh_estate_votes_counted", "gauge",
+        "Votes counted by the monitor.", load(&VOTES_COUNTED));
+    metric(&mut output, "seventh_estate_votes_relayed_total", "counter",
+        "Votes relayed to the blockchain by the server.", load(&VOTES_RELAYED));
+    metric(&mut output, "seventh_estate_invalid_payloads_total", "counter",
+        "Payloads refused by the server or not counted by the monitor.", load(&INVALID_PAYLOADS));
+    metric(&mut output, "seventh_estate_audit_warnings", "gauge",
+        "Audit warnings raised by the monitor.", load(&AUDIT_WARNINGS));
+    metric(&mut output, "seventh_estate_rpc_requests_total", "counter",
+        "Requests to the blockchain node.", load(&RPC_REQUESTS));
+    metric(&mut output, "seventh_estate_rpc_errors_total", "counter",
+        "Failed requests to the blockchain node.", load(&RPC_ERRORS));
+    metric(&mut output, "seventh_estate_rpc_failovers_total", "counter",
+        "Requests sent on to the next node endpoint.", load(&RPC_FAILOVERS));
+
+    let name = "seventh_estate_rpc_latency_seconds";
+    writeln!(output, "# HELP {} Latency of the requests to the blockchain node.", name).unwrap();
+    writeln!(output, "# TYPE {} histogram", name).unwrap();
+    let mut count = 0;
+    for (n, bound) in LATENCY_BUCKETS.iter().enumerate() {
+        count += load(&RPC_LATENCY_BUCKETS[n]);
+        writeln!(output, "{}_bucket{{le=\"{}\"}} {}", name, bound, count).unwrap();
+    }
+    count += load(&RPC_LATENCY_BUCKETS[LATENCY_BUCKETS.len()]);
+    writeln!(output, "{}_bucket{{le=\"+Inf\"}} {}", name, count).unwrap();
+    writeln!(output, "{}_sum {}", name, load(&RPC_LATENCY) as f64 / 1e6).unwrap();
+    writeln!(output, "{}_count {}", name, count).unwrap();
+
+    metric(&mut output, "seventh_estate_last_confirmed_block", "gauge",
+        "Last block read by the monitor.", load(&LAST_CONFIRMED_BLOCK));
+    output
+}
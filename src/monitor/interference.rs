@@ -0,0 +1,121 @@
+//! # Interference Analysis
+//!
+//! Anyone can send a transaction to the vote address of a poll. Those
+//! whose payload is not a vote for the poll, or whose vote code is on no
+//! ballot, cannot change the tally, but they cost their sender the gas
+//! they used. `InterferenceReport` adds them up by sender, with the ether
+//! each one spent, so that the officials of a poll can quantify and
+//! publish the attempts to flood it. The report is written to
+//! `interference_report.yaml` in the data directory of the poll and
+//! published on the bulletin board.
+//!
+//! Votes for audited ballots and repeated votes use real vote codes and
+//! are reported as audit warnings instead; encrypted votes cannot be read
+//! before voting closes and are not analysed.
+
+use std::collections::BTreeMap;
+use std::fmt;
+use serde::{Serialize, Deserialize};
+use crate::ballots::{BallotSerial, VoteCodeMap};
+use crate::blockchain::{format_ether, Spend};
+use super::{DecodedPayload, DecodedTransaction};
+
+pub const INTERFERENCE_REPORT_FILENAME: &str = "interference_report.yaml";
+
+/// Interfering transactions of one sender.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SenderInterference {
+    pub address: String,
+    /// Transactions that are not a vote for the poll.
+    pub invalid_payloads: usize,
+    /// Votes whose vote code is on no ballot.
+    pub unknown_votecodes: usize,
+    pub spend: Spend
+}
+
+/// Interfering transactions to the vote address of a poll.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct InterferenceReport {
+    pub poll_identifier: String,
+    pub from_block: u64,
+    pub to_block: u64,
+    /// Transactions read, besides the commitment.
+    pub transactions: usize,
+    pub invalid_payloads: usize,
+    pub unknown_votecodes: usize,
+    /// Senders by decreasing spend.
+    pub senders: Vec<SenderInterference>,
+    /// Estimated spend of the interfering senders.
+    pub total: Spend
+}
+
+impl InterferenceReport {
+    /// Analyse the transactions read from `from_block` to `to_block`
+    /// against the vote codes of the ballots of the poll.
+    pub fn new(poll_identifier: &str, from_block: u64, to_block: u64, transactions: &[DecodedTransaction], ballots: &VoteCodeMap<BallotSerial>) -> Self {
+        let mut senders: BTreeMap<String, SenderInterference> = BTreeMap::new();
+        let mut report = InterferenceReport {
+            poll_identifier: poll_identifier.to_owned(),
+            from_block,
+            to_block,
+            transactions: 0,
+            invalid_payloads: 0,
+            unknown_votecodes: 0,
+            senders: Vec::new(),
+            total: Spend::default()
+        };
+        for decoded in transactions.iter() {
+            let unknown_votecode = match &decoded.payload {
+                DecodedPayload::Commit => continue,
+                DecodedPayload::Invalid => false,
+                DecodedPayload::Vote(vote) if ballots.get_str(&vote.votecode).is_none() => true,
+                DecodedPayload::Vote(_) | DecodedPayload::Encrypted => {
+                    report.transactions += 1;
+                    continue;
+                }
+            };
+            report.transactions += 1;
+            match unknown_votecode {
+                true => report.unknown_votecodes += 1,
+                false => report.invalid_payloads += 1
+            }
+            let address = decoded.transaction.from.to_lowercase();
+            let sender = senders.entry(address.clone()).or_insert_with(|| SenderInterference {
+                address,
+                invalid_payloads: 0,
+                unknown_votecodes: 0,
+                spend: Spend::default()
+            });
+            match unknown_votecode {
+                true => sender.unknown_votecodes += 1,
+                false => sender.invalid_payloads += 1
+            }
+            sender.spend.add(Some(&decoded.status));
+            report.total.add(Some(&decoded.status));
+        }
+        report.senders = senders.into_values().collect();
+        report.senders.sort_by(|a, b| b.spend.spent_wei.cmp(&a.spend.spent_wei).then_with(|| a.address.cmp(&b.address)));
+        report
+    }
+
+    /// Transactions that could not change the tally.
+    pub fn interfering(&self) -> usize {
+        self.invalid_payloads + self.unknown_votecodes
+    }
+}
+
+impl fmt::Display for InterferenceReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Poll:         {}", self.poll_identifier)?;
+        writeln!(f, "Blocks:       {} to {}", self.from_block, self.to_block)?;
+        writeln!(f, "Transactions: {} ({} interfering)", self.transactions, self.interfering())?;
+        writeln!(f, "  Invalid payloads:   {}", self.invalid_payloads)?;
+        writeln!(f, "  Unknown vote codes: {}", self.unknown_votecodes)?;
+        writeln!(f, "Senders:")?;
+        for sender in self.senders.iter() {
+            writeln!(f, "  {} {} invalid, {} unknown, {} ETH", sender.address,
+                sender.invalid_payloads, sender.unknown_votecodes, format_ether(sender.spend.spent_wei))?;
+        }
+        write!(f, "Spent:        {}", self.total)
+    }
+}
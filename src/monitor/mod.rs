@@ -0,0 +1,492 @@
+//! # Monitoring
+//!
+//! `monitor` follows the votes submitted for a poll on the blockchain.
+//! Each `refresh` reads the blocks made final since the previous refresh,
+//! by the finality policy of the network, and updates the vote counts, gas
+//! spent, chain connectivity and audit warnings of the `MonitorSnapshot`.
+//! The commitment is confirmed, and the end block mined, once final.
+//!
+//! Commit confirmation, vote thresholds, audit warnings, the closing of
+//! the voting window, the mining of the end block of the poll and commit
+//! or relayer accounts running low on funds are reported as `PollEvent`s to the `EventSink`s of the monitor, such as the
+//! webhooks. Blocks after the end block are not read.
+//!
+//! Blocks are read through a pipeline of bounded channels (see
+//! `pipeline`), and a monitor asked to shut down counts the blocks already
+//! read before it stops.
+//!
+//! A vote is counted once per ballot. Votes with unknown vote codes, votes
+//! for audited (spoiled) ballots, repeated votes for a ballot and failed
+//! vote transactions are not counted and raise an audit warning instead.
+//! The invalid payloads and unknown vote codes are analysed by sender in
+//! an `InterferenceReport` (see `interference`).
+
+use std::collections::HashSet;
+use std::fmt;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tracing::{info, warn};
+use crate::ballots::{BallotSerial, VoteCodeMap};
+use crate::blockchain::{AccountBalance, BalanceWatch, Handover, TransactionStatus};
+use crate::blockchain::blocking::{account_balances, final_block, latest_block, transaction_status};
+use crate::metrics;
+use crate::planes::PlaneTally;
+use crate::Result;
+
+pub mod tui;
+
+pub mod pipeline;
+pub use pipeline::*;
+
+pub mod interference;
+pub use interference::*;
+
+/// Number of transactions kept in `MonitorSnapshot::latest_transactions`.
+const LATEST_TRANSACTIONS: usize = 10;
+const WEI_PER_ETHER: f64 = 1e18;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum TransactionKind { Commit, Vote, Encrypted, Rejected, Invalid }
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TransactionSummary {
+    pub transaction_hash: String,
+    pub block_number: u64,
+    pub timestamp: DateTime<Utc>,
+    pub kind: TransactionKind,
+    pub gas_used: Option<u64>
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub enum AuditWarning {
+    UnknownVoteCode { votecode: String, transaction_hash: String },
+    AuditedBallotVoted { serial: BallotSerial, transaction_hash: String },
+    BallotVotedTwice { serial: BallotSerial, transaction_hash: String },
+    TransactionFailed { transaction_hash: String }
+}
+
+impl fmt::Display for AuditWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::UnknownVoteCode { votecode, transaction_hash } =>
+                write!(f, "Unknown vote code {} in {}", votecode, transaction_hash),
+            Self::AuditedBallotVoted { serial, transaction_hash } =>
+                write!(f, "Audited ballot {} voted in {}", serial, transaction_hash),
+            Self::BallotVotedTwice { serial, transaction_hash } =>
+                write!(f, "Ballot {} voted again in {}", serial, transaction_hash),
+            Self::TransactionFailed { transaction_hash } =>
+                write!(f, "Vote transaction {} failed", transaction_hash)
+        }
+    }
+}
+
+/// Event of the poll detected by the monitor.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum PollEvent {
+    CommitConfirmed { transaction_hash: String, block_number: u64 },
+    VoteThresholdReached { threshold: usize, votes_counted: usize },
+    AuditAnomaly { warning: AuditWarning },
+    VotingClosed { votes_counted: usize },
+    VoteAddressHandedOver { handover: Handover },
+    EndBlockMined { end_block: u64, votes_counted: usize },
+    LowBalance { balance: AccountBalance, remaining_transactions: u128 },
+    /// Sent by the relay server once it counted the tally at the deadline.
+    TallyCounted { tally: PlaneTally, consistent: bool }
+}
+
+pub const POLL_EVENTS: [&str; 8] = ["commit_confirmed", "vote_threshold_reached", "audit_anomaly", "voting_closed", "vote_address_handed_over", "end_block_mined", "low_balance", "tally_counted"];
+
+impl PollEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::CommitConfirmed { .. } => POLL_EVENTS[0],
+            Self::VoteThresholdReached { .. } => POLL_EVENTS[1],
+            Self::AuditAnomaly { .. } => POLL_EVENTS[2],
+            Self::VotingClosed { .. } => POLL_EVENTS[3],
+            Self::VoteAddressHandedOver { .. } => POLL_EVENTS[4],
+            Self::EndBlockMined { .. } => POLL_EVENTS[5],
+            Self::LowBalance { .. } => POLL_EVENTS[6],
+            Self::TallyCounted { .. } => POLL_EVENTS[7]
+        }
+    }
+}
+
+impl fmt::Display for PollEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::CommitConfirmed { transaction_hash, block_number } =>
+                write!(f, "Commitment {} confirmed in block {}", transaction_hash, block_number),
+            Self::VoteThresholdReached { threshold, .. } =>
+                write!(f, "{} votes counted", threshold),
+            Self::AuditAnomaly { warning } =>
+                write!(f, "Audit anomaly: {}", warning),
+            Self::VotingClosed { votes_counted } =>
+                write!(f, "Voting window closed with {} votes counted", votes_counted),
+            Self::VoteAddressHandedOver { handover } =>
+                write!(f, "Vote address {} handed over to {} in block {}", handover.from, handover.to, handover.block_number),
+            Self::EndBlockMined { end_block, votes_counted } =>
+                write!(f, "End block {} mined: voting ended with {} votes counted", end_block, votes_counted),
+            Self::LowBalance { balance, remaining_transactions } =>
+                write!(f, "Fund the {} account {}: its balance pays for about {} more transactions",
+                    balance.role, balance.address, remaining_transactions),
+            Self::TallyCounted { tally, consistent } =>
+                write!(f, "Tally {} counted{}", tally, if *consistent { "" } else { ", INCONSISTENT" })
+        }
+    }
+}
+
+/// Receives the events detected by the monitor.
+pub trait EventSink {
+    fn notify(&mut self, poll_identifier: &str, event: &PollEvent);
+}
+
+/// Whether the voting window of the poll is open.
+pub type VotingWindowProbe = Box<dyn Fn() -> Result<bool>>;
+
+/// State of the poll on the blockchain as of the last refresh.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct MonitorSnapshot {
+    pub poll_identifier: String,
+    pub connected: bool,
+    pub last_error: Option<String>,
+    pub latest_block: Option<u64>,
+    /// Last block in which votes are accepted, if the poll defines one.
+    pub end_block: Option<u64>,
+    pub last_refresh: Option<DateTime<Utc>>,
+    pub votes_counted: usize,
+    pub invalid_payloads: usize,
+    pub gas_used: u64,
+    /// Fees paid for the poll transactions, in wei.
+    pub fees: u128,
+    /// Balances of the commit and relayer accounts, if watched.
+    pub balances: Vec<AccountBalance>,
+    pub latest_transactions: Vec<TransactionSummary>,
+    pub warnings: Vec<AuditWarning>
+}
+
+impl fmt::Display for MonitorSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Poll:      {}", self.poll_identifier)?;
+        match (self.last_refresh, self.connected, self.latest_block) {
+            (None, _, _) => writeln!(f, "Chain:     connecting")?,
+            (Some(_), true, Some(block)) => writeln!(f, "Chain:     connected (block {})", block)?,
+            _ => writeln!(f, "! Chain:   disconnected ({})", self.last_error.as_deref().unwrap_or("unknown error"))?
+        }
+        if let Some(last_refresh) = self.last_refresh {
+            writeln!(f, "Refreshed: {}", last_refresh.to_rfc3339())?;
+        }
+        match (self.end_block, self.latest_block) {
+            (Some(end_block), Some(block)) if end_block <= block => writeln!(f, "Voting:    ended at block {}", end_block)?,
+            (Some(end_block), _) => writeln!(f, "Voting:    ends at block {}", end_block)?,
+            (None, _) => ()
+        }
+        writeln!(f, "Votes:     {} counted, {} invalid payloads", self.votes_counted, self.invalid_payloads)?;
+        writeln!(f, "Gas spent: {} gas, {:.6} ETH", self.gas_used, self.fees as f64 / WEI_PER_ETHER)?;
+        self.balances.iter()
+            .try_for_each(|balance| writeln!(f, "Balance:   {}", balance))?;
+        writeln!(f)?;
+        writeln!(f, "Latest transactions:")?;
+        self.latest_transactions.iter()
+            .try_for_each(|transaction| writeln!(f, "  {:>8}  {}  {:<8}  {}",
+                transaction.block_number,
+                transaction.timestamp.format("%H:%M:%S"),
+                format!("{:?}", transaction.kind),
+                transaction.transaction_hash))?;
+        writeln!(f)?;
+        write!(f, "Audit warnings: {}", self.warnings.len())?;
+        self.warnings.iter()
+            .try_for_each(|warning| write!(f, "\n! {}", warning))
+    }
+}
+
+pub struct Monitor {
+    /// Network the poll is followed on.
+    network: String,
+    snapshot: MonitorSnapshot,
+    next_block: u64,
+    /// Poll identifier of the vote payloads of the poll.
+    payload_identifier: String,
+    commit_transaction: String,
+    /// Vote address at the last block read; the configured one if not set.
+    vote_address: Option<String>,
+    /// Ballot serial of every vote code of the poll.
+    ballots: VoteCodeMap<BallotSerial>,
+    audited: HashSet<BallotSerial>,
+    voted: HashSet<BallotSerial>,
+    commit_confirmed: bool,
+    vote_thresholds: Vec<usize>,
+    voting_window: Option<VotingWindowProbe>,
+    voting_open: Option<bool>,
+    end_block_mined: bool,
+    balance_watch: Option<BalanceWatch>,
+    events: Vec<PollEvent>,
+    sinks: Vec<Box<dyn EventSink>>,
+    fetch_window: u64,
+    shutdown: Shutdown
+}
+
+impl Monitor {
+    /// Follow the poll on `network` from the block its commitment was
+    /// posted in.
+    pub fn new(network: &str, poll_identifier: &str, from_block: u64, commit_transaction: &str, ballots: VoteCodeMap<BallotSerial>, audited: Vec<BallotSerial>) -> Self {
+        Monitor {
+            network: network.to_owned(),
+            snapshot: MonitorSnapshot {
+                poll_identifier: poll_identifier.to_owned(),
+                ..MonitorSnapshot::default()
+            },
+            next_block: from_block,
+            payload_identifier: poll_identifier.to_owned(),
+            commit_transaction: commit_transaction.to_owned(),
+            vote_address: None,
+            ballots,
+            audited: audited.into_iter().collect(),
+            voted: HashSet::new(),
+            commit_confirmed: false,
+            vote_thresholds: Vec::new(),
+            voting_window: None,
+            voting_open: None,
+            end_block_mined: false,
+            balance_watch: None,
+            events: Vec::new(),
+            sinks: Vec::new(),
+            fetch_window: FETCH_WINDOW_BLOCKS,
+            shutdown: Shutdown::new()
+        }
+    }
+
+    pub fn snapshot(&self) -> &MonitorSnapshot { &self.snapshot }
+
+    /// Report an event when the number of votes counted reaches each threshold.
+    pub fn set_vote_thresholds(&mut self, vote_thresholds: Vec<usize>) {
+        self.vote_thresholds = vote_thresholds;
+    }
+
+    /// Check the voting window on every refresh, to report when it closes.
+    pub fn watch_voting_window(&mut self, probe: VotingWindowProbe) {
+        self.voting_window = Some(probe);
+    }
+
+    /// Follow the handovers of the vote address from `vote_address`, the
+    /// vote address of the commit receipt.
+    pub fn follow_vote_address(&mut self, vote_address: &str) {
+        self.vote_address = Some(vote_address.to_owned());
+    }
+
+    /// Identify the poll in the vote payloads by `payload_identifier`, its
+    /// UUID, rather than by its poll identifier.
+    pub fn set_payload_identifier(&mut self, payload_identifier: &str) {
+        self.payload_identifier = payload_identifier.to_owned();
+    }
+
+    /// Stop reading votes after `end_block`, and report when it is mined.
+    pub fn set_end_block(&mut self, end_block: u64) {
+        self.snapshot.end_block = Some(end_block);
+    }
+
+    /// Read the balances of the commit and relayer accounts on every
+    /// refresh, and report the accounts that fall low.
+    pub fn watch_balances(&mut self, balance_watch: BalanceWatch) {
+        self.balance_watch = Some(balance_watch);
+    }
+
+    pub fn add_sink(&mut self, sink: Box<dyn EventSink>) {
+        self.sinks.push(sink);
+    }
+
+    /// Read `blocks` blocks at a time.
+    pub fn set_fetch_window(&mut self, blocks: u64) {
+        self.fetch_window = blocks;
+    }
+
+    /// Shutdown request of the monitor, to stop it from another thread.
+    pub fn shutdown(&self) -> Shutdown { self.shutdown.clone() }
+
+    /// Read the blocks mined since the last refresh.
+    /// Connection errors are recorded in the snapshot.
+    pub fn refresh(&mut self) -> &MonitorSnapshot {
+        match self.try_refresh() {
+            Ok(()) => {
+                self.snapshot.connected = true;
+                self.snapshot.last_error = None;
+            },
+            Err(err) => {
+                warn!(error = %err, "Monitor refresh failed");
+                self.snapshot.connected = false;
+                self.snapshot.last_error = Some(err.to_string());
+            }
+        }
+        self.check_voting_window();
+        self.check_balances();
+        self.snapshot.last_refresh = Some(Utc::now());
+
+        let events: Vec<PollEvent> = self.events.drain(..).collect();
+        for event in events.iter() {
+            info!(event = %event, "Poll event");
+            for sink in self.sinks.iter_mut() {
+                sink.notify(&self.snapshot.poll_identifier, event);
+            }
+        }
+        &self.snapshot
+    }
+
+    fn check_voting_window(&mut self) {
+        let open = match self.voting_window.as_ref().map(|probe| probe()) {
+            Some(Ok(open)) => open,
+            Some(Err(err)) => {
+                warn!(error = %err, "Voting window could not be checked");
+                return;
+            },
+            None => return
+        };
+        if self.voting_open == Some(true) && !open {
+            self.events.push(PollEvent::VotingClosed { votes_counted: self.snapshot.votes_counted });
+        }
+        self.voting_open = Some(open);
+    }
+
+    fn check_balances(&mut self) {
+        let balance_watch = match self.balance_watch.as_mut() {
+            Some(balance_watch) => balance_watch,
+            None => return
+        };
+        let balances = match account_balances(&self.network) {
+            Ok(balances) => balances,
+            Err(err) => {
+                warn!(error = %err, "Account balances could not be read");
+                return;
+            }
+        };
+        for balance in balance_watch.check(&balances) {
+            let remaining_transactions = balance.remaining_transactions(balance_watch.alert.gas_per_transaction);
+            warn!(role = %balance.role, address = %balance.address, remaining_transactions, "Account balance is low");
+            self.events.push(PollEvent::LowBalance { balance, remaining_transactions });
+        }
+        self.snapshot.balances = balances;
+    }
+
+    fn try_refresh(&mut self) -> Result<()> {
+        let latest = latest_block(&self.network)?;
+        self.snapshot.latest_block = Some(latest);
+        if !self.commit_confirmed {
+            let status = transaction_status(&self.network, &self.commit_transaction)?;
+            if let (Some(block_number), true) = (status.block_number, status.finalized) {
+                self.commit_confirmed = true;
+                self.events.push(PollEvent::CommitConfirmed {
+                    transaction_hash: self.commit_transaction.clone(),
+                    block_number
+                });
+            }
+        }
+
+        // Blocks are read once, so only final blocks are read.
+        let final_block = final_block(&self.network)?;
+        let to_block = self.snapshot.end_block.map_or(final_block, |end_block| end_block.min(final_block));
+        if to_block < self.next_block {
+            self.check_end_block(final_block);
+            return Ok(());
+        }
+
+        // Every window is read whole before it is recorded, so that a failed
+        // refresh can simply be retried.
+        let pipeline = Pipeline::start(
+            &self.network,
+            &self.payload_identifier,
+            &self.commit_transaction,
+            self.vote_address.clone(),
+            self.next_block,
+            to_block,
+            self.fetch_window,
+            self.shutdown.clone());
+        while let Some(batch) = pipeline.next_batch() {
+            let batch = batch?;
+            batch.transactions.iter().for_each(|transaction| self.record(transaction));
+            self.events.extend(batch.handovers.into_iter().map(|handover| PollEvent::VoteAddressHandedOver { handover }));
+            self.vote_address = Some(batch.vote_address);
+            self.next_block = batch.to_block + 1;
+            metrics::set_last_confirmed_block(batch.to_block);
+        }
+        self.check_end_block(final_block);
+        metrics::set_votes_counted(self.snapshot.votes_counted);
+        metrics::set_audit_warnings(self.snapshot.warnings.len());
+
+        info!(
+            latest_block = latest,
+            votes_counted = self.snapshot.votes_counted,
+            invalid_payloads = self.snapshot.invalid_payloads,
+            warnings = self.snapshot.warnings.len(),
+            "Monitor refreshed");
+        Ok(())
+    }
+
+    fn check_end_block(&mut self, final_block: u64) {
+        match self.snapshot.end_block {
+            Some(end_block) if end_block <= final_block && !self.end_block_mined => {
+                self.end_block_mined = true;
+                self.events.push(PollEvent::EndBlockMined { end_block, votes_counted: self.snapshot.votes_counted });
+            },
+            _ => ()
+        }
+    }
+
+    fn record(&mut self, decoded: &DecodedTransaction) {
+        let (transaction, status) = (&decoded.transaction, &decoded.status);
+        let gas_used = status.gas_used.unwrap_or(0);
+        self.snapshot.gas_used += gas_used;
+        self.snapshot.fees += gas_used as u128 * transaction.gas_price as u128;
+
+        let kind = match self.classify(&decoded.payload, status) {
+            Ok(kind) => kind,
+            Err(warning) => {
+                warn!(warning = %warning, "Audit warning");
+                self.events.push(PollEvent::AuditAnomaly { warning: warning.clone() });
+                self.snapshot.warnings.push(warning);
+                TransactionKind::Rejected
+            }
+        };
+        self.snapshot.latest_transactions.insert(0, TransactionSummary {
+            transaction_hash: transaction.transaction_hash.clone(),
+            block_number: transaction.block_number,
+            timestamp: transaction.timestamp,
+            kind,
+            gas_used: status.gas_used
+        });
+        self.snapshot.latest_transactions.truncate(LATEST_TRANSACTIONS);
+    }
+
+    fn classify(&mut self, payload: &DecodedPayload, status: &TransactionStatus) -> std::result::Result<TransactionKind, AuditWarning> {
+        let vote = match payload {
+            DecodedPayload::Commit => return Ok(TransactionKind::Commit),
+            DecodedPayload::Encrypted => return Ok(TransactionKind::Encrypted),
+            DecodedPayload::Vote(vote) => vote,
+            DecodedPayload::Invalid => {
+                self.snapshot.invalid_payloads += 1;
+                metrics::inc_invalid_payloads();
+                return Ok(TransactionKind::Invalid);
+            }
+        };
+        let transaction_hash = vote.transaction_hash.clone();
+        if status.succeeded == Some(false) {
+            return Err(AuditWarning::TransactionFailed { transaction_hash });
+        }
+        let serial = match self.ballots.get_str(&vote.votecode) {
+            Some(&serial) => serial,
+            None => return Err(AuditWarning::UnknownVoteCode { votecode: vote.votecode.clone(), transaction_hash })
+        };
+        if self.audited.contains(&serial) {
+            return Err(AuditWarning::AuditedBallotVoted { serial, transaction_hash });
+        }
+        if !self.voted.insert(serial) {
+            return Err(AuditWarning::BallotVotedTwice { serial, transaction_hash });
+        }
+        self.snapshot.votes_counted += 1;
+        if self.vote_thresholds.contains(&self.snapshot.votes_counted) {
+            self.events.push(PollEvent::VoteThresholdReached {
+                threshold: self.snapshot.votes_counted,
+                votes_counted: self.snapshot.votes_counted
+            });
+        }
+        Ok(TransactionKind::Vote)
+    }
+}
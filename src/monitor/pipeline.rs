@@ -0,0 +1,168 @@
+//! # Monitoring Pipeline
+//!
+//! A refresh of the monitor reads the blocks mined since the previous one
+//! in three stages connected by bounded channels. The fetcher reads the
+//! vote transactions and their statuses by windows of blocks, the decoder
+//! decodes their payloads and the monitor counts the decoded transactions.
+//! A stage whose output channel is full waits for the next stage, so that
+//! a spike of transactions is held at most `PIPELINE_CAPACITY` windows at a
+//! time instead of being read into memory at once.
+//!
+//! Every window is read whole before it is counted, so that a refresh that
+//! fails or is shut down resumes after the last window counted. Once
+//! shutdown is requested, the fetcher stops reading new windows and the
+//! windows already read are counted.
+
+use std::sync::{Arc, Condvar, Mutex};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::thread;
+use std::time::Duration;
+use crate::blockchain::{ChainTransaction, Handover, TransactionStatus};
+use crate::blockchain::blocking::{transaction_statuses, vote_transactions_between};
+use crate::votes::{is_encrypted_vote, SubmittedVote};
+
+/// Windows of blocks buffered between two stages.
+pub const PIPELINE_CAPACITY: usize = 4;
+/// Blocks read by the fetcher at a time.
+pub const FETCH_WINDOW_BLOCKS: u64 = 1000;
+
+/// Request to stop the monitor, shared with the signal handler.
+#[derive(Debug, Clone, Default)]
+pub struct Shutdown(Arc<(Mutex<bool>, Condvar)>);
+
+impl Shutdown {
+    pub fn new() -> Self { Shutdown::default() }
+
+    pub fn request(&self) {
+        let (requested, condvar) = &*self.0;
+        *requested.lock().unwrap() = true;
+        condvar.notify_all();
+    }
+
+    pub fn requested(&self) -> bool { *(self.0).0.lock().unwrap() }
+
+    /// Wait for `timeout` unless shutdown is requested, returning whether it was.
+    pub fn wait(&self, timeout: Duration) -> bool {
+        let (requested, condvar) = &*self.0;
+        let (requested, _) = condvar.wait_timeout_while(requested.lock().unwrap(), timeout, |requested| !*requested).unwrap();
+        *requested
+    }
+}
+
+/// Payload of a transaction to the vote address, as decoded.
+#[derive(Debug, Clone)]
+pub enum DecodedPayload {
+    Commit,
+    /// Encrypted votes are only read once voting has closed.
+    Encrypted,
+    Vote(SubmittedVote),
+    Invalid
+}
+
+#[derive(Debug, Clone)]
+pub struct DecodedTransaction {
+    pub transaction: ChainTransaction,
+    pub status: TransactionStatus,
+    pub payload: DecodedPayload
+}
+
+/// Transactions of a window of blocks, read whole.
+#[derive(Debug, Clone)]
+pub struct PipelineBatch<T> {
+    pub to_block: u64,
+    pub transactions: Vec<T>,
+    pub handovers: Vec<Handover>,
+    /// Vote address at the end of the window.
+    pub vote_address: String
+}
+
+type Stage<T> = std::result::Result<PipelineBatch<T>, String>;
+
+/// Stages reading the blocks of a refresh.
+pub struct Pipeline {
+    batches: Receiver<Stage<DecodedTransaction>>
+}
+
+impl Pipeline {
+    /// Start reading blocks `from_block` to `to_block`, both included, of
+    /// `network` by windows of `window` blocks.
+    #[allow(clippy::too_many_arguments)]
+    pub fn start(network: &str, poll_identifier: &str, commit_transaction: &str, vote_address: Option<String>, from_block: u64, to_block: u64, window: u64, shutdown: Shutdown) -> Self {
+        let (fetched_sender, fetched) = sync_channel::<Stage<(ChainTransaction, TransactionStatus)>>(PIPELINE_CAPACITY);
+        let (decoded_sender, batches) = sync_channel::<Stage<DecodedTransaction>>(PIPELINE_CAPACITY);
+        let network = network.to_owned();
+        thread::spawn(move || fetch(&network, vote_address, from_block, to_block, window, shutdown, fetched_sender));
+        let poll_identifier = poll_identifier.to_owned();
+        let commit_transaction = commit_transaction.to_owned();
+        thread::spawn(move || decode(&poll_identifier, &commit_transaction, fetched, decoded_sender));
+        Pipeline { batches }
+    }
+
+    /// Next window of decoded transactions, in order, until the range is
+    /// read or a stage fails.
+    pub fn next_batch(&self) -> Option<crate::Result<PipelineBatch<DecodedTransaction>>> {
+        self.batches.recv().ok().map(|batch| batch.map_err(|err| err.into()))
+    }
+}
+
+// Read the windows of blocks, stopping at the first error, on shutdown or
+// once the decoder is gone.
+fn fetch(network: &str, mut vote_address: Option<String>, from_block: u64, to_block: u64, window: u64, shutdown: Shutdown, sender: SyncSender<Stage<(ChainTransaction, TransactionStatus)>>) {
+    for (from_block, to_block) in crate::blockchain::block_windows(from_block, to_block, window) {
+        if shutdown.requested() {
+            return;
+        }
+        let fetched = vote_transactions_between(network, vote_address.as_deref(), from_block, to_block)
+            .and_then(|read| {
+                let hashes: Vec<String> = read.transactions.iter().map(|transaction| transaction.transaction_hash.clone()).collect();
+                let statuses = transaction_statuses(network, &hashes)?;
+                Ok(PipelineBatch {
+                    to_block,
+                    transactions: read.transactions.into_iter().zip(statuses).collect(),
+                    handovers: read.handovers,
+                    vote_address: read.vote_address
+                })
+            })
+            .map_err(|err| err.to_string());
+        let failed = fetched.is_err();
+        if let Ok(batch) = &fetched {
+            vote_address = Some(batch.vote_address.clone());
+        }
+        if sender.send(fetched).is_err() || failed {
+            return;
+        }
+    }
+}
+
+fn decode(poll_identifier: &str, commit_transaction: &str, fetched: Receiver<Stage<(ChainTransaction, TransactionStatus)>>, sender: SyncSender<Stage<DecodedTransaction>>) {
+    for batch in fetched {
+        let decoded = batch.map(|batch| PipelineBatch {
+            to_block: batch.to_block,
+            transactions: batch.transactions.into_iter()
+                .map(|(transaction, status)| {
+                    let payload = decode_payload(poll_identifier, commit_transaction, &transaction);
+                    DecodedTransaction { transaction, status, payload }
+                })
+                .collect(),
+            handovers: batch.handovers,
+            vote_address: batch.vote_address
+        });
+        if sender.send(decoded).is_err() {
+            return;
+        }
+    }
+}
+
+/// Decode the payload of a transaction to the vote address of a poll.
+pub fn decode_payload(poll_identifier: &str, commit_transaction: &str, transaction: &ChainTransaction) -> DecodedPayload {
+    if transaction.transaction_hash == commit_transaction {
+        return DecodedPayload::Commit;
+    }
+    if is_encrypted_vote(poll_identifier, &transaction.input) {
+        return DecodedPayload::Encrypted;
+    }
+    match SubmittedVote::from_transaction(poll_identifier, transaction) {
+        Some(vote) => DecodedPayload::Vote(vote),
+        None => DecodedPayload::Invalid
+    }
+}
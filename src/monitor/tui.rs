@@ -0,0 +1,86 @@
+//! # Monitor Terminal UI
+//!
+//! `tui` shows the `MonitorSnapshot` full screen, refreshing it every
+//! interval. Press `r` to refresh immediately and `q` to quit.
+
+use std::io::{self, Stdout, Write};
+use std::time::{Duration, Instant};
+use crossterm::{execute, queue};
+use crossterm::cursor::{Hide, MoveTo, Show};
+use crossterm::event::{self, Event, KeyCode, KeyModifiers};
+use crossterm::style::{Attribute, Color, Print, ResetColor, SetAttribute, SetForegroundColor};
+use crossterm::terminal::{self, Clear, ClearType, EnterAlternateScreen, LeaveAlternateScreen};
+use super::{Monitor, MonitorSnapshot};
+use crate::logging::set_muted;
+use crate::Result;
+
+const TITLE: &str = "Seventh Estate Poll Monitor";
+const KEYS: &str = "q: quit  r: refresh";
+
+/// Run the terminal UI until the user quits.
+pub fn run_tui(monitor: &mut Monitor, interval: Duration) -> Result<()> {
+    let mut stdout = io::stdout();
+    terminal::enable_raw_mode()?;
+    execute!(stdout, EnterAlternateScreen, Hide)?;
+    set_muted(true);
+
+    let result = event_loop(monitor, interval, &mut stdout);
+
+    set_muted(false);
+    execute!(stdout, Show, LeaveAlternateScreen)?;
+    terminal::disable_raw_mode()?;
+    result
+}
+
+fn event_loop(monitor: &mut Monitor, interval: Duration, stdout: &mut Stdout) -> Result<()> {
+    loop {
+        draw(stdout, monitor.snapshot(), "refreshing...")?;
+        draw(stdout, monitor.refresh(), "")?;
+
+        // Wait for the next refresh, or for a key press.
+        let next_refresh = Instant::now() + interval;
+        loop {
+            let now = Instant::now();
+            if next_refresh <= now { break; }
+            if !event::poll(next_refresh - now)? { break; }
+            match event::read()? {
+                Event::Key(key) => match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+                    KeyCode::Char('r') => break,
+                    _ => ()
+                },
+                Event::Resize(_, _) => draw(stdout, monitor.snapshot(), "")?,
+                _ => ()
+            }
+        }
+    }
+}
+
+fn draw(stdout: &mut Stdout, snapshot: &MonitorSnapshot, status: &str) -> Result<()> {
+    let (columns, rows) = terminal::size()?;
+    let fit = |text: &str| text.chars().take(columns as usize).collect::<String>();
+
+    queue!(stdout, Clear(ClearType::All), MoveTo(0, 0),
+        SetAttribute(Attribute::Reverse),
+        Print(fit(&format!("{:<width$}", TITLE, width = columns as usize))),
+        SetAttribute(Attribute::Reset))?;
+
+    snapshot.to_string().lines()
+        .take(rows.saturating_sub(3) as usize)
+        .enumerate()
+        .try_for_each(|(n, line)| {
+            queue!(stdout, MoveTo(0, n as u16 + 2))?;
+            match line.starts_with('!') {
+                true => queue!(stdout, SetForegroundColor(Color::Yellow), Print(fit(line)), ResetColor),
+                false => queue!(stdout, Print(fit(line)))
+            }
+        })?;
+
+    queue!(stdout, MoveTo(0, rows.saturating_sub(1)),
+        SetAttribute(Attribute::Reverse),
+        Print(fit(&format!("{:<width$}", format!("{}  {}", KEYS, status), width = columns as usize))),
+        SetAttribute(Attribute::Reset))?;
+    stdout.flush()?;
+    Ok(())
+}
@@ -0,0 +1,52 @@
+//! # Command Output
+//!
+//! `output` controls how command results are written to stdout.
+//! In `text` mode results are printed for humans to read, in `json` mode
+//! every result is printed as a single JSON document so that scripts can
+//! consume it directly.
+
+use std::fmt::Display;
+use std::str::FromStr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use serde::Serialize;
+use crate::Result;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputFormat { Text, Json }
+
+pub const OUTPUT_FORMATS: [&str; 2] = ["text", "json"];
+
+static JSON_OUTPUT: AtomicBool = AtomicBool::new(false);
+
+impl FromStr for OutputFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "text" => Ok(OutputFormat::Text),
+            "json" => Ok(OutputFormat::Json),
+            _ => Err(format!("Unknown output format: {}", value))
+        }
+    }
+}
+
+/// Select the output format for the whole process.
+pub fn set_output_format(format: OutputFormat) {
+    JSON_OUTPUT.store(format == OutputFormat::Json, Ordering::Relaxed);
+}
+
+pub fn output_format() -> OutputFormat {
+    match JSON_OUTPUT.load(Ordering::Relaxed) {
+        true => OutputFormat::Json,
+        false => OutputFormat::Text
+    }
+}
+
+/// Write a command result to stdout in the selected output format.
+pub fn emit<T: Serialize + Display>(value: &T) -> Result<()> {
+    match output_format() {
+        OutputFormat::Text => println!("{}", value),
+        OutputFormat::Json => println!("{}", serde_json::to_string(value)?)
+    }
+    Ok(())
+}
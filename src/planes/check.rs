@@ -0,0 +1,50 @@
+//! # Plane Consistency
+//!
+//! The rows of a plane follow the ballots: rows `2n` and `2n + 1` hold the
+//! two vote codes of ballot `n`. Before the planes are committed, every
+//! plane must have exactly two rows per ballot of the poll, and the serials
+//! of the cells in the clear must be those of their rows, so that no ballot
+//! is missing or held twice. Encrypted cells of a plane must be distinct.
+
+use std::collections::HashMap;
+use crate::Result;
+use super::*;
+
+/// Serial of a column 1 cell in the clear: `"{serial}: {votecode}"`.
+fn cell_serial(entry: &str) -> Option<BallotSerial> {
+    entry.split(':').next()?.trim().parse().ok()
+}
+
+/// Check that every plane has two rows per ballot of the poll, in serial
+/// order.
+pub fn check_planes(planes: &[Plane], num_ballots: usize) -> Result<()> {
+    let rows = 2 * num_ballots;
+    for (p, plane) in planes.iter().enumerate() {
+        if plane.len() != rows {
+            return Err(format!("Plane {} has {} rows, but the poll has {} ballots: two rows per ballot make {}.",
+                p + 1, plane.len(), num_ballots, rows).into());
+        }
+        let mut col1_cells: HashMap<&str, usize> = HashMap::new();
+        let mut col3_cells: HashMap<&str, usize> = HashMap::new();
+        for (n, row) in plane.rows.iter().enumerate() {
+            match &row.col1 {
+                Column1Entry::Entry(entry) => match cell_serial(entry) {
+                    Some(serial) if serial == n / 2 => (),
+                    Some(serial) => return Err(format!("Row {} of plane {} holds ballot {}, not ballot {}.", n, p + 1, serial, n / 2).into()),
+                    None => return Err(format!("Row {} of plane {} has no ballot serial.", n, p + 1).into())
+                },
+                Column1Entry::Encrypted(cell) => {
+                    if let Some(other) = col1_cells.insert(&cell.0, n) {
+                        return Err(format!("Rows {} and {} of plane {} hold the same column 1 cell.", other, n, p + 1).into());
+                    }
+                }
+            }
+            if let Column3Entry::Encrypted(cell) = &row.col3 {
+                if let Some(other) = col3_cells.insert(&cell.0, n) {
+                    return Err(format!("Rows {} and {} of plane {} hold the same column 3 cell.", other, n, p + 1).into());
+                }
+            }
+        }
+    }
+    Ok(())
+}
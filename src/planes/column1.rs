@@ -23,8 +23,7 @@ impl Column1Entry {
                     true => {
                         let aevalues = aestr.values().unwrap();
                         let serialized_bytes = aead_decrypt(&filter.key, &aevalues).unwrap();
-                        let serialized = String::from_utf8(serialized_bytes).unwrap();
-                        let entry = serialized;
+                        let entry = str::from_utf8(&serialized_bytes).unwrap().to_owned();
                         Self::Entry(entry)
                     },
                     false => (*self).clone()
@@ -16,8 +16,7 @@ impl Column3Entry {
                     true => {
                         let aevalues = aestr.values().unwrap();
                         let serialized_bytes = aead_decrypt(&filter.key, &aevalues).unwrap();
-                        let serialized = String::from_utf8(serialized_bytes).unwrap();
-                        let entry = serde_yaml::from_str(&serialized).unwrap();
+                        let entry = serde_yaml::from_str(str::from_utf8(&serialized_bytes).unwrap()).unwrap();
                         Self::Entry(entry)
                     },
                     false => (*self).clone()
@@ -28,6 +28,12 @@ pub use column3::*;
 pub mod filter;
 pub use filter::*;
 
+pub mod tally;
+pub use tally::*;
+
+pub mod check;
+pub use check::*;
+
 
 #[derive(Debug)]
 pub struct Plane {
@@ -0,0 +1,13 @@
+//! # Plane Tally
+//!
+//! The tally is part of the verification core, so that the voter-facing
+//! website recounts the revealed planes exactly as this crate does.
+
+use super::*;
+
+pub use seventh_estate_core::tally::*;
+
+impl TallyRow for PlaneRecordFileRow {
+    fn col2(&self) -> &str { &self.col2 }
+    fn col3(&self) -> &str { &self.col3 }
+}
@@ -2,10 +2,20 @@
 //!
 //! The Poll Configuration comprises:
 //! * Poll Master Key (PMK)
-//! * Plane Key [N] = GEN(0x1 || N || PMK)
-//! * Plane Row Permutation Seed [N] = GEN(0x2 || N || PMK)
+//! * Generation Seed (GS), the PMK for polls created without one
+//! * Plane Key [N] = GEN(0x1 || N || GS)
+//! * Plane Row Permutation Seed [N] = GEN(0x2 || N || GS)
 //!
+//! The signing key, the generation seed and the salt of the roster policy
+//! are scrubbed from memory when the configuration is dropped.
 
+use std::collections::HashSet;
+use chrono::FixedOffset;
+use zeroize::Zeroize;
+use crate::ballots::{BallotSerial, SerialRanges, UndeliveredBallot, UndeliveredPolicy};
+use crate::blockchain::{BallotAmendment, RosterChunk};
+use crate::voter_roster::{RosterPolicy, RosterSchema};
+use crate::votes::{ProvisionalBallot, TallyRules, VoteRecord};
 use super::*;
 
 
@@ -17,17 +27,170 @@ pub struct PollConfigurationTrustee {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PollConfiguration {
+    /// Identifier of the poll on chain, for polls created with one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub poll_uuid: Option<PollUuid>,
     pub poll_state: PollState,
     pub signing_key: Base64String,
     pub num_ballots: usize,
     pub num_decoys: usize,
+    /// Serials issued to voters and reserved; every ballot is issued
+    /// without them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial_ranges: Option<SerialRanges>,
     pub voter_roster: Option<Base64String>,
     pub voter_roster_size: usize,
+    /// Fields of the voter roster, the standard fields if not given.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roster_schema: Option<RosterSchema>,
+    /// Batches the roster was committed in as registration closed.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub roster_chunks: Vec<RosterChunk>,
     pub voter_privacy: bool,
+    /// Fields of the voter information published in the committed roster,
+    /// the others only as a salted hash.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roster_policy: Option<RosterPolicy>,
+    /// Hexadecimal seed the vote codes, decoys, summands and planes are
+    /// derived from, so that identical ballots can be regenerated from it.
+    #[serde(default)]
+    pub generation_seed: Option<String>,
     pub drawn_summands_seed: Option<String>,
     pub audited_columns_seed: Option<String>,
     pub audited_ballots: Option<Vec<String>>,
-    pub votes: Option<Vec<VoteCode>>
+    pub votes: Option<Vec<VoteCode>>,
+    /// Number of recorded votes of every source.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub vote_sources: Vec<VoteSourceCount>,
+    #[serde(default)]
+    pub poll_question: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ballot_text: Vec<LocalizedBallotText>,
+    /// Rules of the poll, committed with the question.
+    #[serde(default)]
+    pub poll_rules: Option<String>,
+    /// Ballots may be sent to voters by email, including their vote codes.
+    #[serde(default)]
+    pub electronic_delivery: bool,
+    /// Last block in which votes are accepted.
+    #[serde(default)]
+    pub end_block: Option<u64>,
+    /// UTC offset the times of the tally report are given in.
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// Ballots spoiled after they were issued, and their replacements.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ballot_amendments: Vec<BallotAmendment>,
+    /// Ballots the mailing vendor could not deliver.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub undelivered_ballots: Vec<UndeliveredBallot>,
+    /// What happens to the vote codes of undelivered ballots.
+    #[serde(default, skip_serializing_if = "UndeliveredPolicy::is_default")]
+    pub undelivered_policy: UndeliveredPolicy,
+    /// Ballots whose votes are only counted once the voter is found eligible.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub provisional_ballots: Vec<ProvisionalBallot>,
+    /// Recorded votes for provisional ballots, not part of `votes`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub provisional_votes: Vec<VoteCode>,
+    /// Transaction and block of every recorded vote.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub vote_records: Vec<VoteRecord>,
+    /// Number of votes for test vote codes, which are not counted.
+    #[serde(default)]
+    pub test_votes: usize,
+    /// Counting rules of the poll.
+    #[serde(default, skip_serializing_if = "TallyRules::is_default")]
+    pub tally_rules: TallyRules,
+    /// Number of votes not counted by the tally rules for ballots voted
+    /// with both of their vote codes.
+    #[serde(default)]
+    pub uncounted_ballot_votes: usize,
+    /// Votes are relayed encrypted to the vote encryption key of the poll.
+    #[serde(default)]
+    pub encrypted_votes: bool,
+    /// Votes are relayed encrypted to a key locked until the reveal time.
+    #[serde(default)]
+    pub time_lock: Option<TimeLockedKey>
+}
+
+impl PollConfiguration {
+    /// Poll identifier of the vote payloads of the poll: its UUID, or
+    /// `poll_identifier`, its name, for polls created without one.
+    pub fn payload_identifier(&self, poll_identifier: &str) -> String {
+        match &self.poll_uuid {
+            Some(poll_uuid) => poll_uuid.to_string(),
+            None => poll_identifier.to_owned()
+        }
+    }
+
+    /// Serials of the ballots spoiled after they were issued, whose votes
+    /// are not counted.
+    pub fn spoiled_ballots(&self) -> HashSet<BallotSerial> {
+        self.ballot_amendments.iter()
+            .map(|amendment| amendment.spoiled_serial)
+            .collect()
+    }
+
+    /// Serials of the ballots whose votes are rejected: the spoiled ballots
+    /// and, under a policy invalidating them, the undelivered ballots.
+    pub fn excluded_ballots(&self) -> HashSet<BallotSerial> {
+        let mut excluded = self.spoiled_ballots();
+        if self.undelivered_policy == UndeliveredPolicy::Invalidate {
+            excluded.extend(self.undelivered_ballots.iter().map(|ballot| ballot.serial));
+        }
+        excluded
+    }
+
+    /// Serial ranges of the ballots of the poll.
+    pub fn serial_ranges(&self) -> SerialRanges {
+        self.serial_ranges.clone().unwrap_or_else(|| SerialRanges::for_poll(self.num_ballots))
+    }
+
+    /// Key the votes are encrypted to, for polls with encrypted or
+    /// time-locked votes.
+    pub fn vote_encryption_key(&self) -> crate::Result<Option<VoteEncryptionKey>> {
+        match (&self.time_lock, self.vote_decryption_key()?) {
+            (Some(time_lock), _) => Ok(Some(time_lock.encryption_key()?)),
+            (None, key) => Ok(key.map(|key| key.encryption_key()))
+        }
+    }
+
+    /// Policy the roster is committed under, unless voter privacy is
+    /// disabled.
+    pub fn roster_policy(&self) -> Option<&RosterPolicy> {
+        match self.voter_privacy {
+            true => self.roster_policy.as_ref(),
+            false => None
+        }
+    }
+
+    /// Offset of the timezone of the poll, UTC if it has none.
+    pub fn utc_offset(&self) -> crate::Result<FixedOffset> {
+        match &self.timezone {
+            Some(timezone) => parse_utc_offset(timezone),
+            None => Ok(FixedOffset::east(0))
+        }
+    }
+
+    /// Key encrypted votes are decrypted with, for polls with encrypted
+    /// votes that are not time-locked.
+    pub fn vote_decryption_key(&self) -> crate::Result<Option<VoteDecryptionKey>> {
+        match self.encrypted_votes && self.time_lock.is_none() {
+            true => Ok(Some(VoteDecryptionKey::from_signing_key(&self.signing_key)?)),
+            false => Ok(None)
+        }
+    }
+}
+
+impl Drop for PollConfiguration {
+    fn drop(&mut self) {
+        self.signing_key.0.zeroize();
+        self.generation_seed.zeroize();
+        if let Some(policy) = self.roster_policy.as_mut() {
+            policy.salt.0.zeroize();
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
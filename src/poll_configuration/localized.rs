@@ -0,0 +1,42 @@
+//! # Localized Ballot Text
+//!
+//! Question and choice text of a poll in one locale. Ballots and the tally
+//! report show the text of every locale of the poll, in the order given,
+//! for jurisdictions that must issue multilingual ballots.
+
+use super::*;
+
+/// Separator between the translations of a choice.
+pub const LOCALE_SEPARATOR: &str = " / ";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LocalizedBallotText {
+    pub locale: String,
+    pub question: String,
+    pub choice_for: String,
+    pub choice_against: String
+}
+
+/// Check that every locale is given once.
+pub fn validate_localized_text(texts: &[LocalizedBallotText]) -> Result<(), String> {
+    texts.iter().enumerate().try_for_each(|(n, text)| {
+        if text.locale.trim().is_empty() {
+            return Err("Ballot text locale must not be empty.".to_owned());
+        }
+        match texts[..n].iter().any(|other| other.locale == text.locale) {
+            true => Err(format!("Ballot text given twice for locale {}.", text.locale)),
+            false => Ok(())
+        }
+    })
+}
+
+/// Labels of the For and Against choices in every locale,
+/// or `None` if the poll has no localized text.
+pub fn localized_choice_labels(texts: &[LocalizedBallotText]) -> Option<(String, String)> {
+    match texts.is_empty() {
+        true => None,
+        false => Some((
+            texts.iter().map(|text| text.choice_for.as_str()).collect::<Vec<&str>>().join(LOCALE_SEPARATOR),
+            texts.iter().map(|text| text.choice_against.as_str()).collect::<Vec<&str>>().join(LOCALE_SEPARATOR)))
+    }
+}
@@ -1,8 +1,9 @@
 //! Module for poll configuration information and files.
 
 use serde::{Serialize, Deserialize};
-use crate::cryptography::{Base64String, AEADString};
+use crate::cryptography::{Base64String, AEADString, TimeLockedKey, VoteDecryptionKey, VoteEncryptionKey};
 use crate::ballots::VoteCode;
+use crate::votes::VoteSourceCount;
 
 pub mod complete;
 pub use complete::*;
@@ -12,3 +13,18 @@ pub use secured::*;
 
 pub mod new;
 pub use new::*;
+
+pub mod localized;
+pub use localized::*;
+
+pub mod public;
+pub use public::*;
+
+pub mod question;
+pub use question::*;
+
+pub mod timezone;
+pub use timezone::*;
+
+pub mod uuid;
+pub use self::uuid::*;
@@ -3,16 +3,58 @@
 //! This file is used to generate a new poll.
 //! This file contains no sensitive information or secrets.
 
+use chrono::{DateTime, Utc};
+use crate::ballots::{SerialRanges, UndeliveredPolicy};
+use crate::voter_roster::RosterSchema;
+use crate::votes::TallyRules;
 use super::*;
 
-#[derive(Debug, Clone, Deserialize)]
-pub struct NewPollConfigurationTrustee { pub identifier: String }
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewPollConfigurationTrustee {
+    pub identifier: String,
+    /// Base64 encoded verification key the trustee generated to approve the
+    /// commit of the poll with; one is generated for them if not given.
+    #[serde(default)]
+    pub approval_key: Option<String>
+}
 
-#[derive(Debug, Clone, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct NewPollConfiguration {
     pub poll_identifier: String,
     pub poll_trustees: Vec<NewPollConfigurationTrustee>,
     pub num_ballots: usize,
-    pub num_decoys: usize
+    pub num_decoys: usize,
+    /// Serials issued to voters and reserved, all issued if not given.
+    #[serde(default)]
+    pub serial_ranges: Option<SerialRanges>,
+    /// Fields of the voter roster, the standard fields if not given.
+    #[serde(default)]
+    pub roster_schema: Option<RosterSchema>,
+    #[serde(default)]
+    pub poll_question: Option<String>,
+    #[serde(default)]
+    pub ballot_text: Vec<LocalizedBallotText>,
+    /// Rules of the poll, committed with the question.
+    #[serde(default)]
+    pub poll_rules: Option<String>,
+    #[serde(default)]
+    pub electronic_delivery: bool,
+    #[serde(default)]
+    pub end_block: Option<u64>,
+    /// UTC offset of the poll, such as `+02:00`.
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// Counting rules of the poll, the default rules if not given.
+    #[serde(default)]
+    pub tally_rules: TallyRules,
+    #[serde(default)]
+    pub encrypted_votes: bool,
+    /// What happens to the vote codes of undelivered ballots, kept if not
+    /// given.
+    #[serde(default)]
+    pub undelivered_policy: UndeliveredPolicy,
+    /// Votes are time-locked until this time.
+    #[serde(default)]
+    pub reveal_at: Option<DateTime<Utc>>
 }
 
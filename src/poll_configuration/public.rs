@@ -0,0 +1,94 @@
+//! Structure for the public poll configuration.
+//!
+//! The public poll configuration is the part of the complete configuration
+//! that can be published with the artifacts of a poll: it leaves out the
+//! signing key, the generation seed, the voter roster and the recorded
+//! votes, and keeps the seeds drawn in public that verifiers need.
+
+use std::fmt;
+use crate::blockchain::BallotAmendment;
+use crate::voter_roster::{RosterField, RosterSchema};
+use super::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PublicPollConfiguration {
+    pub poll_identifier: String,
+    /// Base64 encoded poll verification key.
+    pub public_key: String,
+    pub poll_trustees: Vec<String>,
+    pub poll_state: PollState,
+    pub num_ballots: usize,
+    pub num_decoys: usize,
+    pub voter_roster_size: usize,
+    /// Fields of the voter roster, for polls created with a roster schema.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roster_schema: Option<RosterSchema>,
+    pub voter_privacy: bool,
+    /// Fields of the voter information published in the committed roster,
+    /// for polls committed under a roster policy.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub roster_published_fields: Option<Vec<RosterField>>,
+    pub drawn_summands_seed: Option<String>,
+    pub audited_columns_seed: Option<String>,
+    pub audited_ballots: Option<Vec<String>>,
+    #[serde(default)]
+    pub poll_question: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ballot_text: Vec<LocalizedBallotText>,
+    #[serde(default)]
+    pub poll_rules: Option<String>,
+    #[serde(default)]
+    pub end_block: Option<u64>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ballot_amendments: Vec<BallotAmendment>,
+    /// Base64 encoded key votes are encrypted to, for polls with encrypted
+    /// votes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vote_encryption_key: Option<String>,
+    /// Time-lock puzzle of the vote decryption key, for polls with
+    /// time-locked votes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_lock: Option<TimeLockedKey>
+}
+
+impl PublicPollConfiguration {
+    pub fn new(
+        poll_identifier: &str,
+        poll_trustees: &[PollConfigurationTrustee],
+        pollconf: &PollConfiguration,
+        public_key: &Base64String) -> Self
+    {
+        PublicPollConfiguration {
+            poll_identifier: poll_identifier.to_owned(),
+            public_key: public_key.0.clone(),
+            poll_trustees: poll_trustees.iter().map(|trustee| trustee.identifier.clone()).collect(),
+            poll_state: pollconf.poll_state.clone(),
+            num_ballots: pollconf.num_ballots,
+            num_decoys: pollconf.num_decoys,
+            voter_roster_size: pollconf.voter_roster_size,
+            roster_schema: pollconf.roster_schema.clone(),
+            voter_privacy: pollconf.voter_privacy,
+            roster_published_fields: pollconf.roster_policy()
+                .map(|policy| policy.published_fields.clone()),
+            drawn_summands_seed: pollconf.drawn_summands_seed.clone(),
+            audited_columns_seed: pollconf.audited_columns_seed.clone(),
+            audited_ballots: pollconf.audited_ballots.clone(),
+            poll_question: pollconf.poll_question.clone(),
+            ballot_text: pollconf.ballot_text.clone(),
+            poll_rules: pollconf.poll_rules.clone(),
+            end_block: pollconf.end_block,
+            ballot_amendments: pollconf.ballot_amendments.clone(),
+            vote_encryption_key: pollconf.vote_encryption_key().ok().flatten()
+                .map(|key| key.to_base64()),
+            time_lock: pollconf.time_lock.clone()
+        }
+    }
+}
+
+impl fmt::Display for PublicPollConfiguration {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Poll:     {}", self.poll_identifier)?;
+        writeln!(f, "Trustees: {}", self.poll_trustees.join(", "))?;
+        write!(f, "Ballots:  {} ({} decoys)", self.num_ballots, self.num_decoys)
+    }
+}
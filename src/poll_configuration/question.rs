@@ -0,0 +1,82 @@
+//! # Ballot Question
+//!
+//! `BallotQuestion` describes what a poll is about: the question, the
+//! choices, the text of every locale, the rules of the poll, the
+//! counting rules of its tally and the serial ranges of its ballots. It is committed with the poll, as a leaf of
+//! the Merkle tree and by its hash in the commitment transaction, so that
+//! the question cannot be disputed once votes are cast.
+//!
+//! The descriptor is committed in a canonical form: compact JSON with the
+//! fields in the order declared, text trimmed and with `\n` line endings.
+
+use chrono::{DateTime, Utc};
+use crypto::digest::Digest;
+use crypto::sha3::{Sha3, Sha3Mode};
+use crate::ballots::SerialRanges;
+use crate::votes::TallyRules;
+use super::*;
+
+/// Choices of every poll, as counted in the planes.
+pub const BALLOT_CHOICES: [&str; 2] = ["For", "Against"];
+
+pub const BALLOT_QUESTION_FILENAME: &str = "ballot_question.json";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BallotQuestion {
+    pub poll_identifier: String,
+    pub question: Option<String>,
+    pub choices: Vec<String>,
+    pub ballot_text: Vec<LocalizedBallotText>,
+    /// Rules of the poll, as announced to voters.
+    pub rules: Option<String>,
+    pub end_block: Option<u64>,
+    pub reveal_at: Option<DateTime<Utc>>,
+    /// Counting rules of the poll, only committed when they are not the
+    /// default rules, so that the hash of other polls is unchanged.
+    #[serde(default, skip_serializing_if = "TallyRules::is_default")]
+    pub tally_rules: TallyRules,
+    /// Serial ranges of the ballots, committed when they are configured.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub serial_ranges: Option<SerialRanges>
+}
+
+fn canonical_text(text: &str) -> String {
+    text.replace("\r\n", "\n").trim().to_owned()
+}
+
+impl BallotQuestion {
+    pub fn from_poll(poll_identifier: &str, pollconf: &PollConfiguration) -> Self {
+        BallotQuestion {
+            poll_identifier: poll_identifier.to_owned(),
+            question: pollconf.poll_question.as_deref().map(canonical_text),
+            choices: BALLOT_CHOICES.iter().map(|choice| choice.to_string()).collect(),
+            ballot_text: pollconf.ballot_text.iter()
+                .map(|text| LocalizedBallotText {
+                    locale: canonical_text(&text.locale),
+                    question: canonical_text(&text.question),
+                    choice_for: canonical_text(&text.choice_for),
+                    choice_against: canonical_text(&text.choice_against)
+                })
+                .collect(),
+            rules: pollconf.poll_rules.as_deref().map(canonical_text),
+            end_block: pollconf.end_block,
+            reveal_at: pollconf.time_lock.as_ref().map(|time_lock| time_lock.reveal_at),
+            tally_rules: pollconf.tally_rules.clone(),
+            serial_ranges: pollconf.serial_ranges.clone()
+        }
+    }
+
+    /// Canonical form of the descriptor, committed as a leaf.
+    pub fn canonical(&self) -> String {
+        serde_json::to_string(self).expect("ballot question is serializable")
+    }
+
+    /// SHA3-256 hash of the canonical form, posted with the Merkle root.
+    pub fn hash(&self) -> [u8; 32] {
+        let mut hasher = Sha3::new(Sha3Mode::Sha3_256);
+        hasher.input(self.canonical().as_bytes());
+        let mut hash = [0u8; 32];
+        hasher.result(&mut hash);
+        hash
+    }
+}
@@ -0,0 +1,30 @@
+//! # Poll Timezone
+//!
+//! The timezone of a poll is a UTC offset such as `+02:00`, in which the
+//! times of the tally report are given. Polls without one report in UTC.
+
+use chrono::FixedOffset;
+
+/// Offset of `+HH:MM`, `-HH:MM`, `+HHMM`, `+HH`, `Z` or `UTC`.
+pub fn parse_utc_offset(value: &str) -> crate::Result<FixedOffset> {
+    let value = value.trim();
+    if value == "Z" || value.eq_ignore_ascii_case("UTC") {
+        return Ok(FixedOffset::east(0));
+    }
+    let invalid = || format!("Invalid UTC offset {}: expected e.g. +02:00.", value);
+    let (sign, digits) = match value.chars().next() {
+        Some('+') => (1, &value[1..]),
+        Some('-') => (-1, &value[1..]),
+        _ => return Err(invalid().into())
+    };
+    let digits = digits.replace(':', "");
+    if !digits.chars().all(|c| c.is_ascii_digit()) || (digits.len() != 2 && digits.len() != 4) {
+        return Err(invalid().into());
+    }
+    let hours: i32 = digits[..2].parse()?;
+    let minutes: i32 = if digits.len() == 4 { digits[2..].parse()? } else { 0 };
+    if 23 < hours || 59 < minutes {
+        return Err(invalid().into());
+    }
+    Ok(FixedOffset::east(sign * (hours * 3600 + minutes * 60)))
+}
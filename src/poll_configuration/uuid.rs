@@ -0,0 +1,79 @@
+//! # Poll UUID
+//!
+//! Every poll created is given a random (version 4) UUID, written as
+//! `xxxxxxxx-xxxx-4xxx-yxxx-xxxxxxxxxxxx`. One address may commit several
+//! polls over time, under the same name, so the UUID rather than the name
+//! identifies the poll on chain: it is posted in the commitment, and the
+//! vote payloads of the poll carry it as their poll identifier. Polls
+//! created before it are identified by their name.
+
+use std::convert::TryFrom;
+use std::fmt;
+use std::str::FromStr;
+use rand::RngCore;
+use rand::rngs::OsRng;
+use super::*;
+
+pub const POLL_UUID_LENGTH: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct PollUuid([u8; POLL_UUID_LENGTH]);
+
+impl PollUuid {
+    pub fn generate() -> Self {
+        let mut bytes = [0u8; POLL_UUID_LENGTH];
+        OsRng.fill_bytes(&mut bytes);
+        bytes[6] = (bytes[6] & 0x0f) | 0x40;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+        PollUuid(bytes)
+    }
+
+    pub fn from_bytes(bytes: [u8; POLL_UUID_LENGTH]) -> Self {
+        PollUuid(bytes)
+    }
+
+    pub fn as_bytes(&self) -> &[u8; POLL_UUID_LENGTH] {
+        &self.0
+    }
+
+    /// UUID written as `xxxxxxxx-xxxx-xxxx-xxxx-xxxxxxxxxxxx`, in either case.
+    pub fn parse(value: &str) -> Option<Self> {
+        let groups: Vec<&str> = value.trim().split('-').collect();
+        if groups.iter().map(|group| group.len()).collect::<Vec<usize>>() != [8, 4, 4, 4, 12] {
+            return None;
+        }
+        let mut bytes = [0u8; POLL_UUID_LENGTH];
+        hex::decode_to_slice(groups.concat(), &mut bytes).ok()?;
+        Some(PollUuid(bytes))
+    }
+}
+
+impl fmt::Display for PollUuid {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let hex = hex::encode(self.0);
+        write!(f, "{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+    }
+}
+
+impl FromStr for PollUuid {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        PollUuid::parse(value).ok_or_else(|| format!("Invalid poll UUID: {}", value))
+    }
+}
+
+impl TryFrom<String> for PollUuid {
+    type Error = String;
+
+    fn try_from(value: String) -> std::result::Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<PollUuid> for String {
+    fn from(uuid: PollUuid) -> Self {
+        uuid.to_string()
+    }
+}
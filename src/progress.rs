@@ -0,0 +1,92 @@
+//! # Progress Reporting
+//!
+//! `progress` emits periodic progress events for long running operations
+//! such as hashing the leaves of the commitment tree or counting votes.
+//! Events are reported through the logging subscriber every ten percent
+//! of completion, or at most every few seconds for slow operations.
+//!
+//! Progress reporting can be silenced globally with `set_quiet`.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tracing::info;
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+const REPORT_STEPS: usize = 10;
+const REPORT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Enable or disable progress reporting for the whole process.
+pub fn set_quiet(quiet: bool) {
+    QUIET.store(quiet, Ordering::Relaxed);
+}
+
+pub fn is_quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Progress of a single operation over a known number of items.
+pub struct Progress {
+    operation: &'static str,
+    unit: &'static str,
+    total: usize,
+    done: usize,
+    reported: Option<usize>,
+    next_report: usize,
+    started: Instant,
+    last_report: Instant
+}
+
+impl Progress {
+    pub fn new(operation: &'static str, unit: &'static str, total: usize) -> Self {
+        let now = Instant::now();
+        Progress {
+            operation,
+            unit,
+            total,
+            done: 0,
+            reported: None,
+            next_report: Self::step(total),
+            started: now,
+            last_report: now
+        }
+    }
+
+    fn step(total: usize) -> usize {
+        std::cmp::max(1, total / REPORT_STEPS)
+    }
+
+    /// Record `count` more items as done.
+    pub fn inc(&mut self, count: usize) {
+        self.done += count;
+        if self.done >= self.next_report || self.last_report.elapsed() >= REPORT_INTERVAL {
+            self.report();
+            while self.next_report <= self.done {
+                self.next_report += Self::step(self.total);
+            }
+        }
+    }
+
+    /// Number of items recorded so far.
+    pub fn done(&self) -> usize { self.done }
+
+    /// Report the final count for the operation.
+    pub fn finish(mut self) {
+        if self.reported != Some(self.done) {
+            self.report();
+        }
+    }
+
+    fn report(&mut self) {
+        self.last_report = Instant::now();
+        self.reported = Some(self.done);
+        if is_quiet() { return; }
+        info!(
+            operation = self.operation,
+            done = self.done,
+            total = self.total,
+            unit = self.unit,
+            elapsed_ms = self.started.elapsed().as_millis() as u64,
+            "{}: {}/{} {}", self.operation, self.done, self.total, self.unit);
+    }
+}
@@ -0,0 +1,88 @@
+//! # Python Bindings
+//!
+//! `python` exposes the audit side of the crate to Python: loading a voter
+//! roster, recounting the votes from a file written by `cache-transactions`,
+//! counting revealed planes and checking proofs of inclusion. Results are
+//! returned as plain dictionaries and lists.
+//!
+//! The module is built with `maturin build --release`, which enables the
+//! `python` feature (see `pyproject.toml`).
+
+use std::fs;
+use pyo3::prelude::*;
+use pyo3::exceptions::PyValueError;
+use serde::Serialize;
+use seventh_estate_core::{validate_proof, votecode_from_string, InclusionProof, PlaneTally, Recount};
+use crate::voter_roster::{VoterInfo, VoterRoster};
+use crate::votes::CachedTransaction;
+
+#[derive(Serialize)]
+struct RosterEntry<'a> {
+    position: usize,
+    #[serde(flatten)]
+    voter_info: &'a VoterInfo
+}
+
+fn value_error(err: impl ToString) -> PyErr {
+    PyValueError::new_err(err.to_string())
+}
+
+/// Convert a serializable value into Python dictionaries and lists.
+fn to_python<T: Serialize>(py: Python<'_>, value: &T) -> PyResult<PyObject> {
+    let serialized = serde_json::to_string(value).map_err(value_error)?;
+    Ok(py.import("json")?.call_method1("loads", (serialized,))?.unbind())
+}
+
+/// Records of the voter roster CSV file at `path`, in roster order.
+#[pyfunction]
+fn load_roster(py: Python<'_>, path: &str) -> PyResult<PyObject> {
+    let roster = VoterRoster::from_file(&path).map_err(value_error)?;
+    let entries: Vec<RosterEntry> = roster.records.iter()
+        .map(|record| RosterEntry { position: record.position, voter_info: &record.voter_info })
+        .collect();
+    to_python(py, &entries)
+}
+
+/// Recount the votes of the poll from a cached transaction file.
+#[pyfunction]
+fn recount(py: Python<'_>, poll_identifier: &str, transactions_path: &str) -> PyResult<PyObject> {
+    let transactions: Vec<CachedTransaction> = serde_json::from_slice(&fs::read(transactions_path)?)
+        .map_err(value_error)?;
+    to_python(py, &Recount::from_transactions(poll_identifier, &transactions))
+}
+
+/// Count the choices of the voted rows of a published plane file.
+#[pyfunction]
+fn tally_plane(py: Python<'_>, plane_path: &str) -> PyResult<PyObject> {
+    let tally = PlaneTally::from_csv(&fs::read_to_string(plane_path)?).map_err(value_error)?;
+    to_python(py, &tally)
+}
+
+/// Check a proof of inclusion file written by `gen`, in YAML or JSON.
+#[pyfunction]
+fn verify_proof_file(proof_path: &str) -> PyResult<bool> {
+    let proof: InclusionProof = serde_yaml::from_slice(&fs::read(proof_path)?).map_err(value_error)?;
+    Ok(proof.validate())
+}
+
+/// Check a proof of inclusion given as its data, lemma and path.
+#[pyfunction]
+fn verify_proof(data: &str, lemma: Vec<String>, path: Vec<usize>) -> bool {
+    validate_proof(&lemma, &path, data)
+}
+
+#[pyfunction]
+fn is_valid_votecode(votecode: &str) -> bool {
+    votecode_from_string(votecode).is_some()
+}
+
+#[pymodule]
+fn seventh_estate(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_function(wrap_pyfunction!(load_roster, m)?)?;
+    m.add_function(wrap_pyfunction!(recount, m)?)?;
+    m.add_function(wrap_pyfunction!(tally_plane, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_proof_file, m)?)?;
+    m.add_function(wrap_pyfunction!(verify_proof, m)?)?;
+    m.add_function(wrap_pyfunction!(is_valid_votecode, m)?)?;
+    Ok(())
+}
@@ -3,13 +3,28 @@
 //! `poll_master_key` provides an abstraction layer
 //! for generating, sharing, and reconstructing the
 //! Poll Master Key.
+//!
+//! The key and its shares are scrubbed from memory when they are dropped.
 
+use zeroize::Zeroize;
 use super::*;
 
 pub struct PollMasterKey(pub Vec<u8>);
 
 pub struct PollMasterKeyShare(pub Vec<u8>);
 
+impl Drop for PollMasterKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+impl Drop for PollMasterKeyShare {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
 pub type ListOfPollMasterKeyShares = Vec<PollMasterKeyShare>;
 
 impl PollMasterKey {
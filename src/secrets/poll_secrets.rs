@@ -40,22 +40,40 @@ pub struct PollSecrets {
 
 
 impl PollSecrets {
+    /// Derive the secrets of a poll from its hexadecimal generation seed,
+    /// or from the Poll Master Key for polls created without one.
+    pub fn for_poll(pmk: &PollMasterKey, generation_seed: Option<&str>) -> Result<Self> {
+        match generation_seed {
+            Some(seed) => {
+                let seed: Vec<u8> = hex::decode(seed)?;
+                if seed.len() != CSPRNGSeed::SIZE {
+                    return Err(format!("Generation seed must be {} bytes long.", CSPRNGSeed::SIZE).into());
+                }
+                Ok(PollSecrets::from_seed(CSPRNGSeed::from_vec(&seed)))
+            },
+            None => Ok(PollSecrets::derive(pmk))
+        }
+    }
+
     pub fn derive(pmk: &PollMasterKey) -> Self {
         assert!(pmk.0.len() == CSPRNGSeed::SIZE,
             "Poll Master Key not a valid seed length.");
-        let pmk_seed = CSPRNGSeed::from_vec(&pmk.0);
+        PollSecrets::from_seed(CSPRNGSeed::from_vec(&pmk.0))
+    }
 
+    /// Derive the secrets deterministically from `seed`.
+    pub fn from_seed(seed: CSPRNGSeed) -> Self {
         // Derivation Setup
         let mut secrets = PollSecrets::new();
-        let mut pmkrng = CSPRNG::from_csprng_seed(pmk_seed);
+        let mut seedrng = CSPRNG::from_csprng_seed(&seed);
         // Top-level Secrets
-        secrets.votecode_root = CSPRNGSeed::next_seed(&mut pmkrng);
-        secrets.decoy_root = CSPRNGSeed::next_seed(&mut pmkrng);
-        secrets.summands_root = CSPRNGSeed::next_seed(&mut pmkrng);
-        secrets.planes_root = CSPRNGSeed::next_seed(&mut pmkrng);
-        pmkrng.fill_bytes(&mut secrets.summands_key.0);
+        secrets.votecode_root = CSPRNGSeed::next_seed(&mut seedrng);
+        secrets.decoy_root = CSPRNGSeed::next_seed(&mut seedrng);
+        secrets.summands_root = CSPRNGSeed::next_seed(&mut seedrng);
+        secrets.planes_root = CSPRNGSeed::next_seed(&mut seedrng);
+        seedrng.fill_bytes(&mut secrets.summands_key.0);
         // Plane-level Secrets
-        let mut planesrng = CSPRNG::from_csprng_seed(secrets.planes_root);
+        let mut planesrng = CSPRNG::from_csprng_seed(&secrets.planes_root);
         secrets.plane_secrets = (0..NUMBER_OF_PLANES).into_iter()
             .map(|_| PlaneSecrets::derive(CSPRNGSeed::next_seed(&mut planesrng)))
             .collect();
@@ -79,11 +97,11 @@ impl PlaneSecrets {
         let mut secrets = PlaneSecrets::new();
         secrets.plane_root = prk;
         // Top-level Secrets
-        let mut prkrng = CSPRNG::from_csprng_seed(secrets.plane_root);
+        let mut prkrng = CSPRNG::from_csprng_seed(&secrets.plane_root);
         secrets.permutation_root = CSPRNGSeed::next_seed(&mut prkrng);
         secrets.key_root = CSPRNGSeed::next_seed(&mut prkrng);
         // Column-level Secrets
-        let mut keyrng = CSPRNG::from_csprng_seed(secrets.key_root);
+        let mut keyrng = CSPRNG::from_csprng_seed(&secrets.key_root);
         secrets.col1_key_root = CSPRNGSeed::next_seed(&mut keyrng);
         secrets.col1_nonce_root = CSPRNGSeed::next_seed(&mut keyrng);
         secrets.col3_key_root = CSPRNGSeed::next_seed(&mut keyrng);
@@ -92,11 +110,11 @@ impl PlaneSecrets {
     }
 
     pub fn resolve(self: &Self, num_rows: usize) -> DerivedPlaneSecrets {
-        let mut permutation_csprng = CSPRNG::from_csprng_seed(self.permutation_root);
-        let mut col1_key_csprng = CSPRNG::from_csprng_seed(self.col1_key_root);
-        let mut col1_nonce_csprng = CSPRNG::from_csprng_seed(self.col1_nonce_root);
-        let mut col3_key_csprng = CSPRNG::from_csprng_seed(self.col3_key_root);
-        let mut col3_nonce_csprng = CSPRNG::from_csprng_seed(self.col3_nonce_root);
+        let mut permutation_csprng = CSPRNG::from_csprng_seed(&self.permutation_root);
+        let mut col1_key_csprng = CSPRNG::from_csprng_seed(&self.col1_key_root);
+        let mut col1_nonce_csprng = CSPRNG::from_csprng_seed(&self.col1_nonce_root);
+        let mut col3_key_csprng = CSPRNG::from_csprng_seed(&self.col3_key_root);
+        let mut col3_nonce_csprng = CSPRNG::from_csprng_seed(&self.col3_nonce_root);
 
         let mut permutation: Vec<usize> = (0..num_rows).collect();
         permutation.shuffle(&mut permutation_csprng);
@@ -14,28 +14,36 @@
 //! and the key-derivation parameters. It provides no protection against
 //! denial-of-service.
 
+use std::collections::HashMap;
 use std::str;
+use std::sync::RwLock;
+use lazy_static::lazy_static;
+use zeroize::Zeroizing;
 use super::*;
 
+lazy_static! {
+    static ref TRUSTEE_PASSWORDS: RwLock<Option<HashMap<String, String>>> = RwLock::new(None);
+}
+
 
 /// Trustees Sharing implementation.
 #[derive(Debug)]
 pub struct TrusteeShare {
     pub identifier: String,
-    pub value: Vec<u8>
+    pub value: Zeroizing<Vec<u8>>
 }
 
 impl TrusteeShare {
     pub fn new(identifier: String, value: Vec<u8>) -> Self {
         TrusteeShare {
             identifier: identifier,
-            value: value
+            value: Zeroizing::new(value)
         }
     }
 
     pub fn secure(self: &Self) -> SecureTrusteeShare {
         let password = read_trustee_password(&self.identifier);
-        let secure_value = encrypt_trustee_share(&password, &self.identifier, self.value.clone()).unwrap();
+        let secure_value = encrypt_trustee_share(&password, &self.identifier, self.value.to_vec()).unwrap();
         SecureTrusteeShare {
             identifier: self.identifier.clone(),
             secure_value: secure_value
@@ -96,9 +104,17 @@ fn read_trustee_password_terminal(trustee: &str) -> String {
     password
 }
 
+/// Use the passwords of `passwords`, by trustee identifier, instead of
+/// prompting for them, for the whole process. Used by simulations.
+pub fn set_trustee_passwords(passwords: HashMap<String, String>) {
+    *TRUSTEE_PASSWORDS.write().unwrap() = Some(passwords);
+}
+
 /// Function to abstract the functionality of reading a trustee's password.
 pub fn read_trustee_password(trustee: &str) -> String {
-    read_trustee_password_terminal(trustee)
+    let password = TRUSTEE_PASSWORDS.read().unwrap().as_ref()
+        .and_then(|passwords| passwords.get(trustee).cloned());
+    password.unwrap_or_else(|| read_trustee_password_terminal(trustee))
 }
 
 
@@ -113,8 +129,8 @@ pub fn read_trustee_password(trustee: &str) -> String {
 /// let share: Vec<u8> = vec![1, 2, 3, 4];
 /// let encrypted_share: AEADString = encrypt_trustee_share("password", "trustee", share).unwrap();
 /// 
-/// let decrypted_share: Vec<u8> = decrypt_trustee_share("password", "trustee", encrypted_share).unwrap();
-/// assert_eq!(vec![1, 2, 3, 4], decrypted_share);
+/// let decrypted_share = decrypt_trustee_share("password", "trustee", encrypted_share).unwrap();
+/// assert_eq!(vec![1, 2, 3, 4], *decrypted_share);
 /// ```
 pub fn encrypt_trustee_share(password: &str, identity: &str, share: Vec<u8>) -> Result<AEADString> {
     let (key, params) = kdf(password)?;
@@ -126,7 +142,7 @@ pub fn encrypt_trustee_share(password: &str, identity: &str, share: Vec<u8>) ->
                      share)?))
 }
 
-pub fn decrypt_trustee_share(password: &str, identity: &str, encrypted_share: AEADString) -> Result<Vec<u8>> {
+pub fn decrypt_trustee_share(password: &str, identity: &str, encrypted_share: AEADString) -> Result<Zeroizing<Vec<u8>>> {
     let values: AEADValues = encrypted_share.values()?;
     let aad_values: Vec<&str> = str::from_utf8(&values.aad)?.split("-").collect();
     let salt64 = aad_values[1];
@@ -141,7 +157,7 @@ pub fn decrypt_trustee_share(password: &str, identity: &str, encrypted_share: AE
 /// Maybe decrypt trustee share data protected via password.
 ///
 /// Adapter function to yield an Option instead of a Result.
-pub fn maybe_decrypt_trustee_share(password: &str, identity: &str, encrypted_share: AEADString) -> Option<Vec<u8>> {
+pub fn maybe_decrypt_trustee_share(password: &str, identity: &str, encrypted_share: AEADString) -> Option<Zeroizing<Vec<u8>>> {
     match decrypt_trustee_share(password, identity, encrypted_share) {
         Ok(v) => {
             Some(v)
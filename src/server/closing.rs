@@ -0,0 +1,212 @@
+//! # Voting Deadline
+//!
+//! Given a `VotingDeadline`, the relay server closes the voting window of
+//! the poll itself, so that the results do not wait for an operator to run
+//! the right steps on time. The deadline passes at a closing time, or once
+//! a block after the closing block of the poll (its end block and the late
+//! blocks allowed by its tally rules) is mined. From then on:
+//!
+//! * votes are no longer relayed;
+//! * the votes submitted on the blockchain are fetched a final time and
+//!   recorded, as step 6 does with `--chain`, and observers receive a
+//!   `voting_closed` event;
+//! * given the audited columns seed, the audited columns are drawn and the
+//!   tally is counted, as steps 7 and 8 do. The tally report is published
+//!   to the observers as a `tally_updated` event.
+//!
+//! The `voting_closed` and `tally_counted` poll events are also sent to the
+//! event sinks of the closing, such as webhooks. Steps already run by an
+//! operator are not run again.
+
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use tracing::error;
+use crate::blockchain::latest_block;
+use crate::monitor::{EventSink, PollEvent};
+use crate::output;
+use crate::subcommands::{record_votes, generate_tally_audit, generate_poll_revelations};
+use crate::votes::{ChainVotes, VoteSource};
+use super::*;
+
+/// Time between checks of the voting deadline.
+pub const DEADLINE_CHECK_INTERVAL: Duration = Duration::from_secs(15);
+
+/// When the server closes the voting window.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct VotingDeadline {
+    pub close_at: Option<DateTime<Utc>>,
+    /// Last block whose votes are counted.
+    pub closing_block: Option<u64>
+}
+
+impl VotingDeadline {
+    /// Whether the deadline passed at `now`, with `latest_block` the latest
+    /// block mined if it could be read.
+    pub fn passed(&self, now: DateTime<Utc>, latest_block: Option<u64>) -> bool {
+        self.close_at.is_some_and(|close_at| close_at <= now)
+            || matches!((self.closing_block, latest_block), (Some(closing_block), Some(latest_block)) if closing_block < latest_block)
+    }
+}
+
+impl fmt::Display for VotingDeadline {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (self.close_at, self.closing_block) {
+            (Some(close_at), Some(closing_block)) => write!(f, "{} or block {}", close_at.to_rfc3339(), closing_block),
+            (Some(close_at), None) => write!(f, "{}", close_at.to_rfc3339()),
+            (None, Some(closing_block)) => write!(f, "block {}", closing_block),
+            (None, None) => write!(f, "none")
+        }
+    }
+}
+
+/// Steps of the server once the voting deadline passed.
+pub struct PollClosing {
+    pub deadline: VotingDeadline,
+    /// Hex encoded audited columns seed. Without it, the tally waits for
+    /// step 7 to be run.
+    pub audited_columns_seed: Option<String>,
+    /// Receive the `voting_closed` and `tally_counted` poll events.
+    pub sinks: Vec<Box<dyn EventSink + Send>>
+}
+
+impl PollClosing {
+    pub fn new(deadline: VotingDeadline) -> Self {
+        PollClosing { deadline, audited_columns_seed: None, sinks: Vec::new() }
+    }
+
+    // Send `event` to the sinks from a blocking thread, as webhooks are
+    // delivered synchronously.
+    async fn notify(&mut self, poll_identifier: &str, event: PollEvent) {
+        let mut sinks = std::mem::take(&mut self.sinks);
+        let poll_identifier = poll_identifier.to_owned();
+        let notified = tokio::task::spawn_blocking(move || {
+            sinks.iter_mut().for_each(|sink| sink.notify(&poll_identifier, &event));
+            sinks
+        }).await;
+        match notified {
+            Ok(sinks) => self.sinks = sinks,
+            Err(err) => warn!(error = %err, "Poll events are no longer sent")
+        }
+    }
+}
+
+/// Outcome of the closing of the voting window.
+#[derive(Debug, Clone, Serialize)]
+pub struct ClosingReport {
+    pub poll_identifier: String,
+    pub deadline: String,
+    pub closed_at: DateTime<Utc>,
+    pub votes_recorded: usize,
+    /// Tally counted, `None` without the audited columns seed.
+    pub tally: Option<PollTally>
+}
+
+impl fmt::Display for ClosingReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Poll:           {}", self.poll_identifier)?;
+        writeln!(f, "Deadline:       {}", self.deadline)?;
+        writeln!(f, "Closed at:      {}", self.closed_at.to_rfc3339())?;
+        writeln!(f, "Votes recorded: {}", self.votes_recorded)?;
+        match &self.tally {
+            Some(tally) => write!(f, "{}", tally),
+            None => write!(f, "Tally:          waiting for step 7")
+        }
+    }
+}
+
+impl RelayServer {
+    /// Fetch the votes of the blockchain a final time and record them,
+    /// unless they already were, returning the number of votes recorded.
+    pub fn record_final_votes(&self) -> crate::Result<usize> {
+        if !self.status(false)?.poll_state.votes_committed {
+            let sources: Vec<Box<dyn VoteSource>> = vec![Box::new(ChainVotes(self.network.clone()))];
+            record_votes(&self.network, &self.pollconf_filename, &sources, &VoteCodeSet::new(), false, false)?;
+        }
+        Ok(self.status(false)?.votes_recorded)
+    }
+
+    /// Draw the audited columns from `seed` and count the tally, unless the
+    /// tally report was already written.
+    pub fn count_tally(&self, seed: &str) -> crate::Result<PollTally> {
+        if read_poll_tally(&self.poll_identifier)?.is_none() {
+            generate_tally_audit(&self.pollconf_filename, seed)?;
+            generate_poll_revelations(&self.network, &self.pollconf_filename, false, false)?;
+        }
+        read_poll_tally(&self.poll_identifier)?
+            .ok_or_else(|| "Tally report was not written.".into())
+    }
+}
+
+// Run `step` on a blocking thread, reporting its panics as errors: the
+// steps assert the state of the poll.
+async fn closing_step<T: Send + 'static>(step: impl FnOnce() -> crate::Result<T> + Send + 'static) -> crate::Result<T> {
+    match tokio::task::spawn_blocking(move || step().map_err(|err| err.to_string())).await {
+        Ok(result) => result.map_err(|err| err.into()),
+        Err(err) => Err(err.to_string().into())
+    }
+}
+
+/// Close the voting window of `server` once the deadline of `closing`
+/// passed, checking every `interval`, then record the votes and count the
+/// tally.
+pub async fn watch_voting_deadline(server: Arc<RelayServer>, mut closing: PollClosing, interval: Duration) {
+    info!(deadline = %closing.deadline, "Voting window closes at the deadline");
+    loop {
+        let latest_block = match closing.deadline.closing_block {
+            Some(_) => match latest_block(server.network()).await {
+                Ok(latest_block) => Some(latest_block),
+                Err(err) => {
+                    warn!(error = %err, "Latest block could not be read");
+                    None
+                }
+            },
+            None => None
+        };
+        if closing.deadline.passed(Utc::now(), latest_block) {
+            break;
+        }
+        futures_timer::Delay::new(interval).await;
+    }
+    server.close_voting();
+    let closed_at = Utc::now();
+    info!(deadline = %closing.deadline, "Voting window closed");
+
+    let recording_server = server.clone();
+    let votes_recorded = match closing_step(move || recording_server.record_final_votes()).await {
+        Ok(votes_recorded) => votes_recorded,
+        Err(err) => {
+            error!(error = %err, "Votes could not be recorded at the deadline, run step 6");
+            return;
+        }
+    };
+    let deadline = closing.deadline.to_string();
+    server.observers().publish(AuditEvent::VotingClosed { deadline: deadline.clone(), votes_recorded });
+    let poll_identifier = server.poll_identifier().to_owned();
+    closing.notify(&poll_identifier, PollEvent::VotingClosed { votes_counted: votes_recorded }).await;
+
+    let tally = match closing.audited_columns_seed.clone() {
+        Some(seed) => {
+            let counting_server = server.clone();
+            match closing_step(move || counting_server.count_tally(&seed)).await {
+                Ok(tally) => Some(tally),
+                Err(err) => {
+                    error!(error = %err, "Tally could not be counted at the deadline, run steps 7 and 8");
+                    None
+                }
+            }
+        },
+        None => {
+            info!("Votes recorded, the tally waits for step 7");
+            None
+        }
+    };
+    if let Some(tally) = &tally {
+        closing.notify(&poll_identifier, PollEvent::TallyCounted { tally: tally.tally.clone(), consistent: tally.consistent }).await;
+    }
+
+    let report = ClosingReport { poll_identifier, deadline, closed_at, votes_recorded, tally };
+    if let Err(err) = output::emit(&report) {
+        warn!(error = %err, "Closing report could not be written");
+    }
+}
@@ -0,0 +1,295 @@
+//! # Observer Events
+//!
+//! Accredited observers follow the audit of a poll live with `GET /events`,
+//! a stream of server-sent events. `AuditEventWatcher` checks the data
+//! directory of the poll for the artifacts written by each step of the
+//! audit, and the `ObserverHub` of the server numbers the events and sends
+//! them to every open stream. The number of an event is its `id`, so that
+//! observers reconnecting with `Last-Event-ID` receive the events they
+//! missed; new observers receive every event since the server started.
+//!
+//! * `commitment_confirmed`: the commit receipt was written.
+//! * `voting_closed`: the server closed the voting window at its deadline
+//!   and recorded the votes.
+//! * `challenge_drawn`: the drawn summands or the audited columns were written.
+//! * `opening_verified`: a revealed plane was checked against its committed plane.
+//! * `tally_updated`: the tally report was written or changed.
+//! * `tally_finalized`: the finalization receipt was written.
+
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+use chrono::{DateTime, Utc};
+use tokio::sync::broadcast;
+use crate::blockchain::{CommitReceipt, FinalizationReceipt};
+use crate::blockchain::merkle::{CryptoSHA3256Hash, MappedTree};
+use crate::bulletin_board::{check_plane_openings, merkle_leaves, revealed_plane_files, sha256_hex};
+use crate::planes::PlaneTally;
+use super::*;
+
+/// Time between checks of the data directory.
+pub const AUDIT_EVENT_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Events kept for an observer that falls behind before it is disconnected.
+const OBSERVER_BACKLOG: usize = 256;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum AuditEvent {
+    CommitmentConfirmed { root: String, transaction_hash: String, block_number: u64 },
+    VotingClosed { deadline: String, votes_recorded: usize },
+    ChallengeDrawn { artifact: String, sha256: String },
+    OpeningVerified { plane: String, opened: usize, verified: usize },
+    TallyUpdated { tally: PlaneTally, planes_counted: usize, consistent: bool },
+    TallyFinalized { tally_hash: String, transaction_hash: String, block_number: u64 }
+}
+
+pub const AUDIT_EVENTS: [&str; 6] = ["commitment_confirmed", "challenge_drawn", "opening_verified", "tally_updated", "tally_finalized", "voting_closed"];
+
+impl AuditEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::CommitmentConfirmed { .. } => AUDIT_EVENTS[0],
+            Self::ChallengeDrawn { .. } => AUDIT_EVENTS[1],
+            Self::OpeningVerified { .. } => AUDIT_EVENTS[2],
+            Self::TallyUpdated { .. } => AUDIT_EVENTS[3],
+            Self::TallyFinalized { .. } => AUDIT_EVENTS[4],
+            Self::VotingClosed { .. } => AUDIT_EVENTS[5]
+        }
+    }
+}
+
+impl fmt::Display for AuditEvent {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::CommitmentConfirmed { root, block_number, .. } =>
+                write!(f, "Commitment {} confirmed in block {}", root, block_number),
+            Self::VotingClosed { deadline, votes_recorded } =>
+                write!(f, "Voting closed at {} with {} votes recorded", deadline, votes_recorded),
+            Self::ChallengeDrawn { artifact, sha256 } =>
+                write!(f, "Challenge {} drawn ({})", artifact, sha256),
+            Self::OpeningVerified { plane, opened, verified } =>
+                write!(f, "{}: {} of {} opened cells verified", plane, verified, opened),
+            Self::TallyUpdated { tally, planes_counted, consistent } =>
+                write!(f, "Tally {} ({} planes counted{})", tally, planes_counted, if *consistent { "" } else { ", INCONSISTENT" }),
+            Self::TallyFinalized { tally_hash, block_number, .. } =>
+                write!(f, "Tally {} finalized in block {}", tally_hash, block_number)
+        }
+    }
+}
+
+/// Event sent to the observers.
+#[derive(Debug, Clone, Serialize)]
+pub struct ObserverEvent {
+    pub id: u64,
+    pub poll_identifier: String,
+    pub occurred_at: DateTime<Utc>,
+    #[serde(flatten)]
+    pub event: AuditEvent
+}
+
+impl ObserverEvent {
+    /// The event in the server-sent events format.
+    pub fn to_sse(&self) -> String {
+        format!("id: {}\nevent: {}\ndata: {}\n\n", self.id, self.event.name(), serde_json::to_string(self).unwrap())
+    }
+}
+
+/// Events published to the observers of a poll.
+pub struct ObserverHub {
+    poll_identifier: String,
+    history: Mutex<Vec<ObserverEvent>>,
+    sender: broadcast::Sender<ObserverEvent>
+}
+
+impl ObserverHub {
+    pub fn new(poll_identifier: &str) -> Self {
+        let (sender, _) = broadcast::channel(OBSERVER_BACKLOG);
+        ObserverHub {
+            poll_identifier: poll_identifier.to_owned(),
+            history: Mutex::new(Vec::new()),
+            sender
+        }
+    }
+
+    pub fn publish(&self, event: AuditEvent) -> ObserverEvent {
+        let mut history = self.history.lock().unwrap();
+        let event = ObserverEvent {
+            id: history.len() as u64 + 1,
+            poll_identifier: self.poll_identifier.clone(),
+            occurred_at: Utc::now(),
+            event
+        };
+        history.push(event.clone());
+        // Sending fails when no observer is connected.
+        let _ = self.sender.send(event.clone());
+        event
+    }
+
+    /// Subscribe to the events published after `last_event_id`, returning
+    /// those already published and a receiver for the next ones.
+    pub fn subscribe(&self, last_event_id: u64) -> (Vec<ObserverEvent>, broadcast::Receiver<ObserverEvent>) {
+        let history = self.history.lock().unwrap();
+        let receiver = self.sender.subscribe();
+        let missed = history.iter()
+            .filter(|event| last_event_id < event.id)
+            .cloned()
+            .collect();
+        (missed, receiver)
+    }
+}
+
+/// Size and modification time of a file, to notice when it is written.
+type FileStamp = (u64, SystemTime);
+
+/// Finds the audit events of a data directory by the artifacts written or
+/// changed since the previous check.
+pub struct AuditEventWatcher {
+    datadir: PathBuf,
+    merkle_tree: PathBuf,
+    leaves: Option<HashSet<CryptoSHA3256Hash>>,
+    seen: HashMap<String, FileStamp>
+}
+
+impl AuditEventWatcher {
+    pub fn new(datadir: &Path, merkle_tree: &Path) -> Self {
+        AuditEventWatcher {
+            datadir: datadir.to_path_buf(),
+            merkle_tree: merkle_tree.to_path_buf(),
+            leaves: None,
+            seen: HashMap::new()
+        }
+    }
+
+    // Stamps of the files `names` if they all exist and one was written
+    // since it was last seen.
+    fn written(&self, names: &[&str]) -> Option<Vec<(String, FileStamp)>> {
+        let stamps = names.iter()
+            .map(|name| {
+                let metadata = std::fs::metadata(self.datadir.join(name)).ok()?;
+                Some((name.to_string(), (metadata.len(), metadata.modified().ok()?)))
+            })
+            .collect::<Option<Vec<(String, FileStamp)>>>()?;
+        match stamps.iter().any(|(name, stamp)| self.seen.get(name) != Some(stamp)) {
+            true => Some(stamps),
+            false => None
+        }
+    }
+
+    fn read<T: serde::de::DeserializeOwned>(&self, name: &str) -> Option<T> {
+        let data = std::fs::read(self.datadir.join(name)).ok()?;
+        match serde_yaml::from_slice(&data) {
+            Ok(value) => Some(value),
+            Err(err) => {
+                warn!(artifact = name, error = %err, "Audit artifact could not be read");
+                None
+            }
+        }
+    }
+
+    // Leaves of the committed Merkle tree, once it is stored.
+    fn leaves(&mut self) -> Option<&HashSet<CryptoSHA3256Hash>> {
+        if self.leaves.is_none() {
+            let tree = MappedTree::open(&self.merkle_tree.to_string_lossy()).ok()?;
+            self.leaves = Some(merkle_leaves(&tree));
+        }
+        self.leaves.as_ref()
+    }
+
+    /// Events of the artifacts written since the previous check.
+    pub fn check(&mut self) -> Vec<AuditEvent> {
+        let mut events: Vec<AuditEvent> = Vec::new();
+
+        if let Some(stamps) = self.written(&["commit_receipt.yaml"]) {
+            self.seen.extend(stamps);
+            if let Some(receipt) = self.read::<CommitReceipt>("commit_receipt.yaml") {
+                events.push(AuditEvent::CommitmentConfirmed {
+                    root: receipt.root,
+                    transaction_hash: receipt.transaction_hash,
+                    block_number: receipt.submitted_at_block
+                });
+            }
+        }
+
+        for artifact in ["drawn_summands.yaml", "audited_columns.yaml"].iter() {
+            if let Some(stamps) = self.written(&[artifact]) {
+                self.seen.extend(stamps);
+                if let Ok(data) = std::fs::read(self.datadir.join(artifact)) {
+                    events.push(AuditEvent::ChallengeDrawn { artifact: artifact.to_string(), sha256: sha256_hex(&data) });
+                }
+            }
+        }
+
+        for (plane, keys) in revealed_plane_files() {
+            let stamps = match self.written(&[&plane, &keys]) {
+                Some(stamps) => stamps,
+                None => continue
+            };
+            // Openings are checked once the Merkle tree is stored.
+            let datadir = self.datadir.clone();
+            let leaves = match self.leaves() {
+                Some(leaves) => leaves,
+                None => break
+            };
+            match check_plane_openings(&datadir, &plane, &keys, leaves) {
+                Ok((openings, _)) => events.push(AuditEvent::OpeningVerified {
+                    plane: openings.plane,
+                    opened: openings.opened,
+                    verified: openings.verified
+                }),
+                Err(err) => warn!(plane = %plane, error = %err, "Revealed plane could not be checked")
+            }
+            self.seen.extend(stamps);
+        }
+
+        if let Some(stamps) = self.written(&["tally.yaml"]) {
+            self.seen.extend(stamps);
+            if let Some(tally) = self.read::<PollTally>("tally.yaml") {
+                events.push(AuditEvent::TallyUpdated {
+                    tally: tally.tally,
+                    planes_counted: tally.planes_counted,
+                    consistent: tally.consistent
+                });
+            }
+        }
+
+        if let Some(stamps) = self.written(&["finalization_receipt.yaml"]) {
+            self.seen.extend(stamps);
+            if let Some(receipt) = self.read::<FinalizationReceipt>("finalization_receipt.yaml") {
+                events.push(AuditEvent::TallyFinalized {
+                    tally_hash: receipt.tally_hash,
+                    transaction_hash: receipt.transaction_hash,
+                    block_number: receipt.submitted_at_block
+                });
+            }
+        }
+        events
+    }
+}
+
+/// Publish the audit events of the data directory of `server` to its
+/// observers, checking every `interval`.
+pub async fn watch_audit_events(server: Arc<RelayServer>, interval: Duration) {
+    let mut watcher = AuditEventWatcher::new(Path::new(&server.poll_identifier), Path::new(&server.merkle_tree));
+    loop {
+        let checked = tokio::task::spawn_blocking(move || {
+            let events = watcher.check();
+            (watcher, events)
+        }).await;
+        let events = match checked {
+            Ok((checked_watcher, events)) => {
+                watcher = checked_watcher;
+                events
+            },
+            Err(err) => {
+                warn!(error = %err, "Audit events are no longer watched");
+                return;
+            }
+        };
+        for event in events {
+            info!(event = %event, "Audit event");
+            server.observers().publish(event);
+        }
+        futures_timer::Delay::new(interval).await;
+    }
+}
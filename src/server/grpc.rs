@@ -0,0 +1,184 @@
+//! # gRPC Service
+//!
+//! `Registrar` service of `proto/registrar.proto`, for registrar
+//! back-office systems: roster import, poll status, proofs of inclusion
+//! and the tally. It is served next to the HTTP endpoints and shares the
+//! same `RelayServer`.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tonic::{Code, Request, Response, Status};
+use tracing::info;
+use crate::voter_roster::{VoterInfo, VoterRosterRecord};
+use super::*;
+
+pub mod proto {
+    tonic::include_proto!("seventh_estate.registrar");
+}
+
+use proto::registrar_server::{Registrar, RegistrarServer};
+use proto::import_roster_request::Roster;
+
+impl RelayError {
+    pub fn grpc_code(&self) -> Code {
+        match self {
+            RelayError::InvalidPayload(_) => Code::InvalidArgument,
+            RelayError::WrongPoll(_) => Code::InvalidArgument,
+            RelayError::UnknownVoteCode(_) => Code::NotFound,
+            RelayError::AlreadySubmitted(_) => Code::AlreadyExists,
+            RelayError::VotingClosed => Code::FailedPrecondition,
+            RelayError::InvalidRoster(_) => Code::FailedPrecondition,
+            RelayError::NotFound(_) => Code::NotFound,
+            RelayError::Unauthorized => Code::Unauthenticated,
+            RelayError::RateLimited => Code::ResourceExhausted,
+            RelayError::Backend(_) => Code::Unavailable
+        }
+    }
+}
+
+impl From<RelayError> for Status {
+    fn from(err: RelayError) -> Self {
+        Status::new(err.grpc_code(), err.to_string())
+    }
+}
+
+impl From<proto::Voter> for VoterInfo {
+    fn from(voter: proto::Voter) -> Self {
+        VoterInfo {
+            last_name: voter.last_name,
+            first_name: voter.first_name,
+            street_address: voter.street_address,
+            city: voter.city,
+            state: voter.state,
+            zip_code: voter.zip_code,
+            extra_fields: voter.extra_fields.into_iter().collect()
+        }
+    }
+}
+
+impl From<PollStatus> for proto::PollStatusResponse {
+    fn from(status: PollStatus) -> Self {
+        proto::PollStatusResponse {
+            poll_identifier: status.poll_identifier,
+            roster_committed: status.poll_state.roster_committed,
+            columns_committed: status.poll_state.columns_committed,
+            summands_drawn: status.poll_state.summands_drawn,
+            ceremony_conducted: status.poll_state.ceremony_conducted,
+            votes_committed: status.poll_state.votes_committed,
+            columns_revealed: status.poll_state.columns_revealed,
+            num_ballots: status.num_ballots as u64,
+            voter_roster_size: status.voter_roster_size as u64,
+            audited_ballots: status.audited_ballots as u64,
+            votes_recorded: status.votes_recorded as u64,
+            voting_open: status.voting_open,
+            commit_receipt: status.commit_receipt.map(|receipt| proto::CommitReceipt {
+                root: receipt.root,
+                leaves: receipt.leaves as u64,
+                transaction_hash: receipt.transaction_hash,
+                submitted_at_block: receipt.submitted_at_block,
+                posted_at: receipt.posted_at.to_rfc3339()
+            }),
+            commit_transaction: status.commit_transaction.map(|transaction| proto::CommitTransaction {
+                block_number: transaction.block_number.unwrap_or(0),
+                confirmations: transaction.confirmations
+            })
+        }
+    }
+}
+
+impl From<PollTally> for proto::TallyResponse {
+    fn from(tally: PollTally) -> Self {
+        proto::TallyResponse {
+            for_votes: tally.tally.for_votes as u64,
+            against_votes: tally.tally.against_votes as u64,
+            decoy_votes: tally.tally.decoy_votes as u64,
+            planes_counted: tally.planes_counted as u64,
+            consistent: tally.consistent,
+            vote_sources: tally.vote_sources.iter()
+                .map(|count| proto::VoteSourceCount {
+                    source: count.source.name().to_owned(),
+                    votes: count.votes as u64,
+                    duplicates: count.duplicates as u64
+                })
+                .collect()
+        }
+    }
+}
+
+pub struct RegistrarService {
+    server: Arc<RelayServer>
+}
+
+impl RegistrarService {
+    pub fn new(server: Arc<RelayServer>) -> Self {
+        RegistrarService { server }
+    }
+
+    /// Run a blocking call on the server outside of the runtime threads.
+    async fn call<T, F>(&self, call: F) -> Result<T, Status>
+    where T: Send + 'static, F: FnOnce(&RelayServer) -> Result<T, RelayError> + Send + 'static
+    {
+        let server = self.server.clone();
+        tokio::task::spawn_blocking(move || call(&server)).await
+            .map_err(|err| Status::internal(err.to_string()))?
+            .map_err(Status::from)
+    }
+}
+
+#[tonic::async_trait]
+impl Registrar for RegistrarService {
+    async fn import_roster(&self, request: Request<proto::ImportRosterRequest>) -> Result<Response<proto::ImportRosterResponse>, Status> {
+        let request = request.into_inner();
+        let (disable_privacy, force) = (request.disable_privacy, request.force);
+        let roster = match request.roster {
+            Some(Roster::Voters(list)) => VoterRoster {
+                records: list.voters.into_iter().enumerate()
+                    .map(|(position, voter)| VoterRosterRecord { position, voter_info: VoterInfo::from(voter) })
+                    .collect()
+            },
+            Some(Roster::Csv(csv)) => {
+                let schema = self.call(|server| server.roster_schema()).await?;
+                match schema {
+                    Some(schema) => schema.read_roster(csv.as_slice()),
+                    None => VoterRoster::from_reader(csv.as_slice())
+                }.map_err(|err| Status::invalid_argument(err.to_string()))?
+            },
+            None => return Err(Status::invalid_argument("No voter roster given."))
+        };
+        let voter_roster_size = self.call(move |server| {
+            server.import_roster(&roster, disable_privacy, force)
+        }).await?;
+        Ok(Response::new(proto::ImportRosterResponse { voter_roster_size: voter_roster_size as u64 }))
+    }
+
+    async fn get_poll_status(&self, request: Request<proto::PollStatusRequest>) -> Result<Response<proto::PollStatusResponse>, Status> {
+        let check_chain = request.into_inner().check_chain;
+        let status = self.call(move |server| server.status(check_chain)).await?;
+        Ok(Response::new(status.into()))
+    }
+
+    async fn get_proof(&self, request: Request<proto::ProofRequest>) -> Result<Response<proto::ProofResponse>, Status> {
+        let data = request.into_inner().data;
+        let proof = self.call(move |server| server.proof(&data)).await?;
+        Ok(Response::new(proto::ProofResponse {
+            data: proof.data,
+            lemma: proof.lemma,
+            path: proof.path.into_iter().map(|side| side as u64).collect()
+        }))
+    }
+
+    async fn get_tally(&self, _request: Request<proto::TallyRequest>) -> Result<Response<proto::TallyResponse>, Status> {
+        let tally = self.call(|server| server.tally()).await?;
+        Ok(Response::new(tally.into()))
+    }
+}
+
+/// Serve the gRPC service on `address` until the process is stopped.
+pub async fn serve_grpc(server: Arc<RelayServer>, address: SocketAddr) -> crate::Result<()> {
+    info!(address = %address, "gRPC listening");
+    tonic::transport::Server::builder()
+        .add_service(RegistrarServer::new(RegistrarService::new(server)))
+        .serve(address)
+        .await?;
+    Ok(())
+}
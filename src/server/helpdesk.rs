@@ -0,0 +1,74 @@
+//! # Help Desk Lookup
+//!
+//! Help desk staff can look up the status of a ballot by its serial, to
+//! answer voters, without the trustee passwords: the lookup is served by
+//! the relay server, which holds the Poll Master Key, to the holders of a
+//! separate help desk token. Only the status of the ballot is returned,
+//! never its vote codes or choices.
+
+use std::fmt;
+use crypto::util::fixed_time_eq;
+use secrecy::{ExposeSecret, SecretString};
+use serde::Serialize;
+use crate::ballots::BallotSerial;
+
+/// Read-only credential of the help desk, sent as `Authorization: Bearer
+/// <token>`.
+pub struct HelpdeskCredential {
+    token: SecretString
+}
+
+impl HelpdeskCredential {
+    pub fn new(token: SecretString) -> crate::Result<Self> {
+        if token.expose_secret().trim().is_empty() {
+            return Err("The help desk token is empty.".into());
+        }
+        Ok(HelpdeskCredential { token: SecretString::new(token.expose_secret().trim().to_owned()) })
+    }
+
+    /// Whether the value of an `Authorization` header carries the token.
+    pub fn authorizes(&self, authorization: Option<&str>) -> bool {
+        match authorization.and_then(|value| value.strip_prefix("Bearer ")).map(str::trim) {
+            Some(token) if !token.is_empty() => fixed_time_eq(token.as_bytes(), self.token.expose_secret().as_bytes()),
+            _ => false
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BallotStatus {
+    Issued,
+    /// Spoiled by the audit: votes for its vote codes are not counted.
+    Audited,
+    /// Spoiled after it was issued, and replaced: votes for its vote codes
+    /// are not counted.
+    Spoiled,
+    /// A vote code was relayed by the server or recorded by step 6.
+    Voted
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BallotLookup {
+    pub serial: BallotSerial,
+    pub status: BallotStatus,
+    /// Ballot issued instead of a spoiled ballot.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub replacement: Option<BallotSerial>
+}
+
+impl fmt::Display for BallotLookup {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let status = match self.status {
+            BallotStatus::Issued => "issued",
+            BallotStatus::Audited => "audited (spoiled)",
+            BallotStatus::Spoiled => "spoiled",
+            BallotStatus::Voted => "voted"
+        };
+        write!(f, "Ballot {}: {}", self.serial, status)?;
+        if let Some(replacement) = self.replacement {
+            write!(f, ", replaced by ballot {}", replacement)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,291 @@
+//! # HTTP Endpoints
+//!
+//! * `POST /votes`: relay the vote code or vote payload in the request body.
+//! * `POST /votecodes/check`: check the form of the vote code in the request
+//!   body, without looking it up in the ballots.
+//! * `GET /proof?data=<leaf>`: proof of inclusion of a leaf of the Merkle tree.
+//! * `GET /status[?chain=true]`: status of the poll.
+//! * `GET /ballots?serial=<serial>`: status of a ballot, for the help desk,
+//!   with `Authorization: Bearer <token>`.
+//! * `GET /metrics`: metrics in the Prometheus text format.
+//! * `GET /events`: audit events of the poll, as server-sent events,
+//!   resumed after the `Last-Event-ID` header if given.
+//!
+//! Responses are JSON documents. Errors are returned as `{"error": "..."}`
+//! with a matching status code.
+//!
+//! `serve_proofs` serves only `GET /proof` of a `ProofServer`, for the
+//! public. Both servers refuse requests over the rate limit of the client
+//! address, if one is set, and requests over the size limits.
+
+use std::net::{IpAddr, SocketAddr};
+use std::sync::Arc;
+use hyper::{Body, Method, Request, Response, Server, StatusCode};
+use hyper::header::{HeaderValue, ACCESS_CONTROL_ALLOW_HEADERS, ACCESS_CONTROL_ALLOW_METHODS, ACCESS_CONTROL_ALLOW_ORIGIN, AUTHORIZATION, CACHE_CONTROL, CONTENT_LENGTH, CONTENT_TYPE, RETRY_AFTER};
+use hyper::body::HttpBody;
+use hyper::server::conn::AddrStream;
+use hyper::service::{make_service_fn, service_fn};
+use serde::Serialize;
+use tracing::{info, debug, warn};
+use super::*;
+
+/// Largest request body accepted, in bytes.
+const MAX_BODY_SIZE: usize = 4096;
+
+/// Longest request URI accepted, in bytes.
+const MAX_URI_SIZE: usize = 2048;
+
+impl RelayError {
+    pub fn status_code(&self) -> StatusCode {
+        match self {
+            RelayError::InvalidPayload(_) => StatusCode::BAD_REQUEST,
+            RelayError::WrongPoll(_) => StatusCode::BAD_REQUEST,
+            RelayError::UnknownVoteCode(_) => StatusCode::NOT_FOUND,
+            RelayError::AlreadySubmitted(_) => StatusCode::CONFLICT,
+            RelayError::VotingClosed => StatusCode::FORBIDDEN,
+            RelayError::InvalidRoster(_) => StatusCode::CONFLICT,
+            RelayError::NotFound(_) => StatusCode::NOT_FOUND,
+            RelayError::Unauthorized => StatusCode::UNAUTHORIZED,
+            RelayError::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            RelayError::Backend(_) => StatusCode::BAD_GATEWAY
+        }
+    }
+}
+
+/// Serve the endpoints on `address` until the process is stopped.
+pub async fn serve(server: Arc<RelayServer>, address: SocketAddr) -> crate::Result<()> {
+    let make_service = make_service_fn(move |connection: &AddrStream| {
+        let server = server.clone();
+        let remote = connection.remote_addr().ip();
+        async move {
+            Ok::<_, hyper::Error>(service_fn(move |request| {
+                handle(server.clone(), remote, request)
+            }))
+        }
+    });
+    let server = Server::try_bind(&address)?.serve(make_service);
+    info!(address = %address, "Listening");
+    server.await?;
+    Ok(())
+}
+
+async fn handle(server: Arc<RelayServer>, remote: IpAddr, request: Request<Body>) -> Result<Response<Body>, hyper::Error> {
+    if let Some(refused) = refuse(server.rate_limiter(), remote, &request) {
+        return Ok(refused);
+    }
+    let method = request.method().clone();
+    let path = request.uri().path().to_owned();
+    let query: HashMap<String, String> = request.uri().query()
+        .map(|query| url::form_urlencoded::parse(query.as_bytes()).into_owned().collect())
+        .unwrap_or_default();
+    let authorization = request.headers().get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_owned);
+    let last_event_id: u64 = request.headers().get("Last-Event-ID")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(0);
+    debug!(method = %method, path = %path, "Request");
+
+    let response = match (&method, path.as_str()) {
+        (&Method::OPTIONS, _) => empty(StatusCode::NO_CONTENT),
+        (&Method::POST, "/votes") => {
+            match read_body(request.into_body()).await? {
+                Some(body) => {
+                    let submitted = String::from_utf8_lossy(&body).into_owned();
+                    respond(StatusCode::ACCEPTED, server.relay_vote(&submitted).await)
+                },
+                None => empty(StatusCode::PAYLOAD_TOO_LARGE)
+            }
+        },
+        (&Method::POST, "/votecodes/check") => {
+            match read_body(request.into_body()).await? {
+                Some(body) => respond(StatusCode::OK, Ok(check_typed_votecode(&String::from_utf8_lossy(&body)))),
+                None => empty(StatusCode::PAYLOAD_TOO_LARGE)
+            }
+        },
+        (&Method::GET, "/proof") => match query.get("data").cloned() {
+            Some(data) => respond(StatusCode::OK, blocking(move || server.proof(&data)).await),
+            None => respond::<()>(StatusCode::OK, Err(RelayError::InvalidPayload("Missing query parameter: data".to_owned())))
+        },
+        (&Method::GET, "/status") => {
+            let check_chain = query.get("chain").map(|value| value == "true").unwrap_or(false);
+            respond(StatusCode::OK, blocking(move || server.status(check_chain)).await)
+        },
+        (&Method::GET, "/ballots") => match query.get("serial").map(|serial| serial.parse::<BallotSerial>()) {
+            Some(Ok(serial)) => respond(StatusCode::OK, blocking(move || server.lookup_ballot(serial, authorization.as_deref())).await),
+            Some(Err(_)) => respond::<()>(StatusCode::OK, Err(RelayError::InvalidPayload("Invalid ballot serial".to_owned()))),
+            None => respond::<()>(StatusCode::OK, Err(RelayError::InvalidPayload("Missing query parameter: serial".to_owned())))
+        },
+        (&Method::GET, "/metrics") => metrics_response(),
+        (&Method::GET, "/events") => event_stream(server.observers(), last_event_id),
+        (_, "/votes") | (_, "/votecodes/check") | (_, "/proof") | (_, "/status") | (_, "/ballots") | (_, "/metrics") | (_, "/events") => empty(StatusCode::METHOD_NOT_ALLOWED),
+        _ => respond::<()>(StatusCode::OK, Err(RelayError::NotFound(path.clone())))
+    };
+    let response = match path.as_str() {
+        "/status" | "/proof" => allow_cors(response),
+        _ => response
+    };
+    info!(method = %method, path = %path, status = response.status().as_u16(), "Response");
+    Ok(response)
+}
+
+/// Serve the proofs of `server` on `address` until the process is stopped.
+pub async fn serve_proofs(server: Arc<ProofServer>, address: SocketAddr) -> crate::Result<()> {
+    let make_service = make_service_fn(move |connection: &AddrStream| {
+        let server = server.clone();
+        let remote = connection.remote_addr().ip();
+        async move {
+            Ok::<_, hyper::Error>(service_fn(move |request| {
+                handle_proof(server.clone(), remote, request)
+            }))
+        }
+    });
+    let server = Server::try_bind(&address)?.serve(make_service);
+    info!(address = %address, "Serving proofs");
+    server.await?;
+    Ok(())
+}
+
+async fn handle_proof(server: Arc<ProofServer>, remote: IpAddr, request: Request<Body>) -> Result<Response<Body>, hyper::Error> {
+    if let Some(refused) = refuse(server.rate_limiter(), remote, &request) {
+        return Ok(refused);
+    }
+    let query: HashMap<String, String> = request.uri().query()
+        .map(|query| url::form_urlencoded::parse(query.as_bytes()).into_owned().collect())
+        .unwrap_or_default();
+
+    let response = match (request.method(), request.uri().path()) {
+        (&Method::OPTIONS, _) => empty(StatusCode::NO_CONTENT),
+        (&Method::GET, "/proof") => match query.get("data").cloned() {
+            Some(data) => respond(StatusCode::OK, blocking(move || server.proof(&data)).await),
+            None => respond::<()>(StatusCode::OK, Err(RelayError::InvalidPayload("Missing query parameter: data".to_owned())))
+        },
+        (_, "/proof") => empty(StatusCode::METHOD_NOT_ALLOWED),
+        _ => empty(StatusCode::NOT_FOUND)
+    };
+    let response = allow_cors(response);
+    info!(method = %request.method(), path = %request.uri().path(), status = response.status().as_u16(), "Response");
+    Ok(response)
+}
+
+// Read a request body, or `None` if it is larger than `MAX_BODY_SIZE`,
+// without buffering more than that.
+async fn read_body(mut body: Body) -> Result<Option<Vec<u8>>, hyper::Error> {
+    let mut data = Vec::new();
+    while let Some(chunk) = body.data().await {
+        let chunk = chunk?;
+        if MAX_BODY_SIZE < data.len() + chunk.len() {
+            return Ok(None);
+        }
+        data.extend_from_slice(&chunk);
+    }
+    Ok(Some(data))
+}
+
+// Refuse a request over the rate limit of its client or over the size limits.
+fn refuse(rate_limiter: Option<&RateLimiter>, remote: IpAddr, request: &Request<Body>) -> Option<Response<Body>> {
+    if let Some(rate_limiter) = rate_limiter {
+        if !rate_limiter.allow(remote) {
+            warn!(remote = %remote, "Request over the rate limit refused");
+            let mut response = respond::<()>(StatusCode::OK, Err(RelayError::RateLimited));
+            let retry_after = (60 / rate_limiter.requests_per_minute().max(1)).max(1);
+            response.headers_mut().insert(RETRY_AFTER, HeaderValue::from(retry_after));
+            return Some(response);
+        }
+    }
+    if MAX_URI_SIZE < request.uri().to_string().len() {
+        return Some(empty(StatusCode::URI_TOO_LONG));
+    }
+    let content_length = request.headers().get(CONTENT_LENGTH)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<usize>().ok());
+    if content_length.is_some_and(|content_length| MAX_BODY_SIZE < content_length) {
+        return Some(empty(StatusCode::PAYLOAD_TOO_LARGE));
+    }
+    None
+}
+
+/// Serve only `GET /metrics` on `address`, for the monitor.
+pub async fn serve_metrics(address: SocketAddr) -> crate::Result<()> {
+    let make_service = make_service_fn(|_| async {
+        Ok::<_, hyper::Error>(service_fn(|request: Request<Body>| async move {
+            Ok::<_, hyper::Error>(match (request.method(), request.uri().path()) {
+                (&Method::GET, "/metrics") => metrics_response(),
+                (_, "/metrics") => empty(StatusCode::METHOD_NOT_ALLOWED),
+                _ => empty(StatusCode::NOT_FOUND)
+            })
+        }))
+    });
+    let server = Server::try_bind(&address)?.serve(make_service);
+    info!(address = %address, "Serving metrics");
+    server.await?;
+    Ok(())
+}
+
+fn metrics_response() -> Response<Body> {
+    let mut response = Response::new(Body::from(metrics::render()));
+    response.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static("text/plain; version=0.0.4"));
+    response
+}
+
+// Stream the events of `observers` published after `last_event_id`.
+fn event_stream(observers: &ObserverHub, last_event_id: u64) -> Response<Body> {
+    let (missed, mut receiver) = observers.subscribe(last_event_id);
+    let (mut sender, body) = Body::channel();
+    tokio::spawn(async move {
+        for event in missed.iter() {
+            if sender.send_data(event.to_sse().into()).await.is_err() {
+                return;
+            }
+        }
+        // An observer that falls behind is disconnected, to resume from
+        // its last event.
+        while let Ok(event) = receiver.recv().await {
+            if sender.send_data(event.to_sse().into()).await.is_err() {
+                return;
+            }
+        }
+    });
+    let mut response = Response::new(body);
+    response.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static("text/event-stream"));
+    response.headers_mut().insert(CACHE_CONTROL, HeaderValue::from_static("no-cache"));
+    response
+}
+
+// Run blocking work (file and blockchain access) off the server threads.
+async fn blocking<T, F>(work: F) -> Result<T, RelayError>
+    where T: Send + 'static, F: FnOnce() -> Result<T, RelayError> + Send + 'static {
+    match tokio::task::spawn_blocking(work).await {
+        Ok(result) => result,
+        Err(err) => Err(RelayError::Backend(err.to_string()))
+    }
+}
+
+fn respond<T: Serialize>(status: StatusCode, result: Result<T, RelayError>) -> Response<Body> {
+    let (status, body) = match result {
+        Ok(value) => (status, serde_json::to_string(&value)),
+        Err(err) => (err.status_code(), serde_json::to_string(&ErrorResponse { error: err.to_string() }))
+    };
+    let mut response = Response::new(Body::from(body.unwrap()));
+    *response.status_mut() = status;
+    response.headers_mut().insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    response
+}
+
+fn empty(status: StatusCode) -> Response<Body> {
+    let mut response = Response::new(Body::empty());
+    *response.status_mut() = status;
+    response
+}
+
+// The public, read-only routes can be fetched from any origin; votes,
+// ballot lookups and the event stream are only served to the origin of the
+// server.
+fn allow_cors(mut response: Response<Body>) -> Response<Body> {
+    let headers = response.headers_mut();
+    headers.insert(ACCESS_CONTROL_ALLOW_ORIGIN, HeaderValue::from_static("*"));
+    headers.insert(ACCESS_CONTROL_ALLOW_METHODS, HeaderValue::from_static("GET, OPTIONS"));
+    headers.insert(ACCESS_CONTROL_ALLOW_HEADERS, HeaderValue::from_static("Content-Type"));
+    response
+}
@@ -0,0 +1,389 @@
+//! # Vote Relay Server
+//!
+//! `server` backs the web interface of a poll directly with this crate.
+//! Voters submit the vote code printed on their ballot, which is relayed
+//! to the blockchain as a vote payload, and can then fetch the proof of
+//! inclusion of published data and query the status of the poll.
+//!
+//! Help desk staff holding the help desk token can look up the status of a
+//! ballot by its serial.
+//!
+//! Observers can follow the audit of the poll as a stream of events.
+//!
+//! Given a voting deadline, the server closes the voting window itself and
+//! records the votes and counts the tally once it has passed.
+//!
+//! With the `grpc` feature, registrar back-office systems can also import
+//! the voter roster and query the tally through a gRPC service.
+//!
+//! The server holds the Poll Master Key for its whole lifetime, so that
+//! submitted vote codes are checked against the ballots of the poll
+//! before any transaction is sent. For polls with encrypted votes, the
+//! relayed payloads are encrypted to the vote encryption key of the poll.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+use serde::Serialize;
+use tracing::{info, warn};
+use crate::AEADKey;
+use crate::metrics;
+use crate::ballots::*;
+use crate::blockchain::{post_vote, record_sent_transaction, PostedTransaction, SpendPhase};
+use crate::votes::{encrypt_vote_payload, PayloadKind, SignedVotePayload, VotePayload};
+use seventh_estate_core::votecode::check_typed_votecode;
+use crate::voter_roster::{RosterSchema, VoterRoster};
+use crate::poll_configuration::PollConfiguration;
+use crate::cryptography::{aead_decrypt, VoteEncryptionKey};
+use crate::subcommands::{read_poll_configuration_file, write_poll_configuration_file, read_poll_status, read_poll_tally, bind_voter_roster, find_inclusion_proof, GeneratedProof, PollStatus, PollTally};
+
+pub mod http;
+pub use http::*;
+
+pub mod helpdesk;
+pub use helpdesk::*;
+
+pub mod rate_limit;
+pub use rate_limit::*;
+
+pub mod proofs;
+pub use proofs::*;
+
+pub mod events;
+pub use events::*;
+
+pub mod closing;
+pub use closing::*;
+
+pub mod relay_log;
+pub use relay_log::*;
+
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "grpc")]
+pub use grpc::*;
+
+/// Reasons a request to the server is refused.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RelayError {
+    InvalidPayload(String),
+    WrongPoll(String),
+    UnknownVoteCode(String),
+    AlreadySubmitted(String),
+    VotingClosed,
+    InvalidRoster(String),
+    NotFound(String),
+    /// The help desk token is missing or wrong.
+    Unauthorized,
+    /// The client sent more requests than its rate limit.
+    RateLimited,
+    /// The poll configuration, Merkle tree or blockchain could not be read.
+    Backend(String)
+}
+
+/// Signed vote payload of a submission given as hex, with a `0x` prefix.
+fn signed_submission(submitted: &str) -> Option<SignedVotePayload> {
+    let data = hex::decode(submitted.trim().strip_prefix("0x")?).ok()?;
+    SignedVotePayload::from_bytes(&data)
+}
+
+impl fmt::Display for RelayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RelayError::InvalidPayload(reason) => write!(f, "{}", reason),
+            RelayError::WrongPoll(poll) => write!(f, "Payload is for another poll: {}", poll),
+            RelayError::UnknownVoteCode(votecode) => write!(f, "Vote code is not on a ballot of this poll: {}", votecode),
+            RelayError::AlreadySubmitted(votecode) => write!(f, "Vote code was already submitted: {}", votecode),
+            RelayError::VotingClosed => write!(f, "Voting window is not open."),
+            RelayError::InvalidRoster(reason) => write!(f, "Voter roster cannot be bound: {}", reason),
+            RelayError::NotFound(what) => write!(f, "Not found: {}", what),
+            RelayError::Unauthorized => write!(f, "A valid help desk token is required."),
+            RelayError::RateLimited => write!(f, "Too many requests, retry later."),
+            RelayError::Backend(reason) => write!(f, "{}", reason)
+        }
+    }
+}
+
+impl std::error::Error for RelayError {}
+
+/// Error body returned by the server.
+#[derive(Debug, Clone, Serialize)]
+pub struct ErrorResponse {
+    pub error: String
+}
+
+pub struct RelayServer {
+    /// Network the votes are relayed to.
+    network: String,
+    poll_identifier: String,
+    /// Poll identifier of the vote payloads of the poll.
+    payload_identifier: String,
+    pollconf_filename: String,
+    aead_pmk: AEADKey,
+    merkle_tree: String,
+    ballots: VoteCodeMap<BallotSerial>,
+    relayed: Mutex<VoteCodeSet>,
+    /// Serials of the ballots whose vote codes were relayed.
+    relayed_ballots: Mutex<HashSet<BallotSerial>>,
+    /// Vote codes relayed by every run of the server, once opened.
+    relay_log: Mutex<Option<RelayLog>>,
+    helpdesk: Option<HelpdeskCredential>,
+    rate_limiter: Option<RateLimiter>,
+    /// Key the relayed votes are encrypted to.
+    vote_encryption_key: Option<VoteEncryptionKey>,
+    /// Held while the poll configuration file is rewritten.
+    pollconf_lock: Mutex<()>,
+    observers: ObserverHub,
+    /// Set once the voting deadline of the server has passed.
+    closed: AtomicBool
+}
+
+impl RelayServer {
+    /// Relay the votes of the poll to `network`. `ballots` maps the vote
+    /// codes of the poll to their ballot serial.
+    pub fn new(network: &str, poll_identifier: &str, pollconf_filename: &str, aead_pmk: AEADKey, merkle_tree: &str, ballots: VoteCodeMap<BallotSerial>) -> Self {
+        RelayServer {
+            network: network.to_owned(),
+            poll_identifier: poll_identifier.to_owned(),
+            payload_identifier: poll_identifier.to_owned(),
+            pollconf_filename: pollconf_filename.to_owned(),
+            aead_pmk,
+            merkle_tree: merkle_tree.to_owned(),
+            ballots,
+            relayed: Mutex::new(VoteCodeSet::new()),
+            relayed_ballots: Mutex::new(HashSet::new()),
+            relay_log: Mutex::new(None),
+            helpdesk: None,
+            rate_limiter: None,
+            vote_encryption_key: None,
+            pollconf_lock: Mutex::new(()),
+            observers: ObserverHub::new(poll_identifier),
+            closed: AtomicBool::new(false)
+        }
+    }
+
+    pub fn network(&self) -> &str { &self.network }
+
+    pub fn poll_identifier(&self) -> &str { &self.poll_identifier }
+
+    /// Identify the poll in the vote payloads by `payload_identifier`, its
+    /// UUID, rather than by its poll identifier.
+    pub fn set_payload_identifier(&mut self, payload_identifier: &str) {
+        self.payload_identifier = payload_identifier.to_owned();
+    }
+
+    /// Log the relayed vote codes to the data directory of the poll, and
+    /// refuse those relayed by earlier runs of the server, returning their
+    /// number.
+    pub fn open_relay_log(&mut self) -> crate::Result<usize> {
+        let relay_log = RelayLog::open(&self.poll_identifier, &self.aead_pmk)?;
+        self.relayed_ballots.lock().unwrap().extend(relay_log.serials());
+        let relayed = relay_log.len();
+        *self.relay_log.lock().unwrap() = Some(relay_log);
+        Ok(relayed)
+    }
+
+    /// Limit the requests of every client address.
+    pub fn set_rate_limiter(&mut self, rate_limiter: RateLimiter) {
+        self.rate_limiter = Some(rate_limiter);
+    }
+
+    pub fn rate_limiter(&self) -> Option<&RateLimiter> { self.rate_limiter.as_ref() }
+
+    /// Audit events published to the observers.
+    pub fn observers(&self) -> &ObserverHub { &self.observers }
+
+    /// Refuse the votes submitted from now on, whatever the state of the poll.
+    pub fn close_voting(&self) {
+        self.closed.store(true, Ordering::SeqCst);
+    }
+
+    pub fn voting_closed(&self) -> bool { self.closed.load(Ordering::SeqCst) }
+
+    /// Encrypt the relayed votes to `key`.
+    pub fn set_vote_encryption_key(&mut self, key: VoteEncryptionKey) {
+        self.vote_encryption_key = Some(key);
+    }
+
+    /// Serve ballot lookups to the holders of the help desk token.
+    pub fn set_helpdesk_credential(&mut self, credential: HelpdeskCredential) {
+        self.helpdesk = Some(credential);
+    }
+
+    /// Current status of the poll. The poll configuration is read on every
+    /// call, so that steps run while the server is up are reflected.
+    pub fn status(&self, check_chain: bool) -> Result<PollStatus, RelayError> {
+        let _lock = self.pollconf_lock.lock().unwrap();
+        let secured_poll_configuration = read_poll_configuration_file(&self.pollconf_filename)
+            .map_err(|err| RelayError::Backend(err.to_string()))?;
+        read_poll_status(&self.network, &secured_poll_configuration, &self.aead_pmk, check_chain)
+            .map_err(|err| RelayError::Backend(err.to_string()))
+    }
+
+    /// Check a submitted payload against the poll, without relaying it.
+    pub fn check_vote(&self, submitted: &str) -> Result<VotePayload, RelayError> {
+        let checked = self.check_payload(submitted);
+        if checked.is_err() {
+            metrics::inc_invalid_payloads();
+        }
+        checked
+    }
+
+    fn check_payload(&self, submitted: &str) -> Result<VotePayload, RelayError> {
+        if let Some(signed) = signed_submission(submitted) {
+            if !signed.verify(&self.payload_identifier) {
+                return Err(RelayError::InvalidPayload(String::from("Signature of the vote does not match the poll and vote code.")));
+            }
+            if !self.ballots.contains(&signed.votecode) {
+                return Err(RelayError::UnknownVoteCode(string_from_votecode(&signed.votecode)));
+            }
+            return Ok(VotePayload::vote(&self.payload_identifier, &signed.votecode));
+        }
+        let payload: VotePayload = submitted.parse().map_err(RelayError::InvalidPayload)?;
+        if payload.kind != PayloadKind::Vote {
+            return Err(RelayError::InvalidPayload(format!("Not a vote submission: {}", submitted.trim())));
+        }
+        if let Some(poll_identifier) = &payload.poll_identifier {
+            if *poll_identifier != self.payload_identifier {
+                return Err(RelayError::WrongPoll(poll_identifier.clone()));
+            }
+        }
+        if !self.ballots.contains(&payload.votecode) {
+            return Err(RelayError::UnknownVoteCode(string_from_votecode(&payload.votecode)));
+        }
+        Ok(VotePayload::vote(&self.payload_identifier, &payload.votecode))
+    }
+
+    /// Relay a submitted vote code, vote payload or hex encoded signed vote
+    /// payload to the blockchain.
+    /// Each vote code is relayed at most once while the server is up, and
+    /// across restarts once the relay log is opened.
+    pub async fn relay_vote(&self, submitted: &str) -> Result<PostedTransaction, RelayError> {
+        let payload = self.check_vote(submitted)?;
+        if self.relay_log.lock().unwrap().as_ref().is_some_and(|relay_log| relay_log.contains(&payload.votecode)) {
+            return Err(RelayError::AlreadySubmitted(string_from_votecode(&payload.votecode)));
+        }
+        if self.voting_closed() || !self.status(false)?.voting_open {
+            return Err(RelayError::VotingClosed);
+        }
+
+        if !self.relayed.lock().unwrap().insert(payload.votecode) {
+            return Err(RelayError::AlreadySubmitted(string_from_votecode(&payload.votecode)));
+        }
+        // Signed votes are posted as signed by the voter's device.
+        let data = match signed_submission(submitted) {
+            Some(signed) => signed.to_bytes(),
+            None => payload.to_binary()
+        };
+        let data = match &self.vote_encryption_key {
+            Some(key) => encrypt_vote_payload(&self.payload_identifier, key, &data),
+            None => data
+        };
+        match post_vote(&self.network, data).await {
+            Ok(posted) => {
+                info!(tx_hash = %posted.transaction_hash, "Vote relayed");
+                if let Err(err) = record_sent_transaction(&self.poll_identifier, SpendPhase::Vote, &posted.transaction_hash, &posted.address) {
+                    warn!(error = %err, "Relayed vote could not be recorded in the spending log");
+                }
+                metrics::inc_votes_relayed();
+                let serial = self.ballots.get(&payload.votecode).cloned();
+                if let Some(relay_log) = self.relay_log.lock().unwrap().as_mut() {
+                    if let Err(err) = relay_log.record(&payload.votecode, serial) {
+                        warn!(error = %err, "Relayed vote could not be recorded in the relay log");
+                    }
+                }
+                if let Some(serial) = serial {
+                    self.relayed_ballots.lock().unwrap().insert(serial);
+                }
+                Ok(posted)
+            },
+            Err(err) => {
+                // Allow the voter to retry.
+                self.relayed.lock().unwrap().remove(&payload.votecode);
+                warn!(error = %err, "Vote could not be relayed");
+                Err(RelayError::Backend(err.to_string()))
+            }
+        }
+    }
+
+    /// Proof of inclusion of data in the committed Merkle tree.
+    pub fn proof(&self, data: &str) -> Result<GeneratedProof, RelayError> {
+        if !std::path::Path::new(&self.merkle_tree).exists() {
+            return Err(RelayError::NotFound("Merkle tree".to_owned()));
+        }
+        find_inclusion_proof(&self.merkle_tree, data)
+            .map_err(|err| RelayError::Backend(err.to_string()))?
+            .ok_or_else(|| RelayError::NotFound(format!("data in Merkle tree: {}", data)))
+    }
+
+    /// Bind a voter roster to the poll, as `bind-roster` does, returning
+    /// the number of voters.
+    pub fn import_roster(&self, roster: &VoterRoster, disable_privacy: bool, force: bool) -> Result<usize, RelayError> {
+        let _lock = self.pollconf_lock.lock().unwrap();
+        let mut secured_poll_configuration = read_poll_configuration_file(&self.pollconf_filename)
+            .map_err(|err| RelayError::Backend(err.to_string()))?;
+        bind_voter_roster(&mut secured_poll_configuration, &self.aead_pmk, roster, disable_privacy, force)
+            .map_err(|err| RelayError::InvalidRoster(err.to_string()))?;
+        write_poll_configuration_file(&self.pollconf_filename, &secured_poll_configuration)
+            .map_err(|err| RelayError::Backend(err.to_string()))?;
+        info!(voters = roster.len(), "Voter roster imported");
+        Ok(roster.len())
+    }
+
+    /// Decrypted poll configuration, as it is on disk.
+    fn poll_configuration(&self) -> Result<PollConfiguration, RelayError> {
+        let _lock = self.pollconf_lock.lock().unwrap();
+        let secured_poll_configuration = read_poll_configuration_file(&self.pollconf_filename)
+            .map_err(|err| RelayError::Backend(err.to_string()))?;
+        let pollconf_aead_values = secured_poll_configuration.encrypted_poll_configuration.values()
+            .map_err(|err| RelayError::Backend(err.to_string()))?;
+        let serialized_pollconf = aead_decrypt(&self.aead_pmk, &pollconf_aead_values)
+            .map_err(|err| RelayError::Backend(err.to_string()))?;
+        serde_yaml::from_slice::<PollConfiguration>(&serialized_pollconf)
+            .map_err(|err| RelayError::Backend(err.to_string()))
+    }
+
+    /// Roster schema the imported roster is validated against, if the poll
+    /// has one.
+    pub fn roster_schema(&self) -> Result<Option<RosterSchema>, RelayError> {
+        Ok(self.poll_configuration()?.roster_schema.clone())
+    }
+
+    /// Status of the ballot `serial`, for the help desk. `authorization` is
+    /// the value of the `Authorization` header of the request.
+    pub fn lookup_ballot(&self, serial: BallotSerial, authorization: Option<&str>) -> Result<BallotLookup, RelayError> {
+        match &self.helpdesk {
+            Some(credential) if credential.authorizes(authorization) => (),
+            _ => return Err(RelayError::Unauthorized)
+        }
+
+        let pollconf = self.poll_configuration()?;
+        if serial >= pollconf.num_ballots {
+            return Err(RelayError::NotFound(format!("ballot {}", serial)));
+        }
+
+        let audited = pollconf.audited_ballots.iter().flatten()
+            .any(|audited| audited.parse() == Ok(serial));
+        let voted = self.relayed_ballots.lock().unwrap().contains(&serial)
+            || pollconf.votes.iter().flatten().any(|votecode| self.ballots.get(votecode) == Some(&serial));
+        let replacement = pollconf.ballot_amendments.iter()
+            .find(|amendment| amendment.spoiled_serial == serial)
+            .map(|amendment| amendment.replacement_serial);
+        let status = match (audited, replacement, voted) {
+            (true, _, _) => BallotStatus::Audited,
+            (false, Some(_), _) => BallotStatus::Spoiled,
+            (false, None, true) => BallotStatus::Voted,
+            (false, None, false) => BallotStatus::Issued
+        };
+        info!(serial, status = ?status, "Ballot looked up");
+        Ok(BallotLookup { serial, status, replacement })
+    }
+
+    /// Tally of the revealed planes.
+    pub fn tally(&self) -> Result<PollTally, RelayError> {
+        read_poll_tally(&self.poll_identifier)
+            .map_err(|err| RelayError::Backend(err.to_string()))?
+            .ok_or_else(|| RelayError::NotFound("tally, the poll has not been revealed".to_owned()))
+    }
+}
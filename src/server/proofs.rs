@@ -0,0 +1,48 @@
+//! # Public Proof Server
+//!
+//! `ProofServer` serves the proofs of inclusion of a published Merkle tree
+//! to the public, apart from the vote relay: it holds neither the Poll
+//! Master Key nor a blockchain key, and only reads `merkle.yaml` inside the
+//! directory it is given, such as a bulletin board directory. The tree is
+//! opened read-only, and a tree that resolves outside of the directory,
+//! through a symbolic link, is refused.
+
+use std::fs::File;
+use std::path::{Path, PathBuf};
+use crate::bulletin_board::MERKLE_TREE_FILENAME;
+use crate::subcommands::{find_inclusion_proof, GeneratedProof};
+use super::{RateLimiter, RelayError};
+
+pub struct ProofServer {
+    merkle_tree: PathBuf,
+    rate_limiter: Option<RateLimiter>
+}
+
+impl ProofServer {
+    /// Serve the proofs of the Merkle tree of the directory `dir`.
+    pub fn open(dir: &Path) -> crate::Result<Self> {
+        let root = dir.canonicalize()
+            .map_err(|err| format!("{} cannot be served: {}", dir.display(), err))?;
+        let merkle_tree = root.join(MERKLE_TREE_FILENAME).canonicalize()
+            .map_err(|_| format!("No Merkle tree {} in {}", MERKLE_TREE_FILENAME, dir.display()))?;
+        if !merkle_tree.starts_with(&root) || !merkle_tree.is_file() {
+            return Err(format!("{} resolves outside of {}.", MERKLE_TREE_FILENAME, dir.display()).into());
+        }
+        File::open(&merkle_tree)?;
+        Ok(ProofServer { merkle_tree, rate_limiter: None })
+    }
+
+    /// Limit the requests of every client address.
+    pub fn set_rate_limiter(&mut self, rate_limiter: RateLimiter) {
+        self.rate_limiter = Some(rate_limiter);
+    }
+
+    pub fn rate_limiter(&self) -> Option<&RateLimiter> { self.rate_limiter.as_ref() }
+
+    /// Proof of inclusion of data in the served Merkle tree.
+    pub fn proof(&self, data: &str) -> Result<GeneratedProof, RelayError> {
+        find_inclusion_proof(&self.merkle_tree.to_string_lossy(), data)
+            .map_err(|err| RelayError::Backend(err.to_string()))?
+            .ok_or_else(|| RelayError::NotFound(format!("data in Merkle tree: {}", data)))
+    }
+}
@@ -0,0 +1,58 @@
+//! # Rate Limiting
+//!
+//! `RateLimiter` limits the requests of every client address with a token
+//! bucket: a client may send a burst of up to a minute of requests, then
+//! requests at the configured rate. Addresses that have been idle long
+//! enough to refill their bucket are forgotten, so that the limiter does
+//! not grow with the number of clients.
+
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Mutex;
+use std::time::Instant;
+
+/// Addresses tracked before idle ones are forgotten.
+const PRUNE_THRESHOLD: usize = 10_000;
+
+struct Bucket {
+    tokens: f64,
+    updated: Instant
+}
+
+pub struct RateLimiter {
+    requests_per_minute: u32,
+    buckets: Mutex<HashMap<IpAddr, Bucket>>
+}
+
+impl RateLimiter {
+    pub fn per_minute(requests_per_minute: u32) -> Self {
+        RateLimiter { requests_per_minute, buckets: Mutex::new(HashMap::new()) }
+    }
+
+    pub fn requests_per_minute(&self) -> u32 { self.requests_per_minute }
+
+    /// Whether a request of `address` is allowed now.
+    pub fn allow(&self, address: IpAddr) -> bool {
+        self.allow_at(address, Instant::now())
+    }
+
+    /// Whether a request of `address` is allowed at `now`.
+    pub fn allow_at(&self, address: IpAddr, now: Instant) -> bool {
+        let capacity = f64::from(self.requests_per_minute);
+        let refill = |bucket: &Bucket| (bucket.tokens + now.saturating_duration_since(bucket.updated).as_secs_f64() * capacity / 60.0).min(capacity);
+
+        let mut buckets = self.buckets.lock().unwrap();
+        if PRUNE_THRESHOLD <= buckets.len() {
+            buckets.retain(|_, bucket| refill(bucket) < capacity);
+        }
+        let bucket = buckets.entry(address).or_insert(Bucket { tokens: capacity, updated: now });
+        bucket.tokens = refill(bucket);
+        bucket.updated = now;
+        if 1.0 <= bucket.tokens {
+            bucket.tokens -= 1.0;
+            true
+        } else {
+            false
+        }
+    }
+}
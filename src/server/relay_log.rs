@@ -0,0 +1,90 @@
+//! # Relay Log
+//!
+//! `RelayLog` records the vote codes relayed by the server in the data
+//! directory of the poll, so that a vote code relayed before the server
+//! was restarted is still refused after. Vote codes are logged as their
+//! hash keyed with the Poll Master Key, so that the log does not reveal the
+//! vote codes of polls with encrypted votes, with the serial of their
+//! ballot for the help desk.
+
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use crate::Result;
+use crate::AEADKey;
+use crate::ballots::{string_from_votecode, BallotSerial, VoteCode};
+
+/// File of the data directory the relayed vote codes are logged to.
+pub const RELAY_LOG_FILENAME: &str = "relayed_votes.log";
+
+const RELAY_LOG_TAG: &[u8] = b"7E:RELAYED-VOTE:";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RelayedVote {
+    /// Hex encoded keyed hash of the vote code.
+    votecode_hash: String,
+    serial: Option<BallotSerial>
+}
+
+/// Vote codes relayed for a poll, by any run of the server.
+pub struct RelayLog {
+    path: PathBuf,
+    key: AEADKey,
+    relayed: HashMap<String, Option<BallotSerial>>
+}
+
+impl RelayLog {
+    /// Open the relay log of the poll of `datadir_path`, reading the vote
+    /// codes relayed so far.
+    pub fn open(datadir_path: &str, key: &AEADKey) -> Result<Self> {
+        let path = Path::new(datadir_path).join(RELAY_LOG_FILENAME);
+        let relayed = match path.exists() {
+            true => fs::read_to_string(&path)?.lines()
+                .filter(|line| !line.trim().is_empty())
+                .map(|line| {
+                    let record: RelayedVote = serde_json::from_str(line)?;
+                    Ok((record.votecode_hash, record.serial))
+                })
+                .collect::<Result<HashMap<String, Option<BallotSerial>>>>()?,
+            false => HashMap::new()
+        };
+        Ok(RelayLog { path, key: key.clone(), relayed })
+    }
+
+    // SHA-256(tag || key || vote code).
+    fn votecode_hash(&self, votecode: &VoteCode) -> String {
+        let mut hasher = Sha256::new();
+        hasher.input(RELAY_LOG_TAG);
+        hasher.input(&self.key.0);
+        hasher.input(string_from_votecode(votecode).as_bytes());
+        hex::encode(hasher.result())
+    }
+
+    pub fn contains(&self, votecode: &VoteCode) -> bool {
+        self.relayed.contains_key(&self.votecode_hash(votecode))
+    }
+
+    /// Serials of the ballots whose vote codes were relayed.
+    pub fn serials(&self) -> HashSet<BallotSerial> {
+        self.relayed.values().flatten().cloned().collect()
+    }
+
+    pub fn len(&self) -> usize { self.relayed.len() }
+
+    pub fn is_empty(&self) -> bool { self.relayed.is_empty() }
+
+    /// Log a relayed vote code, of the ballot `serial`.
+    pub fn record(&mut self, votecode: &VoteCode, serial: Option<BallotSerial>) -> Result<()> {
+        let record = RelayedVote { votecode_hash: self.votecode_hash(votecode), serial };
+        let mut log = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(log, "{}", serde_json::to_string(&record)?)?;
+        self.relayed.insert(record.votecode_hash, serial);
+        Ok(())
+    }
+}
@@ -0,0 +1,229 @@
+//! # Logic and Accuracy Tests
+//!
+//! Before a poll goes live, election officials run it on a known test deck
+//! and check that the tally comes out as expected. `run_logic_accuracy`
+//! creates a poll with the ballots, decoys and tally rules of the poll
+//! under test on the in-memory `MockChain`, and marks every ballot of the
+//! deck with a known case, in turn: a vote for either choice, no vote, a
+//! vote submitted twice, both vote codes of the ballot and a vote following
+//! an invalid submission. The votes are submitted in deck order, recorded,
+//! revealed and finalized, and the tally is checked against the outcome
+//! expected from the deck and the tally rules.
+//!
+//! The deck is written to `test_deck.csv` in the directory of the test,
+//! with every ballot, its case, the vote codes submitted and the choices
+//! expected to be counted.
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use super::*;
+use crate::votes::{SubmittedVote, TallyPolicy};
+
+const TEST_DECK_FILE: &str = "test_deck.csv";
+
+#[derive(Debug, Clone)]
+pub struct LogicAccuracyConfiguration {
+    pub num_ballots: usize,
+    pub num_decoys: usize,
+    pub tally_rules: TallyRules,
+    /// Hexadecimal seed of the ballots and of the public seeds of the test
+    /// poll; random if `None`.
+    pub seed: Option<String>
+}
+
+/// How a ballot of the test deck is voted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DeckCase {
+    For,
+    Against,
+    Unvoted,
+    /// A vote for the first choice, submitted twice.
+    Duplicate,
+    /// Both vote codes of the ballot, the first choice first.
+    BothChoices,
+    /// A malformed payload, then a vote for the second choice.
+    Invalid
+}
+
+pub const DECK_CASES: [DeckCase; 6] = [DeckCase::For, DeckCase::Against, DeckCase::Unvoted, DeckCase::Duplicate, DeckCase::BothChoices, DeckCase::Invalid];
+
+#[derive(Debug, Clone, Serialize)]
+struct TestDeckRow {
+    serial: BallotSerial,
+    case: DeckCase,
+    decoy: bool,
+    submitted: String,
+    expected: String
+}
+
+/// Outcome of a logic and accuracy test.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LogicAccuracyReport {
+    /// Seed of the test poll, to run it again.
+    pub seed: String,
+    pub tested_at: DateTime<Utc>,
+    pub ballots: usize,
+    pub decoys: usize,
+    pub tally_rules: TallyRules,
+    /// Transactions sent to the vote address.
+    pub submissions: usize,
+    pub expected: PlaneTally,
+    pub tally: PlaneTally,
+    pub planes_counted: usize,
+    pub expected_duplicates: usize,
+    pub duplicates_recorded: usize,
+    /// Votes for ballots voted with both vote codes that the tally rules
+    /// do not count.
+    pub expected_uncounted: usize,
+    pub uncounted_recorded: usize,
+    pub finalized: bool,
+    pub passed: bool
+}
+
+impl fmt::Display for LogicAccuracyReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Test seed: {}", self.seed)?;
+        writeln!(f, "Test deck: {} ballots ({} decoys), {} submissions", self.ballots, self.decoys, self.submissions)?;
+        writeln!(f, "Expected: {} ({} duplicates, {} not counted)", self.expected, self.expected_duplicates, self.expected_uncounted)?;
+        writeln!(f, "Revealed: {} ({} duplicates, {} not counted, {} planes counted)",
+            self.tally, self.duplicates_recorded, self.uncounted_recorded, self.planes_counted)?;
+        writeln!(f, "Finalized: {}", if self.finalized { "yes" } else { "no" })?;
+        write!(f, "Result: {}", if self.passed { "PASSED" } else { "FAILED" })
+    }
+}
+
+/// Run a logic and accuracy test in the directory `dir`, which is created
+/// if needed. Like a simulation, the test replaces the blockchain backend
+/// and the trustee passwords of the process, and runs with `dir` as the
+/// working directory.
+pub fn run_logic_accuracy(configuration: &LogicAccuracyConfiguration, dir: &Path) -> Result<LogicAccuracyReport> {
+    if configuration.num_ballots == 0 || configuration.num_ballots < configuration.num_decoys {
+        return Err("Logic and accuracy test needs at least one ballot and no more decoys than ballots.".into());
+    }
+    let seed: Vec<u8> = match &configuration.seed {
+        Some(seed) => hex::decode(seed)?,
+        None => {
+            let mut seed = vec![0; CSPRNGSeed::SIZE];
+            rand::thread_rng().fill_bytes(&mut seed);
+            seed
+        }
+    };
+    if seed.len() != CSPRNGSeed::SIZE {
+        return Err(format!("Test seed must be {} bytes long.", CSPRNGSeed::SIZE).into());
+    }
+
+    fs::create_dir_all(dir)?;
+    let previous_dir = env::current_dir()?;
+    env::set_current_dir(dir)?;
+    let report = test_deck(configuration, &seed);
+    env::set_current_dir(previous_dir)?;
+    report
+}
+
+/// Case of the ballot at `position` in the deck.
+pub fn deck_case(position: usize) -> DeckCase {
+    DECK_CASES[position % DECK_CASES.len()]
+}
+
+fn test_deck(configuration: &LogicAccuracyConfiguration, seed: &[u8]) -> Result<LogicAccuracyReport> {
+    let mut rng = CSPRNG::from_csprng_seed(&CSPRNGSeed::from_vec(&seed.to_vec()));
+    let new_poll_configuration = NewPollConfiguration {
+        tally_rules: configuration.tally_rules.clone(),
+        ..simulated_new_poll(configuration.num_ballots, configuration.num_decoys)
+    };
+    let poll = create_simulated_poll(&new_poll_configuration, configuration.num_ballots, 0, seed, &mut rng)?;
+
+    // Mark the deck and tally the outcome expected from it.
+    let mut expected = PlaneTally::default();
+    let (mut expected_duplicates, mut expected_uncounted) = (0, 0);
+    let mut submissions: Vec<(String, Vec<u8>)> = Vec::new();
+    let mut csvwriter = csv::Writer::from_path(TEST_DECK_FILE)?;
+    for (position, ballot) in poll.ballots.iter().enumerate() {
+        let case = deck_case(position);
+        let decoy = poll.decoys.contains(&ballot.serial);
+        let voter = simulated_voter(ballot.serial);
+        let vote = |choice: &BallotChoice| VotePayload::vote(&poll.payload_identifier, &choice.votecode).to_binary();
+        let choices: Vec<&BallotChoice> = match case {
+            DeckCase::For | DeckCase::Duplicate => vec![&ballot.choice1],
+            DeckCase::Against | DeckCase::Invalid => vec![&ballot.choice2],
+            DeckCase::Unvoted => Vec::new(),
+            DeckCase::BothChoices => vec![&ballot.choice1, &ballot.choice2]
+        };
+        if case == DeckCase::Invalid {
+            submissions.push((voter.clone(), b"not a vote".to_vec()));
+        }
+        if case == DeckCase::Duplicate {
+            submissions.push((voter.clone(), vote(&ballot.choice1)));
+            expected_duplicates += 1;
+        }
+        choices.iter().for_each(|choice| submissions.push((voter.clone(), vote(choice))));
+
+        // The tally rules decide which votes of a ballot voted twice count.
+        let submitted: Vec<SubmittedVote> = choices.iter()
+            .map(|choice| SubmittedVote {
+                votecode: string_from_votecode(&choice.votecode),
+                transaction_hash: String::new(),
+                from: voter.clone(),
+                block_number: 0,
+                timestamp: Utc::now(),
+                source: Default::default(),
+                decoded: None
+            })
+            .collect();
+        let counted_positions = match submitted.len() {
+            0 | 1 => (0..submitted.len()).collect(),
+            _ => configuration.tally_rules.counted_ballot_votes(&submitted.iter().collect::<Vec<&SubmittedVote>>())
+        };
+        expected_uncounted += choices.len() - counted_positions.len();
+        let counted: Vec<&BallotChoice> = counted_positions.iter().map(|&n| choices[n]).collect();
+        for choice in counted.iter() {
+            match (decoy, choice.choice) {
+                (true, _) => expected.decoy_votes += 1,
+                (false, ChoiceValue::For) => expected.for_votes += 1,
+                (false, ChoiceValue::Against) => expected.against_votes += 1
+            }
+        }
+        csvwriter.serialize(TestDeckRow {
+            serial: ballot.serial,
+            case,
+            decoy,
+            submitted: choices.iter()
+                .map(|choice| string_from_votecode(&choice.votecode))
+                .collect::<Vec<String>>()
+                .join(" "),
+            expected: counted.iter()
+                .map(|choice| string_from_choicevalue(&choice.choice))
+                .collect::<Vec<String>>()
+                .join(" ")
+        })?;
+    }
+    csvwriter.flush()?;
+    info!(ballots = poll.ballots.len(), "Test deck marked");
+    let revealed = reveal_simulated_poll(&poll, &submissions)?;
+
+    let poll_tally = revealed.poll_tally;
+    let duplicates_recorded = poll_tally.vote_sources.iter().map(|count| count.duplicates).sum();
+    Ok(LogicAccuracyReport {
+        seed: hex::encode(seed),
+        tested_at: Utc::now(),
+        ballots: configuration.num_ballots,
+        decoys: configuration.num_decoys,
+        tally_rules: configuration.tally_rules.clone(),
+        submissions: submissions.len(),
+        passed: poll_tally.consistent
+            && 0 < poll_tally.planes_counted
+            && poll_tally.tally == expected
+            && duplicates_recorded == expected_duplicates
+            && poll_tally.uncounted_ballot_votes == expected_uncounted
+            && revealed.finalized,
+        expected,
+        tally: poll_tally.tally,
+        planes_counted: poll_tally.planes_counted,
+        expected_duplicates,
+        duplicates_recorded,
+        expected_uncounted,
+        uncounted_recorded: poll_tally.uncounted_ballot_votes,
+        finalized: revealed.finalized
+    })
+}
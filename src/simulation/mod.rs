@@ -0,0 +1,396 @@
+//! # Poll Simulation
+//!
+//! `simulation` runs a complete poll against the in-memory `MockChain`,
+//! without a blockchain node or trustees at the terminal. A synthetic
+//! roster is bound to a new poll, the ballots are drawn, some of them are
+//! spoiled in the public audit and the poll is committed once a majority
+//! of the trustees approved its commit proposal. Every other
+//! ballot is voted with the probability of the turnout, and the votes are
+//! submitted to the chain together with duplicate and invalid submissions
+//! at the configured rates. The votes are then recorded from the chain,
+//! the tally is audited, revealed and finalized, and the revealed tally is
+//! checked against the choices the simulated voters made.
+//!
+//! Simulations serve as an end-to-end test of the workflow and as a
+//! demonstration of it: all the files of the poll are written to the
+//! directory of the simulation, where they can be inspected afterwards.
+//! Logic and accuracy tests run the same workflow on a known test deck.
+
+use std::collections::HashSet;
+use std::env;
+use std::fmt;
+use std::fs::{self, File};
+use std::path::Path;
+use std::sync::Arc;
+use rand::seq::SliceRandom;
+use serde::Serialize;
+use tracing::info;
+use crate::ballots::*;
+use crate::blockchain::{approval_quorum, set_backend, tally_hash, MockChain, Recipient, COMMIT_PROPOSAL_FILENAME};
+use crate::blockchain::blocking::posted_transaction;
+use crate::cryptography::*;
+use crate::planes::PlaneTally;
+use crate::poll_configuration::*;
+use crate::secrets::*;
+use crate::subcommands::*;
+use crate::voter_roster::VoterRosterFileRow;
+use crate::votes::{ChainVotes, TallyRules, VotePayload, VoteSource};
+use crate::Result;
+
+pub mod logic_accuracy;
+pub use logic_accuracy::*;
+
+/// Identifier, and data directory, of simulated polls.
+pub const SIMULATION_POLL_IDENTIFIER: &str = "simulation";
+
+// Simulated polls run on the mock chain: no network configuration is read.
+const SIMULATION_NETWORK: &str = "simulation";
+
+const SIMULATION_TRUSTEES: [&str; 3] = ["trustee1@simulation", "trustee2@simulation", "trustee3@simulation"];
+
+const NEW_POLL_FILE: &str = "newpoll.yaml";
+const POLL_CONFIGURATION_FILE: &str = "newpoll.yaml.secure";
+const ROSTER_FILE: &str = "roster.csv";
+const AUDITED_BALLOTS_FILE: &str = "audited_ballots.csv";
+
+#[derive(Debug, Clone)]
+pub struct SimulationConfiguration {
+    /// Size of the synthetic roster.
+    pub num_voters: usize,
+    pub num_ballots: usize,
+    pub num_decoys: usize,
+    /// Ballots spoiled in the public audit, which are not voted.
+    pub num_audited: usize,
+    /// Probability that a ballot which was not audited is voted.
+    pub turnout: f64,
+    /// Probability that a vote is submitted a second time.
+    pub duplicate_rate: f64,
+    /// Probability that a vote is followed by an invalid submission, either
+    /// a vote code which is on no ballot or a malformed payload.
+    pub invalid_rate: f64,
+    /// Hexadecimal seed of the ballots, of the choices of the voters and of
+    /// the public seeds of the poll; random if `None`.
+    pub seed: Option<String>
+}
+
+impl Default for SimulationConfiguration {
+    fn default() -> Self {
+        SimulationConfiguration {
+            num_voters: 200,
+            num_ballots: 100,
+            num_decoys: 10,
+            num_audited: 5,
+            turnout: 0.8,
+            duplicate_rate: 0.1,
+            invalid_rate: 0.05,
+            seed: None
+        }
+    }
+}
+
+impl SimulationConfiguration {
+    fn validate(&self) -> Result<()> {
+        if self.num_ballots == 0 || self.num_voters < self.num_ballots {
+            return Err("Simulation needs at least one ballot and as many voters as ballots.".into());
+        }
+        if self.num_ballots < self.num_decoys || self.num_ballots < self.num_audited {
+            return Err("Simulation cannot have more decoys or audited ballots than ballots.".into());
+        }
+        for (name, rate) in [("turnout", self.turnout), ("duplicate rate", self.duplicate_rate), ("invalid rate", self.invalid_rate)].iter() {
+            if !(0.0..=1.0).contains(rate) {
+                return Err(format!("Simulation {} must be between 0 and 1: {}", name, rate).into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Outcome of a simulated poll.
+#[derive(Debug, Clone, Serialize)]
+pub struct SimulationReport {
+    /// Seed of the simulation, to run it again.
+    pub seed: String,
+    pub ballots_voted: usize,
+    /// Transactions sent to the vote address.
+    pub submissions: usize,
+    pub duplicates: usize,
+    pub invalid: usize,
+    /// Tally of the choices of the voters.
+    pub expected: PlaneTally,
+    /// Tally revealed by the poll.
+    pub tally: PlaneTally,
+    pub planes_counted: usize,
+    pub votes_recorded: usize,
+    pub duplicates_recorded: usize,
+    /// Whether the hash of the tally report was posted to the chain.
+    pub finalized: bool,
+    /// Whether the poll recorded and revealed exactly the votes of the voters.
+    pub matches: bool
+}
+
+impl fmt::Display for SimulationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Simulation seed: {}", self.seed)?;
+        writeln!(f, "Ballots voted: {}, submissions: {} ({} duplicates, {} invalid)",
+            self.ballots_voted, self.submissions, self.duplicates, self.invalid)?;
+        writeln!(f, "Votes recorded: {} ({} duplicates)", self.votes_recorded, self.duplicates_recorded)?;
+        writeln!(f, "Expected: {}", self.expected)?;
+        writeln!(f, "Revealed: {} ({} planes counted)", self.tally, self.planes_counted)?;
+        writeln!(f, "Finalized: {}", if self.finalized { "yes" } else { "no" })?;
+        write!(f, "Result: {}", if self.matches { "tally matches the votes" } else { "MISMATCH" })
+    }
+}
+
+/// Run a simulated poll in the directory `dir`, which is created if needed.
+/// The simulation replaces the blockchain backend and the trustee passwords
+/// of the process, and runs with `dir` as the working directory.
+pub fn run_simulation(configuration: &SimulationConfiguration, dir: &Path) -> Result<SimulationReport> {
+    configuration.validate()?;
+    let seed: Vec<u8> = match &configuration.seed {
+        Some(seed) => hex::decode(seed)?,
+        None => {
+            let mut seed = vec![0; CSPRNGSeed::SIZE];
+            rand::thread_rng().fill_bytes(&mut seed);
+            seed
+        }
+    };
+    if seed.len() != CSPRNGSeed::SIZE {
+        return Err(format!("Simulation seed must be {} bytes long.", CSPRNGSeed::SIZE).into());
+    }
+
+    fs::create_dir_all(dir)?;
+    let previous_dir = env::current_dir()?;
+    env::set_current_dir(dir)?;
+    let report = simulate(configuration, &seed);
+    env::set_current_dir(previous_dir)?;
+    report
+}
+
+/// Poll created, audited and committed by a simulation, ready for votes.
+struct SimulatedPoll {
+    chain: Arc<MockChain>,
+    /// Poll identifier of the vote payloads of the poll.
+    payload_identifier: String,
+    ballots: Vec<Ballot>,
+    decoys: Vec<BallotSerial>,
+    audited: HashSet<BallotSerial>,
+    audited_columns_seed: String
+}
+
+/// Tally of a simulated poll, once revealed and finalized.
+struct RevealedPoll {
+    poll_tally: PollTally,
+    /// Whether the hash of the tally report was posted to the chain.
+    finalized: bool
+}
+
+/// Create the poll of `new_poll_configuration` with a roster of
+/// `num_voters` synthetic voters on a new mock chain, audit `num_audited`
+/// of its ballots and commit it. The seeds of the poll and the audited
+/// ballots are drawn from `rng`.
+fn create_simulated_poll(new_poll_configuration: &NewPollConfiguration, num_voters: usize, num_audited: usize, seed: &[u8], rng: &mut CSPRNG) -> Result<SimulatedPoll> {
+    let mut next_seed = || {
+        let mut next_seed = vec![0; CSPRNGSeed::SIZE];
+        rng.fill_bytes(&mut next_seed);
+        hex::encode(next_seed)
+    };
+    let generation_seed = next_seed();
+    let drawn_summands_seed = next_seed();
+    let audited_columns_seed = next_seed();
+    let num_ballots = new_poll_configuration.num_ballots;
+
+    let chain = Arc::new(MockChain::new());
+    set_backend(chain.clone());
+    set_trustee_passwords(SIMULATION_TRUSTEES.iter()
+        .map(|&trustee| (trustee.to_owned(), format!("{}:{}", trustee, hex::encode(seed))))
+        .collect());
+
+    info!(voters = num_voters, ballots = num_ballots, "Creating the simulated poll");
+    serde_yaml::to_writer(File::create(NEW_POLL_FILE)?, new_poll_configuration)?;
+    write_roster(num_voters)?;
+    create_new_poll(NEW_POLL_FILE, Some(&generation_seed))?;
+    bind_roster(POLL_CONFIGURATION_FILE, ROSTER_FILE, false, false)?;
+    generate_poll_commitments(POLL_CONFIGURATION_FILE, false)?;
+    generate_drawn_summands(POLL_CONFIGURATION_FILE, &drawn_summands_seed, false)?;
+
+    // Regenerate the ballots from the generation seed, to know what the voters choose.
+    let poll_secrets = PollSecrets::from_seed(CSPRNGSeed::from_vec(&hex::decode(&generation_seed)?));
+    let serials: Vec<BallotSerial> = (0..num_ballots).collect();
+    let votecodes: Vec<VoteCode> = generate_votecodes(&poll_secrets.votecode_root, 2 * num_ballots);
+    let ballots = generate_ballots(&serials, &votecodes);
+    let decoys = generate_decoy_serials(&poll_secrets.decoy_root, new_poll_configuration.num_decoys, num_ballots);
+
+    info!(audited = num_audited, "Auditing and committing the simulated poll");
+    let audited: HashSet<BallotSerial> = rand::seq::index::sample(rng, num_ballots, num_audited)
+        .into_iter()
+        .collect();
+    let mut csvwriter = csv::Writer::from_path(AUDITED_BALLOTS_FILE)?;
+    csvwriter.write_record(["serial"])?;
+    for serial in audited.iter() {
+        csvwriter.write_record(&[serial.to_string()])?;
+    }
+    csvwriter.flush()?;
+    record_audited_ballots(SIMULATION_NETWORK, POLL_CONFIGURATION_FILE, AUDITED_BALLOTS_FILE, &[], None, false)?;
+
+    // A majority of the trustees approve the commit proposal.
+    let proposal_file = Path::new(SIMULATION_POLL_IDENTIFIER).join(COMMIT_PROPOSAL_FILENAME);
+    let approvals: Vec<String> = SIMULATION_TRUSTEES[..approval_quorum(SIMULATION_TRUSTEES.len())].iter()
+        .map(|trustee| {
+            approve_commit(&proposal_file.to_string_lossy(), trustee, None, None)?;
+            Ok(commit_approval_filename(trustee))
+        })
+        .collect::<Result<Vec<String>>>()?;
+    record_audited_ballots(SIMULATION_NETWORK, POLL_CONFIGURATION_FILE, AUDITED_BALLOTS_FILE, &approvals, None, false)?;
+    let payload_identifier = read_commit_receipt(SIMULATION_POLL_IDENTIFIER)?
+        .ok_or("Simulated poll was not committed.")?
+        .payload_identifier();
+
+    Ok(SimulatedPoll { chain, payload_identifier, ballots, decoys, audited, audited_columns_seed })
+}
+
+/// Submit `submissions`, data sent by a voter address, to the vote address
+/// of a simulated poll, then record, reveal and finalize its tally.
+fn reveal_simulated_poll(poll: &SimulatedPoll, submissions: &[(String, Vec<u8>)]) -> Result<RevealedPoll> {
+    info!(submissions = submissions.len(), "Submitting the simulated votes");
+    for (voter, data) in submissions.iter() {
+        poll.chain.submit(voter, Recipient::Votes, data.clone());
+    }
+
+    info!("Recording and revealing the simulated votes");
+    let sources: Vec<Box<dyn VoteSource>> = vec![Box::new(ChainVotes(SIMULATION_NETWORK.to_owned()))];
+    record_votes(SIMULATION_NETWORK, POLL_CONFIGURATION_FILE, &sources, &VoteCodeSet::new(), false, false)?;
+    generate_tally_audit(POLL_CONFIGURATION_FILE, &poll.audited_columns_seed)?;
+    generate_poll_revelations(SIMULATION_NETWORK, POLL_CONFIGURATION_FILE, true, false)?;
+    finalize_tally(SIMULATION_NETWORK, POLL_CONFIGURATION_FILE)?;
+    let finalization_receipt = read_finalization_receipt(SIMULATION_POLL_IDENTIFIER)?
+        .ok_or("Simulated poll was not finalized.")?;
+    let finalized_hash = tally_hash(&fs::read(Path::new(SIMULATION_POLL_IDENTIFIER).join("tally.yaml"))?);
+    let finalized = finalization_receipt.tally_hash == hex::encode(finalized_hash)
+        && posted_transaction(SIMULATION_NETWORK, &finalization_receipt.address, &finalization_receipt.transaction_hash, finalization_receipt.submitted_at_block)?
+            .is_some_and(|transaction| transaction.input == finalized_hash);
+
+    let poll_tally = read_poll_tally(SIMULATION_POLL_IDENTIFIER)?
+        .ok_or("Simulated poll was not revealed.")?;
+    Ok(RevealedPoll { poll_tally, finalized })
+}
+
+fn simulate(configuration: &SimulationConfiguration, seed: &[u8]) -> Result<SimulationReport> {
+    let mut rng = CSPRNG::from_csprng_seed(&CSPRNGSeed::from_vec(&seed.to_vec()));
+    let poll = create_simulated_poll(
+        &simulated_new_poll(configuration.num_ballots, configuration.num_decoys),
+        configuration.num_voters,
+        configuration.num_audited,
+        seed,
+        &mut rng)?;
+
+    // Vote.
+    let votecodes: VoteCodeSet = poll.ballots.iter()
+        .flat_map(|ballot| vec![ballot.choice1.votecode, ballot.choice2.votecode])
+        .collect();
+    let mut expected = PlaneTally::default();
+    let mut submissions: Vec<(String, Vec<u8>)> = Vec::new();
+    let (mut ballots_voted, mut duplicates, mut invalid) = (0, 0, 0);
+    for ballot in poll.ballots.iter().filter(|ballot| !poll.audited.contains(&ballot.serial)) {
+        if !rng.gen_bool(configuration.turnout) {
+            continue;
+        }
+        let choice = if rng.gen_bool(0.5) { &ballot.choice1 } else { &ballot.choice2 };
+        match (poll.decoys.contains(&ballot.serial), choice.choice) {
+            (true, _) => expected.decoy_votes += 1,
+            (false, ChoiceValue::For) => expected.for_votes += 1,
+            (false, ChoiceValue::Against) => expected.against_votes += 1
+        }
+        ballots_voted += 1;
+
+        let voter = simulated_voter(ballot.serial);
+        let payload = VotePayload::vote(&poll.payload_identifier, &choice.votecode).to_binary();
+        if rng.gen_bool(configuration.duplicate_rate) {
+            submissions.push((voter.clone(), payload.clone()));
+            duplicates += 1;
+        }
+        submissions.push((voter.clone(), payload));
+        if rng.gen_bool(configuration.invalid_rate) {
+            let data = match rng.gen_bool(0.5) {
+                true => {
+                    let votecode = generate_votecodes(&CSPRNGSeed::next_seed(&mut rng), 1)[0];
+                    if votecodes.contains(&votecode) {
+                        continue;
+                    }
+                    VotePayload::vote(&poll.payload_identifier, &votecode).to_binary()
+                },
+                false => b"not a vote".to_vec()
+            };
+            submissions.push((voter, data));
+            invalid += 1;
+        }
+    }
+    submissions.shuffle(&mut rng);
+    info!(ballots_voted, "Simulated ballots voted");
+    let revealed = reveal_simulated_poll(&poll, &submissions)?;
+
+    let poll_tally = revealed.poll_tally;
+    let votes_recorded = poll_tally.vote_sources.iter().map(|count| count.votes).sum();
+    let duplicates_recorded = poll_tally.vote_sources.iter().map(|count| count.duplicates).sum();
+    Ok(SimulationReport {
+        seed: hex::encode(seed),
+        ballots_voted,
+        submissions: submissions.len(),
+        duplicates,
+        invalid,
+        matches: poll_tally.consistent
+            && 0 < poll_tally.planes_counted
+            && poll_tally.tally == expected
+            && votes_recorded == ballots_voted
+            && duplicates_recorded == duplicates,
+        expected,
+        tally: poll_tally.tally,
+        planes_counted: poll_tally.planes_counted,
+        votes_recorded,
+        duplicates_recorded,
+        finalized: revealed.finalized
+    })
+}
+
+/// Address a simulated voter submits the votes of a ballot from.
+fn simulated_voter(serial: BallotSerial) -> String {
+    format!("0x{:040x}", serial + 1)
+}
+
+fn simulated_new_poll(num_ballots: usize, num_decoys: usize) -> NewPollConfiguration {
+    NewPollConfiguration {
+        poll_identifier: SIMULATION_POLL_IDENTIFIER.to_owned(),
+        poll_trustees: SIMULATION_TRUSTEES.iter()
+            .map(|&trustee| NewPollConfigurationTrustee { identifier: trustee.to_owned(), approval_key: None })
+            .collect(),
+        num_ballots,
+        num_decoys,
+        serial_ranges: None,
+        roster_schema: None,
+        poll_question: Some("Should the simulated proposal be adopted?".to_owned()),
+        ballot_text: Vec::new(),
+        poll_rules: None,
+        electronic_delivery: false,
+        end_block: None,
+        timezone: None,
+        tally_rules: TallyRules::default(),
+        encrypted_votes: false,
+        undelivered_policy: UndeliveredPolicy::Keep,
+        reveal_at: None
+    }
+}
+
+fn write_roster(num_voters: usize) -> Result<()> {
+    let mut csvwriter = csv::Writer::from_path(ROSTER_FILE)?;
+    for n in 0..num_voters {
+        csvwriter.serialize(VoterRosterFileRow {
+            last_name: format!("Voter{:05}", n + 1),
+            first_name: "Simulated".to_owned(),
+            street_address: format!("{} Simulation Street", n + 1),
+            city: "Simulation".to_owned(),
+            state: "SM".to_owned(),
+            zip_code: format!("{:05}", n % 100_000)
+        })?;
+    }
+    csvwriter.flush()?;
+    Ok(())
+}
@@ -0,0 +1,94 @@
+//! # Command: Approve Commit
+//!
+//! `generate_approval_key` generates the key pair a trustee approves the
+//! commit of a poll with. The trustee keeps the secret key and hands the
+//! verification key to the authority, which lists it with the trustee in
+//! the new poll configuration; trustees listed without one have a key pair
+//! generated for them when the poll is created.
+//!
+//! `approve_commit` signs the commit proposal written by step 4 as one
+//! trustee of the poll, with their secret approval key. Neither needs the
+//! poll configuration, so a trustee can approve the proposal offline. The
+//! approval is written to a file to hand back for step 4.
+
+use std::convert::TryInto;
+use std::fs;
+use zeroize::Zeroizing;
+use super::*;
+use crate::blockchain::{CommitApproval, CommitProposal};
+use crate::cryptography::signing_key_from_seed;
+
+fn trustee_filename(prefix: &str, trustee: &str, extension: &str) -> String {
+    let trustee: String = trustee.chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '.' || c == '-' { c } else { '_' })
+        .collect();
+    format!("{}_{}.{}", prefix, trustee, extension)
+}
+
+/// File the approval of `trustee` is written to by default.
+pub fn commit_approval_filename(trustee: &str) -> String {
+    trustee_filename("commit_approval", trustee, "yaml")
+}
+
+/// File the secret approval key of `trustee` is read from by default, and
+/// written to when generated with the poll.
+pub fn commit_approval_key_filename(trustee: &str) -> String {
+    trustee_filename("commit_approval", trustee, "key")
+}
+
+/// Verification key of a trustee approval key pair.
+#[derive(Debug, Clone, Serialize)]
+pub struct ApprovalKey {
+    pub public_key: String
+}
+
+impl std::fmt::Display for ApprovalKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.public_key)
+    }
+}
+
+/// Generate an approval key pair, writing the base64 encoded secret key to
+/// the new file `secret_key_filename`, readable by its owner only, and
+/// returning the base64 encoded verification key.
+pub fn new_approval_key(secret_key_filename: &str) -> Result<String> {
+    let (signing_key, public_key) = new_signing_key()?;
+    let seed = Zeroizing::new(base64::decode(&signing_key.0)?);
+    write_secret_key_file(secret_key_filename, &seed)?;
+    Ok(public_key.0)
+}
+
+/// Generate the approval key pair of a trustee, printing the verification
+/// key to list in the new poll configuration.
+pub fn generate_approval_key(secret_key_filename: &str) -> Result<()> {
+    let public_key = new_approval_key(secret_key_filename)?;
+    output::emit(&ApprovalKey { public_key })
+}
+
+/// Approve the proposal `proposal_filename` as `trustee` with the secret
+/// approval key of the file `secret_key_filename`,
+/// commit_approval_<TRUSTEE>.key by default.
+pub fn approve_commit(proposal_filename: &str, trustee: &str, secret_key_filename: Option<&str>, approval_filename: Option<&str>) -> Result<()> {
+    // Read the approval key of the trustee.
+    let secret_key_filename = secret_key_filename.map(str::to_owned)
+        .unwrap_or_else(|| commit_approval_key_filename(trustee));
+    let seed = Zeroizing::new(base64::decode(fs::read_to_string(&secret_key_filename)?.trim())?);
+    let seed: [u8; 32] = seed.as_slice().try_into()
+        .map_err(|_| format!("{} is not an approval key.", secret_key_filename))?;
+    let (signing_key, public_key) = signing_key_from_seed(seed)?;
+
+    // Approve the proposal, if it is for this trustee.
+    let proposal: CommitProposal = serde_yaml::from_reader(File::open(proposal_filename)?)?;
+    match proposal.trustee(trustee) {
+        Some(approval_key) if approval_key.public_key == public_key.0 => (),
+        _ => return Err(format!("The commit proposal is not for {} as a trustee of this poll.", trustee).into())
+    }
+    info!("{}", proposal);
+    let approval = CommitApproval::sign(&proposal, trustee, &signing_key)?;
+
+    let approval_filename = approval_filename.map(str::to_owned)
+        .unwrap_or_else(|| commit_approval_filename(trustee));
+    serde_yaml::to_writer(File::create(&approval_filename)?, &approval)?;
+    output::emit(&approval)?;
+    Ok(())
+}
@@ -2,13 +2,14 @@
 //!
 //! `bind_roster` binds a roster file to a secured poll configuration.
 //! The purpose of this binding is to make the voter roster file
-//! immutable without corrupting the entire poll.
+//! immutable without corrupting the entire poll. For polls created with a
+//! roster schema, the roster is validated against it and bound with its
+//! values in canonical form.
 
 use super::*;
 
 
 pub fn bind_roster(pollconf_filename: &str, roster_filename: &str, disable_privacy: bool, force: bool) -> Result<()> {
-    let pollconf_path = Path::new(pollconf_filename);
     let roster_path = Path::new(roster_filename);
 
     // Read poll configuration file.
@@ -17,20 +18,57 @@ pub fn bind_roster(pollconf_filename: &str, roster_filename: &str, disable_priva
     // Reconstruct the Poll Master Key from the trustee passwords.
     let (_poll_master_key, aead_pmk) = read_poll_master_key(&secured_poll_configuration);
 
+    // Read roster file, with the fields of the roster schema of the poll.
+    let roster_schema = {
+        let pollconf_aead_values = secured_poll_configuration.encrypted_poll_configuration.values()?;
+        let serialized_pollconf = aead_decrypt(&aead_pmk, &pollconf_aead_values)?;
+        serde_yaml::from_slice::<PollConfiguration>(&serialized_pollconf)?.roster_schema.clone()
+    };
+    let roster = VoterRoster::from_file_with_schema(&roster_path, roster_schema.as_ref())?;
+    bind_voter_roster(&mut secured_poll_configuration, &aead_pmk, &roster, disable_privacy, force)?;
+
+    // Save the poll configuration.
+    write_poll_configuration_file(pollconf_filename, &secured_poll_configuration)
+}
+
+/// `roster` validated against the roster schema of the poll, in canonical
+/// form.
+pub fn schema_validated_roster(pollconf: &PollConfiguration, roster: &VoterRoster) -> Result<VoterRoster> {
+    match &pollconf.roster_schema {
+        Some(schema) => schema.apply(roster),
+        None if roster.records.iter().any(|record| !record.voter_info.extra_fields.is_empty()) =>
+            Err("The poll has no roster schema for the fields beyond the standard ones.".into()),
+        None => Ok(roster.clone())
+    }
+}
+
+/// Bind a roster to the secured poll configuration, once the Poll Master
+/// Key is known. The configuration is only updated in memory.
+pub fn bind_voter_roster(
+    secured_poll_configuration: &mut SecuredPollConfiguration,
+    aead_pmk: &AEADKey,
+    roster: &VoterRoster,
+    disable_privacy: bool,
+    force: bool) -> Result<()>
+{
     // Decrypt poll configuration state.
     let pollconf_aead_values = secured_poll_configuration.encrypted_poll_configuration.values()?;
-    let serialized_pollconf = aead_decrypt(&aead_pmk, &pollconf_aead_values)?;
+    let serialized_pollconf = aead_decrypt(aead_pmk, &pollconf_aead_values)?;
     let mut pollconf: PollConfiguration = serde_yaml::from_slice(&serialized_pollconf).unwrap();
 
     // TODO: Consider having a separate announcement step.
     pollconf.poll_state.announced = true;
-    assert!(pollconf.poll_state.announced,
-        "Cannot bind voter roster until poll is announced.");
-    assert!(!pollconf.poll_state.roster_committed || force,
-        "Voter roster already bound. To re-bind, pass --force.");
+    if pollconf.poll_state.roster_committed && !force {
+        return Err("Voter roster already bound. To re-bind, pass --force.".into());
+    }
 
-    // Read roster file.
-    let roster = VoterRoster::from_file(&roster_path)?;
+    // A roster bound whole replaces the chunks posted.
+    if !pollconf.roster_chunks.is_empty() {
+        warn!("Roster chunks discarded: {}", pollconf.roster_chunks.len());
+        pollconf.roster_chunks.clear();
+    }
+
+    let roster = schema_validated_roster(&pollconf, roster)?;
     let serialized_roster = serde_yaml::to_string(&roster)?;
     let roster64 = base64::encode(&serialized_roster);
     // Bind the roster.
@@ -39,18 +77,11 @@ pub fn bind_roster(pollconf_filename: &str, roster_filename: &str, disable_priva
     pollconf.voter_privacy = !disable_privacy;
     pollconf.poll_state.roster_committed = true;
     // Re-encrypt the poll configuration.
-    let serialized_pollconf = serde_yaml::to_string(&pollconf)?;
+    let serialized_pollconf = Zeroizing::new(serde_yaml::to_string(&pollconf)?);
     let secure_serialized_pollconf = AEADString::from_values(
-        aead_encrypt(&aead_pmk,
+        aead_encrypt(aead_pmk,
                      Vec::new(),
                      serialized_pollconf.as_bytes().to_vec())?);
-    // Save the poll configuration.
     secured_poll_configuration.encrypted_poll_configuration = secure_serialized_pollconf;
-    serde_yaml::to_writer(
-        File::create(pollconf_path)?,
-        &secured_poll_configuration)?;
-
     Ok(())
 }
-
-
@@ -0,0 +1,94 @@
+//! # Command: Bulletin Board
+//!
+//! `publish_board` copies the public artifacts of a poll into a board
+//! directory with a signed manifest, `verify_board` checks a board directory
+//! against its manifest and `board_report` writes the audit report of a
+//! board directory. `recount_board` recounts the tally of a board directory
+//! from its final planes alone. With the `server` feature, `serve_board` serves a
+//! board directory over HTTP and `mirror_board` downloads a served board.
+
+use std::fs;
+use super::*;
+use crate::bulletin_board;
+
+pub fn publish_board(pollconf_filename: &str, board_dirname: &str, merkle_tree: &str) -> Result<()> {
+    // Read poll configuration file.
+    let secured_poll_configuration = read_poll_configuration_file(pollconf_filename)?;
+
+    // Reconstruct the Poll Master Key from the trustee passwords.
+    let (_, aead_pmk) = read_poll_master_key(&secured_poll_configuration);
+
+    // Ensure the data directory exists.
+    let datadir_path = ensure_poll_data_directory_exists(&secured_poll_configuration, &aead_pmk)?;
+
+    // Decrypt poll configuration state.
+    let pollconf_aead_values = secured_poll_configuration.encrypted_poll_configuration.values()?;
+    let serialized_pollconf = aead_decrypt(&aead_pmk, &pollconf_aead_values)?;
+    let pollconf: PollConfiguration = serde_yaml::from_slice(&serialized_pollconf).unwrap();
+
+    // Authenticate the verification key.
+    let public_key = use_poll_verification_key(&secured_poll_configuration, &aead_pmk)?;
+
+    let manifest = bulletin_board::publish_board_directory(
+        &datadir_path,
+        Path::new(&datadir_path),
+        Path::new(merkle_tree),
+        Path::new(board_dirname),
+        &pollconf.signing_key,
+        &public_key)?;
+    output::emit(&manifest)
+}
+
+pub fn verify_board(board_dirname: &str, public_key: Option<&str>) -> Result<()> {
+    let verification = bulletin_board::verify_board_directory(Path::new(board_dirname), public_key)?;
+    output::emit(&verification)?;
+    match verification.valid() {
+        true => Ok(()),
+        false => Err("Bulletin board does not match its manifest.".into())
+    }
+}
+
+/// Write the audit report of a board directory to `html_filename` and
+/// `pdf_filename`, if given.
+pub fn board_report(board_dirname: &str, public_key: Option<&str>, html_filename: Option<&str>, pdf_filename: Option<&str>) -> Result<()> {
+    let report = bulletin_board::AuditReport::from_board(Path::new(board_dirname), public_key)?;
+    if let Some(html_filename) = html_filename {
+        fs::write(html_filename, report.to_html())?;
+    }
+    if let Some(pdf_filename) = pdf_filename {
+        report.write_pdf(Path::new(pdf_filename))?;
+    }
+    output::emit(&report)?;
+    match report.passed() {
+        true => Ok(()),
+        false => Err("The audit of the poll found anomalies.".into())
+    }
+}
+
+pub fn recount_board(board_dirname: &str) -> Result<()> {
+    let recount = bulletin_board::PublicRecount::from_board(Path::new(board_dirname))?;
+    output::emit(&recount)?;
+    match recount.agrees() {
+        true => Ok(()),
+        false => Err("The recount of the final planes does not agree with the tally report.".into())
+    }
+}
+
+#[cfg(feature = "server")]
+pub async fn serve_board(board_dirname: &str, listen: &str) -> Result<()> {
+    let address: std::net::SocketAddr = listen.parse()?;
+    if !Path::new(board_dirname).join(bulletin_board::MANIFEST_FILENAME).exists() {
+        return Err(format!("No bulletin board manifest in {}", board_dirname).into());
+    }
+    bulletin_board::serve_board(Path::new(board_dirname), address).await
+}
+
+#[cfg(feature = "server")]
+pub async fn mirror_board(url: &str, board_dirname: &str, public_key: Option<&str>) -> Result<()> {
+    let verification = bulletin_board::mirror_board(url, Path::new(board_dirname), public_key).await?;
+    output::emit(&verification)?;
+    match verification.valid() {
+        true => Ok(()),
+        false => Err("Mirrored bulletin board does not match its manifest.".into())
+    }
+}
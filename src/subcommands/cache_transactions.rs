@@ -0,0 +1,64 @@
+//! # Command: Cache Transactions
+//!
+//! `cache_transactions` writes the transactions sent to the vote address
+//! since the poll was committed to a JSON file, from which the votes can
+//! be recounted offline, for instance from the Python bindings. Only the
+//! public commit receipt is needed, not the trustee passwords, unless the
+//! cache is signed with the poll signing key.
+
+use std::fmt;
+use super::*;
+use crate::blockchain::CommitReceipt;
+use crate::votes::fetch_cached_transactions;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CachedTransactions {
+    pub from_block: u64,
+    pub transactions: usize,
+    pub output: String
+}
+
+impl fmt::Display for CachedTransactions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Cached {} transactions since block {} to {}",
+            self.transactions, self.from_block, self.output)
+    }
+}
+
+pub fn cache_transactions(network: &str, receipt: Option<&str>, from_block: Option<u64>, output_filename: &str, pollconf_filename: Option<&str>) -> Result<()> {
+    let commit_receipt: Option<CommitReceipt> = match receipt {
+        Some(receipt) => Some(serde_yaml::from_reader(File::open(receipt)?)?),
+        None => None
+    };
+    let from_block = match (from_block, &commit_receipt) {
+        (Some(from_block), _) => from_block,
+        (None, Some(commit_receipt)) => commit_receipt.submitted_at_block,
+        (None, None) => return Err("Either a commit receipt or a block is required.".into())
+    };
+    let vote_address = commit_receipt.as_ref().and_then(|commit_receipt| commit_receipt.vote_address.clone());
+    let end_block = commit_receipt.as_ref().and_then(|commit_receipt| commit_receipt.end_block);
+
+    let transactions = fetch_cached_transactions(network, vote_address.as_deref(), from_block, end_block)?;
+    serde_json::to_writer_pretty(File::create(output_filename)?, &transactions)?;
+
+    // Sign the cache, so that it is checked when the votes are recorded.
+    if let Some(pollconf_filename) = pollconf_filename {
+        sign_transaction_cache(pollconf_filename, output_filename)?;
+    }
+
+    output::emit(&CachedTransactions {
+        from_block,
+        transactions: transactions.len(),
+        output: output_filename.to_owned()
+    })
+}
+
+/// Sign a transaction cache with the poll signing key.
+pub fn sign_transaction_cache(pollconf_filename: &str, cache_filename: &str) -> Result<()> {
+    let secured_poll_configuration = read_poll_configuration_file(pollconf_filename)?;
+    let (_, aead_pmk) = read_poll_master_key(&secured_poll_configuration);
+    let pollconf_aead_values = secured_poll_configuration.encrypted_poll_configuration.values()?;
+    let serialized_pollconf = aead_decrypt(&aead_pmk, &pollconf_aead_values)?;
+    let pollconf: PollConfiguration = serde_yaml::from_slice(&serialized_pollconf).unwrap();
+    sign_file(&pollconf.signing_key, Path::new(cache_filename))
+}
@@ -0,0 +1,68 @@
+//! # Command: Check Vote Codes
+//!
+//! `check_votecodes` scans a ballot information file, as written by step 3,
+//! for vote codes shared by more than one ballot choice, across all the
+//! ballots and both choices, and for vote codes that are malformed. It
+//! reads only the file, so that the printer can run it before printing.
+
+use std::fmt;
+use super::*;
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct VoteCodeCheck {
+    pub ballots: usize,
+    pub collisions: Vec<VoteCodeCollision>,
+    /// Choices whose vote code is not a vote code, with its parity digits.
+    pub malformed: Vec<ChoiceLocation>
+}
+
+impl VoteCodeCheck {
+    pub fn valid(&self) -> bool {
+        self.collisions.is_empty() && self.malformed.is_empty()
+    }
+}
+
+impl fmt::Display for VoteCodeCheck {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Ballots checked:      {}", self.ballots)?;
+        writeln!(f, "Shared vote codes:    {}", self.collisions.len())?;
+        for collision in self.collisions.iter() {
+            writeln!(f, "  {}", collision)?;
+        }
+        writeln!(f, "Malformed vote codes: {}", self.malformed.len())?;
+        for choice in self.malformed.iter() {
+            writeln!(f, "  {}", choice)?;
+        }
+        write!(f, "Vote codes unique:    {}", if self.valid() { "yes" } else { "NO" })
+    }
+}
+
+/// Check the vote codes of the rows of a ballot information file.
+pub fn check_ballot_votecodes(rows: &[CompleteBallotRow]) -> VoteCodeCheck {
+    let mut check = VoteCodeCheck { ballots: rows.len(), ..Default::default() };
+    let mut choices: Vec<(ChoiceLocation, VoteCode)> = Vec::with_capacity(2 * rows.len());
+    for row in rows.iter() {
+        let row_choices = [(&row.choice1_votecode, &row.choice1_value), (&row.choice2_votecode, &row.choice2_value)];
+        for (votecode, value) in row_choices.iter() {
+            let location = ChoiceLocation { serial: row.serial.clone(), choice: value.to_string() };
+            match votecode_from_string(votecode) {
+                Some(votecode) => choices.push((location, votecode)),
+                None => check.malformed.push(location)
+            }
+        }
+    }
+    check.collisions = find_votecode_collisions(&choices);
+    check
+}
+
+pub fn check_votecodes(ballots_filename: &str) -> Result<()> {
+    let mut csvreader = csv::Reader::from_path(ballots_filename)?;
+    let rows = csvreader.deserialize::<CompleteBallotRow>()
+        .collect::<std::result::Result<Vec<CompleteBallotRow>, csv::Error>>()?;
+    let check = check_ballot_votecodes(&rows);
+    output::emit(&check)?;
+    match check.valid() {
+        true => Ok(()),
+        false => Err("Ballot information file has shared or malformed vote codes.".into())
+    }
+}
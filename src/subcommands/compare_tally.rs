@@ -0,0 +1,35 @@
+//! # Command: Compare Tally
+//!
+//! `compare_tally` compares two recounts of a poll, such as the official
+//! one and an independent auditor's, and reports the counts that differ,
+//! the transactions only one of them counted and the transactions they
+//! decoded to different vote codes. Either recount can be given as the
+//! JSON of a recount, as returned by the Python and JavaScript bindings, or
+//! as a file written by `cache-transactions`, which is recounted for the
+//! poll.
+
+use seventh_estate_core::{Recount, RecountDiff};
+use super::*;
+use crate::votes::CachedTransaction;
+
+fn read_recount(filename: &str, poll_identifier: Option<&str>) -> Result<Recount> {
+    let value: serde_json::Value = serde_json::from_slice(&std::fs::read(filename)?)?;
+    if !value.is_array() {
+        return Ok(serde_json::from_value(value)?);
+    }
+    let poll_identifier = poll_identifier
+        .ok_or_else(|| format!("{} holds cached transactions; the poll identifier is required.", filename))?;
+    let transactions: Vec<CachedTransaction> = serde_json::from_value(value)?;
+    Ok(Recount::from_transactions(poll_identifier, &transactions))
+}
+
+pub fn compare_tally(official_filename: &str, independent_filename: &str, poll_identifier: Option<&str>) -> Result<()> {
+    let official = read_recount(official_filename, poll_identifier)?;
+    let independent = read_recount(independent_filename, poll_identifier)?;
+    let diff = RecountDiff::between(&official, &independent)?;
+    output::emit(&diff)?;
+    match diff.is_empty() {
+        true => Ok(()),
+        false => Err("Recounts disagree.".into())
+    }
+}
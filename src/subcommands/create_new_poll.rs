@@ -2,11 +2,39 @@
 //!
 //! `create_new_poll` generates a secured poll configuration
 //! from a new poll configuration file.
+//!
+//! The vote codes, decoys, summands and planes of the poll are derived
+//! from a generation seed recorded in the encrypted poll configuration.
+//! An authority that keeps the seed offline can regenerate identical
+//! ballots for a new poll after losing the configuration. The seed is read
+//! from a file, or from standard input, rather than the command line, so
+//! that it stays out of the process list and the shell history.
+//!
+//! With a reveal time, the key the votes are encrypted to is time-locked
+//! until then.
+//!
+//! The verification keys the trustees approve the commit of the poll with
+//! are published in the data directory of the poll. A trustee listed
+//! without one has an approval key pair generated, its secret key written
+//! to `commit_approval_<TRUSTEE>.key` to hand over to them.
 
+use std::io::Read;
 use super::*;
+use crate::blockchain::{TrusteeApprovalKey, TRUSTEE_APPROVAL_KEYS_FILENAME};
 
+/// Hexadecimal generation seed read from `seed_filename`, or from standard
+/// input when `-`.
+pub fn read_generation_seed(seed_filename: &str) -> Result<String> {
+    let mut seed = String::new();
+    match seed_filename {
+        "-" => { std::io::stdin().read_to_string(&mut seed)?; },
+        _ => { File::open(seed_filename)?.read_to_string(&mut seed)?; }
+    }
+    Ok(seed.trim().to_owned())
+}
 
-pub fn create_new_poll(pollconf_filename: &str) -> Result<()> {
+/// Create the poll with the hexadecimal `generation_seed`, or a random one.
+pub fn create_new_poll(pollconf_filename: &str, generation_seed: Option<&str>) -> Result<()> {
     let pollconf_path = Path::new(pollconf_filename);
     let securepollconf_filename = pollconf_filename.to_owned() + ".secure";
     let securepollconf_path = Path::new(&securepollconf_filename);
@@ -17,6 +45,33 @@ pub fn create_new_poll(pollconf_filename: &str) -> Result<()> {
         serde_yaml::from_reader(pollconf_file)?
     };
 
+    validate_localized_text(&new_poll_configuration.ballot_text)?;
+    if let Some(timezone) = &new_poll_configuration.timezone {
+        parse_utc_offset(timezone)?;
+    }
+    if let Some(serial_ranges) = &new_poll_configuration.serial_ranges {
+        serial_ranges.validate(new_poll_configuration.num_ballots)?;
+    }
+    if let Some(roster_schema) = &new_poll_configuration.roster_schema {
+        roster_schema.validate()?;
+    }
+
+    // Bind the generation seed.
+    let generation_seed: String = match generation_seed {
+        Some(seed) => {
+            let seed: Vec<u8> = hex::decode(seed)?;
+            if seed.len() != CSPRNGSeed::SIZE {
+                return Err(format!("Generation seed must be {} bytes long.", CSPRNGSeed::SIZE).into());
+            }
+            hex::encode(seed)
+        },
+        None => {
+            let mut seed = [0u8; CSPRNGSeed::SIZE];
+            getrandom::getrandom(&mut seed)?;
+            hex::encode(seed)
+        }
+    };
+
     // Generate Master Key and Shares.
     let num_trustees: usize = new_poll_configuration.poll_trustees.len();
     let poll_master_key = PollMasterKey::new();
@@ -24,38 +79,84 @@ pub fn create_new_poll(pollconf_filename: &str) -> Result<()> {
     let poll_master_key_shares = poll_master_key.share(num_trustees);
 
     // Secure shares with trustee passwords.
-    let secure_key_shares = new_poll_configuration.poll_trustees.iter()
+    let trustee_shares = new_poll_configuration.poll_trustees.iter()
         .zip(poll_master_key_shares.iter())
-        .map(|(trustee, share)| {
-            let trustee_share = TrusteeShare::new(
-                trustee.identifier.clone(),
-                share.0.clone());
-            trustee_share.secure()
-        })
+        .map(|(trustee, share)| TrusteeShare::new(
+            trustee.identifier.clone(),
+            share.0.clone()))
+        .collect::<Vec<TrusteeShare>>();
+    let secure_key_shares = trustee_shares.iter()
+        .map(|trustee_share| trustee_share.secure())
         .map(|trustee_share| PollConfigurationTrustee {
             identifier: trustee_share.identifier.clone(),
             share: trustee_share.secure_value.clone()
         })
         .collect::<Vec<PollConfigurationTrustee>>();
 
+    // Keys the trustees approve the commit with, independent of the shares.
+    let trustee_approval_keys = new_poll_configuration.poll_trustees.iter()
+        .map(|trustee| Ok(TrusteeApprovalKey {
+            identifier: trustee.identifier.clone(),
+            public_key: match &trustee.approval_key {
+                Some(public_key) => public_key.clone(),
+                None => new_approval_key(&commit_approval_key_filename(&trustee.identifier))?
+            }
+        }))
+        .collect::<Result<Vec<TrusteeApprovalKey>>>()?;
+
+    // Lock the vote decryption key until the reveal time; the factors
+    // of the puzzle are discarded.
+    let time_lock = match new_poll_configuration.reveal_at {
+        Some(reveal_at) => {
+            let rate = squarings_per_second(TIME_LOCK_MODULUS_BITS)?;
+            info!("Locking votes until {} at {} squarings per second", reveal_at, rate);
+            Some(TimeLockedKey::new(reveal_at, rate, TIME_LOCK_MODULUS_BITS)?)
+        },
+        None => None
+    };
+
     // Generate signing key/certificate.
     let (private_key, public_key): (Base64String, Base64String) = new_signing_key()?;
 
     // Create new poll configuration file
     let pollconf = PollConfiguration {
+        poll_uuid: Some(PollUuid::generate()),
         poll_state: PollState::new(),
         signing_key: private_key,
         num_ballots: new_poll_configuration.num_ballots,
         num_decoys: new_poll_configuration.num_decoys,
+        serial_ranges: new_poll_configuration.serial_ranges.clone(),
         voter_roster: None,
         voter_roster_size: 0,
+        roster_schema: new_poll_configuration.roster_schema.clone(),
+        roster_chunks: Vec::new(),
         voter_privacy: true,
+        roster_policy: None,
+        generation_seed: Some(generation_seed),
         drawn_summands_seed: None,
         audited_columns_seed: None,
         audited_ballots: None,
-        votes: None
+        votes: None,
+        vote_sources: Vec::new(),
+        poll_question: new_poll_configuration.poll_question.clone(),
+        ballot_text: new_poll_configuration.ballot_text.clone(),
+        poll_rules: new_poll_configuration.poll_rules.clone(),
+        electronic_delivery: new_poll_configuration.electronic_delivery,
+        end_block: new_poll_configuration.end_block,
+        timezone: new_poll_configuration.timezone.clone(),
+        ballot_amendments: Vec::new(),
+        undelivered_ballots: Vec::new(),
+        undelivered_policy: new_poll_configuration.undelivered_policy,
+        provisional_ballots: Vec::new(),
+        provisional_votes: Vec::new(),
+        vote_records: Vec::new(),
+        test_votes: 0,
+        tally_rules: new_poll_configuration.tally_rules.clone(),
+        uncounted_ballot_votes: 0,
+        encrypted_votes: new_poll_configuration.encrypted_votes,
+        time_lock
     };
-    let serialized_pollconf = serde_yaml::to_string(&pollconf)?;
+    let serialized_pollconf = Zeroizing::new(serde_yaml::to_string(&pollconf)?);
     //debug!("{}\n", serialized_pollconf);
 
     // Encrypt the properties needed for the secure file.
@@ -82,6 +183,21 @@ pub fn create_new_poll(pollconf_filename: &str) -> Result<()> {
         File::create(securepollconf_path)?,
         &secure_poll_configuration)?;
 
+    // Publish the approval keys of the trustees.
+    let datadir_path = Path::new(&new_poll_configuration.poll_identifier);
+    std::fs::create_dir_all(datadir_path)?;
+    serde_yaml::to_writer(
+        File::create(datadir_path.join(TRUSTEE_APPROVAL_KEYS_FILENAME))?,
+        &trustee_approval_keys)?;
+
+    // Publish the time-lock puzzle, so that solving starts without the
+    // trustees.
+    if let Some(time_lock) = &pollconf.time_lock {
+        serde_yaml::to_writer(
+            File::create(datadir_path.join(TIME_LOCK_FILENAME))?,
+            time_lock)?;
+    }
+
     Ok(())
 }
 
@@ -0,0 +1,112 @@
+//! # Command: Escrow Ballot Map
+//!
+//! `escrow_ballot_map` seals the map of every vote code of a poll to its
+//! ballot and choice to the escrow keys of a configuration file. The escrow
+//! file is written to the data directory, to be handed to the holders and
+//! removed from the systems of the authority, and the commitment to it is
+//! written next to it for publication on the bulletin board. The escrow is
+//! recorded in the audit log of the poll.
+//!
+//! `generate_escrow_key` generates the key pair of an escrow holder, and
+//! `open_ballot_map_escrow` opens an escrow file with the keys of a quorum
+//! of holders. Neither needs the poll configuration.
+
+use std::fmt;
+use std::fs;
+use super::*;
+use crate::audit_log::{record_audit_event, AuditLogEvent};
+use crate::bulletin_board::sha256_hex;
+use crate::cryptography::VoteDecryptionKey;
+
+pub fn escrow_ballot_map(pollconf_filename: &str, escrow_keys_filename: &str) -> Result<()> {
+    let configuration = EscrowConfiguration::from_file(&escrow_keys_filename)?;
+
+    // Read poll configuration file.
+    let secured_poll_configuration = read_poll_configuration_file(pollconf_filename)?;
+
+    // Reconstruct the Poll Master Key from the trustee passwords.
+    let (poll_master_key, aead_pmk) = read_poll_master_key(&secured_poll_configuration);
+
+    // Ensure the data directory exists.
+    let datadir_path = ensure_poll_data_directory_exists(&secured_poll_configuration, &aead_pmk)?;
+
+    // Decrypt poll configuration state.
+    let pollconf_aead_values = secured_poll_configuration.encrypted_poll_configuration.values()?;
+    let serialized_pollconf = aead_decrypt(&aead_pmk, &pollconf_aead_values)?;
+    let pollconf: PollConfiguration = serde_yaml::from_slice(&serialized_pollconf).unwrap();
+
+    // Derive the poll secrets and generate the ballots.
+    let poll_secrets: PollSecrets = PollSecrets::for_poll(&poll_master_key, pollconf.generation_seed.as_deref())?;
+    let ballots = generate_poll_ballots(&poll_secrets, pollconf.num_ballots);
+    let map = ballot_map(&ballots, pollconf.num_ballots);
+
+    // Seal the map and commit to it.
+    let escrow = BallotMapEscrow::seal(&datadir_path, &map, &configuration)?;
+    let escrow_data = serde_yaml::to_string(&escrow)?;
+    let escrow_path = Path::new(&datadir_path).join(BALLOT_MAP_ESCROW_FILENAME);
+    fs::write(&escrow_path, &escrow_data)?;
+    let commitment = escrow.commitment(&sha256_hex(escrow_data.as_bytes()));
+    serde_yaml::to_writer(File::create(Path::new(&datadir_path).join(BALLOT_MAP_COMMITMENT_FILENAME))?, &commitment)?;
+
+    record_audit_event(&datadir_path, AuditLogEvent::BallotMapEscrow {
+        file: escrow_path.to_string_lossy().into_owned(),
+        holders: commitment.holders.len(),
+        quorum: commitment.quorum,
+        map_sha256: commitment.map_sha256.clone(),
+        sha256: commitment.escrow_sha256.clone()
+    })?;
+    info!("Hand {} to the escrow holders and remove it from this system", escrow_path.display());
+    output::emit(&commitment)
+}
+
+/// Generate the escrow key pair of `holder`, writing the base64 encoded
+/// secret key to the new file `secret_key_filename`, readable by its owner
+/// only.
+pub fn generate_escrow_key(holder: &str, secret_key_filename: &str) -> Result<()> {
+    let key = VoteDecryptionKey::generate();
+    write_secret_key_file(secret_key_filename, key.secret_key())?;
+    output::emit(&EscrowHolder { holder: holder.to_owned(), public_key: key.encryption_key().to_base64() })
+}
+
+/// Ballot map opened from its escrow.
+#[derive(Debug, Clone, Serialize)]
+pub struct OpenedBallotMap {
+    pub poll_identifier: String,
+    pub entries: usize,
+    pub map_sha256: String,
+    pub file: String
+}
+
+impl fmt::Display for OpenedBallotMap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Poll:       {}", self.poll_identifier)?;
+        writeln!(f, "Ballot map: {} ({} vote codes)", self.map_sha256, self.entries)?;
+        write!(f, "Written to: {}", self.file)
+    }
+}
+
+/// Open `escrow_filename` with the secret escrow keys of the files
+/// `secret_key_filenames`, writing the ballot map to `out` as CSV.
+pub fn open_ballot_map_escrow(escrow_filename: &str, secret_key_filenames: &[String], out: &str) -> Result<()> {
+    let escrow: BallotMapEscrow = serde_yaml::from_reader(File::open(escrow_filename)?)?;
+    let keys = secret_key_filenames.iter()
+        .map(|filename| {
+            let secret_key = base64::decode(fs::read_to_string(filename)?.trim())?;
+            VoteDecryptionKey::from_secret_key(&secret_key)
+                .ok_or_else(|| format!("{} is not an escrow key.", filename).into())
+        })
+        .collect::<Result<Vec<VoteDecryptionKey>>>()?;
+    let map = escrow.open(&keys)?;
+
+    let mut csvwriter = csv::Writer::from_path(out)?;
+    for entry in map.iter() {
+        csvwriter.serialize(entry)?;
+    }
+    csvwriter.flush()?;
+    output::emit(&OpenedBallotMap {
+        poll_identifier: escrow.poll_identifier.clone(),
+        entries: map.len(),
+        map_sha256: escrow.map_sha256.clone(),
+        file: out.to_owned()
+    })
+}
@@ -0,0 +1,59 @@
+//! # Command: Export Ballots
+//!
+//! `export_ballots` writes the ballots of a poll to a CSV or JSON file,
+//! redacted by a `RedactionProfile` for whoever receives them: sealed
+//! escrow, the print vendor or public audit. The voter data of the full
+//! export needs the summands to be drawn, to select the voters. The
+//! SHA-256 digest of every export is recorded in the audit log of the poll.
+
+use std::fs;
+use super::*;
+use crate::audit_log::{record_audit_event, AuditLogEvent};
+use crate::bulletin_board::sha256_hex;
+
+pub fn export_ballots(pollconf_filename: &str, profile: RedactionProfile, format: BallotExportFormat, out: &str) -> Result<()> {
+    // Read poll configuration file.
+    let secured_poll_configuration = read_poll_configuration_file(pollconf_filename)?;
+
+    // Reconstruct the Poll Master Key from the trustee passwords.
+    let (poll_master_key, aead_pmk) = read_poll_master_key(&secured_poll_configuration);
+
+    // Ensure the data directory exists.
+    let datadir_path = ensure_poll_data_directory_exists(&secured_poll_configuration, &aead_pmk)?;
+
+    // Decrypt poll configuration state.
+    let pollconf_aead_values = secured_poll_configuration.encrypted_poll_configuration.values()?;
+    let serialized_pollconf = aead_decrypt(&aead_pmk, &pollconf_aead_values)?;
+    let pollconf: PollConfiguration = serde_yaml::from_slice(&serialized_pollconf).unwrap();
+
+    // Derive the poll secrets and generate the ballots.
+    let poll_secrets: PollSecrets = PollSecrets::for_poll(&poll_master_key, pollconf.generation_seed.as_deref())?;
+    let ballots = generate_poll_ballots(&poll_secrets, pollconf.num_ballots);
+
+    // Select the voters the ballots are mailed to.
+    let voters: Vec<VoterInfo> = match profile {
+        RedactionProfile::Full => {
+            if !pollconf.poll_state.summands_drawn {
+                return Err("Summands must be drawn to export the voters of the ballots.".into());
+            }
+            let roster = read_voter_roster(&pollconf)?;
+            select_poll_voters(&pollconf, &poll_secrets)?.iter()
+                .map(|&n| roster.records[n].voter_info.clone())
+                .collect()
+        },
+        _ => Vec::new()
+    };
+
+    let export = BallotExport::new(profile, &ballots, pollconf.num_ballots, &voters, &poll_master_key.0);
+    let data = export.to_bytes(format)?;
+    fs::write(out, &data)?;
+
+    let entry = record_audit_event(&datadir_path, AuditLogEvent::BallotExport {
+        profile,
+        format,
+        file: out.to_owned(),
+        ballots: export.ballots.len(),
+        sha256: sha256_hex(&data)
+    })?;
+    output::emit(&entry)
+}
@@ -0,0 +1,103 @@
+//! # Command: Finalize Tally
+//!
+//! `finalize_tally` posts the hash of the signed tally report of step 8 to
+//! the blockchain as the closing commitment of the poll and writes the
+//! finalization receipt next to the commit receipt. `verify_tally` checks
+//! a published tally report against a finalization receipt and the
+//! transaction it names, so that a revised result is detected. Only the
+//! public report and receipt are needed to verify, not the trustee
+//! passwords.
+
+use std::fmt;
+use std::fs;
+use super::*;
+use crate::blockchain::{record_sent_transaction, tally_hash, FinalizationReceipt, SpendPhase};
+use crate::blockchain::blocking::{post_finalization, posted_transaction};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TallyVerification {
+    pub poll_identifier: String,
+    pub tally_hash: String,
+    /// Whether the report has the hash of the receipt.
+    pub matches_receipt: bool,
+    /// Block of the finalization transaction, if it was found on chain.
+    pub block_number: Option<u64>,
+    /// Whether the finalization transaction carries the hash of the report.
+    pub matches_chain: bool
+}
+
+impl TallyVerification {
+    pub fn valid(&self) -> bool {
+        self.matches_receipt && self.matches_chain
+    }
+}
+
+impl fmt::Display for TallyVerification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn yes_no(value: bool) -> &'static str { if value { "yes" } else { "NO" } }
+        writeln!(f, "Poll:            {}", self.poll_identifier)?;
+        writeln!(f, "Tally hash:      {}", self.tally_hash)?;
+        writeln!(f, "Matches receipt: {}", yes_no(self.matches_receipt))?;
+        match self.block_number {
+            Some(block) => writeln!(f, "Posted on chain: yes (block {})", block)?,
+            None => writeln!(f, "Posted on chain: NO")?
+        }
+        write!(f, "Matches chain:   {}", yes_no(self.matches_chain))
+    }
+}
+
+pub fn finalize_tally(network: &str, pollconf_filename: &str) -> Result<()> {
+    // Read poll configuration file.
+    let secured_poll_configuration = read_poll_configuration_file(pollconf_filename)?;
+
+    // Reconstruct the Poll Master Key from the trustee passwords.
+    let (_, aead_pmk) = read_poll_master_key(&secured_poll_configuration);
+
+    // Ensure the data directory exists.
+    let datadir_path = ensure_poll_data_directory_exists(&secured_poll_configuration, &aead_pmk)?;
+
+    // Decrypt poll configuration state.
+    let pollconf_aead_values = secured_poll_configuration.encrypted_poll_configuration.values()?;
+    let serialized_pollconf = aead_decrypt(&aead_pmk, &pollconf_aead_values)?;
+    let pollconf: PollConfiguration = serde_yaml::from_slice(&serialized_pollconf).unwrap();
+
+    if !pollconf.poll_state.columns_revealed {
+        return Err("The tally must be revealed (step 8) before it is finalized.".into());
+    }
+    if read_finalization_receipt(&datadir_path)?.is_some() {
+        return Err("The tally was already finalized: a revised tally would not match the posted hash.".into());
+    }
+
+    // Only the signed report is finalized.
+    let public_key = use_poll_verification_key(&secured_poll_configuration, &aead_pmk)?;
+    let tally_path = Path::new(&datadir_path).join("tally.yaml");
+    verify_file(&public_key, &tally_path)?;
+    let tally = fs::read(&tally_path)?;
+
+    // Post the hash of the report and keep the receipt.
+    let receipt = post_finalization(network, &datadir_path, &tally)?;
+    record_sent_transaction(&datadir_path, SpendPhase::Finalization, &receipt.transaction_hash, &receipt.address)?;
+    serde_yaml::to_writer(
+        File::create(Path::new(&datadir_path).join("finalization_receipt.yaml"))?,
+        &receipt)?;
+    output::emit(&receipt)
+}
+
+pub fn verify_tally(network: &str, tally_filename: &str, receipt_filename: &str) -> Result<()> {
+    let receipt: FinalizationReceipt = serde_yaml::from_reader(File::open(receipt_filename)?)?;
+    let hash = hex::encode(tally_hash(&fs::read(tally_filename)?));
+
+    let transaction = posted_transaction(network, &receipt.address, &receipt.transaction_hash, receipt.submitted_at_block)?;
+    let verification = TallyVerification {
+        poll_identifier: receipt.poll_identifier.clone(),
+        matches_receipt: hash == receipt.tally_hash,
+        block_number: transaction.as_ref().map(|transaction| transaction.block_number),
+        matches_chain: transaction.is_some_and(|transaction| hex::encode(&transaction.input) == hash),
+        tally_hash: hash
+    };
+    output::emit(&verification)?;
+    match verification.valid() {
+        true => Ok(()),
+        false => Err("Tally report does not match its finalization.".into())
+    }
+}
@@ -47,7 +47,7 @@ pub fn generate_drawn_summands(pollconf_filename: &str, seed: &str, force: bool)
         pathbuf.into_boxed_path()
     };
     let drawn_summands = DrawnSummands::from_csprng(
-        CSPRNGSeed::from_vec(&drawn_summands_seed),
+        &CSPRNGSeed::from_vec(&drawn_summands_seed),
         pollconf.num_ballots,
         pollconf.voter_roster_size);
     debug!("{:#?}", drawn_summands);
@@ -58,7 +58,7 @@ pub fn generate_drawn_summands(pollconf_filename: &str, seed: &str, force: bool)
     // Update the poll state.
     pollconf.poll_state.summands_drawn = true;
     // Re-encrypt the poll configuration.
-    let serialized_pollconf = serde_yaml::to_string(&pollconf)?;
+    let serialized_pollconf = Zeroizing::new(serde_yaml::to_string(&pollconf)?);
     let secure_serialized_pollconf = AEADString::from_values(
         aead_encrypt(&aead_pmk,
                      Vec::new(),
@@ -31,7 +31,7 @@ pub fn generate_poll_commitments(pollconf_filename: &str, force: bool) -> Result
         "Columns already committed. To re-commit, pass --force.");
 
     // Derive the poll secrets.
-    let poll_secrets: PollSecrets = PollSecrets::derive(&poll_master_key);
+    let poll_secrets: PollSecrets = PollSecrets::for_poll(&poll_master_key, pollconf.generation_seed.as_deref())?;
     debug!("{:?}", poll_secrets);
 
     // Commit the Roster.
@@ -50,11 +50,17 @@ pub fn generate_poll_commitments(pollconf_filename: &str, force: bool) -> Result
             serde_yaml::from_str(serialized_roster)?
         };
 
-        // TODO: Implement voter privacy.
-        if pollconf.voter_privacy {
-            full_roster.restricted()
-        } else {
-            full_roster.restricted()
+        // Commit only the fields published by the roster policy.
+        match pollconf.roster_policy() {
+            Some(policy) => {
+                let check = policy.check(&full_roster);
+                if !check.valid() {
+                    warn!("Published roster fields could identify voters: {} voters are in groups of fewer than {}",
+                        check.voters_at_risk, check.min_anonymity);
+                }
+                full_roster.restricted_with(policy)?
+            },
+            None => full_roster.restricted()
         }
     };
     committed_roster.to_file(&committed_roster_path)?;
@@ -68,7 +74,7 @@ pub fn generate_poll_commitments(pollconf_filename: &str, force: bool) -> Result
         pathbuf.into_boxed_path()
     };
     let committed_summands = CommittedSummands::from_csprng(
-        poll_secrets.summands_root,
+        &poll_secrets.summands_root,
         pollconf.num_ballots,
         pollconf.voter_roster_size);
     let summands_commitment: SecuredFile = committed_summands.aead_commit(&poll_secrets.summands_key)?;
@@ -82,27 +88,18 @@ pub fn generate_poll_commitments(pollconf_filename: &str, force: bool) -> Result
         NUMBER_OF_PLANES,
         2 * pollconf.num_ballots,
         pollconf.num_decoys)?;
-    column_planes.iter().enumerate()
-        .for_each(|(n, plane)| {
-            let committed_planes_path = {
-                let mut pathbuf = PathBuf::new();
-                pathbuf.push(&datadir_path);
-                pathbuf.push(format!("committed_plane_{:02}", n+1));
-                pathbuf.set_extension("csv");
-                pathbuf.into_boxed_path()
-            };
-            let psecrets = poll_secrets.plane_secrets[n].resolve(plane.len());
-            let permuted_plane = plane.permute(&psecrets.permutation);
-            let mut csvwriter = csv::Writer::from_path(committed_planes_path).unwrap();
-            permuted_plane.rows.iter()
-                .for_each(|rec| { csvwriter.serialize(rec).unwrap(); });
-        });
+    for (n, plane) in column_planes.iter().enumerate() {
+        let committed_plane_path = Path::new(&datadir_path).join(committed_plane_filename(n));
+        std::fs::write(committed_plane_path, committed_plane_csv(&poll_secrets, n, plane)?)?;
+    }
+    let serials: Vec<BallotSerial> = (0..pollconf.num_ballots).collect();
+    record_ballot_states(&datadir_path, BallotState::Generated, &serials)?;
 
     // Update the poll state.
     pollconf.poll_state.summands_committed = true;
     pollconf.poll_state.columns_committed = true;
     // Re-encrypt the poll configuration.
-    let serialized_pollconf = serde_yaml::to_string(&pollconf)?;
+    let serialized_pollconf = Zeroizing::new(serde_yaml::to_string(&pollconf)?);
     let secure_serialized_pollconf = AEADString::from_values(
         aead_encrypt(&aead_pmk,
                      Vec::new(),
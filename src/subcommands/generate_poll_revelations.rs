@@ -1,11 +1,168 @@
 //! # Command: Generate Poll Revelations
 //!
+//! `generate_poll_revelations` reveals the summands and the final planes
+//! of the poll and counts the tally. Every plane reveals either the vote
+//! codes or the choices of the ballots that were not audited, chosen by the
+//! audited columns seed, so that the tally is published as aggregate counts
+//! checked by cut and choose, without linking any vote code to its choice.
+//!
+//! The tally report lists every counted vote with the time of the block it
+//! was mined in, in the timezone of the poll. The block timestamps are read
+//! through the cache of the data directory, in batches.
+//!
+//! With `provenance`, the report also has an appendix on the submission of
+//! every counted vote, for resolving disputes about specific submissions:
+//! the format its payload was decoded from and, for votes mined on the
+//! chain, their sending account and whether the relay server of the poll
+//! posted them.
 
+use std::fmt;
+use std::fs;
+use std::collections::HashSet;
 use std::io::Write;
+use chrono::{DateTime, FixedOffset};
 use super::*;
+use crate::blockchain::{read_sent_transactions, BlockTimestamps, SpendPhase};
+use crate::votes::{resolve_provisional_votes, DecodePath, ProvisionalBallot, ProvisionalCount, TallyPolicy, VoteChannel, VoteRecord, VoteSourceCount};
+
+/// Counted vote, as listed in the tally report.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TalliedVote {
+    pub votecode: String,
+    pub source: VoteChannel,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub transaction_hash: String,
+    /// Block the vote was mined in, for votes read from the chain.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_number: Option<u64>,
+    /// Time of the block in the timezone of the poll, if it could be read.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<DateTime<FixedOffset>>
+}
+
+/// Submission of a counted vote, as listed in the provenance appendix of
+/// the tally report.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VoteProvenance {
+    pub votecode: String,
+    pub source: VoteChannel,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub transaction_hash: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub block_number: Option<u64>,
+    /// Sending account, for votes mined on the chain, where it is public
+    /// already. The senders of votes received otherwise, such as the phone
+    /// numbers of votes by text message, are left out of the report.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub sender: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decoded: Option<DecodePath>,
+    /// Whether the relay server posted the vote, for votes mined on the
+    /// chain: relayed votes are in the spending log of the data directory.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relayed: Option<bool>
+}
+
+impl VoteProvenance {
+    /// Provenance of the vote of `record`, posted by the relay server of
+    /// the poll if `relayed`.
+    pub fn new(record: &VoteRecord, relayed: bool) -> Self {
+        let mined = record.mined();
+        VoteProvenance {
+            votecode: record.votecode.clone(),
+            source: record.source,
+            transaction_hash: record.transaction_hash.clone(),
+            block_number: Some(record.block_number).filter(|_| mined),
+            sender: match mined {
+                true => record.from.clone(),
+                false => String::new()
+            },
+            decoded: record.decoded,
+            relayed: Some(relayed).filter(|_| mined)
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PollTally {
+    pub tally: PlaneTally,
+    pub planes_counted: usize,
+    pub consistent: bool,
+    /// Number of recorded votes for spoiled ballots, and for undelivered
+    /// ballots whose vote codes are invalidated, which are not counted.
+    #[serde(default)]
+    pub spoiled_votes: usize,
+    /// Number of votes for test vote codes, which are not counted.
+    #[serde(default)]
+    pub test_votes: usize,
+    /// Number of votes not counted by the tally rules for ballots voted
+    /// with both of their vote codes.
+    #[serde(default)]
+    pub uncounted_ballot_votes: usize,
+    /// Votes for provisional ballots; only the accepted ones are counted.
+    #[serde(default)]
+    pub provisional_votes: ProvisionalCount,
+    /// Number of recorded votes of every source.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub vote_sources: Vec<VoteSourceCount>,
+    /// Question and choice text of the poll in every locale.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ballot_text: Vec<LocalizedBallotText>,
+    /// Counted votes, in the order recorded.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub votes: Vec<TalliedVote>,
+    /// Appendix on the submission of the counted votes, if requested.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub provenance: Vec<VoteProvenance>
+}
+
+impl fmt::Display for PollTally {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        self.ballot_text.iter()
+            .try_for_each(|text| writeln!(f, "[{}] {}", text.locale, text.question))?;
+        match localized_choice_labels(&self.ballot_text) {
+            Some((for_label, against_label)) => write!(f, "Tally: {}: {}, {}: {}, Decoy: {}",
+                for_label, self.tally.for_votes,
+                against_label, self.tally.against_votes,
+                self.tally.decoy_votes)?,
+            None => write!(f, "Tally: {}", self.tally)?
+        }
+        write!(f, " ({} planes counted{})",
+            self.planes_counted,
+            if self.consistent { "" } else { ", INCONSISTENT" })?;
+        if 0 < self.provisional_votes.total() {
+            write!(f, "\nProvisional votes: {}", self.provisional_votes)?;
+        }
+        if 0 < self.test_votes {
+            write!(f, "\nVotes for test vote codes excluded: {}", self.test_votes)?;
+        }
+        if 0 < self.uncounted_ballot_votes {
+            write!(f, "\nVotes not counted for ballots voted twice: {}", self.uncounted_ballot_votes)?;
+        }
+        if 0 < self.spoiled_votes {
+            write!(f, "\nVotes for spoiled or undelivered ballots rejected: {}", self.spoiled_votes)?;
+        }
+        if !self.vote_sources.is_empty() {
+            write!(f, "\nVotes recorded by source: {}", self.vote_sources.iter()
+                .map(|count| count.to_string())
+                .collect::<Vec<String>>()
+                .join(", "))?;
+        }
+        let timestamps = self.votes.iter().filter_map(|vote| vote.timestamp);
+        if let (Some(first), Some(last)) = (timestamps.clone().min(), timestamps.max()) {
+            write!(f, "\nVotes mined from {} to {}", first.to_rfc3339(), last.to_rfc3339())?;
+        }
+        if !self.provenance.is_empty() {
+            let relayed = |relayed: bool| self.provenance.iter().filter(|vote| vote.relayed == Some(relayed)).count();
+            write!(f, "\nVote provenance: {} votes, {} relayed, {} posted directly",
+                self.provenance.len(), relayed(true), relayed(false))?;
+        }
+        Ok(())
+    }
+}
 
 
-pub fn generate_poll_revelations(pollconf_filename: &str, force: bool) -> Result<()> {
+pub fn generate_poll_revelations(network: &str, pollconf_filename: &str, provenance: bool, force: bool) -> Result<()> {
     let pollconf_path = Path::new(pollconf_filename);
 
     // Read poll configuration file.
@@ -32,7 +189,7 @@ pub fn generate_poll_revelations(pollconf_filename: &str, force: bool) -> Result
         "Voter roster already revealed. To re-commit, pass --force.");
 
     // Derive the poll secrets.
-    let poll_secrets: PollSecrets = PollSecrets::derive(&poll_master_key);
+    let poll_secrets: PollSecrets = PollSecrets::for_poll(&poll_master_key, pollconf.generation_seed.as_deref())?;
 
     // Re-construct the audited ballots.
     let audited_ballots: Vec<BallotSerial> = {
@@ -41,20 +198,122 @@ pub fn generate_poll_revelations(pollconf_filename: &str, force: bool) -> Result
             .collect()
     };
 
-    // Re-construct the marked votes.
-    let votes: Vec<VoteCode> = pollconf.votes.clone().unwrap();
+    // Re-construct the marked votes, rejecting the votes for spoiled ballots
+    // and invalidated undelivered ballots.
+    let excluded_ballots = pollconf.excluded_ballots();
+    let ballot_votecodes = votecode_serials(&poll_secrets, pollconf.num_ballots);
+    let (mut votes, excluded_votes): (Vec<VoteCode>, Vec<VoteCode>) = pollconf.votes.clone().unwrap().into_iter()
+        .partition(|votecode| ballot_votecodes.get(votecode).is_none_or(|serial| !excluded_ballots.contains(serial)));
+    if !excluded_votes.is_empty() {
+        warn!("Votes for spoiled or undelivered ballots rejected: {}", excluded_votes.len());
+    }
+
+    // Fold the votes for the accepted provisional ballots into the tally;
+    // the tally rules may reject the ballots still pending.
+    let provisional_ballots: Vec<ProvisionalBallot> = pollconf.provisional_ballots.iter()
+        .map(|ballot| ProvisionalBallot {
+            eligible: ballot.eligible.or(pollconf.tally_rules.pending_eligibility()),
+            ..ballot.clone()
+        })
+        .collect();
+    let (accepted_votes, provisional_votes) = resolve_provisional_votes(
+        &pollconf.provisional_votes,
+        &provisional_ballots,
+        |votecode| ballot_votecodes.get(votecode).copied());
+    if 0 < provisional_votes.pending {
+        return Err(format!("{} votes for provisional ballots are not resolved: resolve the provisional ballots first.", provisional_votes.pending).into());
+    }
+    votes.extend(accepted_votes);
+
+    // Date the counted votes by their blocks, reading the missing block
+    // timestamps in batches.
+    let utc_offset = pollconf.utc_offset()?;
+    let counted: VoteCodeSet = votes.iter().cloned().collect();
+    let vote_records: Vec<&VoteRecord> = pollconf.vote_records.iter()
+        .filter(|record| counted.contains_str(&record.votecode))
+        .collect();
+    let mut block_timestamps = BlockTimestamps::open(&datadir_path)?;
+    let blocks: Vec<u64> = vote_records.iter()
+        .filter(|record| record.mined())
+        .map(|record| record.block_number)
+        .collect();
+    match block_timestamps.resolve(network, &blocks) {
+        Ok(0) => (),
+        Ok(read) => {
+            info!("Block timestamps read: {}", read);
+            block_timestamps.save(&datadir_path)?;
+        },
+        Err(err) => warn!("Block timestamps could not be read, votes are listed without them: {}", err)
+    }
+    let tallied_votes: Vec<TalliedVote> = vote_records.iter()
+        .map(|record| TalliedVote {
+            votecode: record.votecode.clone(),
+            source: record.source,
+            transaction_hash: record.transaction_hash.clone(),
+            block_number: Some(record.block_number).filter(|_| record.mined()),
+            timestamp: Some(record.block_number).filter(|_| record.mined())
+                .and_then(|block_number| block_timestamps.get(block_number))
+                .map(|timestamp| timestamp.with_timezone(&utc_offset))
+        })
+        .collect();
+    let provenance: Vec<VoteProvenance> = match provenance {
+        true => {
+            let relayed: HashSet<String> = read_sent_transactions(&datadir_path)?.into_iter()
+                .filter(|sent| sent.phase == SpendPhase::Vote)
+                .map(|sent| sent.transaction_hash)
+                .collect();
+            vote_records.iter()
+                .map(|record| VoteProvenance::new(record, relayed.contains(&record.transaction_hash)))
+                .collect()
+        },
+        false => Vec::new()
+    };
+
+    // The choices of votes for audited ballots are revealed with the ballots.
+    let audited_votes = votes.iter()
+        .filter(|votecode| ballot_votecodes.get(votecode).is_some_and(|serial| audited_ballots.contains(serial)))
+        .count();
+    if 0 < audited_votes {
+        warn!("Votes for audited ballots, whose choices are revealed: {}", audited_votes);
+    }
     let marked_rows: Vec<usize> = {
         let votecodes: Vec<VoteCode> = generate_votecodes(
-            poll_secrets.votecode_root,
+            &poll_secrets.votecode_root,
             2 * pollconf.num_ballots);
-        votecodes.iter().enumerate()
+        let mut progress = Progress::new("Counting votes", "votecodes", votecodes.len());
+        let marked_rows = votecodes.iter().enumerate()
             .filter_map(|(n, vc)| {
                 debug!("{:?}", vc);
+                progress.inc(1);
                 if votes.contains(vc) { Some(n) }
                 else { None }
-            }).collect()
+            }).collect();
+        progress.finish();
+        marked_rows
+    };
+
+    // Reveal Audited Columns
+    let audited_columns_seed: Vec<u8> = {
+        let seed = pollconf.audited_columns_seed.clone();
+        hex::decode(seed.unwrap())?
+    };
+    let audited_columns: Vec<usize> = {
+        let seed = CSPRNGSeed::from_vec(&audited_columns_seed);
+        let mut prng = CSPRNG::from_csprng_seed(&seed);
+        (0..NUMBER_OF_PLANES).into_iter().map(|_| prng.gen_range(0, 2)).collect()
     };
 
+    // Check that no plane links vote codes to their choices before any file
+    // is revealed.
+    let plane_secrets: Vec<DerivedPlaneSecrets> = poll_secrets.plane_secrets.iter()
+        .map(|secrets| secrets.resolve(2 * pollconf.num_ballots))
+        .collect();
+    let plane_filters: Vec<PlaneFilter> = (0..NUMBER_OF_PLANES)
+        .map(|n| PlaneFilter::from(&plane_secrets[n].col1_keys, &plane_secrets[n].col3_keys)
+            .decrypt_serials(&audited_ballots)
+            .decrypt_column(if audited_columns[n] == 0 {1} else {3}))
+        .collect();
+
     // Reveal Committed Summands
     let committed_summands_revealed_path = {
         let mut pathbuf = PathBuf::new();
@@ -64,13 +323,15 @@ pub fn generate_poll_revelations(pollconf_filename: &str, force: bool) -> Result
         pathbuf.into_boxed_path()
     };
     let committed_summands = CommittedSummands::from_csprng(
-        poll_secrets.summands_root,
+        &poll_secrets.summands_root,
         pollconf.num_ballots,
         pollconf.voter_roster_size);
+    let mut revealed_paths: Vec<PathBuf> = vec![committed_summands_revealed_path.to_path_buf()];
     let mut csvwriter = csv::Writer::from_path(committed_summands_revealed_path)?;
     Summands::from(committed_summands).records.iter().for_each(|summand| {
         csvwriter.serialize(summand).unwrap();
     });
+    csvwriter.flush()?;
     // Reveal Committed Summands Key
     let committed_summands_key_path = {
         let mut pathbuf = PathBuf::new();
@@ -79,19 +340,9 @@ pub fn generate_poll_revelations(pollconf_filename: &str, force: bool) -> Result
         pathbuf.set_extension("key");
         pathbuf.into_boxed_path()
     };
-    File::create(committed_summands_key_path)?
+    File::create(&committed_summands_key_path)?
         .write(base64::encode(&poll_secrets.summands_key.0).as_bytes())?;
-
-    // Reveal Audited Columns
-    let audited_columns_seed: Vec<u8> = {
-        let seed = pollconf.audited_columns_seed.clone();
-        hex::decode(seed.unwrap())?
-    };
-    let audited_columns: Vec<usize> = {
-        let seed = CSPRNGSeed::from_vec(&audited_columns_seed);
-        let mut prng = CSPRNG::from_csprng_seed(seed);
-        (0..NUMBER_OF_PLANES).into_iter().map(|_| prng.gen_range(0, 2)).collect()
-    };
+    revealed_paths.push(committed_summands_key_path.to_path_buf());
 
     // Post the Fully Audited Column Planes.
     let column_planes: Vec<Plane> = generate_column_planes(
@@ -100,8 +351,8 @@ pub fn generate_poll_revelations(pollconf_filename: &str, force: bool) -> Result
         2 * pollconf.num_ballots,
         pollconf.num_decoys)?;
     // Filter planes.
-    column_planes.iter().enumerate()
-        .for_each(|(n, plane)| {
+    let plane_tallies: Vec<PlaneTally> = column_planes.iter().enumerate()
+        .filter_map(|(n, plane)| {
             let posted_planes_path = {
                 let mut pathbuf = PathBuf::new();
                 pathbuf.push(&datadir_path);
@@ -116,32 +367,82 @@ pub fn generate_poll_revelations(pollconf_filename: &str, force: bool) -> Result
                 pathbuf.set_extension("csv");
                 pathbuf.into_boxed_path()
             };
-            let psecrets = poll_secrets.plane_secrets[n].resolve(plane.len());
-            let filter = PlaneFilter::from(&psecrets.col1_keys, &psecrets.col3_keys)
-                .decrypt_serials(&audited_ballots)
-                .decrypt_column(if audited_columns[n] == 0 {1} else {3});
-
-            let permuted_plane = plane.mark_rows(&marked_rows).decrypt(&filter).permute(&psecrets.permutation);
-            let mut csvwriter = csv::Writer::from_path(posted_planes_path).unwrap();
-            permuted_plane.rows.iter()
+            let psecrets = &plane_secrets[n];
+            let filter = &plane_filters[n];
+            let permuted_plane = plane.mark_rows(&marked_rows).decrypt(filter).permute(&psecrets.permutation);
+            let serialized_rows: Vec<PlaneRecordFileRow> = permuted_plane.rows.iter()
+                .map(|rec| rec.serializable(pollconf.num_ballots))
+                .collect();
+            let mut csvwriter = csv::Writer::from_path(&posted_planes_path).unwrap();
+            serialized_rows.iter()
                 .for_each(|rec| {
-                    csvwriter.serialize(rec.serializable(pollconf.num_ballots)).unwrap();
+                    csvwriter.serialize(rec).unwrap();
                 });
+            csvwriter.flush().unwrap();
+            revealed_paths.push(posted_planes_path.to_path_buf());
             
             let permuted_filter = filter.permute(&psecrets.permutation);
-            let mut csvwriter = csv::Writer::from_path(posted_keys_path).unwrap();
+            let mut csvwriter = csv::Writer::from_path(&posted_keys_path).unwrap();
             permuted_filter.serializable().iter()
                 .for_each(|rec| {
                     csvwriter.serialize(rec).unwrap();
                 });
-        });
+            csvwriter.flush().unwrap();
+            revealed_paths.push(posted_keys_path.to_path_buf());
+
+            // Only planes with the third column revealed show the choices.
+            match audited_columns[n] {
+                0 => None,
+                _ => Some(PlaneTally::from_rows(&serialized_rows))
+            }
+        }).collect();
+
+    // Every plane with the third column revealed must agree on the tally.
+    let poll_tally = PollTally {
+        tally: plane_tallies.first().cloned().unwrap_or_default(),
+        planes_counted: plane_tallies.len(),
+        consistent: plane_tallies.windows(2).all(|pair| pair[0] == pair[1]),
+        spoiled_votes: excluded_votes.len(),
+        test_votes: pollconf.test_votes,
+        uncounted_ballot_votes: pollconf.uncounted_ballot_votes,
+        provisional_votes,
+        vote_sources: pollconf.vote_sources.clone(),
+        ballot_text: pollconf.ballot_text.clone(),
+        votes: tallied_votes,
+        provenance
+    };
+    let tally_path = {
+        let mut pathbuf = PathBuf::new();
+        pathbuf.push(&datadir_path);
+        pathbuf.push("tally");
+        pathbuf.set_extension("yaml");
+        pathbuf.into_boxed_path()
+    };
+    // Planes that disagree leave no tally report to publish, nor a stale one.
+    if !poll_tally.consistent {
+        if tally_path.exists() {
+            fs::remove_file(&tally_path)?;
+        }
+        return Err(format!("Revealed planes disagree on the tally: {:?}", plane_tallies).into());
+    }
+
+    // Save the tally report for publication.
+    serde_yaml::to_writer(
+        File::create(&tally_path)?,
+        &poll_tally)?;
+    revealed_paths.push(tally_path.to_path_buf());
+
+    // Sign the revealed files, so that they are checked when loaded.
+    for path in revealed_paths.iter() {
+        sign_file(&pollconf.signing_key, path)?;
+    }
 
     // Update the poll state.
     pollconf.poll_state.roster_revealed = true;
     pollconf.poll_state.summands_revealed = true;
     pollconf.poll_state.columns_revealed = true;
     // Re-encrypt the poll configuration.
-    let serialized_pollconf = serde_yaml::to_string(&pollconf)?;
+    let serialized_pollconf = Zeroizing::new(serde_yaml::to_string(&pollconf)?);
     let secure_serialized_pollconf = AEADString::from_values(
         aead_encrypt(&aead_pmk,
                      Vec::new(),
@@ -152,6 +453,6 @@ pub fn generate_poll_revelations(pollconf_filename: &str, force: bool) -> Result
         File::create(pollconf_path)?,
         &secured_poll_configuration)?;
 
-    Ok(())
+    output::emit(&poll_tally)
 }
 
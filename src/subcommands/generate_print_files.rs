@@ -3,10 +3,11 @@
 //! `generate_print_files` reads from the voter roster and poll secrets
 //! from the secured poll configuration and writes out CSV files for the
 //! address labels and ballot information. These files are intended
-//! for mass printing.
+//! for mass printing. Voters flagged in the `accessibility` field of the
+//! roster also get their ballot in large print or as HTML for screen
+//! readers, next to the standard ballots.
 
 use super::*;
-use crate::voter_selection::select_voters;
 use crate::ballots::print;
 
 #[derive(Debug, Clone, Serialize)]
@@ -21,7 +22,7 @@ pub struct AddressLabel {
 }
 
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CompleteBallotRow {
     pub serial: String,
     pub choice1_votecode: String,
@@ -30,6 +31,18 @@ pub struct CompleteBallotRow {
     pub choice2_value: String
 }
 
+impl CompleteBallotRow {
+    pub fn from_ballot(ballot: &Ballot, num_ballots: usize) -> Self {
+        CompleteBallotRow {
+            serial: string_from_ballotserial(&ballot.serial, num_ballots),
+            choice1_votecode: string_from_votecode(&ballot.choice1.votecode),
+            choice1_value: string_from_choicevalue(&ballot.choice1.choice),
+            choice2_votecode: string_from_votecode(&ballot.choice2.votecode),
+            choice2_value: string_from_choicevalue(&ballot.choice2.choice)
+        }
+    }
+}
+
 #[derive(Debug, Clone, Serialize)]
 pub struct SplitBallotRow {
     pub serial: String,
@@ -38,7 +51,7 @@ pub struct SplitBallotRow {
 }
 
 
-pub fn generate_print_files(pollconf_filename: &str, addresses_filename: &str, ballots_filename: &str) -> Result<()> {
+pub fn generate_print_files(pollconf_filename: &str, addresses_filename: &str, ballots_filename: &str, layout_filename: Option<&str>, ballots_pdf_filename: Option<&str>) -> Result<()> {
     // Read poll configuration file.
     let secured_poll_configuration = read_poll_configuration_file(pollconf_filename)?;
 
@@ -54,44 +67,24 @@ pub fn generate_print_files(pollconf_filename: &str, addresses_filename: &str, b
         "Summands must be drawn to generate voters and print content for public audit.");
 
     // Derive the poll secrets.
-    let poll_secrets: PollSecrets = PollSecrets::derive(&poll_master_key);
+    let poll_secrets: PollSecrets = PollSecrets::for_poll(&poll_master_key, pollconf.generation_seed.as_deref())?;
     
-    // Regenerate the Committed Summands.
-    let committed_summands = CommittedSummands::from_csprng(
-        poll_secrets.summands_root,
-        pollconf.num_ballots,
-        pollconf.voter_roster_size);
-    // Regenerate the Drawn Summands.
-    let drawn_summands_seed: Vec<u8> = {
-        let seed = pollconf.drawn_summands_seed.clone();
-        hex::decode(seed.unwrap())?
-    };
-    let drawn_summands = DrawnSummands::from_csprng(
-        CSPRNGSeed::from_vec(&drawn_summands_seed),
-        pollconf.num_ballots,
-        pollconf.voter_roster_size);
     // Select the Voters.
-    let roster_indices = select_voters(
-        committed_summands,
-        drawn_summands,
-        pollconf.voter_roster_size)?;
+    let roster_indices = select_poll_voters(&pollconf, &poll_secrets)?;
     debug!("Selected Voters: {:?}", roster_indices);
 
     // Generate the Ballots.
-    let serials: Vec<BallotSerial> = (0..pollconf.num_ballots).collect();
-    let votecodes: Vec<VoteCode> = generate_votecodes(
-        poll_secrets.votecode_root,
-        2 * pollconf.num_ballots);
-    let ballots = generate_ballots(&serials, &votecodes);
+    let ballots = generate_poll_ballots(&poll_secrets, pollconf.num_ballots);
     debug!("Ballots: {:?}", ballots);
 
+    // Refuse to print ballots sharing a vote code.
+    let collisions = ballot_votecode_collisions(&ballots, pollconf.num_ballots);
+    if !collisions.is_empty() {
+        return Err(format!("{} vote codes are shared by more than one ballot choice.", collisions.len()).into());
+    }
+
     // Print the Address Labels
-    let roster: VoterRoster = {
-        let encoded_roster = pollconf.voter_roster.clone().unwrap();
-        let decoded_roster = base64::decode(&encoded_roster.0)?;
-        let serialized_roster = str::from_utf8(&decoded_roster)?;
-        serde_yaml::from_str(serialized_roster)?
-    };
+    let roster = read_voter_roster(&pollconf)?;
     let addresses: Vec<AddressLabel> = roster_indices.iter()
         .map(|&n| { roster.records[n].voter_info.clone() })
         .map(|voter| {
@@ -110,25 +103,59 @@ pub fn generate_print_files(pollconf_filename: &str, addresses_filename: &str, b
     addresses.iter()
         .for_each(|record| { csvwriter.serialize(record).unwrap(); });
 
+    // Read the ballot layout.
+    let layout: BallotLayout = match layout_filename {
+        Some(filename) => serde_yaml::from_reader(File::open(Path::new(filename))?)?,
+        None => BallotLayout::default()
+    };
+    let datadir_path = ensure_poll_data_directory_exists(&secured_poll_configuration, &aead_pmk)?;
+    let poll_identifier = pollconf.payload_identifier(&datadir_path);
+    let text = BallotText {
+        poll_identifier: Some(&poll_identifier),
+        question: pollconf.poll_question.as_deref(),
+        localized: &pollconf.ballot_text
+    };
+
     // Print the Ballots
     let ballots_path = Path::new(ballots_filename);
     let mut csvwriter = csv::Writer::from_path(ballots_path)?;
     ballots.iter()
         .for_each(|ballot| {
-            print::print_ballot(&ballot); 
-            let record = CompleteBallotRow {
-                serial: string_from_ballotserial(&ballot.serial, pollconf.num_ballots),
-                choice1_votecode: string_from_votecode(&ballot.choice1.votecode),
-                choice1_value: string_from_choicevalue(&ballot.choice1.choice),
-                choice2_votecode: string_from_votecode(&ballot.choice2.votecode),
-                choice2_value: string_from_choicevalue(&ballot.choice2.choice)
-            };
+            print::print_ballot_with_layout(ballot, &text, &layout);
+            let record = CompleteBallotRow::from_ballot(ballot, pollconf.num_ballots);
             debug!("{:?}", record);
             csvwriter.serialize(record).unwrap();
         });
 
+    // Print the accessible variants of the ballots of the flagged voters.
+    let mut accessible = 0;
+    for (ballot, &n) in ballots.iter().zip(roster_indices.iter()) {
+        let record = &roster.records[n];
+        let format = BallotFormat::for_voter(&record.voter_info)
+            .map_err(|err| format!("Voter {}: {}", record.position, err))?;
+        let path = match format.file_name(ballot.serial) {
+            Some(file_name) => Path::new(print::BALLOTS_PATH).join(file_name),
+            None => continue
+        };
+        match format {
+            BallotFormat::LargePrint => print::print_large_print_ballot(ballot, &text, &layout, &path)?,
+            BallotFormat::ScreenReader => std::fs::write(&path, ballot_html(ballot, &text))?,
+            BallotFormat::Standard => ()
+        }
+        accessible += 1;
+    }
+    if 0 < accessible {
+        info!("Accessible ballots printed: {}", accessible);
+    }
+
+    // Print the print-ready ballot document.
+    if let Some(filename) = ballots_pdf_filename {
+        print::print_ballots(&ballots, &text, &layout, &Path::new(filename))?;
+    }
+
     // No need to update the poll state since this is not a public operation.
-    Ok(())
+    let serials: Vec<BallotSerial> = ballots.iter().map(|ballot| ballot.serial).collect();
+    record_ballot_states(&datadir_path, BallotState::Printed, &serials)
 }
 
 
@@ -1,8 +1,19 @@
 //! # Command: Generate Tally AuditRecord Votes
 //!
 
+use std::fmt;
 use super::*;
 
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditedColumns {
+    pub audited_columns: Vec<String>
+}
+
+impl fmt::Display for AuditedColumns {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.audited_columns.join("\n"))
+    }
+}
 
 
 pub fn generate_tally_audit(pollconf_filename: &str, seed: &str) -> Result<()> {
@@ -41,7 +52,7 @@ pub fn generate_tally_audit(pollconf_filename: &str, seed: &str) -> Result<()> {
     };
     let audited_columns: Vec<usize> = {
         let seed = CSPRNGSeed::from_vec(&audited_columns_seed);
-        let mut prng = CSPRNG::from_csprng_seed(seed);
+        let mut prng = CSPRNG::from_csprng_seed(&seed);
         (0..NUMBER_OF_PLANES).into_iter().map(|_| prng.gen_range(0, 2)).collect()
     };
     let audited_columns_readable: Vec<String> = {
@@ -51,11 +62,12 @@ pub fn generate_tally_audit(pollconf_filename: &str, seed: &str) -> Result<()> {
         }).collect()
     };
     serde_yaml::to_writer(
-        File::create(audited_columns_path)?,
+        File::create(&audited_columns_path)?,
         &audited_columns_readable)?;
+    sign_file(&pollconf.signing_key, &audited_columns_path)?;
 
     // Re-encrypt the poll configuration.
-    let serialized_pollconf = serde_yaml::to_string(&pollconf)?;
+    let serialized_pollconf = Zeroizing::new(serde_yaml::to_string(&pollconf)?);
     let secure_serialized_pollconf = AEADString::from_values(
         aead_encrypt(&aead_pmk,
                      Vec::new(),
@@ -66,6 +78,6 @@ pub fn generate_tally_audit(pollconf_filename: &str, seed: &str) -> Result<()> {
         File::create(pollconf_path)?,
         &secured_poll_configuration)?;
 
-    Ok(())
+    output::emit(&AuditedColumns { audited_columns: audited_columns_readable })
 }
 
@@ -2,7 +2,13 @@
 //!
 //! These functions perform tasks repeated across the various subcommands.
 
+use std::fs::OpenOptions;
+use std::io::Write;
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
 use super::*;
+use crate::blockchain::{CommitReceipt, FinalizationReceipt};
+use crate::voter_selection::{select_voters, VoterRosterIndices};
 
 
 pub fn read_poll_configuration_file(filename: &str) -> Result<SecuredPollConfiguration> {
@@ -14,6 +20,14 @@ pub fn read_poll_configuration_file(filename: &str) -> Result<SecuredPollConfigu
     Ok(secured_poll_configuration)
 }
 
+pub fn write_poll_configuration_file(filename: &str, secured_poll_configuration: &SecuredPollConfiguration) -> Result<()> {
+    serde_yaml::to_writer(
+        File::create(
+            Path::new(filename))?,
+        secured_poll_configuration)?;
+    Ok(())
+}
+
 
 /// Reconstruct the Poll Master Key from the trustee passwords.
 pub fn read_poll_master_key(secured_poll_configuration: &SecuredPollConfiguration) -> (PollMasterKey, AEADKey)  {
@@ -24,7 +38,7 @@ pub fn read_poll_master_key(secured_poll_configuration: &SecuredPollConfiguratio
                 identifier: trustee.identifier.clone(),
                 secure_value: trustee.share.clone()
             }).filter_map(|secure_share| secure_share.read())
-            .map(|share| PollMasterKeyShare(share.value))
+            .map(|mut share| PollMasterKeyShare(std::mem::take(&mut *share.value)))
             .collect::<ListOfPollMasterKeyShares>();
         PollMasterKey::reconstruct(master_key_shares, num_trustees)
     };
@@ -56,6 +70,126 @@ pub fn ensure_poll_data_directory_exists(secured_poll_configuration: &SecuredPol
     Ok(identifier)
 }
 
+/// Write the base64 encoded `secret_key` to the new file `filename`,
+/// readable by its owner only. An existing file is never overwritten.
+pub fn write_secret_key_file(filename: &str, secret_key: &[u8]) -> Result<()> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    let mut file = match options.open(filename) {
+        Ok(file) => file,
+        Err(err) if err.kind() == ErrorKind::AlreadyExists => return Err(format!("{} already exists.", filename).into()),
+        Err(err) => return Err(err.into())
+    };
+    file.write_all(base64::encode(secret_key).as_bytes())?;
+    Ok(())
+}
+
+/// Read the blockchain commit receipt written by step 4, if the poll was committed.
+pub fn read_commit_receipt(datadir_path: &str) -> Result<Option<CommitReceipt>> {
+    let commit_receipt_path = {
+        let mut pathbuf = PathBuf::new();
+        pathbuf.push(datadir_path);
+        pathbuf.push("commit_receipt");
+        pathbuf.set_extension("yaml");
+        pathbuf.into_boxed_path()
+    };
+    match commit_receipt_path.exists() {
+        true => Ok(Some(serde_yaml::from_reader(File::open(&commit_receipt_path)?)?)),
+        false => Ok(None)
+    }
+}
+
+/// Read the finalization receipt, if the tally was finalized.
+pub fn read_finalization_receipt(datadir_path: &str) -> Result<Option<FinalizationReceipt>> {
+    let finalization_receipt_path = Path::new(datadir_path).join("finalization_receipt.yaml");
+    match finalization_receipt_path.exists() {
+        true => Ok(Some(serde_yaml::from_reader(File::open(&finalization_receipt_path)?)?)),
+        false => Ok(None)
+    }
+}
+
+/// Read the tally report, if the poll was revealed.
+pub fn read_poll_tally(datadir_path: &str) -> Result<Option<PollTally>> {
+    let tally_path = {
+        let mut pathbuf = PathBuf::new();
+        pathbuf.push(datadir_path);
+        pathbuf.push("tally");
+        pathbuf.set_extension("yaml");
+        pathbuf.into_boxed_path()
+    };
+    match tally_path.exists() {
+        true => {
+            check_artifact(&tally_path)?;
+            Ok(Some(serde_yaml::from_reader(File::open(&tally_path)?)?))
+        },
+        false => Ok(None)
+    }
+}
+
+/// Authenticate the poll verification key and check the signed artifacts
+/// loaded from now on with it, unless a key was given on the command line.
+pub fn use_poll_verification_key(secured_poll_configuration: &SecuredPollConfiguration, aead_pmk: &AEADKey) -> Result<Base64String> {
+    let public_key = {
+        let aead_values = secured_poll_configuration.signing_certificate.values()?;
+        aead_decrypt(aead_pmk, &aead_values)?;
+        Base64String(String::from_utf8(aead_values.aad)?)
+    };
+    use_verification_key(public_key.clone());
+    Ok(public_key)
+}
+
+/// Select the voters receiving the ballots, by roster position in ballot
+/// serial order, once the summands have been drawn.
+pub fn select_poll_voters(pollconf: &PollConfiguration, poll_secrets: &PollSecrets) -> Result<VoterRosterIndices> {
+    // Regenerate the Committed Summands.
+    let committed_summands = CommittedSummands::from_csprng(
+        &poll_secrets.summands_root,
+        pollconf.num_ballots,
+        pollconf.voter_roster_size);
+    // Regenerate the Drawn Summands.
+    let drawn_summands_seed: Vec<u8> = {
+        let seed = pollconf.drawn_summands_seed.clone();
+        hex::decode(seed.ok_or("Summands have not been drawn.")?)?
+    };
+    let drawn_summands = DrawnSummands::from_csprng(
+        &CSPRNGSeed::from_vec(&drawn_summands_seed),
+        pollconf.num_ballots,
+        pollconf.voter_roster_size);
+    select_voters(
+        committed_summands,
+        drawn_summands,
+        pollconf.voter_roster_size)
+}
+
+/// Decode the voter roster bound to the poll.
+pub fn read_voter_roster(pollconf: &PollConfiguration) -> Result<VoterRoster> {
+    let encoded_roster = pollconf.voter_roster.clone().ok_or("No voter roster is bound to the poll.")?;
+    let decoded_roster = base64::decode(&encoded_roster.0)?;
+    let serialized_roster = str::from_utf8(&decoded_roster)?;
+    Ok(serde_yaml::from_str(serialized_roster)?)
+}
+
+/// Generate the ballots of the poll using the poll secrets.
+pub fn generate_poll_ballots(poll_secrets: &PollSecrets, num_ballots: usize) -> Vec<Ballot> {
+    let serials: Vec<BallotSerial> = (0..num_ballots).collect();
+    let votecodes: Vec<VoteCode> = generate_votecodes(
+        &poll_secrets.votecode_root,
+        2 * num_ballots);
+    generate_ballots(&serials, &votecodes)
+}
+
+/// Map the vote codes of all ballots of the poll to their ballot serial.
+pub fn votecode_serials(poll_secrets: &PollSecrets, num_ballots: usize) -> VoteCodeMap<BallotSerial> {
+    generate_poll_ballots(poll_secrets, num_ballots).iter()
+        .flat_map(|ballot| vec![
+            (ballot.choice1.votecode, ballot.serial),
+            (ballot.choice2.votecode, ballot.serial)
+        ])
+        .collect()
+}
+
 /// Generate the column planes using the poll secrets.
 pub fn generate_column_planes(secrets: &PollSecrets, num_planes: usize, num_rows: usize, num_decoys: usize) -> Result<Vec<Plane>> {
     fn generate_column_plane(secrets: &PollSecrets, plane_num: usize, votecodes: Vec<VoteCode>, decoys: Vec<BallotSerial>) -> Result<Plane> {
@@ -113,8 +247,8 @@ pub fn generate_column_planes(secrets: &PollSecrets, num_planes: usize, num_rows
         })
     }
 
-    let votecodes: Vec<VoteCode> = generate_votecodes(secrets.votecode_root, num_rows);
-    let decoys: Vec<BallotSerial> = generate_decoy_serials(secrets.decoy_root, num_decoys, num_rows / 2);
+    let votecodes: Vec<VoteCode> = generate_votecodes(&secrets.votecode_root, num_rows);
+    let decoys: Vec<BallotSerial> = generate_decoy_serials(&secrets.decoy_root, num_decoys, num_rows / 2);
 
     Ok((0..num_planes).into_iter()
         .map(|n| {
@@ -122,3 +256,19 @@ pub fn generate_column_planes(secrets: &PollSecrets, num_planes: usize, num_rows
         }).collect::<Vec<Plane>>())
 }
 
+/// File name of the committed plane `plane_num`, counting from 0.
+pub fn committed_plane_filename(plane_num: usize) -> String {
+    format!("committed_plane_{:02}.csv", plane_num + 1)
+}
+
+/// Committed form of the plane `plane_num`, as written by step 2: the plane
+/// permuted with its secret permutation, in CSV.
+pub fn committed_plane_csv(poll_secrets: &PollSecrets, plane_num: usize, plane: &Plane) -> Result<Vec<u8>> {
+    let psecrets = poll_secrets.plane_secrets[plane_num].resolve(plane.len());
+    let permuted_plane = plane.permute(&psecrets.permutation);
+    let mut csvwriter = csv::Writer::from_writer(Vec::new());
+    for rec in permuted_plane.rows.iter() {
+        csvwriter.serialize(rec)?;
+    }
+    csvwriter.into_inner().map_err(|err| err.to_string().into())
+}
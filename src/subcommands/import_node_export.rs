@@ -0,0 +1,52 @@
+//! # Command: Import Node Export
+//!
+//! `import_node_export` writes the transactions sent to the vote address
+//! since the poll was committed, read from raw chain data exported from a
+//! node, to a transaction cache in the format of `cache_transactions`, so
+//! that the votes are recorded and recounted from files on disk without a
+//! node or an explorer. Only the public commit receipt is needed, not the
+//! trustee passwords, unless the cache is signed with the poll signing key.
+
+use std::fmt;
+use super::*;
+use crate::blockchain::{exported_vote_transactions, read_node_export, CommitReceipt, NodeExportFormat};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ImportedTransactions {
+    pub input: String,
+    /// Transactions read from the export.
+    pub exported: usize,
+    pub from_block: u64,
+    pub transactions: usize,
+    pub output: String
+}
+
+impl fmt::Display for ImportedTransactions {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Cached {} of the {} transactions of {} since block {} to {}",
+            self.transactions, self.exported, self.input, self.from_block, self.output)
+    }
+}
+
+pub fn import_node_export(format: NodeExportFormat, input_filename: &str, receipt: &str, vote_address: Option<&str>, output_filename: &str, pollconf_filename: Option<&str>) -> Result<()> {
+    let commit_receipt: CommitReceipt = serde_yaml::from_reader(File::open(receipt)?)?;
+    let vote_address = vote_address.or(commit_receipt.vote_address.as_deref())
+        .ok_or("Commit receipt has no vote address, give it with --vote-address.")?;
+
+    let exported = read_node_export(format, &std::fs::read(input_filename)?)?;
+    let transactions = exported_vote_transactions(&exported, vote_address, commit_receipt.submitted_at_block, commit_receipt.end_block)?;
+    serde_json::to_writer_pretty(File::create(output_filename)?, &transactions)?;
+
+    // Sign the cache, so that it is checked when the votes are recorded.
+    if let Some(pollconf_filename) = pollconf_filename {
+        sign_transaction_cache(pollconf_filename, output_filename)?;
+    }
+
+    output::emit(&ImportedTransactions {
+        input: input_filename.to_owned(),
+        exported: exported.len(),
+        from_block: commit_receipt.submitted_at_block,
+        transactions: transactions.len(),
+        output: output_filename.to_owned()
+    })
+}
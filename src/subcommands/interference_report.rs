@@ -0,0 +1,63 @@
+//! # Command: Interference Report
+//!
+//! `interference_report` reads the transactions sent to the vote address
+//! of a committed poll, from the block of its commitment to the latest
+//! block, and analyses those that could not change the tally: invalid
+//! payloads and votes with vote codes on no ballot. The ballots are
+//! re-constructed to recognize the vote codes, so the trustee passwords
+//! are needed. The report is written to the data directory of the poll,
+//! from where it is published on the bulletin board.
+
+use super::*;
+use crate::blockchain::blocking::latest_block;
+use crate::monitor::{DecodedTransaction, InterferenceReport, Pipeline, Shutdown, FETCH_WINDOW_BLOCKS, INTERFERENCE_REPORT_FILENAME};
+
+pub fn interference_report(network: &str, pollconf_filename: &str) -> Result<()> {
+    // Read poll configuration file.
+    let secured_poll_configuration = read_poll_configuration_file(pollconf_filename)?;
+
+    // Reconstruct the Poll Master Key from the trustee passwords.
+    let (poll_master_key, aead_pmk) = read_poll_master_key(&secured_poll_configuration);
+
+    // Ensure the data directory exists.
+    let datadir_path = ensure_poll_data_directory_exists(&secured_poll_configuration, &aead_pmk)?;
+
+    // Decrypt poll configuration state.
+    let pollconf_aead_values = secured_poll_configuration.encrypted_poll_configuration.values()?;
+    let serialized_pollconf = aead_decrypt(&aead_pmk, &pollconf_aead_values)?;
+    let pollconf: PollConfiguration = serde_yaml::from_slice(&serialized_pollconf).unwrap();
+
+    let commit_receipt = match read_commit_receipt(&datadir_path)? {
+        Some(commit_receipt) => commit_receipt,
+        None => return Err("Poll has not been committed to the blockchain.".into())
+    };
+
+    // Re-construct the ballots to recognize the vote codes of the poll.
+    let poll_secrets: PollSecrets = PollSecrets::for_poll(&poll_master_key, pollconf.generation_seed.as_deref())?;
+    let ballots = votecode_serials(&poll_secrets, pollconf.num_ballots);
+
+    // Read the transactions to the vote address, following its handovers.
+    let from_block = commit_receipt.submitted_at_block;
+    let to_block = latest_block(network)?;
+    let pipeline = Pipeline::start(
+        network,
+        &commit_receipt.poll_identifier,
+        &commit_receipt.transaction_hash,
+        commit_receipt.vote_address.clone(),
+        from_block,
+        to_block,
+        FETCH_WINDOW_BLOCKS,
+        Shutdown::new());
+    let mut transactions: Vec<DecodedTransaction> = Vec::new();
+    while let Some(batch) = pipeline.next_batch() {
+        transactions.extend(batch?.transactions);
+    }
+
+    let report = InterferenceReport::new(&commit_receipt.poll_identifier, from_block, to_block, &transactions, &ballots);
+    if 0 < report.interfering() {
+        warn!("{} transactions to the vote address could not change the tally", report.interfering());
+    }
+    let report_path = Path::new(&datadir_path).join(INTERFERENCE_REPORT_FILENAME);
+    serde_yaml::to_writer(File::create(&report_path)?, &report)?;
+    output::emit(&report)
+}
@@ -0,0 +1,48 @@
+//! # Command: Logic and Accuracy
+//!
+//! `logic_and_accuracy` runs the logic and accuracy test of a poll before
+//! it goes live: a test poll with the ballots, decoys and tally rules of the
+//! poll is voted with a known test deck on the in-memory mock chain, and
+//! its tally is checked against the expected outcome. The report is written
+//! to `logic_and_accuracy.yaml` in the data directory of the poll, signed
+//! with the poll signing key.
+
+use std::fs;
+use super::*;
+use crate::simulation::*;
+
+pub const LOGIC_ACCURACY_REPORT_FILENAME: &str = "logic_and_accuracy.yaml";
+
+pub fn logic_and_accuracy(pollconf_filename: &str, dirname: &str, seed: Option<&str>) -> Result<()> {
+    // Read poll configuration file.
+    let secured_poll_configuration = read_poll_configuration_file(pollconf_filename)?;
+
+    // Reconstruct the Poll Master Key from the trustee passwords.
+    let (_, aead_pmk) = read_poll_master_key(&secured_poll_configuration);
+
+    // Ensure the data directory exists; the test changes the working directory.
+    let datadir_path = fs::canonicalize(ensure_poll_data_directory_exists(&secured_poll_configuration, &aead_pmk)?)?;
+
+    // Decrypt poll configuration state.
+    let pollconf_aead_values = secured_poll_configuration.encrypted_poll_configuration.values()?;
+    let serialized_pollconf = aead_decrypt(&aead_pmk, &pollconf_aead_values)?;
+    let pollconf: PollConfiguration = serde_yaml::from_slice(&serialized_pollconf).unwrap();
+
+    let report = run_logic_accuracy(&LogicAccuracyConfiguration {
+        num_ballots: pollconf.num_ballots,
+        num_decoys: pollconf.num_decoys,
+        tally_rules: pollconf.tally_rules.clone(),
+        seed: seed.map(str::to_owned)
+    }, Path::new(dirname))?;
+
+    // Sign the report with the key of the poll under test.
+    let report_path = datadir_path.join(LOGIC_ACCURACY_REPORT_FILENAME);
+    serde_yaml::to_writer(File::create(&report_path)?, &report)?;
+    sign_file(&pollconf.signing_key, &report_path)?;
+
+    output::emit(&report)?;
+    if !report.passed {
+        return Err("Logic and accuracy test failed: the tally does not match the test deck.".into());
+    }
+    Ok(())
+}
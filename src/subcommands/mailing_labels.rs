@@ -0,0 +1,113 @@
+//! # Command: Mailing Labels
+//!
+//! `export_mailing_labels` writes the address labels of the voters the
+//! ballots are mailed to, in the order of the ballots, for the mailing
+//! vendor: as CSV with one normalized address line per column, or as text
+//! with one label per paragraph. It needs the summands to be drawn, to
+//! select the voters. Labels whose address needs review are written all
+//! the same, and listed by the position of the voter in the roster.
+
+use std::fmt;
+use std::fs;
+use std::str::FromStr;
+use super::*;
+use crate::voter_roster::{AddressCountry, MailingLabel, MailingLabelRow};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MailingLabelFormat { Csv, Text }
+
+pub const MAILING_LABEL_FORMATS: [&str; 2] = ["csv", "text"];
+
+impl FromStr for MailingLabelFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        match value {
+            "csv" => Ok(MailingLabelFormat::Csv),
+            "text" => Ok(MailingLabelFormat::Text),
+            _ => Err(format!("Unknown mailing label format: {}", value))
+        }
+    }
+}
+
+/// Mailing labels written for the vendor.
+#[derive(Debug, Clone, Serialize)]
+pub struct MailingLabelExport {
+    pub file: String,
+    pub labels: usize,
+    /// Roster positions of the voters whose address needs review.
+    pub review: Vec<usize>
+}
+
+impl fmt::Display for MailingLabelExport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Wrote {} mailing labels to {}", self.labels, self.file)?;
+        if !self.review.is_empty() {
+            let positions: Vec<String> = self.review.iter().map(usize::to_string).collect();
+            write!(f, "\nAddresses to review ({}): voters {}", self.review.len(), positions.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// Mailing labels in `format`.
+pub fn mailing_label_bytes(labels: &[MailingLabel], format: MailingLabelFormat) -> Result<Vec<u8>> {
+    match format {
+        MailingLabelFormat::Csv => {
+            let mut csvwriter = csv::Writer::from_writer(Vec::new());
+            for (n, label) in labels.iter().enumerate() {
+                csvwriter.serialize(MailingLabelRow::new(n + 1, label))?;
+            }
+            Ok(csvwriter.into_inner().map_err(|err| err.to_string())?)
+        },
+        MailingLabelFormat::Text => Ok(labels.iter()
+            .map(|label| format!("{}\n", label))
+            .collect::<Vec<String>>()
+            .join("\n")
+            .into_bytes())
+    }
+}
+
+pub fn export_mailing_labels(pollconf_filename: &str, format: MailingLabelFormat, origin: AddressCountry, out: &str) -> Result<()> {
+    // Read poll configuration file.
+    let secured_poll_configuration = read_poll_configuration_file(pollconf_filename)?;
+
+    // Reconstruct the Poll Master Key from the trustee passwords.
+    let (poll_master_key, aead_pmk) = read_poll_master_key(&secured_poll_configuration);
+
+    // Ensure the data directory exists.
+    let datadir_path = ensure_poll_data_directory_exists(&secured_poll_configuration, &aead_pmk)?;
+
+    // Decrypt poll configuration state.
+    let pollconf_aead_values = secured_poll_configuration.encrypted_poll_configuration.values()?;
+    let serialized_pollconf = aead_decrypt(&aead_pmk, &pollconf_aead_values)?;
+    let pollconf: PollConfiguration = serde_yaml::from_slice(&serialized_pollconf).unwrap();
+    if !pollconf.poll_state.summands_drawn {
+        return Err("Summands must be drawn to select the voters the ballots are mailed to.".into());
+    }
+
+    // Select the voters, in the order of the ballots.
+    let poll_secrets: PollSecrets = PollSecrets::for_poll(&poll_master_key, pollconf.generation_seed.as_deref())?;
+    let roster = read_voter_roster(&pollconf)?;
+    let labels = roster.mailing_labels(&select_poll_voters(&pollconf, &poll_secrets)?, origin)?;
+
+    for label in labels.iter() {
+        if let Some(problem) = &label.problem {
+            warn!("Address of voter {} needs review: {}", label.position, problem);
+        }
+    }
+    fs::write(out, mailing_label_bytes(&labels, format)?)?;
+
+    // Labels follow the ballots, so ballot `n` is mailed with label `n`.
+    let serials: Vec<BallotSerial> = (0..labels.len()).collect();
+    record_ballot_states(&datadir_path, BallotState::Mailed, &serials)?;
+    output::emit(&MailingLabelExport {
+        file: out.to_owned(),
+        labels: labels.len(),
+        review: labels.iter()
+            .filter(|label| label.problem.is_some())
+            .map(|label| label.position)
+            .collect()
+    })
+}
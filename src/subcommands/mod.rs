@@ -8,7 +8,9 @@ use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
 use std::fs::{File, DirBuilder};
 use serde::{Serialize, Deserialize};
+use zeroize::Zeroizing;
 use crate::*;
+use crate::progress::Progress;
 
 pub mod helpers;
 pub use helpers::*;
@@ -19,6 +21,12 @@ pub use create_new_poll::*;
 pub mod bind_roster;
 pub use bind_roster::*;
 
+pub mod roster_policy;
+pub use roster_policy::*;
+
+pub mod roster_chunks;
+pub use roster_chunks::*;
+
 pub mod generate_poll_commitments;
 pub use generate_poll_commitments::*;
 
@@ -31,17 +39,124 @@ pub use generate_print_files::*;
 pub mod record_audited_ballots;
 pub use record_audited_ballots::*;
 
+pub mod approve_commit;
+pub use approve_commit::*;
+
+pub mod spoil_ballot;
+pub use spoil_ballot::*;
+
+pub mod replacement_envelope;
+pub use replacement_envelope::*;
+
+pub mod provisional_ballots;
+pub use provisional_ballots::*;
+
 pub mod record_votes;
 pub use record_votes::*;
 
+pub mod unlock_votes;
+pub use unlock_votes::*;
+
 pub mod generate_tally_audit;
 pub use generate_tally_audit::*;
 
 pub mod generate_poll_revelations;
 pub use generate_poll_revelations::*;
 
+pub mod finalize_tally;
+pub use finalize_tally::*;
+
+pub mod verify_question;
+pub use verify_question::*;
+
+pub mod verify_print_file;
+pub use verify_print_file::*;
+
+pub mod check_votecodes;
+pub use check_votecodes::*;
+
+pub mod regenerate_ballots;
+pub use regenerate_ballots::*;
+
+pub mod export_ballots;
+pub use export_ballots::*;
+
+pub mod mailing_labels;
+pub use mailing_labels::*;
+
+pub mod undelivered_ballots;
+pub use undelivered_ballots::*;
+
+pub mod escrow_ballot_map;
+pub use escrow_ballot_map::*;
+
+pub mod stamp_commit;
+pub use stamp_commit::*;
+
 pub mod sign;
 pub use sign::*;
 
 pub mod proofs;
-pub use proofs::*;
\ No newline at end of file
+pub use proofs::*;
+
+pub mod reveal_cell;
+pub use reveal_cell::*;
+
+pub mod poll_status;
+pub use poll_status::*;
+pub mod verify_receipt;
+pub use verify_receipt::*;
+
+pub mod monitor_poll;
+pub use monitor_poll::*;
+
+pub mod cache_transactions;
+pub use cache_transactions::*;
+
+pub mod import_node_export;
+pub use import_node_export::*;
+
+pub mod compare_tally;
+pub use compare_tally::*;
+
+pub mod spend_report;
+pub use spend_report::*;
+
+pub mod interference_report;
+pub use interference_report::*;
+
+pub mod rotate_key;
+pub use rotate_key::*;
+
+pub mod sign_transaction;
+pub use sign_transaction::*;
+
+#[cfg(feature = "keyring")]
+pub mod network_keyring;
+#[cfg(feature = "keyring")]
+pub use network_keyring::*;
+
+pub mod simulate_poll;
+pub use simulate_poll::*;
+
+pub mod logic_accuracy;
+pub use logic_accuracy::*;
+
+#[cfg(feature = "email")]
+pub mod send_email;
+#[cfg(feature = "email")]
+pub use send_email::*;
+
+#[cfg(feature = "server")]
+pub mod serve_poll;
+#[cfg(feature = "server")]
+pub use serve_poll::*;
+
+pub mod bulletin_board;
+pub use self::bulletin_board::*;
+
+pub mod workspace;
+pub use self::workspace::*;
+
+pub mod public_archive;
+pub use public_archive::*;
@@ -0,0 +1,120 @@
+//! # Command: Monitor Poll
+//!
+//! `monitor_poll` follows the votes submitted for a committed poll on the
+//! blockchain, either printing a report on every refresh or showing the
+//! terminal UI. With the `webhooks` feature, the poll events found by the
+//! monitor are posted to the webhooks of a configuration file. With the
+//! `server` feature, the metrics are served for Prometheus. Given a number
+//! of transactions, the monitor warns when the commit or relayer account
+//! pays for fewer.
+//!
+//! An interrupt (Ctrl-C) stops the monitor gracefully: the blocks already
+//! read are counted, the pending events are delivered and the last report
+//! is printed before it exits.
+
+use std::time::Duration;
+use super::*;
+use crate::blockchain::{BalanceAlert, BalanceWatch};
+use crate::monitor::Monitor;
+use crate::monitor::tui::run_tui;
+
+#[allow(clippy::too_many_arguments)]
+pub fn monitor_poll(network: &str, pollconf_filename: &str, interval: u64, tui: bool, once: bool, webhooks: Option<&str>, metrics: Option<&str>, low_balance: Option<u64>) -> Result<()> {
+    if webhooks.is_some() && !cfg!(feature = "webhooks") {
+        return Err("Webhooks require the webhooks feature.".into());
+    }
+    if metrics.is_some() && !cfg!(feature = "server") {
+        return Err("Metrics endpoint requires the server feature.".into());
+    }
+
+    #[cfg(feature = "server")]
+    if let Some(metrics) = metrics {
+        let address: std::net::SocketAddr = metrics.parse()?;
+        tokio::spawn(async move {
+            if let Err(err) = crate::server::serve_metrics(address).await {
+                error!("Metrics endpoint stopped: {}", err);
+            }
+        });
+    }
+
+    // Read poll configuration file.
+    let secured_poll_configuration = read_poll_configuration_file(pollconf_filename)?;
+
+    // Reconstruct the Poll Master Key from the trustee passwords.
+    let (poll_master_key, aead_pmk) = read_poll_master_key(&secured_poll_configuration);
+
+    // Ensure the data directory exists.
+    let datadir_path = ensure_poll_data_directory_exists(&secured_poll_configuration, &aead_pmk)?;
+
+    // Decrypt poll configuration state.
+    let pollconf_aead_values = secured_poll_configuration.encrypted_poll_configuration.values()?;
+    let serialized_pollconf = aead_decrypt(&aead_pmk, &pollconf_aead_values)?;
+    let pollconf: PollConfiguration = serde_yaml::from_slice(&serialized_pollconf).unwrap();
+
+    // Votes are only accepted after the commitment was posted.
+    let commit_receipt = match read_commit_receipt(&datadir_path)? {
+        Some(commit_receipt) => commit_receipt,
+        None => return Err("Poll has not been committed to the blockchain.".into())
+    };
+
+    // Re-construct the ballots to recognize the vote codes of the poll.
+    let poll_secrets: PollSecrets = PollSecrets::for_poll(&poll_master_key, pollconf.generation_seed.as_deref())?;
+    let ballots = votecode_serials(&poll_secrets, pollconf.num_ballots);
+
+    // Re-construct the audited ballots.
+    let audited_ballots: Vec<BallotSerial> = pollconf.audited_ballots.clone().unwrap_or_default().iter()
+        .filter_map(|serial| serial.parse().ok())
+        .collect();
+
+    let mut monitor = Monitor::new(
+        network,
+        &datadir_path,
+        commit_receipt.submitted_at_block,
+        &commit_receipt.transaction_hash,
+        ballots,
+        audited_ballots);
+    monitor.set_payload_identifier(&pollconf.payload_identifier(&datadir_path));
+    if let Some(vote_address) = &commit_receipt.vote_address {
+        monitor.follow_vote_address(vote_address);
+    }
+    if let Some(end_block) = commit_receipt.end_block {
+        monitor.set_end_block(end_block);
+    }
+    if let Some(low_balance) = low_balance {
+        monitor.watch_balances(BalanceWatch::new(BalanceAlert::new(low_balance)));
+    }
+
+    #[cfg(feature = "webhooks")]
+    if let Some(webhooks) = webhooks {
+        use crate::webhooks::{WebhookConfiguration, WebhookNotifier};
+        let configuration = WebhookConfiguration::from_file(&webhooks)?;
+        monitor.set_vote_thresholds(configuration.vote_thresholds.clone());
+        monitor.add_sink(Box::new(WebhookNotifier::new(configuration, pollconf.signing_key.clone())));
+
+        // Re-read the poll state on every refresh to report when voting closes.
+        let pollconf_filename = pollconf_filename.to_owned();
+        let network = network.to_owned();
+        monitor.watch_voting_window(Box::new(move || {
+            let secured_poll_configuration = read_poll_configuration_file(&pollconf_filename)?;
+            Ok(read_poll_status(&network, &secured_poll_configuration, &aead_pmk, false)?.voting_open)
+        }));
+    }
+
+    let interval = Duration::from_secs(interval);
+
+    if tui {
+        return run_tui(&mut monitor, interval);
+    }
+    let shutdown = monitor.shutdown();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            info!("Interrupted, stopping the monitor");
+            shutdown.request();
+        }
+    });
+    let shutdown = monitor.shutdown();
+    loop {
+        output::emit(monitor.refresh())?;
+        if once || shutdown.wait(interval) { return Ok(()); }
+    }
+}
@@ -0,0 +1,55 @@
+//! # Command: Keyring
+//!
+//! `store_keyring_entry` and `delete_keyring_entry` manage the keys and
+//! node of a network kept in the operating system keyring, read
+//! by network configurations with `keyring: true`. The value is prompted
+//! for, so that it never appears in the shell history.
+
+use std::fmt;
+use secrecy::SecretString;
+use super::*;
+use crate::blockchain::{store_keyring_secret, delete_keyring_secret, KEYRING_SERVICE};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyringEntry {
+    pub service: String,
+    pub network: String,
+    pub entry: String,
+    /// Whether the entry is stored after the command.
+    pub stored: bool
+}
+
+impl fmt::Display for KeyringEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.stored {
+            true => write!(f, "{} of network {} stored in the {} keyring", self.entry, self.network, self.service),
+            false => write!(f, "{} of network {} removed from the {} keyring", self.entry, self.network, self.service)
+        }
+    }
+}
+
+pub fn store_keyring_entry(network: &str, entry: &str) -> Result<()> {
+    let prompt = format!("{} of network {}: ", entry, network);
+    let secret = SecretString::new(rpassword::read_password_from_tty(Some(&prompt))?);
+    store_keyring_secret(network, entry, &secret)?;
+
+    output::emit(&KeyringEntry {
+        service: KEYRING_SERVICE.to_owned(),
+        network: network.to_owned(),
+        entry: entry.to_owned(),
+        stored: true
+    })
+}
+
+pub fn delete_keyring_entry(network: &str, entry: &str) -> Result<()> {
+    if !delete_keyring_secret(network, entry)? {
+        return Err(format!("{} of network {} is not stored in the keyring.", entry, network).into());
+    }
+
+    output::emit(&KeyringEntry {
+        service: KEYRING_SERVICE.to_owned(),
+        network: network.to_owned(),
+        entry: entry.to_owned(),
+        stored: false
+    })
+}
@@ -0,0 +1,171 @@
+//! # Command: Poll Status
+//!
+//! `poll_status` reports where the poll is in its lifecycle from the
+//! secured poll configuration, the commit receipt and, optionally,
+//! the blockchain.
+
+use std::fmt;
+use super::*;
+use crate::ballots::{read_ballot_states, BallotStateCounts, UndeliveredPolicy};
+use crate::blockchain::{CommitReceipt, CommitmentCheck, FinalizationReceipt, TransactionStatus};
+use crate::blockchain::blocking::{check_commitment, transaction_status};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PollStatus {
+    pub poll_identifier: String,
+    pub poll_state: PollState,
+    pub num_ballots: usize,
+    pub voter_roster_size: usize,
+    pub audited_ballots: usize,
+    /// Ballots spoiled after they were issued and replaced.
+    pub ballots_reissued: usize,
+    pub provisional_ballots: usize,
+    /// Provisional ballots whose voters are not yet found eligible or not.
+    pub provisional_pending: usize,
+    /// Ballots the mailing vendor could not deliver.
+    pub ballots_undelivered: usize,
+    pub undelivered_policy: UndeliveredPolicy,
+    /// Issued ballots neither audited nor undelivered, expected to be voted.
+    pub ballots_delivered: usize,
+    pub votes_recorded: usize,
+    /// Share of the delivered ballots voted, once votes are recorded.
+    pub turnout: Option<f64>,
+    /// Ballots in each issuance state, as tracked by the workflows.
+    pub ballot_states: BallotStateCounts,
+    /// Share of the ballots tracked as mailed or later that were voted,
+    /// once votes are recorded.
+    pub mailed_turnout: Option<f64>,
+    pub voting_open: bool,
+    pub commit_receipt: Option<CommitReceipt>,
+    pub commit_transaction: Option<TransactionStatus>,
+    /// Commitment transaction read back from the chain, checked against the
+    /// commit receipt.
+    pub commitment: Option<CommitmentCheck>,
+    pub finalization_receipt: Option<FinalizationReceipt>
+}
+
+impl fmt::Display for PollStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn yes_no(value: bool) -> &'static str { if value { "yes" } else { "no" } }
+        writeln!(f, "Poll:               {}", self.poll_identifier)?;
+        writeln!(f, "Roster bound:       {} ({} voters)", yes_no(self.poll_state.roster_committed), self.voter_roster_size)?;
+        writeln!(f, "Planes committed:   {} ({} ballots)", yes_no(self.poll_state.columns_committed), self.num_ballots)?;
+        writeln!(f, "Summands drawn:     {}", yes_no(self.poll_state.summands_drawn))?;
+        writeln!(f, "Ballots audited:    {} ({} spoiled)", yes_no(self.poll_state.ceremony_conducted), self.audited_ballots)?;
+        if 0 < self.ballots_reissued {
+            writeln!(f, "Ballots reissued:   {}", self.ballots_reissued)?;
+        }
+        if 0 < self.provisional_ballots {
+            writeln!(f, "Provisional:        {} ({} pending)", self.provisional_ballots, self.provisional_pending)?;
+        }
+        if 0 < self.ballots_undelivered {
+            writeln!(f, "Undeliverable:      {} ({})", self.ballots_undelivered, self.undelivered_policy)?;
+        }
+        match &self.commit_receipt {
+            Some(receipt) => writeln!(f, "Posted on chain:    yes (tx {}, block {})", receipt.transaction_hash, receipt.submitted_at_block)?,
+            None => writeln!(f, "Posted on chain:    no")?
+        }
+        if let Some(transaction) = &self.commit_transaction {
+            match (transaction.block_number, transaction.finalized) {
+                (Some(block), true) => writeln!(f, "Commit confirmed:   yes (block {}, {} confirmations)", block, transaction.confirmations)?,
+                (Some(block), false) => writeln!(f, "Commit confirmed:   no (block {} not final, {} confirmations)", block, transaction.confirmations)?,
+                (None, _) => writeln!(f, "Commit confirmed:   no (pending)")?
+            }
+        }
+        if let Some(commitment) = &self.commitment {
+            writeln!(f, "Commitment intact:  {}", commitment)?;
+        }
+        writeln!(f, "Voting window open: {}", yes_no(self.voting_open))?;
+        writeln!(f, "Votes recorded:     {}", self.votes_recorded)?;
+        if let Some(turnout) = self.turnout {
+            writeln!(f, "Turnout:            {:.1}% of {} delivered ballots", 100.0 * turnout, self.ballots_delivered)?;
+        }
+        if 0 < self.ballot_states.tracked() {
+            writeln!(f, "Ballot states:      {}", self.ballot_states)?;
+        }
+        if let Some(turnout) = self.mailed_turnout {
+            writeln!(f, "Mailed turnout:     {:.1}% of {} mailed ballots", 100.0 * turnout, self.ballot_states.mailed_or_later())?;
+        }
+        writeln!(f, "Tally revealed:     {}", yes_no(self.poll_state.columns_revealed))?;
+        match &self.finalization_receipt {
+            Some(receipt) => write!(f, "Tally finalized:    yes (tx {}, block {})", receipt.transaction_hash, receipt.submitted_at_block),
+            None => write!(f, "Tally finalized:    no")
+        }
+    }
+}
+
+pub fn poll_status(network: &str, pollconf_filename: &str, check_chain: bool) -> Result<()> {
+    // Read poll configuration file.
+    let secured_poll_configuration = read_poll_configuration_file(pollconf_filename)?;
+
+    // Reconstruct the Poll Master Key from the trustee passwords.
+    let (_poll_master_key, aead_pmk) = read_poll_master_key(&secured_poll_configuration);
+
+    let status = read_poll_status(network, &secured_poll_configuration, &aead_pmk, check_chain)?;
+    output::emit(&status)
+}
+
+/// Collect the status of the poll once the Poll Master Key is known,
+/// checking the commitment on `network` with `check_chain`.
+pub fn read_poll_status(network: &str, secured_poll_configuration: &SecuredPollConfiguration, aead_pmk: &AEADKey, check_chain: bool) -> Result<PollStatus> {
+    // Ensure the data directory exists.
+    let datadir_path = ensure_poll_data_directory_exists(secured_poll_configuration, aead_pmk)?;
+
+    // Decrypt poll configuration state.
+    let pollconf_aead_values = secured_poll_configuration.encrypted_poll_configuration.values()?;
+    let serialized_pollconf = aead_decrypt(aead_pmk, &pollconf_aead_values)?;
+    let pollconf: PollConfiguration = serde_yaml::from_slice(&serialized_pollconf).unwrap();
+
+    // Read the commit receipt, if the poll was committed.
+    let commit_receipt = read_commit_receipt(&datadir_path)?;
+
+    // Read the finalization receipt, if the tally was finalized.
+    let finalization_receipt = read_finalization_receipt(&datadir_path)?;
+
+    // Check the commitment transaction on chain, and that it still
+    // carries the root of the receipt.
+    let (commit_transaction, commitment) = match (&commit_receipt, check_chain) {
+        (Some(receipt), true) => (Some(transaction_status(network, &receipt.transaction_hash)?), Some(check_commitment(network, receipt)?)),
+        _ => (None, None)
+    };
+
+    // Ballots expected to be voted: the issued ballots not audited, nor
+    // returned by the mailing vendor.
+    let audited_ballots = pollconf.audited_ballots.as_ref().map_or(0, |ballots| ballots.len());
+    let ballots_delivered = pollconf.serial_ranges().issued.count
+        .saturating_sub(audited_ballots)
+        .saturating_sub(pollconf.undelivered_ballots.len());
+    let votes_recorded = pollconf.votes.as_ref().map_or(0, |votes| votes.len());
+    let turnout = match (&pollconf.votes, ballots_delivered) {
+        (Some(_), 1..) => Some(votes_recorded as f64 / ballots_delivered as f64),
+        _ => None
+    };
+    let ballot_states = read_ballot_states(&datadir_path)?.counts();
+    let mailed_turnout = match (&pollconf.votes, ballot_states.mailed_or_later()) {
+        (Some(_), 1..) => Some(ballot_states.voted as f64 / ballot_states.mailed_or_later() as f64),
+        _ => None
+    };
+
+    Ok(PollStatus {
+        poll_identifier: datadir_path,
+        num_ballots: pollconf.num_ballots,
+        voter_roster_size: pollconf.voter_roster_size,
+        audited_ballots,
+        ballots_reissued: pollconf.ballot_amendments.len(),
+        provisional_ballots: pollconf.provisional_ballots.len(),
+        provisional_pending: pollconf.provisional_ballots.iter().filter(|ballot| ballot.eligible.is_none()).count(),
+        ballots_undelivered: pollconf.undelivered_ballots.len(),
+        undelivered_policy: pollconf.undelivered_policy,
+        ballots_delivered,
+        votes_recorded,
+        turnout,
+        ballot_states,
+        mailed_turnout,
+        voting_open: pollconf.poll_state.ceremony_conducted && !pollconf.poll_state.votes_committed,
+        poll_state: pollconf.poll_state.clone(),
+        commit_receipt,
+        commit_transaction,
+        commitment,
+        finalization_receipt
+    })
+}
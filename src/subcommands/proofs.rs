@@ -3,21 +3,81 @@
 
 use super::*;
 use crate::blockchain::merkle::*;
+use crate::blockchain::{build_proof_index, leaf_index_path, proof_index_path, LeafEntry, LeafIndex, ProofIndex};
+use std::fmt;
 use std::io::Read;
+use std::time::Duration;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GeneratedProof {
+    pub data: String,
+    pub lemma: Vec<String>,
+    pub path: Vec<usize>
+}
+
+#[derive(Serialize)]
+pub struct ProofValidation {
     data: String,
-    lemma: Vec<String>,
-    path: Vec<usize>
+    valid: bool
+}
+
+impl fmt::Display for GeneratedProof {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", serde_yaml::to_string(self).map_err(|_| fmt::Error)?)
+    }
+}
+
+impl fmt::Display for ProofValidation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.valid {
+            true => write!(f, "Proof of inclusion validated correctly"),
+            false => write!(f, "Wrong proof of inclusion")
+        }
+    }
+}
+
+#[derive(Serialize)]
+pub struct IndexedProofs {
+    merkle_tree: String,
+    proof_index: String,
+    leafs: usize
+}
+
+impl fmt::Display for IndexedProofs {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Indexed the proofs of the {} leaves of {} in {}", self.leafs, self.merkle_tree, self.proof_index)
+    }
 }
 
 pub fn generate_proof(path: &str, data: &str) -> Result<()>{
-    // Load tree from YAML file
-    let tree: MerkleRoot = load_tree(String::from(path))?;
+    output::emit(&inclusion_proof(path, data)?)
+}
+
+/// Generate the proof of inclusion for data in the Merkle tree stored at `path`.
+pub fn inclusion_proof(path: &str, data: &str) -> Result<GeneratedProof> {
+    find_inclusion_proof(path, data)?
+        .ok_or_else(|| format!("Data not found in tree: {}", data).into())
+}
+
+/// Proof of inclusion for data in the Merkle tree stored at `path`, or
+/// `None` if the tree does not contain the data. The tree is mapped in
+/// memory rather than loaded, and the proof is read from the proof index of
+/// the tree when it is current.
+pub fn find_inclusion_proof(path: &str, data: &str) -> Result<Option<GeneratedProof>> {
+    check_artifact(Path::new(path))?;
+
+    // Map tree from YAML file
+    let tree = MappedTree::open(path)?;
 
     // Generate proof of inclusion for data
-    let m_path = get_path(tree, String::from(data).to_owned())?;
+    let proof = match ProofIndex::open_current(&proof_index_path(Path::new(path)), &tree)? {
+        Some(index) => index.proof(data)?,
+        None => tree.proof(data)?
+    };
+    let m_path = match proof {
+        Some(m_path) => m_path,
+        None => return Ok(None)
+    };
 
     // Get lemma and path
     let lemma = m_path.lemma();
@@ -25,7 +85,7 @@ pub fn generate_proof(path: &str, data: &str) -> Result<()>{
 
     // Print hex encoded lemma for usability
     let mut ser_lemma: Vec<String> = Vec::new();
-    lemma.into_iter().for_each(|l| {
+    lemma.iter().for_each(|l| {
         let encoded = hex::encode(l);
         ser_lemma.push(encoded);
     });
@@ -35,10 +95,127 @@ pub fn generate_proof(path: &str, data: &str) -> Result<()>{
         lemma: ser_lemma,
         path: p_path.to_vec()
     };
-    let ser_data = serde_yaml::to_string(&ser_data)?;
-    println!("{}", ser_data);
+    Ok(Some(ser_data))
+}
+
+/// Leaf to locate with the leaf index of a tree.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LeafTarget {
+    /// Voter at a roster position.
+    Voter(usize),
+    /// Audited ballot and plane cells of a ballot serial.
+    Ballot(BallotSerial),
+    Leaf(usize)
+}
+
+impl fmt::Display for LeafTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            LeafTarget::Voter(position) => write!(f, "Voter {}", position),
+            LeafTarget::Ballot(serial) => write!(f, "Ballot {}", serial),
+            LeafTarget::Leaf(leaf) => write!(f, "Leaf {}", leaf)
+        }
+    }
+}
+
+/// Leaf located with the leaf index, with its proof of inclusion. The
+/// proof is checked with the data of the leaf, which the tree does not
+/// hold.
+#[derive(Debug, Clone, Serialize)]
+pub struct LocatedLeaf {
+    #[serde(flatten)]
+    pub entry: LeafEntry,
+    pub lemma: Vec<String>,
+    pub path: Vec<usize>
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct LocatedLeaves {
+    pub leaves: Vec<LocatedLeaf>
+}
+
+impl fmt::Display for LocatedLeaves {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (n, leaf) in self.leaves.iter().enumerate() {
+            if 0 < n {
+                writeln!(f)?;
+            }
+            write!(f, "{} ({})", leaf.entry, leaf.lemma.first().map(String::as_str).unwrap_or_default())?;
+        }
+        Ok(())
+    }
+}
+
+/// Leaf index of the Merkle tree stored at `path`, if stored with it.
+pub fn read_leaf_index(path: &str) -> Result<Option<LeafIndex>> {
+    let tree = MappedTree::open(path)?;
+    let index_path = leaf_index_path(Path::new(path));
+    if index_path.exists() {
+        check_artifact(&index_path)?;
+    }
+    LeafIndex::open_current(&index_path, &hex::encode(tree.root()?))
+}
+
+/// Proof of inclusion of leaf `leaf` of the Merkle tree stored at `path`,
+/// with `data` as its data.
+pub fn leaf_inclusion_proof(path: &str, leaf: usize, data: &str) -> Result<GeneratedProof> {
+    let tree = MappedTree::open(path)?;
+    let proof = tree.proof_at(leaf)?;
+    if proof.item() != leaf_hash(data) {
+        return Err(format!("Leaf {} of {} does not hold the data.", leaf, path).into());
+    }
+    Ok(GeneratedProof {
+        data: data.to_owned(),
+        lemma: proof.lemma().iter().map(hex::encode).collect(),
+        path: proof.path().to_vec()
+    })
+}
+
+/// Locate `target` in the Merkle tree stored at `path` with its leaf
+/// index.
+pub fn locate_leaves(path: &str, target: LeafTarget) -> Result<LocatedLeaves> {
+    check_artifact(Path::new(path))?;
+    let index = read_leaf_index(path)?
+        .ok_or_else(|| format!("{} has no leaf index of its root.", path))?;
+    let leaves: Vec<usize> = match target {
+        LeafTarget::Voter(position) => index.voter_leaf(position).into_iter().collect(),
+        LeafTarget::Ballot(serial) => index.ballot_leaves(serial),
+        LeafTarget::Leaf(leaf) => vec![leaf]
+    };
+    let tree = MappedTree::open(path)?;
+    let leaves = leaves.into_iter()
+        .map(|leaf| {
+            let entry = index.entry(leaf).ok_or_else(|| format!("{} has no leaf {}.", path, leaf))?;
+            let proof = tree.proof_at(leaf)?;
+            Ok(LocatedLeaf {
+                entry,
+                lemma: proof.lemma().iter().map(hex::encode).collect(),
+                path: proof.path().to_vec()
+            })
+        })
+        .collect::<Result<Vec<LocatedLeaf>>>()?;
+    if leaves.is_empty() {
+        return Err(format!("{} is not in {}.", target, path).into());
+    }
+    Ok(LocatedLeaves { leaves })
+}
+
+pub fn locate_leaf(path: &str, target: LeafTarget) -> Result<()> {
+    output::emit(&locate_leaves(path, target)?)
+}
 
-    Ok(())
+/// Precompute the proofs of inclusion of every leaf of the Merkle tree
+/// stored at `path` into its proof index, pausing `throttle` between
+/// chunks of leaves.
+pub fn index_proofs(path: &str, throttle: Duration) -> Result<()> {
+    check_artifact(Path::new(path))?;
+    let index_path = proof_index_path(Path::new(path));
+    let index = build_proof_index(Path::new(path), &index_path, throttle)?;
+    output::emit(&IndexedProofs {
+        merkle_tree: path.to_owned(),
+        proof_index: index_path.to_string_lossy().into_owned(),
+        leafs: index.leafs()
+    })
 }
 
 pub fn validate_proof(proof_path: &str) -> Result<()> {
@@ -53,10 +230,15 @@ pub fn validate_proof(proof_path: &str) -> Result<()> {
     // Load yaml array into Vec<String> of hashes
     let tree_data: GeneratedProof = serde_yaml::from_str(&ser_data).unwrap();
 
-    if !validate(tree_data.lemma, tree_data.path, tree_data.data)?{
-        panic!("Wrong proof of inclusion");
-    }
+    let data = tree_data.data.clone();
+    let validation = ProofValidation {
+        valid: validate(tree_data.lemma, tree_data.path, tree_data.data)?,
+        data
+    };
+    output::emit(&validation)?;
 
-    println!("Proof of inclusion validated correctly");
-    Ok(())
+    match validation.valid {
+        true => Ok(()),
+        false => Err(validation.to_string().into())
+    }
 }
\ No newline at end of file
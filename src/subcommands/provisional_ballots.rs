@@ -0,0 +1,79 @@
+//! # Command: Provisional Ballots
+//!
+//! `mark_provisional` marks a ballot issued to a voter whose eligibility is
+//! still to be checked, before the votes are recorded (step 6). Its votes
+//! are recorded apart from the other votes. `resolve_provisional` records
+//! whether the voter was found eligible; the votes of the accepted ballots
+//! are folded into the tally revealed by step 8, which requires every
+//! provisional ballot to be resolved.
+
+use super::*;
+use crate::votes::ProvisionalBallot;
+
+pub fn mark_provisional(pollconf_filename: &str, serial: BallotSerial) -> Result<()> {
+    update_provisional_ballots(pollconf_filename, |pollconf| {
+        if !pollconf.poll_state.ceremony_conducted {
+            return Err("Ballots are marked provisional after they are issued: record the audited ballots (step 4) first.".into());
+        }
+        if pollconf.poll_state.votes_committed {
+            return Err("Votes were already recorded (step 6): ballots can no longer be marked provisional.".into());
+        }
+        if serial >= pollconf.num_ballots {
+            return Err(format!("Ballot {} is not a ballot of the poll.", serial).into());
+        }
+        if pollconf.audited_ballots.iter().flatten().any(|audited| audited.parse() == Ok(serial)) {
+            return Err(format!("Ballot {} was audited.", serial).into());
+        }
+        if pollconf.spoiled_ballots().contains(&serial) {
+            return Err(format!("Ballot {} was spoiled.", serial).into());
+        }
+        if pollconf.provisional_ballots.iter().any(|ballot| ballot.serial == serial) {
+            return Err(format!("Ballot {} is already provisional.", serial).into());
+        }
+
+        let ballot = ProvisionalBallot { serial, eligible: None };
+        pollconf.provisional_ballots.push(ballot.clone());
+        Ok(ballot)
+    })
+}
+
+pub fn resolve_provisional(pollconf_filename: &str, serial: BallotSerial, eligible: bool) -> Result<()> {
+    update_provisional_ballots(pollconf_filename, |pollconf| {
+        if pollconf.poll_state.columns_revealed {
+            return Err("The tally was already revealed (step 8): provisional ballots can no longer be resolved.".into());
+        }
+        let ballot = pollconf.provisional_ballots.iter_mut()
+            .find(|ballot| ballot.serial == serial)
+            .ok_or_else(|| format!("Ballot {} is not provisional.", serial))?;
+        ballot.eligible = Some(eligible);
+        Ok(ballot.clone())
+    })
+}
+
+// Apply `update` to the decrypted poll configuration and save it.
+fn update_provisional_ballots<F>(pollconf_filename: &str, update: F) -> Result<()>
+    where F: FnOnce(&mut PollConfiguration) -> Result<ProvisionalBallot>
+{
+    // Read poll configuration file.
+    let mut secured_poll_configuration = read_poll_configuration_file(pollconf_filename)?;
+
+    // Reconstruct the Poll Master Key from the trustee passwords.
+    let (_, aead_pmk) = read_poll_master_key(&secured_poll_configuration);
+
+    // Decrypt poll configuration state.
+    let pollconf_aead_values = secured_poll_configuration.encrypted_poll_configuration.values()?;
+    let serialized_pollconf = aead_decrypt(&aead_pmk, &pollconf_aead_values)?;
+    let mut pollconf: PollConfiguration = serde_yaml::from_slice(&serialized_pollconf).unwrap();
+
+    let ballot = update(&mut pollconf)?;
+
+    // Re-encrypt the poll configuration.
+    let serialized_pollconf = Zeroizing::new(serde_yaml::to_string(&pollconf)?);
+    secured_poll_configuration.encrypted_poll_configuration = AEADString::from_values(
+        aead_encrypt(&aead_pmk,
+                     Vec::new(),
+                     serialized_pollconf.as_bytes().to_vec())?);
+    write_poll_configuration_file(pollconf_filename, &secured_poll_configuration)?;
+
+    output::emit(&ballot)
+}
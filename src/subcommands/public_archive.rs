@@ -0,0 +1,72 @@
+//! # Command: Public Archive
+//!
+//! `export_archive` packs the public artifacts of a finalized poll into a
+//! signed archive for long-term keeping. `verify_public_archive` checks an
+//! archive without extracting it and `import_archive` checks it against
+//! the verification key of the poll and extracts it; neither needs the
+//! trustee passwords nor the blockchain.
+
+use super::*;
+use crate::workspace;
+
+/// Export the public artifacts of the poll into `archive_filename`, by
+/// default `<poll directory>-public.tar.gz`.
+pub fn export_archive(pollconf_filename: &str, merkle_tree: &str, transactions: &str, archive_filename: Option<&str>) -> Result<()> {
+    // Read poll configuration file.
+    let secured_poll_configuration = read_poll_configuration_file(pollconf_filename)?;
+
+    // Reconstruct the Poll Master Key from the trustee passwords.
+    let (_, aead_pmk) = read_poll_master_key(&secured_poll_configuration);
+
+    // Ensure the data directory exists.
+    let datadir_path = ensure_poll_data_directory_exists(&secured_poll_configuration, &aead_pmk)?;
+    if read_finalization_receipt(&datadir_path)?.is_none() {
+        return Err("The tally must be finalized before the poll is exported.".into());
+    }
+
+    // Decrypt poll configuration state.
+    let pollconf_aead_values = secured_poll_configuration.encrypted_poll_configuration.values()?;
+    let serialized_pollconf = aead_decrypt(&aead_pmk, &pollconf_aead_values)?;
+    let pollconf: PollConfiguration = serde_yaml::from_slice(&serialized_pollconf).unwrap();
+
+    // Authenticate the verification key.
+    let public_key = use_poll_verification_key(&secured_poll_configuration, &aead_pmk)?;
+
+    let public_configuration = PublicPollConfiguration::new(
+        &datadir_path,
+        &secured_poll_configuration.poll_trustees,
+        &pollconf,
+        &public_key);
+    let archive_path = match archive_filename {
+        Some(archive_filename) => PathBuf::from(archive_filename),
+        None => PathBuf::from(format!("{}-public.tar.gz", workspace::poll_directory_name(&datadir_path)))
+    };
+    let manifest = workspace::export_public_archive(
+        &public_configuration,
+        Path::new(&datadir_path),
+        Path::new(merkle_tree),
+        Path::new(transactions),
+        &archive_path,
+        &pollconf.signing_key)?;
+    output::emit(&manifest)
+}
+
+pub fn verify_public_archive(archive_filename: &str, public_key: Option<&str>) -> Result<()> {
+    let verification = workspace::verify_public_archive(Path::new(archive_filename), public_key)?;
+    output::emit(&verification)?;
+    match verification.valid() {
+        true => Ok(()),
+        false => Err("Public archive does not match its manifest or finalization.".into())
+    }
+}
+
+/// Import the archive into `dirname` once checked against `public_key`, the
+/// trusted verification key of the poll.
+pub fn import_archive(archive_filename: &str, dirname: &str, public_key: &str) -> Result<()> {
+    let verification = workspace::import_public_archive(Path::new(archive_filename), Path::new(dirname), public_key)?;
+    output::emit(&verification)?;
+    match verification.valid() {
+        true => Ok(()),
+        false => Err("Public archive does not match its manifest or finalization: nothing was imported.".into())
+    }
+}
@@ -2,16 +2,36 @@
 //!
 //! `record_audited_ballots` takes a file of ballots spoiled during the public
 //! audit and records them as part of the secured poll configuration.
+//!
+//! The commit that follows is recorded in the commit journal of the poll,
+//! so that an interrupted run is completed by running the command again.
+//!
+//! The root is only posted with the approvals of a quorum of trustees: the
+//! first run writes the commit proposal for them to approve with
+//! `approve_commit`, and the run with their approval files posts it.
+//!
+//! With the commit key kept offline, the run that would post the root
+//! writes the unsigned commitment transaction instead, to be signed on the
+//! offline machine with `sign_transaction`, and the run with the signed
+//! transaction posts it.
 
 use super::*;
+use crate::blockchain::{record_sent_transaction, CommitApproval, CommitJournal, CommitOutcome, CommitPhase, SignedTransaction, SpendPhase, UNSIGNED_TRANSACTION_FILENAME};
 
 #[derive(Debug, Clone, Deserialize)]
 pub struct AuditedBallotRecord {
     serial: BallotSerial
 }
 
-pub fn record_audited_ballots(pollconf_filename: &str, audited_ballots_filename: &str, force: bool) -> Result<()> {
+pub fn record_audited_ballots(network: &str, pollconf_filename: &str, audited_ballots_filename: &str, approval_filenames: &[String], signed_filename: Option<&str>, force: bool) -> Result<()> {
     let pollconf_path = Path::new(pollconf_filename);
+    let approvals = approval_filenames.iter()
+        .map(|filename| -> Result<CommitApproval> { Ok(serde_yaml::from_reader(File::open(filename)?)?) })
+        .collect::<Result<Vec<CommitApproval>>>()?;
+    let signed = match signed_filename {
+        Some(filename) => Some(serde_yaml::from_reader::<_, SignedTransaction>(File::open(filename)?)?),
+        None => None
+    };
 
     // Read poll configuration file.
     let mut secured_poll_configuration = read_poll_configuration_file(pollconf_filename)?;
@@ -27,13 +47,23 @@ pub fn record_audited_ballots(pollconf_filename: &str, audited_ballots_filename:
     let serialized_pollconf = aead_decrypt(&aead_pmk, &pollconf_aead_values)?;
     let mut pollconf: PollConfiguration = serde_yaml::from_slice(&serialized_pollconf).unwrap();
     
+    // Resume an interrupted or proposed commit.
+    let mut journal = CommitJournal::open(&datadir_path)?;
+    match journal.resumed_from {
+        Some(CommitPhase::Proposed) => info!("Resuming the commit proposed to the trustees, with {} approvals.", approvals.len()),
+        Some(CommitPhase::Unsigned) if signed.is_some() => info!("Resuming the commit with the commitment signed offline."),
+        Some(CommitPhase::Unsigned) => info!("Resuming the commit awaiting the signature of the offline commit key; the commitment is built again."),
+        Some(phase) => warn!("Resuming the commit interrupted at phase {:?}.", phase),
+        None => ()
+    }
+
     assert!(pollconf.poll_state.summands_drawn,
         "Content for public audit must be printed before marking audited ballots.");
-    assert!(!pollconf.poll_state.ceremony_conducted || force,
+    assert!(!pollconf.poll_state.ceremony_conducted || force || journal.resumed_from.is_some(),
         "Audited ballots already recorded. To re-record, pass --force.");
 
     // Derive the poll secrets.
-    let poll_secrets: PollSecrets = PollSecrets::derive(&poll_master_key);
+    let poll_secrets: PollSecrets = PollSecrets::for_poll(&poll_master_key, pollconf.generation_seed.as_deref())?;
     
     // Record audited ballots.
     let audited_ballots = {
@@ -46,9 +76,18 @@ pub fn record_audited_ballots(pollconf_filename: &str, audited_ballots_filename:
                 else { None }
             }).collect::<Vec<BallotSerial>>()
     };
-    pollconf.audited_ballots = Some(audited_ballots.iter()
+    let audited_ballots_recorded: Vec<String> = audited_ballots.iter()
         .map(|serial| serial.to_string())
-        .collect());
+        .collect();
+    if journal.is_posted() && journal.audited_ballots != audited_ballots_recorded {
+        return Err("Audited ballots differ from those of the interrupted commit, which already posted its root.".into());
+    }
+    pollconf.audited_ballots = Some(audited_ballots_recorded.clone());
+    record_ballot_states(&datadir_path, BallotState::Spoiled, &audited_ballots)?;
+    if !journal.is_posted() {
+        journal.audited_ballots = audited_ballots_recorded;
+        journal.record(CommitPhase::Started)?;
+    }
 
     // Post the Column Planes.
     let column_planes: Vec<Plane> = generate_column_planes(
@@ -95,7 +134,7 @@ pub fn record_audited_ballots(pollconf_filename: &str, audited_ballots_filename:
     // Update the poll state.
     pollconf.poll_state.ceremony_conducted = true;
     // Re-encrypt the poll configuration.
-    let serialized_pollconf = serde_yaml::to_string(&pollconf)?;
+    let serialized_pollconf = Zeroizing::new(serde_yaml::to_string(&pollconf)?);
     let secure_serialized_pollconf = AEADString::from_values(
         aead_encrypt(&aead_pmk,
                      Vec::new(),
@@ -105,8 +144,43 @@ pub fn record_audited_ballots(pollconf_filename: &str, audited_ballots_filename:
     serde_yaml::to_writer(
         File::create(pollconf_path)?,
         &secured_poll_configuration)?;
+    if !journal.is_posted() {
+        journal.record(CommitPhase::ConfigurationSaved)?;
+    }
 
-    blockchain::commit(pollconf, column_planes)?;
+    // Commit to the blockchain and keep the receipt.
+    let commit_receipt_path = {
+        let mut pathbuf = PathBuf::new();
+        pathbuf.push(&datadir_path);
+        pathbuf.push("commit_receipt");
+        pathbuf.set_extension("yaml");
+        pathbuf.into_boxed_path()
+    };
+    // Publish the ballot question committed with the poll.
+    let question = BallotQuestion::from_poll(&journal.poll_identifier, &pollconf);
+    std::fs::write(Path::new(&datadir_path).join(BALLOT_QUESTION_FILENAME), question.canonical())?;
+    let already_posted = journal.is_posted();
+    let receipt = match blockchain::blocking::commit(network, pollconf, column_planes, &mut journal, &approvals, signed.as_ref())? {
+        CommitOutcome::Posted(receipt) => receipt,
+        CommitOutcome::Proposed(_, check) => {
+            output::emit(&check)?;
+            return Ok(());
+        },
+        CommitOutcome::Unsigned(unsigned) => {
+            info!("Sign {} on the offline machine with sign-transaction, and run step 4 again with the signed transaction.",
+                Path::new(&datadir_path).join(UNSIGNED_TRANSACTION_FILENAME).display());
+            output::emit(&unsigned)?;
+            return Ok(());
+        }
+    };
+    if !already_posted {
+        record_sent_transaction(&datadir_path, SpendPhase::Commit, &receipt.transaction_hash, &receipt.address)?;
+    }
+    serde_yaml::to_writer(
+        File::create(commit_receipt_path)?,
+        &receipt)?;
+    journal.record(CommitPhase::Completed)?;
+    output::emit(&receipt)?;
 
     Ok(())
 }
@@ -1,35 +1,43 @@
 //! # Command: Record Votes
 //!
-//! `record_votes` takes a votes file (list of vote codes) and records them
-//! as part of the secured poll configuration.
+//! `record_votes` reads the votes of every vote source of the poll (the
+//! blockchain, transaction caches, SMS and web API exports and votes files
+//! listing vote codes), merges them by vote code and records them as part
+//! of the secured poll configuration, together with the number of votes
+//! attributed to every source. Votes for spoiled ballots and for undelivered
+//! ballots whose vote codes are invalidated are not recorded, and votes for
+//! provisional ballots are recorded apart until resolved.
+//! Duplicate submissions, ballots voted with both of their vote codes and
+//! votes mined after the end block are counted by the tally rules of the
+//! poll. Votes are only read from final blocks of the blockchain, by the
+//! finality policy of the network.
+//!
+//! Replacement ballots issued with fresh vote codes (see
+//! `ballots::envelope`) are voted with them only: their vote codes, opened
+//! by the print pipeline once voting closed, are read from the data
+//! directory, checked against the amendments of the poll and recorded in
+//! its audit log, and votes for them are recorded for the committed choices
+//! of the ballot.
+//!
+//! Votes for the test vote codes of the poll, issued for usability tests or
+//! as coercion canaries, and for the ballots of its reserved test serials
+//! are not counted: every submission of one is reported in
+//! `test_votes.yaml` in the data directory instead, which is not published.
 
-use std::cmp::max;
+use std::collections::{HashMap, HashSet};
+use rayon::prelude::*;
 use super::*;
+use crate::audit_log::{record_audit_event, AuditLogEvent};
+use crate::bulletin_board::sha256_hex;
+use crate::votes::*;
+use crate::blockchain::{BallotAmendment, BlockTimestamps};
+use crate::blockchain::blocking::final_block;
 
 
-#[derive(Debug, Clone, Deserialize)]
-pub struct VoteRecordFileRow {
-    votecode: String
-}
-
-
-impl VoteRecordFileRow {
-    fn to_votecode(self: &Self) -> VoteCode {
-        let mut votecode: VoteCode = [0; VOTE_CODE_LENGTH];
-        let votecode_vec: Vec<u8> = self.votecode.replace("-", "").split("")
-            .filter_map(|x| {
-                if 0 < x.len() { Some(u8::from_str_radix(x, 10).unwrap()) }
-                else { None }
-            })
-            .collect();
-        let copylen: usize = max(votecode_vec.len(), VOTE_CODE_LENGTH);
-        votecode.copy_from_slice(&votecode_vec[0..copylen]);
-        votecode
-    }
-}
-
-
-pub fn record_votes(pollconf_filename: &str, votes_file: &str, force: bool) -> Result<()> {
+/// Read the votes of `sources`, in this order, and record them. Vote codes
+/// submitted through several sources are attributed to the first one.
+/// With `verbose`, every counted vote is logged.
+pub fn record_votes(network: &str, pollconf_filename: &str, sources: &[Box<dyn VoteSource>], test_codes: &VoteCodeSet, verbose: bool, force: bool) -> Result<()> {
     let pollconf_path = Path::new(pollconf_filename);
 
     // Read poll configuration file.
@@ -45,14 +53,17 @@ pub fn record_votes(pollconf_filename: &str, votes_file: &str, force: bool) -> R
     let pollconf_aead_values = secured_poll_configuration.encrypted_poll_configuration.values()?;
     let serialized_pollconf = aead_decrypt(&aead_pmk, &pollconf_aead_values)?;
     let mut pollconf: PollConfiguration = serde_yaml::from_slice(&serialized_pollconf).unwrap();
-    
+
+    // Check the signed artifacts loaded with the poll verification key.
+    use_poll_verification_key(&secured_poll_configuration, &aead_pmk)?;
+
     assert!(pollconf.poll_state.ceremony_conducted,
         "Recording votes cannot take place prior to public audit.");
     assert!(!pollconf.poll_state.votes_committed || force,
         "Votes already committed. To re-commit, pass --force.");
 
     // Derive the poll secrets.
-    let poll_secrets: PollSecrets = PollSecrets::derive(&poll_master_key);
+    let poll_secrets: PollSecrets = PollSecrets::for_poll(&poll_master_key, pollconf.generation_seed.as_deref())?;
 
     // Re-construct the audited ballots.
     let audited_ballots: Vec<BallotSerial> = {
@@ -61,24 +72,127 @@ pub fn record_votes(pollconf_filename: &str, votes_file: &str, force: bool) -> R
             .collect()
     };
 
-    // Read the Votes file.
-    let votes: Vec<VoteCode> = {
-        let votes_path = Path::new(votes_file);
-        let mut csvreader = csv::Reader::from_path(votes_path)?;
-        let records = csvreader.deserialize::<VoteRecordFileRow>();
-        records.map(|row| { row.unwrap().to_votecode() }).collect()
+    // Votes are only accepted after the commitment was posted.
+    let commit_receipt = read_commit_receipt(&datadir_path)?;
+    if commit_receipt.is_none() && sources.iter().any(|source| source.channel() == VoteChannel::Chain) {
+        return Err("Poll has not been committed to the blockchain.".into());
+    }
+    let policy = pollconf.tally_rules.clone();
+    let end_block = policy.closing_block(commit_receipt.as_ref().and_then(|commit_receipt| commit_receipt.end_block));
+
+    // Encrypted votes are only decrypted once voting has closed.
+    let decryption_key = read_vote_decryption_key(&pollconf, &datadir_path)?;
+    if let (Some(time_lock), None) = (&pollconf.time_lock, &decryption_key) {
+        return Err(format!("Votes are time-locked until {}: run unlock-votes first.", time_lock.reveal_at).into());
+    }
+    if let (Some(_), Some(end_block)) = (&decryption_key, end_block) {
+        if final_block(network)? < end_block {
+            return Err(format!("Encrypted votes are decrypted once the end block {} is final.", end_block).into());
+        }
+    }
+    let window = VotingWindow {
+        poll_identifier: pollconf.payload_identifier(&datadir_path),
+        from_block: commit_receipt.as_ref().map_or(0, |commit_receipt| commit_receipt.submitted_at_block),
+        vote_address: commit_receipt.as_ref().and_then(|commit_receipt| commit_receipt.vote_address.clone()),
+        since: commit_receipt.as_ref().map(|commit_receipt| commit_receipt.posted_at),
+        end_block,
+        decryption_key
     };
+
+    // Read and merge the Votes of every source.
+    let ballot_votecodes = votecode_serials(&poll_secrets, pollconf.num_ballots);
+    let excluded_ballots = pollconf.excluded_ballots();
+
+    // The vote codes of the test ballots of the poll are test vote codes.
+    let test_serials = pollconf.serial_ranges().test_serials();
+    let mut test_codes = test_codes.clone();
+    if !test_serials.is_empty() {
+        generate_votecodes(&poll_secrets.votecode_root, 2 * pollconf.num_ballots).iter().enumerate()
+            .filter(|(n, _)| test_serials.contains(&(n / 2)))
+            .for_each(|(_, votecode)| { test_codes.insert(*votecode); });
+    }
+    let test_codes = &test_codes;
+
+    // Replacement ballots issued with fresh vote codes are voted with them
+    // only, for their committed choices.
+    let replacement_votecodes = read_replacement_votecodes(&pollconf, &datadir_path, &poll_secrets)?;
+    let fresh_ballots: HashSet<BallotSerial> = pollconf.ballot_amendments.iter()
+        .filter(|amendment| amendment.ballot_commitment.is_some())
+        .map(|amendment| amendment.replacement_serial)
+        .collect();
+    let mut merged = merge_votes(sources, &window, &policy, |votecode| test_codes.contains_str(votecode)
+        || replacement_votecodes.contains_key(votecode)
+        || ballot_votecodes.get_str(votecode).is_some_and(|serial| !excluded_ballots.contains(serial) && !fresh_ballots.contains(serial)))?;
+    merged.votes.iter_mut()
+        .for_each(|vote| if let Some(votecode) = replacement_votecodes.get(&vote.votecode) {
+            vote.votecode = votecode.clone();
+        });
+
+    // Report the votes for test vote codes instead of counting them.
+    let test_votes = split_test_votes(&mut merged, test_codes);
+    if !test_votes.is_empty() {
+        warn!("Votes for test vote codes excluded: {}", test_votes.len());
+    }
+    test_votes.iter()
+        .for_each(|vote| info!("Test vote: {} from {} {}", vote.votecode, vote.source, vote.transaction_hash));
+    serde_yaml::to_writer(
+        File::create(Path::new(&datadir_path).join("test_votes.yaml"))?,
+        &test_votes)?;
+    pollconf.test_votes = test_votes.len();
+
+    // Apply the tally rules to the ballots voted with both vote codes.
+    let uncounted_votes = split_uncounted_ballot_votes(&mut merged, &policy,
+        |votecode| ballot_votecodes.get_str(votecode).copied());
+    if !uncounted_votes.is_empty() {
+        warn!("Votes not counted for ballots voted with both vote codes: {}", uncounted_votes.len());
+    }
+    uncounted_votes.iter()
+        .for_each(|vote| info!("Vote not counted: {} from {} {}", vote.votecode, vote.source, vote.transaction_hash));
+    let uncounted_ballot_votes = uncounted_votes.len();
+    merged.sources.iter()
+        .for_each(|count| info!("Votes recorded from {}", count));
+
+    // Keep the block of every vote for the tally report, and cache the
+    // block timestamps read with the votes.
+    let mut block_timestamps = BlockTimestamps::open(&datadir_path)?;
+    merged.votes.iter()
+        .filter(|vote| VoteRecord::from(*vote).mined() && 0 < vote.timestamp.timestamp())
+        .for_each(|vote| block_timestamps.insert(vote.block_number, vote.timestamp));
+    block_timestamps.save(&datadir_path)?;
+    pollconf.vote_records = merged.votes.iter().map(VoteRecord::from).collect();
+    if verbose {
+        merged.votes.iter()
+            .for_each(|vote| info!("Vote counted: {} from {} {}", vote.votecode, vote.source, vote.transaction_hash));
+    }
+    let votes: Vec<VoteCode> = merged.votes.par_iter()
+        .filter_map(|vote| votecode_from_string(&vote.votecode))
+        .collect();
+    let (votes, provisional_votes) = bucket_provisional_votes(votes, &pollconf.provisional_ballots,
+        |votecode| ballot_votecodes.get(votecode).copied());
+    if !provisional_votes.is_empty() {
+        info!("Votes for provisional ballots set aside: {}", provisional_votes.len());
+    }
+    let voted: Vec<BallotSerial> = votes.iter()
+        .filter_map(|votecode| ballot_votecodes.get(votecode).copied())
+        .collect();
+    record_ballot_states(&datadir_path, BallotState::Voted, &voted)?;
     pollconf.votes = Some(votes.clone());
+    pollconf.provisional_votes = provisional_votes;
+    pollconf.vote_sources = merged.sources;
+    pollconf.uncounted_ballot_votes = uncounted_ballot_votes;
     let marked_rows: Vec<usize> = {
         let votecodes: Vec<VoteCode> = generate_votecodes(
-            poll_secrets.votecode_root,
+            &poll_secrets.votecode_root,
             2 * pollconf.num_ballots);
-        votecodes.iter().enumerate()
-            .filter_map(|(n, vc)| {
-                debug!("{:?}", vc);
-                if votes.contains(vc) { Some(n) }
-                else { None }
-            }).collect()
+        let votes: VoteCodeSet = votes.into_iter().collect();
+        let mut progress = Progress::new("Counting votes", "votecodes", votecodes.len());
+        let marked_rows = votecodes.par_iter().enumerate()
+            .filter(|(_, vc)| votes.contains(vc))
+            .map(|(n, _)| n)
+            .collect();
+        progress.inc(votecodes.len());
+        progress.finish();
+        marked_rows
     };
 
     // Post the Column Planes.
@@ -126,7 +240,7 @@ pub fn record_votes(pollconf_filename: &str, votes_file: &str, force: bool) -> R
     // Update the poll state.
     pollconf.poll_state.votes_committed = true;
     // Re-encrypt the poll configuration.
-    let serialized_pollconf = serde_yaml::to_string(&pollconf)?;
+    let serialized_pollconf = Zeroizing::new(serde_yaml::to_string(&pollconf)?);
     let secure_serialized_pollconf = AEADString::from_values(
         aead_encrypt(&aead_pmk,
                      Vec::new(),
@@ -140,4 +254,37 @@ pub fn record_votes(pollconf_filename: &str, votes_file: &str, force: bool) -> R
     Ok(())
 }
 
-
+// Committed vote codes of the replacement ballots issued with fresh vote
+// codes, by fresh vote code.
+fn read_replacement_votecodes(pollconf: &PollConfiguration, datadir_path: &str, poll_secrets: &PollSecrets) -> Result<HashMap<String, String>> {
+    let amendments: Vec<&BallotAmendment> = pollconf.ballot_amendments.iter()
+        .filter(|amendment| amendment.ballot_commitment.is_some())
+        .collect();
+    if amendments.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let ballots = generate_poll_ballots(poll_secrets, pollconf.num_ballots);
+    let committed = votecode_serials(poll_secrets, pollconf.num_ballots);
+    let mut translation = HashMap::new();
+    for amendment in amendments {
+        let path = Path::new(datadir_path).join(replacement_codes_filename(amendment.replacement_serial));
+        let data = std::fs::read(&path)
+            .map_err(|err| format!("Ballot {} was issued with fresh vote codes: copy {} opened by the print pipeline first ({}).",
+                amendment.replacement_serial, path.display(), err))?;
+        let codes: ReplacementCodes = serde_yaml::from_slice(&data)?;
+        codes.check_amendment(amendment)?;
+        for (votecode, committed_votecode) in codes.translation(&ballots[amendment.replacement_serial])? {
+            if committed.get_str(&votecode).is_some() || translation.contains_key(&votecode) {
+                return Err(format!("The fresh vote codes of ballot {} are already vote codes of the poll.", amendment.replacement_serial).into());
+            }
+            translation.insert(votecode, committed_votecode);
+        }
+        record_audit_event(datadir_path, AuditLogEvent::ReplacementCodes {
+            spoiled_serial: amendment.spoiled_serial,
+            replacement_serial: amendment.replacement_serial,
+            file: path.to_string_lossy().into_owned(),
+            sha256: sha256_hex(&data)
+        })?;
+    }
+    Ok(translation)
+}
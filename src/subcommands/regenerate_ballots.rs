@@ -0,0 +1,228 @@
+//! # Command: Regenerate Ballots
+//!
+//! The serials and vote codes of the ballots, the decoys and the
+//! permutations of the planes are all drawn from the poll secrets, which
+//! are derived from the generation seed sealed in the poll configuration.
+//! `regenerate_ballots` writes the ballot information file of step 3 and
+//! the committed planes of step 2 again from the sealed seed, to recover a
+//! poll whose files were lost. `check_regenerated_ballots` regenerates them
+//! in memory and checks that they hash to the committed artifacts: the
+//! committed planes of the data directory, the Merkle root of the commit
+//! receipt and, if given, the ballot information file sent to the printer.
+
+use std::fmt;
+use std::fs;
+use crypto::digest::Digest;
+use crypto::sha3::{Sha3, Sha3Mode};
+use super::*;
+use crate::blockchain::merkle::new_tree;
+use crate::blockchain::{merkle_tree_data, CommitReceipt};
+
+pub const REGENERATED_BALLOTS_FILENAME: &str = "ballots.csv";
+
+fn sha3_hex(data: &[u8]) -> String {
+    let mut hasher = Sha3::new(Sha3Mode::Sha3_256);
+    hasher.input(data);
+    let mut hash = [0u8; 32];
+    hasher.result(&mut hash);
+    hex::encode(hash)
+}
+
+/// Artifacts regenerated from the sealed seed of a poll.
+struct RegeneratedArtifacts {
+    ballots: usize,
+    ballots_csv: Vec<u8>,
+    committed_planes: Vec<Vec<u8>>,
+    planes: Vec<Plane>
+}
+
+fn regenerate(pollconf: &PollConfiguration, poll_secrets: &PollSecrets) -> Result<RegeneratedArtifacts> {
+    let ballots = generate_poll_ballots(poll_secrets, pollconf.num_ballots);
+    let mut csvwriter = csv::Writer::from_writer(Vec::new());
+    for ballot in ballots.iter() {
+        csvwriter.serialize(CompleteBallotRow::from_ballot(ballot, pollconf.num_ballots))?;
+    }
+    let ballots_csv = csvwriter.into_inner().map_err(|err| err.to_string())?;
+
+    let planes: Vec<Plane> = generate_column_planes(
+        poll_secrets,
+        NUMBER_OF_PLANES,
+        2 * pollconf.num_ballots,
+        pollconf.num_decoys)?;
+    let committed_planes = planes.iter().enumerate()
+        .map(|(n, plane)| committed_plane_csv(poll_secrets, n, plane))
+        .collect::<Result<Vec<Vec<u8>>>>()?;
+    Ok(RegeneratedArtifacts { ballots: ballots.len(), ballots_csv, committed_planes, planes })
+}
+
+/// File written by `regenerate_ballots`, with its SHA3-256 hash.
+#[derive(Debug, Clone, Serialize)]
+pub struct RegeneratedFile {
+    pub file: String,
+    pub hash: String
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RegeneratedBallots {
+    pub ballots: usize,
+    pub files: Vec<RegeneratedFile>
+}
+
+impl fmt::Display for RegeneratedBallots {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Regenerated {} ballots:", self.ballots)?;
+        for file in self.files.iter() {
+            write!(f, "\n  {} {}", file.hash, file.file)?;
+        }
+        Ok(())
+    }
+}
+
+/// Committed artifact checked against its regenerated form.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct ArtifactCheck {
+    pub artifact: String,
+    /// Hash of the committed artifact, `None` if it is missing.
+    pub committed: Option<String>,
+    pub regenerated: String
+}
+
+impl ArtifactCheck {
+    pub fn matches(&self) -> bool {
+        self.committed.as_deref() == Some(self.regenerated.as_str())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RegenerationCheck {
+    pub ballots: usize,
+    pub artifacts: Vec<ArtifactCheck>
+}
+
+impl RegenerationCheck {
+    pub fn valid(&self) -> bool {
+        self.artifacts.iter().all(ArtifactCheck::matches)
+    }
+}
+
+impl fmt::Display for RegenerationCheck {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Ballots regenerated: {}", self.ballots)?;
+        for artifact in self.artifacts.iter() {
+            let state = match (&artifact.committed, artifact.matches()) {
+                (None, _) => "MISSING",
+                (Some(_), true) => "matches",
+                (Some(_), false) => "DIFFERS"
+            };
+            writeln!(f, "  {:<24} {} {}", artifact.artifact, artifact.regenerated, state)?;
+        }
+        write!(f, "Matches commit:      {}", if self.valid() { "yes" } else { "NO" })
+    }
+}
+
+/// Check the artifacts regenerated for a poll against the committed ones:
+/// the committed planes in its data directory, the Merkle root of `receipt`
+/// once the poll is committed, and the ballot information file
+/// `ballots_csv` if given.
+fn check_regenerated(pollconf: &PollConfiguration, poll_identifier: &str, regenerated: RegeneratedArtifacts, receipt: Option<&CommitReceipt>, ballots_csv: Option<(&str, Vec<u8>)>) -> Result<RegenerationCheck> {
+    let mut check = RegenerationCheck { ballots: regenerated.ballots, ..Default::default() };
+    if let Some((ballots_filename, committed)) = ballots_csv {
+        check.artifacts.push(ArtifactCheck {
+            artifact: ballots_filename.to_owned(),
+            committed: Some(sha3_hex(&committed)),
+            regenerated: sha3_hex(&regenerated.ballots_csv)
+        });
+    }
+    for (n, plane) in regenerated.committed_planes.iter().enumerate() {
+        let filename = committed_plane_filename(n);
+        let committed = match Path::new(poll_identifier).join(&filename) {
+            path if path.exists() => Some(sha3_hex(&fs::read(path)?)),
+            _ => None
+        };
+        check.artifacts.push(ArtifactCheck { artifact: filename, committed, regenerated: sha3_hex(plane) });
+    }
+    if let Some(receipt) = receipt {
+        let question = BallotQuestion::from_poll(poll_identifier, pollconf);
+        let data = merkle_tree_data(pollconf, &question, regenerated.planes)?;
+        let tree = new_tree(data)?;
+        check.artifacts.push(ArtifactCheck {
+            artifact: "Merkle root".to_owned(),
+            committed: Some(receipt.root.clone()),
+            regenerated: hex::encode(tree.root())
+        });
+    }
+    Ok(check)
+}
+
+pub fn regenerate_ballots(pollconf_filename: &str, dirname: &str) -> Result<()> {
+    // Read poll configuration file.
+    let secured_poll_configuration = read_poll_configuration_file(pollconf_filename)?;
+
+    // Reconstruct the Poll Master Key from the trustee passwords.
+    let (poll_master_key, aead_pmk) = read_poll_master_key(&secured_poll_configuration);
+
+    // Decrypt poll configuration state.
+    let pollconf_aead_values = secured_poll_configuration.encrypted_poll_configuration.values()?;
+    let serialized_pollconf = aead_decrypt(&aead_pmk, &pollconf_aead_values)?;
+    let pollconf: PollConfiguration = serde_yaml::from_slice(&serialized_pollconf).unwrap();
+
+    // Derive the poll secrets from the sealed seed.
+    let poll_secrets: PollSecrets = PollSecrets::for_poll(&poll_master_key, pollconf.generation_seed.as_deref())?;
+    let regenerated = regenerate(&pollconf, &poll_secrets)?;
+
+    // Never overwrite the files being recovered.
+    let dir = Path::new(dirname);
+    fs::create_dir_all(dir)?;
+    let files: Vec<(String, &Vec<u8>)> = std::iter::once((REGENERATED_BALLOTS_FILENAME.to_owned(), &regenerated.ballots_csv))
+        .chain(regenerated.committed_planes.iter().enumerate().map(|(n, plane)| (committed_plane_filename(n), plane)))
+        .collect();
+    if let Some((filename, _)) = files.iter().find(|(filename, _)| dir.join(filename).exists()) {
+        return Err(format!("{} already exists in {}.", filename, dirname).into());
+    }
+    for (filename, data) in files.iter() {
+        fs::write(dir.join(filename), data)?;
+    }
+
+    output::emit(&RegeneratedBallots {
+        ballots: regenerated.ballots,
+        files: files.iter()
+            .map(|(filename, data)| RegeneratedFile {
+                file: dir.join(filename).to_string_lossy().into_owned(),
+                hash: sha3_hex(data)
+            })
+            .collect()
+    })?;
+    Ok(())
+}
+
+pub fn check_regenerated_ballots(pollconf_filename: &str, ballots_filename: Option<&str>) -> Result<()> {
+    // Read poll configuration file.
+    let secured_poll_configuration = read_poll_configuration_file(pollconf_filename)?;
+
+    // Reconstruct the Poll Master Key from the trustee passwords.
+    let (poll_master_key, aead_pmk) = read_poll_master_key(&secured_poll_configuration);
+
+    // Ensure the data directory exists.
+    let datadir_path = ensure_poll_data_directory_exists(&secured_poll_configuration, &aead_pmk)?;
+
+    // Decrypt poll configuration state.
+    let pollconf_aead_values = secured_poll_configuration.encrypted_poll_configuration.values()?;
+    let serialized_pollconf = aead_decrypt(&aead_pmk, &pollconf_aead_values)?;
+    let pollconf: PollConfiguration = serde_yaml::from_slice(&serialized_pollconf).unwrap();
+
+    // Derive the poll secrets from the sealed seed.
+    let poll_secrets: PollSecrets = PollSecrets::for_poll(&poll_master_key, pollconf.generation_seed.as_deref())?;
+    let regenerated = regenerate(&pollconf, &poll_secrets)?;
+
+    let receipt = read_commit_receipt(&datadir_path)?;
+    let ballots_csv = match ballots_filename {
+        Some(filename) => Some((filename, fs::read(filename)?)),
+        None => None
+    };
+    let check = check_regenerated(&pollconf, &datadir_path, regenerated, receipt.as_ref(), ballots_csv)?;
+    output::emit(&check)?;
+    match check.valid() {
+        true => Ok(()),
+        false => Err("Regenerated ballots do not match the committed artifacts.".into())
+    }
+}
@@ -0,0 +1,110 @@
+//! # Command: Replacement Envelope
+//!
+//! `generate_envelope_key` generates the key pair of the print pipeline.
+//! `issue_replacement_ballot` issues a replacement ballot with fresh vote
+//! codes sealed to its public key, writing the ballot as the print vendor
+//! export and the envelope to keep until voting closed; its commitment is
+//! given to `spoil_ballot`. `open_replacement_envelope` opens the envelope
+//! with the secret key, checked against the published amendments of the
+//! poll, and writes the vote codes for step 6. None of them needs the poll
+//! configuration.
+
+use std::fmt;
+use std::fs;
+use super::*;
+use crate::blockchain::BallotAmendment;
+use crate::cryptography::{VoteDecryptionKey, VoteEncryptionKey};
+
+/// Generate the key pair of the print pipeline, writing the base64 encoded
+/// secret key to the new file `secret_key_filename`, readable by its owner
+/// only.
+pub fn generate_envelope_key(secret_key_filename: &str) -> Result<()> {
+    let key = VoteDecryptionKey::generate();
+    write_secret_key_file(secret_key_filename, key.secret_key())?;
+    output::emit(&EnvelopeKey { public_key: key.encryption_key().to_base64() })
+}
+
+/// Replacement ballot issued with fresh vote codes.
+#[derive(Debug, Clone, Serialize)]
+pub struct IssuedReplacement {
+    pub spoiled_serial: BallotSerial,
+    pub replacement_serial: BallotSerial,
+    /// Commitment of the vote codes, to spoil the ballot with.
+    pub commitment: String,
+    pub envelope: String,
+    pub file: String
+}
+
+impl fmt::Display for IssuedReplacement {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Replacement: {} (replaces ballot {})", self.replacement_serial, self.spoiled_serial)?;
+        writeln!(f, "Commitment:  {}", self.commitment)?;
+        writeln!(f, "Envelope:    {}", self.envelope)?;
+        write!(f, "Written to:  {}", self.file)
+    }
+}
+
+/// Issue the replacement `replacement` of ballot `spoiled` of the poll
+/// `poll_identifier` with fresh vote codes sealed to `print_key`, writing
+/// the ballot to `out` as CSV and the envelope to
+/// `replacement_envelope_<SERIAL>.yaml`, or `envelope_filename`.
+pub fn issue_replacement_ballot(poll_identifier: &str, spoiled: BallotSerial, replacement: BallotSerial, print_key: &str, out: &str, envelope_filename: Option<&str>) -> Result<()> {
+    let print_key = VoteEncryptionKey::from_base64(print_key)?;
+    let (envelope, ballot) = ReplacementEnvelope::issue(poll_identifier, spoiled, replacement, &print_key)?;
+    let envelope_filename = envelope_filename.map(str::to_owned)
+        .unwrap_or_else(|| replacement_envelope_filename(replacement));
+    serde_yaml::to_writer(File::create(&envelope_filename)?, &envelope)?;
+    let export = BallotExport { profile: RedactionProfile::PrintVendor, ballots: vec![ballot] };
+    fs::write(out, export.to_bytes(BallotExportFormat::Csv)?)?;
+    output::emit(&IssuedReplacement {
+        spoiled_serial: spoiled,
+        replacement_serial: replacement,
+        commitment: envelope.commitment,
+        envelope: envelope_filename,
+        file: out.to_owned()
+    })
+}
+
+/// Vote codes of a replacement ballot opened from its envelope.
+#[derive(Debug, Clone, Serialize)]
+pub struct OpenedEnvelope {
+    pub spoiled_serial: BallotSerial,
+    pub replacement_serial: BallotSerial,
+    pub commitment: String,
+    pub file: String
+}
+
+impl fmt::Display for OpenedEnvelope {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Replacement: {} (replaces ballot {})", self.replacement_serial, self.spoiled_serial)?;
+        writeln!(f, "Commitment:  {}", self.commitment)?;
+        write!(f, "Written to:  {}", self.file)
+    }
+}
+
+/// Open `envelope_filename` with the secret key of the file
+/// `secret_key_filename`, checked against the amendments published in
+/// `amendments_filename`, writing the vote codes to `out`,
+/// `replacement_codes_<SERIAL>.yaml` by default.
+pub fn open_replacement_envelope(envelope_filename: &str, secret_key_filename: &str, amendments_filename: &str, out: Option<&str>) -> Result<()> {
+    let envelope: ReplacementEnvelope = serde_yaml::from_reader(File::open(envelope_filename)?)?;
+    let amendments: Vec<BallotAmendment> = serde_yaml::from_reader(File::open(amendments_filename)?)?;
+    let amendment = amendments.iter()
+        .find(|amendment| amendment.replacement_serial == envelope.replacement_serial)
+        .ok_or_else(|| format!("No amendment of {} issues ballot {}.", amendments_filename, envelope.replacement_serial))?;
+    envelope.check_amendment(amendment)?;
+
+    let secret_key = base64::decode(fs::read_to_string(secret_key_filename)?.trim())?;
+    let key = VoteDecryptionKey::from_secret_key(&secret_key)
+        .ok_or_else(|| format!("{} is not an envelope key.", secret_key_filename))?;
+    let codes = envelope.open(&key)?;
+    let out = out.map(str::to_owned)
+        .unwrap_or_else(|| replacement_codes_filename(envelope.replacement_serial));
+    serde_yaml::to_writer(File::create(&out)?, &codes)?;
+    output::emit(&OpenedEnvelope {
+        spoiled_serial: envelope.spoiled_serial,
+        replacement_serial: envelope.replacement_serial,
+        commitment: envelope.commitment.clone(),
+        file: out
+    })
+}
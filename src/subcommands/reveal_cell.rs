@@ -0,0 +1,189 @@
+//! # Command: Reveal Cell
+//!
+//! `reveal_cell` answers the challenge of a single cell of a committed
+//! plane, as published in `committed_plane_NN.csv`: it opens the cell with
+//! its key and proves that the encrypted cell is a leaf of the committed
+//! Merkle tree. Anyone can check the opening with the key, the entry and
+//! the proof of inclusion alone.
+//!
+//! A plane reveals one column of the rows of the ballots that were not
+//! audited, so that no vote code is linked to its choice. Only the cells
+//! the revelation of the poll opens anyway can be revealed: those of the
+//! column drawn for their plane by the tally audit, and both cells of the
+//! rows of the audited ballots. Every reveal is recorded in the audit log
+//! of the poll with the challenger who requested it.
+
+use std::fmt;
+use std::fs;
+use super::*;
+use crate::audit_log::{record_audit_event, AuditLogEvent};
+use crate::blockchain::merkle::validate;
+use crate::bulletin_board::{open_cell, sha256_hex};
+
+/// Cell of a committed plane opened for a challenge.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PlaneCellOpening {
+    /// Plane number, counted from 1.
+    pub plane: usize,
+    /// Row of the committed plane file, counted from 1.
+    pub row: usize,
+    pub column: usize,
+    /// Encrypted cell, as committed.
+    pub cell: String,
+    /// Base64 encoded key of the cell.
+    pub key: String,
+    /// Entry the cell decrypts to.
+    pub entry: String,
+    /// Hex encoded root of the Merkle tree.
+    pub root: String,
+    pub proof: GeneratedProof
+}
+
+impl PlaneCellOpening {
+    /// Whether the cell decrypts to the entry with the key, and the proof
+    /// proves it a leaf of the tree of the root.
+    pub fn verify(&self) -> bool {
+        open_cell(&self.cell, &self.key).is_some_and(|entry| entry.trim() == self.entry.trim())
+            && self.proof.data == self.cell
+            && self.proof.lemma.last() == Some(&self.root)
+            && validate(self.proof.lemma.clone(), self.proof.path.clone(), self.proof.data.clone()).unwrap_or(false)
+    }
+}
+
+impl fmt::Display for PlaneCellOpening {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Plane {} row {} column {}", self.plane, self.row, self.column)?;
+        writeln!(f, "Entry:       {}", self.entry)?;
+        writeln!(f, "Key:         {}", self.key)?;
+        writeln!(f, "Merkle root: {}", self.root)?;
+        write!(f, "Proof:       {} hashes", self.proof.lemma.len())
+    }
+}
+
+/// Check that revealing `column` of the committed row `row` of a plane
+/// links no vote code to its choice beyond the revelation of the poll.
+/// `audited_column` is the column drawn for the plane by the tally audit,
+/// if drawn.
+pub fn check_cell_reveal(row: usize, column: usize, audited_ballots: &[BallotSerial], audited_column: Option<usize>) -> Result<()> {
+    if column != 1 && column != 3 {
+        return Err(format!("Column {} has no committed cells: cells are revealed in column 1 or 3.", column).into());
+    }
+    if audited_ballots.contains(&(row / 2)) {
+        return Ok(());
+    }
+    match audited_column {
+        None => Err("The audited columns are not drawn (generate-tally-audit): only cells of audited ballots can be revealed.".into()),
+        Some(audited_column) if audited_column == column => Ok(()),
+        Some(audited_column) => Err(format!("The plane reveals column {}: revealing column {} would link vote codes to their choices.", audited_column, column).into())
+    }
+}
+
+pub fn reveal_cell(pollconf_filename: &str, merkle_tree: &str, plane: usize, row: usize, column: usize, challenger: &str, out: &str) -> Result<()> {
+    // Read poll configuration file.
+    let secured_poll_configuration = read_poll_configuration_file(pollconf_filename)?;
+
+    // Reconstruct the Poll Master Key from the trustee passwords.
+    let (poll_master_key, aead_pmk) = read_poll_master_key(&secured_poll_configuration);
+
+    // Ensure the data directory exists.
+    let datadir_path = ensure_poll_data_directory_exists(&secured_poll_configuration, &aead_pmk)?;
+
+    // Decrypt poll configuration state.
+    let pollconf_aead_values = secured_poll_configuration.encrypted_poll_configuration.values()?;
+    let serialized_pollconf = aead_decrypt(&aead_pmk, &pollconf_aead_values)?;
+    let pollconf: PollConfiguration = serde_yaml::from_slice(&serialized_pollconf).unwrap();
+
+    // Check the signed artifacts loaded with the poll verification key.
+    use_poll_verification_key(&secured_poll_configuration, &aead_pmk)?;
+
+    let commit_receipt = read_commit_receipt(&datadir_path)?
+        .ok_or("Poll has not been committed to the blockchain.")?;
+    if plane == 0 || NUMBER_OF_PLANES < plane {
+        return Err(format!("Plane {} is not a plane of the poll, which has {} planes.", plane, NUMBER_OF_PLANES).into());
+    }
+    let num_rows = 2 * pollconf.num_ballots;
+    if row == 0 || num_rows < row {
+        return Err(format!("Row {} is not a row of the planes, which have {} rows.", row, num_rows).into());
+    }
+
+    // Locate the committed row of the published row.
+    let poll_secrets: PollSecrets = PollSecrets::for_poll(&poll_master_key, pollconf.generation_seed.as_deref())?;
+    let psecrets = poll_secrets.plane_secrets[plane - 1].resolve(num_rows);
+    let committed_row = psecrets.permutation[row - 1];
+
+    // Only cells the revelation opens anyway are revealed.
+    let audited_ballots: Vec<BallotSerial> = pollconf.audited_ballots.clone().unwrap_or_default().iter()
+        .filter_map(|serial| serial.parse().ok())
+        .collect();
+    let audited_column = match &pollconf.audited_columns_seed {
+        Some(seed) => {
+            let seed = CSPRNGSeed::from_vec(&hex::decode(seed)?);
+            let mut prng = CSPRNG::from_csprng_seed(&seed);
+            let audited_columns: Vec<usize> = (0..NUMBER_OF_PLANES).map(|_| prng.gen_range(0, 2)).collect();
+            Some(if audited_columns[plane - 1] == 0 { 1 } else { 3 })
+        },
+        None => None
+    };
+    check_cell_reveal(committed_row, column, &audited_ballots, audited_column)?;
+
+    // Open the cell.
+    let column_planes: Vec<Plane> = generate_column_planes(
+        &poll_secrets,
+        plane,
+        num_rows,
+        pollconf.num_decoys)?;
+    let record = &column_planes[plane - 1].rows[committed_row];
+    let (cell, key) = match column {
+        1 => match &record.col1 {
+            Column1Entry::Encrypted(cell) => (cell.clone(), &psecrets.col1_keys[committed_row]),
+            Column1Entry::Entry(_) => return Err("Committed cells are encrypted.".into())
+        },
+        _ => match &record.col3 {
+            Column3Entry::Encrypted(cell) => (cell.clone(), &psecrets.col3_keys[committed_row]),
+            Column3Entry::Entry(_) => return Err("Committed cells are encrypted.".into())
+        }
+    };
+    let entry = String::from_utf8(aead_decrypt(key, &cell.values()?)?.to_vec())?;
+
+    // Prove the cell committed, at its leaf if the tree is indexed.
+    let not_committed = || format!("Row {} column {} of plane {} is not a leaf of {}.", row, column, plane, merkle_tree);
+    let proof = match read_leaf_index(merkle_tree)?.and_then(|index| index.plane_cell_leaf(plane, committed_row, column)) {
+        Some(leaf) => leaf_inclusion_proof(merkle_tree, leaf, &cell.0).map_err(|_| not_committed())?,
+        None => find_inclusion_proof(merkle_tree, &cell.0)?.ok_or_else(not_committed)?
+    };
+    let root = proof.lemma.last().cloned().unwrap_or_default();
+    if root != commit_receipt.root {
+        return Err(format!("The root {} of {} is not the committed root {}.", root, merkle_tree, commit_receipt.root).into());
+    }
+    let opening = PlaneCellOpening {
+        plane,
+        row,
+        column,
+        cell: cell.0,
+        key: base64::encode(&key.0),
+        entry: entry.trim().to_owned(),
+        root,
+        proof
+    };
+    let data = serde_yaml::to_string(&opening)?;
+    fs::write(out, &data)?;
+
+    record_audit_event(&datadir_path, AuditLogEvent::CellReveal {
+        challenger: challenger.to_owned(),
+        plane,
+        row,
+        column,
+        file: out.to_owned(),
+        sha256: sha256_hex(data.as_bytes())
+    })?;
+    output::emit(&opening)
+}
+
+/// Check a cell opening written by `reveal_cell`.
+pub fn verify_cell_opening(opening_filename: &str) -> Result<()> {
+    let opening: PlaneCellOpening = serde_yaml::from_reader(File::open(opening_filename)?)?;
+    match opening.verify() {
+        true => output::emit(&opening),
+        false => Err(format!("Plane {} row {} column {} does not open its commitment.", opening.plane, opening.row, opening.column).into())
+    }
+}
@@ -0,0 +1,76 @@
+//! # Command: Commit Roster Chunk
+//!
+//! `commit_roster_chunk` adds a batch of voters to the roster of the poll
+//! as its registration closes, and commits to it on the blockchain right
+//! away: the Merkle root of the roster leaves of the batch is posted with
+//! the commit key, and the chunk is recorded in the secured poll
+//! configuration and published in `roster_chunks.yaml`. The voters of a
+//! batch follow the voters of the chunks before it in the roster. Chunks
+//! are added until the summands are committed (step 1); the commitment of
+//! the poll binds every chunk root.
+
+use super::*;
+use crate::blockchain::{record_sent_transaction, roster_chunk_root, check_roster_chunks, roster_leaves, SpendPhase, ROSTER_CHUNKS_FILENAME};
+use crate::blockchain::blocking::post_roster_chunk;
+
+pub fn commit_roster_chunk(network: &str, pollconf_filename: &str, roster_filename: &str) -> Result<()> {
+    // Read poll configuration file.
+    let mut secured_poll_configuration = read_poll_configuration_file(pollconf_filename)?;
+
+    // Reconstruct the Poll Master Key from the trustee passwords.
+    let (_, aead_pmk) = read_poll_master_key(&secured_poll_configuration);
+
+    // Ensure the data directory exists.
+    let datadir_path = ensure_poll_data_directory_exists(&secured_poll_configuration, &aead_pmk)?;
+
+    // Decrypt poll configuration state.
+    let pollconf_aead_values = secured_poll_configuration.encrypted_poll_configuration.values()?;
+    let serialized_pollconf = aead_decrypt(&aead_pmk, &pollconf_aead_values)?;
+    let mut pollconf: PollConfiguration = serde_yaml::from_slice(&serialized_pollconf).unwrap();
+
+    if pollconf.poll_state.summands_committed {
+        return Err("The summands were committed (step 1): the roster is closed.".into());
+    }
+    if pollconf.poll_state.roster_committed && pollconf.roster_chunks.is_empty() {
+        return Err("The roster was bound whole: re-bind it with bind-roster --force instead.".into());
+    }
+
+    // Append the voters of the batch to the voters of the chunks before it.
+    let batch = VoterRoster::from_file_with_schema(&Path::new(roster_filename), pollconf.roster_schema.as_ref())?;
+    let batch = schema_validated_roster(&pollconf, &batch)?;
+    if batch.records.is_empty() {
+        return Err("The roster batch has no voters.".into());
+    }
+    let mut roster = match pollconf.roster_chunks.is_empty() {
+        true => VoterRoster { records: Vec::new() },
+        false => read_voter_roster(&pollconf)?
+    };
+    let first_position = roster.len();
+    roster.records.extend(batch.records.into_iter().enumerate()
+        .map(|(n, record)| VoterRosterRecord { position: first_position + n, voter_info: record.voter_info }));
+    pollconf.voter_roster = Some(Base64String(base64::encode(&serde_yaml::to_string(&roster)?)));
+    pollconf.voter_roster_size = roster.len();
+    pollconf.poll_state.announced = true;
+    pollconf.poll_state.roster_committed = true;
+
+    // The chunks before still hash to their roots; commit to the new one.
+    let leaves = roster_leaves(&pollconf)?;
+    check_roster_chunks(&pollconf.roster_chunks, &leaves[..first_position])?;
+    let root = roster_chunk_root(&leaves[first_position..])?;
+    let chunk = post_roster_chunk(network, &datadir_path, pollconf.roster_chunks.len(), first_position, leaves.len() - first_position, &root)?;
+    record_sent_transaction(&datadir_path, SpendPhase::Roster, &chunk.transaction_hash, &chunk.address)?;
+    pollconf.roster_chunks.push(chunk.clone());
+    serde_yaml::to_writer(
+        File::create(Path::new(&datadir_path).join(ROSTER_CHUNKS_FILENAME))?,
+        &pollconf.roster_chunks)?;
+
+    // Re-encrypt the poll configuration.
+    let serialized_pollconf = Zeroizing::new(serde_yaml::to_string(&pollconf)?);
+    secured_poll_configuration.encrypted_poll_configuration = AEADString::from_values(
+        aead_encrypt(&aead_pmk,
+                     Vec::new(),
+                     serialized_pollconf.as_bytes().to_vec())?);
+    write_poll_configuration_file(pollconf_filename, &secured_poll_configuration)?;
+
+    output::emit(&chunk)
+}
@@ -0,0 +1,99 @@
+//! # Command: Roster Policy
+//!
+//! `set_roster_policy` chooses the fields of the voter information that
+//! step 1 publishes in the committed roster, with the others only published
+//! as salted hashes, and checks the anonymity of the bound roster under it.
+//! The policy cannot change once the roster is committed.
+//! `check_roster_privacy` checks the anonymity of the voters of a roster
+//! file for the fields to publish. It reads only the file, so that the
+//! roster can be checked before it is bound.
+
+use std::fmt;
+use super::*;
+
+/// Parse the names of roster fields.
+pub fn parse_roster_fields(names: &[String]) -> Result<Vec<RosterField>> {
+    names.iter()
+        .map(|name| name.parse::<RosterField>().map_err(|err| err.into()))
+        .collect()
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RosterPolicyReport {
+    pub published_fields: Vec<RosterField>,
+    pub private_fields: Vec<RosterField>,
+    pub min_anonymity: usize,
+    /// Anonymity of the bound roster, `None` if no roster is bound.
+    pub anonymity: Option<AnonymityCheck>
+}
+
+impl fmt::Display for RosterPolicyReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let names = |fields: &[RosterField]| match fields.is_empty() {
+            true => "none".to_owned(),
+            false => fields.iter().map(RosterField::name).collect::<Vec<&str>>().join(", ")
+        };
+        match &self.anonymity {
+            Some(check) => writeln!(f, "{}", check)?,
+            None => {
+                writeln!(f, "Published fields:   {}", names(&self.published_fields))?;
+                writeln!(f, "Anonymity:          roster not bound (required {})", self.min_anonymity)?;
+            }
+        }
+        write!(f, "Hashed fields:      {}", names(&self.private_fields))
+    }
+}
+
+pub fn set_roster_policy(pollconf_filename: &str, published_fields: &[RosterField], min_anonymity: usize) -> Result<()> {
+    // Read poll configuration file.
+    let mut secured_poll_configuration = read_poll_configuration_file(pollconf_filename)?;
+
+    // Reconstruct the Poll Master Key from the trustee passwords.
+    let (_poll_master_key, aead_pmk) = read_poll_master_key(&secured_poll_configuration);
+
+    // Decrypt poll configuration state.
+    let pollconf_aead_values = secured_poll_configuration.encrypted_poll_configuration.values()?;
+    let serialized_pollconf = aead_decrypt(&aead_pmk, &pollconf_aead_values)?;
+    let mut pollconf: PollConfiguration = serde_yaml::from_slice(&serialized_pollconf).unwrap();
+
+    if pollconf.poll_state.summands_committed {
+        return Err("Voter roster already committed, its policy can no longer change.".into());
+    }
+    let policy = RosterPolicy::new(published_fields, min_anonymity);
+    let anonymity = match pollconf.voter_roster {
+        Some(_) => Some(policy.check(&read_voter_roster(&pollconf)?)),
+        None => None
+    };
+    if let Some(check) = anonymity.as_ref().filter(|check| !check.valid()) {
+        warn!("Published roster fields could identify voters: {} voters are in groups of fewer than {}",
+            check.voters_at_risk, check.min_anonymity);
+    }
+    let report = RosterPolicyReport {
+        published_fields: policy.published_fields.clone(),
+        private_fields: policy.private_fields(),
+        min_anonymity,
+        anonymity
+    };
+    pollconf.roster_policy = Some(policy);
+
+    // Re-encrypt the poll configuration.
+    let serialized_pollconf = Zeroizing::new(serde_yaml::to_string(&pollconf)?);
+    secured_poll_configuration.encrypted_poll_configuration = AEADString::from_values(
+        aead_encrypt(&aead_pmk,
+                     Vec::new(),
+                     serialized_pollconf.as_bytes().to_vec())?);
+    write_poll_configuration_file(pollconf_filename, &secured_poll_configuration)?;
+
+    output::emit(&report)?;
+    Ok(())
+}
+
+pub fn check_roster_privacy(roster_filename: &str, published_fields: &[RosterField], min_anonymity: usize) -> Result<()> {
+    let roster = VoterRoster::from_file(&roster_filename)?;
+    let check = check_anonymity(&roster, published_fields, min_anonymity);
+    output::emit(&check)?;
+    match check.valid() {
+        true => Ok(()),
+        false => Err("Published roster fields could identify voters.".into())
+    }
+}
@@ -0,0 +1,50 @@
+//! # Command: Rotate Key
+//!
+//! `rotate_key` hands the vote address of a committed poll over to a new
+//! address, so that the posting key can be rotated in the middle of the
+//! poll. The handover record is signed with the commit key, which must hold
+//! the current vote address; readers following the vote address from the
+//! commit receipt gather the transactions to the new address from then on.
+//! Only the public commit receipt is needed, not the trustee passwords.
+
+use std::fmt;
+use super::*;
+use crate::blockchain::{record_sent_transaction, CommitReceipt, SpendPhase};
+use crate::blockchain::blocking::{latest_block, post_handover, vote_transactions_between};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct KeyRotation {
+    pub from: String,
+    pub to: String,
+    pub transaction_hash: String,
+    /// Handovers of the vote address before this one.
+    pub previous_handovers: usize
+}
+
+impl fmt::Display for KeyRotation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Vote address {} handed over to {} in {}", self.from, self.to, self.transaction_hash)?;
+        write!(f, "Set vote_address and the keys of the network configuration to the new address.")
+    }
+}
+
+pub fn rotate_key(network: &str, receipt: &str, new_address: &str) -> Result<()> {
+    let commit_receipt: CommitReceipt = serde_yaml::from_reader(File::open(receipt)?)?;
+
+    // Follow the handovers since the commitment to the current vote address.
+    let latest = latest_block(network)?;
+    let read = vote_transactions_between(network, commit_receipt.vote_address.as_deref(), commit_receipt.submitted_at_block, latest)?;
+
+    let posted = post_handover(network, &read.vote_address, new_address)?;
+    // The commit receipt is kept in the data directory of the poll.
+    let datadir_path = Path::new(receipt).parent()
+        .filter(|path| !path.as_os_str().is_empty())
+        .unwrap_or_else(|| Path::new("."));
+    record_sent_transaction(&datadir_path.to_string_lossy(), SpendPhase::Handover, &posted.transaction_hash, &posted.address)?;
+    output::emit(&KeyRotation {
+        from: read.vote_address,
+        to: new_address.to_owned(),
+        transaction_hash: posted.transaction_hash,
+        previous_handovers: read.handovers.len()
+    })
+}
@@ -0,0 +1,202 @@
+//! # Command: Send Email
+//!
+//! `send_email` emails the voters selected for the poll, matched to their
+//! email address by roster position in a contacts file, and writes a
+//! delivery report keyed by ballot serial. Notifications and receipts
+//! never contain vote codes; ballots, which do, are only sent for polls
+//! configured for electronic delivery.
+
+use std::collections::HashMap;
+use std::fmt;
+use super::*;
+use crate::blockchain::blocking::transaction_statuses;
+use crate::email::*;
+use crate::votes::{SubmittedVote, TallyPolicy, fetch_submitted_votes};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct EmailDelivery {
+    pub kind: EmailKind,
+    pub sent: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    pub report: String
+}
+
+impl fmt::Display for EmailDelivery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Emails ({}): {} sent, {} failed, {} skipped; report written to {}",
+            self.kind, self.sent, self.failed, self.skipped, self.report)
+    }
+}
+
+/// Where the emails go: an SMTP configuration file, or an outbox directory
+/// with the sender address.
+pub enum EmailDestination<'a> {
+    Smtp(&'a str),
+    Outbox(&'a str, &'a str)
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn send_email(
+    network: &str,
+    pollconf_filename: &str,
+    kind: EmailKind,
+    contacts_filename: &str,
+    template_filename: Option<&str>,
+    destination: EmailDestination,
+    rate: u32,
+    report_filename: &str) -> Result<()>
+{
+    // Read poll configuration file.
+    let secured_poll_configuration = read_poll_configuration_file(pollconf_filename)?;
+
+    // Reconstruct the Poll Master Key from the trustee passwords.
+    let (poll_master_key, aead_pmk) = read_poll_master_key(&secured_poll_configuration);
+
+    // Ensure the data directory exists.
+    let datadir_path = ensure_poll_data_directory_exists(&secured_poll_configuration, &aead_pmk)?;
+
+    // Decrypt poll configuration state.
+    let pollconf_aead_values = secured_poll_configuration.encrypted_poll_configuration.values()?;
+    let serialized_pollconf = aead_decrypt(&aead_pmk, &pollconf_aead_values)?;
+    let pollconf: PollConfiguration = serde_yaml::from_slice(&serialized_pollconf).unwrap();
+
+    if kind == EmailKind::Ballot && !pollconf.electronic_delivery {
+        return Err("Ballots can only be emailed for polls configured for electronic delivery.".into());
+    }
+    if !pollconf.poll_state.summands_drawn {
+        return Err("Summands must be drawn to select the voters.".into());
+    }
+
+    let template = match template_filename {
+        Some(filename) => EmailTemplate::from_file(&filename, kind)?,
+        None => EmailTemplate::default_for(kind)
+    };
+    let contacts = read_contacts(&contacts_filename)?;
+    let mut mailer = match destination {
+        EmailDestination::Smtp(filename) => Mailer::smtp(&SmtpConfiguration::from_file(&filename)?, rate)?,
+        EmailDestination::Outbox(path, from) => Mailer::outbox(Path::new(path), from, rate)?
+    };
+
+    // Select the Voters.
+    let poll_secrets: PollSecrets = PollSecrets::for_poll(&poll_master_key, pollconf.generation_seed.as_deref())?;
+    let roster_indices = select_poll_voters(&pollconf, &poll_secrets)?;
+    let roster = read_voter_roster(&pollconf)?;
+
+    // Generate the Ballots.
+    let serials: Vec<BallotSerial> = (0..pollconf.num_ballots).collect();
+    let votecodes: Vec<VoteCode> = generate_votecodes(
+        &poll_secrets.votecode_root,
+        2 * pollconf.num_ballots);
+    let ballots = generate_ballots(&serials, &votecodes);
+
+    let audited_ballots: Vec<BallotSerial> = pollconf.audited_ballots.clone().unwrap_or_default().iter()
+        .filter_map(|serial| serial.parse().ok())
+        .collect();
+
+    // Find the first successful vote of every ballot for the receipts.
+    let recorded: HashMap<BallotSerial, SubmittedVote> = match kind {
+        EmailKind::Receipt => {
+            let commit_receipt = read_commit_receipt(&datadir_path)?
+                .ok_or("Poll has not been committed to the blockchain.")?;
+            let ballot_serials = votecode_serials(&poll_secrets, pollconf.num_ballots);
+            let votes: Vec<(BallotSerial, SubmittedVote)> = fetch_submitted_votes(network, &pollconf.payload_identifier(&datadir_path), commit_receipt.vote_address.as_deref(), commit_receipt.submitted_at_block, pollconf.tally_rules.closing_block(commit_receipt.end_block), read_vote_decryption_key(&pollconf, &datadir_path)?.as_ref())?
+                .into_iter()
+                .filter_map(|vote| ballot_serials.get_str(&vote.votecode).map(|&serial| (serial, vote)))
+                .collect();
+            // Failed transactions do not record a vote.
+            let hashes: Vec<String> = votes.iter().map(|(_, vote)| vote.transaction_hash.clone()).collect();
+            let statuses = transaction_statuses(network, &hashes)?;
+            let mut recorded: HashMap<BallotSerial, SubmittedVote> = HashMap::new();
+            for ((serial, vote), status) in votes.into_iter().zip(statuses.iter()) {
+                if status.succeeded != Some(false) {
+                    recorded.entry(serial).or_insert(vote);
+                }
+            }
+            recorded
+        },
+        _ => HashMap::new()
+    };
+
+    let mut report: Vec<DeliveryRecord> = Vec::new();
+    let mut delivered: Vec<BallotSerial> = Vec::new();
+    let mut progress = Progress::new("Sending emails", "voters", roster_indices.len());
+    for (ballot, &position) in ballots.iter().zip(roster_indices.iter()) {
+        progress.inc(1);
+        let serial = string_from_ballotserial(&ballot.serial, pollconf.num_ballots);
+        let mut record = DeliveryRecord {
+            serial: serial.clone(),
+            roster_position: position,
+            email: contacts.get(&position).cloned().unwrap_or_default(),
+            status: DeliveryStatus::Skipped,
+            detail: String::new(),
+            sent_at: None
+        };
+        if record.email.is_empty() {
+            record.detail = "no email address".to_owned();
+            report.push(record);
+            continue;
+        }
+        if kind == EmailKind::Ballot && audited_ballots.contains(&ballot.serial) {
+            record.detail = "audited ballot".to_owned();
+            report.push(record);
+            continue;
+        }
+
+        let voter = &roster.records[position].voter_info;
+        let mut values: HashMap<&str, String> = HashMap::new();
+        values.insert("poll", datadir_path.clone());
+        values.insert("serial", serial);
+        values.insert("first_name", voter.first_name.clone());
+        values.insert("last_name", voter.last_name.clone());
+        match kind {
+            EmailKind::Notification => {},
+            EmailKind::Receipt => {
+                let vote = recorded.get(&ballot.serial);
+                values.insert("status", if vote.is_some() { "recorded" } else { "not recorded" }.to_owned());
+                values.insert("block", vote.map(|vote| vote.block_number.to_string()).unwrap_or_default());
+                values.insert("transaction", vote.map(|vote| vote.transaction_hash.clone()).unwrap_or_default());
+            },
+            EmailKind::Ballot => {
+                values.insert("choice1", string_from_choicevalue(&ballot.choice1.choice));
+                values.insert("votecode1", string_from_votecode(&ballot.choice1.votecode));
+                values.insert("choice2", string_from_choicevalue(&ballot.choice2.choice));
+                values.insert("votecode2", string_from_votecode(&ballot.choice2.votecode));
+            }
+        }
+        let (subject, body) = template.render(&values);
+
+        match mailer.send(&record.email, &subject, &body) {
+            Ok(()) => {
+                record.status = DeliveryStatus::Sent;
+                record.sent_at = Some(chrono::Utc::now());
+                if kind == EmailKind::Ballot {
+                    delivered.push(ballot.serial);
+                }
+            },
+            Err(err) => {
+                warn!("Email to roster position {} could not be sent: {}", position, err);
+                record.status = DeliveryStatus::Failed;
+                record.detail = err.to_string();
+            }
+        }
+        report.push(record);
+    }
+    progress.finish();
+
+    let mut csvwriter = csv::Writer::from_path(report_filename)?;
+    for record in report.iter() {
+        csvwriter.serialize(record)?;
+    }
+    csvwriter.flush()?;
+    record_ballot_states(&datadir_path, BallotState::Delivered, &delivered)?;
+
+    let count = |status: DeliveryStatus| report.iter().filter(|record| record.status == status).count();
+    output::emit(&EmailDelivery {
+        kind,
+        sent: count(DeliveryStatus::Sent),
+        failed: count(DeliveryStatus::Failed),
+        skipped: count(DeliveryStatus::Skipped),
+        report: report_filename.to_owned()
+    })
+}
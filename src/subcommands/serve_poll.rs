@@ -0,0 +1,238 @@
+//! # Command: Serve Poll
+//!
+//! `serve_poll` runs the vote relay server for a poll, so that the web
+//! interface can submit votes, fetch proofs of inclusion and query the
+//! status of the poll through this crate. The audit events found in the
+//! data directory of the poll are streamed to observers. Given a help desk
+//! token file, ballot lookups are served to its holders. With the `grpc`
+//! feature, the registrar gRPC service can be served from the same process.
+//!
+//! `serve_public_proofs` serves only the proofs of inclusion of a published
+//! Merkle tree, without the poll configuration or trustee passwords, for
+//! exposing proof lookup to the public. Both limit the requests of every
+//! client address when given a rate limit.
+//!
+//! Given a number of transactions, the relay server warns when the commit
+//! or relayer account pays for fewer, checking every minute.
+//!
+//! The relay server keeps the proof index of its Merkle tree current in the
+//! background, building it, throttled, whenever the tree has none or the
+//! tree was stored again, so that proofs are read from the index.
+//!
+//! Given a voting deadline, a closing time or the closing block of the
+//! poll, the relay server closes the voting window itself, records the
+//! votes and, given the audited columns seed, counts the tally. The trustee
+//! passwords are then kept for the lifetime of the server, to run these
+//! steps unattended.
+
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use super::*;
+use crate::blockchain::{account_balances, build_proof_index, proof_index_path, BalanceAlert, BalanceWatch, ProofIndex};
+use crate::blockchain::merkle::MappedTree;
+use crate::votes::TallyPolicy;
+use crate::server::{serve, serve_proofs, watch_audit_events, watch_voting_deadline, HelpdeskCredential, AUDIT_EVENT_INTERVAL, DEADLINE_CHECK_INTERVAL, PollClosing, ProofServer, RateLimiter, RelayServer, VotingDeadline};
+
+/// Time between checks of the account balances.
+const BALANCE_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Time between checks of the proof index of the Merkle tree.
+const PROOF_INDEX_CHECK_INTERVAL: Duration = Duration::from_secs(60);
+/// Pause of the background build of the proof index between chunks of
+/// leaves.
+const PROOF_INDEX_THROTTLE: Duration = Duration::from_millis(20);
+
+/// Whether the Merkle tree at `merkle_tree` exists and has no current
+/// proof index.
+fn proof_index_stale(merkle_tree: &Path) -> Result<bool> {
+    if !merkle_tree.exists() {
+        return Ok(false);
+    }
+    let tree = MappedTree::open(&merkle_tree.to_string_lossy())?;
+    Ok(ProofIndex::open_current(&proof_index_path(merkle_tree), &tree)?.is_none())
+}
+
+async fn watch_proof_index(merkle_tree: PathBuf) {
+    loop {
+        let tree = merkle_tree.clone();
+        let built = tokio::task::spawn_blocking(move || {
+            let build = || -> Result<Option<usize>> {
+                match proof_index_stale(&tree)? {
+                    true => Ok(Some(build_proof_index(&tree, &proof_index_path(&tree), PROOF_INDEX_THROTTLE)?.leafs())),
+                    false => Ok(None)
+                }
+            };
+            build().map_err(|err| err.to_string())
+        }).await;
+        match built {
+            Ok(Ok(Some(leafs))) => info!("Indexed the proofs of the {} leaves of {}", leafs, merkle_tree.display()),
+            Ok(Ok(None)) => (),
+            Ok(Err(err)) => warn!("The proofs of {} could not be indexed: {}", merkle_tree.display(), err),
+            Err(err) => {
+                warn!("The proof index is no longer kept: {}", err);
+                return;
+            }
+        }
+        futures_timer::Delay::new(PROOF_INDEX_CHECK_INTERVAL).await;
+    }
+}
+
+async fn watch_balances(network: String, mut balance_watch: BalanceWatch) {
+    loop {
+        match account_balances(&network).await {
+            Ok(balances) => {
+                for balance in balance_watch.check(&balances) {
+                    warn!("Fund the {} account {}: its balance pays for about {} more transactions.",
+                        balance.role, balance.address, balance.remaining_transactions(balance_watch.alert.gas_per_transaction));
+                }
+            },
+            Err(err) => warn!("Account balances could not be read: {}", err)
+        }
+        futures_timer::Delay::new(BALANCE_CHECK_INTERVAL).await;
+    }
+}
+
+/// Closing of the voting window by the relay server.
+#[derive(Debug, Clone, Default)]
+pub struct DeadlineOptions<'a> {
+    /// Closing time, in RFC 3339 format.
+    pub close_at: Option<&'a str>,
+    /// Close once a block after the closing block of the poll is mined.
+    pub at_end_block: bool,
+    /// Hex encoded audited columns seed to count the tally with.
+    pub audited_columns_seed: Option<&'a str>,
+    /// Webhook configuration file the closing events are posted to.
+    pub webhooks: Option<&'a str>
+}
+
+impl DeadlineOptions<'_> {
+    pub fn enabled(&self) -> bool {
+        self.close_at.is_some() || self.at_end_block
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+pub async fn serve_poll(network: &str, pollconf_filename: &str, listen: &str, merkle_tree: &str, grpc_listen: Option<&str>, helpdesk_token: Option<&str>, rate_limit: Option<u32>, low_balance: Option<u64>, deadline: &DeadlineOptions<'_>) -> Result<()> {
+    let address: SocketAddr = listen.parse()?;
+    let grpc_address: Option<SocketAddr> = match grpc_listen {
+        Some(grpc_listen) if cfg!(feature = "grpc") => Some(grpc_listen.parse()?),
+        Some(_) => return Err("gRPC service requires the grpc feature.".into()),
+        None => None
+    };
+    if !deadline.enabled() && (deadline.audited_columns_seed.is_some() || deadline.webhooks.is_some()) {
+        return Err("The audited columns seed and webhooks are used at a voting deadline.".into());
+    }
+    if deadline.webhooks.is_some() && !cfg!(feature = "webhooks") {
+        return Err("Webhooks require the webhooks feature.".into());
+    }
+    let close_at = match deadline.close_at {
+        Some(close_at) => Some(DateTime::parse_from_rfc3339(close_at)?.with_timezone(&Utc)),
+        None => None
+    };
+    if let Some(seed) = deadline.audited_columns_seed {
+        if hex::decode(seed)?.len() != CSPRNGSeed::SIZE {
+            return Err(format!("Seed for Audited Columns must be {} bytes long.", CSPRNGSeed::SIZE).into());
+        }
+    }
+
+    // Read poll configuration file.
+    let secured_poll_configuration = read_poll_configuration_file(pollconf_filename)?;
+
+    // Keep the trustee passwords to run the closing steps unattended.
+    if deadline.enabled() {
+        set_trustee_passwords(secured_poll_configuration.poll_trustees.iter()
+            .map(|trustee| (trustee.identifier.clone(), read_trustee_password(&trustee.identifier)))
+            .collect());
+    }
+
+    // Reconstruct the Poll Master Key from the trustee passwords.
+    let (poll_master_key, aead_pmk) = read_poll_master_key(&secured_poll_configuration);
+
+    // Ensure the data directory exists.
+    let datadir_path = ensure_poll_data_directory_exists(&secured_poll_configuration, &aead_pmk)?;
+
+    // Decrypt poll configuration state.
+    let pollconf_aead_values = secured_poll_configuration.encrypted_poll_configuration.values()?;
+    let serialized_pollconf = aead_decrypt(&aead_pmk, &pollconf_aead_values)?;
+    let pollconf: PollConfiguration = serde_yaml::from_slice(&serialized_pollconf).unwrap();
+
+    // Check the signed artifacts loaded with the poll verification key.
+    use_poll_verification_key(&secured_poll_configuration, &aead_pmk)?;
+
+    // Re-construct the ballots to recognize the vote codes of the poll.
+    let poll_secrets: PollSecrets = PollSecrets::for_poll(&poll_master_key, pollconf.generation_seed.as_deref())?;
+    let ballots = votecode_serials(&poll_secrets, pollconf.num_ballots);
+
+    let mut server = RelayServer::new(
+        network,
+        &datadir_path,
+        pollconf_filename,
+        aead_pmk,
+        merkle_tree,
+        ballots);
+    let relayed = server.open_relay_log()?;
+    if 0 < relayed {
+        info!("Vote codes relayed before: {}", relayed);
+    }
+    if let Some(helpdesk_token) = helpdesk_token {
+        let token = std::fs::read_to_string(helpdesk_token)?;
+        server.set_helpdesk_credential(HelpdeskCredential::new(secrecy::SecretString::new(token))?);
+    }
+    if let Some(rate_limit) = rate_limit {
+        server.set_rate_limiter(RateLimiter::per_minute(rate_limit));
+    }
+    server.set_payload_identifier(&pollconf.payload_identifier(&datadir_path));
+    if let Some(key) = pollconf.vote_encryption_key()? {
+        server.set_vote_encryption_key(key);
+    }
+    let closing = match deadline.enabled() {
+        true => {
+            let closing_block = match deadline.at_end_block {
+                true => match read_commit_receipt(&datadir_path)?.and_then(|commit_receipt| commit_receipt.end_block) {
+                    Some(end_block) => pollconf.tally_rules.closing_block(Some(end_block)),
+                    None => return Err("The poll has no end block to close at.".into())
+                },
+                false => None
+            };
+            let mut closing = PollClosing::new(VotingDeadline { close_at, closing_block });
+            closing.audited_columns_seed = deadline.audited_columns_seed.map(str::to_owned);
+            #[cfg(feature = "webhooks")]
+            if let Some(webhooks) = deadline.webhooks {
+                use crate::webhooks::{WebhookConfiguration, WebhookNotifier};
+                let configuration = WebhookConfiguration::from_file(&webhooks)?;
+                closing.sinks.push(Box::new(WebhookNotifier::new(configuration, pollconf.signing_key.clone())));
+            }
+            Some(closing)
+        },
+        false => None
+    };
+    let server = Arc::new(server);
+    tokio::spawn(watch_audit_events(server.clone(), AUDIT_EVENT_INTERVAL));
+    tokio::spawn(watch_proof_index(PathBuf::from(merkle_tree)));
+    if let Some(closing) = closing {
+        tokio::spawn(watch_voting_deadline(server.clone(), closing, DEADLINE_CHECK_INTERVAL));
+    }
+    if let Some(low_balance) = low_balance {
+        tokio::spawn(watch_balances(network.to_owned(), BalanceWatch::new(BalanceAlert::new(low_balance))));
+    }
+
+    match grpc_address {
+        #[cfg(feature = "grpc")]
+        Some(grpc_address) => {
+            futures::try_join!(
+                serve(server.clone(), address),
+                crate::server::serve_grpc(server, grpc_address))?;
+            Ok(())
+        },
+        _ => serve(server, address).await
+    }
+}
+
+pub async fn serve_public_proofs(dirname: &str, listen: &str, rate_limit: u32) -> Result<()> {
+    let address: SocketAddr = listen.parse()?;
+    let mut server = ProofServer::open(Path::new(dirname))?;
+    server.set_rate_limiter(RateLimiter::per_minute(rate_limit));
+    serve_proofs(Arc::new(server), address).await
+}
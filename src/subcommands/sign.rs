@@ -2,9 +2,23 @@
 //!
 //! `sign` generates a signature using the poll signing key.
 
+use std::fmt;
 use std::fs;
 use super::*;
 
+#[derive(Debug, Clone, Serialize)]
+pub struct DocumentSignature {
+    pub document: String,
+    pub signature_file: String,
+    pub signature: String
+}
+
+impl fmt::Display for DocumentSignature {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Signature for {} written to {}", self.document, self.signature_file)
+    }
+}
+
 pub fn sign_document(pollconf_filename: &str, document_filename: &str) -> Result<()> {
     let document_path = Path::new(document_filename);
 
@@ -25,5 +39,9 @@ pub fn sign_document(pollconf_filename: &str, document_filename: &str) -> Result
     let document_signature_path = Path::new(&document_signature_path_str);
     fs::write(&document_signature_path, base64::encode(&signature))?;
 
-    Ok(())
+    output::emit(&DocumentSignature {
+        document: document_filename.to_owned(),
+        signature_file: document_signature_path_str,
+        signature: base64::encode(&signature)
+    })
 }
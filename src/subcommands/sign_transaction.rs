@@ -0,0 +1,21 @@
+//! # Command: Sign Transaction
+//!
+//! `sign_transaction` signs a transaction built for the offline commit key
+//! with the commit key of the network configuration, on the offline
+//! machine. It reads no poll configuration and sends nothing to the node;
+//! the signed transaction is carried back to the online machine and posted
+//! by running step 4 again with it.
+
+use std::fs;
+use super::*;
+use crate::blockchain::{sign_offline, UnsignedTransaction};
+
+pub fn sign_transaction(network: &str, unsigned_filename: &str, signed_filename: &str) -> Result<()> {
+    if Path::new(signed_filename).exists() {
+        return Err(format!("{} already exists.", signed_filename).into());
+    }
+    let unsigned: UnsignedTransaction = serde_yaml::from_reader(File::open(unsigned_filename)?)?;
+    let signed = sign_offline(network, &unsigned)?;
+    fs::write(signed_filename, serde_yaml::to_string(&signed)?)?;
+    output::emit(&signed)
+}
@@ -0,0 +1,18 @@
+//! # Command: Simulate Poll
+//!
+//! `simulate_poll` runs a complete simulated poll in a directory against
+//! the in-memory mock chain, and checks that the revealed tally matches
+//! the votes of the simulated voters.
+
+use super::*;
+use crate::simulation::*;
+
+
+pub fn simulate_poll(dirname: &str, configuration: &SimulationConfiguration) -> Result<()> {
+    let report = run_simulation(configuration, Path::new(dirname))?;
+    output::emit(&report)?;
+    if !report.matches {
+        return Err("Revealed tally does not match the simulated votes.".into());
+    }
+    Ok(())
+}
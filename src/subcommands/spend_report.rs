@@ -0,0 +1,20 @@
+//! # Command: Spend Report
+//!
+//! `spend_report` reports the gas used and the ether spent by the
+//! transactions sent for a poll, per phase and per sending account, from
+//! the spending log in its data directory and the receipts of the
+//! transactions on the chain. Only the data directory is needed, not the
+//! trustee passwords.
+
+use super::*;
+use crate::blockchain::{read_sent_transactions, SpendReport};
+use crate::blockchain::blocking::transaction_statuses;
+
+pub fn spend_report(network: &str, datadir_path: &str) -> Result<()> {
+    let sent = read_sent_transactions(datadir_path)?;
+    let transaction_hashes: Vec<String> = sent.iter()
+        .map(|transaction| transaction.transaction_hash.clone())
+        .collect();
+    let statuses = transaction_statuses(network, &transaction_hashes)?;
+    output::emit(&SpendReport::new(datadir_path, &sent, &statuses))
+}
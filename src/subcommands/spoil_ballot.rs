@@ -0,0 +1,90 @@
+//! # Command: Spoil Ballot
+//!
+//! `spoil_ballot` marks a ballot that a voter damaged or lost as spoiled
+//! and records the ballot issued to the voter instead. The replacement is
+//! committed to the blockchain as an amendment of the poll, recorded in the
+//! secured poll configuration and published in `amendments.yaml`. Votes for
+//! the vote codes of a spoiled ballot are neither recorded by step 6 nor
+//! counted by the tally. When the poll reserves replacement serials, the
+//! replacement is taken from them.
+//!
+//! A replacement issued by the print pipeline with fresh vote codes (see
+//! `ballots::envelope`) is given with the commitment of its vote codes,
+//! which the amendment commits to. Its vote codes are never shown to the
+//! official spoiling the ballot, nor derived from the Poll Master Key.
+
+use super::*;
+use crate::blockchain::{record_sent_transaction, SpendPhase};
+use crate::blockchain::blocking::post_amendment;
+
+/// Spoil ballot `serial` for `replacement`, issued with fresh vote codes
+/// if given the hex encoded `commitment` of its codes.
+pub fn spoil_ballot(network: &str, pollconf_filename: &str, serial: BallotSerial, replacement: BallotSerial, reason: Option<&str>, commitment: Option<&str>) -> Result<()> {
+    if let Some(commitment) = commitment {
+        if hex::decode(commitment).map(|commitment| commitment.len()).ok() != Some(32) {
+            return Err(format!("{} is not the commitment of the vote codes of a replacement ballot.", commitment).into());
+        }
+    }
+
+    // Read poll configuration file.
+    let mut secured_poll_configuration = read_poll_configuration_file(pollconf_filename)?;
+
+    // Reconstruct the Poll Master Key from the trustee passwords.
+    let (_, aead_pmk) = read_poll_master_key(&secured_poll_configuration);
+
+    // Ensure the data directory exists.
+    let datadir_path = ensure_poll_data_directory_exists(&secured_poll_configuration, &aead_pmk)?;
+
+    // Decrypt poll configuration state.
+    let pollconf_aead_values = secured_poll_configuration.encrypted_poll_configuration.values()?;
+    let serialized_pollconf = aead_decrypt(&aead_pmk, &pollconf_aead_values)?;
+    let mut pollconf: PollConfiguration = serde_yaml::from_slice(&serialized_pollconf).unwrap();
+
+    if !pollconf.poll_state.ceremony_conducted {
+        return Err("Ballots are spoiled after they are issued: record the audited ballots (step 4) first.".into());
+    }
+    if pollconf.poll_state.votes_committed {
+        return Err("Votes were already recorded (step 6): ballots can no longer be spoiled.".into());
+    }
+
+    // Neither ballot may be audited, spoiled or already a replacement.
+    let audited_ballots: Vec<BallotSerial> = pollconf.audited_ballots.clone().unwrap_or_default().iter()
+        .filter_map(|serial| serial.parse().ok())
+        .collect();
+    for ballot in &[serial, replacement] {
+        if *ballot >= pollconf.num_ballots {
+            return Err(format!("Ballot {} is not a ballot of the poll.", ballot).into());
+        }
+        if audited_ballots.contains(ballot) {
+            return Err(format!("Ballot {} was audited.", ballot).into());
+        }
+        if let Some(amendment) = pollconf.ballot_amendments.iter()
+            .find(|amendment| amendment.spoiled_serial == *ballot || amendment.replacement_serial == *ballot) {
+            return Err(format!("Ballot {} was already amended: ballot {} was replaced by ballot {}.",
+                ballot, amendment.spoiled_serial, amendment.replacement_serial).into());
+        }
+    }
+    if serial == replacement {
+        return Err("A spoiled ballot cannot replace itself.".into());
+    }
+    pollconf.serial_ranges().check_replacement(serial, replacement)?;
+
+    // Commit to the replacement before recording it.
+    let amendment = post_amendment(network, &datadir_path, serial, replacement, reason, commitment)?;
+    record_sent_transaction(&datadir_path, SpendPhase::Amendment, &amendment.transaction_hash, &amendment.address)?;
+    pollconf.ballot_amendments.push(amendment.clone());
+    record_ballot_states(&datadir_path, BallotState::Spoiled, &[serial])?;
+    serde_yaml::to_writer(
+        File::create(Path::new(&datadir_path).join("amendments.yaml"))?,
+        &pollconf.ballot_amendments)?;
+
+    // Re-encrypt the poll configuration.
+    let serialized_pollconf = Zeroizing::new(serde_yaml::to_string(&pollconf)?);
+    secured_poll_configuration.encrypted_poll_configuration = AEADString::from_values(
+        aead_encrypt(&aead_pmk,
+                     Vec::new(),
+                     serialized_pollconf.as_bytes().to_vec())?);
+    write_poll_configuration_file(pollconf_filename, &secured_poll_configuration)?;
+
+    output::emit(&amendment)
+}
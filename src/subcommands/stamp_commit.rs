@@ -0,0 +1,187 @@
+//! # Command: Stamp Commit
+//!
+//! `stamp_commit` timestamps the commitment of a poll with OpenTimestamps,
+//! next to its transaction on the EVM chain, and stores the proof as
+//! `commit_receipt.ots` next to the commit receipt. `verify_stamp` checks a
+//! proof against a commit receipt, completes its pending attestations from
+//! the calendars once they reach Bitcoin, and checks its Bitcoin
+//! attestations against the merkle roots of their blocks. Only the public
+//! receipt and proof are needed, not the trustee passwords.
+
+use std::fmt;
+use super::*;
+use crate::blockchain::{commitment_digest, Attestation, CommitReceipt, DetachedTimestamp, OTS_FILENAME};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StampedCommit {
+    pub poll_identifier: String,
+    pub digest: String,
+    pub calendars: Vec<String>,
+    pub output: String
+}
+
+impl fmt::Display for StampedCommit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Commitment {} of {} submitted to {} calendars, proof written to {}",
+            self.digest, self.poll_identifier, self.calendars.len(), self.output)
+    }
+}
+
+/// Timestamp the commitment of the poll of `datadir_path` with the
+/// calendars, or the default calendars if none are given.
+pub fn stamp_commit(datadir_path: &str, calendars: &[String]) -> Result<()> {
+    let receipt = read_commit_receipt(datadir_path)?.ok_or("Poll has not been committed yet.")?;
+    let digest = commitment_digest(&receipt)?;
+    let calendars: Vec<String> = match calendars.is_empty() {
+        true => blockchain::DEFAULT_CALENDARS.iter().map(|calendar| calendar.to_string()).collect(),
+        false => calendars.to_vec()
+    };
+    let detached = stamp_digest(digest, &calendars)?;
+    let output = Path::new(datadir_path).join(OTS_FILENAME);
+    detached.write(&output)?;
+    output::emit(&StampedCommit {
+        poll_identifier: receipt.poll_identifier,
+        digest: hex::encode(digest),
+        calendars,
+        output: output.display().to_string()
+    })
+}
+
+#[cfg(feature = "timestamps")]
+fn stamp_digest(digest: [u8; 32], calendars: &[String]) -> Result<DetachedTimestamp> {
+    blockchain::blocking::stamp(digest, calendars)
+}
+
+#[cfg(not(feature = "timestamps"))]
+fn stamp_digest(_: [u8; 32], _: &[String]) -> Result<DetachedTimestamp> {
+    Err("Timestamping commitments requires the timestamps feature.".into())
+}
+
+/// Attestation of a proof, with whether it was checked against Bitcoin.
+#[derive(Debug, Clone, Serialize)]
+pub struct StampAttestation {
+    /// Calendar of a pending attestation.
+    pub pending: Option<String>,
+    /// Bitcoin block height of a completed attestation.
+    pub bitcoin_block: Option<u64>,
+    /// Whether the commitment is the merkle root of the Bitcoin block, if
+    /// it was checked.
+    pub matches_block: Option<bool>
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct StampVerification {
+    pub poll_identifier: String,
+    pub digest: String,
+    /// Whether the proof is for the commitment of the receipt.
+    pub matches_receipt: bool,
+    /// Pending attestations completed from the calendars.
+    pub upgraded: usize,
+    pub attestations: Vec<StampAttestation>
+}
+
+impl StampVerification {
+    pub fn valid(&self) -> bool {
+        self.matches_receipt
+            && !self.attestations.is_empty()
+            && self.attestations.iter().all(|attestation| attestation.matches_block != Some(false))
+    }
+}
+
+impl fmt::Display for StampVerification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn yes_no(value: bool) -> &'static str { if value { "yes" } else { "NO" } }
+        writeln!(f, "Poll:            {}", self.poll_identifier)?;
+        writeln!(f, "Digest:          {}", self.digest)?;
+        writeln!(f, "Matches receipt: {}", yes_no(self.matches_receipt))?;
+        if 0 < self.upgraded {
+            writeln!(f, "Upgraded:        {} attestations", self.upgraded)?;
+        }
+        write!(f, "Attestations:    {}", self.attestations.len())?;
+        for attestation in self.attestations.iter() {
+            match (&attestation.pending, attestation.bitcoin_block, attestation.matches_block) {
+                (Some(calendar), _, _) => write!(f, "\n  pending at {}", calendar)?,
+                (_, Some(block), Some(matches)) => write!(f, "\n  Bitcoin block {}: {}", block, yes_no(matches))?,
+                (_, Some(block), None) => write!(f, "\n  Bitcoin block {}: not checked", block)?,
+                _ => write!(f, "\n  unknown attestation")?
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Check the proof of `proof_filename`, by default `commit_receipt.ots`
+/// next to the receipt, against the commit receipt; with `upgrade`, first
+/// complete its pending attestations and check the Bitcoin attestations
+/// with the Esplora API at `esplora`. An upgraded proof is written back.
+pub fn verify_stamp(receipt_filename: &str, proof_filename: Option<&str>, upgrade: bool, esplora: &str) -> Result<()> {
+    let receipt: CommitReceipt = serde_yaml::from_reader(File::open(receipt_filename)?)?;
+    let proof_path = match proof_filename {
+        Some(proof_filename) => PathBuf::from(proof_filename),
+        None => Path::new(receipt_filename).with_file_name(OTS_FILENAME)
+    };
+    let mut detached = DetachedTimestamp::read(&proof_path)?;
+
+    let upgraded = match upgrade {
+        true => upgrade_stamp(&mut detached)?,
+        false => 0
+    };
+    if 0 < upgraded {
+        detached.write(&proof_path)?;
+    }
+    let attestations = detached.attestations().into_iter()
+        .map(|(commitment, attestation)| Ok(match attestation {
+            Attestation::Pending(calendar) => StampAttestation {
+                pending: Some(calendar),
+                bitcoin_block: None,
+                matches_block: None
+            },
+            Attestation::Bitcoin(height) => StampAttestation {
+                pending: None,
+                bitcoin_block: Some(height),
+                matches_block: match upgrade {
+                    true => Some(bitcoin_merkle_root(esplora, height)? == commitment),
+                    false => None
+                }
+            },
+            Attestation::Unknown { .. } => StampAttestation {
+                pending: None,
+                bitcoin_block: None,
+                matches_block: None
+            }
+        }))
+        .collect::<Result<Vec<StampAttestation>>>()?;
+
+    let verification = StampVerification {
+        poll_identifier: receipt.poll_identifier.clone(),
+        digest: hex::encode(detached.digest),
+        matches_receipt: commitment_digest(&receipt)? == detached.digest,
+        upgraded,
+        attestations
+    };
+    output::emit(&verification)?;
+    match verification.valid() {
+        true => Ok(()),
+        false => Err("Timestamp does not match its commitment.".into())
+    }
+}
+
+#[cfg(feature = "timestamps")]
+fn upgrade_stamp(detached: &mut DetachedTimestamp) -> Result<usize> {
+    blockchain::blocking::upgrade_timestamp(detached)
+}
+
+#[cfg(feature = "timestamps")]
+fn bitcoin_merkle_root(esplora: &str, height: u64) -> Result<Vec<u8>> {
+    blockchain::blocking::bitcoin_merkle_root(esplora, height)
+}
+
+#[cfg(not(feature = "timestamps"))]
+fn upgrade_stamp(_: &mut DetachedTimestamp) -> Result<usize> {
+    Err("Upgrading timestamps requires the timestamps feature.".into())
+}
+
+#[cfg(not(feature = "timestamps"))]
+fn bitcoin_merkle_root(_: &str, _: u64) -> Result<Vec<u8>> {
+    Err("Checking Bitcoin attestations requires the timestamps feature.".into())
+}
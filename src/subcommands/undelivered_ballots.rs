@@ -0,0 +1,116 @@
+//! # Command: Import Undeliverable
+//!
+//! `import_undeliverable` reads the list of the ballots the mailing vendor
+//! could not deliver and records them as undelivered in the secured poll
+//! configuration. The undelivered ballots are published in
+//! `undelivered.yaml` and left out of the expected turnout reported by the
+//! poll status. Under the `invalidate` undelivered policy, votes for their
+//! vote codes are neither recorded by step 6 nor counted by the tally.
+//! Lists are imported as the vendor returns them: serials already recorded
+//! are skipped.
+
+use std::fmt;
+use chrono::Utc;
+use super::*;
+use crate::ballots::{BallotSerial, SerialPurpose, UndeliveredBallot, UndeliveredPolicy, read_undeliverable_list};
+
+/// Undeliverable list imported.
+#[derive(Debug, Clone, Serialize)]
+pub struct UndeliverableImport {
+    /// Serials newly recorded as undelivered.
+    pub imported: Vec<BallotSerial>,
+    /// Serials of the list already recorded.
+    pub already_recorded: usize,
+    /// Undelivered ballots of the poll.
+    pub undelivered: usize,
+    pub policy: UndeliveredPolicy
+}
+
+impl fmt::Display for UndeliverableImport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Undelivered ballots imported: {}", self.imported.len())?;
+        if 0 < self.already_recorded {
+            writeln!(f, "Already recorded: {}", self.already_recorded)?;
+        }
+        write!(f, "Undelivered ballots of the poll: {} ({})", self.undelivered, self.policy)
+    }
+}
+
+pub fn import_undeliverable(pollconf_filename: &str, list_filename: &str) -> Result<()> {
+    // Read poll configuration file.
+    let mut secured_poll_configuration = read_poll_configuration_file(pollconf_filename)?;
+
+    // Reconstruct the Poll Master Key from the trustee passwords.
+    let (_, aead_pmk) = read_poll_master_key(&secured_poll_configuration);
+
+    // Ensure the data directory exists.
+    let datadir_path = ensure_poll_data_directory_exists(&secured_poll_configuration, &aead_pmk)?;
+
+    // Decrypt poll configuration state.
+    let pollconf_aead_values = secured_poll_configuration.encrypted_poll_configuration.values()?;
+    let serialized_pollconf = aead_decrypt(&aead_pmk, &pollconf_aead_values)?;
+    let mut pollconf: PollConfiguration = serde_yaml::from_slice(&serialized_pollconf).unwrap();
+
+    if !pollconf.poll_state.ceremony_conducted {
+        return Err("Ballots are mailed after they are audited: record the audited ballots (step 4) first.".into());
+    }
+    if pollconf.poll_state.votes_committed {
+        return Err("Votes were already recorded (step 6): undelivered ballots can no longer be imported.".into());
+    }
+
+    // Every serial of the list must be a ballot mailed to a voter.
+    let rows = read_undeliverable_list(File::open(list_filename)?)?;
+    let audited_ballots: Vec<BallotSerial> = pollconf.audited_ballots.clone().unwrap_or_default().iter()
+        .filter_map(|serial| serial.parse().ok())
+        .collect();
+    let serial_ranges = pollconf.serial_ranges();
+    for row in rows.iter() {
+        if row.serial >= pollconf.num_ballots {
+            return Err(format!("Ballot {} is not a ballot of the poll.", row.serial).into());
+        }
+        if audited_ballots.contains(&row.serial) {
+            return Err(format!("Ballot {} was audited.", row.serial).into());
+        }
+        if serial_ranges.purpose(row.serial) == Some(SerialPurpose::Test) {
+            return Err(format!("Ballot {} is a test ballot.", row.serial).into());
+        }
+    }
+
+    // Record the serials not recorded yet.
+    let recorded_at = Utc::now();
+    let mut imported = Vec::new();
+    let mut already_recorded = 0;
+    for row in rows {
+        if pollconf.undelivered_ballots.iter().any(|ballot| ballot.serial == row.serial) {
+            already_recorded += 1;
+            continue;
+        }
+        imported.push(row.serial);
+        pollconf.undelivered_ballots.push(UndeliveredBallot {
+            serial: row.serial,
+            reason: row.reason,
+            recorded_at
+        });
+    }
+    if !imported.is_empty() {
+        info!("Ballots recorded as undelivered: {}", imported.len());
+    }
+    serde_yaml::to_writer(
+        File::create(Path::new(&datadir_path).join("undelivered.yaml"))?,
+        &pollconf.undelivered_ballots)?;
+
+    // Re-encrypt the poll configuration.
+    let serialized_pollconf = Zeroizing::new(serde_yaml::to_string(&pollconf)?);
+    secured_poll_configuration.encrypted_poll_configuration = AEADString::from_values(
+        aead_encrypt(&aead_pmk,
+                     Vec::new(),
+                     serialized_pollconf.as_bytes().to_vec())?);
+    write_poll_configuration_file(pollconf_filename, &secured_poll_configuration)?;
+
+    output::emit(&UndeliverableImport {
+        imported,
+        already_recorded,
+        undelivered: pollconf.undelivered_ballots.len(),
+        policy: pollconf.undelivered_policy
+    })
+}
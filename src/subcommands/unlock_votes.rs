@@ -0,0 +1,119 @@
+//! # Command: Unlock Votes
+//!
+//! `unlock_votes` solves the time-lock puzzle of a poll with time-locked
+//! votes, published in the data directory when the poll is created, and
+//! writes the vote decryption key next to it, where `record_votes` reads
+//! it. Solving takes until about the reveal time when started as the poll
+//! is created, on a machine as fast as the one that created it. It needs
+//! neither the poll configuration nor the trustees, and checkpoints the
+//! squarings done every minute, so that solving resumes where it was
+//! interrupted.
+
+use std::fmt;
+use std::fs::{self, OpenOptions};
+use std::time::{Duration, Instant};
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+use chrono::{DateTime, Utc};
+use super::*;
+
+/// File of the data directory the time-lock puzzle of the poll is
+/// published to.
+pub const TIME_LOCK_FILENAME: &str = "time_lock.yaml";
+
+const UNLOCKED_VOTE_KEY_FILENAME: &str = "unlocked_vote_key";
+
+const TIME_LOCK_CHECKPOINT_FILENAME: &str = "time_lock_checkpoint.yaml";
+
+const CHECKPOINT_INTERVAL: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, Serialize)]
+pub struct UnlockedVotes {
+    pub reveal_at: DateTime<Utc>,
+    pub squarings: u64,
+    /// Squarings done before, resumed from their checkpoint.
+    pub resumed_from: Option<u64>,
+    pub output: String
+}
+
+impl fmt::Display for UnlockedVotes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Votes locked until {} unlocked after {} squarings to {}",
+            self.reveal_at, self.squarings, self.output)?;
+        if let Some(resumed_from) = self.resumed_from {
+            write!(f, " (resumed after {} squarings)", resumed_from)?;
+        }
+        Ok(())
+    }
+}
+
+// Replace the checkpoint, readable by its owner only, so that an
+// interruption while writing leaves the previous one.
+fn write_checkpoint(path: &Path, checkpoint: &TimeLockCheckpoint) -> Result<()> {
+    let partial = path.with_extension("yaml.partial");
+    let mut options = OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    options.mode(0o600);
+    serde_yaml::to_writer(options.open(&partial)?, checkpoint)?;
+    fs::rename(&partial, path)?;
+    Ok(())
+}
+
+/// Solve the time-lock puzzle of the file `time_lock_filename`, resuming
+/// from its checkpoint if solving was interrupted, and write the vote
+/// decryption key to the same directory.
+pub fn unlock_votes(time_lock_filename: &str) -> Result<()> {
+    let time_lock: TimeLockedKey = serde_yaml::from_reader(File::open(time_lock_filename)?)?;
+    let datadir_path = Path::new(time_lock_filename).parent().unwrap_or_else(|| Path::new(""));
+    let output = datadir_path.join(UNLOCKED_VOTE_KEY_FILENAME);
+    if output.exists() {
+        return Err(format!("Votes are already unlocked to {}.", output.display()).into());
+    }
+
+    let checkpoint_path = datadir_path.join(TIME_LOCK_CHECKPOINT_FILENAME);
+    let resumed: Option<TimeLockCheckpoint> = match checkpoint_path.exists() {
+        true => Some(serde_yaml::from_reader(File::open(&checkpoint_path)?)?),
+        false => None
+    };
+    let squarings = time_lock.puzzle.squarings;
+    let mut progress = Progress::new("Solving time lock", "squarings", squarings as usize);
+    let mut checkpointed = Instant::now();
+    let secret_key = Zeroizing::new(time_lock.puzzle.resume(resumed.as_ref(), |checkpoint| {
+        progress.inc(checkpoint.squarings as usize - progress.done());
+        if checkpointed.elapsed() >= CHECKPOINT_INTERVAL {
+            write_checkpoint(&checkpoint_path, checkpoint)?;
+            checkpointed = Instant::now();
+        }
+        Ok(())
+    })?);
+    progress.finish();
+    time_lock.decryption_key(&secret_key)?;
+
+    write_secret_key_file(&output.to_string_lossy(), &secret_key)?;
+    if checkpoint_path.exists() {
+        fs::remove_file(&checkpoint_path)?;
+    }
+    output::emit(&UnlockedVotes {
+        reveal_at: time_lock.reveal_at,
+        squarings,
+        resumed_from: resumed.map(|checkpoint| checkpoint.squarings),
+        output: output.display().to_string()
+    })
+}
+
+/// Key the encrypted votes of the poll are decrypted with: the key derived
+/// from the poll signing key for encrypted votes, or the key written by
+/// `unlock_votes` for time-locked votes, if unlocked.
+pub fn read_vote_decryption_key(pollconf: &PollConfiguration, datadir_path: &str) -> Result<Option<VoteDecryptionKey>> {
+    let time_lock = match &pollconf.time_lock {
+        Some(time_lock) => time_lock,
+        None => return pollconf.vote_decryption_key()
+    };
+    let path = Path::new(datadir_path).join(UNLOCKED_VOTE_KEY_FILENAME);
+    if !path.exists() {
+        return Ok(None);
+    }
+    let secret_key = Zeroizing::new(base64::decode(std::fs::read_to_string(&path)?.trim())?);
+    Ok(Some(time_lock.decryption_key(&secret_key)?))
+}
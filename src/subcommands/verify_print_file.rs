@@ -0,0 +1,184 @@
+//! # Command: Verify Print File
+//!
+//! `verify_print_file` checks the ballot information file sent to the
+//! printer in step 3 against the Merkle tree committed in step 4. The
+//! serial and vote code of every choice of a ballot, and the choice, are
+//! encrypted again with the keys of their rows in every plane; each cell
+//! must be a leaf of the committed tree, whose root must be that of the
+//! commit receipt. A print file altered after the commitment, or missing
+//! ballots, is reported.
+
+use std::collections::HashSet;
+use std::fmt;
+use super::*;
+use crate::blockchain::merkle::{leaf_hash, CryptoSHA3256Hash, MappedTree};
+
+/// Row of the print file that does not match the committed planes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PrintFileMismatch {
+    /// Line of the print file, counting the header.
+    pub line: usize,
+    pub serial: String,
+    pub reason: String
+}
+
+/// Ballots of a print file checked against the committed planes.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct PrintFileCheck {
+    pub ballots: usize,
+    pub mismatches: Vec<PrintFileMismatch>,
+    /// Ballots of the poll not in the print file.
+    pub missing_ballots: usize
+}
+
+impl PrintFileCheck {
+    pub fn valid(&self) -> bool {
+        self.mismatches.is_empty() && self.missing_ballots == 0
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct PrintFileVerification {
+    pub ballots_file: String,
+    pub root: String,
+    /// Whether the Merkle tree has the root of the commit receipt.
+    pub matches_receipt: bool,
+    #[serde(flatten)]
+    pub check: PrintFileCheck
+}
+
+impl PrintFileVerification {
+    pub fn valid(&self) -> bool {
+        self.matches_receipt && self.check.valid()
+    }
+}
+
+impl fmt::Display for PrintFileVerification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn yes_no(value: bool) -> &'static str { if value { "yes" } else { "NO" } }
+        writeln!(f, "Print file:      {}", self.ballots_file)?;
+        writeln!(f, "Merkle root:     {}", self.root)?;
+        writeln!(f, "Matches receipt: {}", yes_no(self.matches_receipt))?;
+        writeln!(f, "Ballots checked: {}", self.check.ballots)?;
+        writeln!(f, "Missing ballots: {}", self.check.missing_ballots)?;
+        for mismatch in self.check.mismatches.iter() {
+            writeln!(f, "  line {} (ballot {}): {}", mismatch.line, mismatch.serial, mismatch.reason)?;
+        }
+        write!(f, "Matches commit:  {}", yes_no(self.check.valid()))
+    }
+}
+
+/// Check the rows of a print file against the leaves of the committed
+/// tree, with the planes derived from `poll_secrets`.
+pub fn check_print_file(rows: &[CompleteBallotRow], poll_secrets: &PollSecrets, num_ballots: usize, num_decoys: usize, leaves: &HashSet<CryptoSHA3256Hash>) -> PrintFileCheck {
+    let num_rows = 2 * num_ballots;
+    let plane_secrets: Vec<DerivedPlaneSecrets> = poll_secrets.plane_secrets.iter()
+        .map(|secrets| secrets.resolve(num_rows))
+        .collect();
+    let decoys: HashSet<BallotSerial> = generate_decoy_serials(&poll_secrets.decoy_root, num_decoys, num_ballots)
+        .into_iter()
+        .collect();
+    let committed = |data: Option<AEADString>| data.is_some_and(|data| leaves.contains(&leaf_hash(&data.0)));
+
+    let mut check = PrintFileCheck::default();
+    let mut serials: HashSet<BallotSerial> = HashSet::new();
+    for (n, row) in rows.iter().enumerate() {
+        let mut mismatch = |reason: String| check.mismatches.push(PrintFileMismatch {
+            line: n + 2,
+            serial: row.serial.clone(),
+            reason
+        });
+        let serial = match row.serial.parse::<BallotSerial>() {
+            Ok(serial) if serial < num_ballots && string_from_ballotserial(&serial, num_ballots) == row.serial => serial,
+            _ => {
+                mismatch("Not a ballot serial of the poll".to_owned());
+                continue;
+            }
+        };
+        if !serials.insert(serial) {
+            mismatch("Ballot listed twice".to_owned());
+            continue;
+        }
+        let choices = [(&row.choice1_votecode, &row.choice1_value), (&row.choice2_votecode, &row.choice2_value)];
+        for (votecode, value) in choices.iter() {
+            let (offset, choice) = match CHOICE_VALUES.iter().position(|choice| string_from_choicevalue(choice) == **value) {
+                Some(offset) => (offset, CHOICE_VALUES[offset]),
+                None => {
+                    mismatch(format!("Unknown choice {}", value));
+                    continue;
+                }
+            };
+            let tagged_choice = match decoys.contains(&serial) {
+                true => TaggedChoiceValue::Decoy,
+                false => TaggedChoiceValue::from(choice)
+            };
+            let col1 = Column1Entry::Entry(format!("{}: {}", row.serial, votecode));
+            let col3 = Column3Entry::Entry(string_from_taggedchoicevalue_padded(&tagged_choice));
+            let row_number = 2 * serial + offset;
+            let votecode_committed = plane_secrets.iter().all(|secrets| committed(
+                match col1.encrypt(&secrets.col1_keys[row_number], &secrets.col1_nonce[row_number]) {
+                    Some(Column1Entry::Encrypted(data)) => Some(data),
+                    _ => None
+                }));
+            let choice_committed = plane_secrets.iter().all(|secrets| committed(
+                match col3.encrypt(&secrets.col3_keys[row_number], &secrets.col3_nonce[row_number]) {
+                    Some(Column3Entry::Encrypted(data)) => Some(data),
+                    _ => None
+                }));
+            if !votecode_committed {
+                mismatch(format!("Vote code {} for {} is not committed", votecode, value));
+            } else if !choice_committed {
+                mismatch(format!("Choice {} is not committed", value));
+            }
+        }
+        check.ballots += 1;
+    }
+    check.missing_ballots = num_ballots - serials.len();
+    check
+}
+
+pub fn verify_print_file(pollconf_filename: &str, ballots_filename: &str, merkle_tree: &str) -> Result<()> {
+    // Read poll configuration file.
+    let secured_poll_configuration = read_poll_configuration_file(pollconf_filename)?;
+
+    // Reconstruct the Poll Master Key from the trustee passwords.
+    let (poll_master_key, aead_pmk) = read_poll_master_key(&secured_poll_configuration);
+
+    // Ensure the data directory exists.
+    let datadir_path = ensure_poll_data_directory_exists(&secured_poll_configuration, &aead_pmk)?;
+
+    // Decrypt poll configuration state.
+    let pollconf_aead_values = secured_poll_configuration.encrypted_poll_configuration.values()?;
+    let serialized_pollconf = aead_decrypt(&aead_pmk, &pollconf_aead_values)?;
+    let pollconf: PollConfiguration = serde_yaml::from_slice(&serialized_pollconf).unwrap();
+
+    // Check the signed artifacts loaded with the poll verification key.
+    use_poll_verification_key(&secured_poll_configuration, &aead_pmk)?;
+
+    let commit_receipt = read_commit_receipt(&datadir_path)?
+        .ok_or("Poll has not been committed to the blockchain.")?;
+    check_artifact(Path::new(merkle_tree))?;
+    let tree = MappedTree::open(merkle_tree)?;
+    let root = hex::encode(tree.root()?);
+    let leaves: HashSet<CryptoSHA3256Hash> = (0..tree.leafs())
+        .map(|n| tree.hash_at(n))
+        .collect::<Result<HashSet<CryptoSHA3256Hash>>>()?;
+
+    // Derive the poll secrets.
+    let poll_secrets: PollSecrets = PollSecrets::for_poll(&poll_master_key, pollconf.generation_seed.as_deref())?;
+
+    let mut csvreader = csv::Reader::from_path(ballots_filename)?;
+    let rows = csvreader.deserialize::<CompleteBallotRow>()
+        .collect::<std::result::Result<Vec<CompleteBallotRow>, csv::Error>>()?;
+    let verification = PrintFileVerification {
+        ballots_file: ballots_filename.to_owned(),
+        matches_receipt: root == commit_receipt.root,
+        root,
+        check: check_print_file(&rows, &poll_secrets, pollconf.num_ballots, pollconf.num_decoys, &leaves)
+    };
+    output::emit(&verification)?;
+    match verification.valid() {
+        true => Ok(()),
+        false => Err("Print file does not match the commitment.".into())
+    }
+}
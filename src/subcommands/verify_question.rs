@@ -0,0 +1,65 @@
+//! # Command: Verify Question
+//!
+//! `verify_question` checks a published ballot question against a commit
+//! receipt and the commitment transaction it names, so that the question
+//! a poll was about cannot be revised after it was committed. Only the
+//! public question and receipt are needed, not the trustee passwords.
+
+use std::fmt;
+use super::*;
+use crate::blockchain::CommitReceipt;
+use crate::blockchain::blocking::check_commitment;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct QuestionVerification {
+    pub poll_identifier: String,
+    pub question_hash: String,
+    /// Whether the question has the hash of the receipt.
+    pub matches_receipt: bool,
+    /// Block of the commitment transaction, if it was found on chain.
+    pub block_number: Option<u64>,
+    /// Whether the commitment transaction was sent by the address of the
+    /// receipt and carries its root and question hash.
+    pub matches_chain: bool
+}
+
+impl QuestionVerification {
+    pub fn valid(&self) -> bool {
+        self.matches_receipt && self.matches_chain
+    }
+}
+
+impl fmt::Display for QuestionVerification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn yes_no(value: bool) -> &'static str { if value { "yes" } else { "NO" } }
+        writeln!(f, "Poll:            {}", self.poll_identifier)?;
+        writeln!(f, "Question hash:   {}", self.question_hash)?;
+        writeln!(f, "Matches receipt: {}", yes_no(self.matches_receipt))?;
+        match self.block_number {
+            Some(block) => writeln!(f, "Posted on chain: yes (block {})", block)?,
+            None => writeln!(f, "Posted on chain: NO")?
+        }
+        write!(f, "Matches chain:   {}", yes_no(self.matches_chain))
+    }
+}
+
+pub fn verify_question(network: &str, question_filename: &str, receipt_filename: &str) -> Result<()> {
+    let receipt: CommitReceipt = serde_yaml::from_reader(File::open(receipt_filename)?)?;
+    let question: BallotQuestion = serde_json::from_reader(File::open(question_filename)?)?;
+    let hash = question.hash();
+
+    let commitment = check_commitment(network, &receipt)?;
+    let verification = QuestionVerification {
+        poll_identifier: question.poll_identifier.clone(),
+        matches_receipt: receipt.question_hash.as_deref() == Some(hex::encode(hash).as_str())
+            && question.poll_identifier == receipt.poll_identifier,
+        block_number: commitment.block_number,
+        matches_chain: commitment.valid(),
+        question_hash: hex::encode(hash)
+    };
+    output::emit(&verification)?;
+    match verification.valid() {
+        true => Ok(()),
+        false => Err("Ballot question does not match its commitment.".into())
+    }
+}
@@ -0,0 +1,133 @@
+//! # Command: Verify Receipt
+//!
+//! `verify_receipt` confirms, against the votes submitted to the blockchain,
+//! whether a vote was recorded for a ballot. The ballot is identified either
+//! by its serial or by the receipt code (vote code or receipt QR payload)
+//! kept by the voter. The choice made on the ballot is never reported.
+
+use std::fmt;
+use super::*;
+use chrono::{DateTime, Utc};
+use crate::blockchain::blocking::transaction_statuses;
+use crate::votes::{SubmittedVote, TallyPolicy, VotePayload, fetch_submitted_votes};
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ReceiptVerification {
+    pub poll_identifier: String,
+    pub ballot: String,
+    pub audited: bool,
+    pub recorded: bool,
+    /// Number of votes submitted for the ballot; more than one means the
+    /// ballot was voted more than once.
+    pub votes_found: usize,
+    pub transaction_hash: Option<String>,
+    pub block_number: Option<u64>,
+    pub timestamp: Option<DateTime<Utc>>
+}
+
+impl fmt::Display for ReceiptVerification {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Poll:     {}", self.poll_identifier)?;
+        writeln!(f, "Ballot:   {}", self.ballot)?;
+        if self.audited {
+            writeln!(f, "Warning:  this ballot was audited (spoiled) and cannot be voted")?;
+        }
+        match (&self.transaction_hash, self.block_number, self.timestamp) {
+            (Some(transaction_hash), Some(block_number), Some(timestamp)) => {
+                writeln!(f, "Recorded: yes")?;
+                writeln!(f, "Block:    {} ({})", block_number, timestamp.to_rfc3339())?;
+                write!(f, "Tx:       {}", transaction_hash)?;
+                if 1 < self.votes_found {
+                    write!(f, "\nWarning:  {} votes were submitted for this ballot", self.votes_found)?;
+                }
+                Ok(())
+            },
+            _ => write!(f, "Recorded: no")
+        }
+    }
+}
+
+pub fn verify_receipt(network: &str, pollconf_filename: &str, serial: Option<&str>, receipt: Option<&str>) -> Result<()> {
+    // Read poll configuration file.
+    let secured_poll_configuration = read_poll_configuration_file(pollconf_filename)?;
+
+    // Reconstruct the Poll Master Key from the trustee passwords.
+    let (poll_master_key, aead_pmk) = read_poll_master_key(&secured_poll_configuration);
+
+    // Ensure the data directory exists.
+    let datadir_path = ensure_poll_data_directory_exists(&secured_poll_configuration, &aead_pmk)?;
+
+    // Decrypt poll configuration state.
+    let pollconf_aead_values = secured_poll_configuration.encrypted_poll_configuration.values()?;
+    let serialized_pollconf = aead_decrypt(&aead_pmk, &pollconf_aead_values)?;
+    let pollconf: PollConfiguration = serde_yaml::from_slice(&serialized_pollconf).unwrap();
+
+    // Votes are only accepted after the commitment was posted.
+    let commit_receipt = match read_commit_receipt(&datadir_path)? {
+        Some(commit_receipt) => commit_receipt,
+        None => return Err("Poll has not been committed to the blockchain.".into())
+    };
+
+    // Find the vote codes of the ballot.
+    let (ballot, votecodes, audited_serial): (String, Vec<VoteCode>, Option<BallotSerial>) = match (serial, receipt) {
+        (Some(serial), _) => {
+            let serial: BallotSerial = serial.trim().parse()?;
+            if pollconf.num_ballots <= serial {
+                return Err(format!("No ballot with serial {}.", serial).into());
+            }
+            let poll_secrets: PollSecrets = PollSecrets::for_poll(&poll_master_key, pollconf.generation_seed.as_deref())?;
+            let serials: Vec<BallotSerial> = (0..pollconf.num_ballots).collect();
+            let votecodes: Vec<VoteCode> = generate_votecodes(
+                &poll_secrets.votecode_root,
+                2 * pollconf.num_ballots);
+            let ballot = &generate_ballots(&serials, &votecodes)[serial];
+            (string_from_ballotserial(&serial, pollconf.num_ballots),
+             vec![ballot.choice1.votecode, ballot.choice2.votecode],
+             Some(serial))
+        },
+        (None, Some(receipt)) => {
+            let payload: VotePayload = receipt.parse()?;
+            if let Some(poll_identifier) = &payload.poll_identifier {
+                if *poll_identifier != pollconf.payload_identifier(&datadir_path) {
+                    return Err(format!("Receipt is for poll {}.", poll_identifier).into());
+                }
+            }
+            (string_from_votecode(&payload.votecode), vec![payload.votecode], None)
+        },
+        (None, None) => return Err("Either a ballot serial or a receipt code is required.".into())
+    };
+
+    let audited_ballots: Vec<BallotSerial> = pollconf.audited_ballots.clone().unwrap_or_default().iter()
+        .filter_map(|serial| serial.parse().ok())
+        .collect();
+    let audited = audited_serial
+        .map(|serial| audited_ballots.contains(&serial))
+        .unwrap_or(false);
+
+    // Look the vote codes up in the submitted votes.
+    let votes = fetch_submitted_votes(network, &pollconf.payload_identifier(&datadir_path), commit_receipt.vote_address.as_deref(), commit_receipt.submitted_at_block, pollconf.tally_rules.closing_block(commit_receipt.end_block), read_vote_decryption_key(&pollconf, &datadir_path)?.as_ref())?;
+    let matches: Vec<&SubmittedVote> = votes.iter()
+        .filter(|vote| VoteCode::parse(&vote.votecode).is_some_and(|votecode| votecodes.contains(&votecode)))
+        .collect();
+
+    // Failed transactions do not record a vote.
+    let hashes: Vec<String> = matches.iter().map(|vote| vote.transaction_hash.clone()).collect();
+    let statuses = transaction_statuses(network, &hashes)?;
+    let matches: Vec<&SubmittedVote> = matches.into_iter().zip(statuses.iter())
+        .filter(|(_, status)| status.succeeded != Some(false))
+        .map(|(vote, _)| vote)
+        .collect();
+    let first = matches.first();
+
+    let verification = ReceiptVerification {
+        poll_identifier: datadir_path.clone(),
+        ballot,
+        audited,
+        recorded: first.is_some(),
+        votes_found: matches.len(),
+        transaction_hash: first.map(|vote| vote.transaction_hash.clone()),
+        block_number: first.map(|vote| vote.block_number),
+        timestamp: first.map(|vote| vote.timestamp)
+    };
+    output::emit(&verification)
+}
@@ -0,0 +1,139 @@
+//! # Command: Workspace
+//!
+//! `workspace_init` creates a workspace, `workspace_create` creates a poll
+//! in a directory of its own and `workspace_add` registers a poll directory
+//! already in the workspace. `workspace_list` lists the polls and
+//! `workspace_use` switches the active poll, which `enter_active_poll`
+//! runs the commands against. `archive_poll` archives a finalized poll into
+//! a signed tarball, checked by `verify_archive`.
+
+use super::*;
+use crate::workspace::{self as polls, Workspace};
+
+pub fn workspace_init(workspace_dirname: &str) -> Result<()> {
+    let workspace = polls::init_workspace(Path::new(workspace_dirname))?;
+    output::emit(&workspace.listing(Path::new(workspace_dirname)))
+}
+
+/// Create the poll of the new poll configuration file in a poll directory
+/// of the workspace and make it the active poll.
+pub fn workspace_create(workspace_dirname: &str, pollconf_filename: &str, generation_seed: Option<&str>) -> Result<()> {
+    let workspace_dir = Path::new(workspace_dirname);
+    let mut workspace = Workspace::open(workspace_dir)?;
+
+    let new_poll_configuration: NewPollConfiguration = serde_yaml::from_reader(File::open(pollconf_filename)?)?;
+    let name = polls::poll_directory_name(&new_poll_configuration.poll_identifier);
+    if name.is_empty() {
+        return Err(format!("Poll {} has no name usable as a directory.", new_poll_configuration.poll_identifier).into());
+    }
+    let poll_dir = workspace_dir.join(&name);
+    if poll_dir.exists() {
+        return Err(format!("Poll directory {} already exists.", poll_dir.display()).into());
+    }
+
+    // Create the poll next to a copy of its new poll configuration.
+    let config_filename = Path::new(pollconf_filename).file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| format!("{} is not a poll configuration file.", pollconf_filename))?;
+    DirBuilder::new().create(&poll_dir)?;
+    std::fs::copy(pollconf_filename, poll_dir.join(&config_filename))?;
+    create_new_poll(&poll_dir.join(&config_filename).to_string_lossy(), generation_seed)?;
+
+    let entry = workspace.add(workspace_dir, &name, &(config_filename + ".secure"))?.clone();
+    workspace.save(workspace_dir)?;
+    output::emit(&entry)
+}
+
+/// Register the poll with the secured poll configuration `config` in the
+/// poll directory `poll_dirname` of the workspace.
+pub fn workspace_add(workspace_dirname: &str, poll_dirname: &str, config: &str) -> Result<()> {
+    let workspace_dir = Path::new(workspace_dirname);
+    let mut workspace = Workspace::open(workspace_dir)?;
+    let entry = workspace.add(workspace_dir, poll_dirname, config)?.clone();
+    workspace.save(workspace_dir)?;
+    output::emit(&entry)
+}
+
+pub fn workspace_list(workspace_dirname: &str) -> Result<()> {
+    let workspace = Workspace::open(Path::new(workspace_dirname))?;
+    output::emit(&workspace.listing(Path::new(workspace_dirname)))
+}
+
+pub fn workspace_use(workspace_dirname: &str, poll: &str) -> Result<()> {
+    let workspace_dir = Path::new(workspace_dirname);
+    let mut workspace = Workspace::open(workspace_dir)?;
+    let entry = workspace.activate(poll)?.clone();
+    workspace.save(workspace_dir)?;
+    output::emit(&entry)
+}
+
+/// Change to the directory of the active poll, returning its secured poll
+/// configuration file.
+pub fn enter_active_poll(workspace_dirname: &str) -> Result<String> {
+    let workspace_dir = Path::new(workspace_dirname);
+    let workspace = Workspace::open(workspace_dir)
+        .map_err(|err| format!("No poll configuration given (--config): {}", err))?;
+    let entry = workspace.poll_or_active(None)?;
+    std::env::set_current_dir(entry.directory(workspace_dir))?;
+    Ok(entry.config.clone())
+}
+
+/// Archive the finalized poll `poll`, or the active poll, into
+/// `archive_filename`, by default `<poll directory>.tar.gz` in the workspace.
+pub fn archive_poll(workspace_dirname: &str, poll: Option<&str>, archive_filename: Option<&str>) -> Result<()> {
+    let workspace_dir = Path::new(workspace_dirname);
+    let mut workspace = Workspace::open(workspace_dir)?;
+    let entry = workspace.poll_or_active(poll)?.clone();
+    let poll_dir = entry.directory(workspace_dir);
+
+    // Read poll configuration file.
+    let secured_poll_configuration = read_poll_configuration_file(&poll_dir.join(&entry.config).to_string_lossy())?;
+
+    // Reconstruct the Poll Master Key from the trustee passwords.
+    let (_, aead_pmk) = read_poll_master_key(&secured_poll_configuration);
+    let identifier = {
+        let aead_values = secured_poll_configuration.poll_identifier.values()?;
+        aead_decrypt(&aead_pmk, &aead_values)?;
+        String::from_utf8(aead_values.aad)?
+    };
+    if identifier != entry.identifier {
+        return Err(format!("Poll directory {} holds poll {}, not {}.", entry.name, identifier, entry.identifier).into());
+    }
+    if read_finalization_receipt(&entry.datadir(workspace_dir).to_string_lossy())?.is_none() {
+        return Err(format!("Poll {} must be finalized before it is archived.", entry.identifier).into());
+    }
+
+    // Decrypt poll configuration state.
+    let pollconf_aead_values = secured_poll_configuration.encrypted_poll_configuration.values()?;
+    let serialized_pollconf = aead_decrypt(&aead_pmk, &pollconf_aead_values)?;
+    let pollconf: PollConfiguration = serde_yaml::from_slice(&serialized_pollconf).unwrap();
+
+    // Authenticate the verification key.
+    let public_key = use_poll_verification_key(&secured_poll_configuration, &aead_pmk)?;
+
+    let archive_path = match archive_filename {
+        Some(archive_filename) => PathBuf::from(archive_filename),
+        None => workspace_dir.join(format!("{}.tar.gz", entry.name))
+    };
+    let manifest = polls::archive_poll_directory(
+        &identifier,
+        &poll_dir,
+        &archive_path,
+        &pollconf.signing_key,
+        &public_key)?;
+
+    if let Some(archived) = workspace.polls.iter_mut().find(|archived| archived.name == entry.name) {
+        archived.archive = Some(archive_path.to_string_lossy().into_owned());
+    }
+    workspace.save(workspace_dir)?;
+    output::emit(&manifest)
+}
+
+pub fn verify_archive(archive_filename: &str, public_key: Option<&str>) -> Result<()> {
+    let verification = polls::verify_poll_archive(Path::new(archive_filename), public_key)?;
+    output::emit(&verification)?;
+    match verification.valid() {
+        true => Ok(()),
+        false => Err("Poll archive does not match its manifest.".into())
+    }
+}
@@ -28,7 +28,7 @@ impl CommittedSummands {
         Ok(SecuredFile::new(key, "".to_owned(), String::from_utf8(csvec)?))
     }
 
-    pub fn from_csprng(seed: CSPRNGSeed, count: usize, modulus: usize) -> Self {
+    pub fn from_csprng(seed: &CSPRNGSeed, count: usize, modulus: usize) -> Self {
         committed_summands_from_csprng_fdr(seed, count, modulus)
     }
 
@@ -49,7 +49,7 @@ impl From<CommittedSummands> for Summands {
 
 
 /// Generate a list of summands using a CSPRNG feeding the Fast Dice Roller.
-fn committed_summands_from_csprng_fdr(seed: CSPRNGSeed, count: usize, modulus: usize) -> CommittedSummands {
+fn committed_summands_from_csprng_fdr(seed: &CSPRNGSeed, count: usize, modulus: usize) -> CommittedSummands {
     /*
     fn try_generate(seed: CSPRNGSeed, count: usize, modulus: usize, num_bytes: usize) -> Option<Vec<usize>> {
         let mut prng = CSPRNG::from_csprng_seed(seed);
@@ -12,7 +12,7 @@ pub struct DrawnSummands {
 }
 
 impl DrawnSummands {
-    pub fn from_csprng(seed: CSPRNGSeed, count: usize, modulus: usize) -> Self {
+    pub fn from_csprng(seed: &CSPRNGSeed, count: usize, modulus: usize) -> Self {
         drawn_summands_from_csprng_fdr(seed, count, modulus)
     }
 
@@ -32,7 +32,7 @@ impl From<DrawnSummands> for Summands {
 }
 
 /// Generate a list of summands using a CSPRNG feeding the Fast Dice Roller.
-fn drawn_summands_from_csprng_fdr(seed: CSPRNGSeed, count: usize, modulus: usize) -> DrawnSummands {
+fn drawn_summands_from_csprng_fdr(seed: &CSPRNGSeed, count: usize, modulus: usize) -> DrawnSummands {
     let mut prng = CSPRNG::from_csprng_seed(seed);
     DrawnSummands {
         records: (0..count).into_iter()
@@ -0,0 +1,226 @@
+//! Mailing Labels
+//!
+//! `VoterRoster::mailing_labels` turns the voters ballots are mailed to
+//! into address labels for the mailing vendor, with the address lines in
+//! the order of the postal service of the country of the voter. The
+//! country of a voter is the `country` field of the roster schema, as an
+//! ISO 3166 code, or the country mailed from. Mail sent abroad carries the
+//! name of the country on its last line.
+//!
+//! Address lines are normalized for machine sorting: uppercase, without
+//! periods or commas, and whitespace collapsed. Postal codes are written in
+//! the format of their country; a label whose postal code does not fit it
+//! is still written, and reported for review.
+
+use std::fmt;
+use std::str::FromStr;
+use serde::{Serialize, Deserialize};
+use super::*;
+
+/// Countries whose addresses are formatted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum AddressCountry { Us, Ca, Gb, De, Fr, Au }
+
+pub const ADDRESS_COUNTRIES: [&str; 6] = ["US", "CA", "GB", "DE", "FR", "AU"];
+
+impl AddressCountry {
+    pub const ALL: [AddressCountry; 6] = [
+        AddressCountry::Us,
+        AddressCountry::Ca,
+        AddressCountry::Gb,
+        AddressCountry::De,
+        AddressCountry::Fr,
+        AddressCountry::Au
+    ];
+
+    pub fn code(&self) -> &'static str {
+        ADDRESS_COUNTRIES[Self::ALL.iter().position(|country| country == self).unwrap()]
+    }
+
+    /// Name of the country on the last line of mail sent abroad.
+    pub fn name(&self) -> &'static str {
+        match self {
+            AddressCountry::Us => "UNITED STATES OF AMERICA",
+            AddressCountry::Ca => "CANADA",
+            AddressCountry::Gb => "UNITED KINGDOM",
+            AddressCountry::De => "GERMANY",
+            AddressCountry::Fr => "FRANCE",
+            AddressCountry::Au => "AUSTRALIA"
+        }
+    }
+
+    /// Postal code in the format of the country, or `None` if it does not
+    /// fit it.
+    pub fn postal_code(&self, value: &str) -> Option<String> {
+        let compact: String = value.chars()
+            .filter(|c| !c.is_whitespace() && *c != '-')
+            .collect::<String>()
+            .to_uppercase();
+        let digits = |n: usize| compact.len() == n && compact.chars().all(|c| c.is_ascii_digit());
+        match self {
+            AddressCountry::Us if digits(5) => Some(compact),
+            AddressCountry::Us if digits(9) => Some(format!("{}-{}", &compact[..5], &compact[5..])),
+            AddressCountry::Ca => {
+                let valid = compact.len() == 6 && compact.chars().enumerate()
+                    .all(|(n, c)| if n % 2 == 0 { c.is_ascii_alphabetic() } else { c.is_ascii_digit() });
+                match valid {
+                    true => Some(format!("{} {}", &compact[..3], &compact[3..])),
+                    false => None
+                }
+            },
+            AddressCountry::Gb => {
+                let valid = (5..=7).contains(&compact.len())
+                    && compact.chars().all(|c| c.is_ascii_alphanumeric())
+                    && compact.starts_with(|c: char| c.is_ascii_alphabetic())
+                    && compact[compact.len() - 3..].starts_with(|c: char| c.is_ascii_digit());
+                match valid {
+                    true => Some(format!("{} {}", &compact[..compact.len() - 3], &compact[compact.len() - 3..])),
+                    false => None
+                }
+            },
+            AddressCountry::De | AddressCountry::Fr if digits(5) => Some(compact),
+            AddressCountry::Au if digits(4) => Some(compact),
+            _ => None
+        }
+    }
+
+    /// Address lines below the name, in the order of the postal service of
+    /// the country.
+    fn address_lines(&self, street: String, city: String, state: String, postal_code: String) -> Vec<String> {
+        let join = |parts: &[&str]| parts.iter().filter(|part| !part.is_empty()).cloned().collect::<Vec<&str>>().join(" ");
+        match self {
+            AddressCountry::Us | AddressCountry::Ca | AddressCountry::Au =>
+                vec![street, join(&[&city, &state, &postal_code])],
+            AddressCountry::Gb => vec![street, city, postal_code],
+            AddressCountry::De | AddressCountry::Fr => vec![street, join(&[&postal_code, &city])]
+        }
+    }
+}
+
+impl fmt::Display for AddressCountry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.pad(self.code())
+    }
+}
+
+impl FromStr for AddressCountry {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        let code = value.trim().to_uppercase();
+        match code.as_str() {
+            "UK" => Ok(AddressCountry::Gb),
+            code => ADDRESS_COUNTRIES.iter().position(|country| *country == code)
+                .map(|n| Self::ALL[n])
+                .ok_or_else(|| format!("No address format for country {}, expected one of: {}", value, ADDRESS_COUNTRIES.join(", ")))
+        }
+    }
+}
+
+/// Address line normalized for machine sorting.
+pub fn normalize_address_line(line: &str) -> String {
+    line.chars()
+        .filter(|c| *c != '.' && *c != ',')
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<&str>>()
+        .join(" ")
+        .to_uppercase()
+}
+
+/// Address label of a voter.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MailingLabel {
+    /// Position of the voter in the roster.
+    pub position: usize,
+    pub country: AddressCountry,
+    /// Name of the voter, then the address lines.
+    pub lines: Vec<String>,
+    /// Why the address needs review before it is mailed.
+    pub problem: Option<String>
+}
+
+impl MailingLabel {
+    /// Label of `voter`, mailed from `origin`.
+    pub fn new(position: usize, voter: &VoterInfo, origin: AddressCountry) -> Self {
+        let (country, mut problem) = match voter.extra_fields.get("country") {
+            Some(code) => match code.parse::<AddressCountry>() {
+                Ok(country) => (country, None),
+                Err(err) => (origin, Some(err))
+            },
+            None => (origin, None)
+        };
+        let postal_code = match country.postal_code(&voter.zip_code) {
+            Some(postal_code) => postal_code,
+            None => {
+                problem.get_or_insert_with(|| format!("{} is not a postal code of {}", voter.zip_code.trim(), country));
+                normalize_address_line(&voter.zip_code)
+            }
+        };
+        let name = normalize_address_line(&format!("{} {}", voter.first_name, voter.last_name));
+        let mut lines = vec![name];
+        lines.extend(country.address_lines(
+            normalize_address_line(&voter.street_address),
+            normalize_address_line(&voter.city),
+            normalize_address_line(&voter.state),
+            postal_code));
+        if country != origin {
+            lines.push(country.name().to_owned());
+        }
+        lines.retain(|line| !line.is_empty());
+        if problem.is_none() && lines.len() < 3 {
+            problem = Some("The address is incomplete".to_owned());
+        }
+        MailingLabel { position, country, lines, problem }
+    }
+}
+
+impl fmt::Display for MailingLabel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.lines.join("\n"))
+    }
+}
+
+/// Mailing label in the CSV format of print houses, in mailing order: the
+/// five lines hold the name, the three address lines of the United Kingdom
+/// and the country.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MailingLabelRow {
+    pub sequence: usize,
+    pub line_1: String,
+    pub line_2: String,
+    pub line_3: String,
+    pub line_4: String,
+    pub line_5: String,
+    pub country_code: String
+}
+
+impl MailingLabelRow {
+    /// Row of the label mailed `sequence`th, counted from 1.
+    pub fn new(sequence: usize, label: &MailingLabel) -> Self {
+        let line = |n: usize| label.lines.get(n).cloned().unwrap_or_default();
+        MailingLabelRow {
+            sequence,
+            line_1: line(0),
+            line_2: line(1),
+            line_3: line(2),
+            line_4: line(3),
+            line_5: line(4),
+            country_code: label.country.code().to_owned()
+        }
+    }
+}
+
+impl VoterRoster {
+    /// Mailing labels of the voters at `positions`, in that order, mailed
+    /// from `origin`.
+    pub fn mailing_labels(&self, positions: &[usize], origin: AddressCountry) -> Result<Vec<MailingLabel>> {
+        positions.iter()
+            .map(|&position| match self.records.get(position) {
+                Some(record) => Ok(MailingLabel::new(record.position, &record.voter_info, origin)),
+                None => Err(format!("No voter at position {} of the roster", position).into())
+            })
+            .collect()
+    }
+}
@@ -14,3 +14,12 @@ pub use voter_roster_file::*;
 
 pub mod restricted_file;
 pub use restricted_file::*;
+
+pub mod privacy;
+pub use privacy::*;
+
+pub mod schema;
+pub use schema::*;
+
+pub mod mailing;
+pub use mailing::*;
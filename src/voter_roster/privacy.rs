@@ -0,0 +1,225 @@
+//! Roster Privacy
+//!
+//! A `RosterPolicy` chooses which fields of the voter information are
+//! published in the committed roster. The other fields are only published
+//! as a hash salted for every voter, so that the entry of one voter can be
+//! opened without revealing the others. The salt of a voter is derived
+//! from the secret salt of the policy and the position of the voter.
+//!
+//! Published fields together can identify a voter, such as a city and a
+//! rare last name. `check_anonymity` groups the voters by the values of the
+//! published fields and finds the groups of fewer than `min_anonymity`
+//! voters. It reports how many groups and voters are at risk, never which.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::str::FromStr;
+use crypto::digest::Digest;
+use crypto::sha3::{Sha3, Sha3Mode};
+use rand::RngCore;
+use rand::rngs::OsRng;
+use serde::{Serialize, Deserialize};
+use crate::cryptography::Base64String;
+use super::*;
+
+/// Smallest number of voters sharing their published fields by default.
+pub const DEFAULT_MIN_ANONYMITY: usize = 5;
+
+pub const ROSTER_FIELDS: [&str; 6] = ["last_name", "first_name", "street_address", "city", "state", "zip_code"];
+
+/// Field of the voter information.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RosterField {
+    LastName,
+    FirstName,
+    StreetAddress,
+    City,
+    State,
+    ZipCode
+}
+
+impl RosterField {
+    pub const ALL: [RosterField; 6] = [
+        RosterField::LastName,
+        RosterField::FirstName,
+        RosterField::StreetAddress,
+        RosterField::City,
+        RosterField::State,
+        RosterField::ZipCode
+    ];
+
+    pub fn name(&self) -> &'static str {
+        ROSTER_FIELDS[Self::ALL.iter().position(|field| field == self).unwrap()]
+    }
+
+    pub fn value<'a>(&self, info: &'a VoterInfo) -> &'a str {
+        match self {
+            Self::LastName => &info.last_name,
+            Self::FirstName => &info.first_name,
+            Self::StreetAddress => &info.street_address,
+            Self::City => &info.city,
+            Self::State => &info.state,
+            Self::ZipCode => &info.zip_code
+        }
+    }
+}
+
+impl fmt::Display for RosterField {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for RosterField {
+    type Err = String;
+
+    fn from_str(value: &str) -> std::result::Result<Self, Self::Err> {
+        ROSTER_FIELDS.iter().position(|name| *name == value)
+            .map(|n| Self::ALL[n])
+            .ok_or_else(|| format!("{} is not a roster field, expected one of: {}", value, ROSTER_FIELDS.join(", ")))
+    }
+}
+
+/// Fields of the voter information published in the committed roster.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RosterPolicy {
+    pub published_fields: Vec<RosterField>,
+    /// Smallest number of voters that may share their published fields.
+    pub min_anonymity: usize,
+    /// Secret the salts of the voters are derived from.
+    pub salt: Base64String
+}
+
+impl RosterPolicy {
+    /// Policy with a new random salt.
+    pub fn new(published_fields: &[RosterField], min_anonymity: usize) -> Self {
+        let mut salt = [0u8; 32];
+        OsRng.fill_bytes(&mut salt);
+        RosterPolicy {
+            published_fields: RosterField::ALL.iter()
+                .filter(|field| published_fields.contains(field))
+                .cloned()
+                .collect(),
+            min_anonymity,
+            salt: Base64String(base64::encode(&salt))
+        }
+    }
+
+    /// Fields only published as a salted hash.
+    pub fn private_fields(&self) -> Vec<RosterField> {
+        RosterField::ALL.iter()
+            .filter(|field| !self.published_fields.contains(field))
+            .cloned()
+            .collect()
+    }
+
+    /// Salt of the voter at `position`: SHA3-256(salt || position).
+    pub fn voter_salt(&self, position: usize) -> Result<[u8; 32]> {
+        let salt = base64::decode(&self.salt.0)?;
+        let mut hasher = Sha3::new(Sha3Mode::Sha3_256);
+        hasher.input(&salt);
+        hasher.input(&(position as u64).to_be_bytes());
+        let mut voter_salt = [0u8; 32];
+        hasher.result(&mut voter_salt);
+        Ok(voter_salt)
+    }
+
+    /// Check the anonymity of the voters of `roster` under this policy.
+    pub fn check(&self, roster: &VoterRoster) -> AnonymityCheck {
+        check_anonymity(roster, &self.published_fields, self.min_anonymity)
+    }
+
+    /// Committed entry of a voter: the published fields, one per line,
+    /// then the hexadecimal SHA3-256 hash of the voter salt and the private
+    /// fields, one per line, followed by the fields of the roster schema
+    /// beyond the standard ones as `name: value` lines.
+    pub fn restricted(&self, record: &VoterRosterRecord) -> Result<RestrictedVoterInfo> {
+        let mut hasher = Sha3::new(Sha3Mode::Sha3_256);
+        hasher.input(&self.voter_salt(record.position)?);
+        for field in self.private_fields() {
+            hasher.input(b"\n");
+            hasher.input(field.value(&record.voter_info).as_bytes());
+        }
+        for line in record.voter_info.extra_field_lines() {
+            hasher.input(b"\n");
+            hasher.input(line.as_bytes());
+        }
+        let mut hash = [0u8; 32];
+        hasher.result(&mut hash);
+        let mut lines: Vec<&str> = self.published_fields.iter()
+            .map(|field| field.value(&record.voter_info))
+            .collect();
+        let hash = hex::encode(hash);
+        lines.push(&hash);
+        Ok(lines.join("\n"))
+    }
+}
+
+/// Anonymity of the voters given the fields published of them.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct AnonymityCheck {
+    pub published_fields: Vec<RosterField>,
+    pub min_anonymity: usize,
+    pub voters: usize,
+    /// Size of the smallest group of voters sharing their published fields.
+    pub anonymity: usize,
+    /// Groups of fewer than `min_anonymity` voters.
+    pub groups_at_risk: usize,
+    /// Voters in the groups at risk.
+    pub voters_at_risk: usize
+}
+
+impl AnonymityCheck {
+    pub fn valid(&self) -> bool {
+        self.voters_at_risk == 0
+    }
+}
+
+impl fmt::Display for AnonymityCheck {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let fields = self.published_fields.iter().map(RosterField::name).collect::<Vec<&str>>();
+        writeln!(f, "Published fields:   {}", if fields.is_empty() { "none".to_owned() } else { fields.join(", ") })?;
+        writeln!(f, "Voters:             {}", self.voters)?;
+        writeln!(f, "Anonymity:          {} (required {})", self.anonymity, self.min_anonymity)?;
+        writeln!(f, "Groups at risk:     {}", self.groups_at_risk)?;
+        writeln!(f, "Voters at risk:     {}", self.voters_at_risk)?;
+        write!(f, "Roster anonymous:   {}", if self.valid() { "yes" } else { "NO" })
+    }
+}
+
+/// Group the voters of `roster` by the values of `fields`, ignoring case
+/// and surrounding whitespace, and check that every group has at least
+/// `min_anonymity` voters.
+pub fn check_anonymity(roster: &VoterRoster, fields: &[RosterField], min_anonymity: usize) -> AnonymityCheck {
+    let mut groups: HashMap<Vec<String>, usize> = HashMap::new();
+    for record in roster.records.iter() {
+        let key = fields.iter()
+            .map(|field| field.value(&record.voter_info).trim().to_lowercase())
+            .collect::<Vec<String>>();
+        *groups.entry(key).or_insert(0) += 1;
+    }
+    let at_risk = groups.values().filter(|size| **size < min_anonymity);
+    AnonymityCheck {
+        published_fields: fields.to_vec(),
+        min_anonymity,
+        voters: roster.len(),
+        anonymity: groups.values().cloned().min().unwrap_or(0),
+        groups_at_risk: at_risk.clone().count(),
+        voters_at_risk: at_risk.sum()
+    }
+}
+
+impl VoterRoster {
+    /// Roster committed under `policy`.
+    pub fn restricted_with(&self, policy: &RosterPolicy) -> Result<RestrictedVoterRoster> {
+        Ok(RestrictedVoterRoster {
+            records: self.records.iter()
+                .map(|record| Ok(RestrictedVoterRosterRecord {
+                    position: record.position,
+                    voter_info: policy.restricted(record)?
+                }))
+                .collect::<Result<Vec<RestrictedVoterRosterRecord>>>()?
+        })
+    }
+}
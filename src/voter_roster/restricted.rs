@@ -55,11 +55,15 @@ impl From<VoterRosterRecord> for RestrictedVoterRosterRecord {
 impl From<VoterInfo> for RestrictedVoterInfo {
     fn from(info: VoterInfo) -> Self {
         let public = format!("{}, {}", info.last_name, info.first_name);
-        let private = format!("{}\n{} {}, {}",
+        let mut private = format!("{}\n{} {}, {}",
                 info.street_address,
                 info.city,
                 info.state,
                 info.zip_code);
+        for line in info.extra_field_lines() {
+            private.push('\n');
+            private.push_str(&line);
+        }
         format!("{}\n{}", public, private)
     }
 }
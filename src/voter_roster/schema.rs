@@ -0,0 +1,191 @@
+//! Roster Schema
+//!
+//! Different elections collect different voter fields. A `RosterSchema`,
+//! set with `roster_schema` in the new poll configuration, lists the fields
+//! of the roster of the poll, their type and whether they are required.
+//! The standard fields (`ROSTER_FIELDS`) are kept in the voter information
+//! as before, empty when the schema leaves them out; the other fields are
+//! kept by name in its `extra_fields`.
+//!
+//! A roster is validated against the schema as it is imported: every
+//! column must be a field of the schema, every required field a column,
+//! and every value must have the type of its field. Values are stored in
+//! canonical form, so that the same voter always hashes the same:
+//!
+//! * `text`: surrounding whitespace trimmed, other whitespace collapsed to
+//!   single spaces, so that no value spans several lines.
+//! * `integer`: decimal, without sign for positive values or leading zeros.
+//! * `date`: `YYYY-MM-DD`.
+//! * `email`: trimmed and lowercase.
+//!
+//! Empty optional values are left out of `extra_fields`. Without a schema,
+//! the roster has the standard fields only, read as given.
+
+use std::collections::{BTreeMap, HashSet};
+use std::fmt;
+use chrono::NaiveDate;
+use serde::{Serialize, Deserialize};
+use super::*;
+
+/// Type of the values of a roster field.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RosterFieldType {
+    #[default]
+    Text,
+    Integer,
+    Date,
+    Email
+}
+
+impl fmt::Display for RosterFieldType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            RosterFieldType::Text => write!(f, "text"),
+            RosterFieldType::Integer => write!(f, "integer"),
+            RosterFieldType::Date => write!(f, "date"),
+            RosterFieldType::Email => write!(f, "email")
+        }
+    }
+}
+
+impl RosterFieldType {
+    /// Canonical form of `value`, which is not empty once trimmed.
+    pub fn canonical(&self, value: &str) -> std::result::Result<String, String> {
+        let value = value.trim();
+        match self {
+            RosterFieldType::Text => Ok(value.split_whitespace().collect::<Vec<&str>>().join(" ")),
+            RosterFieldType::Integer => value.parse::<i64>()
+                .map(|n| n.to_string())
+                .map_err(|_| format!("{} is not an integer", value)),
+            RosterFieldType::Date => NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                .map(|date| date.format("%Y-%m-%d").to_string())
+                .map_err(|_| format!("{} is not a date in YYYY-MM-DD format", value)),
+            RosterFieldType::Email => match value.split_once('@') {
+                Some((local, domain)) if !local.is_empty() && domain.contains('.') && !domain.contains('@')
+                    && !value.contains(char::is_whitespace) => Ok(value.to_lowercase()),
+                _ => Err(format!("{} is not an email address", value))
+            }
+        }
+    }
+}
+
+/// Field of the roster schema.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RosterFieldSpec {
+    pub name: String,
+    #[serde(default, rename = "type")]
+    pub field_type: RosterFieldType,
+    /// Every voter has a value for the field.
+    #[serde(default)]
+    pub required: bool
+}
+
+/// Fields of the voter roster of a poll.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RosterSchema {
+    pub fields: Vec<RosterFieldSpec>
+}
+
+impl RosterSchema {
+    /// Check that the schema has fields, all with distinct names of
+    /// lowercase letters, digits and underscores.
+    pub fn validate(&self) -> Result<()> {
+        if self.fields.is_empty() {
+            return Err("The roster schema has no fields.".into());
+        }
+        let mut names = HashSet::new();
+        for field in self.fields.iter() {
+            let valid = field.name.starts_with(|c: char| c.is_ascii_lowercase())
+                && field.name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+            if !valid {
+                return Err(format!("Roster field {:?} must be lowercase letters, digits and underscores.", field.name).into());
+            }
+            if !names.insert(field.name.as_str()) {
+                return Err(format!("Roster field {} is listed twice.", field.name).into());
+            }
+        }
+        Ok(())
+    }
+
+    pub fn field(&self, name: &str) -> Option<&RosterFieldSpec> {
+        self.fields.iter().find(|field| field.name == name)
+    }
+
+    /// Voter information of the voter at `position` from the values of its
+    /// fields by name, validated and in canonical form.
+    pub fn voter_info(&self, position: usize, values: &BTreeMap<String, String>) -> Result<VoterInfo> {
+        let invalid = |reason: String| format!("Voter {}: {}", position, reason);
+        if let Some(name) = values.keys().find(|name| self.field(name).is_none()) {
+            return Err(invalid(format!("{} is not a field of the roster schema", name)).into());
+        }
+        let mut canonical: BTreeMap<String, String> = BTreeMap::new();
+        for field in self.fields.iter() {
+            match values.get(&field.name).map(|value| value.trim()).filter(|value| !value.is_empty()) {
+                Some(value) => {
+                    let value = field.field_type.canonical(value)
+                        .map_err(|reason| invalid(format!("{}: {}", field.name, reason)))?;
+                    canonical.insert(field.name.clone(), value);
+                },
+                None if field.required => return Err(invalid(format!("{} is required", field.name)).into()),
+                None => ()
+            }
+        }
+        let mut standard = |name: &str| canonical.remove(name).unwrap_or_default();
+        Ok(VoterInfo {
+            last_name: standard("last_name"),
+            first_name: standard("first_name"),
+            street_address: standard("street_address"),
+            city: standard("city"),
+            state: standard("state"),
+            zip_code: standard("zip_code"),
+            extra_fields: canonical
+        })
+    }
+
+    /// Roster read from CSV, with a header row naming the field of every
+    /// column.
+    pub fn read_roster<R: std::io::Read>(&self, reader: R) -> Result<VoterRoster> {
+        let mut csvreader = csv::Reader::from_reader(reader);
+        let headers: Vec<String> = csvreader.headers()?.iter().map(|name| name.trim().to_owned()).collect();
+        if let Some(name) = headers.iter().find(|name| self.field(name).is_none()) {
+            return Err(format!("Roster column {} is not a field of the roster schema.", name).into());
+        }
+        if let Some(field) = self.fields.iter().find(|field| field.required && !headers.contains(&field.name)) {
+            return Err(format!("Roster has no column for the required field {}.", field.name).into());
+        }
+        let records = csvreader.records().enumerate()
+            .map(|(position, row)| {
+                let values = headers.iter().cloned().zip(row?.iter().map(str::to_owned)).collect();
+                Ok(VoterRosterRecord { position, voter_info: self.voter_info(position, &values)? })
+            })
+            .collect::<Result<VoterRosterRecords>>()?;
+        Ok(VoterRoster { records })
+    }
+
+    /// `roster` validated against the schema and in canonical form.
+    pub fn apply(&self, roster: &VoterRoster) -> Result<VoterRoster> {
+        let records = roster.records.iter()
+            .map(|record| {
+                let mut values = record.voter_info.extra_fields.clone();
+                for field in RosterField::ALL.iter() {
+                    let value = field.value(&record.voter_info);
+                    if !value.is_empty() {
+                        values.insert(field.name().to_owned(), value.to_owned());
+                    }
+                }
+                Ok(VoterRosterRecord { position: record.position, voter_info: self.voter_info(record.position, &values)? })
+            })
+            .collect::<Result<VoterRosterRecords>>()?;
+        Ok(VoterRoster { records })
+    }
+}
+
+impl VoterInfo {
+    /// Fields beyond the standard ones as `name: value` lines, by name.
+    pub fn extra_field_lines(&self) -> Vec<String> {
+        self.extra_fields.iter()
+            .map(|(name, value)| format!("{}: {}", name, value))
+            .collect()
+    }
+}
@@ -1,5 +1,6 @@
 //! Implementation for the Voter Roster.
 
+use std::collections::BTreeMap;
 use serde::{Serialize, Deserialize};
 use super::*;
 
@@ -28,19 +29,37 @@ pub struct VoterInfo {
     pub street_address: String,
     pub city: String,
     pub state: String,
-    pub zip_code: String
+    pub zip_code: String,
+    /// Fields of the roster schema beyond the standard ones, by name.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extra_fields: BTreeMap<String, String>
 }
 
 impl VoterRoster {
     pub fn from_file(path: &dyn AsRef<Path>) -> Result<Self> {
-        let mut csvreader = csv::Reader::from_path(path)?;
-        let records = csvreader.deserialize::<VoterRosterFileRow>();
+        VoterRoster::from_reader(std::fs::File::open(path)?)
+    }
+
+    /// Read a roster with the fields of `schema`, or the standard fields if
+    /// the poll has no roster schema.
+    pub fn from_file_with_schema(path: &dyn AsRef<Path>, schema: Option<&RosterSchema>) -> Result<Self> {
+        match schema {
+            Some(schema) => schema.read_roster(std::fs::File::open(path)?),
+            None => VoterRoster::from_file(path)
+        }
+    }
+
+    /// Read a roster in the CSV format of the roster file.
+    pub fn from_reader<R: std::io::Read>(reader: R) -> Result<Self> {
+        let mut csvreader = csv::Reader::from_reader(reader);
+        let rows = csvreader.deserialize::<VoterRosterFileRow>()
+            .collect::<std::result::Result<Vec<VoterRosterFileRow>, csv::Error>>()?;
         Ok(VoterRoster {
-            records: records.enumerate()
-                .map(|(n, result)| {
+            records: rows.into_iter().enumerate()
+                .map(|(n, row)| {
                     VoterRosterRecord {
                         position: n,
-                        voter_info: VoterInfo::from(result.unwrap())
+                        voter_info: VoterInfo::from(row)
                     }})
                 .collect::<VoterRosterRecords>()
         })
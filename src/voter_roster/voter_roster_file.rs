@@ -22,7 +22,8 @@ impl From<VoterRosterFileRow> for VoterInfo {
             street_address: row.street_address,
             city: row.city,
             state: row.state,
-            zip_code: row.zip_code
+            zip_code: row.zip_code,
+            extra_fields: Default::default()
         }
     }
 }
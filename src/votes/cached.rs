@@ -0,0 +1,75 @@
+//! # Cached Transactions
+//!
+//! `cached` reads the transactions sent to the vote address into the
+//! `CachedTransaction` records of the verification core, so that the
+//! votes can be recounted offline from a file.
+//!
+//! `TransactionCache` maps a cache file in memory and borrows the strings
+//! of its transactions from the file, so that large caches are read
+//! without copying them.
+
+use std::borrow::Cow;
+use std::fs::File;
+use std::path::Path;
+use memmap2::Mmap;
+use super::*;
+use crate::blockchain::blocking::{transaction_statuses, vote_transactions};
+use crate::cryptography::check_artifact;
+use crate::Result;
+
+/// Read the transactions sent to the vote address on `network` since block
+/// `from_block` and up to `end_block`, if given, following its handovers
+/// from `vote_address`, together with their execution status.
+pub fn fetch_cached_transactions(network: &str, vote_address: Option<&str>, from_block: u64, end_block: Option<u64>) -> Result<Vec<CachedTransaction>> {
+    let transactions = vote_transactions(network, vote_address, from_block, end_block)?;
+    let hashes: Vec<String> = transactions.iter().map(|transaction| transaction.transaction_hash.clone()).collect();
+    let statuses = transaction_statuses(network, &hashes)?;
+    Ok(transactions.into_iter().zip(statuses)
+        .map(|(transaction, status)| CachedTransaction {
+            transaction_hash: transaction.transaction_hash,
+            block_number: transaction.block_number,
+            input: hex::encode(&transaction.input),
+            succeeded: status.succeeded,
+            timestamp: Some(transaction.timestamp.timestamp())
+        })
+        .collect())
+}
+
+/// Transaction of a mapped cache file, borrowing its strings from the file.
+#[derive(Debug, Clone, Deserialize)]
+pub struct CachedTransactionRef<'a> {
+    #[serde(borrow)]
+    pub transaction_hash: Cow<'a, str>,
+    pub block_number: u64,
+    /// Hex encoded input data of the transaction.
+    #[serde(borrow)]
+    pub input: Cow<'a, str>,
+    #[serde(default)]
+    pub succeeded: Option<bool>,
+    #[serde(default)]
+    pub timestamp: Option<i64>
+}
+
+/// Transaction cache file written by `cache-transactions`, mapped in memory.
+pub struct TransactionCache {
+    map: Mmap
+}
+
+impl TransactionCache {
+    pub fn open(path: &Path) -> Result<Self> {
+        check_artifact(path)?;
+        let file = File::open(path)?;
+        if file.metadata()?.len() == 0 {
+            return Err(format!("{} is empty", path.display()).into());
+        }
+        // The cache is written once by `cache-transactions` and only read.
+        let map = unsafe { Mmap::map(&file)? };
+        Ok(TransactionCache { map })
+    }
+
+    /// Transactions of the cache, in order. Their input data is left
+    /// encoded until it is decoded by the caller.
+    pub fn transactions(&self) -> Result<Vec<CachedTransactionRef<'_>>> {
+        Ok(serde_json::from_slice(&self.map)?)
+    }
+}
@@ -0,0 +1,74 @@
+//! # Encrypted Vote Payloads
+//!
+//! A vote payload can be sealed to the vote encryption key of the poll
+//! before it is posted, so that the vote code is not readable from the
+//! transaction while voting is open:
+//!
+//! ```text
+//! <version> <poll tag> <sealed payload>
+//! ```
+//!
+//! * `version` is one byte, `ENCRYPTED_PAYLOAD_VERSION`.
+//! * `poll tag` is encoded as in binary vote payloads, so that the votes
+//!   of the poll can be told apart without decrypting them.
+//! * `sealed payload` is a binary or signed vote payload sealed to the key.
+//!
+//! The payloads are decrypted when the votes are recorded, with the key
+//! reconstructed by the trustees.
+//!
+//! Votes read from the chain for a poll identified by its UUID are only
+//! counted from payloads naming the poll, so that the votes of polls run
+//! from the same address are never mixed up.
+
+use crate::cryptography::{VoteDecryptionKey, VoteEncryptionKey};
+use crate::poll_configuration::PollUuid;
+use super::*;
+
+pub const ENCRYPTED_PAYLOAD_VERSION: u8 = 3;
+
+/// Encrypted payload of a vote payload for a poll.
+pub fn encrypt_vote_payload(poll_identifier: &str, key: &VoteEncryptionKey, payload: &[u8]) -> Vec<u8> {
+    let sealed = key.seal(payload);
+    let mut encrypted = Vec::with_capacity(1 + POLL_TAG_LENGTH + sealed.len());
+    encrypted.push(ENCRYPTED_PAYLOAD_VERSION);
+    encrypted.extend_from_slice(&poll_tag(poll_identifier));
+    encrypted.extend_from_slice(&sealed);
+    encrypted
+}
+
+/// Whether the data is an encrypted payload for the poll.
+pub fn is_encrypted_vote(poll_identifier: &str, data: &[u8]) -> bool {
+    data.len() > 1 + POLL_TAG_LENGTH
+        && data[0] == ENCRYPTED_PAYLOAD_VERSION
+        && data[1..=POLL_TAG_LENGTH] == poll_tag(poll_identifier)
+}
+
+/// Extract the vote code submitted for a poll from the input data of a
+/// transaction, as `poll_votecode`, decrypting encrypted payloads with
+/// `key`. Encrypted payloads are ignored without a key.
+pub fn decrypt_poll_votecode(poll_identifier: &str, data: &[u8], key: Option<&VoteDecryptionKey>) -> Option<VoteCode> {
+    decrypt_poll_vote(poll_identifier, data, key).map(|(votecode, _)| votecode)
+}
+
+/// Vote code submitted for a poll by a payload, which must name the poll
+/// for polls identified by their UUID.
+fn chain_poll_votecode(poll_identifier: &str, data: &[u8]) -> Option<VoteCode> {
+    match PollUuid::parse(poll_identifier) {
+        Some(_) => named_poll_votecode(poll_identifier, data),
+        None => poll_votecode(poll_identifier, data)
+    }
+}
+
+/// As `decrypt_poll_votecode`, with the path the vote code was decoded by.
+pub fn decrypt_poll_vote(poll_identifier: &str, data: &[u8], key: Option<&VoteDecryptionKey>) -> Option<(VoteCode, DecodePath)> {
+    if !is_encrypted_vote(poll_identifier, data) {
+        let votecode = chain_poll_votecode(poll_identifier, data)?;
+        return Some((votecode, DecodePath { format: payload_format(data)?, encrypted: false }));
+    }
+    let payload = key?.open(&data[1 + POLL_TAG_LENGTH..])?;
+    if payload.first() == Some(&ENCRYPTED_PAYLOAD_VERSION) {
+        return None;
+    }
+    let votecode = chain_poll_votecode(poll_identifier, &payload)?;
+    Some((votecode, DecodePath { format: payload_format(&payload)?, encrypted: true }))
+}
@@ -0,0 +1,33 @@
+//! # Votes Module
+//!
+//! `votes` contains the encoding of votes as they are submitted by voters,
+//! either typed in by hand or scanned from the QR codes printed on ballots
+//! and receipts.
+
+use serde::{Serialize, Deserialize};
+use crate::ballots::*;
+pub use seventh_estate_core::CachedTransaction;
+
+pub mod payload;
+pub use payload::*;
+
+pub mod encrypted;
+pub use encrypted::*;
+
+pub mod submitted;
+pub use submitted::*;
+
+pub mod cached;
+pub use cached::*;
+
+pub mod sms;
+pub use sms::*;
+
+pub mod sources;
+pub use sources::*;
+
+pub mod provisional;
+pub use provisional::*;
+
+pub mod policy;
+pub use policy::*;
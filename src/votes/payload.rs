@@ -0,0 +1,9 @@
+//! # Vote Payloads
+//!
+//! The payload encoding is part of the verification core, so that the
+//! voter-facing website decodes payloads exactly as this crate does. Votes
+//! signed by the voter's device are checked with the `signatures` feature
+//! of the core, which this crate enables.
+
+pub use seventh_estate_core::payload::*;
+pub use seventh_estate_core::typed_vote::*;
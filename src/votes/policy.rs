@@ -0,0 +1,146 @@
+//! # Tally Policies
+//!
+//! `TallyPolicy` gathers the counting rules that differ between
+//! jurisdictions: which submission of a vote code is counted, which votes
+//! count for a ballot voted with both of its vote codes, how many blocks
+//! after the end block votes are still accepted, and whether provisional
+//! ballots still pending when the tally is revealed hold it up or are
+//! rejected. `TallyRules` is the policy set in the poll configuration; its
+//! defaults are the rules of polls created without one.
+
+use std::collections::HashMap;
+use super::*;
+
+pub trait TallyPolicy {
+    /// Last block in which votes are counted, for a poll closing at `end_block`.
+    fn closing_block(&self, end_block: Option<u64>) -> Option<u64>;
+
+    /// Whether `later`, a later submission of the vote code of `counted`,
+    /// is counted instead of it.
+    fn replaces(&self, counted: &SubmittedVote, later: &SubmittedVote) -> bool;
+
+    /// Positions of the counted votes among the votes for the vote codes
+    /// of a ballot, given in the order they were recorded.
+    fn counted_ballot_votes(&self, votes: &[&SubmittedVote]) -> Vec<usize>;
+
+    /// Eligibility assumed for the provisional ballots still pending when
+    /// the tally is revealed; `None` if they must be resolved first.
+    fn pending_eligibility(&self) -> Option<bool>;
+}
+
+/// Submission counted for a vote code submitted more than once.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicateVotes {
+    #[default]
+    First,
+    Last
+}
+
+/// Votes counted for a ballot voted with both of its vote codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BallotVotes {
+    /// Both votes are counted.
+    #[default]
+    CountAll,
+    /// Neither vote is counted.
+    Cancel,
+    First,
+    Last
+}
+
+/// Provisional ballots still pending when the tally is revealed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PendingProvisional {
+    /// The tally is not revealed until they are resolved.
+    #[default]
+    Refuse,
+    /// Their votes are rejected.
+    Reject
+}
+
+/// Tally policy of a poll, as configured.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TallyRules {
+    #[serde(default)]
+    pub duplicate_votes: DuplicateVotes,
+    #[serde(default)]
+    pub ballot_votes: BallotVotes,
+    /// Blocks after the end block in which votes are still counted.
+    #[serde(default)]
+    pub late_blocks: u64,
+    #[serde(default)]
+    pub pending_provisional: PendingProvisional
+}
+
+impl TallyRules {
+    pub fn is_default(&self) -> bool {
+        *self == TallyRules::default()
+    }
+}
+
+impl TallyPolicy for TallyRules {
+    fn closing_block(&self, end_block: Option<u64>) -> Option<u64> {
+        end_block.map(|end_block| end_block.saturating_add(self.late_blocks))
+    }
+
+    fn replaces(&self, _counted: &SubmittedVote, _later: &SubmittedVote) -> bool {
+        self.duplicate_votes == DuplicateVotes::Last
+    }
+
+    fn counted_ballot_votes(&self, votes: &[&SubmittedVote]) -> Vec<usize> {
+        match (self.ballot_votes, votes.len()) {
+            (_, 0) => Vec::new(),
+            (BallotVotes::CountAll, count) | (_, count @ 1) => (0..count).collect(),
+            (BallotVotes::Cancel, _) => Vec::new(),
+            (BallotVotes::First, _) => vec![0],
+            (BallotVotes::Last, count) => vec![count - 1]
+        }
+    }
+
+    fn pending_eligibility(&self) -> Option<bool> {
+        match self.pending_provisional {
+            PendingProvisional::Refuse => None,
+            PendingProvisional::Reject => Some(false)
+        }
+    }
+}
+
+/// Votes with their position in the merged votes.
+type NumberedVotes = Vec<(usize, SubmittedVote)>;
+
+/// Take the votes the policy does not count for ballots voted with both of
+/// their vote codes out of the merged votes, and return them. `serial`
+/// gives the ballot of a vote code.
+pub fn split_uncounted_ballot_votes<F>(merged: &mut MergedVotes, policy: &dyn TallyPolicy, serial: F) -> Vec<SubmittedVote>
+    where F: Fn(&str) -> Option<BallotSerial>
+{
+    let mut ballots: HashMap<BallotSerial, Vec<usize>> = HashMap::new();
+    for (n, vote) in merged.votes.iter().enumerate() {
+        if let Some(serial) = serial(&vote.votecode) {
+            ballots.entry(serial).or_default().push(n);
+        }
+    }
+    let mut uncounted = vec![false; merged.votes.len()];
+    for positions in ballots.values().filter(|positions| 1 < positions.len()) {
+        let votes: Vec<&SubmittedVote> = positions.iter().map(|&n| &merged.votes[n]).collect();
+        let counted = policy.counted_ballot_votes(&votes);
+        positions.iter().enumerate()
+            .filter(|(m, _)| !counted.contains(m))
+            .for_each(|(_, &n)| uncounted[n] = true);
+    }
+    let (uncounted_votes, votes): (NumberedVotes, NumberedVotes) = merged.votes.drain(..)
+        .enumerate()
+        .partition(|(n, _)| uncounted[*n]);
+    merged.votes = votes.into_iter().map(|(_, vote)| vote).collect();
+    let uncounted_votes: Vec<SubmittedVote> = uncounted_votes.into_iter().map(|(_, vote)| vote).collect();
+    for vote in uncounted_votes.iter() {
+        if let Some(count) = merged.sources.iter_mut().find(|count| count.source == vote.source) {
+            count.votes -= 1;
+        }
+    }
+    merged.sources.retain(|count| 0 < count.votes || 0 < count.duplicates);
+    uncounted_votes
+}
@@ -0,0 +1,82 @@
+//! # Provisional Ballots
+//!
+//! A provisional ballot is issued to a voter whose eligibility is checked
+//! after voting. Its votes are recorded apart from the other votes by step
+//! 6 and are only counted by the tally once the voter was found eligible;
+//! every provisional ballot must be resolved before the tally is revealed.
+
+use std::fmt;
+use super::*;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ProvisionalBallot {
+    pub serial: BallotSerial,
+    /// Whether the voter was found eligible; `None` until resolved.
+    #[serde(default)]
+    pub eligible: Option<bool>
+}
+
+impl fmt::Display for ProvisionalBallot {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.eligible {
+            Some(true) => write!(f, "Provisional ballot {}: accepted", self.serial),
+            Some(false) => write!(f, "Provisional ballot {}: rejected", self.serial),
+            None => write!(f, "Provisional ballot {}: pending", self.serial)
+        }
+    }
+}
+
+/// Votes for provisional ballots, by resolution.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ProvisionalCount {
+    pub accepted: usize,
+    pub rejected: usize,
+    pub pending: usize
+}
+
+impl ProvisionalCount {
+    pub fn total(&self) -> usize {
+        self.accepted + self.rejected + self.pending
+    }
+}
+
+impl fmt::Display for ProvisionalCount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} accepted, {} rejected, {} pending", self.accepted, self.rejected, self.pending)
+    }
+}
+
+/// Split recorded votes into the votes counted right away and the votes for
+/// the `provisional` ballots, set aside until they are resolved. `serial`
+/// gives the ballot of a vote code.
+pub fn bucket_provisional_votes<F>(votes: Vec<VoteCode>, provisional: &[ProvisionalBallot], serial: F) -> (Vec<VoteCode>, Vec<VoteCode>)
+    where F: Fn(&VoteCode) -> Option<BallotSerial>
+{
+    votes.into_iter().partition(|votecode| match serial(votecode) {
+        Some(serial) => !provisional.iter().any(|ballot| ballot.serial == serial),
+        None => true
+    })
+}
+
+/// Provisional votes counted by the tally, those of the accepted ballots,
+/// and the count of every resolution.
+pub fn resolve_provisional_votes<F>(provisional_votes: &[VoteCode], provisional: &[ProvisionalBallot], serial: F) -> (Vec<VoteCode>, ProvisionalCount)
+    where F: Fn(&VoteCode) -> Option<BallotSerial>
+{
+    let mut accepted = Vec::new();
+    let mut count = ProvisionalCount::default();
+    for votecode in provisional_votes {
+        let eligible = serial(votecode)
+            .and_then(|serial| provisional.iter().find(|ballot| ballot.serial == serial))
+            .and_then(|ballot| ballot.eligible);
+        match eligible {
+            Some(true) => {
+                count.accepted += 1;
+                accepted.push(*votecode);
+            },
+            Some(false) => count.rejected += 1,
+            None => count.pending += 1
+        }
+    }
+    (accepted, count)
+}
@@ -0,0 +1,114 @@
+//! # SMS Votes
+//!
+//! `sms` normalizes the messages received by an SMS gateway into submitted
+//! votes. Messages are read from a gateway export, either a CSV file with
+//! `id`, `from`, `received_at` and `body` columns or a JSON array of such
+//! objects (also accepted as the `messages` field of an object), or, with
+//! the `sms` feature, fetched as JSON from the gateway API. Web API exports
+//! of submitted votes are read in the same format.
+//!
+//! The body of a message is a vote code, with or without separators, or a
+//! vote payload. Messages for other polls or without a valid vote code are
+//! ignored.
+
+use std::fs::File;
+use std::path::Path;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::Deserializer;
+use super::*;
+use crate::Result;
+
+/// Message received by the SMS gateway.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SmsMessage {
+    #[serde(alias = "sid", alias = "message_id")]
+    pub id: String,
+    #[serde(alias = "sender", alias = "phone")]
+    pub from: String,
+    /// RFC 3339 or RFC 2822 date, or `YYYY-MM-DD HH:MM:SS` in UTC.
+    #[serde(alias = "date", alias = "timestamp", deserialize_with = "deserialize_date")]
+    pub received_at: DateTime<Utc>,
+    #[serde(alias = "text", alias = "message")]
+    pub body: String
+}
+
+fn deserialize_date<'de, D: Deserializer<'de>>(deserializer: D) -> std::result::Result<DateTime<Utc>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    let value = value.trim();
+    DateTime::parse_from_rfc3339(value)
+        .or_else(|_| DateTime::parse_from_rfc2822(value))
+        .map(|date| date.with_timezone(&Utc))
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+            .map(|date| DateTime::<Utc>::from_utc(date, Utc)))
+        .map_err(|_| serde::de::Error::custom(format!("Invalid message date: {}", value)))
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SmsExport {
+    Messages(Vec<SmsMessage>),
+    Page { messages: Vec<SmsMessage> }
+}
+
+impl From<SmsExport> for Vec<SmsMessage> {
+    fn from(export: SmsExport) -> Self {
+        match export {
+            SmsExport::Messages(messages) => messages,
+            SmsExport::Page { messages } => messages
+        }
+    }
+}
+
+impl SubmittedVote {
+    /// Decode the vote submitted by an SMS message for the poll.
+    pub fn from_sms(poll_identifier: &str, message: &SmsMessage) -> Option<Self> {
+        let body = message.body.trim().as_bytes();
+        let votecode = poll_votecode(poll_identifier, body)?;
+        Some(SubmittedVote {
+            votecode: string_from_votecode(&votecode),
+            transaction_hash: message.id.clone(),
+            from: message.from.clone(),
+            block_number: 0,
+            timestamp: message.received_at,
+            source: VoteChannel::Sms,
+            decoded: payload_format(body).map(|format| DecodePath { format, encrypted: false })
+        })
+    }
+}
+
+/// Read a gateway export, in JSON if the file name ends in `.json` and in
+/// CSV otherwise.
+pub fn read_sms_export(path: &Path) -> Result<Vec<SmsMessage>> {
+    if path.extension().is_some_and(|extension| extension == "json") {
+        let export: SmsExport = serde_json::from_reader(File::open(path)?)?;
+        return Ok(export.into());
+    }
+    let mut csvreader = csv::Reader::from_path(path)?;
+    let messages = csvreader.deserialize::<SmsMessage>()
+        .collect::<std::result::Result<Vec<SmsMessage>, csv::Error>>()?;
+    Ok(messages)
+}
+
+/// Fetch the messages from the gateway API at `url`, authenticated with
+/// the bearer `token` if given.
+#[cfg(feature = "sms")]
+pub fn fetch_sms_messages(url: &str, token: Option<&str>) -> Result<Vec<SmsMessage>> {
+    use hyper::{Body, Client, Request};
+    use hyper_tls::HttpsConnector;
+
+    let client = Client::builder().build::<_, Body>(HttpsConnector::new());
+    let mut request = Request::get(url).header("Accept", "application/json");
+    if let Some(token) = token {
+        request = request.header("Authorization", format!("Bearer {}", token));
+    }
+    let request = request.body(Body::empty())?;
+    let body = futures::executor::block_on(async {
+        let response = client.request(request).await?;
+        if !response.status().is_success() {
+            return Err(format!("SMS gateway answered {}", response.status()).into());
+        }
+        Ok::<_, Box<dyn std::error::Error>>(hyper::body::to_bytes(response.into_body()).await?)
+    })?;
+    let export: SmsExport = serde_json::from_slice(&body)?;
+    Ok(export.into())
+}
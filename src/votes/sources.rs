@@ -0,0 +1,308 @@
+//! # Vote Sources
+//!
+//! `sources` reads the votes submitted through every channel of a poll
+//! behind the `VoteSource` trait: the blockchain, a transaction cache
+//! written by `cache-transactions`, an SMS gateway export, a web API export
+//! and a votes file. `merge_votes` merges the votes of several sources,
+//! counting every vote code once and attributing it to the submission the
+//! tally policy counts, the first one unless configured otherwise. Test
+//! vote codes are split from the merged votes by `split_test_votes`, so
+//! that they are reported instead of counted.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, TimeZone, Utc};
+use rayon::prelude::*;
+use tracing::{info, warn};
+use super::*;
+use crate::Result;
+use crate::blockchain::blocking::transaction_statuses;
+use crate::cryptography::VoteDecryptionKey;
+
+/// Part of the poll in which votes are accepted.
+#[derive(Debug, Clone)]
+pub struct VotingWindow {
+    pub poll_identifier: String,
+    /// Block of the commitment transaction.
+    pub from_block: u64,
+    /// Vote address when the commitment was posted; the configured vote
+    /// address if not given.
+    pub vote_address: Option<String>,
+    /// Time the commitment was posted; votes received earlier are ignored.
+    pub since: Option<DateTime<Utc>>,
+    /// Last block in which votes are accepted. Only votes read from the
+    /// chain or a transaction cache carry a block.
+    pub end_block: Option<u64>,
+    /// Key encrypted votes read from the chain or a transaction cache are
+    /// decrypted with; encrypted votes are ignored without it.
+    pub decryption_key: Option<VoteDecryptionKey>
+}
+
+pub trait VoteSource {
+    /// Channel the votes of this source are attributed to.
+    fn channel(&self) -> VoteChannel;
+
+    /// Location of the source, for logs.
+    fn location(&self) -> String;
+
+    /// Read the votes submitted for the poll in the voting window.
+    fn read_votes(&self, window: &VotingWindow) -> Result<Vec<SubmittedVote>>;
+}
+
+/// Votes submitted on the blockchain of the network `.0` since the
+/// commitment. Failed transactions do not record a vote.
+pub struct ChainVotes(pub String);
+
+impl VoteSource for ChainVotes {
+    fn channel(&self) -> VoteChannel { VoteChannel::Chain }
+
+    fn location(&self) -> String { "vote address".to_owned() }
+
+    fn read_votes(&self, window: &VotingWindow) -> Result<Vec<SubmittedVote>> {
+        let votes = fetch_submitted_votes(&self.0, &window.poll_identifier, window.vote_address.as_deref(), window.from_block, window.end_block, window.decryption_key.as_ref())?;
+        let hashes: Vec<String> = votes.iter().map(|vote| vote.transaction_hash.clone()).collect();
+        let statuses = transaction_statuses(&self.0, &hashes)?;
+        Ok(votes.into_iter().zip(statuses)
+            .filter(|(_, status)| status.succeeded != Some(false))
+            .map(|(vote, _)| vote)
+            .collect())
+    }
+}
+
+/// Votes of a JSON transaction cache written by `cache-transactions`,
+/// mapped in memory.
+pub struct CachedVotes(pub PathBuf);
+
+impl VoteSource for CachedVotes {
+    fn channel(&self) -> VoteChannel { VoteChannel::Cached }
+
+    fn location(&self) -> String { self.0.display().to_string() }
+
+    fn read_votes(&self, window: &VotingWindow) -> Result<Vec<SubmittedVote>> {
+        let cache = TransactionCache::open(&self.0)?;
+        let transactions = cache.transactions()?;
+        let votes = transactions.par_iter()
+            .filter(|transaction| window.from_block <= transaction.block_number)
+            .filter(|transaction| window.end_block.is_none_or(|end_block| transaction.block_number <= end_block))
+            .filter(|transaction| transaction.succeeded != Some(false))
+            .filter_map(|transaction| {
+                let input = hex::decode(transaction.input.as_bytes()).ok()?;
+                let (votecode, decoded) = decrypt_poll_vote(&window.poll_identifier, &input, window.decryption_key.as_ref())?;
+                Some(SubmittedVote {
+                    votecode: string_from_votecode(&votecode),
+                    transaction_hash: transaction.transaction_hash.to_string(),
+                    from: String::new(),
+                    block_number: transaction.block_number,
+                    timestamp: Utc.timestamp(transaction.timestamp.unwrap_or(0), 0),
+                    source: VoteChannel::Cached,
+                    decoded: Some(decoded)
+                })
+            })
+            .collect();
+        Ok(votes)
+    }
+}
+
+/// Votes of the messages exported by an SMS gateway or a web API, read from
+/// a file or, with the `sms` feature, an `http` or `https` URL.
+pub struct MessageExport {
+    pub channel: VoteChannel,
+    pub location: String,
+    /// Bearer token for the API.
+    pub token: Option<String>
+}
+
+impl MessageExport {
+    fn read_messages(&self) -> Result<Vec<SmsMessage>> {
+        if self.location.starts_with("http://") || self.location.starts_with("https://") {
+            #[cfg(feature = "sms")]
+            return fetch_sms_messages(&self.location, self.token.as_deref());
+            #[cfg(not(feature = "sms"))]
+            return Err("Reading votes from an API requires the sms feature.".into());
+        }
+        read_sms_export(Path::new(&self.location))
+    }
+}
+
+impl VoteSource for MessageExport {
+    fn channel(&self) -> VoteChannel { self.channel }
+
+    fn location(&self) -> String { self.location.clone() }
+
+    fn read_votes(&self, window: &VotingWindow) -> Result<Vec<SubmittedVote>> {
+        let messages = self.read_messages()?;
+        let votes: Vec<SubmittedVote> = messages.par_iter()
+            .filter(|message| window.since.is_none_or(|since| since <= message.received_at))
+            .filter_map(|message| SubmittedVote::from_sms(&window.poll_identifier, message))
+            .map(|vote| SubmittedVote { source: self.channel, ..vote })
+            .collect();
+        if votes.len() < messages.len() {
+            warn!(channel = self.channel.name(), ignored = messages.len() - votes.len(), "Messages without a vote for the poll ignored");
+        }
+        Ok(votes)
+    }
+}
+
+/// Vote codes of a votes file, with a `votecode` column.
+pub struct FileVotes(pub PathBuf);
+
+#[derive(Debug, Clone, Deserialize)]
+struct VotesFileRow {
+    votecode: String
+}
+
+impl VoteSource for FileVotes {
+    fn channel(&self) -> VoteChannel { VoteChannel::File }
+
+    fn location(&self) -> String { self.0.display().to_string() }
+
+    fn read_votes(&self, _window: &VotingWindow) -> Result<Vec<SubmittedVote>> {
+        read_votes_file(&self.0)
+    }
+}
+
+/// Vote codes of a votes file, such as the test vote codes of a poll.
+pub fn read_votecodes_file(path: &Path) -> Result<VoteCodeSet> {
+    let votes = read_votes_file(path)?;
+    Ok(votes.iter()
+        .filter_map(|vote| votecode_from_string(&vote.votecode))
+        .collect())
+}
+
+fn read_votes_file(path: &Path) -> Result<Vec<SubmittedVote>> {
+    let mut csvreader = csv::Reader::from_path(path)?;
+    let rows = csvreader.deserialize::<VotesFileRow>()
+        .collect::<std::result::Result<Vec<VotesFileRow>, csv::Error>>()?;
+    let votes = rows.par_iter().enumerate()
+        .filter_map(|(n, row)| {
+            let votecode = match votecode_from_string(&row.votecode) {
+                Some(votecode) => votecode,
+                None => {
+                    warn!(line = n + 2, votecode = %row.votecode, "Invalid vote code ignored");
+                    return None;
+                }
+            };
+            Some(SubmittedVote {
+                votecode: string_from_votecode(&votecode),
+                transaction_hash: String::new(),
+                from: String::new(),
+                block_number: 0,
+                timestamp: Utc.timestamp(0, 0),
+                source: VoteChannel::File,
+                decoded: None
+            })
+        })
+        .collect();
+    Ok(votes)
+}
+
+/// Votes counted from a source.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VoteSourceCount {
+    pub source: VoteChannel,
+    /// Vote codes counted for the poll and attributed to the source.
+    pub votes: usize,
+    /// Submissions of vote codes already counted, from this or another source.
+    #[serde(default)]
+    pub duplicates: usize
+}
+
+impl fmt::Display for VoteSourceCount {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.source, self.votes)?;
+        if 0 < self.duplicates {
+            write!(f, " ({} duplicates)", self.duplicates)?;
+        }
+        Ok(())
+    }
+}
+
+/// Votes merged from several sources.
+#[derive(Debug, Clone)]
+pub struct MergedVotes {
+    /// First submission of every counted vote code.
+    pub votes: Vec<SubmittedVote>,
+    /// Counts of every channel, in the order of `VOTE_CHANNELS`.
+    pub sources: Vec<VoteSourceCount>,
+    /// Submissions of vote codes that are not on a ballot of the poll.
+    pub unknown_votes: usize
+}
+
+/// Merge the votes of `submitted`, given in the order of the sources, by
+/// vote code. Only the vote codes accepted by `on_ballot` are counted, and
+/// `policy` chooses which submission of a vote code is counted. Sources
+/// decode their votes in parallel; the merge itself is sequential, so that
+/// the submissions of a vote code are considered in order.
+pub fn merge_submitted_votes<F>(submitted: Vec<SubmittedVote>, policy: &dyn TallyPolicy, on_ballot: F) -> MergedVotes
+    where F: Fn(&str) -> bool
+{
+    let mut counted: VoteCodeMap<usize> = VoteCodeMap::new();
+    let mut votes: Vec<SubmittedVote> = Vec::new();
+    let mut counts: HashMap<VoteChannel, VoteSourceCount> = HashMap::new();
+    let mut unknown_votes = 0;
+    for vote in submitted {
+        let votecode = match votecode_from_string(&vote.votecode) {
+            Some(votecode) if on_ballot(&vote.votecode) => votecode,
+            _ => {
+                unknown_votes += 1;
+                continue;
+            }
+        };
+        counts.entry(vote.source)
+            .or_insert(VoteSourceCount { source: vote.source, votes: 0, duplicates: 0 });
+        match counted.get(&votecode).copied() {
+            None => {
+                counted.insert(votecode, votes.len());
+                counts.get_mut(&vote.source).unwrap().votes += 1;
+                votes.push(vote);
+            },
+            Some(n) if policy.replaces(&votes[n], &vote) => {
+                let replaced = counts.get_mut(&votes[n].source).unwrap();
+                replaced.votes -= 1;
+                replaced.duplicates += 1;
+                counts.get_mut(&vote.source).unwrap().votes += 1;
+                votes[n] = vote;
+            },
+            Some(_) => counts.get_mut(&vote.source).unwrap().duplicates += 1
+        }
+    }
+    MergedVotes {
+        votes,
+        sources: VOTE_CHANNELS.iter().filter_map(|channel| counts.remove(channel)).collect(),
+        unknown_votes
+    }
+}
+
+/// Read and merge the votes of `sources`; see `merge_submitted_votes`.
+pub fn merge_votes<F>(sources: &[Box<dyn VoteSource>], window: &VotingWindow, policy: &dyn TallyPolicy, on_ballot: F) -> Result<MergedVotes>
+    where F: Fn(&str) -> bool
+{
+    let mut submitted: Vec<SubmittedVote> = Vec::new();
+    for source in sources.iter() {
+        let votes = source.read_votes(window)?;
+        info!(channel = source.channel().name(), location = %source.location(), votes = votes.len(), "Votes read");
+        submitted.extend(votes);
+    }
+    let merged = merge_submitted_votes(submitted, policy, on_ballot);
+    if 0 < merged.unknown_votes {
+        warn!(ignored = merged.unknown_votes, "Vote codes not on a ballot of the poll ignored");
+    }
+    Ok(merged)
+}
+
+/// Take the votes for `test_codes`, such as usability test or coercion
+/// canary vote codes, out of the merged votes, so that they are reported
+/// instead of counted.
+pub fn split_test_votes(merged: &mut MergedVotes, test_codes: &VoteCodeSet) -> Vec<SubmittedVote> {
+    let (test_votes, votes) = merged.votes.drain(..)
+        .partition(|vote| test_codes.contains_str(&vote.votecode));
+    merged.votes = votes;
+    for vote in test_votes.iter() {
+        if let Some(count) = merged.sources.iter_mut().find(|count| count.source == vote.source) {
+            count.votes -= 1;
+        }
+    }
+    merged.sources.retain(|count| 0 < count.votes || 0 < count.duplicates);
+    test_votes
+}
@@ -0,0 +1,156 @@
+//! # Submitted Votes
+//!
+//! `submitted` reads back the votes submitted for a poll from the
+//! transactions sent to the vote address. Votes received through other
+//! channels, such as an SMS gateway, are normalized into the same form and
+//! labeled with their source.
+
+use std::fmt;
+use super::*;
+use chrono::{DateTime, Utc};
+use crate::blockchain::ChainTransaction;
+use crate::blockchain::blocking::vote_transactions;
+use crate::cryptography::VoteDecryptionKey;
+use crate::Result;
+
+/// Channel through which a vote was submitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum VoteChannel {
+    /// Transaction sent to the vote address.
+    #[default]
+    Chain,
+    /// Transaction read from a cache written by `cache-transactions`.
+    Cached,
+    /// Message received by an SMS gateway.
+    Sms,
+    /// Submission exported by a web API.
+    Api,
+    /// Vote code listed in a votes file.
+    File
+}
+
+pub const VOTE_CHANNELS: [VoteChannel; 5] = [VoteChannel::Chain, VoteChannel::Cached, VoteChannel::Sms, VoteChannel::Api, VoteChannel::File];
+
+impl VoteChannel {
+    pub fn name(&self) -> &'static str {
+        match self {
+            VoteChannel::Chain => "chain",
+            VoteChannel::Cached => "cached",
+            VoteChannel::Sms => "sms",
+            VoteChannel::Api => "api",
+            VoteChannel::File => "file"
+        }
+    }
+}
+
+impl fmt::Display for VoteChannel {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// How the payload of a vote transaction was decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DecodePath {
+    pub format: PayloadFormat,
+    /// Whether the payload was encrypted to the vote encryption key.
+    #[serde(default)]
+    pub encrypted: bool
+}
+
+impl fmt::Display for DecodePath {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.encrypted {
+            true => write!(f, "encrypted {}", self.format),
+            false => write!(f, "{}", self.format)
+        }
+    }
+}
+
+/// A vote submitted for the poll. Votes received by SMS carry the gateway
+/// message identifier as transaction hash, the sender as `from` and block 0.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SubmittedVote {
+    pub votecode: String,
+    pub transaction_hash: String,
+    pub from: String,
+    pub block_number: u64,
+    pub timestamp: DateTime<Utc>,
+    #[serde(default)]
+    pub source: VoteChannel,
+    /// How the payload was decoded, for votes read from transactions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decoded: Option<DecodePath>
+}
+
+impl SubmittedVote {
+    /// Decode the vote submitted by a transaction for the poll.
+    /// Payloads for other polls are ignored.
+    pub fn from_transaction(poll_identifier: &str, transaction: &ChainTransaction) -> Option<Self> {
+        SubmittedVote::decrypt_transaction(poll_identifier, transaction, None)
+    }
+
+    /// Decode the vote submitted by a transaction for the poll, decrypting
+    /// encrypted payloads with `key`.
+    pub fn decrypt_transaction(poll_identifier: &str, transaction: &ChainTransaction, key: Option<&VoteDecryptionKey>) -> Option<Self> {
+        let (votecode, decoded) = decrypt_poll_vote(poll_identifier, &transaction.input, key)?;
+        Some(SubmittedVote {
+            votecode: string_from_votecode(&votecode),
+            transaction_hash: transaction.transaction_hash.clone(),
+            from: transaction.from.clone(),
+            block_number: transaction.block_number,
+            timestamp: transaction.timestamp,
+            source: VoteChannel::Chain,
+            decoded: Some(decoded)
+        })
+    }
+}
+
+/// Transaction and block a recorded vote was read from.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct VoteRecord {
+    pub votecode: String,
+    pub source: VoteChannel,
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub transaction_hash: String,
+    /// Block the vote was mined in; 0 for votes not read from the chain.
+    #[serde(default)]
+    pub block_number: u64,
+    /// Sender of the transaction or message, if known.
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub from: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub decoded: Option<DecodePath>
+}
+
+impl VoteRecord {
+    /// Whether the vote was mined in a block of the chain.
+    pub fn mined(&self) -> bool {
+        matches!(self.source, VoteChannel::Chain | VoteChannel::Cached) && 0 < self.block_number
+    }
+}
+
+impl From<&SubmittedVote> for VoteRecord {
+    fn from(vote: &SubmittedVote) -> Self {
+        VoteRecord {
+            votecode: vote.votecode.clone(),
+            source: vote.source,
+            transaction_hash: vote.transaction_hash.clone(),
+            block_number: vote.block_number,
+            from: vote.from.clone(),
+            decoded: vote.decoded
+        }
+    }
+}
+
+/// Read the votes submitted for the poll on `network` since block
+/// `from_block` and up to `end_block`, if given, in the order they were
+/// mined, following the handovers of the vote address from `vote_address`.
+/// Encrypted votes are decrypted with `key`, if given.
+pub fn fetch_submitted_votes(network: &str, poll_identifier: &str, vote_address: Option<&str>, from_block: u64, end_block: Option<u64>, key: Option<&VoteDecryptionKey>) -> Result<Vec<SubmittedVote>> {
+    let votes: Vec<SubmittedVote> = vote_transactions(network, vote_address, from_block, end_block)?.iter()
+        .filter_map(|transaction| SubmittedVote::decrypt_transaction(poll_identifier, transaction, key))
+        .collect();
+    Ok(votes)
+}
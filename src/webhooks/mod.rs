@@ -0,0 +1,162 @@
+//! # Webhooks
+//!
+//! `webhooks` posts the events detected by the monitor to the URLs of a
+//! webhook configuration file:
+//!
+//! ```yaml
+//! vote_thresholds: [100, 500]
+//! webhooks:
+//!   - url: https://hooks.example.org/poll
+//!     events: [audit_anomaly, voting_closed]   # all events if omitted
+//! ```
+//!
+//! Each payload is a JSON document signed with the poll signing key. The
+//! base64 encoded signature of the body is sent in the `X-Signature` header
+//! and is checked with the poll verification key published in the bulletin
+//! board manifest. The `text` field of the payload is a readable summary of
+//! the event, shown as is by chat incoming webhooks.
+
+use std::fs::File;
+use std::path::Path;
+use std::time::Duration;
+use chrono::{DateTime, Utc};
+use hyper::{Body, Client, Method, Request, Uri};
+use hyper::client::HttpConnector;
+use hyper_tls::HttpsConnector;
+use serde::{Serialize, Deserialize};
+use tracing::{debug, warn};
+use crate::Result;
+use crate::cryptography::{sign, Base64String};
+use crate::monitor::{EventSink, PollEvent, POLL_EVENTS};
+
+/// Time allowed for each delivery attempt.
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+const DELIVERY_ATTEMPTS: usize = 3;
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Webhook {
+    pub url: String,
+    /// Events posted to the webhook; all events if empty.
+    #[serde(default)]
+    pub events: Vec<String>
+}
+
+impl Webhook {
+    pub fn subscribed(&self, event: &PollEvent) -> bool {
+        self.events.is_empty() || self.events.iter().any(|name| name == event.name())
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WebhookConfiguration {
+    /// Numbers of votes counted that raise a `vote_threshold_reached` event.
+    #[serde(default)]
+    pub vote_thresholds: Vec<usize>,
+    pub webhooks: Vec<Webhook>
+}
+
+impl WebhookConfiguration {
+    pub fn from_file(path: &dyn AsRef<Path>) -> Result<Self> {
+        let configuration: WebhookConfiguration = serde_yaml::from_reader(File::open(path)?)?;
+        configuration.validate()?;
+        Ok(configuration)
+    }
+
+    pub fn validate(&self) -> Result<()> {
+        for webhook in self.webhooks.iter() {
+            let uri: Uri = webhook.url.parse()?;
+            if !matches!(uri.scheme_str(), Some("http") | Some("https")) {
+                return Err(format!("Webhook URL must be http or https: {}", webhook.url).into());
+            }
+            if let Some(name) = webhook.events.iter().find(|name| !POLL_EVENTS.contains(&name.as_str())) {
+                return Err(format!("Unknown webhook event: {} (expected one of {})", name, POLL_EVENTS.join(", ")).into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Body posted to the webhooks.
+#[derive(Debug, Clone, Serialize)]
+pub struct WebhookPayload<'a> {
+    pub poll_identifier: &'a str,
+    pub occurred_at: DateTime<Utc>,
+    pub text: String,
+    #[serde(flatten)]
+    pub event: &'a PollEvent
+}
+
+/// Serialize the payload of an event, returning the body and its base64
+/// encoded signature.
+pub fn signed_payload(signing_key: &Base64String, poll_identifier: &str, event: &PollEvent) -> Result<(Vec<u8>, String)> {
+    let payload = WebhookPayload {
+        poll_identifier,
+        occurred_at: Utc::now(),
+        text: format!("{}: {}", poll_identifier, event),
+        event
+    };
+    let (body, signature) = sign(signing_key, serde_json::to_vec(&payload)?)?;
+    Ok((body, base64::encode(&signature)))
+}
+
+pub struct WebhookNotifier {
+    configuration: WebhookConfiguration,
+    signing_key: Base64String,
+    client: Client<HttpsConnector<HttpConnector>>
+}
+
+impl WebhookNotifier {
+    pub fn new(configuration: WebhookConfiguration, signing_key: Base64String) -> Self {
+        WebhookNotifier {
+            configuration,
+            signing_key,
+            client: Client::builder().build::<_, Body>(HttpsConnector::new())
+        }
+    }
+
+    async fn post(&self, webhook: &Webhook, event: &PollEvent, body: &[u8], signature: &str) -> Result<()> {
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(webhook.url.as_str())
+            .header("Content-Type", "application/json")
+            .header("X-Signature", signature)
+            .header("X-Event", event.name())
+            .body(Body::from(body.to_vec()))?;
+        let response = tokio::time::timeout(DELIVERY_TIMEOUT, self.client.request(request)).await??;
+        match response.status().is_success() {
+            true => Ok(()),
+            false => Err(format!("Webhook answered {}", response.status()).into())
+        }
+    }
+
+    /// Post an event to a webhook, retrying failed attempts.
+    pub fn deliver(&self, webhook: &Webhook, event: &PollEvent, body: &[u8], signature: &str) -> Result<()> {
+        let mut attempt = 1;
+        loop {
+            match futures::executor::block_on(self.post(webhook, event, body, signature)) {
+                Ok(()) => return Ok(()),
+                Err(err) if DELIVERY_ATTEMPTS <= attempt => return Err(err),
+                Err(err) => debug!(url = %webhook.url, attempt, error = %err, "Webhook delivery failed, retrying")
+            }
+            attempt += 1;
+        }
+    }
+}
+
+impl EventSink for WebhookNotifier {
+    fn notify(&mut self, poll_identifier: &str, event: &PollEvent) {
+        let (body, signature) = match signed_payload(&self.signing_key, poll_identifier, event) {
+            Ok(signed) => signed,
+            Err(err) => {
+                warn!(event = event.name(), error = %err, "Webhook payload could not be signed");
+                return;
+            }
+        };
+        for webhook in self.configuration.webhooks.iter().filter(|webhook| webhook.subscribed(event)) {
+            match self.deliver(webhook, event, &body, &signature) {
+                Ok(()) => debug!(url = %webhook.url, event = event.name(), "Webhook delivered"),
+                Err(err) => warn!(url = %webhook.url, event = event.name(), error = %err, "Webhook could not be delivered")
+            }
+        }
+    }
+}
@@ -0,0 +1,316 @@
+//! # Poll Archive
+//!
+//! `archive_poll_directory` packs every file of a poll directory into a
+//! gzipped tarball together with a manifest, in the format of the bulletin
+//! board manifest, listing the SHA-256 hash and signature of every file.
+//! The manifest is signed with the poll signing key, so that an archive
+//! can be checked without being extracted by `verify_poll_archive`.
+//!
+//! The archive holds the secured poll configuration and the private poll
+//! data: it is kept by the poll authority, unlike a bulletin board.
+//! `export_public_archive` packs only the public artifacts of a finished
+//! poll, with its public poll configuration, for anyone to keep; years
+//! later, `import_public_archive` checks it against the verification key
+//! of the poll, including the tally report against its finalization
+//! receipt, before extracting it.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::{Component, Path};
+use std::time::UNIX_EPOCH;
+use chrono::Utc;
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use tar::{Archive, Builder, EntryType, Header};
+use crate::Result;
+use crate::blockchain::{tally_hash, FinalizationReceipt};
+use crate::bulletin_board::{is_public_artifact, read_manifest, sha256_hex, verify_artifact, Artifact, BoardVerification,
+    Manifest, MANIFEST_FILENAME, MANIFEST_SIGNATURE_FILENAME, MERKLE_TREE_FILENAME};
+use crate::cryptography::{check_artifact, is_signed_artifact, sign, signature_path, Base64String};
+use crate::poll_configuration::PublicPollConfiguration;
+use super::poll_directory_name;
+
+/// Longest file read from an archive.
+const MAX_ARCHIVED_FILE_LENGTH: u64 = 256 * 1024 * 1024;
+
+/// Most files, and entries of other types, read from an archive.
+const MAX_ARCHIVE_ENTRIES: usize = 10_000;
+
+/// Most bytes of files read from an archive in all.
+const MAX_ARCHIVE_LENGTH: u64 = 1024 * 1024 * 1024;
+
+/// Public poll configuration file of a public archive.
+pub const PUBLIC_POLL_CONFIGURATION_FILENAME: &str = "poll.yaml";
+
+/// Transaction cache file of a public archive.
+pub const TRANSACTIONS_FILENAME: &str = "transactions.json";
+
+/// Archive the files of the poll directory `poll_dir` into `archive_path`.
+/// Files are stored under the name of the poll directory; symbolic links
+/// and earlier archives are left out.
+pub fn archive_poll_directory(
+    poll_identifier: &str,
+    poll_dir: &Path,
+    archive_path: &Path,
+    signing_key: &Base64String,
+    public_key: &Base64String) -> Result<Manifest>
+{
+    let root = poll_dir.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .ok_or_else(|| format!("{} is not a poll directory.", poll_dir.display()))?;
+    let mut sources: Vec<(String, u64)> = Vec::new();
+    collect_files(poll_dir, &root, &mut sources)?;
+    sources.sort();
+
+    let mut files: Vec<(String, u64, Vec<u8>)> = Vec::new();
+    for (name, modified) in sources {
+        let relative = name.strip_prefix(&root).unwrap_or(&name).trim_start_matches('/');
+        files.push((name.clone(), modified, fs::read(poll_dir.join(relative))?));
+    }
+    write_signed_archive(poll_identifier, files, archive_path, signing_key, public_key)
+}
+
+/// Archive the public artifacts of a finished poll into `archive_path`: the
+/// public poll configuration, the public poll data files of the data
+/// directory, the Merkle tree and the transaction cache, if they exist, and
+/// the detached signatures of all of them.
+pub fn export_public_archive(
+    public_configuration: &PublicPollConfiguration,
+    datadir_path: &Path,
+    merkle_tree: &Path,
+    transactions: &Path,
+    archive_path: &Path,
+    signing_key: &Base64String) -> Result<Manifest>
+{
+    let root = poll_directory_name(&public_configuration.poll_identifier);
+    let mut sources: Vec<(String, std::path::PathBuf)> = Vec::new();
+    for entry in fs::read_dir(datadir_path)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if entry.file_type()?.is_file() && is_public_artifact(&name) {
+            sources.push((name, entry.path()));
+        }
+    }
+    for (name, source) in [(MERKLE_TREE_FILENAME, merkle_tree), (TRANSACTIONS_FILENAME, transactions)].iter() {
+        if source.exists() {
+            sources.push((name.to_string(), source.to_path_buf()));
+        }
+    }
+    sources.sort();
+
+    let archived_at = Utc::now().timestamp() as u64;
+    let mut files: Vec<(String, u64, Vec<u8>)> = vec![(
+        format!("{}/{}", root, PUBLIC_POLL_CONFIGURATION_FILENAME),
+        archived_at,
+        serde_yaml::to_string(public_configuration)?.into_bytes())];
+    for (name, source) in sources {
+        if is_signed_artifact(&name) {
+            check_artifact(&source)?;
+        }
+        files.push((format!("{}/{}", root, name), archived_at, fs::read(&source)?));
+        let signature = signature_path(&source);
+        if signature.exists() {
+            files.push((format!("{}/{}.sig", root, name), archived_at, fs::read(&signature)?));
+        }
+    }
+    let public_key = Base64String(public_configuration.public_key.clone());
+    write_signed_archive(&public_configuration.poll_identifier, files, archive_path, signing_key, &public_key)
+}
+
+/// Check every file of a poll archive against its signed manifest. Files of
+/// the archive that the manifest does not list are reported as invalid.
+pub fn verify_poll_archive(archive_path: &Path, public_key: Option<&str>) -> Result<BoardVerification> {
+    let files = read_archive(archive_path)?;
+    verify_archive_files(archive_path, &files, public_key)
+}
+
+/// Check a public archive against its signed manifest, and its tally report
+/// against its finalization receipt, without the blockchain. A public
+/// archive without a finalization receipt is not valid.
+pub fn verify_public_archive(archive_path: &Path, public_key: Option<&str>) -> Result<BoardVerification> {
+    let files = read_archive(archive_path)?;
+    verify_public_archive_files(archive_path, &files, public_key)
+}
+
+/// Check a public archive against the trusted verification key
+/// `public_key` and, if it is valid, extract the files it was checked with
+/// and its signed manifest into the directory `dir`. An archive is never
+/// imported on the strength of the key its own manifest carries.
+pub fn import_public_archive(archive_path: &Path, dir: &Path, public_key: &str) -> Result<BoardVerification> {
+    let files = read_archive(archive_path)?;
+    let verification = verify_public_archive_files(archive_path, &files, Some(public_key))?;
+    if !verification.valid() {
+        return Ok(verification);
+    }
+
+    for name in files.keys() {
+        let path = Path::new(name);
+        if !path.components().all(|component| matches!(component, Component::Normal(_))) {
+            return Err(format!("Archived file {} would be extracted outside of {}.", name, dir.display()).into());
+        }
+    }
+    for (name, data) in files.iter() {
+        let path = dir.join(name);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, data)?;
+    }
+    Ok(verification)
+}
+
+// Sign the files and write them to a gzipped tarball after the signed
+// manifest.
+fn write_signed_archive(
+    poll_identifier: &str,
+    files: Vec<(String, u64, Vec<u8>)>,
+    archive_path: &Path,
+    signing_key: &Base64String,
+    public_key: &Base64String) -> Result<Manifest>
+{
+    let mut entries: Vec<(String, u64, Vec<u8>)> = Vec::new();
+    let mut artifacts: Vec<Artifact> = Vec::new();
+    for (name, modified, data) in files {
+        let (data, signature) = sign(signing_key, data)?;
+        artifacts.push(Artifact {
+            name: name.clone(),
+            size: data.len() as u64,
+            sha256: sha256_hex(&data),
+            signature: base64::encode(&signature)
+        });
+        entries.push((name, modified, data));
+    }
+
+    let manifest = Manifest {
+        poll_identifier: poll_identifier.to_owned(),
+        public_key: public_key.0.clone(),
+        published_at: Utc::now(),
+        artifacts
+    };
+    let serialized_manifest = serde_yaml::to_string(&manifest)?;
+    let (serialized_manifest, signature) = sign(signing_key, serialized_manifest.into_bytes())?;
+    let archived_at = manifest.published_at.timestamp() as u64;
+    entries.insert(0, (MANIFEST_SIGNATURE_FILENAME.to_owned(), archived_at, base64::encode(&signature).into_bytes()));
+    entries.insert(0, (MANIFEST_FILENAME.to_owned(), archived_at, serialized_manifest));
+
+    let mut builder = Builder::new(GzEncoder::new(fs::File::create(archive_path)?, Compression::default()));
+    for (name, modified, data) in entries.iter() {
+        let mut header = Header::new_ustar();
+        header.set_entry_type(EntryType::Regular);
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        header.set_mtime(*modified);
+        builder.append_data(&mut header, name, data.as_slice())?;
+    }
+    builder.into_inner()?.finish()?;
+    Ok(manifest)
+}
+
+fn read_archive(archive_path: &Path) -> Result<BTreeMap<String, Vec<u8>>> {
+    read_tar(GzDecoder::new(fs::File::open(archive_path)?))
+}
+
+// Check the files of an archive against its manifest.
+fn verify_archive_files(archive_path: &Path, files: &BTreeMap<String, Vec<u8>>, public_key: Option<&str>) -> Result<BoardVerification> {
+    let manifest = files.get(MANIFEST_FILENAME)
+        .ok_or_else(|| format!("No manifest in {}", archive_path.display()))?;
+    let signature = files.get(MANIFEST_SIGNATURE_FILENAME)
+        .ok_or_else(|| format!("No manifest signature in {}", archive_path.display()))?;
+    let (manifest, manifest_valid) = read_manifest(manifest, signature, public_key)?;
+
+    let verification_key = Base64String(manifest.public_key.clone());
+    let mut invalid_artifacts: Vec<String> = manifest.artifacts.iter()
+        .filter(|artifact| match files.get(&artifact.name) {
+            Some(data) => !verify_artifact(&verification_key, artifact, data),
+            None => true
+        })
+        .map(|artifact| artifact.name.clone())
+        .collect();
+    invalid_artifacts.extend(files.keys()
+        .filter(|name| *name != MANIFEST_FILENAME && *name != MANIFEST_SIGNATURE_FILENAME)
+        .filter(|name| manifest.artifact(name).is_none())
+        .cloned());
+
+    Ok(BoardVerification {
+        poll_identifier: manifest.poll_identifier,
+        manifest_valid,
+        artifacts: manifest.artifacts.len(),
+        invalid_artifacts
+    })
+}
+
+// Check the files of a public archive against its manifest, and its tally
+// report against its finalization receipt.
+fn verify_public_archive_files(archive_path: &Path, files: &BTreeMap<String, Vec<u8>>, public_key: Option<&str>) -> Result<BoardVerification> {
+    let mut verification = verify_archive_files(archive_path, files, public_key)?;
+
+    let root = poll_directory_name(&verification.poll_identifier);
+    let tally_name = format!("{}/tally.yaml", root);
+    let receipt_name = format!("{}/finalization_receipt.yaml", root);
+    let receipt: Option<FinalizationReceipt> = files.get(&receipt_name)
+        .and_then(|receipt| serde_yaml::from_slice(receipt).ok());
+    match (receipt, files.get(&tally_name)) {
+        (Some(receipt), Some(tally)) => {
+            if hex::encode(tally_hash(tally)) != receipt.tally_hash {
+                verification.invalid_artifacts.push(tally_name);
+            }
+        },
+        (Some(_), None) => verification.invalid_artifacts.push(tally_name),
+        (None, _) => verification.invalid_artifacts.push(receipt_name)
+    }
+    verification.invalid_artifacts.sort();
+    verification.invalid_artifacts.dedup();
+    Ok(verification)
+}
+
+// Collect the regular files below `dir`, named under `prefix`, with their
+// modification time.
+fn collect_files(dir: &Path, prefix: &str, files: &mut Vec<(String, u64)>) -> Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let entry = entry?;
+        let name = format!("{}/{}", prefix, entry.file_name().to_string_lossy());
+        let metadata = fs::symlink_metadata(entry.path())?;
+        if metadata.is_dir() {
+            collect_files(&entry.path(), &name, files)?;
+        } else if metadata.is_file() && !name.ends_with(".tar.gz") {
+            let modified = metadata.modified()?.duration_since(UNIX_EPOCH).map(|time| time.as_secs()).unwrap_or(0);
+            files.push((name, modified));
+        }
+    }
+    Ok(())
+}
+
+// Read the regular files of a tarball, by name.
+fn read_tar<R: Read>(reader: R) -> Result<BTreeMap<String, Vec<u8>>> {
+    let mut files = BTreeMap::new();
+    let mut length = 0u64;
+    for (n, entry) in Archive::new(reader).entries()?.enumerate() {
+        if MAX_ARCHIVE_ENTRIES <= n {
+            return Err(format!("Corrupted archive: more than {} entries.", MAX_ARCHIVE_ENTRIES).into());
+        }
+        let entry = entry?;
+        if !entry.header().entry_type().is_file() {
+            continue;
+        }
+        let name = entry.path()?.to_string_lossy().into_owned();
+        let size = entry.header().size()?;
+        if MAX_ARCHIVED_FILE_LENGTH < size {
+            return Err(format!("Corrupted archive: {} exceeds {} bytes.", name, MAX_ARCHIVED_FILE_LENGTH).into());
+        }
+        length += size;
+        if MAX_ARCHIVE_LENGTH < length {
+            return Err(format!("Corrupted archive: files exceed {} bytes in all.", MAX_ARCHIVE_LENGTH).into());
+        }
+        let mut data = Vec::new();
+        entry.take(size).read_to_end(&mut data)?;
+        if (data.len() as u64) < size {
+            return Err("Corrupted archive: truncated file.".into());
+        }
+        if files.insert(name.clone(), data).is_some() {
+            return Err(format!("Corrupted archive: {} is archived twice.", name).into());
+        }
+    }
+    Ok(files)
+}
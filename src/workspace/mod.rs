@@ -0,0 +1,224 @@
+//! # Poll Workspace
+//!
+//! A workspace is a directory holding one directory per poll, with its
+//! secured poll configuration, its data directory, receipts, transaction
+//! caches and Merkle trees, so that the files of several polls run by the
+//! same authority do not mix. `workspace.yaml` lists the polls of the
+//! workspace and the active one, which the commands run against when no
+//! poll configuration is given.
+//!
+//! A completed poll is archived into a single tarball, signed with the poll
+//! signing key, that can be checked with `verify_poll_archive`.
+
+use std::fmt;
+use std::fs::{self, File};
+use std::path::{Path, PathBuf};
+use serde::{Serialize, Deserialize};
+use crate::Result;
+use crate::poll_configuration::SecuredPollConfiguration;
+
+pub mod archive;
+pub use archive::*;
+
+pub const WORKSPACE_FILENAME: &str = "workspace.yaml";
+
+/// Progress of a poll, as found from its receipts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkspacePollStatus {
+    Created,
+    Committed,
+    Finalized,
+    Archived
+}
+
+impl fmt::Display for WorkspacePollStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            WorkspacePollStatus::Created => write!(f, "created"),
+            WorkspacePollStatus::Committed => write!(f, "committed"),
+            WorkspacePollStatus::Finalized => write!(f, "finalized"),
+            WorkspacePollStatus::Archived => write!(f, "archived")
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WorkspacePoll {
+    /// Directory of the poll in the workspace.
+    pub name: String,
+    pub identifier: String,
+    /// Secured poll configuration file in the poll directory.
+    pub config: String,
+    /// Archive of the poll, once archived.
+    #[serde(default)]
+    pub archive: Option<String>
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Workspace {
+    #[serde(default)]
+    pub active: Option<String>,
+    #[serde(default)]
+    pub polls: Vec<WorkspacePoll>
+}
+
+impl Workspace {
+    /// Read the workspace of the directory `workspace_dir`.
+    pub fn open(workspace_dir: &Path) -> Result<Self> {
+        let workspace_path = workspace_dir.join(WORKSPACE_FILENAME);
+        let workspace_file = File::open(&workspace_path)
+            .map_err(|_| format!("No workspace in {}: run `workspace init` first.", workspace_dir.display()))?;
+        Ok(serde_yaml::from_reader(workspace_file)?)
+    }
+
+    pub fn save(&self, workspace_dir: &Path) -> Result<()> {
+        serde_yaml::to_writer(File::create(workspace_dir.join(WORKSPACE_FILENAME))?, self)?;
+        Ok(())
+    }
+
+    /// Poll named `poll`, by directory or identifier.
+    pub fn poll(&self, poll: &str) -> Result<&WorkspacePoll> {
+        self.polls.iter()
+            .find(|entry| entry.name == poll)
+            .or_else(|| self.polls.iter().find(|entry| entry.identifier == poll))
+            .ok_or_else(|| format!("No poll {} in the workspace.", poll).into())
+    }
+
+    /// Poll named `poll`, or the active poll.
+    pub fn poll_or_active(&self, poll: Option<&str>) -> Result<&WorkspacePoll> {
+        match poll.or(self.active.as_deref()) {
+            Some(poll) => self.poll(poll),
+            None => Err("No active poll in the workspace: run `workspace use` first.".into())
+        }
+    }
+
+    /// Register the poll with the secured poll configuration `config` in the
+    /// poll directory `name`, and make it the active poll.
+    pub fn add(&mut self, workspace_dir: &Path, name: &str, config: &str) -> Result<&WorkspacePoll> {
+        if self.polls.iter().any(|entry| entry.name == name) {
+            return Err(format!("The workspace already has a poll {}.", name).into());
+        }
+        let secured_poll_configuration: SecuredPollConfiguration = serde_yaml::from_reader(
+            File::open(workspace_dir.join(name).join(config))?)?;
+        let identifier = read_poll_identifier(&secured_poll_configuration)?;
+        if self.polls.iter().any(|entry| entry.identifier == identifier) {
+            return Err(format!("The workspace already has a poll {}.", identifier).into());
+        }
+        self.polls.push(WorkspacePoll {
+            name: name.to_owned(),
+            identifier,
+            config: config.to_owned(),
+            archive: None
+        });
+        self.active = Some(name.to_owned());
+        Ok(&self.polls[self.polls.len() - 1])
+    }
+
+    /// Make the poll named `poll` the active poll.
+    pub fn activate(&mut self, poll: &str) -> Result<&WorkspacePoll> {
+        let name = self.poll(poll)?.name.clone();
+        self.active = Some(name);
+        self.poll_or_active(None)
+    }
+
+    pub fn listing(&self, workspace_dir: &Path) -> WorkspaceListing {
+        let polls = self.polls.iter()
+            .map(|entry| WorkspaceListingEntry {
+                name: entry.name.clone(),
+                identifier: entry.identifier.clone(),
+                active: self.active.as_deref() == Some(entry.name.as_str()),
+                status: entry.status(workspace_dir)
+            })
+            .collect();
+        WorkspaceListing { polls }
+    }
+}
+
+impl WorkspacePoll {
+    pub fn directory(&self, workspace_dir: &Path) -> PathBuf {
+        workspace_dir.join(&self.name)
+    }
+
+    /// Data directory of the poll.
+    pub fn datadir(&self, workspace_dir: &Path) -> PathBuf {
+        self.directory(workspace_dir).join(&self.identifier)
+    }
+
+    pub fn status(&self, workspace_dir: &Path) -> WorkspacePollStatus {
+        let datadir = self.datadir(workspace_dir);
+        if self.archive.is_some() {
+            WorkspacePollStatus::Archived
+        } else if datadir.join("finalization_receipt.yaml").exists() {
+            WorkspacePollStatus::Finalized
+        } else if datadir.join("commit_receipt.yaml").exists() {
+            WorkspacePollStatus::Committed
+        } else {
+            WorkspacePollStatus::Created
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceListingEntry {
+    pub name: String,
+    pub identifier: String,
+    pub active: bool,
+    pub status: WorkspacePollStatus
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkspaceListing {
+    pub polls: Vec<WorkspaceListingEntry>
+}
+
+impl fmt::Display for WorkspaceListing {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.polls.is_empty() {
+            return write!(f, "No polls in the workspace.");
+        }
+        let width = self.polls.iter().map(|entry| entry.name.len()).max().unwrap_or(0);
+        for (index, entry) in self.polls.iter().enumerate() {
+            if index > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{} {:width$}  {:9}  {}",
+                if entry.active { "*" } else { " " },
+                entry.name, entry.status.to_string(), entry.identifier, width = width)?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for WorkspacePoll {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Poll:   {}", self.identifier)?;
+        writeln!(f, "Dir:    {}", self.name)?;
+        write!(f, "Config: {}", self.config)
+    }
+}
+
+/// Poll identifier of a secured poll configuration, read without the Poll
+/// Master Key and therefore not authenticated.
+pub fn read_poll_identifier(secured_poll_configuration: &SecuredPollConfiguration) -> Result<String> {
+    Ok(String::from_utf8(secured_poll_configuration.poll_identifier.values()?.aad)?)
+}
+
+/// Directory name of a poll in a workspace.
+pub fn poll_directory_name(identifier: &str) -> String {
+    let name: String = identifier.chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c.to_ascii_lowercase() } else { '-' })
+        .collect();
+    name.split('-').filter(|part| !part.is_empty()).collect::<Vec<_>>().join("-")
+}
+
+/// Create an empty workspace in `workspace_dir`.
+pub fn init_workspace(workspace_dir: &Path) -> Result<Workspace> {
+    fs::create_dir_all(workspace_dir)?;
+    if workspace_dir.join(WORKSPACE_FILENAME).exists() {
+        return Err(format!("{} already is a workspace.", workspace_dir.display()).into());
+    }
+    let workspace = Workspace::default();
+    workspace.save(workspace_dir)?;
+    Ok(workspace)
+}
@@ -0,0 +1,74 @@
+use std::fs;
+use seventh_estate::bulletin_board::*;
+use seventh_estate::cryptography::*;
+
+#[test]
+fn test_public_artifacts() {
+    assert!(is_public_artifact("committed_roster.csv"));
+    assert!(is_public_artifact("committed_plane_07.csv"));
+    assert!(is_public_artifact("final_plane_50_keys.csv"));
+    assert!(!is_public_artifact("final_plane_.csv"));
+    assert!(!is_public_artifact("final_plane_01.yaml"));
+    assert!(!is_public_artifact("../committed_roster.csv"));
+    assert!(!is_public_artifact("example.yaml.secure"));
+}
+
+#[test]
+fn test_publish_verify_board() {
+    let root = std::env::temp_dir().join(format!("seventh-estate-board-{}", std::process::id()));
+    let datadir = root.join("Example Poll");
+    let board = root.join("board");
+    fs::create_dir_all(&datadir).unwrap();
+    fs::write(datadir.join("committed_roster.csv"), "roster").unwrap();
+    fs::write(datadir.join("print_audit_plane_01_keys.csv"), "keys").unwrap();
+    fs::write(datadir.join("notes.txt"), "private").unwrap();
+
+    let (signing_key, public_key) = new_signing_key().unwrap();
+    let manifest = publish_board_directory("Example Poll", &datadir, &root.join("merkle.yaml"), &board, &signing_key, &public_key).unwrap();
+    let names: Vec<&str> = manifest.artifacts.iter().map(|artifact| artifact.name.as_str()).collect();
+    assert_eq!(vec!["committed_roster.csv", "print_audit_plane_01_keys.csv"], names);
+    assert!(!board.join("notes.txt").exists());
+
+    let verification = verify_board_directory(&board, Some(&public_key.0)).unwrap();
+    assert!(verification.valid());
+
+    // Another verification key, a tampered artifact
+    let (_, other_key) = new_signing_key().unwrap();
+    assert!(!verify_board_directory(&board, Some(&other_key.0)).unwrap().manifest_valid);
+    fs::write(board.join("committed_roster.csv"), "tampered").unwrap();
+    let verification = verify_board_directory(&board, None).unwrap();
+    assert!(verification.manifest_valid);
+    assert_eq!(vec!["committed_roster.csv".to_owned()], verification.invalid_artifacts);
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_signed_artifacts() {
+    let root = std::env::temp_dir().join(format!("seventh-estate-signed-{}", std::process::id()));
+    let datadir = root.join("Example Poll");
+    fs::create_dir_all(&datadir).unwrap();
+    let tally = datadir.join("tally.yaml");
+    fs::write(&tally, "tally").unwrap();
+    assert!(is_signed_artifact("merkle.yaml"));
+    assert!(is_signed_artifact("final_plane_03_keys.csv"));
+    assert!(!is_signed_artifact("committed_roster.csv"));
+
+    let (signing_key, public_key) = new_signing_key().unwrap();
+    assert!(verify_file(&public_key, &tally).is_err());
+    sign_file(&signing_key, &tally).unwrap();
+    assert!(signature_path(&tally).ends_with("tally.yaml.sig"));
+    verify_file(&public_key, &tally).unwrap();
+    let (_, other_key) = new_signing_key().unwrap();
+    assert!(verify_file(&other_key, &tally).is_err());
+
+    // Signed artifacts are checked before they are published
+    set_verification_key(public_key.clone());
+    check_artifact(&tally).unwrap();
+    fs::write(&tally, "tampered").unwrap();
+    assert!(check_artifact(&tally).is_err());
+    let board = root.join("board");
+    assert!(publish_board_directory("Example Poll", &datadir, &root.join("merkle.yaml"), &board, &signing_key, &public_key).is_err());
+
+    fs::remove_dir_all(&root).unwrap();
+}
@@ -0,0 +1,31 @@
+#![cfg(feature = "email")]
+
+use std::collections::HashMap;
+use seventh_estate::email::*;
+
+#[test]
+fn test_email_templates() {
+    for kind in EMAIL_KINDS.iter() {
+        let kind: EmailKind = kind.parse().unwrap();
+        assert!(EmailTemplate::default_for(kind).validate(kind).is_ok());
+    }
+
+    // Vote codes are only available in ballots.
+    let template = EmailTemplate {
+        subject: "{poll}: ballot {serial}".to_owned(),
+        body: "{first_name}, vote {votecode1} for {choice1}.".to_owned()
+    };
+    assert!(template.validate(EmailKind::Ballot).is_ok());
+    assert!(template.validate(EmailKind::Receipt).is_err());
+    assert!(template.validate(EmailKind::Notification).is_err());
+
+    let mut values: HashMap<&str, String> = HashMap::new();
+    values.insert("poll", "Example Poll".to_owned());
+    values.insert("serial", "07".to_owned());
+    values.insert("first_name", "Jane".to_owned());
+    values.insert("votecode1", "12340-56784-11116-00000".to_owned());
+    values.insert("choice1", "For".to_owned());
+    let (subject, body) = template.render(&values);
+    assert_eq!("Example Poll: ballot 07", subject);
+    assert_eq!("Jane, vote 12340-56784-11116-00000 for For.", body);
+}
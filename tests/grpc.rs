@@ -0,0 +1,27 @@
+#![cfg(feature = "grpc")]
+
+use std::sync::Arc;
+use tonic::Code;
+use seventh_estate::ballots::VoteCodeMap;
+use seventh_estate::cryptography::AEADKey;
+use seventh_estate::server::*;
+use seventh_estate::server::proto::*;
+use seventh_estate::server::proto::registrar_client::RegistrarClient;
+
+// No vote reaches a node: no network configuration is read.
+const NETWORK: &str = "test";
+
+#[tokio::test]
+async fn test_registrar_errors() {
+    let server = RelayServer::new(NETWORK, "Example Poll", "example.yaml.secure", AEADKey([0; 32]), "missing_merkle.yaml", VoteCodeMap::new());
+    tokio::spawn(async move { serve_grpc(Arc::new(server), "127.0.0.1:50151".parse().unwrap()).await.ok(); });
+    tokio::time::delay_for(std::time::Duration::from_millis(200)).await;
+
+    let mut client = RegistrarClient::connect("http://127.0.0.1:50151").await.unwrap();
+    let tally = client.get_tally(TallyRequest {}).await.unwrap_err();
+    assert_eq!(Code::NotFound, tally.code());
+    let proof = client.get_proof(ProofRequest { data: String::from("Voted") }).await.unwrap_err();
+    assert_eq!(Code::NotFound, proof.code());
+    let roster = client.import_roster(ImportRosterRequest { roster: None, disable_privacy: false, force: false }).await.unwrap_err();
+    assert_eq!(Code::InvalidArgument, roster.code());
+}
@@ -0,0 +1,39 @@
+use std::fs;
+use seventh_estate::simulation::*;
+use seventh_estate::votes::{BallotVotes, TallyRules};
+
+#[test]
+fn test_logic_accuracy() {
+    let root = std::env::temp_dir().join(format!("seventh-estate-logic-accuracy-{}", std::process::id()));
+    let configuration = LogicAccuracyConfiguration {
+        num_ballots: 12,
+        num_decoys: 2,
+        tally_rules: TallyRules::default(),
+        seed: Some("00".repeat(32))
+    };
+    let report = run_logic_accuracy(&configuration, &root.join("default")).unwrap();
+    assert!(report.passed, "{}", report);
+    // Two ballots of every case, both choices of a ballot voted twice counted.
+    assert_eq!(12, report.expected.total());
+    assert_eq!(2, report.expected_duplicates);
+    assert_eq!(0, report.expected_uncounted);
+    assert_eq!(2 * 8, report.submissions);
+    let deck = fs::read_to_string(root.join("default").join("test_deck.csv")).unwrap();
+    assert_eq!(13, deck.lines().count());
+    assert!(deck.contains(",both_choices,"));
+
+    // Ballots voted with both vote codes are cancelled by the tally rules.
+    let cancelling = LogicAccuracyConfiguration {
+        tally_rules: TallyRules { ballot_votes: BallotVotes::Cancel, ..TallyRules::default() },
+        ..configuration.clone()
+    };
+    let report = run_logic_accuracy(&cancelling, &root.join("cancel")).unwrap();
+    assert!(report.passed, "{}", report);
+    assert_eq!(8, report.expected.total());
+    assert_eq!(4, report.uncounted_recorded);
+    fs::remove_dir_all(&root).unwrap();
+
+    assert_eq!(DeckCase::For, deck_case(6));
+    let invalid = LogicAccuracyConfiguration { num_decoys: 13, ..configuration };
+    assert!(run_logic_accuracy(&invalid, &root).is_err());
+}
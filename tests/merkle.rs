@@ -120,4 +120,130 @@ fn test_store_load_tree() {
 
     // Delete test file
     fs::remove_file(filename).unwrap();
-}
\ No newline at end of file
+}
+#[test]
+fn test_mapped_tree() {
+    let data = vec![
+        "Colombier,Gerri,7 Del Sol Lane,Philadelphia,PA,19160"                                                    .to_string(),
+        "64: 86961-67106-91541-74973"                                                                             .to_string(),
+        "Not Voted"                                                                                               .to_string(),
+        "13, 20, 35, 43, 58, 69, 73, 77, 81, 88, 93, 96"                                                          .to_string(),
+        "More Data"                                                                                               .to_string(),
+    ];
+    let filename = "test_mapped_merkle.yaml";
+
+    let mut leaves = CryptoHashData::new(data.clone());
+    leaves.pad();
+    let t = new_tree(leaves).unwrap();
+    store_tree(&t, String::from(filename)).unwrap();
+
+    // The mapped tree gives the proofs of the loaded tree.
+    let mapped = MappedTree::open(filename).unwrap();
+    assert_eq!(8, mapped.leafs());
+    assert_eq!(t.root(), mapped.root().unwrap());
+    for d in data.iter() {
+        let p = mapped.proof(d).unwrap().unwrap();
+        let expected = get_path(load_tree(String::from(filename)).unwrap(), d.clone()).unwrap();
+        assert_eq!(expected.lemma(), p.lemma());
+        assert_eq!(expected.path(), p.path());
+    }
+    assert!(mapped.proof("Data not present").unwrap().is_none());
+
+    // A truncated tree is rejected.
+    let stored = fs::read(filename).unwrap();
+    fs::write(filename, &stored[..stored.len() - 10]).unwrap();
+    assert!(MappedTree::open(filename).is_err());
+    fs::remove_file(filename).unwrap();
+}
+
+#[test]
+fn test_proof_index() {
+    use std::time::Duration;
+    use seventh_estate::blockchain::{build_proof_index, proof_index_path, ProofIndex};
+    use seventh_estate::subcommands::find_inclusion_proof;
+
+    let data: Vec<String> = (0..37).map(|i| format!("Leaf {}", i)).collect();
+    let filename = "test_indexed_merkle.yaml";
+    let index_path = proof_index_path(Path::new(filename));
+    assert_eq!(Path::new("test_indexed_merkle.proofs"), index_path);
+
+    let mut leaves = CryptoHashData::new(data.clone());
+    leaves.pad();
+    let t = new_tree(leaves).unwrap();
+    store_tree(&t, String::from(filename)).unwrap();
+    let mapped = MappedTree::open(filename).unwrap();
+    assert!(ProofIndex::open_current(&index_path, &mapped).unwrap().is_none());
+
+    // The index gives the proofs of the mapped tree, padding included.
+    let index = build_proof_index(Path::new(filename), &index_path, Duration::from_millis(0)).unwrap();
+    assert_eq!(64, index.leafs());
+    assert_eq!(t.root(), index.root());
+    for d in data.iter().chain(std::iter::once(&String::from("\0"))) {
+        let expected = mapped.proof(d).unwrap().unwrap();
+        let p = index.proof(d).unwrap().unwrap();
+        assert_eq!(expected.lemma(), p.lemma());
+        assert_eq!(expected.path(), p.path());
+    }
+    assert!(index.proof("Data not present").unwrap().is_none());
+    assert!(ProofIndex::open_current(&index_path, &mapped).unwrap().is_some());
+    let proof = find_inclusion_proof(filename, &data[5]).unwrap().unwrap();
+    assert!(validate(proof.lemma, proof.path, proof.data).unwrap());
+
+    // The index of a tree stored again is not current.
+    let mut leaves = CryptoHashData::new(data[1..].to_vec());
+    leaves.pad();
+    store_tree(&new_tree(leaves).unwrap(), String::from(filename)).unwrap();
+    let mapped = MappedTree::open(filename).unwrap();
+    assert!(ProofIndex::open_current(&index_path, &mapped).unwrap().is_none());
+    assert!(find_inclusion_proof(filename, &data[0]).unwrap().is_none());
+
+    // A truncated index is rejected.
+    let stored = fs::read(&index_path).unwrap();
+    fs::write(&index_path, &stored[..stored.len() - 10]).unwrap();
+    assert!(ProofIndex::open(&index_path).is_err());
+    fs::remove_file(&index_path).unwrap();
+    fs::remove_file(filename).unwrap();
+}
+
+#[test]
+fn test_check_print_file() {
+    use std::collections::HashSet;
+    use seventh_estate::ballots::*;
+    use seventh_estate::cryptography::CSPRNGSeed;
+    use seventh_estate::secrets::PollSecrets;
+    use seventh_estate::subcommands::{check_print_file, generate_column_planes, CompleteBallotRow};
+
+    let (num_ballots, num_decoys) = (8, 2);
+    let secrets = PollSecrets::from_seed(CSPRNGSeed::from_vec(&vec![7u8; CSPRNGSeed::SIZE]));
+    let planes = generate_column_planes(&secrets, secrets.plane_secrets.len(), 2 * num_ballots, num_decoys).unwrap();
+    let leaves: HashSet<CryptoSHA3256Hash> = planes.iter()
+        .flat_map(|plane| plane.rows.iter())
+        .map(|row| row.serializable(num_ballots))
+        .flat_map(|row| vec![leaf_hash(&row.col1), leaf_hash(&row.col3)])
+        .collect();
+
+    let serials: Vec<BallotSerial> = (0..num_ballots).collect();
+    let ballots = generate_ballots(&serials, &generate_votecodes(&secrets.votecode_root, 2 * num_ballots));
+    let mut rows: Vec<CompleteBallotRow> = ballots.iter()
+        .map(|ballot| CompleteBallotRow {
+            serial: string_from_ballotserial(&ballot.serial, num_ballots),
+            choice1_votecode: string_from_votecode(&ballot.choice1.votecode),
+            choice1_value: string_from_choicevalue(&ballot.choice1.choice),
+            choice2_votecode: string_from_votecode(&ballot.choice2.votecode),
+            choice2_value: string_from_choicevalue(&ballot.choice2.choice)
+        })
+        .collect();
+    let check = check_print_file(&rows, &secrets, num_ballots, num_decoys, &leaves);
+    assert!(check.valid());
+    assert_eq!(num_ballots, check.ballots);
+
+    // Swapped vote codes and a missing ballot are detected
+    let swapped = rows[3].choice1_votecode.clone();
+    rows[3].choice1_votecode = rows[3].choice2_votecode.clone();
+    rows[3].choice2_votecode = swapped;
+    rows.pop();
+    let check = check_print_file(&rows, &secrets, num_ballots, num_decoys, &leaves);
+    assert!(!check.valid());
+    assert_eq!(vec![5, 5], check.mismatches.iter().map(|mismatch| mismatch.line).collect::<Vec<usize>>());
+    assert_eq!(1, check.missing_ballots);
+}
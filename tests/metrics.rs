@@ -0,0 +1,25 @@
+use std::time::Duration;
+use seventh_estate::metrics::*;
+
+#[test]
+fn test_render_metrics() {
+    set_votes_counted(12);
+    set_last_confirmed_block(42);
+    inc_invalid_payloads();
+    record_rpc(Duration::from_millis(200), true);
+    record_rpc(Duration::from_secs(20), false);
+    inc_rpc_failovers();
+
+    let rendered = render();
+    assert!(rendered.contains("# TYPE seventh_estate_votes_counted gauge\nseventh_estate_votes_counted 12\n"));
+    assert!(rendered.contains("seventh_estate_invalid_payloads_total 1\n"));
+    assert!(rendered.contains("seventh_estate_rpc_requests_total 2\n"));
+    assert!(rendered.contains("seventh_estate_rpc_errors_total 1\n"));
+    assert!(rendered.contains("seventh_estate_rpc_failovers_total 1\n"));
+    assert!(rendered.contains("seventh_estate_rpc_latency_seconds_bucket{le=\"0.1\"} 0\n"));
+    assert!(rendered.contains("seventh_estate_rpc_latency_seconds_bucket{le=\"0.25\"} 1\n"));
+    assert!(rendered.contains("seventh_estate_rpc_latency_seconds_bucket{le=\"10\"} 1\n"));
+    assert!(rendered.contains("seventh_estate_rpc_latency_seconds_bucket{le=\"+Inf\"} 2\n"));
+    assert!(rendered.contains("seventh_estate_rpc_latency_seconds_sum 20.2\n"));
+    assert!(rendered.contains("seventh_estate_last_confirmed_block 42\n"));
+}
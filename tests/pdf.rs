@@ -1,21 +1,14 @@
 use seventh_estate::ballots::*;
-use hex;
+use seventh_estate::poll_configuration::LocalizedBallotText;
 use std::path::Path;
 use std::fs;
 use mime_guess;
 use mime::APPLICATION_PDF;
 
-#[macro_use] extern crate slice_as_array;
-
-fn slice_as_hash(xs: &[u8]) -> &[u8; 20] {
-    slice_as_array!(xs, [u8; 20]).expect("bad hash length")
-}
-
 #[test]
 fn test_pdf() {
 
-    let vote1 = hex::decode("b28de6131ecdd6075b1473ca6525c0bf990fde7f").unwrap();
-    let vote1 = *slice_as_hash(&vote1);
+    let vote1 = VoteCode::parse("12340-56784-11116-00019").unwrap();
     
     let choice1: BallotChoice = BallotChoice {
         serial: 123456,
@@ -48,4 +41,155 @@ fn test_pdf() {
 
     // Delete test file
     fs::remove_file(filename).unwrap();
-}
\ No newline at end of file
+}
+
+#[test]
+fn test_ballots_pdf() {
+
+    let vote1 = VoteCode::parse("12340-56784-11116-00019").unwrap();
+
+    let ballots: Vec<Ballot> = (654321..654324).map(|serial| Ballot {
+        serial,
+        choice1: BallotChoice { serial, votecode: vote1, choice: ChoiceValue::For },
+        choice2: BallotChoice { serial, votecode: vote1, choice: ChoiceValue::Against }
+    }).collect();
+
+    let layout = BallotLayout {
+        page_size: PageSize::Letter,
+        fold_lines: 2,
+        scratch_off_markers: true,
+        qr_codes: true,
+        ..BallotLayout::default()
+    };
+    let text = BallotText {
+        poll_identifier: Some("Example Poll"),
+        question: Some("Should the example poll pass?"),
+        ..BallotText::default()
+    };
+
+    let filename = std::env::temp_dir().join("7e-test-ballots.pdf");
+    print_ballots(&ballots, &text, &layout, &filename).unwrap();
+    assert!(filename.exists());
+
+    let filetype = mime_guess::from_path(&filename);
+    assert_eq!(Some(APPLICATION_PDF), filetype.first());
+
+    fs::remove_file(filename).unwrap();
+}
+
+#[test]
+fn test_localized_ballots_pdf() {
+
+    let serial = 654321;
+    let vote1 = VoteCode::parse("12340-56784-11116-00019").unwrap();
+    let ballots = vec![Ballot {
+        serial,
+        choice1: BallotChoice { serial, votecode: vote1, choice: ChoiceValue::For },
+        choice2: BallotChoice { serial, votecode: vote1, choice: ChoiceValue::Against }
+    }];
+
+    let localized = vec![
+        LocalizedBallotText {
+            locale: "en".to_owned(),
+            question: "Should the example poll pass?".to_owned(),
+            choice_for: "Yes".to_owned(),
+            choice_against: "No".to_owned()
+        },
+        LocalizedBallotText {
+            locale: "fr".to_owned(),
+            question: "Le sondage d'exemple doit-il être adopté ?".to_owned(),
+            choice_for: "Oui".to_owned(),
+            choice_against: "Non".to_owned()
+        }
+    ];
+    let text = BallotText {
+        poll_identifier: Some("Example Poll"),
+        question: Some("Not printed"),
+        localized: &localized
+    };
+
+    // The localized text takes precedence over the question, in every
+    // locale.
+    let html = ballot_html(&ballots[0], &text);
+    assert!(html.contains("<th scope=\"row\">Yes / Oui</th>"));
+    assert!(html.contains("<th scope=\"row\">No / Non</th>"));
+    assert!(html.contains("Le sondage d'exemple doit-il être adopté ?"));
+    assert!(!html.contains("Not printed"));
+
+    let filename = std::env::temp_dir().join("7e-test-localized-ballots.pdf");
+    print_ballots(&ballots, &text, &BallotLayout::default(), &filename).unwrap();
+    assert_eq!(Some(APPLICATION_PDF), mime_guess::from_path(&filename).first());
+    assert!(fs::read(&filename).unwrap().starts_with(b"%PDF"));
+    fs::remove_file(&filename).unwrap();
+
+    // The ballot text is printed with the font of the layout.
+    let layout = BallotLayout { font: Some("missing-font.ttf".to_owned()), ..BallotLayout::default() };
+    assert!(print_ballots(&ballots, &text, &layout, &filename).is_err());
+}
+
+
+#[test]
+fn test_receipt_pdf() {
+
+    let votecode = votecode_from_string("12340-56784-11116-00000").unwrap();
+
+    let filename = std::env::temp_dir().join("7e-test-receipt.pdf");
+    print_receipt("Example Poll", &votecode, &filename).unwrap();
+    assert!(filename.exists());
+
+    fs::remove_file(filename).unwrap();
+}
+
+#[test]
+fn test_accessible_ballots() {
+
+    let serial = 42;
+    let ballot = Ballot {
+        serial,
+        choice1: BallotChoice { serial, votecode: VoteCode::parse("12340-56784-11116-00019").unwrap(), choice: ChoiceValue::For },
+        choice2: BallotChoice { serial, votecode: VoteCode::parse("12340-56784-11116-00000").unwrap(), choice: ChoiceValue::Against }
+    };
+    let localized = vec![LocalizedBallotText {
+        locale: "en".to_owned(),
+        question: "Should the <example> poll pass?".to_owned(),
+        choice_for: "Yes".to_owned(),
+        choice_against: "No".to_owned()
+    }];
+    let text = BallotText {
+        poll_identifier: Some("Example Poll"),
+        question: None,
+        localized: &localized
+    };
+
+    // The accessibility field of the roster picks the format.
+    let mut voter = seventh_estate::voter_roster::VoterInfo {
+        last_name: "Smith".to_owned(),
+        first_name: "Ann".to_owned(),
+        street_address: "1 Main Street".to_owned(),
+        city: "Dallas".to_owned(),
+        state: "TX".to_owned(),
+        zip_code: "75367".to_owned(),
+        extra_fields: Default::default()
+    };
+    assert_eq!(Ok(BallotFormat::Standard), BallotFormat::for_voter(&voter));
+    voter.extra_fields.insert(ACCESSIBILITY_FIELD.to_owned(), "Large-Print".to_owned());
+    assert_eq!(Ok(BallotFormat::LargePrint), BallotFormat::for_voter(&voter));
+    voter.extra_fields.insert(ACCESSIBILITY_FIELD.to_owned(), "braille".to_owned());
+    assert_eq!(Err("Unknown ballot format: braille".to_owned()), BallotFormat::for_voter(&voter));
+    assert_eq!(None, BallotFormat::Standard.file_name(serial));
+    assert_eq!(Some("42.html".to_owned()), BallotFormat::ScreenReader.file_name(serial));
+
+    let html = ballot_html(&ballot, &text);
+    assert!(html.contains("<html lang=\"en\">"));
+    assert!(html.contains("<p>Should the &lt;example&gt; poll pass?</p>"));
+    assert!(html.contains("<th scope=\"row\">Yes</th><td><span aria-label=\"1 2 3 4 0, 5 6 7 8 4, 1 1 1 1 6, 0 0 0 1 9\">12340-56784-11116-00019</span></td>"));
+    assert!(html.contains("<th scope=\"row\">No</th>"));
+
+    let layout = BallotLayout { scratch_off_markers: true, ..BallotLayout::default() };
+    let filename = std::env::temp_dir().join("7e-test-large-print.pdf");
+    print_large_print_ballot(&ballot, &text, &layout, &filename).unwrap();
+    assert_eq!(Some(APPLICATION_PDF), mime_guess::from_path(&filename).first());
+    assert!(fs::read(&filename).unwrap().starts_with(b"%PDF"));
+
+    fs::remove_file(filename).unwrap();
+}
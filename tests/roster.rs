@@ -0,0 +1,174 @@
+use seventh_estate::voter_roster::*;
+
+const ROSTER: &str = "last_name,first_name,street_address,city,state,zip_code
+Smith,Ann,1 Main Street,Dallas,TX,75367
+smith ,Bob,2 Main Street,Dallas,TX,75367
+Jones,Carl,3 Main Street,Dallas,TX,75367
+Jones,Dana,4 Main Street,Dallas,TX,75367
+Oyelaran,Eve,5 Main Street,Dallas,TX,75367
+Smith,Fay,6 Bay Plaza,El Paso,TX,88530
+Smith,Gus,7 Bay Plaza,El Paso,TX,88530
+";
+
+#[test]
+fn test_roster_anonymity() {
+    let roster = VoterRoster::from_reader(ROSTER.as_bytes()).unwrap();
+
+    let check = check_anonymity(&roster, &[RosterField::City], 2);
+    assert!(check.valid());
+    assert_eq!(2, check.anonymity);
+    assert_eq!(7, check.voters);
+
+    // A rare last name in a city singles out a voter; case and whitespace
+    // do not tell voters apart.
+    let check = check_anonymity(&roster, &[RosterField::City, RosterField::LastName], 2);
+    assert!(!check.valid());
+    assert_eq!(1, check.anonymity);
+    assert_eq!((1, 1), (check.groups_at_risk, check.voters_at_risk));
+
+    let check = check_anonymity(&roster, &[RosterField::LastName], 3);
+    assert_eq!((2, 3), (check.groups_at_risk, check.voters_at_risk));
+
+    // Publishing no field leaves every voter anonymous.
+    assert!(check_anonymity(&roster, &[], DEFAULT_MIN_ANONYMITY).valid());
+
+    assert_eq!(Ok(RosterField::ZipCode), "zip_code".parse());
+    assert!("district".parse::<RosterField>().is_err());
+}
+
+#[test]
+fn test_roster_policy() {
+    let roster = VoterRoster::from_reader(ROSTER.as_bytes()).unwrap();
+    let policy = RosterPolicy::new(&[RosterField::State, RosterField::City], 2);
+    assert_eq!(vec![RosterField::City, RosterField::State], policy.published_fields);
+    assert!(policy.check(&roster).valid());
+
+    let committed = roster.restricted_with(&policy).unwrap();
+    let entry = &committed.records[0].voter_info;
+    let lines: Vec<&str> = entry.lines().collect();
+    assert_eq!(vec!["Dallas", "TX"], lines[..2].to_vec());
+    assert_eq!(64, lines[2].len());
+    assert!(!entry.contains("Smith") && !entry.contains("Main Street"));
+
+    // Committed entries are reproducible from the salt, and differ between
+    // voters with the same published fields.
+    assert_eq!(entry, &policy.restricted(&roster.records[0]).unwrap());
+    assert_ne!(lines[2], committed.records[1].voter_info.lines().nth(2).unwrap());
+
+    // Another salt hides the same voter differently.
+    let other = RosterPolicy::new(&[RosterField::State, RosterField::City], 2);
+    assert_ne!(entry, &other.restricted(&roster.records[0]).unwrap());
+}
+
+#[test]
+fn test_roster_schema() {
+    let schema: RosterSchema = serde_yaml::from_str("
+fields:
+  - { name: last_name, required: true }
+  - { name: first_name, required: true }
+  - { name: zip_code }
+  - { name: voter_id, type: integer, required: true }
+  - { name: birth_date, type: date }
+  - { name: email, type: email }
+").unwrap();
+    schema.validate().unwrap();
+    assert_eq!(RosterFieldType::Text, schema.field("zip_code").unwrap().field_type);
+
+    // Values are stored in canonical form, the standard fields left out
+    // of the schema empty.
+    let roster = schema.read_roster("last_name,first_name,voter_id,birth_date,email
+  Smith ,Ann  Marie,0042,2001-02-03,Ann@Example.org
+Jones,Carl,+7,,
+".as_bytes()).unwrap();
+    let ann = &roster.records[0].voter_info;
+    assert_eq!(("Smith", "Ann Marie", ""), (ann.last_name.as_str(), ann.first_name.as_str(), ann.city.as_str()));
+    assert_eq!(vec!["birth_date: 2001-02-03", "email: ann@example.org", "voter_id: 42"], ann.extra_field_lines());
+    assert_eq!(vec!["voter_id: 7"], roster.records[1].voter_info.extra_field_lines());
+
+    // Canonical rosters hash the same, and are left unchanged.
+    let applied = schema.apply(&roster).unwrap();
+    assert_eq!(serde_yaml::to_string(&roster).unwrap(), serde_yaml::to_string(&applied).unwrap());
+    assert!(RestrictedVoterInfo::from(ann.clone()).ends_with("\nbirth_date: 2001-02-03\nemail: ann@example.org\nvoter_id: 42"));
+
+    // Extra fields are private under a roster policy.
+    let policy = RosterPolicy::new(&[RosterField::LastName], 1);
+    let mut other = roster.clone();
+    other.records[0].voter_info.extra_fields.insert("voter_id".to_owned(), "43".to_owned());
+    assert_ne!(policy.restricted(&roster.records[0]).unwrap(), policy.restricted(&other.records[0]).unwrap());
+
+    // Unknown columns, missing required fields and mistyped values are
+    // rejected.
+    assert!(schema.read_roster("last_name,first_name,voter_id,city\nSmith,Ann,1,Dallas\n".as_bytes()).is_err());
+    assert!(schema.read_roster("last_name,first_name\nSmith,Ann\n".as_bytes()).is_err());
+    assert!(schema.read_roster("last_name,first_name,voter_id\nSmith,,1\n".as_bytes()).is_err());
+    assert!(schema.read_roster("last_name,first_name,voter_id\nSmith,Ann,one\n".as_bytes()).is_err());
+    assert!(schema.read_roster("last_name,first_name,voter_id,birth_date\nSmith,Ann,1,03/02/2001\n".as_bytes()).is_err());
+    assert!(schema.read_roster("last_name,first_name,voter_id,email\nSmith,Ann,1,ann\n".as_bytes()).is_err());
+
+    // Field names are distinct identifiers.
+    let twice = RosterSchema { fields: vec![schema.fields[0].clone(), schema.fields[0].clone()] };
+    assert!(twice.validate().is_err());
+    let mut named = schema.fields[0].clone();
+    named.name = "Last Name".to_owned();
+    assert!(RosterSchema { fields: vec![named] }.validate().is_err());
+    assert!(RosterSchema { fields: Vec::new() }.validate().is_err());
+}
+
+#[test]
+fn test_mailing_labels() {
+    use seventh_estate::subcommands::{mailing_label_bytes, MailingLabelFormat};
+
+    let schema: RosterSchema = serde_yaml::from_str("
+fields:
+  - { name: last_name, required: true }
+  - { name: first_name, required: true }
+  - { name: street_address }
+  - { name: city }
+  - { name: state }
+  - { name: zip_code }
+  - { name: country }
+").unwrap();
+    let roster = schema.read_roster("last_name,first_name,street_address,city,state,zip_code,country
+Smith,Ann,1 Main St.,Dallas,TX,75367-0001,
+Tremblay,Luc,\"12, rue Principale\",Montréal,QC,h2x1y4,CA
+Jones,Carl,3 High Street,London,,sw1a1aa,GB
+Müller,Eva,Hauptstraße 5,Berlin,,10115,DE
+Doe,Jim,4 Elm Road,Austin,TX,7870,US
+Roe,Kim,5 Oak Road,Lima,,15001,PE
+".as_bytes()).unwrap();
+
+    let labels = roster.mailing_labels(&[1, 0, 2, 3, 4, 5], AddressCountry::Us).unwrap();
+    assert_eq!(vec!["ANN SMITH", "1 MAIN ST", "DALLAS TX 75367-0001"], labels[1].lines);
+    assert_eq!(vec!["LUC TREMBLAY", "12 RUE PRINCIPALE", "MONTRÉAL QC H2X 1Y4", "CANADA"], labels[0].lines);
+    assert_eq!(vec!["CARL JONES", "3 HIGH STREET", "LONDON", "SW1A 1AA", "UNITED KINGDOM"], labels[2].lines);
+    assert_eq!(vec!["EVA MÜLLER", "HAUPTSTRASSE 5", "10115 BERLIN", "GERMANY"], labels[3].lines);
+    assert_eq!(AddressCountry::De, labels[3].country);
+    assert!(labels[..4].iter().all(|label| label.problem.is_none()));
+
+    // Addresses to review are labeled all the same.
+    assert_eq!(Some("7870 is not a postal code of US".to_owned()), labels[4].problem);
+    assert_eq!(vec!["JIM DOE", "4 ELM ROAD", "AUSTIN TX 7870"], labels[4].lines);
+    assert!(labels[5].problem.as_ref().unwrap().contains("No address format for country PE"));
+    assert_eq!(5, labels[5].position);
+
+    // Mailed from Germany, German addresses and those of voters without a
+    // country have no country line.
+    let labels = roster.mailing_labels(&[3, 4, 0], AddressCountry::De).unwrap();
+    assert_eq!(vec!["EVA MÜLLER", "HAUPTSTRASSE 5", "10115 BERLIN"], labels[0].lines);
+    assert_eq!("UNITED STATES OF AMERICA", labels[1].lines[3]);
+    assert_eq!(AddressCountry::De, labels[2].country);
+    assert!(labels[2].problem.is_some());
+    assert!(roster.mailing_labels(&[6], AddressCountry::Us).is_err());
+
+    let csv = String::from_utf8(mailing_label_bytes(&labels, MailingLabelFormat::Csv).unwrap()).unwrap();
+    assert_eq!("sequence,line_1,line_2,line_3,line_4,line_5,country_code
+1,EVA MÜLLER,HAUPTSTRASSE 5,10115 BERLIN,,,DE
+2,JIM DOE,4 ELM ROAD,AUSTIN TX 7870,UNITED STATES OF AMERICA,,US
+3,ANN SMITH,1 MAIN ST,75367-0001 DALLAS,,,DE
+", csv);
+    let text = String::from_utf8(mailing_label_bytes(&labels, MailingLabelFormat::Text).unwrap()).unwrap();
+    assert!(text.starts_with("EVA MÜLLER\nHAUPTSTRASSE 5\n10115 BERLIN\n\nJIM DOE\n"));
+
+    assert_eq!(Ok(AddressCountry::Gb), "uk".parse());
+    assert_eq!("GB", AddressCountry::Gb.to_string());
+}
@@ -0,0 +1,157 @@
+#![cfg(feature = "server")]
+
+use seventh_estate::ballots::{votecode_from_string, VoteCodeMap};
+use seventh_estate::cryptography::AEADKey;
+use seventh_estate::server::*;
+use secrecy::SecretString;
+
+const VOTE_CODE: &str = "12340-56784-11116-00000";
+
+// No vote reaches a node: no network configuration is read.
+const NETWORK: &str = "test";
+
+#[test]
+fn test_check_vote() {
+    let ballots: VoteCodeMap<usize> = vec![(votecode_from_string(VOTE_CODE).unwrap(), 0)].into_iter().collect();
+    let server = RelayServer::new(NETWORK, "Example Poll", "example.yaml.secure", AEADKey([0; 32]), "merkle.yaml", ballots);
+
+    // Bare vote codes are relayed for the poll of the server
+    let payload = server.check_vote(VOTE_CODE).unwrap();
+    assert_eq!(format!("7E:VOTE:{}:Example Poll", VOTE_CODE), payload.to_string());
+    assert!(server.check_vote(&format!("7E:VOTE:{}:Example Poll", VOTE_CODE)).is_ok());
+
+    assert_eq!(Err(RelayError::WrongPoll("Other Poll".to_owned())),
+        server.check_vote(&format!("7E:VOTE:{}:Other Poll", VOTE_CODE)));
+    assert_eq!(Err(RelayError::UnknownVoteCode("11116-11116-11116-11116".to_owned())),
+        server.check_vote("11116-11116-11116-11116"));
+    assert!(matches!(server.check_vote(&format!("7E:PROOF:{}:Example Poll", VOTE_CODE)), Err(RelayError::InvalidPayload(_))));
+    assert!(matches!(server.check_vote("12341-56784-11116-00000"), Err(RelayError::InvalidPayload(_))));
+}
+
+#[tokio::test]
+async fn test_relay_log() {
+    let votecode = votecode_from_string(VOTE_CODE).unwrap();
+    let datadir = std::env::temp_dir().join(format!("seventh-estate-relay-log-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&datadir);
+    std::fs::create_dir_all(&datadir).unwrap();
+    let datadir_path = datadir.to_string_lossy().into_owned();
+
+    let mut relay_log = RelayLog::open(&datadir_path, &AEADKey([1; 32])).unwrap();
+    assert!(relay_log.is_empty());
+    relay_log.record(&votecode, Some(0)).unwrap();
+    let log = std::fs::read_to_string(datadir.join(RELAY_LOG_FILENAME)).unwrap();
+    assert!(!log.contains(VOTE_CODE) && !log.contains(&VOTE_CODE.replace('-', "")));
+
+    // The vote codes relayed before are refused once the server restarts,
+    // before the poll configuration is read.
+    let ballots: VoteCodeMap<usize> = vec![(votecode, 0)].into_iter().collect();
+    let mut server = RelayServer::new(NETWORK, &datadir_path, "example.yaml.secure", AEADKey([1; 32]), "merkle.yaml", ballots.clone());
+    assert_eq!(1, server.open_relay_log().unwrap());
+    assert_eq!(Some(RelayError::AlreadySubmitted(VOTE_CODE.to_owned())), server.relay_vote(VOTE_CODE).await.err());
+
+    // The log of another Poll Master Key does not match.
+    let mut server = RelayServer::new(NETWORK, &datadir_path, "example.yaml.secure", AEADKey([2; 32]), "merkle.yaml", ballots);
+    server.open_relay_log().unwrap();
+    assert!(matches!(server.relay_vote(VOTE_CODE).await, Err(RelayError::Backend(_))));
+    std::fs::remove_dir_all(&datadir).unwrap();
+}
+
+#[test]
+fn test_helpdesk_credential() {
+    assert!(HelpdeskCredential::new(SecretString::new(" \n".to_owned())).is_err());
+    let credential = HelpdeskCredential::new(SecretString::new("s3cret\n".to_owned())).unwrap();
+    assert!(credential.authorizes(Some("Bearer s3cret")));
+    assert!(!credential.authorizes(Some("Bearer secret")));
+    assert!(!credential.authorizes(Some("s3cret")));
+    assert!(!credential.authorizes(Some("Bearer ")));
+    assert!(!credential.authorizes(None));
+
+    // Lookups are refused before the poll configuration is read
+    let mut server = RelayServer::new(NETWORK, "Example Poll", "example.yaml.secure", AEADKey([0; 32]), "merkle.yaml", VoteCodeMap::new());
+    assert_eq!(Some(RelayError::Unauthorized), server.lookup_ballot(0, Some("Bearer s3cret")).err());
+    server.set_helpdesk_credential(credential);
+    assert_eq!(Some(RelayError::Unauthorized), server.lookup_ballot(0, Some("Bearer secret")).err());
+    assert!(matches!(server.lookup_ballot(0, Some("Bearer s3cret")), Err(RelayError::Backend(_))));
+}
+
+#[test]
+fn test_rate_limiter() {
+    use std::time::{Duration, Instant};
+    let limiter = RateLimiter::per_minute(2);
+    let (client, other) = ("192.0.2.1".parse().unwrap(), "192.0.2.2".parse().unwrap());
+    let now = Instant::now();
+
+    // A burst is allowed, then requests at the configured rate.
+    assert!(limiter.allow_at(client, now));
+    assert!(limiter.allow_at(client, now));
+    assert!(!limiter.allow_at(client, now));
+    assert!(limiter.allow_at(other, now));
+    assert!(limiter.allow_at(client, now + Duration::from_secs(30)));
+    assert!(!limiter.allow_at(client, now + Duration::from_secs(31)));
+}
+
+#[cfg(unix)]
+#[test]
+fn test_proof_server_sandbox() {
+    let root = std::env::temp_dir().join(format!("seventh-estate-proofs-{}", std::process::id()));
+    let (board, outside) = (root.join("board"), root.join("outside"));
+    std::fs::create_dir_all(&board).unwrap();
+    std::fs::create_dir_all(&outside).unwrap();
+    assert!(ProofServer::open(&board).is_err());
+
+    // The Merkle tree must not resolve outside of the served directory.
+    std::fs::write(outside.join("merkle.yaml"), "").unwrap();
+    std::os::unix::fs::symlink(outside.join("merkle.yaml"), board.join("merkle.yaml")).unwrap();
+    assert!(ProofServer::open(&board).is_err());
+    assert!(ProofServer::open(&outside).is_ok());
+    std::fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_audit_events() {
+    let datadir = std::env::temp_dir().join(format!("seventh-estate-events-{}", std::process::id()));
+    std::fs::create_dir_all(&datadir).unwrap();
+    let mut watcher = AuditEventWatcher::new(&datadir, &datadir.join("merkle.yaml"));
+    assert!(watcher.check().is_empty());
+
+    // Events are found once for every artifact written.
+    std::fs::write(datadir.join("audited_columns.yaml"), "---\n- \"Plane [1]: Column [3]\"\n").unwrap();
+    std::fs::write(datadir.join("tally.yaml"), "tally:\n  for_votes: 2\n  against_votes: 1\n  decoy_votes: 0\nplanes_counted: 1\nconsistent: true\n").unwrap();
+    let events = watcher.check();
+    let names: Vec<&str> = events.iter().map(AuditEvent::name).collect();
+    assert_eq!(vec!["challenge_drawn", "tally_updated"], names);
+    assert!(watcher.check().is_empty());
+    std::fs::write(datadir.join("tally.yaml"), "tally:\n  for_votes: 12\n  against_votes: 1\n  decoy_votes: 0\nplanes_counted: 1\nconsistent: true\n").unwrap();
+    let updated = watcher.check();
+    assert!(matches!(updated.as_slice(), [AuditEvent::TallyUpdated { planes_counted: 1, consistent: true, .. }]));
+
+    // Observers resume after the last event they received.
+    let observers = ObserverHub::new("Example Poll");
+    events.into_iter().chain(updated).for_each(|event| { observers.publish(event); });
+    let (missed, _) = observers.subscribe(1);
+    assert_eq!(vec![2, 3], missed.iter().map(|event| event.id).collect::<Vec<u64>>());
+    assert!(missed[0].to_sse().starts_with("id: 2\nevent: tally_updated\ndata: {\"id\":2,\"poll_identifier\":\"Example Poll\""));
+    assert!(observers.subscribe(3).0.is_empty());
+    std::fs::remove_dir_all(&datadir).unwrap();
+}
+
+#[test]
+fn test_voting_deadline() {
+    let close_at = "2026-11-03T20:00:00-05:00".parse::<chrono::DateTime<chrono::Utc>>().unwrap();
+    let deadline = VotingDeadline { close_at: Some(close_at), closing_block: Some(120) };
+    assert!(!deadline.passed(close_at - chrono::Duration::seconds(1), Some(120)));
+    assert!(deadline.passed(close_at, None));
+
+    // Votes mined in the closing block are counted: it closes once the next one is mined.
+    let deadline = VotingDeadline { close_at: None, closing_block: Some(120) };
+    assert!(!deadline.passed(close_at, None));
+    assert!(deadline.passed(close_at, Some(121)));
+    assert_eq!("block 120", deadline.to_string());
+
+    // A closed server refuses votes before reading the poll configuration.
+    let ballots: VoteCodeMap<usize> = vec![(votecode_from_string(VOTE_CODE).unwrap(), 0)].into_iter().collect();
+    let server = RelayServer::new(NETWORK, "Example Poll", "example.yaml.secure", AEADKey([0; 32]), "merkle.yaml", ballots);
+    assert!(matches!(futures::executor::block_on(server.relay_vote(VOTE_CODE)), Err(RelayError::Backend(_))));
+    server.close_voting();
+    assert_eq!(Some(RelayError::VotingClosed), futures::executor::block_on(server.relay_vote(VOTE_CODE)).err());
+}
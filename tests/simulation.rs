@@ -0,0 +1,189 @@
+use std::fs;
+use seventh_estate::simulation::*;
+use seventh_estate::bulletin_board::{AuditReport, PublicRecount};
+use seventh_estate::audit_log::{read_audit_log, AuditLogEvent};
+use seventh_estate::ballots::{BallotExportFormat, BallotMapCommitment, BallotMapEntry, EscrowConfiguration, EscrowHolder, ExportedBallot, RedactionProfile};
+use seventh_estate::cryptography::VoteDecryptionKey;
+use seventh_estate::subcommands::{check_regenerated_ballots, escrow_ballot_map, export_ballots, generate_escrow_key, open_ballot_map_escrow, publish_board, regenerate_ballots, reveal_cell, verify_cell_opening, locate_leaves, LeafTarget, PlaneCellOpening, PollTally};
+
+#[test]
+fn test_simulated_poll() {
+    let root = std::env::temp_dir().join(format!("seventh-estate-simulation-{}", std::process::id()));
+    let configuration = SimulationConfiguration {
+        num_voters: 40,
+        num_ballots: 20,
+        num_decoys: 2,
+        num_audited: 2,
+        turnout: 1.0,
+        duplicate_rate: 0.5,
+        invalid_rate: 0.5,
+        seed: Some("00".repeat(32))
+    };
+    let report = run_simulation(&configuration, &root.join("first")).unwrap();
+    assert_eq!(report.ballots_voted, 18);
+    assert_eq!(report.expected.total(), 18);
+    assert!(0 < report.duplicates && 0 < report.invalid);
+    assert_eq!(report.submissions, report.ballots_voted + report.duplicates + report.invalid);
+    assert!(report.matches, "{}", report);
+    assert!(report.finalized, "{}", report);
+
+    // The same seed regenerates the same ballots, planes and votes.
+    let repeated = run_simulation(&configuration, &root.join("second")).unwrap();
+    assert_eq!(repeated.expected, report.expected);
+    assert_eq!(repeated.tally, report.tally);
+    assert_eq!(repeated.submissions, report.submissions);
+    let plane = |run: &str| fs::read(root.join(run).join(SIMULATION_POLL_IDENTIFIER).join("committed_plane_01.csv")).unwrap();
+    assert_eq!(plane("first"), plane("second"));
+
+    // The ballots regenerated from the sealed seed match the commitment.
+    let previous_dir = std::env::current_dir().unwrap();
+    std::env::set_current_dir(root.join("first")).unwrap();
+    let checked = check_regenerated_ballots("newpoll.yaml.secure", None);
+    regenerate_ballots("newpoll.yaml.secure", "recovered").unwrap();
+    assert!(regenerate_ballots("newpoll.yaml.secure", "recovered").is_err());
+    fs::write(root.join("first").join(SIMULATION_POLL_IDENTIFIER).join("committed_plane_02.csv"), "serial\n").unwrap();
+    let tampered = check_regenerated_ballots("newpoll.yaml.secure", Some("recovered/ballots.csv"));
+    let exported: Vec<_> = [
+        (RedactionProfile::Full, BallotExportFormat::Json, "escrow.json"),
+        (RedactionProfile::PrintVendor, BallotExportFormat::Csv, "vendor.csv"),
+        (RedactionProfile::PublicAudit, BallotExportFormat::Csv, "audit.csv")
+    ].iter().map(|&(profile, format, out)| export_ballots("newpoll.yaml.secure", profile, format, out)).collect();
+    let escrow_keys: Vec<VoteDecryptionKey> = (0..3).map(|_| VoteDecryptionKey::generate()).collect();
+    let escrow_configuration = EscrowConfiguration {
+        quorum: Some(2),
+        holders: escrow_keys.iter().enumerate()
+            .map(|(n, key)| EscrowHolder { holder: format!("observer{}", n), public_key: key.encryption_key().to_base64() })
+            .collect()
+    };
+    fs::write("escrow_keys.yaml", serde_yaml::to_string(&escrow_configuration).unwrap()).unwrap();
+    for (n, key) in escrow_keys.iter().enumerate() {
+        fs::write(format!("observer{}.key", n), base64::encode(key.secret_key())).unwrap();
+    }
+    let escrowed = escrow_ballot_map("newpoll.yaml.secure", "escrow_keys.yaml");
+    let escrow_file = format!("{}/ballot_map_escrow.yaml", SIMULATION_POLL_IDENTIFIER);
+    let opened = open_ballot_map_escrow(&escrow_file, &["observer0.key".to_owned(), "observer2.key".to_owned()], "ballot_map.csv");
+    let below_quorum = open_ballot_map_escrow(&escrow_file, &["observer1.key".to_owned()], "ballot_map_1.csv");
+    std::env::set_current_dir(&previous_dir).unwrap();
+    assert!(checked.is_ok());
+    assert!(tampered.is_err());
+
+    // Every ballot export is redacted by its profile and logged with its digest.
+    assert!(exported.iter().all(|exported| exported.is_ok()));
+    let escrow: Vec<ExportedBallot> = serde_json::from_slice(&fs::read(root.join("first").join("escrow.json")).unwrap()).unwrap();
+    assert_eq!(20, escrow.len());
+    assert!(escrow.iter().all(|ballot| ballot.salt.is_some() && ballot.last_name.is_some()));
+    let vendor = fs::read_to_string(root.join("first").join("vendor.csv")).unwrap();
+    assert!(vendor.starts_with("serial,choice1_votecode,choice1_value,choice2_votecode,choice2_value\n"));
+    let audit: Vec<ExportedBallot> = csv::Reader::from_path(root.join("first").join("audit.csv")).unwrap()
+        .deserialize().collect::<Result<_, _>>().unwrap();
+    assert_eq!(escrow.iter().map(|ballot| (&ballot.serial, &ballot.commitment)).collect::<Vec<_>>(),
+        audit.iter().map(|ballot| (&ballot.serial, &ballot.commitment)).collect::<Vec<_>>());
+    assert!(audit.iter().all(|ballot| ballot.choice1_votecode.is_none()));
+    let log = read_audit_log(&root.join("first").join(SIMULATION_POLL_IDENTIFIER).to_string_lossy()).unwrap();
+    let digests: Vec<&str> = log.iter().filter_map(|entry| match &entry.event {
+        AuditLogEvent::BallotExport { sha256, .. } => Some(sha256.as_str()),
+        _ => None
+    }).collect();
+    assert_eq!(3, digests.len());
+    assert!(digests[0] != digests[1] && digests[1] != digests[2]);
+
+    // The ballot map is opened by a quorum of escrow holders only, and
+    // matches its published digest.
+    escrowed.unwrap();
+    opened.unwrap();
+    assert!(below_quorum.is_err());
+
+    // The secret key of a holder is only readable by its owner, and never
+    // overwritten.
+    let holder_key = root.join("first").join("observer3.key");
+    generate_escrow_key("observer3", holder_key.to_str().unwrap()).unwrap();
+    let holder_secret = fs::read_to_string(&holder_key).unwrap();
+    #[cfg(unix)]
+    assert_eq!(0o600, std::os::unix::fs::PermissionsExt::mode(&fs::metadata(&holder_key).unwrap().permissions()) & 0o777);
+    assert!(generate_escrow_key("observer3", holder_key.to_str().unwrap()).is_err());
+    assert_eq!(holder_secret, fs::read_to_string(&holder_key).unwrap());
+    let commitment: BallotMapCommitment = serde_yaml::from_reader(fs::File::open(
+        root.join("first").join(SIMULATION_POLL_IDENTIFIER).join("ballot_map_commitment.yaml")).unwrap()).unwrap();
+    assert_eq!((2, 3), (commitment.quorum, commitment.holders.len()));
+    let map: Vec<BallotMapEntry> = csv::Reader::from_path(root.join("first").join("ballot_map.csv")).unwrap()
+        .deserialize().collect::<Result<_, _>>().unwrap();
+    assert_eq!(40, map.len());
+    assert!(escrow.iter().all(|ballot| map.iter().any(|entry| Some(&entry.votecode) == ballot.choice1_votecode.as_ref()
+        && entry.serial == ballot.serial && Some(&entry.choice) == ballot.choice1_value.as_ref())));
+    assert_eq!(plane("second"), fs::read(root.join("first").join("recovered").join("committed_plane_01.csv")).unwrap());
+
+    // The audit reported from the published board opens every revealed cell.
+    std::env::set_current_dir(root.join("second")).unwrap();
+    let published = publish_board("newpoll.yaml.secure", "board", "merkle.yaml");
+
+    // A challenged cell of the column revealed by its plane is opened with
+    // its proof of inclusion; the other column of the row is not.
+    let keys: Vec<(String, String, String)> = csv::Reader::from_path(root.join("second").join(SIMULATION_POLL_IDENTIFIER).join("final_plane_01_keys.csv")).unwrap()
+        .deserialize().collect::<Result<_, _>>().unwrap();
+    let (row, (col1_key, _, _)) = keys.iter().enumerate()
+        .find(|(_, (col1_key, _, col3_key))| col1_key.is_empty() != col3_key.is_empty())
+        .unwrap();
+    let (revealed, hidden) = if col1_key.is_empty() { (3, 1) } else { (1, 3) };
+    let revealed_cell = reveal_cell("newpoll.yaml.secure", "merkle.yaml", 1, row + 1, revealed, "observer0", "opening.yaml");
+    let hidden_cell = reveal_cell("newpoll.yaml.secure", "merkle.yaml", 1, row + 1, hidden, "observer0", "hidden.yaml");
+    let verified_cell = verify_cell_opening("opening.yaml");
+    let voter = locate_leaves("merkle.yaml", LeafTarget::Voter(39));
+    let ballot = locate_leaves("merkle.yaml", LeafTarget::Ballot(19));
+    let beyond = locate_leaves("merkle.yaml", LeafTarget::Voter(40));
+    std::env::set_current_dir(&previous_dir).unwrap();
+    published.unwrap();
+    revealed_cell.unwrap();
+    verified_cell.unwrap();
+    assert!(hidden_cell.unwrap_err().to_string().contains("would link vote codes to their choices"));
+    let mut opening: PlaneCellOpening = serde_yaml::from_str(&fs::read_to_string(root.join("second").join("opening.yaml")).unwrap()).unwrap();
+    assert!(opening.verify());
+    let reveals: Vec<(usize, usize, usize)> = read_audit_log(&root.join("second").join(SIMULATION_POLL_IDENTIFIER).to_string_lossy()).unwrap().iter()
+        .filter_map(|entry| match &entry.event {
+            AuditLogEvent::CellReveal { plane, row, column, .. } => Some((*plane, *row, *column)),
+            _ => None
+        })
+        .collect();
+    assert_eq!(vec![(1, row + 1, revealed)], reveals);
+    opening.entry.push('X');
+    assert!(!opening.verify());
+
+    // The leaf index stored with the tree locates the leaves of a voter or
+    // ballot with their proofs.
+    let voter = voter.unwrap();
+    assert_eq!(("voter", "39"), (voter.leaves[0].entry.category.to_string().as_str(), voter.leaves[0].entry.identifier.as_str()));
+    assert_eq!(opening.root, *voter.leaves[0].lemma.last().unwrap());
+    assert_eq!(4 * 50, ballot.unwrap().leaves.len());
+    assert_eq!("Voter 40 is not in merkle.yaml.", beyond.unwrap_err().to_string());
+    let board = root.join("second").join("board");
+    let audit = AuditReport::from_board(&board, None).unwrap();
+    assert!(audit.passed(), "{}", audit);
+    assert!(audit.openings.iter().all(|plane| plane.opened == plane.verified));
+    assert!(0 < audit.openings.iter().map(|plane| plane.verified).sum::<usize>());
+    assert!(audit.to_html().contains(audit.merkle_root.as_deref().unwrap()));
+    audit.write_pdf(&board.join("audit_report.pdf")).unwrap();
+
+    // The tally is recounted from the final planes alone, and a tally
+    // report that does not match them is caught.
+    let recount = audit.recount.as_ref().unwrap();
+    assert_eq!(audit.tally.as_ref().map(|tally| &tally.tally), recount.tally.as_ref());
+    assert_eq!(audit.tally.as_ref().unwrap().votes.len(), recount.votecodes.len());
+    assert!(recount.planes.iter().any(|plane| plane.column == 1) && recount.planes.iter().any(|plane| plane.column == 3));
+    assert!(PublicRecount::from_board(&board).unwrap().agrees());
+    let tally_report = fs::read_to_string(board.join("tally.yaml")).unwrap();
+    let mut tampered: PollTally = serde_yaml::from_str(&tally_report).unwrap();
+    tampered.tally.for_votes += 1;
+    fs::write(board.join("tally.yaml"), serde_yaml::to_string(&tampered).unwrap()).unwrap();
+    let tampered_recount = PublicRecount::from_board(&board).unwrap();
+    assert!(tampered_recount.anomalies.iter().any(|anomaly| anomaly.starts_with("The recount of the final planes")), "{}", tampered_recount);
+    fs::write(board.join("tally.yaml"), tally_report).unwrap();
+
+    let final_plane = fs::read_to_string(board.join("final_plane_01.csv")).unwrap();
+    let swapped = final_plane.replace(",Against", ",Swapped").replace(",For", ",Against").replace(",Swapped", ",For");
+    fs::write(board.join("final_plane_01.csv"), swapped).unwrap();
+    let audit = AuditReport::from_board(&board, None).unwrap();
+    assert!(audit.anomalies.iter().any(|anomaly| anomaly.starts_with("final_plane_01.csv row")), "{}", audit);
+    fs::remove_dir_all(&root).unwrap();
+
+    let invalid = SimulationConfiguration { turnout: 1.5, ..configuration };
+    assert!(run_simulation(&invalid, &root).is_err());
+}
@@ -0,0 +1,58 @@
+use chrono::FixedOffset;
+use seventh_estate::planes::*;
+use seventh_estate::poll_configuration::parse_utc_offset;
+
+fn row(col2: &str, col3: &str) -> PlaneRecordFileRow {
+    PlaneRecordFileRow {
+        col1: String::from(""),
+        col2: String::from(col2),
+        col3: String::from(col3)
+    }
+}
+
+#[test]
+fn test_plane_tally() {
+    let rows = vec![
+        row("Voted", "For"),
+        row("Voted", "Against"),
+        row("Not Voted", "For"),
+        row("Voted", "Decoy"),
+        row("Voted", "For"),
+        row("Voted", "$chacha20_poly1305_aead$GZm76RMgPAkMQMki$R1ptNzZSTWdQQWtNUU1raQ==$OFz4Z9GNmg==$6MzPD1MV07tqNG+JCYkp6Q==$"),
+    ];
+
+    let tally = PlaneTally::from_rows(&rows);
+    assert_eq!(2, tally.for_votes);
+    assert_eq!(1, tally.against_votes);
+    assert_eq!(1, tally.decoy_votes);
+}
+
+#[test]
+fn test_localized_choice_labels() {
+    use seventh_estate::poll_configuration::*;
+
+    let text = |locale: &str, choice_for: &str, choice_against: &str| LocalizedBallotText {
+        locale: String::from(locale),
+        question: String::from("?"),
+        choice_for: String::from(choice_for),
+        choice_against: String::from(choice_against)
+    };
+
+    assert_eq!(None, localized_choice_labels(&[]));
+    let texts = vec![text("en", "Yes", "No"), text("fr", "Oui", "Non")];
+    assert_eq!(Some((String::from("Yes / Oui"), String::from("No / Non"))), localized_choice_labels(&texts));
+    assert!(validate_localized_text(&texts).is_ok());
+
+    let texts = vec![text("en", "Yes", "No"), text("en", "Aye", "Nay")];
+    assert!(validate_localized_text(&texts).is_err());
+}
+
+#[test]
+fn test_parse_utc_offset() {
+    assert_eq!(FixedOffset::east(2 * 3600), parse_utc_offset("+02:00").unwrap());
+    assert_eq!(FixedOffset::west(5 * 3600 + 30 * 60), parse_utc_offset("-0530").unwrap());
+    assert_eq!(FixedOffset::east(9 * 3600), parse_utc_offset("+09").unwrap());
+    assert_eq!(FixedOffset::east(0), parse_utc_offset("UTC").unwrap());
+    assert!(parse_utc_offset("Europe/Lisbon").is_err());
+    assert!(parse_utc_offset("+25:00").is_err());
+}
@@ -0,0 +1,666 @@
+use seventh_estate::ballots::*;
+use seventh_estate::votes::*;
+use seventh_estate::blockchain::{amendment_hash, BallotAmendment, ChainTransaction};
+use seventh_estate::cryptography::VoteDecryptionKey;
+use seventh_estate::poll_configuration::PollUuid;
+use chrono::{TimeZone, Utc};
+
+const VOTE_CODE: &str = "12340-56784-11116-00000";
+
+#[test]
+fn test_votecode_parity() {
+    let votecode = votecode_from_string(VOTE_CODE).unwrap();
+    assert_eq!(VOTE_CODE, string_from_votecode(&votecode));
+
+    // Typed in without separators
+    assert_eq!(Some(votecode), votecode_from_string("12340567841111600000"));
+
+    // Wrong parity digit, wrong length
+    assert_eq!(None, votecode_from_string("12341-56784-11116-00000"));
+    assert_eq!(None, votecode_from_string("12340-56784-11116"));
+}
+
+#[test]
+fn test_votecode_constructors() {
+    let votecode = VoteCode::parse(VOTE_CODE).unwrap();
+    assert_eq!(VOTE_CODE, votecode.to_string());
+
+    // Written with other spacing, the same vote code
+    assert_eq!(Some(votecode), VoteCode::parse(" 12340 56784-11116 00000 "));
+    assert_eq!(Ok(votecode), "12340-56784-11116-00000".parse::<VoteCode>());
+    assert_eq!(Some(votecode), VoteCode::from_digits(*votecode.digits()));
+
+    // Digits out of range or with a wrong parity digit
+    let mut digits = *votecode.digits();
+    digits[0] = 11;
+    digits[1] = 9;
+    assert_eq!(None, VoteCode::from_digits(digits));
+    digits[0] = 2;
+    assert_eq!(None, VoteCode::from_digits(digits));
+
+    // Serialized as its digits, checked when deserialized
+    let serialized = serde_json::to_string(&votecode).unwrap();
+    assert_eq!("[1,2,3,4,0,5,6,7,8,4,1,1,1,1,6,0,0,0,0,0]", serialized);
+    assert_eq!(votecode, serde_json::from_str::<VoteCode>(&serialized).unwrap());
+    assert!(serde_json::from_str::<VoteCode>("[2,2,3,4,0,5,6,7,8,4,1,1,1,1,6,0,0,0,0,0]").is_err());
+}
+
+#[test]
+fn test_votecode_lookup() {
+    let votecode = votecode_from_string(VOTE_CODE).unwrap();
+    let mut ballots: VoteCodeMap<BallotSerial> = vec![(votecode, 7)].into_iter().collect();
+    assert_eq!(Some(&7), ballots.get(&votecode));
+    assert_eq!(Some(&7), ballots.get_str("12340567841111600000"));
+
+    // Vote codes differing in a digit, or malformed, are not matched
+    assert!(!ballots.contains(&votecode_from_string("12340-56784-11116-11116").unwrap()));
+    assert!(!ballots.contains_str("12341-56784-11116-00000"));
+
+    assert_eq!(Some(7), ballots.insert(votecode, 8));
+    assert_eq!(1, ballots.len());
+
+    let mut relayed = VoteCodeSet::new();
+    assert!(relayed.insert(votecode));
+    assert!(!relayed.insert(votecode));
+    assert!(relayed.remove(&votecode));
+    assert!(relayed.is_empty());
+}
+
+#[test]
+fn test_payload() {
+    let votecode = votecode_from_string(VOTE_CODE).unwrap();
+
+    let payload = VotePayload::vote("Example: Poll", &votecode);
+    assert_eq!("7E:VOTE:12340-56784-11116-00000:Example: Poll", payload.to_string());
+    assert_eq!(Ok(payload.clone()), payload.to_string().parse());
+    assert_eq!(Some(votecode), transaction_to_votecode(&payload.to_bytes()));
+
+    // Bare vote codes are votes for an unspecified poll
+    assert_eq!(Some(votecode), transaction_to_votecode(VOTE_CODE.as_bytes()));
+
+    // Proof lookups are not votes
+    let payload = VotePayload::proof("Example Poll", &votecode);
+    assert_eq!(Some(payload.clone()), VotePayload::from_bytes(&payload.to_bytes()));
+    assert_eq!(None, transaction_to_votecode(&payload.to_bytes()));
+
+    assert_eq!(None, transaction_to_votecode(b"7E:TALLY:12340-56784-11116-00000:Example Poll"));
+    assert_eq!(None, transaction_to_votecode(&[0xde, 0xad, 0xbe, 0xef]));
+}
+
+#[test]
+fn test_submitted_vote() {
+    let votecode = votecode_from_string(VOTE_CODE).unwrap();
+    let transaction = |input: Vec<u8>| ChainTransaction {
+        transaction_hash: "0x01".to_owned(),
+        from: "0x02".to_owned(),
+        block_number: 42,
+        timestamp: Utc.timestamp(1600000000, 0),
+        gas_price: 1_000_000_000,
+        input
+    };
+
+    let vote = SubmittedVote::from_transaction("Example Poll",
+        &transaction(VotePayload::vote("Example Poll", &votecode).to_bytes())).unwrap();
+    assert_eq!(VOTE_CODE, vote.votecode);
+    assert_eq!(42, vote.block_number);
+
+    // Votes for other polls and commitments are not votes for this poll
+    assert!(SubmittedVote::from_transaction("Example Poll",
+        &transaction(VotePayload::vote("Other Poll", &votecode).to_bytes())).is_none());
+    assert!(SubmittedVote::from_transaction("Example Poll", &transaction(vec![0; 32])).is_none());
+
+    // A poll identified by its UUID only counts the payloads naming it.
+    let poll_uuid = PollUuid::generate().to_string();
+    let other_uuid = PollUuid::generate().to_string();
+    assert!(SubmittedVote::from_transaction(&poll_uuid, &transaction(VOTE_CODE.as_bytes().to_vec())).is_none());
+    assert!(SubmittedVote::from_transaction("Example Poll", &transaction(VOTE_CODE.as_bytes().to_vec())).is_some());
+    let json = JsonVotePayload { poll_identifier: None, ..JsonVotePayload::from(&VotePayload::vote(&poll_uuid, &votecode)) };
+    assert!(SubmittedVote::from_transaction(&poll_uuid, &transaction(json.to_bytes())).is_none());
+    for payload in [VotePayload::vote(&poll_uuid, &votecode).to_binary(), VotePayload::vote(&poll_uuid, &votecode).to_bytes()] {
+        assert!(SubmittedVote::from_transaction(&poll_uuid, &transaction(payload.clone())).is_some());
+        assert!(SubmittedVote::from_transaction(&other_uuid, &transaction(payload)).is_none());
+    }
+    assert_eq!(Some(votecode), named_poll_votecode("Example Poll", &VotePayload::vote("Example Poll", &votecode).to_json()));
+    assert_eq!(None, named_poll_votecode("Example Poll", VOTE_CODE.as_bytes()));
+}
+
+#[test]
+fn test_poll_uuid() {
+    let poll_uuid = PollUuid::generate();
+    let written = poll_uuid.to_string();
+    assert_eq!(36, written.len());
+    assert_eq!(Some('4'), written.chars().nth(14));
+    assert_eq!(Some(poll_uuid), PollUuid::parse(&written));
+    assert_eq!(Some(poll_uuid), PollUuid::parse(&written.to_uppercase()));
+    assert_eq!(poll_uuid, serde_yaml::from_str(&serde_yaml::to_string(&poll_uuid).unwrap()).unwrap());
+    assert_ne!(poll_uuid, PollUuid::generate());
+    assert_eq!(None, PollUuid::parse("Example Poll"));
+    assert_eq!(None, PollUuid::parse(&written.replace('-', "")));
+    assert!("0123456789abcdef0123456789abcdef0123".parse::<PollUuid>().is_err());
+}
+
+#[test]
+fn test_encrypted_vote() {
+    let votecode = votecode_from_string(VOTE_CODE).unwrap();
+    let (signing_key, _) = seventh_estate::cryptography::new_signing_key().unwrap();
+    let key = seventh_estate::cryptography::VoteDecryptionKey::from_signing_key(&signing_key).unwrap();
+    let payload = VotePayload::vote("Example Poll", &votecode).to_binary();
+    let encrypted = encrypt_vote_payload("Example Poll", &key.encryption_key(), &payload);
+    assert!(is_encrypted_vote("Example Poll", &encrypted));
+    assert!(!is_encrypted_vote("Other Poll", &encrypted));
+
+    // The vote code is only read with the key of the poll.
+    assert_eq!(None, poll_votecode("Example Poll", &encrypted));
+    assert_eq!(None, decrypt_poll_votecode("Example Poll", &encrypted, None));
+    assert_eq!(Some(votecode), decrypt_poll_votecode("Example Poll", &encrypted, Some(&key)));
+    let (other_signing_key, _) = seventh_estate::cryptography::new_signing_key().unwrap();
+    let other_key = seventh_estate::cryptography::VoteDecryptionKey::from_signing_key(&other_signing_key).unwrap();
+    assert_eq!(None, decrypt_poll_votecode("Example Poll", &encrypted, Some(&other_key)));
+    assert_eq!(Some(votecode), decrypt_poll_votecode("Example Poll", &payload, Some(&key)));
+
+    // The decode path of a vote tells how it was submitted.
+    assert_eq!(Some((votecode, DecodePath { format: PayloadFormat::Binary, encrypted: true })),
+        decrypt_poll_vote("Example Poll", &encrypted, Some(&key)));
+    let json = VotePayload::vote("Example Poll", &votecode).to_json();
+    assert_eq!(Some((votecode, DecodePath { format: PayloadFormat::Json, encrypted: false })),
+        decrypt_poll_vote("Example Poll", &json, None));
+    assert_eq!(Some(PayloadFormat::Text), payload_format(VOTE_CODE.as_bytes()));
+    assert_eq!(None, payload_format(b"hello"));
+}
+
+#[test]
+fn test_time_locked_vote() {
+    use seventh_estate::cryptography::*;
+    let votecode = votecode_from_string(VOTE_CODE).unwrap();
+    let key = VoteDecryptionKey::generate();
+    let puzzle = TimeLockPuzzle::lock(key.secret_key(), 1000, 512).unwrap();
+    let encrypted = encrypt_vote_payload("Example Poll", &key.encryption_key(), &VotePayload::vote("Example Poll", &votecode).to_binary());
+
+    let mut squarings = 0;
+    let unlocked = VoteDecryptionKey::from_secret_key(&puzzle.unlock(|done| squarings = done).unwrap()).unwrap();
+    assert_eq!(1000, squarings);
+    assert_eq!(Some(votecode), decrypt_poll_votecode("Example Poll", &encrypted, Some(&unlocked)));
+
+    // Fewer squarings do not unlock the key.
+    let early = TimeLockPuzzle { squarings: 999, ..puzzle };
+    assert!(early.unlock(|_| ()).is_err());
+}
+
+#[test]
+fn test_resume_time_lock() {
+    use seventh_estate::cryptography::*;
+    use seventh_estate::subcommands::{unlock_votes, TIME_LOCK_FILENAME};
+    let key = VoteDecryptionKey::generate();
+    let puzzle = TimeLockPuzzle::lock(key.secret_key(), TIME_LOCK_CHECKPOINT_SQUARINGS + 10, 512).unwrap();
+
+    // Solving resumes from the checkpoint of an interrupted run.
+    let mut checkpoints = Vec::new();
+    let secret_key = puzzle.resume(None, |checkpoint| {
+        checkpoints.push(checkpoint.clone());
+        Ok(())
+    }).unwrap();
+    assert_eq!(vec![0, TIME_LOCK_CHECKPOINT_SQUARINGS, TIME_LOCK_CHECKPOINT_SQUARINGS + 10],
+        checkpoints.iter().map(|checkpoint| checkpoint.squarings).collect::<Vec<u64>>());
+    assert_eq!(secret_key, puzzle.resume(Some(&checkpoints[1]), |_| Ok(())).unwrap());
+    let past = TimeLockCheckpoint { squarings: TIME_LOCK_CHECKPOINT_SQUARINGS + 11, ..checkpoints[2].clone() };
+    assert!(puzzle.resume(Some(&past), |_| Ok(())).is_err());
+
+    // The published puzzle is unlocked without the poll configuration.
+    let datadir = std::env::temp_dir().join(format!("seventh-estate-unlock-{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&datadir);
+    std::fs::create_dir_all(&datadir).unwrap();
+    let time_lock = TimeLockedKey::new(chrono::Utc::now() + chrono::Duration::seconds(60), 10, 512).unwrap();
+    let time_lock_path = datadir.join(TIME_LOCK_FILENAME);
+    serde_yaml::to_writer(std::fs::File::create(&time_lock_path).unwrap(), &time_lock).unwrap();
+    unlock_votes(&time_lock_path.to_string_lossy()).unwrap();
+    let unlocked = std::fs::read_to_string(datadir.join("unlocked_vote_key")).unwrap();
+    assert!(time_lock.decryption_key(&base64::decode(unlocked.trim()).unwrap()).is_ok());
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(datadir.join("unlocked_vote_key")).unwrap().permissions().mode();
+        assert_eq!(0o600, mode & 0o777);
+    }
+    assert!(unlock_votes(&time_lock_path.to_string_lossy()).is_err());
+    std::fs::remove_dir_all(&datadir).unwrap();
+}
+
+#[test]
+fn test_sms_votes() {
+    use seventh_estate::subcommands::VoteProvenance;
+    let export = std::env::temp_dir().join(format!("seventh-estate-sms-{}.csv", std::process::id()));
+    std::fs::write(&export, format!(
+        "sid,from,date,body\n\
+         SM1,+15550001,2020-09-13T12:26:40Z,{}\n\
+         SM2,+15550002,\"Sun, 13 Sep 2020 12:30:00 +0000\",7E:VOTE:{}:Other Poll\n\
+         SM3,+15550003,2020-09-13 12:35:00,hello\n\
+         SM4,+15550004,2020-09-13 12:40:00,12340 56784 11116 00000\n",
+        VOTE_CODE, VOTE_CODE)).unwrap();
+    let messages = read_sms_export(&export).unwrap();
+    std::fs::remove_file(&export).unwrap();
+    assert_eq!(4, messages.len());
+    assert_eq!(Utc.timestamp(1600000000, 0), messages[0].received_at);
+
+    let votes: Vec<SubmittedVote> = messages.iter()
+        .filter_map(|message| SubmittedVote::from_sms("Example Poll", message))
+        .collect();
+    assert_eq!(2, votes.len());
+    assert!(votes.iter().all(|vote| vote.votecode == VOTE_CODE && vote.source == VoteChannel::Sms));
+    assert_eq!("SM1", votes[0].transaction_hash);
+
+    // The phone number of a vote by text message is left out of its
+    // provenance in the public tally report; the account of a vote mined
+    // on the chain is public already.
+    let provenance = VoteProvenance::new(&VoteRecord::from(&votes[0]), false);
+    assert_eq!(("", None), (provenance.sender.as_str(), provenance.relayed));
+    assert!(!serde_yaml::to_string(&provenance).unwrap().contains("+1555"));
+    let mined = SubmittedVote { source: VoteChannel::Chain, from: "0x01".to_owned(), block_number: 7, ..votes[0].clone() };
+    let provenance = VoteProvenance::new(&VoteRecord::from(&mined), true);
+    assert_eq!(("0x01", Some(7), Some(true)), (provenance.sender.as_str(), provenance.block_number, provenance.relayed));
+}
+
+struct FixedVotes(VoteChannel, Vec<&'static str>);
+
+impl VoteSource for FixedVotes {
+    fn channel(&self) -> VoteChannel { self.0 }
+
+    fn location(&self) -> String { "test".to_owned() }
+
+    fn read_votes(&self, _window: &VotingWindow) -> Result<Vec<SubmittedVote>, Box<dyn std::error::Error>> {
+        Ok(self.1.iter().map(|votecode| SubmittedVote {
+            votecode: votecode.to_string(),
+            transaction_hash: String::new(),
+            from: String::new(),
+            block_number: 0,
+            timestamp: Utc.timestamp(1600000000, 0),
+            source: self.0,
+            decoded: None
+        }).collect())
+    }
+}
+
+#[test]
+fn test_merge_votes() {
+    const OTHER_CODE: &str = "11116-11116-11116-11116";
+    let sources: Vec<Box<dyn VoteSource>> = vec![
+        Box::new(FixedVotes(VoteChannel::Sms, vec![VOTE_CODE, VOTE_CODE])),
+        Box::new(FixedVotes(VoteChannel::Chain, vec![VOTE_CODE, OTHER_CODE, "00000-00000-00000-00000"]))
+    ];
+    let window = VotingWindow { poll_identifier: "Example Poll".to_owned(), from_block: 0, vote_address: None, since: None, end_block: None, decryption_key: None };
+    let merged = merge_votes(&sources, &window, &TallyRules::default(), |votecode| votecode != "00000-00000-00000-00000").unwrap();
+
+    // Each vote code counts once, for the first source that submitted it.
+    assert_eq!(vec![VOTE_CODE, OTHER_CODE], merged.votes.iter().map(|vote| vote.votecode.as_str()).collect::<Vec<&str>>());
+    assert_eq!(VoteChannel::Sms, merged.votes[0].source);
+    assert_eq!(vec![
+        VoteSourceCount { source: VoteChannel::Chain, votes: 1, duplicates: 1 },
+        VoteSourceCount { source: VoteChannel::Sms, votes: 1, duplicates: 1 }
+    ], merged.sources);
+    assert_eq!(1, merged.unknown_votes);
+}
+
+#[test]
+fn test_split_test_votes() {
+    const TEST_CODE: &str = "11116-11116-11116-11116";
+    let sources: Vec<Box<dyn VoteSource>> = vec![
+        Box::new(FixedVotes(VoteChannel::Sms, vec![TEST_CODE, TEST_CODE])),
+        Box::new(FixedVotes(VoteChannel::Chain, vec![VOTE_CODE]))
+    ];
+    let window = VotingWindow { poll_identifier: "Example Poll".to_owned(), from_block: 0, vote_address: None, since: None, end_block: None, decryption_key: None };
+    let mut merged = merge_votes(&sources, &window, &TallyRules::default(), |_| true).unwrap();
+    let test_codes: VoteCodeSet = votecode_from_string(TEST_CODE).into_iter().collect();
+
+    // Test votes are reported, not counted, but still count as duplicates.
+    let test_votes = split_test_votes(&mut merged, &test_codes);
+    assert_eq!(vec![TEST_CODE], test_votes.iter().map(|vote| vote.votecode.as_str()).collect::<Vec<&str>>());
+    assert_eq!(vec![VOTE_CODE], merged.votes.iter().map(|vote| vote.votecode.as_str()).collect::<Vec<&str>>());
+    assert_eq!(vec![
+        VoteSourceCount { source: VoteChannel::Chain, votes: 1, duplicates: 0 },
+        VoteSourceCount { source: VoteChannel::Sms, votes: 0, duplicates: 1 }
+    ], merged.sources);
+}
+
+#[test]
+fn test_tally_rules() {
+    const OTHER_CODE: &str = "11116-11116-11116-11116";
+    const THIRD_CODE: &str = "00000-00000-00000-00000";
+    let sources: Vec<Box<dyn VoteSource>> = vec![
+        Box::new(FixedVotes(VoteChannel::Sms, vec![VOTE_CODE])),
+        Box::new(FixedVotes(VoteChannel::Chain, vec![OTHER_CODE, VOTE_CODE, THIRD_CODE]))
+    ];
+    let window = VotingWindow { poll_identifier: "Example Poll".to_owned(), from_block: 0, vote_address: None, since: None, end_block: None, decryption_key: None };
+    // The first two vote codes are those of ballot 0.
+    let serial = |votecode: &str| match votecode {
+        VOTE_CODE | OTHER_CODE => Some(0),
+        _ => Some(1)
+    };
+    let counted = |rules: &TallyRules| {
+        let mut merged = merge_votes(&sources, &window, rules, |_| true).unwrap();
+        let uncounted = split_uncounted_ballot_votes(&mut merged, rules, serial);
+        (merged, uncounted.len())
+    };
+    let votecodes = |merged: &MergedVotes| merged.votes.iter()
+        .map(|vote| vote.votecode.clone())
+        .collect::<Vec<String>>();
+
+    // Both votes of a ballot are counted by default, for the first source.
+    let (merged, uncounted) = counted(&TallyRules::default());
+    assert_eq!(vec![VOTE_CODE, OTHER_CODE, THIRD_CODE], votecodes(&merged));
+    assert_eq!(VoteChannel::Sms, merged.votes[0].source);
+    assert_eq!(0, uncounted);
+
+    // The last submission of a vote code can be counted instead.
+    let rules = TallyRules { duplicate_votes: DuplicateVotes::Last, ..TallyRules::default() };
+    let (merged, _) = counted(&rules);
+    assert_eq!(VoteChannel::Chain, merged.votes[0].source);
+    assert_eq!(vec![VoteSourceCount { source: VoteChannel::Chain, votes: 3, duplicates: 0 }, VoteSourceCount { source: VoteChannel::Sms, votes: 0, duplicates: 1 }], merged.sources);
+
+    // Ballots voted with both vote codes are cancelled or counted once.
+    let rules = TallyRules { ballot_votes: BallotVotes::Cancel, ..TallyRules::default() };
+    let (merged, uncounted) = counted(&rules);
+    assert_eq!(vec![THIRD_CODE], votecodes(&merged));
+    assert_eq!(2, uncounted);
+    assert_eq!(vec![VoteSourceCount { source: VoteChannel::Chain, votes: 1, duplicates: 1 }], merged.sources);
+    let rules = TallyRules { ballot_votes: BallotVotes::Last, ..TallyRules::default() };
+    assert_eq!(vec![OTHER_CODE, THIRD_CODE], votecodes(&counted(&rules).0));
+    let rules = TallyRules { ballot_votes: BallotVotes::First, ..TallyRules::default() };
+    assert_eq!(vec![VOTE_CODE, THIRD_CODE], votecodes(&counted(&rules).0));
+
+    // Late votes and pending provisional ballots.
+    assert_eq!(Some(100), TallyRules::default().closing_block(Some(100)));
+    assert_eq!(None, TallyRules::default().pending_eligibility());
+    let rules: TallyRules = serde_yaml::from_str("{late_blocks: 10, pending_provisional: reject}").unwrap();
+    assert_eq!(Some(110), rules.closing_block(Some(100)));
+    assert_eq!(None, rules.closing_block(None));
+    assert_eq!(Some(false), rules.pending_eligibility());
+    assert!(!rules.is_default());
+}
+
+#[test]
+fn test_provisional_votes() {
+    let votecodes: Vec<VoteCode> = [VOTE_CODE, "11116-11116-11116-11116", "00000-00000-00000-00000"].iter()
+        .filter_map(|votecode| votecode_from_string(votecode))
+        .collect();
+    let ballots: VoteCodeMap<BallotSerial> = votecodes.iter().cloned().zip(0..).collect();
+    let serial = |votecode: &VoteCode| ballots.get(votecode).copied();
+    let mut provisional = vec![ProvisionalBallot { serial: 1, eligible: None }, ProvisionalBallot { serial: 2, eligible: None }];
+
+    // Votes for provisional ballots are set aside.
+    let (counted, set_aside) = bucket_provisional_votes(votecodes.clone(), &provisional, serial);
+    assert_eq!(vec![votecodes[0]], counted);
+    assert_eq!(vec![votecodes[1], votecodes[2]], set_aside);
+    let (accepted, count) = resolve_provisional_votes(&set_aside, &provisional, serial);
+    assert!(accepted.is_empty());
+    assert_eq!(ProvisionalCount { accepted: 0, rejected: 0, pending: 2 }, count);
+
+    // Only the accepted ones are counted once resolved.
+    provisional[0].eligible = Some(true);
+    provisional[1].eligible = Some(false);
+    let (accepted, count) = resolve_provisional_votes(&set_aside, &provisional, serial);
+    assert_eq!(vec![votecodes[1]], accepted);
+    assert_eq!(ProvisionalCount { accepted: 1, rejected: 1, pending: 0 }, count);
+}
+
+#[test]
+fn test_cached_votes() {
+    let payload = |poll: &str| hex::encode(format!("7E:VOTE:{}:{}", VOTE_CODE, poll));
+    let transactions = vec![
+        CachedTransaction { transaction_hash: "0x01".to_owned(), block_number: 5, input: payload("Example Poll"), succeeded: Some(true), timestamp: Some(1600000000) },
+        CachedTransaction { transaction_hash: "0x02".to_owned(), block_number: 2, input: payload("Example Poll"), succeeded: Some(true), timestamp: None },
+        CachedTransaction { transaction_hash: "0x03".to_owned(), block_number: 6, input: payload("Example Poll"), succeeded: Some(false), timestamp: None },
+        CachedTransaction { transaction_hash: "0x04".to_owned(), block_number: 7, input: payload("Other Poll"), succeeded: None, timestamp: None }
+    ];
+    let path = std::env::temp_dir().join(format!("seventh-estate-cache-{}.json", std::process::id()));
+    std::fs::write(&path, serde_json::to_string_pretty(&transactions).unwrap()).unwrap();
+
+    // Only successful votes for the poll since the commitment are read.
+    let window = VotingWindow { poll_identifier: "Example Poll".to_owned(), from_block: 4, vote_address: None, since: None, end_block: None, decryption_key: None };
+    let votes = CachedVotes(path.clone()).read_votes(&window).unwrap();
+    assert_eq!(1, votes.len());
+    assert_eq!(VOTE_CODE, votes[0].votecode);
+    assert_eq!("0x01", votes[0].transaction_hash);
+    assert_eq!(4, TransactionCache::open(&path).unwrap().transactions().unwrap().len());
+    std::fs::remove_file(&path).unwrap();
+}
+
+#[test]
+fn test_serial_ranges() {
+    let ranges: SerialRanges = serde_yaml::from_str("
+issued: {start: 0, count: 90}
+reserved:
+  - {purpose: test, start: 90, count: 5}
+  - {purpose: replacement, start: 95, count: 5}
+").unwrap();
+    assert!(ranges.validate(100).is_ok());
+    assert!(ranges.validate(99).is_err());
+    assert_eq!(vec![90, 91, 92, 93, 94], ranges.test_serials());
+    assert_eq!(Some(SerialPurpose::Replacement), ranges.purpose(97));
+    assert_eq!(None, ranges.purpose(10));
+
+    // Reserved ranges may not collide with the issued ballots or each other.
+    let mut colliding = ranges.clone();
+    colliding.reserved[1].start = 85;
+    assert!(colliding.validate(100).is_err());
+    colliding.reserved[1].start = 92;
+    assert!(colliding.validate(100).is_err());
+
+    // Replacements are taken from the replacement reserve.
+    assert!(ranges.check_replacement(3, 95).is_ok());
+    assert!(ranges.check_replacement(3, 4).is_err());
+    assert!(ranges.check_replacement(3, 90).is_err());
+    assert!(ranges.check_replacement(96, 95).is_err());
+
+    // Without reserved ranges, every ballot is issued and may replace another.
+    let ranges = SerialRanges::for_poll(100);
+    assert!(ranges.validate(100).is_ok());
+    assert!(ranges.check_replacement(3, 4).is_ok());
+    assert!(ranges.test_serials().is_empty());
+}
+
+#[test]
+fn test_votecode_collisions() {
+    use seventh_estate::cryptography::CSPRNGSeed;
+    use seventh_estate::subcommands::{check_ballot_votecodes, CompleteBallotRow};
+
+    let num_ballots = 500;
+    let seed = CSPRNGSeed::from_vec(&vec![3u8; CSPRNGSeed::SIZE]);
+    let votecodes = generate_votecodes(&seed, 2 * num_ballots);
+    let mut unique = votecodes.clone();
+    unique.sort_unstable();
+    unique.dedup();
+    assert_eq!(2 * num_ballots, unique.len());
+    assert_eq!(votecodes, generate_votecodes(&seed, 2 * num_ballots));
+
+    let serials: Vec<BallotSerial> = (0..num_ballots).collect();
+    let ballots = generate_ballots(&serials, &votecodes);
+    assert!(ballot_votecode_collisions(&ballots, num_ballots).is_empty());
+
+    // A vote code printed on another ballot, and one mistyped, are reported.
+    let mut rows: Vec<CompleteBallotRow> = ballots.iter()
+        .map(|ballot| CompleteBallotRow {
+            serial: string_from_ballotserial(&ballot.serial, num_ballots),
+            choice1_votecode: string_from_votecode(&ballot.choice1.votecode),
+            choice1_value: string_from_choicevalue(&ballot.choice1.choice),
+            choice2_votecode: string_from_votecode(&ballot.choice2.votecode),
+            choice2_value: string_from_choicevalue(&ballot.choice2.choice)
+        })
+        .collect();
+    assert!(check_ballot_votecodes(&rows).valid());
+    rows[250].choice2_votecode = rows[7].choice1_votecode.clone();
+    rows[9].choice1_votecode = "12340-56784-11116-00001".to_owned();
+    let check = check_ballot_votecodes(&rows);
+    assert!(!check.valid());
+    assert_eq!(1, check.collisions.len());
+    assert_eq!(rows[7].choice1_votecode, check.collisions[0].votecode);
+    assert_eq!(vec!["ballot 007 (For)", "ballot 250 (Against)"],
+        check.collisions[0].choices.iter().map(|choice| choice.to_string()).collect::<Vec<String>>());
+    assert_eq!(vec!["ballot 009 (For)"],
+        check.malformed.iter().map(|choice| choice.to_string()).collect::<Vec<String>>());
+}
+
+#[test]
+fn test_undelivered_ballots() {
+    let rows = read_undeliverable_list("serial,reason\n 3 , Unknown address\n5,\n".as_bytes()).unwrap();
+    assert_eq!(vec![3, 5], rows.iter().map(|row| row.serial).collect::<Vec<BallotSerial>>());
+    assert_eq!(Some("Unknown address".to_owned()), rows[0].reason);
+    assert_eq!(None, rows[1].reason);
+    assert!(read_undeliverable_list("serial\nthree\n".as_bytes()).is_err());
+
+    // Votes for undelivered ballots are only rejected under a policy
+    // invalidating their vote codes.
+    let pollconf = |policy: &str| -> seventh_estate::poll_configuration::PollConfiguration {
+        serde_yaml::from_str(&format!("
+poll_state: {{announced: true, roster_committed: true, columns_committed: true, summands_committed: true, summands_drawn: true, ceremony_conducted: true, votes_committed: false, summands_revealed: false, roster_revealed: false, columns_revealed: false}}
+signing_key: ''
+num_ballots: 8
+num_decoys: 0
+voter_roster: ~
+voter_roster_size: 4
+voter_privacy: true
+drawn_summands_seed: ~
+audited_columns_seed: ~
+audited_ballots: ~
+votes: ~
+undelivered_ballots:
+- serial: 3
+  reason: Unknown address
+  recorded_at: 2026-10-01T00:00:00Z
+{}", policy)).unwrap()
+    };
+    let kept = pollconf("");
+    assert_eq!(UndeliveredPolicy::Keep, kept.undelivered_policy);
+    assert_eq!("Ballot 3 undelivered: Unknown address", kept.undelivered_ballots[0].to_string());
+    assert!(kept.excluded_ballots().is_empty());
+    let invalidated = pollconf("undelivered_policy: invalidate");
+    assert_eq!(vec![3], invalidated.excluded_ballots().into_iter().collect::<Vec<BallotSerial>>());
+}
+
+#[test]
+fn test_ballot_states() {
+    let datadir = std::env::temp_dir().join(format!("seventh-estate-ballot-states-{}", std::process::id()));
+    std::fs::create_dir_all(&datadir).unwrap();
+    let datadir_path = datadir.to_str().unwrap();
+    assert_eq!(BallotStates::default(), read_ballot_states(datadir_path).unwrap());
+
+    let serials: Vec<BallotSerial> = (0..10).collect();
+    record_ballot_states(datadir_path, BallotState::Generated, &serials).unwrap();
+    record_ballot_states(datadir_path, BallotState::Printed, &serials).unwrap();
+    record_ballot_states(datadir_path, BallotState::Mailed, &serials[..8]).unwrap();
+    record_ballot_states(datadir_path, BallotState::Delivered, &[2, 1]).unwrap();
+    record_ballot_states(datadir_path, BallotState::Spoiled, &[3]).unwrap();
+    record_ballot_states(datadir_path, BallotState::Voted, &[1, 4]).unwrap();
+    record_ballot_states(datadir_path, BallotState::Generated, &[]).unwrap();
+
+    // Ballots only move forward, and spoiled and voted ballots stay so.
+    record_ballot_states(datadir_path, BallotState::Printed, &serials).unwrap();
+    record_ballot_states(datadir_path, BallotState::Voted, &[3]).unwrap();
+    record_ballot_states(datadir_path, BallotState::Spoiled, &[4]).unwrap();
+
+    let states = read_ballot_states(datadir_path).unwrap();
+    assert_eq!(Some(BallotState::Voted), states.state(1));
+    assert_eq!(Some(BallotState::Delivered), states.state(2));
+    assert_eq!(Some(BallotState::Spoiled), states.state(3));
+    assert_eq!(Some(BallotState::Voted), states.state(4));
+    assert_eq!(Some(BallotState::Mailed), states.state(7));
+    assert_eq!(Some(BallotState::Printed), states.state(9));
+    assert_eq!(None, states.state(10));
+    let counts = states.counts();
+    assert_eq!(BallotStateCounts { generated: 0, printed: 2, mailed: 4, delivered: 1, spoiled: 1, voted: 2 }, counts);
+    assert_eq!(10, counts.tracked());
+    assert_eq!(7, counts.mailed_or_later());
+    assert_eq!("0 generated, 2 printed, 4 mailed, 1 delivered, 1 spoiled, 2 voted", counts.to_string());
+
+    // Updates record their serials as ranges.
+    let updates = std::fs::read_to_string(datadir.join(BALLOT_STATES_FILENAME)).unwrap();
+    assert_eq!(9, updates.lines().count());
+    let delivered: BallotStateUpdate = serde_json::from_str(updates.lines().nth(3).unwrap()).unwrap();
+    assert_eq!(vec![SerialRange { start: 1, count: 2 }], delivered.serials);
+    std::fs::remove_dir_all(&datadir).unwrap();
+}
+
+#[test]
+fn test_replacement_envelope() {
+    let print_key = VoteDecryptionKey::generate();
+    let (envelope, ballot) = ReplacementEnvelope::issue("Example Poll", 3, 7, &print_key.encryption_key()).unwrap();
+    assert_eq!((3, 7), (envelope.spoiled_serial, envelope.replacement_serial));
+    assert_eq!("replacement_envelope_7.yaml", replacement_envelope_filename(7));
+    let votecode = ballot.choice1_votecode.clone().unwrap();
+    assert!(VoteCode::parse(&votecode).is_some());
+    assert!(!envelope.sealed.contains(&votecode));
+    assert_eq!((None, None), (ballot.commitment.clone(), ballot.salt.clone()));
+
+    // Every replacement has fresh vote codes, drawn on the print pipeline.
+    let (other, other_ballot) = ReplacementEnvelope::issue("Example Poll", 3, 7, &print_key.encryption_key()).unwrap();
+    assert_ne!(envelope.commitment, other.commitment);
+    assert_ne!(ballot.choice1_votecode, other_ballot.choice1_votecode);
+
+    // Only the print pipeline opens the envelope, to the committed codes.
+    let codes = envelope.open(&print_key).unwrap();
+    assert_eq!((ballot.choice1_votecode.clone(), ballot.choice2_votecode.clone()),
+        (Some(codes.choice1_votecode.clone()), Some(codes.choice2_votecode.clone())));
+    assert_eq!(envelope.commitment, codes.commitment().unwrap());
+    assert_eq!("replacement_codes_7.yaml", replacement_codes_filename(7));
+    assert!(envelope.open(&VoteDecryptionKey::generate()).is_err());
+    let tampered = ReplacementEnvelope { commitment: "00".repeat(32), ..envelope.clone() };
+    assert!(tampered.open(&print_key).is_err());
+    let misplaced = ReplacementEnvelope { replacement_serial: 8, ..envelope.clone() };
+    assert!(misplaced.open(&print_key).is_err());
+
+    // The envelope and its codes are checked against the amendment
+    // committing to them.
+    let amendment = |commitment: Option<&str>| BallotAmendment {
+        spoiled_serial: 3,
+        replacement_serial: 7,
+        reason: Some("Damaged".to_owned()),
+        ballot_commitment: commitment.map(str::to_owned),
+        amendment_hash: hex::encode(amendment_hash("Example Poll", 3, 7, Some("Damaged"), commitment)),
+        transaction_hash: "0x01".to_owned(),
+        address: "0x02".to_owned(),
+        submitted_at_block: 1,
+        posted_at: Utc::now()
+    };
+    envelope.check_amendment(&amendment(Some(&envelope.commitment))).unwrap();
+    codes.check_amendment(&amendment(Some(&envelope.commitment))).unwrap();
+    assert!(envelope.check_amendment(&amendment(None)).is_err());
+    assert!(codes.check_amendment(&amendment(Some(&other.commitment))).is_err());
+    let rehashed = BallotAmendment { reason: None, ..amendment(Some(&envelope.commitment)) };
+    assert!(envelope.check_amendment(&rehashed).is_err());
+    let forged = ReplacementCodes { choice1_votecode: codes.choice2_votecode.clone(), choice2_votecode: codes.choice1_votecode.clone(), ..codes.clone() };
+    assert!(forged.check_amendment(&amendment(Some(&envelope.commitment))).is_err());
+
+    // Votes for the fresh vote codes count for the choices of the
+    // replacement ballot committed with the poll.
+    let committed = |serial: BallotSerial| {
+        let choice = |votecode: &str, choice| BallotChoice { serial, votecode: VoteCode::parse(votecode).unwrap(), choice };
+        Ballot {
+            serial,
+            choice1: choice("43210-87654-61111-91000", ChoiceValue::Against),
+            choice2: choice("12340-56784-11116-00019", ChoiceValue::For)
+        }
+    };
+    let translation = codes.translation(&committed(7)).unwrap();
+    let fresh_for = match codes.choice1_value.as_str() {
+        "For" => &codes.choice1_votecode,
+        _ => &codes.choice2_votecode
+    };
+    assert_eq!(2, translation.len());
+    assert_eq!("12340-56784-11116-00019", translation[fresh_for]);
+    assert!(codes.translation(&committed(8)).is_err());
+
+    // The secret key of the print pipeline is only readable by its owner,
+    // and never overwritten.
+    use seventh_estate::subcommands::generate_envelope_key;
+    let key_path = std::env::temp_dir().join(format!("seventh-estate-print-{}.key", std::process::id()));
+    let key_filename = key_path.to_str().unwrap();
+    generate_envelope_key(key_filename).unwrap();
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        assert_eq!(0o600, std::fs::metadata(&key_path).unwrap().permissions().mode() & 0o777);
+    }
+    let secret_key = std::fs::read_to_string(&key_path).unwrap();
+    assert!(VoteDecryptionKey::from_secret_key(&base64::decode(&secret_key).unwrap()).is_some());
+    assert_eq!(format!("{} already exists.", key_filename), generate_envelope_key(key_filename).unwrap_err().to_string());
+    assert_eq!(secret_key, std::fs::read_to_string(&key_path).unwrap());
+    std::fs::remove_file(&key_path).unwrap();
+}
@@ -0,0 +1,36 @@
+#![cfg(feature = "webhooks")]
+
+use seventh_estate::cryptography::{new_signing_key, verify};
+use seventh_estate::monitor::*;
+use seventh_estate::webhooks::*;
+
+#[test]
+fn test_webhook_configuration() {
+    let configuration: WebhookConfiguration = serde_yaml::from_str(
+        "vote_thresholds: [100]\nwebhooks:\n  - url: https://hooks.example.org/poll\n    events: [voting_closed]\n").unwrap();
+    assert!(configuration.validate().is_ok());
+    let closed = PollEvent::VotingClosed { votes_counted: 3 };
+    let anomaly = PollEvent::AuditAnomaly { warning: AuditWarning::TransactionFailed { transaction_hash: "0x01".to_owned() } };
+    assert!(configuration.webhooks[0].subscribed(&closed));
+    assert!(!configuration.webhooks[0].subscribed(&anomaly));
+
+    let unknown_event: WebhookConfiguration = serde_yaml::from_str(
+        "webhooks:\n  - url: https://hooks.example.org/poll\n    events: [voting_opened]\n").unwrap();
+    assert!(unknown_event.validate().is_err());
+    let wrong_scheme: WebhookConfiguration = serde_yaml::from_str(
+        "webhooks:\n  - url: ftp://hooks.example.org/poll\n").unwrap();
+    assert!(wrong_scheme.validate().is_err());
+}
+
+#[test]
+fn test_signed_payload() {
+    let (signing_key, public_key) = new_signing_key().unwrap();
+    let event = PollEvent::VoteThresholdReached { threshold: 100, votes_counted: 100 };
+    let (body, signature) = signed_payload(&signing_key, "Example Poll", &event).unwrap();
+    assert!(verify(&public_key, &body, &base64::decode(&signature).unwrap()).unwrap());
+
+    let payload: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!("vote_threshold_reached", payload["event"]);
+    assert_eq!("Example Poll", payload["poll_identifier"]);
+    assert_eq!("Example Poll: 100 votes counted", payload["text"]);
+}
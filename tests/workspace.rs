@@ -0,0 +1,163 @@
+use std::fs;
+use seventh_estate::workspace::*;
+use seventh_estate::cryptography::*;
+use seventh_estate::poll_configuration::{PollState, PublicPollConfiguration};
+
+#[test]
+fn test_poll_directory_name() {
+    assert_eq!("example-poll", poll_directory_name("Example Poll"));
+    assert_eq!("council-2026", poll_directory_name(" Council / 2026 "));
+    assert_eq!("", poll_directory_name("../"));
+}
+
+#[test]
+fn test_archive_verify_poll() {
+    let root = std::env::temp_dir().join(format!("seventh-estate-workspace-{}", std::process::id()));
+    let poll_dir = root.join("example-poll");
+    let datadir = poll_dir.join("Example Poll");
+    let long_name = "plane_".repeat(14) + ".csv";
+    fs::create_dir_all(&datadir).unwrap();
+    fs::write(poll_dir.join("example.yaml.secure"), "secured").unwrap();
+    fs::write(datadir.join("tally.yaml"), "tally").unwrap();
+    fs::write(datadir.join(&long_name), vec![7u8; 1000]).unwrap();
+    fs::write(poll_dir.join("example-poll.tar.gz"), "earlier archive").unwrap();
+
+    let (signing_key, public_key) = new_signing_key().unwrap();
+    let archive = root.join("example-poll.tar.gz");
+    let manifest = archive_poll_directory("Example Poll", &poll_dir, &archive, &signing_key, &public_key).unwrap();
+    let names: Vec<&str> = manifest.artifacts.iter().map(|artifact| artifact.name.as_str()).collect();
+    assert_eq!(vec![
+        format!("example-poll/Example Poll/{}", long_name).as_str(),
+        "example-poll/Example Poll/tally.yaml",
+        "example-poll/example.yaml.secure"], names);
+
+    let verification = verify_poll_archive(&archive, Some(&public_key.0)).unwrap();
+    assert!(verification.valid());
+    assert_eq!(3, verification.artifacts);
+
+    // Another verification key, a truncated archive
+    let (_, other_key) = new_signing_key().unwrap();
+    assert!(!verify_poll_archive(&archive, Some(&other_key.0)).unwrap().manifest_valid);
+    let data = fs::read(&archive).unwrap();
+    fs::write(&archive, &data[..data.len() / 2]).unwrap();
+    assert!(verify_poll_archive(&archive, None).is_err());
+
+    // A crafted entry claiming more than a file of a poll holds is refused
+    // before it is read.
+    let mut header = tar::Header::new_ustar();
+    header.set_path("example-poll/tally.yaml").unwrap();
+    header.set_size(1 << 33);
+    header.set_cksum();
+    let mut encoder = flate2::write::GzEncoder::new(fs::File::create(&archive).unwrap(), flate2::Compression::default());
+    std::io::Write::write_all(&mut encoder, header.as_bytes()).unwrap();
+    std::io::Write::write_all(&mut encoder, &[0u8; 1024]).unwrap();
+    encoder.finish().unwrap();
+    let err = verify_poll_archive(&archive, None).unwrap_err();
+    assert!(err.to_string().contains("exceeds"), "{}", err);
+
+    // So is an archive of more entries than a poll has files.
+    let mut builder = tar::Builder::new(flate2::write::GzEncoder::new(fs::File::create(&archive).unwrap(), flate2::Compression::default()));
+    for n in 0..10_001 {
+        let mut header = tar::Header::new_ustar();
+        header.set_size(0);
+        header.set_mode(0o644);
+        builder.append_data(&mut header, format!("example-poll/{}", n), std::io::empty()).unwrap();
+    }
+    builder.into_inner().unwrap().finish().unwrap();
+    let err = verify_poll_archive(&archive, None).unwrap_err();
+    assert!(err.to_string().contains("entries"), "{}", err);
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_export_import_public_archive() {
+    let root = std::env::temp_dir().join(format!("seventh-estate-export-{}", std::process::id()));
+    let datadir = root.join("Example Poll");
+    fs::create_dir_all(&datadir).unwrap();
+    fs::write(datadir.join("committed_roster.csv"), "roster").unwrap();
+    fs::write(datadir.join("test_votes.yaml"), "private").unwrap();
+    fs::write(datadir.join("tally.yaml"), "tally").unwrap();
+    fs::write(datadir.join("finalization_receipt.yaml"), format!(
+        "poll_identifier: Example Poll\ntally_hash: {}\ntransaction_hash: '0x01'\naddress: '0x02'\nsubmitted_at_block: 1\nposted_at: 2026-01-01T00:00:00Z\n",
+        hex::encode(seventh_estate::blockchain::tally_hash(b"tally")))).unwrap();
+    fs::write(root.join("merkle.yaml"), "tree").unwrap();
+
+    let (signing_key, public_key) = new_signing_key().unwrap();
+    let public_configuration = PublicPollConfiguration {
+        poll_identifier: "Example Poll".to_owned(),
+        public_key: public_key.0.clone(),
+        poll_trustees: vec!["alice".to_owned()],
+        poll_state: PollState::new(),
+        num_ballots: 10,
+        num_decoys: 1,
+        voter_roster_size: 5,
+        roster_schema: None,
+        voter_privacy: true,
+        roster_published_fields: None,
+        drawn_summands_seed: None,
+        audited_columns_seed: None,
+        audited_ballots: None,
+        poll_question: None,
+        ballot_text: Vec::new(),
+        poll_rules: None,
+        end_block: None,
+        ballot_amendments: Vec::new(),
+        vote_encryption_key: None,
+        time_lock: None
+    };
+    let archive = root.join("public.tar.gz");
+    let manifest = export_public_archive(&public_configuration, &datadir, &root.join("merkle.yaml"), &root.join("transactions.json"), &archive, &signing_key).unwrap();
+    let names: Vec<&str> = manifest.artifacts.iter().map(|artifact| artifact.name.as_str()).collect();
+    assert_eq!(vec![
+        "example-poll/poll.yaml",
+        "example-poll/committed_roster.csv",
+        "example-poll/finalization_receipt.yaml",
+        "example-poll/merkle.yaml",
+        "example-poll/tally.yaml"], names);
+    assert!(verify_public_archive(&archive, Some(&public_key.0)).unwrap().valid());
+
+    let imported = root.join("imported");
+    assert!(import_public_archive(&archive, &imported, &public_key.0).unwrap().valid());
+    assert_eq!("tally", fs::read_to_string(imported.join("example-poll/tally.yaml")).unwrap());
+    assert!(imported.join("manifest.yaml.sig").exists());
+
+    // A tally report that does not match its finalization receipt
+    fs::write(datadir.join("tally.yaml"), "revised").unwrap();
+    export_public_archive(&public_configuration, &datadir, &root.join("merkle.yaml"), &root.join("transactions.json"), &archive, &signing_key).unwrap();
+    let verification = verify_public_archive(&archive, None).unwrap();
+    assert_eq!(vec!["example-poll/tally.yaml".to_owned()], verification.invalid_artifacts);
+    assert!(!import_public_archive(&archive, &root.join("rejected"), &public_key.0).unwrap().valid());
+    assert!(!root.join("rejected").exists());
+
+    // An archive re-signed with another key carries that key in its
+    // manifest: it checks out on its own, but is not imported.
+    fs::write(datadir.join("tally.yaml"), "tally").unwrap();
+    let (forged_key, forged_public_key) = new_signing_key().unwrap();
+    let forged_configuration = PublicPollConfiguration { public_key: forged_public_key.0.clone(), ..public_configuration.clone() };
+    export_public_archive(&forged_configuration, &datadir, &root.join("merkle.yaml"), &root.join("transactions.json"), &archive, &forged_key).unwrap();
+    assert!(verify_public_archive(&archive, None).unwrap().valid());
+    assert!(!import_public_archive(&archive, &root.join("forged"), &public_key.0).unwrap().valid());
+    assert!(!root.join("forged").exists());
+
+    fs::remove_dir_all(&root).unwrap();
+}
+
+#[test]
+fn test_read_generation_seed() {
+    use seventh_estate::subcommands::{create_new_poll, read_generation_seed};
+    let seed_path = std::env::temp_dir().join(format!("seventh-estate-seed-{}", std::process::id()));
+    fs::write(&seed_path, format!(" {}\n", "ab".repeat(32))).unwrap();
+    assert_eq!("ab".repeat(32), read_generation_seed(seed_path.to_str().unwrap()).unwrap());
+    assert!(read_generation_seed("missing_seed.hex").is_err());
+    fs::remove_file(&seed_path).unwrap();
+
+    // A seed of the wrong length is refused before the poll is created.
+    let dir = std::env::temp_dir().join(format!("seventh-estate-seed-poll-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let newpoll = dir.join("newpoll.yaml");
+    fs::copy("examples/newpoll.yaml", &newpoll).unwrap();
+    assert!(create_new_poll(newpoll.to_str().unwrap(), Some(&"ab".repeat(16))).is_err());
+    assert!(!dir.join("newpoll.yaml.secure").exists());
+    fs::remove_dir_all(&dir).unwrap();
+}